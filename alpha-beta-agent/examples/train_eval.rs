@@ -0,0 +1,123 @@
+//! Usage: `cargo run --release -p alpha-beta-agent --example train_eval --
+//! [<file>]`
+//!
+//! Trains the example linear model of `models/linear.json`, or writes it to
+//! `<file>`: a ridge regression of how games ended on the features of their
+//! positions, seen from each player, 1000 for a win and -1000 for a loss.
+//! The games are played by buying a card when one can and moving at random
+//! otherwise. Trivially trained, it shows the plumbing of `--eval` working
+//! more than it plays well.
+
+use alpha_beta_agent::Network;
+use game_def::{features::FEATURE_COUNT, Action, GameConfig, GameResult, State};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+const GAMES: usize = 1000;
+const MAX_PLIES: usize = 300;
+/// How much the regression pulls weights towards 0, which keeps those of
+/// features that never change, like the slots of a third player, at 0.
+const RIDGE: f64 = 1000.0;
+
+fn main() -> anyhow::Result<()> {
+    let out = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| concat!(env!("CARGO_MANIFEST_DIR"), "/models/linear.json").to_owned());
+    let mut rng = StdRng::seed_from_u64(0);
+    // The normal equations, with the bias as a last feature always 1.
+    let n = FEATURE_COUNT + 1;
+    let mut xtx = vec![vec![0.0; n]; n];
+    let mut xty = vec![0.0; n];
+    let mut positions = 0;
+    for _ in 0..GAMES {
+        let mut state = State::setup(&["a", "b"], GameConfig::default(), &mut rng);
+        // The features of each position, from each player.
+        let mut seen = vec![];
+        for _ in 0..MAX_PLIES {
+            if state.result() != GameResult::Ongoing {
+                break;
+            }
+            for me in 0..state.players.len() {
+                let mut features = state.features(me);
+                features.push(1.0);
+                seen.push((me, features));
+            }
+            let action = play(&state, &mut rng);
+            state
+                .run(action)
+                .expect("legal_actions yielded an illegal action");
+        }
+        // Draws and games going on teach little.
+        let GameResult::Won(winner) = state.result() else {
+            continue;
+        };
+        for (me, features) in seen {
+            let y = if me == winner { 1000.0 } else { -1000.0 };
+            for i in 0..n {
+                let xi = f64::from(features[i]);
+                for j in 0..n {
+                    xtx[i][j] += xi * f64::from(features[j]);
+                }
+                xty[i] += xi * y;
+            }
+            positions += 1;
+        }
+    }
+    for (i, row) in xtx.iter_mut().enumerate() {
+        row[i] += RIDGE;
+    }
+    let solved = solve(xtx, xty);
+    let bias = solved[FEATURE_COUNT] as f32;
+    let network = Network::linear(
+        solved[..FEATURE_COUNT].iter().map(|&w| w as f32).collect(),
+        bias,
+    );
+    network.check()?;
+    std::fs::write(&out, serde_json::to_string_pretty(&network)? + "\n")?;
+    println!("Fit {positions} positions, wrote {out}");
+    Ok(())
+}
+
+/// A purchase when there is one, 3 times out of 4, otherwise any move but
+/// resigning.
+fn play(state: &State, rng: &mut StdRng) -> Action {
+    let moves: Vec<_> = state
+        .legal_actions()
+        .filter(|action| *action != Action::Resign)
+        .collect();
+    let purchases: Vec<_> = moves
+        .iter()
+        .filter(|action| matches!(action, Action::Purchase { .. }))
+        .collect();
+    if !purchases.is_empty() && rng.gen_bool(0.75) {
+        return (*purchases.choose(rng).unwrap()).clone();
+    }
+    moves.choose(rng).cloned().unwrap_or(Action::Skip)
+}
+
+/// Solves `a x = b` by Gaussian elimination, `a` being positive definite.
+fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Vec<f64> {
+    let n = b.len();
+    for k in 0..n {
+        let pivot = (k..n)
+            .max_by(|&i, &j| a[i][k].abs().total_cmp(&a[j][k].abs()))
+            .unwrap();
+        a.swap(k, pivot);
+        b.swap(k, pivot);
+        let (above, below) = a.split_at_mut(k + 1);
+        let (b_above, b_below) = b.split_at_mut(k + 1);
+        let pivot = &above[k];
+        for (row, bi) in below.iter_mut().zip(b_below) {
+            let factor = row[k] / pivot[k];
+            for (x, p) in row[k..].iter_mut().zip(&pivot[k..]) {
+                *x -= factor * p;
+            }
+            *bi -= factor * b_above[k];
+        }
+    }
+    let mut x = vec![0.0; n];
+    for k in (0..n).rev() {
+        let rest: f64 = (k + 1..n).map(|j| a[k][j] * x[j]).sum();
+        x[k] = (b[k] - rest) / a[k][k];
+    }
+    x
+}
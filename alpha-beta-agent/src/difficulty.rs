@@ -0,0 +1,117 @@
+//! Levels of play below the full strength of the search, for people
+//! learning the game: a shallower search, and now and then a move other
+//! than the best, see [`Difficulty`].
+
+use std::{fmt::Display, str::FromStr, time::Duration};
+
+use rand::{distributions::WeightedIndex, prelude::Distribution, seq::SliceRandom, Rng};
+
+use crate::{AlphaBeta, RootMove, DEFAULT_TIME_LIMIT, MAX_DEPTH, WON};
+
+/// The most [`AlphaBeta::blunder`] may be, so that even the weakest level
+/// plays mostly sensible moves.
+pub const MAX_BLUNDER: f64 = 0.25;
+
+/// How well the agent plays, by the knobs of [`AlphaBeta`] each level sets:
+///
+/// | level  | depth | time  | temperature | blunder | endgame |
+/// |--------|-------|-------|-------------|---------|---------|
+/// | easy   | 1     | 200ms | 100         | 0.25    | 0       |
+/// | medium | 2     | 500ms | 25          | 0.1     | 1       |
+/// | hard   | 64    | 2s    | 0           | 0       | 3       |
+///
+/// Hard is the search at full strength, and custom sets nothing, leaving
+/// the knobs as they are given.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    #[default]
+    Custom,
+}
+
+impl Difficulty {
+    /// Sets the knobs of `agent` this level stands for.
+    ///
+    /// ```
+    /// use alpha_beta_agent::{AlphaBeta, Difficulty};
+    ///
+    /// let mut agent = AlphaBeta::default();
+    /// Difficulty::Easy.apply(&mut agent);
+    /// assert_eq!((agent.depth, agent.temperature, agent.blunder), (1, 100.0, 0.25));
+    /// let mut custom = agent.clone();
+    /// Difficulty::Custom.apply(&mut custom);
+    /// assert_eq!(custom.depth, 1);
+    /// ```
+    pub fn apply(self, agent: &mut AlphaBeta) {
+        let (depth, time_ms, temperature, blunder, endgame) = match self {
+            Difficulty::Easy => (1, 200, 100.0, 0.25, 0),
+            Difficulty::Medium => (2, 500, 25.0, 0.1, 1),
+            Difficulty::Hard => (
+                MAX_DEPTH,
+                DEFAULT_TIME_LIMIT.as_millis() as u64,
+                0.0,
+                0.0,
+                3,
+            ),
+            Difficulty::Custom => return,
+        };
+        agent.depth = depth;
+        agent.time_limit = Duration::from_millis(time_ms);
+        agent.temperature = temperature;
+        agent.blunder = blunder;
+        agent.endgame = endgame;
+    }
+}
+
+impl Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+            Difficulty::Custom => "custom",
+        })
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Difficulty> {
+        match s {
+            "easy" => Ok(Difficulty::Easy),
+            "medium" => Ok(Difficulty::Medium),
+            "hard" => Ok(Difficulty::Hard),
+            "custom" => Ok(Difficulty::Custom),
+            _ => anyhow::bail!("Unknown difficulty {s}, try easy, medium, hard or custom"),
+        }
+    }
+}
+
+/// Which of `root`, best first with exact scores, to play: with a chance
+/// of `blunder` any of them, and otherwise one picked with a chance that
+/// falls by e for each `temperature` points it scores below the best, or
+/// the best at 0. Never one whose search found it loses, unless they all
+/// do.
+pub(crate) fn pick(root: &[RootMove], temperature: f64, blunder: f64, rng: &mut impl Rng) -> usize {
+    let safe: Vec<usize> = (0..root.len()).filter(|&k| root[k].score > -WON).collect();
+    if safe.len() <= 1 {
+        return 0;
+    }
+    if rng.gen_bool(blunder.clamp(0.0, MAX_BLUNDER)) {
+        return *safe.choose(rng).unwrap();
+    }
+    if temperature <= 0.0 {
+        return 0;
+    }
+    let best = f64::from(root[0].score);
+    let weights = safe
+        .iter()
+        .map(|&k| ((f64::from(root[k].score) - best) / temperature).exp());
+    match WeightedIndex::new(weights) {
+        Ok(index) => safe[index.sample(rng)],
+        Err(_) => 0,
+    }
+}
@@ -0,0 +1,182 @@
+//! The exact search near the end of a game, which proves a win where the
+//! evaluation can only guess at one, see [`solve`].
+//!
+//! It searches to the end of the game, or finds no win: the player searching
+//! tries its purchases and the coin picks that bring it closer to a card
+//! worth points, every other player everything it may play. A line is given
+//! up once even buying the best card every turn can't reach the target score
+//! in the plies left, a ply being one player's turn.
+
+use std::{cmp::Reverse, collections::HashMap};
+
+use game_def::{Action, GameResult, ObjectiveMode, Phase, State};
+
+use super::{moves, picked, promise, Budget};
+
+/// The most plies a proof may take.
+const MAX_PLIES: u32 = 8;
+/// Nodes a proof may take before the search by the evaluation goes on
+/// without one.
+const MAX_NODES: u64 = 200_000;
+/// The most moves the player to move may have for a proof to be tried.
+const MAX_MOVES: usize = 20;
+
+/// Whether it's worth looking for a proof in `state`: someone is within
+/// `points` of the target score, and the player to move has few moves
+/// toward it.
+pub(super) fn close(state: &State, points: u8) -> bool {
+    let target = state.config.target_score;
+    points > 0
+        && state.config.objective_mode == ObjectiveMode::Nobles
+        && state
+            .players
+            .iter()
+            .any(|p| p.score.saturating_add(points) >= target)
+        && relevant(state).len() <= MAX_MOVES
+}
+
+/// A move of the player to move that wins whatever the others play, and
+/// the most plies until the game is over, or `None` if no win is found
+/// within [`MAX_PLIES`] and the budget. The cards dealt on the way are
+/// those of the determinized `state`, so the proof holds as far as they
+/// are the real ones.
+pub(super) fn solve(state: &State, budget: &mut Budget) -> Option<(Action, u32)> {
+    // A search cut short leaves its copy anywhere inside it.
+    let state = &mut state.clone();
+    let cards = state
+        .decks
+        .values()
+        .flat_map(|deck| deck.iter())
+        .chain(state.players.iter().flat_map(|p| &p.reserved))
+        .map(|card| card.score());
+    let nobels = state.nobels.iter().map(|n| n.score);
+    let mut solver = Solver {
+        me: state.turn,
+        budget,
+        gain: cards.max().unwrap_or(0) + nobels.max().unwrap_or(0),
+        known: HashMap::new(),
+    };
+    let moves = relevant(state);
+    for plies in 1..=MAX_PLIES {
+        for ac in &moves {
+            let token = state
+                .apply(ac)
+                .expect("legal_actions yielded an illegal action");
+            let left = if state.turn == solver.me {
+                plies
+            } else {
+                plies - 1
+            };
+            let won = solver.wins(state, left)?;
+            state.undo(token);
+            if won {
+                return Some((ac.clone(), plies));
+            }
+        }
+    }
+    None
+}
+
+struct Solver<'a> {
+    me: usize,
+    budget: &'a mut Budget,
+    /// The most points a player can score in one turn.
+    gain: u8,
+    /// Positions proven won within some plies, or not won within some.
+    known: HashMap<u64, (u32, bool)>,
+}
+
+impl Solver<'_> {
+    /// Whether `me` wins `state` within `plies` whatever the others play,
+    /// or `None` if the budget ran out first, leaving `state` somewhere
+    /// inside the search.
+    fn wins(&mut self, state: &mut State, plies: u32) -> Option<bool> {
+        if self.budget.exhausted() || self.budget.nodes > MAX_NODES {
+            return None;
+        }
+        match state.result() {
+            GameResult::Ongoing => (),
+            GameResult::Won(winner) => return Some(winner == self.me),
+            GameResult::Draw(_) => return Some(false),
+        }
+        if plies == 0 || self.hopeless(state, plies) {
+            return Some(false);
+        }
+        let hash = state.zobrist();
+        match self.known.get(&hash) {
+            Some(&(known, true)) if known <= plies => return Some(true),
+            Some(&(known, false)) if known >= plies => return Some(false),
+            _ => (),
+        }
+        let mine = state.turn == self.me;
+        let mut moves = match mine {
+            true => relevant(state),
+            false => state
+                .legal_actions()
+                .filter(|ac| *ac != Action::Resign)
+                .collect(),
+        };
+        moves.sort_by_cached_key(|ac| Reverse(promise(state, ac)));
+        let turn = state.turn;
+        // One win is enough for `me`, one escape for the others.
+        let mut won = !mine;
+        for ac in moves {
+            let token = state
+                .apply(&ac)
+                .expect("legal_actions yielded an illegal action");
+            let left = if state.turn == turn { plies } else { plies - 1 };
+            let wins = self.wins(state, left)?;
+            state.undo(token);
+            if wins == mine {
+                won = mine;
+                break;
+            }
+        }
+        self.known.insert(hash, (plies, won));
+        Some(won)
+    }
+
+    /// Whether `me` can't reach the target score in `plies` even buying the
+    /// best card every turn, and so can't win in them. With a round limit
+    /// the game can end below the target, and with someone resigned turns
+    /// come round faster, so then it can't tell.
+    fn hopeless(&self, state: &State, plies: u32) -> bool {
+        if state.config.max_rounds.is_some() || state.players.iter().any(|p| p.resigned) {
+            return false;
+        }
+        let players = state.players.len();
+        let turns = (0..plies as usize)
+            .filter(|k| (state.turn + k) % players == self.me)
+            .count();
+        let best = usize::from(state.players[self.me].score) + turns * usize::from(self.gain);
+        best < usize::from(state.config.target_score)
+    }
+}
+
+/// The moves the player to move tries in a proof: purchases and the coin
+/// picks that bring it closer to a card worth points, face up or reserved,
+/// or every move when it's finishing a turn by discarding or choosing a
+/// nobel.
+fn relevant(state: &State) -> Vec<Action> {
+    let mut moves = moves(state);
+    if state.phase != Phase::Play {
+        return moves;
+    }
+    let player = &state.players[state.turn];
+    let points: Vec<_> = state
+        .card_iter()
+        .map(|(deck, card)| &state.decks[deck][card])
+        .chain(&player.reserved)
+        .filter(|card| card.score() > 0)
+        .map(|card| player.mortal.gems.deficit(&card.cost_for(player)))
+        .collect();
+    moves.retain(|ac| match ac {
+        Action::Purchase { .. } | Action::PurchaseReserved { .. } => true,
+        _ => picked(ac).is_some_and(|picked| {
+            points
+                .iter()
+                .any(|deficit| picked.iter().any(|(c, n)| *n > 0 && deficit[c] > 0))
+        }),
+    });
+    moves
+}
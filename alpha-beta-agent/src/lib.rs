@@ -0,0 +1,1053 @@
+//! An alpha-beta search over a determinized state, the agent of the
+//! alpha-beta-agent program and of the runner's `builtin:alphabeta`.
+//!
+//! The search deepens a round at a time until its time is up, so it looks
+//! far ahead where there are few moves and stays quick where there are
+//! many. Each depth searches the best move of the one before first. The
+//! depth reached and the nodes searched are logged to stderr for each move.
+//!
+//! Positions reached again, like after taking the same coins in another
+//! order, are looked up in a transposition table instead of searched again,
+//! see [`Table`]. Moves that look best are searched first, and so are the
+//! killer moves that cut off the search next to them, see [`promise`].
+//!
+//! Since the first move is so often the best, the others are only searched
+//! to see whether they beat it, with a window of one point, and again in
+//! full only when they do: a principal variation search. Each depth starts
+//! with a window around the score of the depth before, an aspiration
+//! window, and widens it when the score falls outside.
+//!
+//! With more than two players it takes the others either to all play
+//! against it or each to play for itself, see [`Model`].
+//!
+//! Close to the end of the game it first looks for a win it can prove,
+//! searching to the end of the game with no evaluation, see [`endgame`].
+//!
+//! It can't see the face down cards, so it searches states with them made
+//! up from the cards it hasn't seen. With more than one determinization it
+//! searches several such states, each for its share of the time, and plays
+//! the move most of them found best.
+//!
+//! Positions are scored by hand-written [`Weights`], or by a learned
+//! [`Network`] over the features of the state.
+//!
+//! Below full strength it searches less deep and now and then plays
+//! another move than the best, see [`Difficulty`].
+
+use std::{
+    cmp::Reverse,
+    fmt::Display,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use game_def::{Action, AgentHandler, Card, GameResult, Observation, Player, ResourceMap, State};
+use rand::{rngs::StdRng, SeedableRng};
+
+mod difficulty;
+mod endgame;
+mod network;
+mod weights;
+
+pub use difficulty::{Difficulty, MAX_BLUNDER};
+pub use network::{Network, Scratch};
+pub use weights::Weights;
+
+/// Deepening stops here even with time to spare.
+const MAX_DEPTH: i32 = 64;
+pub const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(2);
+/// Hard limits that hold whatever the depth is, so that pathological states
+/// (huge reserve lists, absurd coin counts) can not stop us from answering,
+/// by default, see [`AlphaBeta::max_nodes`].
+pub const DEFAULT_MAX_NODES: u64 = 5_000_000;
+pub const DEFAULT_MAX_THINK_TIME: Duration = Duration::from_secs(20);
+/// Scores of a won game and beyond, which no deeper search changes.
+const WON: i32 = 1_000_000_000;
+pub const DEFAULT_TABLE_MEMORY: usize = 16 << 20;
+/// How far from the score of the depth before the window of the next one
+/// starts, a quarter of a card.
+const ASPIRATION: i32 = 25;
+/// Scores beyond every other, the widest window there is.
+const INFINITY: i32 = 2_000_000_000;
+
+/// What a stored score says of the position: it is the score, or the score
+/// is at least or at most that, where the search was cut off.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    hash: u64,
+    depth: i32,
+    score: i32,
+    bound: Bound,
+    best: Action,
+}
+
+/// Positions searched before, by their Zobrist hash, with the best move
+/// found to try first when they come up again. Each slot keeps the deeper
+/// of the positions that land in it. Scores are for the player searching,
+/// like those of [`Weights::evaluate`], so a table is only good for one move.
+struct Table {
+    slots: Vec<Option<Entry>>,
+}
+
+impl Table {
+    /// A table of at most `memory` bytes, none if that is too little.
+    fn new(memory: usize) -> Self {
+        let slots = memory / std::mem::size_of::<Option<Entry>>();
+        let slots = slots.checked_ilog2().map_or(0, |bits| 1 << bits);
+        Table {
+            slots: vec![None; slots],
+        }
+    }
+
+    fn slot(&self, hash: u64) -> usize {
+        hash as usize & (self.slots.len() - 1)
+    }
+
+    fn get(&self, hash: u64) -> Option<&Entry> {
+        if self.slots.is_empty() {
+            return None;
+        }
+        self.slots[self.slot(hash)]
+            .as_ref()
+            .filter(|entry| entry.hash == hash)
+    }
+
+    fn put(&mut self, entry: Entry) {
+        if self.slots.is_empty() {
+            return;
+        }
+        let slot = self.slot(entry.hash);
+        if self.slots[slot]
+            .as_ref()
+            .is_none_or(|old| old.depth <= entry.depth)
+        {
+            self.slots[slot] = Some(entry);
+        }
+    }
+}
+
+struct Budget {
+    nodes: u64,
+    max_nodes: u64,
+    deadline: Instant,
+    /// Set from outside to stop the search at the next node.
+    stop: Arc<AtomicBool>,
+}
+
+impl Budget {
+    fn exhausted(&mut self) -> bool {
+        self.nodes += 1;
+        self.nodes > self.max_nodes
+            || self.stop.load(Ordering::Relaxed)
+            || (self.nodes.is_multiple_of(1024) && Instant::now() > self.deadline)
+    }
+}
+
+/// Every legal move but resigning. Skip is only legal when nothing else is,
+/// and then it is searched like any move so the others play on.
+fn moves(state: &State) -> Vec<Action> {
+    state
+        .legal_actions()
+        .filter(|action| *action != Action::Resign)
+        .collect()
+}
+
+/// How good `ac` looks before it is searched, so the best looking moves are
+/// searched first and cut off the rest sooner: purchases by the points and
+/// nobel progress they bring for each coin paid, then coin picks by how
+/// much closer they bring the cards on the table, weighing those worth more,
+/// then reserves.
+fn promise(state: &State, ac: &Action) -> i32 {
+    let player = &state.players[state.turn];
+    let picked = match ac {
+        Action::Purchase { deck, card, .. } => {
+            return 3_000_000 + purchase_promise(state, player, &state.decks[*deck][*card]);
+        }
+        Action::PurchaseReserved { index, .. } => {
+            return 3_000_000 + purchase_promise(state, player, &player.reserved[*index]);
+        }
+        Action::Reserve { .. } | Action::ReserveBlind { .. } => return 1_000_000,
+        _ => match picked(ac) {
+            Some(picked) => picked,
+            None => return 0,
+        },
+    };
+    let closer = state
+        .card_iter()
+        .map(|(deck, card)| &state.decks[deck][card])
+        .map(|card| {
+            let deficit = player.mortal.gems.deficit(&card.cost_for(player));
+            let closer: usize = picked.iter().map(|(c, n)| (*n).min(deficit[c])).sum();
+            (i32::from(card.score()) + 1) * closer as i32
+        })
+        .sum::<i32>();
+    2_000_000 + closer
+}
+
+/// The coins `ac` picks, if it picks any.
+fn picked(ac: &Action) -> Option<ResourceMap> {
+    match ac {
+        Action::PickThree { one, two, three } => {
+            Some(ResourceMap::from([(*one, 1), (*two, 1), (*three, 1)]))
+        }
+        Action::PickTwo { color } => Some(ResourceMap::from([(*color, 2)])),
+        Action::PickFewer { colors } => Some(colors.iter().map(|c| (*c, 1)).collect()),
+        _ => None,
+    }
+}
+
+fn purchase_promise(state: &State, player: &Player, card: &Card) -> i32 {
+    let nobels = state
+        .nobels
+        .iter()
+        .filter(|n| {
+            card.color()
+                .is_some_and(|c| player.immortal.deficit(&n.cost)[c] > 0)
+        })
+        .count() as i32;
+    let paid = card.cost_for(player).sum();
+    (i32::from(card.score()) * 10 + nobels * 3 + 1) * 1000 / paid.saturating_add(1)
+}
+
+/// The search for one move: whose it is, what it may spend and what it
+/// learns on the way.
+struct Search {
+    /// Whoever is to move at the root, in whatever seat.
+    me: usize,
+    model: Model,
+    weights: Weights,
+    /// Scores positions in place of `weights` when there is one.
+    network: Option<Arc<Network>>,
+    scratch: Scratch,
+    budget: Budget,
+    table: Table,
+    /// Whether to search the most promising moves first, see [`promise`].
+    ordering: bool,
+    /// Whether to search moves after the first with a null window first.
+    pvs: bool,
+    /// Whether every move at the root gets its exact score, not only the
+    /// best, for [`AlphaBeta::temperature`] and [`AlphaBeta::blunder`].
+    exact_root: bool,
+    /// For each ply, the last two moves that cut off the search there. The
+    /// same move often cuts off next to it too, so it is searched early.
+    killers: Vec<[Option<Action>; 2]>,
+}
+
+impl Search {
+    /// Scores the moves of `root` from `next` on, taking the next one left
+    /// each time so that several threads can share them, until none are
+    /// left. `alpha` is just below the best score found on any thread, so
+    /// worse moves are cut off and equal ones get their exact score, and
+    /// moves scoring `beta` or more are cut off too.
+    ///
+    /// Returns the index in `root`, the score and whether that is exact
+    /// rather than a bound for each move searched, and whether the budget
+    /// lasted for all of them.
+    fn root(
+        &mut self,
+        state: &State,
+        root: &[(usize, Action)],
+        depth: i32,
+        next: &AtomicUsize,
+        alpha: &AtomicI32,
+        beta: i32,
+    ) -> (Vec<(usize, i32, bool)>, bool) {
+        let mut state = state.clone();
+        let mut scores = vec![];
+        loop {
+            let k = next.fetch_add(1, Ordering::Relaxed);
+            let Some((_, ac)) = root.get(k) else {
+                return (scores, true);
+            };
+            let token = state
+                .apply(ac)
+                .expect("legal_actions yielded an illegal action");
+            let depth = if state.turn == self.me {
+                depth
+            } else {
+                depth - 1
+            };
+            let below = alpha.load(Ordering::Relaxed);
+            let score = match self.model {
+                // Whether it is at least as good as the best so far first.
+                Model::Paranoid if self.pvs && !self.exact_root && k > 0 => self
+                    .search(&mut state, depth, 1, below, below + 1)
+                    .and_then(|(score, _)| match score > below {
+                        true => self.search(&mut state, depth, 1, below, beta),
+                        false => Some((score, Action::Skip)),
+                    })
+                    .map(|(score, _)| score),
+                Model::Paranoid => self
+                    .search(&mut state, depth, 1, below, beta)
+                    .map(|(score, _)| score),
+                Model::MaxN => self
+                    .max_n(&mut state, depth, 1)
+                    .map(|(scores, _)| scores[self.me]),
+            };
+            // Otherwise `state` is somewhere in the tree now, and no use.
+            let Some(score) = score else {
+                return (scores, false);
+            };
+            state.undo(token);
+            if !self.exact_root {
+                alpha.fetch_max(score - 1, Ordering::Relaxed);
+            }
+            let exact = score > below && score < beta;
+            scores.push((k, score, exact || self.model == Model::MaxN));
+        }
+    }
+
+    /// The best move for whoever is to move, scored for `me`: `me`
+    /// maximizes and every other player is assumed to play against it,
+    /// which with two players is plain alpha-beta. Positions are scored at
+    /// the start of a round, so everyone has had as many moves. `ply` is
+    /// how many moves `state` is from the root.
+    ///
+    /// Returns `None` if the budget ran out before the search could finish,
+    /// in which case `state` is left somewhere inside the search tree.
+    fn search(
+        &mut self,
+        state: &mut State,
+        depth: i32,
+        ply: usize,
+        mut alpha: i32,
+        mut beta: i32,
+    ) -> Option<(i32, Action)> {
+        let me = self.me;
+        if self.budget.exhausted() {
+            return None;
+        }
+        match state.result() {
+            GameResult::Ongoing => (),
+            GameResult::Won(winner) if winner == me => return Some((WON, Action::Skip)),
+            GameResult::Won(_) => return Some((-WON, Action::Skip)),
+            GameResult::Draw(winners) if winners.contains(&me) => return Some((0, Action::Skip)),
+            GameResult::Draw(_) => return Some((-WON, Action::Skip)),
+        }
+        if state.turn == state.first_player && depth <= 0 {
+            return Some((self.evaluate(state, me), Action::Skip));
+        }
+        let hash = state.zobrist();
+        let stored = self.table.get(hash);
+        if let Some(entry) = stored.filter(|entry| entry.depth >= depth) {
+            let cut = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.score >= beta,
+                Bound::Upper => entry.score <= alpha,
+            };
+            if cut {
+                return Some((entry.score, entry.best.clone()));
+            }
+        }
+        let stored = stored.map(|entry| entry.best.clone());
+        let mut moves = moves(state);
+        if self.ordering {
+            self.order(state, ply, &mut moves, stored.as_ref());
+        }
+        let window = (alpha, beta);
+        let maximizing = state.turn == me;
+        let mut r = match maximizing {
+            true => (-1_000_000_001, Action::Skip),
+            false => (1_000_000_001, Action::Skip),
+        };
+        let turn = state.turn;
+        for (k, ac) in moves.into_iter().enumerate() {
+            let token = state
+                .apply(&ac)
+                .expect("legal_actions yielded an illegal action");
+            // A discard or nobel choice finishes the same turn, it is not a reply.
+            let depth = if state.turn == turn { depth } else { depth - 1 };
+            // Past the first move, whether it beats the best so far first,
+            // and its score only if it does.
+            let null = match maximizing {
+                true => (alpha, alpha + 1),
+                false => (beta - 1, beta),
+            };
+            let score = match self.pvs && k > 0 {
+                true => {
+                    let score = self.search(state, depth, ply + 1, null.0, null.1)?.0;
+                    match alpha < score && score < beta {
+                        true => self.search(state, depth, ply + 1, alpha, beta)?.0,
+                        false => score,
+                    }
+                }
+                false => self.search(state, depth, ply + 1, alpha, beta)?.0,
+            };
+            state.undo(token);
+            let cut = if maximizing && r.0 < score {
+                r = (score, ac);
+                alpha = alpha.max(score);
+                score >= beta
+            } else if !maximizing && r.0 > score {
+                r = (score, ac);
+                beta = beta.min(score);
+                score <= alpha
+            } else {
+                false
+            };
+            if cut {
+                self.kill(ply, &r.1);
+                break;
+            }
+        }
+        let bound = if r.0 <= window.0 {
+            Bound::Upper
+        } else if r.0 >= window.1 {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table.put(Entry {
+            hash,
+            depth,
+            score: r.0,
+            bound,
+            best: r.1.clone(),
+        });
+        Some(r)
+    }
+
+    /// Like [`Search::search`] for the max-n model: a score for every
+    /// player, each of them playing the move best for itself. Nothing is
+    /// cut off, since what is bad for one is not good for all the others.
+    fn max_n(&mut self, state: &mut State, depth: i32, ply: usize) -> Option<(Vec<i32>, Action)> {
+        if self.budget.exhausted() {
+            return None;
+        }
+        let players = 0..state.players.len();
+        let scores: Vec<_> = match state.result() {
+            GameResult::Ongoing => vec![],
+            GameResult::Won(winner) => players
+                .map(|i| if i == winner { WON } else { -WON })
+                .collect(),
+            GameResult::Draw(winners) => players
+                .map(|i| if winners.contains(&i) { 0 } else { -WON })
+                .collect(),
+        };
+        if !scores.is_empty() {
+            return Some((scores, Action::Skip));
+        }
+        let mut moves = moves(state);
+        if (state.turn == state.first_player && depth <= 0) || moves.is_empty() {
+            let mut scores = Vec::with_capacity(state.players.len());
+            for i in 0..state.players.len() {
+                scores.push(self.evaluate(state, i));
+            }
+            return Some((scores, Action::Skip));
+        }
+        if self.ordering {
+            self.order(state, ply, &mut moves, None);
+        }
+        let turn = state.turn;
+        let mut r: Option<(Vec<i32>, Action)> = None;
+        for ac in moves {
+            let token = state
+                .apply(&ac)
+                .expect("legal_actions yielded an illegal action");
+            let depth = if state.turn == turn { depth } else { depth - 1 };
+            let scores = self.max_n(state, depth, ply + 1)?.0;
+            state.undo(token);
+            if r.as_ref().is_none_or(|(best, _)| scores[turn] > best[turn]) {
+                r = Some((scores, ac));
+            }
+        }
+        r
+    }
+
+    /// What `state` is worth to `me`, by the network or the weights.
+    fn evaluate(&mut self, state: &State, me: usize) -> i32 {
+        match &self.network {
+            Some(network) => network.evaluate(state, me, &mut self.scratch),
+            None => self.weights.evaluate(state, me),
+        }
+    }
+
+    /// Puts the stored best move first, then purchases, then the killers of
+    /// `ply`, each by [`promise`].
+    fn order(&self, state: &State, ply: usize, moves: &mut [Action], best: Option<&Action>) {
+        let killers = self.killers.get(ply);
+        moves.sort_by_cached_key(|ac| {
+            let rank = if Some(ac) == best {
+                3
+            } else if matches!(
+                ac,
+                Action::Purchase { .. } | Action::PurchaseReserved { .. }
+            ) {
+                2
+            } else if killers.is_some_and(|k| k.iter().flatten().any(|k| k == ac)) {
+                1
+            } else {
+                0
+            };
+            Reverse((rank, promise(state, ac)))
+        });
+    }
+
+    /// Remembers that `ac` cut off the search at `ply`. Purchases come
+    /// first anyway, so they are not kept.
+    fn kill(&mut self, ply: usize, ac: &Action) {
+        if !self.ordering
+            || matches!(
+                ac,
+                Action::Purchase { .. } | Action::PurchaseReserved { .. }
+            )
+        {
+            return;
+        }
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None, None]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0].as_ref() != Some(ac) {
+            killers[1] = killers[0].replace(ac.clone());
+        }
+    }
+}
+
+/// `first` and the best moves the tables of `searches` have for the
+/// positions after it, as long as they have exact scores for them.
+fn principal_variation(state: &mut State, first: &Action, searches: &[Search]) -> Vec<Action> {
+    let mut pv = vec![];
+    let mut tokens = vec![];
+    let mut next = Some(first.clone());
+    while let Some(ac) = next.take() {
+        if pv.len() >= MAX_DEPTH as usize || !moves(state).contains(&ac) {
+            break;
+        }
+        tokens.push(state.apply(&ac).expect("moves yielded an illegal action"));
+        pv.push(ac);
+        if !state.is_finished() {
+            let hash = state.zobrist();
+            next = searches
+                .iter()
+                .filter_map(|search| search.table.get(hash))
+                .find(|entry| entry.bound == Bound::Exact)
+                .map(|entry| entry.best.clone());
+        }
+    }
+    for token in tokens.into_iter().rev() {
+        state.undo(token);
+    }
+    pv
+}
+
+/// Searches one round deeper at a time, up to `depth` rounds, until
+/// `time_limit` is up, and plays the best move of the deepest search that
+/// finished. `table_memory` is the most bytes its transposition table
+/// takes, and 0 searches without one. Without `ordering`, moves are
+/// searched in the order they are listed, which finds the same move slower.
+/// With more than two players, `model` says what the others play for.
+/// `seed` fills in the cards the search can't see, the same ones every time
+/// for the same seed. Positions are scored by `network` if there is one,
+/// and by `weights` otherwise. With more than one
+/// of `threads`, the moves at the root are searched on that many threads,
+/// each with a share of the table, and the same move is played as with one.
+/// Once someone is within `endgame` points of the target score, it first
+/// looks for a win it can prove, and 0 never does. With more than one of
+/// `determinizations`, it searches that many states with the hidden cards
+/// dealt differently, from `seed` on, and plays the move most of them vote
+/// for, see [`AlphaBeta::think`]. `pvs` searches with null windows and
+/// `aspiration` each depth with a window around the last score, see the
+/// [crate docs](crate); without them it plays the same moves slower.
+/// `temperature` and `blunder` play other moves than the best now and then,
+/// with the same choices for the same `seed`, see [`Difficulty`], which
+/// sets them with the depth and time for each level.
+#[derive(Debug, Clone)]
+pub struct AlphaBeta {
+    pub depth: i32,
+    pub time_limit: Duration,
+    pub table_memory: usize,
+    pub ordering: bool,
+    pub model: Model,
+    pub seed: u64,
+    pub weights: Weights,
+    pub network: Option<Arc<Network>>,
+    pub threads: usize,
+    pub endgame: u8,
+    pub determinizations: usize,
+    pub pvs: bool,
+    pub aspiration: bool,
+    /// In points of the score: the chance of playing a move falls by e for
+    /// each `temperature` it scores below the best. 0 plays the best.
+    pub temperature: f64,
+    /// The chance of playing any move the search didn't find losing, at
+    /// most [`MAX_BLUNDER`].
+    pub blunder: f64,
+    /// Nodes and time each search stops at whatever `depth` and
+    /// `time_limit` are, answering with the best move found so far, or the
+    /// first legal one.
+    pub max_nodes: u64,
+    pub max_think_time: Duration,
+}
+
+/// What the search takes the others to play for, which only matters with
+/// more than two players.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Model {
+    /// Against us, as if they were one opponent. Cautious, and the only one
+    /// of the two that can cut off moves.
+    #[default]
+    Paranoid,
+    /// Each for itself, with every position scored for every player.
+    MaxN,
+}
+
+impl FromStr for Model {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Model> {
+        match s {
+            "paranoid" => Ok(Model::Paranoid),
+            "maxn" => Ok(Model::MaxN),
+            _ => anyhow::bail!("Unknown model {s}, try paranoid or maxn"),
+        }
+    }
+}
+
+impl Default for AlphaBeta {
+    fn default() -> Self {
+        AlphaBeta {
+            depth: MAX_DEPTH,
+            time_limit: DEFAULT_TIME_LIMIT,
+            table_memory: DEFAULT_TABLE_MEMORY,
+            ordering: true,
+            model: Model::Paranoid,
+            seed: 0,
+            weights: Weights::default(),
+            network: None,
+            threads: 1,
+            endgame: 3,
+            determinizations: 1,
+            pvs: true,
+            aspiration: true,
+            temperature: 0.0,
+            blunder: 0.0,
+            max_nodes: DEFAULT_MAX_NODES,
+            max_think_time: DEFAULT_MAX_THINK_TIME,
+        }
+    }
+}
+
+/// What [`AlphaBeta::think`] found.
+#[derive(Debug, Clone)]
+pub struct Thought {
+    pub action: Action,
+    /// The score of `action` at `depth`, for the player to move.
+    pub score: i32,
+    /// The deepest search that finished, 0 if there was only one move.
+    pub depth: i32,
+    /// The most plies until the game is won whatever the others play, a
+    /// ply being one player's turn, when the endgame search proved it.
+    pub mate: Option<u32>,
+    /// Positions searched, at every depth.
+    pub nodes: u64,
+    /// The determinizations searched, and how many of them found `action`
+    /// best.
+    pub determinizations: usize,
+    pub votes: usize,
+    pub elapsed: Duration,
+    pub threads: usize,
+    /// Nodes searched a second with all threads, over those searched a
+    /// second by one thread alone on the first move of each depth.
+    pub speedup: f64,
+    /// The moves expected from `action` on, by every player, as far as the
+    /// transposition table remembers them.
+    pub pv: Vec<Action>,
+    /// Every move at the root at `depth`, best first.
+    pub root: Vec<RootMove>,
+    /// Where `action` is in `root`: 0 for the best, more when
+    /// [`AlphaBeta::temperature`] or [`AlphaBeta::blunder`] picked another.
+    pub rank: usize,
+}
+
+/// A move at the root and its score. Only the best moves get an exact
+/// score; that of the others is only known to be at most `score`.
+#[derive(Debug, Clone)]
+pub struct RootMove {
+    pub action: Action,
+    pub score: i32,
+    pub exact: bool,
+}
+
+impl Display for RootMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.exact {
+            true => write!(f, "{} {}", self.action, self.score),
+            false => write!(f, "{} <={}", self.action, self.score),
+        }
+    }
+}
+
+/// The line logged for each move, like `depth 3, 5120 nodes in 40.2ms,
+/// score 120, playing take r g u, pv take r g u / buy 1.2 / take r g w`,
+/// with where the move is among the others, like `(move 2 of 30)`, when it
+/// isn't the best.
+impl Display for Thought {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "depth {}, {} nodes in {:.1?}",
+            self.depth, self.nodes, self.elapsed
+        )?;
+        if self.threads > 1 {
+            write!(f, " on {} threads, {:.1}x", self.threads, self.speedup)?;
+        }
+        write!(f, ", score {}", self.score)?;
+        if self.determinizations > 1 {
+            write!(
+                f,
+                ", {} of {} determinizations",
+                self.votes, self.determinizations
+            )?;
+        }
+        if let Some(plies) = self.mate {
+            write!(f, ", mate in {plies} plies")?;
+        }
+        write!(f, ", playing {}", self.action)?;
+        if self.rank > 0 {
+            write!(f, " (move {} of {})", self.rank + 1, self.root.len())?;
+        }
+        if !self.pv.is_empty() {
+            let pv: Vec<_> = self.pv.iter().map(|ac| ac.to_string()).collect();
+            write!(f, ", pv {}", pv.join(" / "))?;
+        }
+        Ok(())
+    }
+}
+
+impl AlphaBeta {
+    pub fn choose(&self, observation: Observation) -> Action {
+        let thought = self.think(observation);
+        eprintln!("alphabeta: {thought}");
+        thought.action
+    }
+
+    /// What the player to move in `observation` expects to be asked next,
+    /// after playing `thought.action` and the others replying as in
+    /// `thought.pv`, or `None` if the principal variation doesn't go that
+    /// far or the game ends before. Cards dealt on the way are made up with
+    /// `seed`, so the guess is only right when none are.
+    pub fn next_position(
+        &self,
+        observation: &Observation,
+        thought: &Thought,
+    ) -> Option<Observation> {
+        let mut state = observation.to_determinized_state(&mut StdRng::seed_from_u64(self.seed));
+        let me = state.turn;
+        let mut pv = thought.pv.iter();
+        state.apply(pv.next()?).ok()?;
+        while state.turn != me {
+            state.apply(pv.next()?).ok()?;
+        }
+        (!state.is_finished()).then(|| state.observation_for(me))
+    }
+
+    /// Searches for the best move in `observation`. With more than one of
+    /// `determinizations`, the move found best in the most of them, then
+    /// the one with the best mean score there; its score is that mean, and
+    /// the rest of the [`Thought`] is that of the first determinization
+    /// that found it.
+    pub fn think(&self, observation: Observation) -> Thought {
+        self.think_until(observation, Arc::default())
+    }
+
+    /// Only the search to the end of the game that [`AlphaBeta::think`]
+    /// starts with: the move of the player to move that wins whatever the
+    /// others play, and the most plies until the game is over. `None` when
+    /// nobody is within [`AlphaBeta::endgame`] points of the target score,
+    /// or no win is found within its time.
+    pub fn solve(&self, observation: Observation) -> Option<(Action, u32)> {
+        let mut state = observation.to_determinized_state(&mut StdRng::seed_from_u64(self.seed));
+        state.rehash();
+        if !endgame::close(&state, self.endgame) {
+            return None;
+        }
+        endgame::solve(&state, &mut self.budget(self.time_limit, Arc::default()))
+    }
+
+    /// Like [`AlphaBeta::think`], but once `stop` is set it stops searching
+    /// and returns what it found so far, like when its time is up.
+    pub fn think_until(&self, observation: Observation, stop: Arc<AtomicBool>) -> Thought {
+        let started = Instant::now();
+        let position = observation.state.zobrist();
+        let count = self.determinizations.max(1);
+        let share = AlphaBeta {
+            time_limit: self.time_limit / count as u32,
+            ..self.clone()
+        };
+        let mut thoughts = vec![];
+        for k in 0..count {
+            if k > 0 && stop.load(Ordering::Relaxed) {
+                break;
+            }
+            // The search needs full decks; fill the face down cards with a
+            // fixed seed so the same position always gets the same answer.
+            let seed = self.seed.wrapping_add(k as u64);
+            let state = observation.to_determinized_state(&mut StdRng::seed_from_u64(seed));
+            thoughts.push(share.search(state, stop.clone()));
+        }
+        let mut thought = vote(thoughts);
+        // A proven win is played whatever the level.
+        if self.sampling() && thought.mate.is_none() && !thought.root.is_empty() {
+            let mut rng = StdRng::seed_from_u64(self.seed ^ position);
+            let k = difficulty::pick(&thought.root, self.temperature, self.blunder, &mut rng);
+            if k > 0 {
+                thought.action = thought.root[k].action.clone();
+                thought.score = thought.root[k].score;
+                thought.pv = vec![thought.action.clone()];
+                thought.rank = k;
+            }
+        }
+        thought.elapsed = started.elapsed();
+        thought
+    }
+
+    /// A search of at most `time_limit`, and [`AlphaBeta::max_nodes`] and
+    /// [`AlphaBeta::max_think_time`].
+    fn budget(&self, time_limit: Duration, stop: Arc<AtomicBool>) -> Budget {
+        Budget {
+            nodes: 0,
+            max_nodes: self.max_nodes,
+            deadline: Instant::now() + time_limit.min(self.max_think_time),
+            stop,
+        }
+    }
+
+    /// Whether it may play another move than the best.
+    fn sampling(&self) -> bool {
+        self.temperature > 0.0 || self.blunder > 0.0
+    }
+
+    /// The search of one determinized `state`.
+    fn search(&self, mut state: State, stop: Arc<AtomicBool>) -> Thought {
+        state.rehash();
+        let me = state.turn;
+        let started = Instant::now();
+        let threads = self.threads.max(1);
+        let mut searches: Vec<_> = (0..threads)
+            .map(|_| Search {
+                me,
+                model: self.model,
+                weights: self.weights,
+                network: self.network.clone(),
+                scratch: Scratch::default(),
+                budget: self.budget(self.time_limit, stop.clone()),
+                table: Table::new(self.table_memory / threads),
+                ordering: self.ordering,
+                pvs: self.pvs,
+                exact_root: self.sampling(),
+                killers: vec![],
+            })
+            .collect();
+        // Each move with where it is listed, which breaks ties between equal
+        // scores the same way whatever order they are searched in, and on
+        // however many threads.
+        let mut root: Vec<_> = moves(&state).into_iter().enumerate().collect();
+        if self.ordering {
+            root.sort_by_cached_key(|(_, ac)| Reverse(promise(&state, ac)));
+        }
+        let mut thought = Thought {
+            action: root.first().map_or(Action::Skip, |(_, ac)| ac.clone()),
+            score: 0,
+            depth: 0,
+            mate: None,
+            nodes: 0,
+            determinizations: 1,
+            votes: 1,
+            elapsed: Duration::ZERO,
+            threads,
+            speedup: 1.0,
+            pv: vec![],
+            root: vec![],
+            rank: 0,
+        };
+        let mut solving = self.budget(self.time_limit / 2, stop.clone());
+        if root.len() > 1 && endgame::close(&state, self.endgame) {
+            if let Some((action, plies)) = endgame::solve(&state, &mut solving) {
+                thought.action = action;
+                thought.score = WON;
+                thought.depth = plies as i32;
+                thought.mate = Some(plies);
+            }
+        }
+        let nodes = |searches: &[Search]| searches.iter().map(|s| s.budget.nodes).sum::<u64>();
+        // Nodes and time searching alone, then on all threads.
+        let mut alone = (0, Duration::ZERO);
+        let mut together = (0, Duration::ZERO);
+        for depth in 1..=self.depth {
+            if root.len() <= 1 || thought.mate.is_some() {
+                break;
+            }
+            // Another depth takes longer than all those before it.
+            if (depth > 1 && started.elapsed() > self.time_limit / 2)
+                || stop.load(Ordering::Relaxed)
+            {
+                break;
+            }
+            let first = root
+                .iter()
+                .position(|(_, ac)| *ac == thought.action)
+                .unwrap_or(0);
+            root[..=first].rotate_right(1);
+            // Around the score of the last depth, widened on the side the
+            // best score falls out of until it falls within, or it fails.
+            let mut window = (-INFINITY, INFINITY);
+            if self.aspiration && !self.sampling() && self.model == Model::Paranoid && depth > 1 {
+                window = (thought.score - ASPIRATION, thought.score + ASPIRATION);
+            }
+            let mut width = ASPIRATION;
+            let (ranked, finished, low) = loop {
+                // The best move of the last depth first and alone, so the
+                // others have its score to cut off against on every thread.
+                let alpha = AtomicI32::new(window.0);
+                let (searched, before) = (Instant::now(), nodes(&searches));
+                let (mut scores, mut finished) = searches[0].root(
+                    &state,
+                    &root[..1],
+                    depth,
+                    &AtomicUsize::new(0),
+                    &alpha,
+                    window.1,
+                );
+                alone.0 += nodes(&searches) - before;
+                alone.1 += searched.elapsed();
+                if finished {
+                    let (searched, before) = (Instant::now(), nodes(&searches));
+                    let next = AtomicUsize::new(1);
+                    let run = |search: &mut Search| {
+                        search.root(&state, &root, depth, &next, &alpha, window.1)
+                    };
+                    let runs: Vec<_> = if threads == 1 {
+                        vec![run(&mut searches[0])]
+                    } else {
+                        std::thread::scope(|scope| {
+                            let handles: Vec<_> = searches
+                                .iter_mut()
+                                .map(|search| scope.spawn(|| run(search)))
+                                .collect();
+                            handles.into_iter().map(|h| h.join().unwrap()).collect()
+                        })
+                    };
+                    for (more, done) in runs {
+                        scores.extend(more);
+                        finished &= done;
+                    }
+                    together.0 += nodes(&searches) - before;
+                    together.1 += searched.elapsed();
+                }
+                // Only moves whose search finished, so an unfinished depth
+                // only changes the move for one that beat the best of the
+                // last depth.
+                let mut ranked: Vec<_> = scores
+                    .into_iter()
+                    .map(|(k, score, exact)| (score, Reverse(root[k].0), k, exact))
+                    .collect();
+                ranked.sort_by_key(|&(score, first, _, _)| Reverse((score, first)));
+                let best = ranked.first().map_or(window.0, |&(score, ..)| score);
+                let low = best <= window.0 && window.0 > -INFINITY;
+                let high = best >= window.1 && window.1 < INFINITY;
+                if !finished || !(low || high) {
+                    break (ranked, finished, low);
+                }
+                width = width.saturating_mul(4);
+                if low {
+                    window.0 = best.saturating_sub(width).max(-INFINITY);
+                }
+                if high {
+                    window.1 = best.saturating_add(width).min(INFINITY);
+                }
+            };
+            let Some(&(score, _, k, _)) = ranked.first() else {
+                break;
+            };
+            // Below the window every score is only a bound, and the move of
+            // the last depth as good as any.
+            if low {
+                break;
+            }
+            thought.action = root[k].1.clone();
+            if !finished {
+                break;
+            }
+            thought.score = score;
+            thought.depth = depth;
+            thought.root = ranked
+                .into_iter()
+                .map(|(score, _, k, exact)| RootMove {
+                    action: root[k].1.clone(),
+                    score,
+                    exact,
+                })
+                .collect();
+            if score.abs() >= WON {
+                break;
+            }
+        }
+        thought.nodes = nodes(&searches) + solving.nodes;
+        thought.pv = principal_variation(&mut state, &thought.action, &searches);
+        let rate = |(nodes, time): (u64, Duration)| nodes as f64 / time.as_secs_f64().max(1e-9);
+        if alone.0 > 0 && together.0 > 0 {
+            thought.speedup = rate(together) / rate(alone);
+        }
+        thought.elapsed = started.elapsed();
+        thought
+    }
+}
+
+/// The move found best in the most of `thoughts`, see [`AlphaBeta::think`].
+/// A win is only proven if every one of them proved it.
+fn vote(thoughts: Vec<Thought>) -> Thought {
+    // Each move with its votes and the sum of its scores, in the order
+    // first found.
+    let mut tally: Vec<(&Action, usize, i64)> = vec![];
+    for thought in &thoughts {
+        match tally.iter_mut().find(|(ac, _, _)| **ac == thought.action) {
+            Some((_, votes, sum)) => {
+                *votes += 1;
+                *sum += i64::from(thought.score);
+            }
+            None => tally.push((&thought.action, 1, i64::from(thought.score))),
+        }
+    }
+    let mean = |(_, votes, sum): &(&Action, usize, i64)| *sum / *votes as i64;
+    let mut best = 0;
+    for k in 1..tally.len() {
+        if (tally[k].1, mean(&tally[k])) > (tally[best].1, mean(&tally[best])) {
+            best = k;
+        }
+    }
+    let (action, votes, score) = (tally[best].0.clone(), tally[best].1, mean(&tally[best]));
+    let nodes = thoughts.iter().map(|t| t.nodes).sum();
+    let determinizations = thoughts.len();
+    let mate = match thoughts.iter().all(|t| t.action == action) {
+        true => thoughts.iter().map(|t| t.mate).collect::<Option<Vec<_>>>(),
+        false => None,
+    };
+    let mut thought = thoughts
+        .into_iter()
+        .find(|t| t.action == action)
+        .expect("the move voted for was found best");
+    thought.score = score as i32;
+    thought.mate = mate.and_then(|plies| plies.into_iter().max());
+    thought.nodes = nodes;
+    thought.determinizations = determinizations;
+    thought.votes = votes;
+    thought
+}
+
+impl AgentHandler for AlphaBeta {
+    fn act(&mut self, observation: Observation) -> anyhow::Result<Action> {
+        Ok(self.choose(observation))
+    }
+}
@@ -1,96 +1,355 @@
-use game_def::{ai_from_function, Action, Player, State};
+//! Usage: `alpha-beta-agent [--depth <rounds>] [--time-limit <seconds>]
+//! [--time-ms <milliseconds>] [--table-mb <megabytes>]
+//! [--model paranoid|maxn] [--seed <n>] [--weights <file>] [--eval <file>]
+//! [--threads <n>] [--endgame <points>] [--determinizations <n>]
+//! [--log stderr|<file>] [--verbose] [--ponder] [--no-pvs] [--no-aspiration]
+//! [--difficulty easy|medium|hard|custom] [--temperature <points>]
+//! [--blunder <rate>] [--analyze <file> [--top <n>]]`
+//!
+//! Searches at most `--depth` rounds ahead and stops deepening after
+//! `--time-limit` seconds, or `--time-ms` milliseconds, 2 seconds by default.
+//! Its transposition table takes at most `--table-mb` megabytes, 16 by
+//! default, and 0 turns it off. With more than two players, `--model
+//! paranoid` takes the others to all play against it, the default, and
+//! `--model maxn` each to play for itself. `--seed` picks the cards it
+//! imagines where it can't see them. `--weights` reads the weights it
+//! scores positions by from a JSON file, see [`Weights`]; those it plays
+//! with are printed to stderr first thing. `--eval` scores them by a
+//! learned network from a JSON file instead, see [`Network`], and prints
+//! its shape. `--threads` searches on that many threads, 1 by default. Once
+//! someone is within `--endgame` points of the target score, 3 by default,
+//! it first looks for a win it can prove by searching to the end of the
+//! game, and logs `mate in <n> plies` when it finds one; 0 never looks.
+//! `--determinizations` searches that many ways the cards it can't see may
+//! lie, each for its share of the time, and plays the move most of them
+//! found best, 1 by default. `--no-pvs` and `--no-aspiration` turn off the
+//! principal variation search and the aspiration windows, which play the
+//! same moves faster, to find out whether they are what broke something.
+//!
+//! `--difficulty` plays below full strength for people learning the game,
+//! setting the depth, time, endgame and the two knobs below for the level,
+//! see [`Difficulty`]; other arguments change them from there, wherever
+//! they are given. `--temperature` plays a worse move now and then, the
+//! less often the more points it scores below the best, 0 by default for
+//! always the best. `--blunder` plays any move not found losing at that
+//! rate, at most 0.25. Both pick their moves by `--seed`, so a game with
+//! the same seeds plays out the same.
+//!
+//! For each move it logs the depth it reached, the nodes it searched, the
+//! time it took, the score and the moves it expects next, to stderr or
+//! appended to the file `--log` names. `--verbose` adds its three best
+//! moves with their scores.
+//!
+//! With `--ponder` it goes on searching while the others think, on the
+//! position it expects after their replies. When that is the position it is
+//! asked about, it answers with that search once it has had its time, or
+//! right away if it already had; otherwise it drops it and searches again.
+//!
+//! `--analyze` searches the one position of a JSON file, a state or an
+//! observation, see [`Observation::load`], prints what it found and exits:
+//! the best move, its score, the moves it expects next, the `--top` moves
+//! at the root with their scores, 5 by default, and how much it searched.
+//! The replay prompt's `save` writes any ply of a recorded game to such a
+//! file.
+//!
+//! Bad arguments fail before the first state is read, so a runner starting
+//! it with `--agent "./alpha-beta-agent --depth 5"` reports them right away.
+//! `--format` and `--protocol`, which the runner adds, are for
+//! [`run_agent`].
 
-fn moves(state: State) -> Vec<(State, Action)> {
-    let mut r = vec![];
-    for (deck, card) in state.card_iter() {
-        let action = Action::Purchase { deck, card };
-        let mut s = state.clone();
-        if s.run(action.clone()).is_ok() {
-            r.push((s, action));
-        }
-    }
+use std::{
+    fs::File,
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    time::Duration,
+};
 
-    for index in 0..state.players[state.turn].reserved.len() {
-        let action = Action::PurchaseReserved { index };
-        let mut s = state.clone();
-        if s.run(action.clone()).is_ok() {
-            r.push((s, action));
-        }
-    }
+use alpha_beta_agent::{AlphaBeta, Difficulty, Network, Thought, Weights, MAX_BLUNDER};
+use anyhow::Context;
+use game_def::{run_agent, Action, AgentHandler, Analysis, Observation};
 
-    for (one, two, three) in state.pick_three_iter() {
-        let action = Action::PickThree { one, two, three };
-        let mut s = state.clone();
-        if s.run(action.clone()).is_ok() {
-            r.push((s, action));
-        }
-    }
+const USAGE: &str = "usage: alpha-beta-agent [--depth <rounds>] [--time-limit <seconds>] [--time-ms <milliseconds>] [--table-mb <megabytes>] [--model paranoid|maxn] [--seed <n>] [--weights <file>] [--eval <file>] [--threads <n>] [--endgame <points>] [--determinizations <n>] [--log stderr|<file>] [--verbose] [--ponder] [--no-pvs] [--no-aspiration] [--difficulty easy|medium|hard|custom] [--temperature <points>] [--blunder <rate>] [--analyze <file> [--top <n>]]";
 
-    for color in state.pick_two_iter() {
-        let action = Action::PickTwo { color };
-        let mut s = state.clone();
-        if s.run(action.clone()).is_ok() {
-            r.push((s, action));
-        }
-    }
+/// The agent, writing what it thought of each move to `log`.
+struct Logged {
+    agent: AlphaBeta,
+    log: Box<dyn Write>,
+    verbose: bool,
+    pondering: bool,
+    ponder: Option<Ponder>,
+}
 
-    for (deck, card) in state.card_iter() {
-        let action = Action::Reserve { deck, card };
-        let mut s = state.clone();
-        if s.run(action.clone()).is_ok() {
-            r.push((s, action));
-        }
-    }
-    r
+/// A search running on the position expected next, while the others think.
+struct Ponder {
+    /// The [`State::zobrist`](game_def::State::zobrist) of that position.
+    position: u64,
+    stop: Arc<AtomicBool>,
+    thought: Receiver<Thought>,
 }
 
-fn heuristic(state: &State) -> i32 {
-    player_heuristic(state, &state.players[0]) - player_heuristic(state, &state.players[1])
+impl Ponder {
+    fn start(agent: &AlphaBeta, observation: &Observation, thought: &Thought) -> Option<Ponder> {
+        let next = agent.next_position(observation, thought)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let (send, receive) = mpsc::channel();
+        // Until stopped, as long as a search may take.
+        let agent = AlphaBeta {
+            time_limit: Duration::MAX,
+            ..agent.clone()
+        };
+        let position = next.state.zobrist();
+        let until = stop.clone();
+        std::thread::spawn(move || {
+            // Nobody is waiting for it once the agent is done.
+            let _ = send.send(agent.think_until(next, until));
+        });
+        Some(Ponder {
+            position,
+            stop,
+            thought: receive,
+        })
+    }
+
+    /// Stops the search and returns what it found.
+    fn stop(self) -> Option<Thought> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.thought.recv().ok()
+    }
 }
 
-fn player_heuristic(state: &State, player: &Player) -> i32 {
-    player.mortal.sum() * 3
-        + player.wilds as i32 * 4
-        + player.immortal.sum() * 100
-        + (1 << player.score) * 10
-        + state
-            .nobels
-            .iter()
-            .map(|n| player.immortal.0.iter().map(|(c, v)| *v - n.cost[c]).sum::<usize>())
-            .map(|t| 1000 >> t)
-            .sum::<i32>()
+impl Logged {
+    fn think(&mut self, observation: &Observation) -> anyhow::Result<Thought> {
+        let Some(ponder) = self.ponder.take() else {
+            return Ok(self.agent.think(observation.clone()));
+        };
+        if ponder.position != observation.state.zobrist() {
+            writeln!(self.log, "alphabeta: ponder miss")?;
+            ponder.stop();
+            return Ok(self.agent.think(observation.clone()));
+        }
+        writeln!(self.log, "alphabeta: ponder hit")?;
+        let thought = match ponder.thought.recv_timeout(self.agent.time_limit) {
+            Ok(thought) => Some(thought),
+            Err(_) => ponder.stop(),
+        };
+        Ok(match thought {
+            Some(thought) if thought.depth > 0 => thought,
+            _ => self.agent.think(observation.clone()),
+        })
+    }
 }
 
-fn max_score(state: State, depth: i32, mut alpha: i32, beta: i32) -> (i32, Action) {
-    if state.is_finished() {
-        if state.winner() == 0 {
-            return (1_000_000_000, Action::Skip);
-        } else {
-            return (-1_000_000_000, Action::Skip);
+impl AgentHandler for Logged {
+    fn act(&mut self, observation: Observation) -> anyhow::Result<Action> {
+        let thought = self.think(&observation)?;
+        if self.pondering {
+            self.ponder = Ponder::start(&self.agent, &observation, &thought);
+        }
+        writeln!(self.log, "alphabeta: {thought}")?;
+        if self.verbose && !thought.root.is_empty() {
+            let top: Vec<_> = thought.root.iter().take(3).map(|m| m.to_string()).collect();
+            writeln!(self.log, "alphabeta: best {}", top.join(", "))?;
         }
+        Ok(thought.action)
     }
-    if state.turn == 0 && depth <= 0 {
-        return (heuristic(&state), Action::Skip);
+}
+
+fn main() -> anyhow::Result<()> {
+    let mut agent = AlphaBeta::default();
+    let mut log: Box<dyn Write> = Box::new(std::io::stderr());
+    let mut verbose = false;
+    let mut pondering = false;
+    let mut analyze = None;
+    let mut top = 5;
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    // The level first, for the other arguments to change.
+    let difficulty = match args.iter().position(|arg| arg == "--difficulty") {
+        Some(k) => Some(
+            args.get(k + 1)
+                .context("--difficulty needs easy, medium, hard or custom")?
+                .parse::<Difficulty>()?,
+        ),
+        None => None,
+    };
+    if let Some(difficulty) = difficulty {
+        difficulty.apply(&mut agent);
     }
-    let mut r = (-1_000_000_001, Action::Skip);
-    for (st, ac) in moves(state) {
-        let score = -max_score(st, depth - 1, -beta, -alpha).0;
-        if r.0 < score {
-            r = (score, ac);
-            alpha = alpha.max(score);
-            if score >= beta {
-                break;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--depth" => {
+                agent.depth = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .filter(|&depth| depth > 0)
+                    .context("--depth needs a positive number")?
+            }
+            "--time-limit" => {
+                agent.time_limit = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .and_then(|x| Duration::try_from_secs_f64(x).ok())
+                    .context("--time-limit needs a positive number of seconds")?
+            }
+            "--time-ms" => {
+                agent.time_limit = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .map(Duration::from_millis)
+                    .context("--time-ms needs a number of milliseconds")?
+            }
+            "--table-mb" => {
+                let megabytes: usize = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .context("--table-mb needs a number of megabytes")?;
+                agent.table_memory = megabytes << 20;
             }
+            "--model" => {
+                agent.model = args
+                    .next()
+                    .context("--model needs paranoid or maxn")?
+                    .parse()?
+            }
+            "--seed" => {
+                agent.seed = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .context("--seed needs a number")?
+            }
+            "--weights" => {
+                let path = args.next().context("--weights needs a file")?;
+                agent.weights = Weights::load(path.as_ref())?;
+            }
+            "--eval" => {
+                let path = args.next().context("--eval needs a file")?;
+                agent.network = Some(Arc::new(Network::load(path.as_ref())?));
+            }
+            "--threads" => {
+                agent.threads = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .filter(|&threads| threads > 0)
+                    .context("--threads needs a positive number")?
+            }
+            "--endgame" => {
+                agent.endgame = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .context("--endgame needs a number of points")?
+            }
+            "--determinizations" => {
+                agent.determinizations = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .filter(|&n| n > 0)
+                    .context("--determinizations needs a positive number")?
+            }
+            "--log" => {
+                log = match args
+                    .next()
+                    .context("--log needs stderr or a file")?
+                    .as_str()
+                {
+                    "stderr" => Box::new(std::io::stderr()),
+                    path => Box::new(
+                        File::options()
+                            .create(true)
+                            .append(true)
+                            .open(path)
+                            .with_context(|| format!("Failed to open the log {path}"))?,
+                    ),
+                }
+            }
+            "--verbose" => verbose = true,
+            "--ponder" => pondering = true,
+            "--no-pvs" => agent.pvs = false,
+            "--no-aspiration" => agent.aspiration = false,
+            "--difficulty" => {
+                args.next();
+            }
+            "--temperature" => {
+                agent.temperature = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .filter(|&t: &f64| t >= 0.0 && t.is_finite())
+                    .context("--temperature needs a number of points")?
+            }
+            "--blunder" => {
+                agent.blunder = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .filter(|rate| (0.0..=MAX_BLUNDER).contains(rate))
+                    .with_context(|| format!("--blunder needs a rate from 0 to {MAX_BLUNDER}"))?
+            }
+            "--analyze" => analyze = Some(args.next().context("--analyze needs a file")?),
+            "--top" => {
+                top = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .context("--top needs a number of moves")?
+            }
+            "--format" | "--protocol" => {
+                args.next();
+            }
+            _ => anyhow::bail!("Unknown argument {arg}, {USAGE}"),
         }
     }
-    r
-}
-
-fn logic(state: State) -> Action {
-    let (_, ac) = max_score(state, 4, -2_000_000_000, 2_000_000_000);
-    ac
+    if let Some(difficulty) = difficulty {
+        eprintln!("alphabeta: difficulty {difficulty}");
+    }
+    match &agent.network {
+        Some(network) => eprintln!("alphabeta: network {}", network.shape()),
+        None => eprintln!(
+            "alphabeta: weights {}",
+            serde_json::to_string(&agent.weights)?
+        ),
+    }
+    if let Some(path) = analyze {
+        let observation = Observation::load(&path)?;
+        let thought = agent.think(observation.clone());
+        print!("{}", report(&observation, &thought, top));
+        return Ok(());
+    }
+    run_agent(Logged {
+        agent,
+        log,
+        verbose,
+        pondering,
+        ponder: None,
+    })
 }
 
-fn main() {
-    ai_from_function(logic);
+/// What `--analyze` prints about the search of `observation`.
+fn report(observation: &Observation, thought: &Thought, top: usize) -> String {
+    let mut searched = format!(
+        "depth {}, {} nodes in {:.1?}",
+        thought.depth, thought.nodes, thought.elapsed
+    );
+    if thought.threads > 1 {
+        searched += &format!(" on {} threads, {:.1}x", thought.threads, thought.speedup);
+    }
+    if thought.determinizations > 1 {
+        searched += &format!(
+            ", {} of {} determinizations",
+            thought.votes, thought.determinizations
+        );
+    }
+    Analysis {
+        observation,
+        best: &thought.action,
+        pv: &thought.pv,
+        score: match thought.mate {
+            Some(plies) => format!("{}, mate in {plies} plies", thought.score),
+            None => thought.score.to_string(),
+        },
+        root: thought.root.iter().map(|root| root.to_string()).collect(),
+        top,
+        searched,
+    }
+    .to_string()
 }
@@ -0,0 +1,210 @@
+//! A learned evaluation, scoring positions by a small neural network over
+//! [`State::features`] in place of [`Weights`](crate::Weights).
+
+use std::path::Path;
+
+use anyhow::{bail, ensure, Context};
+use game_def::{features::FEATURE_COUNT, State};
+use serde::{Deserialize, Serialize};
+
+/// What `format` says in every file.
+pub const FORMAT: &str = "splendor-eval";
+/// The version of the format this build reads, the only one so far.
+pub const VERSION: u32 = 1;
+/// What a network may say a position is worth at most, well below the
+/// scores of won games.
+const MAX_SCORE: f32 = 100_000_000.0;
+
+/// A network read from JSON with [`Network::load`], version 1 of the format
+/// being:
+///
+/// ```json
+/// {
+///   "format": "splendor-eval",
+///   "version": 1,
+///   "inputs": 163,
+///   "layers": [
+///     {"weights": [[0.5, -1.0, ...], ...], "bias": [0.0, ...]},
+///     {"weights": [[2.0, ...]], "bias": [0.0]}
+///   ]
+/// }
+/// ```
+///
+/// `inputs` is [`FEATURE_COUNT`], the length of the features the network
+/// was made for. Each layer has a row of `weights` and a `bias` for each of
+/// its outputs, a row having a weight for each output of the layer before,
+/// or for each feature. The outputs of every layer but the last go through
+/// a ReLU, and the last has one output, what the position is worth to the
+/// player the features are seen from, in the units [`Weights`] scores in: a
+/// card bought is worth about 100. A linear model is a network of one layer.
+///
+/// [`Weights`]: crate::Weights
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Network {
+    pub format: String,
+    pub version: u32,
+    pub inputs: usize,
+    pub layers: Vec<Layer>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Layer {
+    pub weights: Vec<Vec<f32>>,
+    pub bias: Vec<f32>,
+}
+
+/// The buffers [`Network::evaluate`] works in, kept between positions so
+/// that scoring them allocates nothing.
+#[derive(Debug, Clone, Default)]
+pub struct Scratch {
+    input: Vec<f32>,
+    output: Vec<f32>,
+}
+
+impl Network {
+    /// A linear model: a network of one layer, with `weights` for the
+    /// features and `bias`.
+    pub fn linear(weights: Vec<f32>, bias: f32) -> Network {
+        Network {
+            format: FORMAT.to_owned(),
+            version: VERSION,
+            inputs: weights.len(),
+            layers: vec![Layer {
+                weights: vec![weights],
+                bias: vec![bias],
+            }],
+        }
+    }
+
+    /// Reads the network from the JSON file at `path`, failing on anything
+    /// [`Network::check`] does.
+    pub fn load(path: &Path) -> anyhow::Result<Network> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the network {}", path.display()))?;
+        let network: Network = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse the network {}", path.display()))?;
+        network
+            .check()
+            .with_context(|| format!("Bad network in {}", path.display()))?;
+        Ok(network)
+    }
+
+    /// Fails on another format or version, on a network made for features
+    /// of another length, on layers that don't fit together or don't end in
+    /// one output, and on weights that are not numbers.
+    ///
+    /// ```
+    /// use alpha_beta_agent::Network;
+    /// use game_def::features::FEATURE_COUNT;
+    ///
+    /// let network = Network::linear(vec![0.0; FEATURE_COUNT], 1.0);
+    /// assert!(network.check().is_ok());
+    /// assert!(Network::linear(vec![0.0; 5], 1.0).check().is_err());
+    /// let mut newer = network.clone();
+    /// newer.version = 2;
+    /// assert!(newer.check().is_err());
+    /// let mut wide = network.clone();
+    /// wide.layers[0].bias.push(0.0);
+    /// assert!(wide.check().is_err());
+    /// let mut broken = network.clone();
+    /// broken.layers[0].weights[0][3] = f32::NAN;
+    /// assert!(broken.check().is_err());
+    /// ```
+    pub fn check(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.format == FORMAT,
+            "the format is {:?}, not {FORMAT:?}",
+            self.format
+        );
+        ensure!(
+            self.version == VERSION,
+            "version {} of the format, this build reads version {VERSION}",
+            self.version
+        );
+        ensure!(
+            self.inputs == FEATURE_COUNT,
+            "made for {} features, the state has {FEATURE_COUNT}",
+            self.inputs
+        );
+        let mut width = self.inputs;
+        for (i, layer) in self.layers.iter().enumerate() {
+            ensure!(
+                layer.weights.len() == layer.bias.len(),
+                "layer {i} has {} rows of weights and {} biases",
+                layer.weights.len(),
+                layer.bias.len()
+            );
+            ensure!(!layer.bias.is_empty(), "layer {i} has no outputs");
+            for (j, row) in layer.weights.iter().enumerate() {
+                ensure!(
+                    row.len() == width,
+                    "row {j} of layer {i} has {} weights for {width} inputs",
+                    row.len()
+                );
+            }
+            let mut numbers = layer.weights.iter().flatten().chain(&layer.bias);
+            if let Some(bad) = numbers.find(|x| !x.is_finite()) {
+                bail!("layer {i} has a weight of {bad}");
+            }
+            width = layer.bias.len();
+        }
+        ensure!(!self.layers.is_empty(), "there are no layers");
+        ensure!(width == 1, "the last layer has {width} outputs, not 1");
+        Ok(())
+    }
+
+    /// The widths of the layers, features first, like `163-32-1`.
+    pub fn shape(&self) -> String {
+        let widths = self.layers.iter().map(|layer| layer.bias.len());
+        let widths: Vec<_> = std::iter::once(self.inputs)
+            .chain(widths)
+            .map(|n| n.to_string())
+            .collect();
+        widths.join("-")
+    }
+
+    /// What `state` is worth to `me`, working in `scratch`. The network must
+    /// have passed [`Network::check`].
+    ///
+    /// ```
+    /// use alpha_beta_agent::{Network, Scratch};
+    /// use game_def::{features::feature_names, GameConfig, State};
+    ///
+    /// // Points and nothing else, 100 each.
+    /// let weights = feature_names()
+    ///     .iter()
+    ///     .map(|name| match name.as_str() {
+    ///         "p0.score" => 100.0,
+    ///         "p1.score" => -100.0,
+    ///         _ => 0.0,
+    ///     })
+    ///     .collect();
+    /// let network = Network::linear(weights, 5.0);
+    /// let mut state = State::setup(&["a", "b"], GameConfig::default(), &mut rand::thread_rng());
+    /// state.players[0].score = 3;
+    /// let mut scratch = Scratch::default();
+    /// assert_eq!(network.evaluate(&state, 0, &mut scratch), 305);
+    /// assert_eq!(network.evaluate(&state, 1, &mut scratch), -295);
+    /// ```
+    pub fn evaluate(&self, state: &State, me: usize, scratch: &mut Scratch) -> i32 {
+        let Scratch { input, output } = scratch;
+        state.features_into(me, input);
+        let last = self.layers.len() - 1;
+        for (i, layer) in self.layers.iter().enumerate() {
+            output.clear();
+            for (row, bias) in layer.weights.iter().zip(&layer.bias) {
+                let sum = row
+                    .iter()
+                    .zip(input.iter())
+                    .map(|(w, x)| w * x)
+                    .sum::<f32>()
+                    + bias;
+                output.push(if i < last { sum.max(0.0) } else { sum });
+            }
+            std::mem::swap(input, output);
+        }
+        input[0].clamp(-MAX_SCORE, MAX_SCORE) as i32
+    }
+}
@@ -0,0 +1,277 @@
+//! What the search scores a position by, with every weight of it in
+//! [`Weights`], so they can be tuned without building again.
+
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use game_def::{Card, Objective, Player, State};
+use serde::{Deserialize, Serialize};
+
+/// The weights of the evaluation, read from JSON with [`Weights::load`].
+/// Those a file leaves out keep their defaults, the values the search was
+/// tuned with by hand.
+///
+/// A player is worth the sum of its coins, cards, points, reserves, the
+/// card it can buy next and nobel progress, and a position is what the
+/// player searching is worth less `opponents` times the best of the others,
+/// and the terms of what it keeps from the others: `threat`, `denied` and
+/// `blocked`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Weights {
+    /// Each colored coin in hand.
+    pub coin: f64,
+    /// Each wild coin in hand.
+    pub wild: f64,
+    /// Each card bought, by its tier, cheapest first.
+    pub card: [f64; 3],
+    /// Points are worth `score_scale * score_growth.powi(points)`, so each
+    /// point is worth more the closer it is to winning.
+    pub score_scale: f64,
+    pub score_growth: f64,
+    /// Each nobel is worth `nobel / (cards missing + 1)`, see
+    /// [`Weights::nobel_closeness`], and all of them together at most
+    /// `nobel_cap`, so being close to several doesn't outweigh the rest.
+    pub nobel: f64,
+    pub nobel_cap: f64,
+    /// Each card reserved.
+    pub reserve: f64,
+    /// The best card a player can buy right now, face up or reserved, is
+    /// worth `reach` for each point it gives and once more for the card.
+    pub reach: f64,
+    /// Each card one of the others can buy right now that takes it to the
+    /// target score counts `threat` against the position.
+    pub threat: f64,
+    /// Each card reserved that one of the others could buy right now is
+    /// worth `denied` for each point it gives and once more, for taking it
+    /// from under them.
+    pub denied: f64,
+    /// The best card on the table one of the others is at most three coins
+    /// short of, all of colors the bank has run out of, is worth `blocked`
+    /// for each point it gives and once more.
+    pub blocked: f64,
+    /// How much the best of the others counts against the position. Below 1
+    /// plays more for itself, above 1 more to hold the others back.
+    pub opponents: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            coin: 3.0,
+            wild: 4.0,
+            card: [100.0; 3],
+            score_scale: 10.0,
+            score_growth: 2.0,
+            nobel: 300.0,
+            nobel_cap: 400.0,
+            reserve: 0.0,
+            reach: 20.0,
+            threat: 1000.0,
+            denied: 20.0,
+            blocked: 20.0,
+            opponents: 1.0,
+        }
+    }
+}
+
+impl Weights {
+    /// Reads the weights from the JSON file at `path`, like
+    /// `{"coin": 2.5, "card": [80, 100, 120]}`.
+    pub fn load(path: &Path) -> anyhow::Result<Weights> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the weights {}", path.display()))?;
+        let weights: Weights = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse the weights {}", path.display()))?;
+        weights
+            .check()
+            .with_context(|| format!("Bad weights in {}", path.display()))?;
+        Ok(weights)
+    }
+
+    /// Fails on weights that are not numbers or below zero, and on a score
+    /// curve that would make more points worth less.
+    ///
+    /// ```
+    /// use alpha_beta_agent::Weights;
+    ///
+    /// assert!(Weights::default().check().is_ok());
+    /// let weights = Weights { coin: f64::NAN, ..Weights::default() };
+    /// assert!(weights.check().is_err());
+    /// let weights = Weights { card: [100.0, -1.0, 100.0], ..Weights::default() };
+    /// assert!(weights.check().is_err());
+    /// let weights = Weights { score_growth: 0.5, ..Weights::default() };
+    /// assert!(weights.check().is_err());
+    /// ```
+    pub fn check(&self) -> anyhow::Result<()> {
+        let named = [
+            ("coin", self.coin),
+            ("wild", self.wild),
+            ("card", self.card[0]),
+            ("card", self.card[1]),
+            ("card", self.card[2]),
+            ("score_scale", self.score_scale),
+            ("score_growth", self.score_growth),
+            ("nobel", self.nobel),
+            ("nobel_cap", self.nobel_cap),
+            ("reserve", self.reserve),
+            ("reach", self.reach),
+            ("threat", self.threat),
+            ("denied", self.denied),
+            ("blocked", self.blocked),
+            ("opponents", self.opponents),
+        ];
+        for (name, weight) in named {
+            if !weight.is_finite() || weight < 0.0 {
+                bail!("{name} is {weight}, it needs to be a number of at least 0");
+            }
+        }
+        if self.score_growth < 1.0 {
+            bail!(
+                "score_growth is {}, it needs to be at least 1",
+                self.score_growth
+            );
+        }
+        Ok(())
+    }
+
+    /// How far `me` is ahead of the best of the others.
+    pub fn evaluate(&self, state: &State, me: usize) -> i32 {
+        let best_other = (0..state.players.len())
+            .filter(|&i| i != me)
+            .map(|i| self.player_value(state, i))
+            .fold(0.0, f64::max);
+        // Casts saturate, like the sums did when these were integers.
+        (self.player_value(state, me) + self.denial(state, me) - self.opponents * best_other) as i32
+    }
+
+    /// What `me` keeps from the others, less the winning cards it leaves
+    /// them, see [`Weights::threat`].
+    ///
+    /// ```
+    /// use alpha_beta_agent::Weights;
+    /// use game_def::{GameConfig, ResourceMap, State, Tier};
+    ///
+    /// let mut state = State::setup(&["a", "b"], GameConfig::default(), &mut rand::thread_rng());
+    /// let weights = Weights::default();
+    /// assert_eq!(weights.denial(&state, 0), 0.0);
+    /// // b can buy a card, which a holds reserved.
+    /// let card = state.decks[Tier::One].pop().unwrap();
+    /// state.players[1].mortal.gems = card.cost().clone();
+    /// let points = f64::from(card.score()) + 1.0;
+    /// state.players[0].reserved.push(card);
+    /// state.players[0].reserved_blind.push(false);
+    /// assert_eq!(weights.denial(&state, 0), weights.denied * points);
+    /// ```
+    pub fn denial(&self, state: &State, me: usize) -> f64 {
+        let mut value = 0.0;
+        let mut blocked: Option<u8> = None;
+        // No card gives more than 5 points, and nothing is blocked while
+        // the bank has every color.
+        let run_out = state.coins.gems.iter().any(|(_, &n)| n == 0);
+        for (i, other) in state.players.iter().enumerate() {
+            if i == me {
+                continue;
+            }
+            let winning = state.config.target_score.saturating_sub(other.score);
+            let threatens = |card: &Card| card.score() >= winning;
+            if winning <= 5 || run_out {
+                for card in visible(state) {
+                    let (missing, patched) = other.missing_for(card.cost());
+                    let short = missing.sum() as usize - patched;
+                    if short == 0 && threatens(card) {
+                        value -= self.threat;
+                    } else if (1..=3).contains(&short)
+                        && missing
+                            .iter()
+                            .all(|(color, &n)| n == 0 || state.coins.gems[color] == 0)
+                    {
+                        blocked = blocked.max(Some(card.score()));
+                    }
+                }
+            }
+            if winning <= 5 {
+                for card in &other.reserved {
+                    if threatens(card) && other.can_purchase(card.cost()) {
+                        value -= self.threat;
+                    }
+                }
+            }
+            for card in &state.players[me].reserved {
+                if other.can_purchase(card.cost()) {
+                    value += self.denied * (f64::from(card.score()) + 1.0);
+                }
+            }
+        }
+        value + blocked.map_or(0.0, |points| self.blocked * (f64::from(points) + 1.0))
+    }
+
+    fn player_value(&self, state: &State, i: usize) -> f64 {
+        let player = &state.players[i];
+        let reach = visible(state)
+            .chain(&player.reserved)
+            .filter(|card| player.can_purchase(card.cost()))
+            .map(|card| card.score())
+            .max()
+            .map_or(0.0, |points| f64::from(points) + 1.0);
+        let nobels = state
+            .nobels
+            .iter()
+            .map(|n| self.nobel_closeness(player, n))
+            .sum::<f64>()
+            .min(self.nobel_cap);
+        // Cards bought in states saved without their ids count as the cheapest.
+        let unknown = (player.immortal.sum() as usize).saturating_sub(player.purchased.len());
+        let cards = player
+            .purchased
+            .iter()
+            .map(|&id| state.config.tier_of(id).map_or(0, usize::from))
+            .map(|tier| self.card[tier.min(2)])
+            .sum::<f64>()
+            + unknown as f64 * self.card[0];
+        player.mortal.gems.sum() as f64 * self.coin
+            + player.mortal.gold as f64 * self.wild
+            + cards
+            + self.score_scale * self.score_growth.powi(player.score.into())
+            + player.reserved.len() as f64 * self.reserve
+            + reach * self.reach
+            + nobels
+    }
+
+    /// How close `player` is to `nobel`, from [`Weights::nobel`] with all
+    /// the cards it needs down to nothing, by the cards still missing.
+    ///
+    /// ```
+    /// use alpha_beta_agent::Weights;
+    /// use game_def::{Objective, Player, ResourceMap};
+    ///
+    /// let nobel = Objective {
+    ///     cost: ResourceMap::from_code("3r+3g"),
+    ///     score: 3,
+    ///     min_score: None,
+    ///     ends_game: false,
+    /// };
+    /// let weights = Weights::default();
+    /// // Six cards away, with none yet.
+    /// let mut player = Player::new("a");
+    /// assert_eq!(weights.nobel_closeness(&player, &nobel) as i32, 42);
+    /// player.immortal = ResourceMap::from_code("2r+2g");
+    /// assert_eq!(weights.nobel_closeness(&player, &nobel), 100.0);
+    /// // Cards beyond what the nobel needs don't count.
+    /// player.immortal = ResourceMap::from_code("5r+2g+1u");
+    /// assert_eq!(weights.nobel_closeness(&player, &nobel), 150.0);
+    /// player.immortal = ResourceMap::from_code("3r+3g");
+    /// assert_eq!(weights.nobel_closeness(&player, &nobel), 300.0);
+    /// ```
+    pub fn nobel_closeness(&self, player: &Player, nobel: &Objective) -> f64 {
+        let away = player.immortal.deficit(&nobel.cost).sum();
+        self.nobel / (f64::from(away) + 1.0)
+    }
+}
+
+/// The cards face up on the table.
+fn visible(state: &State) -> impl Iterator<Item = &Card> {
+    state
+        .card_iter()
+        .map(|(deck, card)| &state.decks[deck][card])
+}
@@ -0,0 +1,252 @@
+//! The alpha-beta-agent program, started like a runner starts it.
+
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+use alpha_beta_agent::AlphaBeta;
+use game_def::{Action, GameConfig, State};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Runs the program with `args` on `input` until its input ends.
+fn run(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_alpha-beta-agent"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn flags_configure_the_agent_and_bad_ones_fail_first() {
+    let state = State::setup(
+        &["a", "b"],
+        GameConfig::default(),
+        &mut StdRng::seed_from_u64(3),
+    );
+    let line = serde_json::to_string(&state.observation_for(state.turn)).unwrap() + "\n";
+    let dir = std::env::temp_dir();
+    let log = dir.join(format!("alpha-beta-agent-{}.log", std::process::id()));
+    let weights = dir.join(format!("alpha-beta-agent-{}.json", std::process::id()));
+    std::fs::write(&weights, r#"{"coin": 2.5, "card": [80, 100, 120]}"#).unwrap();
+    let args = [
+        "--depth",
+        "1",
+        "--time-ms",
+        "500",
+        "--seed",
+        "9",
+        "--model",
+        "maxn",
+        "--log",
+        log.to_str().unwrap(),
+        "--weights",
+        weights.to_str().unwrap(),
+        "--no-pvs",
+        "--no-aspiration",
+    ];
+    let output = run(&args, &line);
+    assert!(output.status.success(), "{output:?}");
+    let action: Action = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(
+        state.legal_actions().any(|legal| legal == action),
+        "{action}"
+    );
+    let logged = std::fs::read_to_string(&log).unwrap();
+    std::fs::remove_file(&log).unwrap();
+    assert!(logged.starts_with("alphabeta: depth 1, "), "{logged}");
+    // The weights it plays with come first, on stderr whatever the log.
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr
+            .starts_with(r#"alphabeta: weights {"coin":2.5,"wild":4.0,"card":[80.0,100.0,120.0],"#),
+        "{stderr}"
+    );
+    assert_eq!(stderr.lines().count(), 1, "{stderr}");
+
+    // Runners add these for the protocol.
+    let output = run(&["--depth", "1", "--protocol", "states"], &line);
+    assert!(output.status.success(), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("\nalphabeta: depth 1, "), "{stderr}");
+
+    // Fails without reading a thing.
+    let output = run(&["--dpth", "5"], "");
+    assert!(!output.status.success());
+    let error = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        error.contains("Unknown argument --dpth, usage: "),
+        "{error}"
+    );
+    let output = run(&["--model", "minimax"], "");
+    let error = String::from_utf8(output.stderr).unwrap();
+    assert!(error.contains("Unknown model minimax"), "{error}");
+    std::fs::write(&weights, r#"{"coin": -1}"#).unwrap();
+    let output = run(&["--weights", weights.to_str().unwrap()], "");
+    std::fs::remove_file(&weights).unwrap();
+    let error = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        error.contains("coin is -1, it needs to be a number of at least 0"),
+        "{error}"
+    );
+}
+
+#[test]
+fn stderr_shows_the_principal_variation_of_the_move_played() {
+    let mut state = State::setup(
+        &["a", "b"],
+        GameConfig::default(),
+        &mut StdRng::seed_from_u64(7),
+    );
+    for _ in 0..4 {
+        let action = state.legal_actions().next().unwrap();
+        state.run(action).unwrap();
+    }
+    let line = serde_json::to_string(&state.observation_for(state.turn)).unwrap() + "\n";
+    let output = run(&["--depth", "2", "--verbose"], &line);
+    assert!(output.status.success(), "{output:?}");
+    let action: Action = serde_json::from_slice(&output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let mut lines = stderr.lines().skip(1);
+    let thought = lines.next().unwrap();
+    assert!(thought.starts_with("alphabeta: depth 2, "), "{stderr}");
+    assert!(
+        thought.contains(&format!(", playing {action}, ")),
+        "{stderr}"
+    );
+    let (_, pv) = thought.split_once(", pv ").unwrap();
+    let pv: Vec<_> = pv.split(" / ").collect();
+    assert_eq!(pv[0], action.to_string());
+    // And the reply to it, at least.
+    assert!(pv.len() >= 2, "{stderr}");
+    let best = lines.next().unwrap();
+    assert!(
+        best.starts_with(&format!("alphabeta: best {action} ")),
+        "{stderr}"
+    );
+    assert_eq!(best.matches(", ").count(), 2, "{stderr}");
+}
+
+#[test]
+fn eval_scores_by_a_network_and_bad_ones_fail_first() {
+    let state = State::setup(
+        &["a", "b"],
+        GameConfig::default(),
+        &mut StdRng::seed_from_u64(3),
+    );
+    let line = serde_json::to_string(&state.observation_for(state.turn)).unwrap() + "\n";
+    let model = concat!(env!("CARGO_MANIFEST_DIR"), "/models/linear.json");
+    let output = run(&["--depth", "1", "--eval", model], &line);
+    assert!(output.status.success(), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("alphabeta: network 163-1\n"), "{stderr}");
+
+    let network =
+        std::env::temp_dir().join(format!("alpha-beta-agent-{}.eval", std::process::id()));
+    let text = std::fs::read_to_string(model).unwrap();
+    std::fs::write(&network, text.replace(r#""version": 1"#, r#""version": 2"#)).unwrap();
+    let output = run(&["--eval", network.to_str().unwrap()], "");
+    std::fs::remove_file(&network).unwrap();
+    assert!(!output.status.success());
+    let error = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        error.contains("version 2 of the format, this build reads version 1"),
+        "{error}"
+    );
+}
+
+#[test]
+fn analyze_reports_on_a_saved_position() {
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../game-def/fixtures/positions/midgame.json"
+    );
+    let state: State = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    let output = run(&["--depth", "2", "--analyze", path, "--top", "3"], "");
+    assert!(output.status.success(), "{output:?}");
+    let report = String::from_utf8(output.stdout).unwrap();
+    let line = |label: &str| {
+        let line = report.lines().find(|l| l.starts_with(label));
+        line.unwrap_or_else(|| panic!("{report}"))[label.len()..].to_owned()
+    };
+    assert_eq!(line("Position: "), "round 13, alice to move");
+    let best: Action = line("Best move: ").parse().unwrap();
+    assert!(state.legal_actions().any(|legal| legal == best), "{report}");
+    line("Score: ").parse::<i32>().unwrap();
+    let pv = line("Principal variation: ");
+    let pv: Vec<Action> = pv.split(" / ").map(|ac| ac.parse().unwrap()).collect();
+    assert_eq!(pv[0], best, "{report}");
+    assert!(pv.len() >= 2, "{report}");
+    let top: Vec<_> = report.lines().filter(|l| l.starts_with("  ")).collect();
+    assert_eq!(top.len(), 3, "{report}");
+    assert!(top[0].starts_with(&format!("  1. {best} ")), "{report}");
+    assert!(line("Searched: ").starts_with("depth 2, "), "{report}");
+
+    // The observation the player to move is sent finds the same.
+    let observation = std::env::temp_dir().join(format!(
+        "alpha-beta-agent-{}.observation.json",
+        std::process::id()
+    ));
+    std::fs::write(&observation, state.observation_for(state.turn).json()).unwrap();
+    let output = run(
+        &["--depth", "2", "--analyze", observation.to_str().unwrap()],
+        "",
+    );
+    std::fs::remove_file(&observation).unwrap();
+    let again = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(again.lines().nth(1), report.lines().nth(1), "{again}");
+
+    let output = run(&["--analyze", "/nonexistent/position.json"], "");
+    assert!(!output.status.success());
+    let error = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        error.contains("Failed to read /nonexistent/position.json"),
+        "{error}"
+    );
+}
+
+#[test]
+fn pondering_plays_the_moves_it_plays_without() {
+    let state = State::setup(
+        &["a", "b"],
+        GameConfig::default(),
+        &mut StdRng::seed_from_u64(5),
+    );
+    let first = state.observation_for(state.turn);
+    let agent = AlphaBeta {
+        depth: 2,
+        ..AlphaBeta::default()
+    };
+    let next = agent
+        .next_position(&first, &agent.think(first.clone()))
+        .unwrap();
+    // The expected position, then one it didn't expect.
+    let input: String = [&first, &next, &first]
+        .map(|o| serde_json::to_string(o).unwrap() + "\n")
+        .concat();
+    let pondering = run(&["--depth", "2", "--ponder"], &input);
+    let plain = run(&["--depth", "2"], &input);
+    assert!(pondering.status.success(), "{pondering:?}");
+    assert_eq!(pondering.stdout, plain.stdout);
+    assert_eq!(
+        String::from_utf8(pondering.stdout).unwrap().lines().count(),
+        3
+    );
+    let stderr = String::from_utf8(pondering.stderr).unwrap();
+    let ponders: Vec<_> = stderr.lines().filter(|l| l.contains("ponder")).collect();
+    assert_eq!(
+        ponders,
+        ["alphabeta: ponder hit", "alphabeta: ponder miss"],
+        "{stderr}"
+    );
+}
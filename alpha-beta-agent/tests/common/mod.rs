@@ -0,0 +1,29 @@
+//! Positions more than one of the tests search.
+
+use game_def::{GameConfig, ResourceKind, ResourceMap, State, Tier};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// A game with `seed` where the player not to move has 11 points and can
+/// buy the 4 point card of 7 white, face up first in the third deck, next.
+/// That ends the game unless the player to move buys or reserves it first.
+pub fn one_purchase_from_winning(seed: u64) -> State {
+    let mut state = State::setup(
+        &["a", "b"],
+        GameConfig::default(),
+        &mut StdRng::seed_from_u64(seed),
+    );
+    state.nobels.clear();
+    let other = 1 - state.turn;
+    let deck = &mut state.decks[Tier::Three];
+    let k = deck
+        .iter()
+        .position(|card| *card.cost() == ResourceMap::from_code("7w"))
+        .unwrap();
+    deck.swap(0, k);
+    let player = &mut state.players[other];
+    player.score = 11;
+    player.immortal = ResourceMap::from_code("4w");
+    player.mortal.gems = ResourceMap::from_code("3w");
+    state.coins.gems[ResourceKind::White] -= 3;
+    state
+}
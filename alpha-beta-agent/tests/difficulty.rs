@@ -0,0 +1,188 @@
+//! Difficulty levels: the moves they pick, the same moves for the same
+//! seeds, and seeded self-play telling the levels apart.
+
+use std::time::Duration;
+
+use alpha_beta_agent::{AlphaBeta, Difficulty, MAX_BLUNDER};
+use game_def::{play_game, Agent, GameConfig, GameResult, Handled, ResourceMap, State};
+use rand::{rngs::StdRng, SeedableRng};
+
+mod common;
+
+fn setup(seed: u64) -> State {
+    State::setup(
+        &["a", "b"],
+        GameConfig::default(),
+        &mut StdRng::seed_from_u64(seed),
+    )
+}
+
+/// The level, searching to a fixed depth instead of for a time, so the
+/// moves don't depend on how fast the machine is. Hard goes 2 rounds deep,
+/// where it would go as deep as its 2 seconds take it.
+fn level(difficulty: Difficulty, seed: u64) -> AlphaBeta {
+    let mut agent = AlphaBeta {
+        seed,
+        table_memory: 1 << 20,
+        ..AlphaBeta::default()
+    };
+    difficulty.apply(&mut agent);
+    if difficulty == Difficulty::Hard {
+        agent.depth = 2;
+    }
+    agent.time_limit = Duration::MAX;
+    agent
+}
+
+/// Games the `stronger` level wins of `games` against the `weaker`, in
+/// alternating seats.
+fn wins(stronger: Difficulty, weaker: Difficulty, games: u64) -> u64 {
+    let mut wins = 0;
+    for game in 0..games {
+        let seat = (game % 2) as usize;
+        let mut agents: Vec<Box<dyn Agent>> = vec![
+            Box::new(Handled(level(weaker, game))),
+            Box::new(Handled(level(stronger, game + 100))),
+        ];
+        agents.swap(0, 1 - seat);
+        let result = play_game(setup(game), &mut agents, 100);
+        println!("Game {game}, {stronger} in seat {seat} against {weaker}: {result:?}");
+        if result == GameResult::Won(seat) {
+            wins += 1;
+        }
+    }
+    wins
+}
+
+#[test]
+fn levels_set_their_knobs_and_parse_by_name() {
+    for difficulty in [
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Custom,
+    ] {
+        assert_eq!(
+            difficulty.to_string().parse::<Difficulty>().unwrap(),
+            difficulty
+        );
+    }
+    let error = "expert".parse::<Difficulty>().unwrap_err().to_string();
+    assert_eq!(
+        error,
+        "Unknown difficulty expert, try easy, medium, hard or custom"
+    );
+
+    let mut agent = AlphaBeta::default();
+    Difficulty::Hard.apply(&mut agent);
+    let hard = AlphaBeta::default();
+    assert_eq!(
+        (
+            agent.depth,
+            agent.time_limit,
+            agent.temperature,
+            agent.blunder
+        ),
+        (hard.depth, hard.time_limit, 0.0, 0.0)
+    );
+    for difficulty in [Difficulty::Easy, Difficulty::Medium] {
+        let mut agent = AlphaBeta::default();
+        difficulty.apply(&mut agent);
+        assert!(agent.depth < hard.depth && agent.time_limit < hard.time_limit);
+        assert!(agent.temperature > 0.0);
+        assert!(agent.blunder > 0.0 && agent.blunder <= MAX_BLUNDER);
+    }
+}
+
+#[test]
+fn weaker_levels_play_other_moves_than_the_best_reproducibly() {
+    let mut worse = 0;
+    let mut positions = 0;
+    for seed in 0..20 {
+        let state = setup(seed);
+        let observation = state.observation_for(state.turn);
+        let best = level(Difficulty::Easy, seed);
+        let best = AlphaBeta {
+            temperature: 0.0,
+            blunder: 0.0,
+            ..best
+        }
+        .think(observation.clone());
+        assert_eq!(best.rank, 0);
+
+        let easy = level(Difficulty::Easy, seed);
+        let thought = easy.think(observation.clone());
+        // The same moves for the same seed.
+        assert_eq!(easy.think(observation.clone()).action, thought.action);
+        assert_eq!(thought.root[thought.rank].action, thought.action);
+        assert_eq!(thought.root.len(), best.root.len());
+        if thought.rank > 0 {
+            assert!(
+                thought.to_string().contains(&format!(
+                    " (move {} of {})",
+                    thought.rank + 1,
+                    thought.root.len()
+                )),
+                "{thought}"
+            );
+        } else {
+            assert_eq!(thought.action, best.action);
+        }
+        // Moves tied with the best are as good, only a lower score counts.
+        if thought.score < best.score {
+            worse += 1;
+        }
+        positions += 1;
+    }
+    assert!(worse > 0 && worse < positions, "{worse} of {positions}");
+}
+
+#[test]
+fn blunders_never_lose_on_the_spot() {
+    // The two moves that don't lose are buying the card the other player
+    // wins by and reserving it.
+    let mut state = common::one_purchase_from_winning(7);
+    let me = state.turn;
+    state.players[me].immortal = ResourceMap::from_code("7w");
+    let observation = state.observation_for(state.turn);
+    let mut blunders = 0;
+    for seed in 0..20 {
+        let blundering = AlphaBeta {
+            seed,
+            blunder: MAX_BLUNDER,
+            depth: 2,
+            time_limit: Duration::MAX,
+            ..AlphaBeta::default()
+        };
+        let thought = blundering.think(observation.clone());
+        // Scored as lost, whatever the weights.
+        let lost = |score| score <= -1_000_000_000;
+        let safe = thought.root.iter().filter(|m| !lost(m.score)).count();
+        assert!(safe > 1 && safe < thought.root.len(), "{safe}");
+        assert!(!lost(thought.score), "{thought}");
+        if thought.rank > 0 {
+            blunders += 1;
+        }
+    }
+    assert!(blunders > 0);
+}
+
+/// Two dozen games, minutes without optimizations: run it with `cargo test
+/// --release -p alpha-beta-agent --test difficulty -- --ignored` after
+/// changing a level.
+#[test]
+#[ignore = "plays 24 games, run with --release -- --ignored"]
+fn hard_beats_medium_beats_easy() {
+    const GAMES: u64 = 12;
+    let medium = wins(Difficulty::Medium, Difficulty::Easy, GAMES);
+    let hard = wins(Difficulty::Hard, Difficulty::Medium, GAMES);
+    println!("medium won {medium}, hard won {hard} of {GAMES}");
+    assert!(
+        medium >= GAMES * 3 / 4,
+        "medium won {medium} of {GAMES} against easy"
+    );
+    assert!(
+        hard >= GAMES * 3 / 4,
+        "hard won {hard} of {GAMES} against medium"
+    );
+}
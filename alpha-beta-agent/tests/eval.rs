@@ -0,0 +1,48 @@
+//! The search scoring positions by the example network of `models/`.
+
+use std::{path::Path, sync::Arc, time::Duration};
+
+use alpha_beta_agent::{AlphaBeta, Network};
+use game_def::{play_game, Action, Agent, GameConfig, GameResult, Handled, State};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+const GAMES: usize = 6;
+
+#[test]
+fn example_network_beats_a_random_player() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("models/linear.json");
+    let network = Arc::new(Network::load(&path).unwrap());
+    let mut wins = 0;
+    for game in 0..GAMES {
+        let seat = game % 2;
+        let searching = AlphaBeta {
+            depth: 2,
+            time_limit: Duration::from_millis(200),
+            table_memory: 1 << 20,
+            network: Some(network.clone()),
+            ..AlphaBeta::default()
+        };
+        // Any move but resigning, like random-agent.
+        let mut rng = StdRng::seed_from_u64(game as u64);
+        let random = move |state: &State| {
+            let moves: Vec<_> = state
+                .legal_actions()
+                .filter(|action| *action != Action::Resign)
+                .collect();
+            moves.choose(&mut rng).cloned().unwrap_or(Action::Skip)
+        };
+        let mut agents: Vec<Box<dyn Agent>> = vec![Box::new(random), Box::new(Handled(searching))];
+        agents.swap(0, 1 - seat);
+        let state = State::setup(
+            &["a", "b"],
+            GameConfig::default(),
+            &mut StdRng::seed_from_u64(game as u64),
+        );
+        let result = play_game(state, &mut agents, 100);
+        println!("Game {game}, searching in seat {seat}: {result:?}");
+        if result == GameResult::Won(seat) {
+            wins += 1;
+        }
+    }
+    assert!(wins > GAMES / 2 + 1, "won {wins} of {GAMES}");
+}
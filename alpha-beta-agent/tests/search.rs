@@ -0,0 +1,590 @@
+//! The search on fixed positions, run with `--nocapture` to see the numbers.
+
+use std::time::{Duration, Instant};
+
+use alpha_beta_agent::{AlphaBeta, Thought, Weights, DEFAULT_TABLE_MEMORY};
+use game_def::{
+    Action, Card, GameConfig, GameResult, Observation, ResourceKind, ResourceMap, State, Tier,
+};
+use rand::{rngs::StdRng, SeedableRng};
+
+mod common;
+
+/// Deep enough for coins to be taken in every order.
+const DEPTH: i32 = 5;
+
+/// The position of a game with `seed` after playing the first legal move
+/// `moves` times.
+fn position(seed: u64, moves: usize) -> State {
+    let mut state = State::setup(
+        &["a", "b"],
+        GameConfig::default(),
+        &mut StdRng::seed_from_u64(seed),
+    );
+    for _ in 0..moves {
+        let action = state.legal_actions().next().unwrap();
+        state.run(action).unwrap();
+    }
+    state
+}
+
+fn to_move(state: &State) -> Observation {
+    state.observation_for(state.turn)
+}
+
+#[test]
+fn transposition_table_saves_nodes_at_a_fixed_depth() {
+    let observation = to_move(&position(7, 4));
+    let think = |table_memory| {
+        let agent = AlphaBeta {
+            depth: DEPTH,
+            time_limit: Duration::from_secs(600),
+            max_think_time: Duration::from_secs(600),
+            table_memory,
+            ..AlphaBeta::default()
+        };
+        agent.think(observation.clone())
+    };
+    let without = think(0);
+    let with = think(DEFAULT_TABLE_MEMORY);
+    println!(
+        "Depth {DEPTH}: {} nodes without the table, {} with it, {:.0}% fewer",
+        without.nodes,
+        with.nodes,
+        100.0 - 100.0 * with.nodes as f64 / without.nodes as f64
+    );
+    assert_eq!((without.depth, with.depth), (DEPTH, DEPTH));
+    // Cut offs from the table don't change what the position is worth.
+    assert_eq!(with.score, without.score);
+    assert!(with.nodes < without.nodes, "{with:?} {without:?}");
+}
+
+#[test]
+fn move_ordering_keeps_the_choice_at_a_fixed_depth() {
+    const DEPTH: i32 = 3;
+    let (mut without, mut with) = (0, 0);
+    for (seed, moves) in [
+        (7, 0),
+        (7, 4),
+        (8, 2),
+        (9, 6),
+        (10, 10),
+        (7, 20),
+        (8, 30),
+        (9, 40),
+        (10, 25),
+    ] {
+        let observation = to_move(&position(seed, moves));
+        let think = |ordering| {
+            let agent = AlphaBeta {
+                depth: DEPTH,
+                time_limit: Duration::from_secs(600),
+                ordering,
+                ..AlphaBeta::default()
+            };
+            agent.think(observation.clone())
+        };
+        let plain = think(false);
+        let ordered = think(true);
+        assert_eq!(
+            (&ordered.action, ordered.score),
+            (&plain.action, plain.score),
+            "seed {seed} after {moves} moves"
+        );
+        without += plain.nodes;
+        with += ordered.nodes;
+    }
+    println!(
+        "Depth {DEPTH}: {without} nodes without ordering, {with} with it, {:.0}% fewer",
+        100.0 - 100.0 * with as f64 / without as f64
+    );
+    assert!(with < without);
+}
+
+#[test]
+fn pvs_and_aspiration_keep_the_choice_at_a_fixed_depth() {
+    const DEPTH: i32 = 4;
+    // Nodes with neither, with null windows, with aspiration windows and
+    // with both.
+    let mut nodes = [0; 4];
+    // Positions each searched in a few seconds, well within the hard time
+    // limit, in a debug build.
+    for (seed, moves) in [
+        (7, 0),
+        (7, 4),
+        (8, 2),
+        (9, 6),
+        (7, 20),
+        (8, 30),
+        (9, 40),
+        (11, 3),
+        (13, 15),
+        (15, 35),
+        (17, 50),
+        (18, 1),
+    ] {
+        let observation = to_move(&position(seed, moves));
+        let think = |pvs, aspiration| {
+            let agent = AlphaBeta {
+                depth: DEPTH,
+                time_limit: Duration::from_secs(600),
+                pvs,
+                aspiration,
+                // Only the search that deepens.
+                endgame: 0,
+                ..AlphaBeta::default()
+            };
+            agent.think(observation.clone())
+        };
+        let plain = think(false, false);
+        nodes[0] += plain.nodes;
+        for (k, (pvs, aspiration)) in [(true, false), (false, true), (true, true)]
+            .into_iter()
+            .enumerate()
+        {
+            let thought = think(pvs, aspiration);
+            assert_eq!(
+                (&thought.action, thought.score, thought.depth),
+                (&plain.action, plain.score, plain.depth),
+                "seed {seed} after {moves} moves, pvs {pvs}, aspiration {aspiration}"
+            );
+            nodes[k + 1] += thought.nodes;
+        }
+    }
+    let fewer = |n: u64| 100.0 - 100.0 * n as f64 / nodes[0] as f64;
+    println!(
+        "Depth {DEPTH}: {} nodes plain, {} with pvs, {:.0}% fewer, {} with aspiration, {:.0}% fewer, {} with both, {:.0}% fewer",
+        nodes[0],
+        nodes[1],
+        fewer(nodes[1]),
+        nodes[2],
+        fewer(nodes[2]),
+        nodes[3],
+        fewer(nodes[3])
+    );
+    assert!(nodes[1..].iter().all(|&n| n < nodes[0]), "{nodes:?}");
+}
+
+/// As the agent scores a won game.
+const WON: i32 = 1_000_000_000;
+
+/// What `state` is worth to `me`, searched `depth` rounds deep the way the
+/// agent searched before it applied and undid moves on one state: a copy of
+/// the state for every move, and no table, ordering or null windows.
+fn cloned_search(
+    state: &State,
+    me: usize,
+    depth: i32,
+    mut alpha: i32,
+    mut beta: i32,
+    nodes: &mut u64,
+) -> i32 {
+    *nodes += 1;
+    match state.result() {
+        GameResult::Ongoing => (),
+        GameResult::Won(winner) if winner == me => return WON,
+        GameResult::Won(_) => return -WON,
+        GameResult::Draw(winners) if winners.contains(&me) => return 0,
+        GameResult::Draw(_) => return -WON,
+    }
+    if state.turn == state.first_player && depth <= 0 {
+        return Weights::default().evaluate(state, me);
+    }
+    let maximizing = state.turn == me;
+    let mut best = if maximizing { -WON - 1 } else { WON + 1 };
+    for action in state.legal_actions() {
+        if action == Action::Resign {
+            continue;
+        }
+        let mut child = state.clone();
+        child.apply(&action).unwrap();
+        let depth = if child.turn == state.turn {
+            depth
+        } else {
+            depth - 1
+        };
+        let score = cloned_search(&child, me, depth, alpha, beta, nodes);
+        if maximizing {
+            best = best.max(score);
+            alpha = alpha.max(score);
+        } else {
+            best = best.min(score);
+            beta = beta.min(score);
+        }
+        if alpha >= beta {
+            break;
+        }
+    }
+    best
+}
+
+#[test]
+fn apply_and_undo_keep_the_choice_of_cloned_states() {
+    const DEPTH: i32 = 3;
+    let (mut cloned, mut applied) = ((0, Duration::ZERO), (0, Duration::ZERO));
+    for (seed, moves) in [(7, 0), (8, 2), (9, 6), (7, 20), (8, 30), (9, 40)] {
+        let observation = to_move(&position(seed, moves));
+        let agent = AlphaBeta {
+            depth: DEPTH,
+            time_limit: Duration::from_secs(600),
+            max_think_time: Duration::from_secs(600),
+            // Only what differs from the search on copies.
+            table_memory: 0,
+            ordering: false,
+            pvs: false,
+            aspiration: false,
+            endgame: 0,
+            ..AlphaBeta::default()
+        };
+        let thought = agent.think(observation.clone());
+        applied.0 += thought.nodes;
+        applied.1 += thought.elapsed;
+        // The cards it can't see dealt as the agent deals them.
+        let state = observation.to_determinized_state(&mut StdRng::seed_from_u64(agent.seed));
+        let me = state.turn;
+        let started = Instant::now();
+        // The first of the best moves, in the order they are listed.
+        let mut best: Option<(i32, Action)> = None;
+        for action in state.legal_actions() {
+            if action == Action::Resign {
+                continue;
+            }
+            let mut child = state.clone();
+            child.apply(&action).unwrap();
+            let depth = if child.turn == me { DEPTH } else { DEPTH - 1 };
+            let alpha = best.as_ref().map_or(-WON - 1, |(score, _)| *score);
+            let score = cloned_search(&child, me, depth, alpha, WON + 1, &mut cloned.0);
+            if best.as_ref().is_none_or(|(best, _)| score > *best) {
+                best = Some((score, action));
+            }
+        }
+        cloned.1 += started.elapsed();
+        let (score, action) = best.unwrap();
+        assert_eq!(
+            (&thought.action, thought.score, thought.depth),
+            (&action, score, DEPTH),
+            "seed {seed} after {moves} moves"
+        );
+    }
+    let rate = |(nodes, time): (u64, Duration)| nodes as f64 / time.as_secs_f64();
+    println!(
+        "Depth {DEPTH}: {} nodes a second on copies of the state, {} applying and undoing moves",
+        rate(cloned) as u64,
+        rate(applied) as u64
+    );
+}
+
+#[test]
+fn mirrored_seats_make_the_same_decision() {
+    let agent = AlphaBeta {
+        depth: 2,
+        time_limit: Duration::from_secs(600),
+        ..AlphaBeta::default()
+    };
+    for (seed, moves) in [(7, 0), (7, 5), (8, 12), (9, 21)] {
+        let state = position(seed, moves);
+        // The same position with the players in each other's seats.
+        let mut mirrored = state.clone();
+        mirrored.players.swap(0, 1);
+        mirrored.turn = 1 - state.turn;
+        mirrored.first_player = 1 - state.first_player;
+        let seated = agent.think(to_move(&state));
+        let swapped = agent.think(to_move(&mirrored));
+        assert_eq!(
+            (&swapped.action, swapped.score),
+            (&seated.action, seated.score),
+            "seed {seed} after {moves} moves"
+        );
+    }
+}
+
+#[test]
+fn parallel_search_plays_the_serial_move() {
+    const DEPTH: i32 = 3;
+    for (seed, moves) in [(7, 0), (8, 2), (9, 6), (7, 20)] {
+        let observation = to_move(&position(seed, moves));
+        let think = |threads| {
+            let agent = AlphaBeta {
+                depth: DEPTH,
+                time_limit: Duration::from_secs(600),
+                threads,
+                ..AlphaBeta::default()
+            };
+            agent.think(observation.clone())
+        };
+        let serial = think(1);
+        let parallel = think(4);
+        println!("Seed {seed} after {moves} moves: serial {serial}, parallel {parallel}");
+        assert_eq!(parallel.depth, DEPTH);
+        assert_eq!(
+            (&parallel.action, parallel.score),
+            (&serial.action, serial.score),
+            "seed {seed} after {moves} moves"
+        );
+    }
+}
+
+#[test]
+fn blocking_reserve_stops_a_winning_purchase() {
+    let state = common::one_purchase_from_winning(7);
+    let agent = AlphaBeta {
+        depth: 2,
+        time_limit: Duration::from_secs(600),
+        ..AlphaBeta::default()
+    };
+    let thought = agent.think(to_move(&state));
+    let block = Action::Reserve {
+        deck: Tier::Three,
+        card: 0,
+        id: None,
+    };
+    assert_eq!(thought.action, block, "{thought}");
+}
+
+/// Takes the first face down card of `tier` that `pick` picks out of the
+/// deck and into the reserve of the player to move.
+fn reserve(state: &mut State, tier: Tier, pick: impl Fn(&Card) -> bool) {
+    let visible = state.config.visible_cards;
+    let deck = &mut state.decks[tier];
+    let k = (visible..deck.len()).find(|&k| pick(&deck[k])).unwrap();
+    let card = deck.remove(k);
+    let player = &mut state.players[state.turn];
+    player.reserved.push(card);
+    player.reserved_blind.push(false);
+}
+
+#[test]
+fn endgame_search_proves_a_win_beyond_the_depth() {
+    // The player to move has 12 points and moves first, with three 1 point
+    // cards in reserve that its own cards pay for, and nothing else in
+    // reach: buying them wins in 6 plies, which depth 4 doesn't see to.
+    let mut state = position(7, 0);
+    state.nobels.clear();
+    let me = state.turn;
+    for color in ["4w", "4u", "4g"] {
+        let cost = ResourceMap::from_code(color);
+        reserve(&mut state, Tier::One, |card| *card.cost() == cost);
+    }
+    let player = &mut state.players[me];
+    player.score = 12;
+    player.immortal = ResourceMap::from_code("4w+4u+4g");
+    state.rehash();
+    let (plain, solved) = with_and_without_endgame(&state);
+    assert!(
+        matches!(solved.action, Action::PurchaseReserved { .. }),
+        "{solved}"
+    );
+    assert!(
+        plain.mate.is_none() && plain.score < 1_000_000_000,
+        "{plain}"
+    );
+    assert_eq!(solved.mate, Some(6), "{solved}");
+}
+
+#[test]
+fn endgame_search_proves_a_win_that_takes_coins_first() {
+    // 13 points, and a 2 point card in reserve that takes 2 coins of each
+    // of two colors more. The others can't take enough of them to stop two
+    // picks getting them, so it wins in 6 plies too.
+    let mut state = position(7, 0);
+    state.nobels.clear();
+    let me = state.turn;
+    let needs = |card: &Card| {
+        let colors = card.cost().iter().filter(|(_, n)| **n >= 2).count();
+        card.score() == 2 && colors >= 2
+    };
+    reserve(&mut state, Tier::Two, needs);
+    let cost = state.players[me].reserved[0].cost().clone();
+    let mut missing = ResourceMap::default();
+    for (color, _) in cost.iter().filter(|(_, n)| **n >= 2).take(2) {
+        missing[color] = 2;
+    }
+    let player = &mut state.players[me];
+    player.score = 13;
+    player.immortal = missing.deficit(&cost);
+    state.rehash();
+    let (plain, solved) = with_and_without_endgame(&state);
+    assert!(
+        plain.mate.is_none() && plain.score < 1_000_000_000,
+        "{plain}"
+    );
+    assert_eq!(solved.mate, Some(6), "{solved}");
+    assert!(
+        matches!(solved.action, Action::PickThree { .. }),
+        "{solved}"
+    );
+}
+
+/// What depth 4 plays in `state` without the endgame search, then with it.
+fn with_and_without_endgame(state: &State) -> (Thought, Thought) {
+    let think = |endgame| {
+        let agent = AlphaBeta {
+            depth: 4,
+            time_limit: Duration::from_secs(600),
+            endgame,
+            ..AlphaBeta::default()
+        };
+        agent.think(to_move(state))
+    };
+    let (plain, solved) = (think(0), think(3));
+    println!("Without the endgame search: {plain}\nWith it: {solved}");
+    (plain, solved)
+}
+
+#[test]
+fn determinizations_stop_counting_on_the_card_a_deck_deals() {
+    // Four cards of tier 3 are left face down, one of them worth 5 points
+    // that the cards of the player to move, at 10 points, pay for. Dealt it
+    // by a blind reserve, it wins next turn; but it is only one in four.
+    let mut state = position(7, 0);
+    state.nobels.clear();
+    let me = state.turn;
+    let visible = state.config.visible_cards;
+    let hidden = state.decks[Tier::Three].split_off(visible);
+    let lucky = hidden
+        .iter()
+        .find(|card| card.score() == 5)
+        .unwrap()
+        .clone();
+    let covered = |card: &Card| lucky.cost().deficit(card.cost()).sum() == 0;
+    let unlucky = hidden.iter().filter(|card| !covered(card)).take(3);
+    let left: Vec<_> = std::iter::once(&lucky).chain(unlucky).cloned().collect();
+    // The others were bought, so they can't be dealt.
+    state.players[1 - me].purchased = hidden
+        .iter()
+        .filter(|card| !left.contains(card))
+        .map(|card| card.id())
+        .collect();
+    state.decks[Tier::Three].extend(left);
+    let player = &mut state.players[me];
+    player.score = 10;
+    player.immortal = lucky.cost().clone();
+    state.rehash();
+    let observation = to_move(&state);
+    let deals_lucky = |seed| {
+        let dealt = observation.to_determinized_state(&mut StdRng::seed_from_u64(seed));
+        dealt.decks[Tier::Three][visible].id() == lucky.id()
+    };
+    let seed = (0..).find(|&seed| deals_lucky(seed)).unwrap();
+    let think = |determinizations| {
+        let agent = AlphaBeta {
+            depth: 3,
+            time_limit: Duration::from_secs(600),
+            seed,
+            determinizations,
+            ..AlphaBeta::default()
+        };
+        agent.think(observation.clone())
+    };
+    let (known, sampled) = (think(1), think(8));
+    println!("Taking the deck as dealt with seed {seed}: {known}\nSampling it: {sampled}");
+    let blind = Action::ReserveBlind { deck: Tier::Three };
+    assert_eq!(known.action, blind, "{known}");
+    assert_ne!(sampled.action, blind, "{sampled}");
+    assert_eq!(sampled.determinizations, 8);
+}
+
+/// Extreme but legal states, each searched with caps of 1 node up to a
+/// thousand: it still answers in time, with a move the rules take.
+#[test]
+fn capped_search_answers_a_legal_move_in_extreme_states() {
+    let mut stuck = position(3, 0);
+    let me = stuck.turn;
+    stuck.coins = Default::default();
+    stuck.players[me].mortal = Default::default();
+    for _ in 0..stuck.config.max_reserved {
+        let card = stuck.decks[Tier::Three].pop().unwrap();
+        stuck.players[me].reserved.push(card);
+        stuck.players[me].reserved_blind.push(false);
+    }
+
+    let mut full_hands = position(3, 0);
+    for player in 0..2 {
+        full_hands.players[player].mortal.gems = ResourceMap::from_code("2r+2u+2g+2w+2k");
+        for kind in [ResourceKind::Red, ResourceKind::Blue, ResourceKind::Green] {
+            full_hands.coins.gems[kind] -= 2;
+        }
+    }
+    for kind in [ResourceKind::White, ResourceKind::Black] {
+        full_hands.coins.gems[kind] = 0;
+    }
+
+    let mut empty_decks = position(3, 6);
+    for (_, deck) in empty_decks.decks.iter_mut() {
+        deck.clear();
+    }
+
+    let mut hoard = position(3, 0);
+    let card = hoard.decks[Tier::One][0].clone();
+    let player = &mut hoard.players[hoard.turn];
+    player.mortal.gems = ResourceMap::uniform(1000);
+    player.reserved = vec![card; 2000];
+    player.reserved_blind = vec![false; 2000];
+
+    for (name, state) in [
+        ("stuck", &stuck),
+        ("full hands", &full_hands),
+        ("empty decks", &empty_decks),
+        ("hoard", &hoard),
+    ] {
+        for max_nodes in [1, 10, 1000] {
+            let agent = AlphaBeta {
+                time_limit: Duration::from_secs(600),
+                max_nodes,
+                max_think_time: Duration::from_millis(200),
+                ..AlphaBeta::default()
+            };
+            let thought = agent.think(to_move(state));
+            println!("{name}, {max_nodes} nodes: {thought}");
+            assert!(
+                thought.elapsed < Duration::from_secs(5),
+                "{name}: {thought}"
+            );
+            assert!(
+                state.clone().run(thought.action.clone()).is_ok(),
+                "{name}: {thought}"
+            );
+        }
+    }
+    assert_eq!(stuck.legal_actions().next(), Some(Action::Skip));
+}
+
+/// A position where `stuck` can only skip: the bank is empty, nobody holds a
+/// coin and their reserves are full.
+fn only_skip(stuck: usize) -> State {
+    let mut state = position(3, 0);
+    state.coins = Default::default();
+    for _ in 0..state.config.max_reserved {
+        let card = state.decks[Tier::Three].pop().unwrap();
+        state.players[stuck].reserved.push(card);
+        state.players[stuck].reserved_blind.push(false);
+    }
+    state
+}
+
+#[test]
+fn forced_skips_are_searched_through() {
+    for stuck in 0..2 {
+        let state = only_skip(stuck);
+        let agent = AlphaBeta {
+            depth: 3,
+            time_limit: Duration::from_secs(600),
+            max_think_time: Duration::from_secs(600),
+            ..AlphaBeta::default()
+        };
+        let thought = agent.think(to_move(&state));
+        println!("Player {stuck} stuck: {thought}");
+        if stuck == state.turn {
+            // The only move is played without a search.
+            assert_eq!(thought.action, Action::Skip);
+            continue;
+        }
+        assert_eq!(thought.depth, 3, "{thought}");
+        // Scored by the position after the skip, not as a node without moves.
+        assert!(thought.score.abs() < 1_000_000, "{thought}");
+        assert!(
+            state.clone().run(thought.action.clone()).is_ok(),
+            "{thought}"
+        );
+    }
+}
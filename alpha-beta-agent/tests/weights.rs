@@ -0,0 +1,72 @@
+//! The terms of the evaluation that keep the others from what they want,
+//! each on a position with and without it.
+
+use alpha_beta_agent::Weights;
+use game_def::{Action, ResourceKind, ResourceMap, State, Tier};
+
+mod common;
+
+/// The evaluation with only `weights` changed from the defaults, less the
+/// one with the denial terms off.
+fn with(weights: Weights, state: &State) -> i32 {
+    let off = Weights {
+        threat: 0.0,
+        denied: 0.0,
+        blocked: 0.0,
+        ..weights
+    };
+    weights.evaluate(state, state.turn) - off.evaluate(state, state.turn)
+}
+
+#[test]
+fn leaving_a_winning_card_buyable_is_a_threat() {
+    let state = common::one_purchase_from_winning(7);
+    let weights = Weights {
+        threat: 700.0,
+        ..Weights::default()
+    };
+    assert_eq!(with(weights, &state), -700);
+
+    // With a point less to win with, it doesn't.
+    let mut state = state;
+    state.players[1 - state.turn].score = 10;
+    assert_eq!(with(weights, &state), 0);
+}
+
+#[test]
+fn reserving_the_card_the_other_wins_by_is_denied() {
+    let mut state = common::one_purchase_from_winning(7);
+    state
+        .run(Action::Reserve {
+            deck: Tier::Three,
+            card: 0,
+            id: None,
+        })
+        .unwrap();
+    // Back to the player who reserved it.
+    state.turn = 1 - state.turn;
+    let weights = Weights {
+        denied: 30.0,
+        ..Weights::default()
+    };
+    // 4 points and once more, with nothing left to threaten.
+    assert_eq!(with(weights, &state), 150);
+}
+
+#[test]
+fn taking_the_last_coins_of_a_color_blocks() {
+    let mut state = common::one_purchase_from_winning(7);
+    let other = 1 - state.turn;
+    state.players[other].score = 0;
+    state.players[other].mortal.gems = ResourceMap::new();
+    let weights = Weights {
+        blocked: 10.0,
+        ..Weights::default()
+    };
+    // Three white short of the 4 point card, while the bank has white.
+    state.coins.gems[ResourceKind::White] = 1;
+    assert_eq!(with(weights, &state), 0);
+    // Taken, it is worth 4 points and once more.
+    state.coins.gems[ResourceKind::White] = 0;
+    assert_eq!(with(weights, &state), 50);
+}
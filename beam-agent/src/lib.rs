@@ -0,0 +1,278 @@
+//! A beam search over the agent's own plans, the agent of the beam-agent
+//! program: it looks many turns ahead where alpha-beta can't, like saving
+//! for a card that only the cards it buys first make cheap enough.
+//!
+//! Each step plays every move but resigning in each line kept, the others
+//! answering as [`Opponents`] says, and keeps the `width` lines with the
+//! best sum of what [`value`] gives each position on the way, so of two
+//! lines getting as far the one getting there first wins. After `depth` of
+//! its own moves it plays the first move of the best line. Like the
+//! alpha-beta search, it searches one determinized state, the face down
+//! cards made up with a fixed seed.
+
+use std::{
+    cmp::Reverse,
+    collections::HashSet,
+    fmt::Display,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use game_def::{Action, AgentHandler, GameResult, Observation, ResourceMap, State};
+use rand::{rngs::StdRng, SeedableRng};
+
+pub const DEFAULT_WIDTH: usize = 12;
+pub const DEFAULT_DEPTH: usize = 8;
+/// The value of a won game, above anything [`value`] gives one going on.
+const WON: i64 = 1_000_000_000;
+
+/// Keeps the `width` best lines for `depth` of its own moves, the others
+/// playing as `opponents` says. `seed` fills in the cards it can't see.
+#[derive(Debug, Clone, Copy)]
+pub struct Beam {
+    pub width: usize,
+    pub depth: usize,
+    pub opponents: Opponents,
+    pub seed: u64,
+}
+
+impl Default for Beam {
+    fn default() -> Self {
+        Beam {
+            width: DEFAULT_WIDTH,
+            depth: DEFAULT_DEPTH,
+            opponents: Opponents::Greedy,
+            seed: 0,
+        }
+    }
+}
+
+/// What the search takes the others to play between its own moves: only
+/// one move each, so lines stay cheap to follow.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Opponents {
+    /// [`greedy_agent::best_action`].
+    #[default]
+    Greedy,
+    /// Their first legal move, quicker and blind to what they are after.
+    First,
+}
+
+impl FromStr for Opponents {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Opponents> {
+        match s {
+            "greedy" => Ok(Opponents::Greedy),
+            "first" => Ok(Opponents::First),
+            _ => anyhow::bail!("Unknown opponents {s}, try greedy or first"),
+        }
+    }
+}
+
+impl Opponents {
+    /// Plays the others' moves until it's `me` to move again or the game
+    /// is over.
+    fn answer(self, state: &mut State, me: usize) {
+        while state.turn != me && !state.is_finished() {
+            let action = match self {
+                Opponents::Greedy => greedy_agent::best_action(state),
+                Opponents::First => state.legal_actions().next().unwrap_or(Action::Skip),
+            };
+            state
+                .apply(&action)
+                .expect("legal_actions yielded an illegal action");
+        }
+    }
+}
+
+/// What [`Beam::think`] found.
+#[derive(Debug, Clone)]
+pub struct Thought {
+    pub action: Action,
+    /// The moves of the best line, `action` first, without those of the
+    /// others.
+    pub plan: Vec<Action>,
+    /// What [`value`] gives the end of `plan`.
+    pub value: i64,
+    /// The sum of what it gives each position of `plan`, which lines are
+    /// ranked by.
+    pub total: i64,
+    /// Lines valued, at every step.
+    pub lines: usize,
+    pub elapsed: Duration,
+}
+
+/// The line logged for each move, like `1204 lines in 35.1ms, value 2310,
+/// total 9650, playing buy 1.2, plan buy 1.2 / buy 2.0 / take r g u`.
+impl Display for Thought {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} lines in {:.1?}, value {}, total {}, playing {}",
+            self.lines, self.elapsed, self.value, self.total, self.action
+        )?;
+        if !self.plan.is_empty() {
+            let plan: Vec<_> = self.plan.iter().map(|ac| ac.to_string()).collect();
+            write!(f, ", plan {}", plan.join(" / "))?;
+        }
+        Ok(())
+    }
+}
+
+/// A position reached by `plan` and the others' answers.
+#[derive(Clone)]
+struct Line {
+    state: State,
+    plan: Vec<Action>,
+    value: i64,
+    total: i64,
+}
+
+impl Beam {
+    /// Searches for the best plan in `observation`.
+    pub fn think(&self, observation: Observation) -> Thought {
+        let started = Instant::now();
+        let state = observation.to_determinized_state(&mut StdRng::seed_from_u64(self.seed));
+        let me = state.turn;
+        let mut beam = vec![Line {
+            value: value(&state, me),
+            total: 0,
+            state,
+            plan: vec![],
+        }];
+        let mut lines = 0;
+        for _ in 0..self.depth {
+            let mut next = vec![];
+            // Lines reaching the same position, like by taking the same
+            // coins in another order, are only kept once.
+            let mut seen = HashSet::new();
+            for line in &beam {
+                if line.state.is_finished() {
+                    if seen.insert(line.state.zobrist()) {
+                        let mut line = line.clone();
+                        line.total += line.value;
+                        next.push(line);
+                    }
+                    continue;
+                }
+                for action in moves(&line.state) {
+                    let mut state = line.state.clone();
+                    state
+                        .apply(&action)
+                        .expect("legal_actions yielded an illegal action");
+                    self.opponents.answer(&mut state, me);
+                    if !seen.insert(state.zobrist()) {
+                        continue;
+                    }
+                    lines += 1;
+                    let mut plan = line.plan.clone();
+                    plan.push(action);
+                    let value = value(&state, me);
+                    next.push(Line {
+                        value,
+                        total: line.total + value,
+                        state,
+                        plan,
+                    });
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            // Ties keep the order the moves are listed in.
+            next.sort_by_key(|line| Reverse(line.total));
+            next.truncate(self.width.max(1));
+            beam = next;
+        }
+        let best = &beam[0];
+        Thought {
+            action: best.plan.first().cloned().unwrap_or(Action::Skip),
+            plan: best.plan.clone(),
+            value: best.value,
+            total: best.total,
+            lines,
+            elapsed: started.elapsed(),
+        }
+    }
+}
+
+/// Every legal move but resigning.
+fn moves(state: &State) -> Vec<Action> {
+    state
+        .legal_actions()
+        .filter(|action| *action != Action::Resign)
+        .collect()
+}
+
+/// What `state` is worth to `me` in the long run, a point being 1000: its
+/// points, the cards it bought for what they pay of the nobels and of the
+/// cards worth points it may buy next, the cards it reserved for how close
+/// it is to buying them, and its coins, less half the points of the best of
+/// the others.
+pub fn value(state: &State, me: usize) -> i64 {
+    match state.result() {
+        GameResult::Won(winner) if winner == me => return WON,
+        GameResult::Won(_) => return -WON,
+        GameResult::Draw(_) => return 0,
+        GameResult::Ongoing => (),
+    }
+    let player = &state.players[me];
+    let mut value = 1000 * i64::from(player.score);
+    for (_, &n) in player.immortal.iter() {
+        value += 100 * n.min(4) as i64 + 20 * n.saturating_sub(4) as i64;
+    }
+    for nobel in &state.nobels {
+        value += i64::from(nobel.score) * progress(&nobel.cost, player.immortal.clone()) / 2;
+    }
+    let visible = state
+        .card_iter()
+        .map(|(deck, card)| &state.decks[deck][card]);
+    for card in visible.chain(&player.reserved) {
+        value += i64::from(card.score()) * progress(card.cost(), player.immortal.clone()) * 2 / 5;
+    }
+    // A reserved card is the player's alone to buy, coins and all.
+    for card in &player.reserved {
+        let mut paid = player.immortal.clone() + player.mortal.gems.clone();
+        let short = paid.deficit(card.cost());
+        // Gold pays for any color.
+        let mut gold = player.mortal.gold;
+        for (color, n) in short.iter() {
+            let used = (*n).min(gold);
+            paid[color] += used;
+            gold -= used;
+        }
+        value += i64::from(card.score()) * progress(card.cost(), paid) * 3 / 20;
+    }
+    value += 60 * i64::from(player.mortal.gems.sum()) + 100 * player.mortal.gold as i64;
+    let others = (0..state.players.len())
+        .filter(|&i| i != me)
+        .map(|i| i64::from(state.players[i].score))
+        .max()
+        .unwrap_or(0);
+    value - 500 * others
+}
+
+/// How much of `cost` is `paid`, squared so that a cost nearly paid counts
+/// far more than one just begun, in thousandths.
+fn progress(cost: &ResourceMap, paid: ResourceMap) -> i64 {
+    let needed = i64::from(cost.sum());
+    if needed == 0 {
+        return 1000;
+    }
+    let done = needed - i64::from(paid.deficit(cost).sum());
+    1000 * done * done / (needed * needed)
+}
+
+/// [`Beam`] playing a game.
+pub struct BeamAgent {
+    pub beam: Beam,
+}
+
+impl AgentHandler for BeamAgent {
+    fn act(&mut self, observation: Observation) -> anyhow::Result<Action> {
+        let thought = self.beam.think(observation);
+        eprintln!("beam: {thought}");
+        Ok(thought.action)
+    }
+}
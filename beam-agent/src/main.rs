@@ -0,0 +1,59 @@
+//! Usage: `beam-agent [--width <n>] [--depth <moves>] [--opponents greedy|first]
+//! [--seed <n>]`
+//!
+//! Keeps the `--width` best lines, 12 by default, for `--depth` of its own
+//! moves, 8 by default, and plays the first move of the best. `--opponents
+//! greedy`, the default, takes the others to play like the greedy agent,
+//! and `--opponents first` their first legal move. `--seed` picks the cards
+//! it imagines where it can't see them. For each move it logs the lines it
+//! valued and the plan it follows to stderr.
+//!
+//! Bad arguments fail before the first state is read. `--format` and
+//! `--protocol`, which the runner adds, are for [`run_agent`].
+
+use anyhow::Context;
+use beam_agent::{Beam, BeamAgent};
+use game_def::run_agent;
+
+const USAGE: &str =
+    "usage: beam-agent [--width <n>] [--depth <moves>] [--opponents greedy|first] [--seed <n>]";
+
+fn main() -> anyhow::Result<()> {
+    let mut beam = Beam::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--width" => {
+                beam.width = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .filter(|&n| n > 0)
+                    .context("--width needs a positive number")?
+            }
+            "--depth" => {
+                beam.depth = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .filter(|&n| n > 0)
+                    .context("--depth needs a positive number of moves")?
+            }
+            "--opponents" => {
+                beam.opponents = args
+                    .next()
+                    .context("--opponents needs greedy or first")?
+                    .parse()?
+            }
+            "--seed" => {
+                beam.seed = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .context("--seed needs a number")?
+            }
+            "--format" | "--protocol" => {
+                args.next();
+            }
+            _ => anyhow::bail!("Unknown argument {arg}, {USAGE}"),
+        }
+    }
+    run_agent(BeamAgent { beam })
+}
@@ -0,0 +1,110 @@
+//! Plans the greedy agent can't see, on positions set up for them.
+
+use beam_agent::{Beam, BeamAgent};
+use game_def::{Action, Agent, GameConfig, Handled, ResourceKind, ResourceMap, State, Tier};
+use greedy_agent::Greedy;
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Puts the card of `tier` that costs `cost` and is worth `score` face up
+/// in `slot`.
+fn deal(state: &mut State, tier: Tier, slot: usize, cost: &str, score: u8) {
+    let cost = ResourceMap::from_code(cost);
+    let deck = &mut state.decks[tier];
+    let k = deck
+        .iter()
+        .position(|card| *card.cost() == cost && card.score() == score)
+        .unwrap();
+    deck.swap(slot, k);
+}
+
+/// The points of the player to move after it played `agent` for `turns`
+/// turns, the others playing like the greedy agent.
+fn points_after(state: &State, agent: &mut dyn Agent, turns: usize) -> u8 {
+    let mut state = state.clone();
+    let me = state.turn;
+    let mut greedy = Greedy::new(0.0, 0);
+    for _ in 0..turns {
+        loop {
+            let action = agent.act(&state);
+            state.apply(&action).unwrap();
+            if state.turn != me {
+                break;
+            }
+        }
+        while state.turn != me && !state.is_finished() {
+            let action = greedy.act(&state);
+            state.apply(&action).unwrap();
+        }
+    }
+    state.players[me].score
+}
+
+#[test]
+fn buys_the_cards_that_make_the_next_free() {
+    // The player to move has four blue cards and four black coins, and
+    // nobody can take a blue coin, in a game without gold. The 1 point card
+    // of 4 black is the best buy there is, but the blue card of 3 black
+    // makes the blue card of 5 blue free, that one the blue card of 6 blue,
+    // and that one the 4 point card of 7 blue. The decks are out of face
+    // down cards, so buying deals nothing new.
+    let config = GameConfig {
+        wilds: 0,
+        ..GameConfig::default()
+    };
+    let mut state = State::setup(&["a", "b"], config, &mut StdRng::seed_from_u64(7));
+    state.nobels.clear();
+    deal(&mut state, Tier::One, 0, "4k", 1);
+    deal(&mut state, Tier::One, 1, "3k", 0);
+    deal(&mut state, Tier::One, 2, "3w", 0);
+    deal(&mut state, Tier::One, 3, "3r", 0);
+    deal(&mut state, Tier::Two, 0, "5u", 2);
+    deal(&mut state, Tier::Two, 1, "6u", 3);
+    deal(&mut state, Tier::Two, 2, "3w+5k", 2);
+    deal(&mut state, Tier::Two, 3, "5r+3k", 2);
+    for (slot, cost) in ["7u", "7w", "7k", "7g"].into_iter().enumerate() {
+        deal(&mut state, Tier::Three, slot, cost, 4);
+    }
+    let visible = state.config.visible_cards;
+    for deck in state.decks.values_mut() {
+        deck.truncate(visible);
+    }
+    let me = state.turn;
+    state.players[me].immortal = ResourceMap::from_code("4u");
+    state.players[me].mortal.gems = ResourceMap::from_code("4k");
+    state.players[1 - me].mortal.gems = ResourceMap::from_code("4u");
+    state.coins.gems[ResourceKind::Black] -= 4;
+    state.coins.gems[ResourceKind::Blue] -= 4;
+    state.rehash();
+
+    let buy = |deck, card| Action::Purchase {
+        deck,
+        card,
+        payment: None,
+        wilds: None,
+        id: None,
+    };
+    let greedy = greedy_agent::best_action(&state);
+    assert_eq!(greedy, buy(Tier::One, 0), "{greedy}");
+    let thought = Beam::default().think(state.observation_for(me));
+    println!("Greedy plays {greedy}, the beam search {thought}");
+    // It may keep the last card from the others first.
+    assert_ne!(thought.action, greedy, "{thought}");
+    let start = thought.plan.iter().position(|ac| *ac == buy(Tier::One, 1));
+    assert!(start.is_some_and(|k| k < 2), "{thought}");
+    assert_eq!(
+        thought.plan[start.unwrap() + 1],
+        buy(Tier::Two, 0),
+        "{thought}"
+    );
+
+    let mut beam = Handled(BeamAgent {
+        beam: Beam::default(),
+    });
+    let mut greedy = Greedy::new(0.0, 0);
+    let (planned, grabbed) = (
+        points_after(&state, &mut beam, 5),
+        points_after(&state, &mut greedy, 5),
+    );
+    println!("After five turns the beam search has {planned} points, greedy {grabbed}");
+    assert!(planned > grabbed);
+}
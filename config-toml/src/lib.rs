@@ -0,0 +1,216 @@
+//! The part of TOML that configuration files here need: comments, tables,
+//! arrays of tables, and strings, numbers, booleans and arrays on one line.
+//! The runner's `--config` files and the phases of portfolio-agent are
+//! read with it.
+//!
+//! ```
+//! use config_toml::{parse, Value};
+//!
+//! let text = "seed = 7\n[match]\ngames = 100 # a comment\n[[agents]]\ncommand = 'a'\n";
+//! let tables = parse("run.toml", text).unwrap();
+//! // The keys before the first table are in one with no name.
+//! assert_eq!(tables[0].name, "");
+//! assert_eq!(tables[0].entries[0].1, Value::Integer(7));
+//! assert_eq!((tables[1].name.as_str(), tables[1].array), ("match", false));
+//! assert_eq!(tables[2].entries[0].1.to_string(), r#""a""#);
+//! assert_eq!(tables[2].entries[0].2, 5);
+//!
+//! let error = parse("run.toml", "[match]\ngames = many\n").unwrap_err();
+//! assert_eq!(error.to_string(), "run.toml:2: `many` is not a value, strings need quotes");
+//! ```
+
+use std::fmt;
+
+use anyhow::anyhow;
+use serde_json::json;
+
+/// The value of a key.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    /// The same value in JSON, as the runner keeps what it was configured
+    /// with.
+    pub fn json(&self) -> serde_json::Value {
+        match self {
+            Value::Text(s) => json!(s),
+            Value::Integer(n) => json!(n),
+            Value::Float(x) => json!(x),
+            Value::Bool(b) => json!(b),
+            Value::Array(items) => items.iter().map(Value::json).collect(),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Text(s) => write!(f, "{s:?}"),
+            Value::Integer(n) => write!(f, "{n}"),
+            Value::Float(x) => write!(f, "{x}"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Array(items) => {
+                let items: Vec<_> = items.iter().map(Value::to_string).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+        }
+    }
+}
+
+/// A `[table]` or `[[table]]` of the file, with each key, its value and the
+/// line it is on.
+#[derive(Debug)]
+pub struct Table {
+    pub name: String,
+    /// Whether it is a `[[table]]`.
+    pub array: bool,
+    /// 0 for the one before the first table.
+    pub line: usize,
+    pub entries: Vec<(String, Value, usize)>,
+}
+
+fn is_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// The tables of `text`, the keys before the first table in one with no
+/// name. Errors start with `path` and the line they are on.
+pub fn parse(path: &str, text: &str) -> anyhow::Result<Vec<Table>> {
+    let mut tables = vec![Table {
+        name: String::new(),
+        array: false,
+        line: 0,
+        entries: vec![],
+    }];
+    for (line, text) in text.lines().enumerate() {
+        let line = line + 1;
+        let fail = |message: String| anyhow!("{path}:{line}: {message}");
+        let text = text.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        if let Some(header) = text.strip_prefix('[') {
+            let (array, header) = match header.strip_prefix('[') {
+                Some(header) => (true, header),
+                None => (false, header),
+            };
+            let close = if array { "]]" } else { "]" };
+            let Some((name, rest)) = header.split_once(close) else {
+                return Err(fail(format!("expected {close} after the table name")));
+            };
+            end_of_line(rest).map_err(fail)?;
+            let name = name.trim();
+            if !is_key(name) {
+                return Err(fail(format!("`{name}` is not a table name")));
+            }
+            if !array && tables.iter().any(|t| t.name == name) {
+                return Err(fail(format!("[{name}] is there twice")));
+            }
+            tables.push(Table {
+                name: name.to_owned(),
+                array,
+                line,
+                entries: vec![],
+            });
+            continue;
+        }
+        let Some((key, value)) = text.split_once('=') else {
+            return Err(fail("expected key = value".to_owned()));
+        };
+        let key = key.trim();
+        if !is_key(key) {
+            return Err(fail(format!("`{key}` is not a key, like max_rounds")));
+        }
+        let (value, rest) = read_value(value.trim()).map_err(fail)?;
+        end_of_line(rest).map_err(fail)?;
+        let table = tables.last_mut().unwrap();
+        if table.entries.iter().any(|(k, ..)| k == key) {
+            return Err(fail(format!("{key} is set twice")));
+        }
+        table.entries.push((key.to_owned(), value, line));
+    }
+    Ok(tables)
+}
+
+/// Checks that only a comment follows a value or a table name.
+fn end_of_line(rest: &str) -> Result<(), String> {
+    let rest = rest.trim();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(())
+    } else {
+        Err(format!("unexpected `{rest}`"))
+    }
+}
+
+/// The value at the start of `text`, and what follows it.
+fn read_value(text: &str) -> Result<(Value, &str), String> {
+    if let Some(rest) = text.strip_prefix('"') {
+        let mut s = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Ok((Value::Text(s), &rest[i + 1..])),
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some(c) => return Err(format!("unknown escape \\{c}")),
+                    None => break,
+                },
+                c => s.push(c),
+            }
+        }
+        Err("the string isn't closed with \"".to_owned())
+    } else if let Some(rest) = text.strip_prefix('\'') {
+        let (s, rest) = rest
+            .split_once('\'')
+            .ok_or("the string isn't closed with '")?;
+        Ok((Value::Text(s.to_owned()), rest))
+    } else if let Some(mut rest) = text.strip_prefix('[') {
+        let mut items = vec![];
+        loop {
+            rest = rest.trim_start();
+            if let Some(rest) = rest.strip_prefix(']') {
+                return Ok((Value::Array(items), rest));
+            }
+            let (item, after) = read_value(rest)?;
+            items.push(item);
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after;
+            } else if !rest.starts_with(']') {
+                return Err("expected , or ] in the array, which has to be on one line".to_owned());
+            }
+        }
+    } else {
+        let end = text
+            .find(|c: char| c.is_whitespace() || c == ',' || c == ']' || c == '#')
+            .unwrap_or(text.len());
+        let (word, rest) = text.split_at(end);
+        let digits = word.replace('_', "");
+        let value = match word {
+            "" => return Err("expected a value".to_owned()),
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ if !word.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-') => {
+                return Err(format!("`{word}` is not a value, strings need quotes"))
+            }
+            _ => match (digits.parse(), digits.parse::<f64>()) {
+                (Ok(n), _) => Value::Integer(n),
+                (_, Ok(x)) if x.is_finite() => Value::Float(x),
+                _ => return Err(format!("`{word}` is not a number")),
+            },
+        };
+        Ok((value, rest))
+    }
+}
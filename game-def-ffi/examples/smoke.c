@@ -0,0 +1,67 @@
+/* Plays a game through the C interface: create, act until it is over, free.
+ *
+ *   cargo build --release -p game-def-ffi
+ *   cc game-def-ffi/examples/smoke.c -Igame-def-ffi/include \
+ *       -Ltarget/release -lgame_def_ffi -o smoke
+ *   LD_LIBRARY_PATH=target/release ./smoke
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "splendor.h"
+
+/* The first action of the JSON array in `actions`, as its own string. */
+static char *first_action(const char *actions) {
+    const char *start = strchr(actions, '{');
+    int depth = 0;
+    const char *p = start;
+    do {
+        if (*p == '{') depth++;
+        if (*p == '}') depth--;
+        p++;
+    } while (depth > 0);
+    char *r = malloc(p - start + 1);
+    memcpy(r, start, p - start);
+    r[p - start] = 0;
+    return r;
+}
+
+int main(void) {
+    char err[256];
+    SplendorState *state;
+    if (splendor_state_new(2, 42, &state, err, sizeof err) != SPLENDOR_OK) {
+        fprintf(stderr, "new: %s\n", err);
+        return 1;
+    }
+    if (splendor_state_run(state, "{\"type\":\"buy\"}", err, sizeof err) !=
+        SPLENDOR_INVALID_ARGUMENT) {
+        fprintf(stderr, "bad json was accepted\n");
+        return 1;
+    }
+    int32_t winner = -1;
+    int moves = 0;
+    while (winner == -1 && moves < 10000) {
+        size_t needed;
+        if (splendor_legal_actions_json(state, NULL, 0, &needed) !=
+            SPLENDOR_BUFFER_TOO_SMALL) {
+            fprintf(stderr, "expected a size query\n");
+            return 1;
+        }
+        char *actions = malloc(needed);
+        splendor_legal_actions_json(state, actions, needed, &needed);
+        char *action = first_action(actions);
+        if (splendor_state_run(state, action, err, sizeof err) != SPLENDOR_OK) {
+            fprintf(stderr, "run %s: %s\n", action, err);
+            return 1;
+        }
+        free(action);
+        free(actions);
+        splendor_state_winner(state, &winner);
+        moves++;
+    }
+    printf("%d moves, winner %d\n", moves, winner);
+    splendor_state_free(state);
+    return 0;
+}
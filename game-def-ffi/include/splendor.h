@@ -0,0 +1,44 @@
+/* C interface of game-def-ffi. Kept by hand in sync with src/lib.rs. */
+
+#ifndef SPLENDOR_H
+#define SPLENDOR_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define SPLENDOR_OK 0
+/* A null pointer, bad UTF-8 or JSON that doesn't parse. */
+#define SPLENDOR_INVALID_ARGUMENT 1
+/* The rules rejected the action; the state is unchanged. */
+#define SPLENDOR_ILLEGAL_ACTION 2
+/* The output didn't fit, `needed` tells how big the buffer has to be. */
+#define SPLENDOR_BUFFER_TOO_SMALL 3
+/* A bug in the engine. The state may be inconsistent and should be freed. */
+#define SPLENDOR_PANIC 4
+
+typedef struct SplendorState SplendorState;
+
+/* `err` may be null; messages that don't fit in `err_len` are cut. */
+int splendor_state_new(size_t players, uint64_t seed, SplendorState **out,
+                       char *err, size_t err_len);
+int splendor_state_from_json(const char *json, SplendorState **out, char *err,
+                             size_t err_len);
+int splendor_state_to_json(const SplendorState *state, char *buf, size_t len,
+                           size_t *needed);
+int splendor_state_run(SplendorState *state, const char *action_json,
+                       char *err, size_t err_len);
+int splendor_legal_actions_json(const SplendorState *state, char *buf,
+                                size_t len, size_t *needed);
+/* -1 while the game is on, -2 after a draw. */
+int splendor_state_winner(const SplendorState *state, int32_t *winner);
+void splendor_state_free(SplendorState *state);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,233 @@
+//! A C ABI over game-def with an opaque state handle, declared in
+//! `include/splendor.h`. Every function returns one of the `SPLENDOR_*` codes
+//! and never unwinds into the caller; error messages are written into a
+//! caller provided buffer.
+
+use std::{
+    ffi::{c_char, c_int, CStr},
+    panic::{catch_unwind, AssertUnwindSafe},
+};
+
+use game_def::{Action, GameConfig, GameResult, State};
+use rand::{rngs::StdRng, SeedableRng};
+
+pub const SPLENDOR_OK: c_int = 0;
+/// A null pointer, bad UTF-8 or JSON that doesn't parse.
+pub const SPLENDOR_INVALID_ARGUMENT: c_int = 1;
+/// The rules rejected the action; the state is unchanged.
+pub const SPLENDOR_ILLEGAL_ACTION: c_int = 2;
+/// The output didn't fit, `needed` tells how big the buffer has to be.
+pub const SPLENDOR_BUFFER_TOO_SMALL: c_int = 3;
+/// A bug in the engine. The state may be inconsistent and should be freed.
+pub const SPLENDOR_PANIC: c_int = 4;
+
+/// Opaque to C.
+pub struct SplendorState(State);
+
+struct Error(c_int, String);
+
+/// Copies `s` and a NUL into `buf` if it fits, and stores the size it needs
+/// in `needed` either way.
+unsafe fn write_str(s: &str, buf: *mut c_char, len: usize, needed: *mut usize) -> c_int {
+    if !needed.is_null() {
+        *needed = s.len() + 1;
+    }
+    if buf.is_null() || len < s.len() + 1 {
+        return SPLENDOR_BUFFER_TOO_SMALL;
+    }
+    std::ptr::copy_nonoverlapping(s.as_ptr(), buf.cast(), s.len());
+    *buf.add(s.len()) = 0;
+    SPLENDOR_OK
+}
+
+/// Runs `f`, turning errors and panics into a code and a message in `err`,
+/// cut to fit if needed.
+unsafe fn guard(err: *mut c_char, err_len: usize, f: impl FnOnce() -> Result<(), Error>) -> c_int {
+    let (code, message) = match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => return SPLENDOR_OK,
+        Ok(Err(Error(code, message))) => (code, message),
+        Err(_) => (SPLENDOR_PANIC, "The engine panicked".to_owned()),
+    };
+    if !err.is_null() && err_len > 0 {
+        let mut end = message.len().min(err_len - 1);
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        write_str(&message[..end], err, err_len, std::ptr::null_mut());
+    }
+    code
+}
+
+unsafe fn arg<'a>(s: *const c_char, what: &str) -> Result<&'a str, Error> {
+    if s.is_null() {
+        return Err(Error(SPLENDOR_INVALID_ARGUMENT, format!("{what} is null")));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|e| Error(SPLENDOR_INVALID_ARGUMENT, format!("Invalid {what}: {e}")))
+}
+
+unsafe fn state_ref<'a>(state: *const SplendorState) -> Result<&'a State, Error> {
+    state
+        .as_ref()
+        .map(|s| &s.0)
+        .ok_or_else(|| Error(SPLENDOR_INVALID_ARGUMENT, "state is null".to_owned()))
+}
+
+unsafe fn output(s: &str, buf: *mut c_char, len: usize, needed: *mut usize) -> Result<(), Error> {
+    match write_str(s, buf, len, needed) {
+        SPLENDOR_OK => Ok(()),
+        code => Err(Error(
+            code,
+            format!("The output needs {} bytes", s.len() + 1),
+        )),
+    }
+}
+
+fn invalid(e: impl std::fmt::Display) -> Error {
+    Error(SPLENDOR_INVALID_ARGUMENT, e.to_string())
+}
+
+/// A new standard game for `players` players, shuffled with `seed`.
+///
+/// # Safety
+/// `out` must be valid for a write, `err` for `err_len` bytes or null.
+#[no_mangle]
+pub unsafe extern "C" fn splendor_state_new(
+    players: usize,
+    seed: u64,
+    out: *mut *mut SplendorState,
+    err: *mut c_char,
+    err_len: usize,
+) -> c_int {
+    guard(err, err_len, || {
+        if out.is_null() {
+            return Err(invalid("out is null"));
+        }
+        if !(2..=4).contains(&players) {
+            return Err(invalid(format!("{players} players is not 2 to 4")));
+        }
+        let names: Vec<String> = (0..players).map(|i| format!("Player {i}")).collect();
+        let names: Vec<&str> = names.iter().map(|x| x.as_str()).collect();
+        let state = State::setup(
+            &names,
+            GameConfig::default(),
+            &mut StdRng::seed_from_u64(seed),
+        );
+        *out = Box::into_raw(Box::new(SplendorState(state)));
+        Ok(())
+    })
+}
+
+/// Parses a JSON state, as the runner writes it.
+///
+/// # Safety
+/// `json` must be a NUL terminated string, `out` valid for a write, `err`
+/// valid for `err_len` bytes or null.
+#[no_mangle]
+pub unsafe extern "C" fn splendor_state_from_json(
+    json: *const c_char,
+    out: *mut *mut SplendorState,
+    err: *mut c_char,
+    err_len: usize,
+) -> c_int {
+    guard(err, err_len, || {
+        if out.is_null() {
+            return Err(invalid("out is null"));
+        }
+        let state: State = serde_json::from_str(arg(json, "json")?).map_err(invalid)?;
+        *out = Box::into_raw(Box::new(SplendorState(state)));
+        Ok(())
+    })
+}
+
+/// Writes the state as JSON.
+///
+/// # Safety
+/// `state` must come from this library, `buf` be valid for `len` bytes or
+/// null, `needed` valid for a write or null.
+#[no_mangle]
+pub unsafe extern "C" fn splendor_state_to_json(
+    state: *const SplendorState,
+    buf: *mut c_char,
+    len: usize,
+    needed: *mut usize,
+) -> c_int {
+    guard(std::ptr::null_mut(), 0, || {
+        output(&state_ref(state)?.json(), buf, len, needed)
+    })
+}
+
+/// Runs a JSON action on the state. On any error the state is unchanged.
+///
+/// # Safety
+/// `state` must come from this library, `action_json` be a NUL terminated
+/// string and `err` valid for `err_len` bytes or null.
+#[no_mangle]
+pub unsafe extern "C" fn splendor_state_run(
+    state: *mut SplendorState,
+    action_json: *const c_char,
+    err: *mut c_char,
+    err_len: usize,
+) -> c_int {
+    guard(err, err_len, || {
+        let state = state.as_mut().ok_or_else(|| invalid("state is null"))?;
+        let action: Action = serde_json::from_str(arg(action_json, "action")?).map_err(invalid)?;
+        let mut next = state.0.clone();
+        next.run(action)
+            .map_err(|e| Error(SPLENDOR_ILLEGAL_ACTION, format!("{e:#}")))?;
+        state.0 = next;
+        Ok(())
+    })
+}
+
+/// Writes the legal actions as a JSON array.
+///
+/// # Safety
+/// As for [`splendor_state_to_json`].
+#[no_mangle]
+pub unsafe extern "C" fn splendor_legal_actions_json(
+    state: *const SplendorState,
+    buf: *mut c_char,
+    len: usize,
+    needed: *mut usize,
+) -> c_int {
+    guard(std::ptr::null_mut(), 0, || {
+        let actions: Vec<Action> = state_ref(state)?.legal_actions().collect();
+        output(&serde_json::to_string(&actions).unwrap(), buf, len, needed)
+    })
+}
+
+/// Stores in `winner` the winning player, -1 while the game is on and -2
+/// after a draw.
+///
+/// # Safety
+/// `state` must come from this library and `winner` be valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn splendor_state_winner(
+    state: *const SplendorState,
+    winner: *mut i32,
+) -> c_int {
+    guard(std::ptr::null_mut(), 0, || {
+        if winner.is_null() {
+            return Err(invalid("winner is null"));
+        }
+        *winner = match state_ref(state)?.result() {
+            GameResult::Ongoing => -1,
+            GameResult::Won(i) => i as i32,
+            GameResult::Draw(_) => -2,
+        };
+        Ok(())
+    })
+}
+
+/// Frees a state. Null is ignored.
+///
+/// # Safety
+/// `state` must come from this library and not be freed already.
+#[no_mangle]
+pub unsafe extern "C" fn splendor_state_free(state: *mut SplendorState) {
+    if !state.is_null() {
+        _ = catch_unwind(|| drop(Box::from_raw(state)));
+    }
+}
@@ -0,0 +1,143 @@
+"""The game-def rules engine for Python agents.
+
+Build the library with `cargo build --release -p game-def-py`; it is looked
+up in `target/release` of this repository, or at `$SPLENDOR_LIB`.
+
+States and actions are the plain dicts of the runner's JSON, so an action
+from here can be printed straight to stdout as the agent's answer:
+
+    state = State.from_json(line)  # the "state" of an observation
+    action = state.legal_actions()[0]
+    print(json.dumps(action), flush=True)
+"""
+
+import ctypes
+import json
+import os
+import sys
+
+_ROOT = os.path.join(os.path.dirname(os.path.abspath(__file__)), "..", "..")
+_NAMES = {"darwin": "libgame_def_py.dylib", "win32": "game_def_py.dll"}
+
+
+def _load():
+    path = os.environ.get("SPLENDOR_LIB") or os.path.join(
+        _ROOT, "target", "release", _NAMES.get(sys.platform, "libgame_def_py.so")
+    )
+    lib = ctypes.CDLL(path)
+    for name, args in [
+        ("splendor_legal_actions", 1),
+        ("splendor_run", 2),
+        ("splendor_result", 1),
+    ]:
+        f = getattr(lib, name)
+        f.argtypes = [ctypes.c_char_p] * args
+        f.restype = ctypes.c_void_p
+    lib.splendor_free.argtypes = [ctypes.c_void_p]
+    lib.splendor_free.restype = None
+    return lib
+
+
+_lib = _load()
+
+
+def _call(name, *args):
+    ptr = getattr(_lib, name)(*(json.dumps(a).encode() for a in args))
+    try:
+        response = json.loads(ctypes.string_at(ptr).decode())
+    finally:
+        _lib.splendor_free(ptr)
+    if "error" in response:
+        raise ValueError(response["error"])
+    return response["ok"]
+
+
+class State:
+    def __init__(self, data):
+        self.data = data
+
+    @staticmethod
+    def from_json(text):
+        return State(json.loads(text))
+
+    def json(self):
+        return json.dumps(self.data)
+
+    def legal_actions(self):
+        return _call("splendor_legal_actions", self.data)
+
+    def run(self, action):
+        """Plays `action` in place and returns the events it caused. Raises
+        ValueError, leaving the state as it was, if the action is illegal."""
+        response = _call("splendor_run", self.data, action)
+        self.data = response["state"]
+        return response["events"]
+
+    def result(self):
+        """"ongoing", {"won": player} or {"draw": [players]}."""
+        return _call("splendor_result", self.data)
+
+    def is_finished(self):
+        return self.result() != "ongoing"
+
+    def winner(self):
+        """The winning player, None while the game is on or after a draw."""
+        result = self.result()
+        return result.get("won") if isinstance(result, dict) else None
+
+
+COLORS = ["red", "blue", "green", "white", "black"]
+# Actions name colors by these one letter codes, coin maps by the names.
+CODES = {"red": "r", "blue": "u", "green": "g", "white": "w", "black": "k"}
+
+
+class Action:
+    """Builds actions as the runner expects them, with colors from `COLORS`."""
+
+    @staticmethod
+    def pick_three(one, two, three):
+        return {"type": "pick_three", "one": CODES[one], "two": CODES[two], "three": CODES[three]}
+
+    @staticmethod
+    def pick_two(color):
+        return {"type": "pick_two", "color": CODES[color]}
+
+    @staticmethod
+    def pick_fewer(colors):
+        return {"type": "pick_fewer", "colors": [CODES[c] for c in colors]}
+
+    # Decks are 0, 1 and 2 here, cheapest first, unlike the 1, 2 and 3 of
+    # the runner's notation.
+    @staticmethod
+    def purchase(deck, card):
+        return {"type": "purchase", "deck": deck, "card": card}
+
+    @staticmethod
+    def purchase_reserved(index):
+        return {"type": "purchase_reserved", "index": index}
+
+    @staticmethod
+    def reserve(deck, card):
+        return {"type": "reserve", "deck": deck, "card": card}
+
+    @staticmethod
+    def reserve_blind(deck):
+        return {"type": "reserve_blind", "deck": deck}
+
+    @staticmethod
+    def discard(coins, wilds=0):
+        """`coins` maps colors to counts, missing colors count as 0."""
+        coins = {color: coins.get(color, 0) for color in COLORS}
+        return {"type": "discard", "coins": coins, "wilds": wilds}
+
+    @staticmethod
+    def choose_nobel(index):
+        return {"type": "choose_nobel", "index": index}
+
+    @staticmethod
+    def skip():
+        return {"type": "skip"}
+
+    @staticmethod
+    def resign():
+        return {"type": "resign"}
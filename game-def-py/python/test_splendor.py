@@ -0,0 +1,64 @@
+"""Parity of the bindings with the Rust engine, on a game it recorded.
+
+Run through `cargo test -p game-def-py`, which builds the library, records a
+random game to `$SPLENDOR_PARITY` and runs this with pytest, or without it
+when pytest isn't installed.
+"""
+
+import json
+import os
+
+from splendor import Action, State
+
+
+def _recorded():
+    with open(os.environ["SPLENDOR_PARITY"]) as f:
+        return json.load(f)
+
+
+def test_random_game_matches_rust():
+    game = _recorded()
+    state = State(game["states"][0])
+    for i, action in enumerate(game["actions"]):
+        assert not state.is_finished()
+        assert state.legal_actions() == game["legal"][i], f"move {i}"
+        state.run(action)
+        assert state.data == game["states"][i + 1], f"after move {i}"
+    assert state.is_finished()
+    assert state.result() == game["result"]
+    assert state.winner() == game["winner"]
+
+
+def test_illegal_actions_leave_the_state_alone():
+    state = State(_recorded()["states"][0])
+    before = state.json()
+    try:
+        state.run(Action.purchase_reserved(0))
+    except ValueError as e:
+        assert "reserved" in str(e).lower(), e
+    else:
+        raise AssertionError("bought a card that was never reserved")
+    assert state.json() == before
+
+
+def test_actions_serialize_like_the_runner():
+    built = [
+        Action.pick_three("red", "blue", "green"),
+        Action.pick_two("white"),
+        Action.pick_fewer(["black", "red"]),
+        Action.purchase(0, 1),
+        Action.purchase_reserved(2),
+        Action.reserve(1, 3),
+        Action.reserve_blind(2),
+        Action.discard({"red": 1}, wilds=1),
+        Action.choose_nobel(0),
+        Action.skip(),
+    ]
+    assert built == _recorded()["built"]
+
+
+if __name__ == "__main__":
+    for name, test in list(globals().items()):
+        if name.startswith("test_"):
+            test()
+            print(f"{name} passed")
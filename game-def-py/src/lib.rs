@@ -0,0 +1,70 @@
+//! A C ABI over game-def for Python agents, loaded with ctypes by
+//! `python/splendor.py`. Like `game_def::wasm`, everything goes in and out as
+//! JSON strings: each call returns `{"ok": ...}` or `{"error": "..."}`, to be
+//! freed with [`splendor_free`].
+
+use std::ffi::{c_char, CStr, CString};
+
+use game_def::{Action, State};
+use serde_json::{json, Value};
+
+fn respond(result: Result<Value, String>) -> *mut c_char {
+    let value = match result {
+        Ok(value) => json!({ "ok": value }),
+        Err(error) => json!({ "error": error }),
+    };
+    // serde_json escapes NUL, so this can't fail.
+    CString::new(value.to_string()).unwrap().into_raw()
+}
+
+/// # Safety
+/// `s` must be a valid NUL terminated string.
+unsafe fn read<T: serde::de::DeserializeOwned>(s: *const c_char, what: &str) -> Result<T, String> {
+    let s = CStr::from_ptr(s)
+        .to_str()
+        .map_err(|e| format!("Invalid {what}: {e}"))?;
+    serde_json::from_str(s).map_err(|e| format!("Invalid {what}: {e}"))
+}
+
+/// The legal actions of a JSON [`State`], as a JSON array.
+///
+/// # Safety
+/// `state` must be a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn splendor_legal_actions(state: *const c_char) -> *mut c_char {
+    respond(read::<State>(state, "state").map(|s| json!(s.legal_actions().collect::<Vec<_>>())))
+}
+
+/// Runs a JSON [`Action`] on a JSON [`State`]. Gives `{"state": ..., "events": [...]}`.
+///
+/// # Safety
+/// `state` and `action` must be valid NUL terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn splendor_run(state: *const c_char, action: *const c_char) -> *mut c_char {
+    respond((|| {
+        let mut state: State = read(state, "state")?;
+        let action: Action = read(action, "action")?;
+        let events = state.run(action).map_err(|e| format!("{e:#}"))?;
+        Ok(json!({ "state": state, "events": events }))
+    })())
+}
+
+/// The [`game_def::GameResult`] of a JSON [`State`].
+///
+/// # Safety
+/// `state` must be a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn splendor_result(state: *const c_char) -> *mut c_char {
+    respond(read::<State>(state, "state").map(|s| json!(s.result())))
+}
+
+/// Frees a string returned by the other functions.
+///
+/// # Safety
+/// `s` must come from this library and not be freed already.
+#[no_mangle]
+pub unsafe extern "C" fn splendor_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
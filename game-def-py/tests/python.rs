@@ -0,0 +1,130 @@
+//! The Python bindings against the engine, on a random game recorded here
+//! and replayed by `python/test_splendor.py`.
+
+use std::{path::PathBuf, process::Command};
+
+use game_def::{Action, GameConfig, ResourceKind, ResourceMap, State, Tier};
+use rand::{rngs::StdRng, seq::IteratorRandom, SeedableRng};
+use serde_json::{json, Value};
+
+/// A random game from its first state to its last, with the legal actions
+/// before each move, and the actions the Python builders should make.
+fn recorded_game() -> Value {
+    let mut rng = StdRng::seed_from_u64(55);
+    let mut state = State::setup(&["a", "b", "c"], GameConfig::default(), &mut rng);
+    let (mut states, mut legal, mut actions) = (vec![json!(state)], vec![], vec![]);
+    while !state.is_finished() {
+        let moves: Vec<_> = state.legal_actions().collect();
+        let action = moves
+            .iter()
+            .filter(|a| **a != Action::Resign)
+            .choose(&mut rng)
+            .unwrap()
+            .clone();
+        legal.push(json!(moves));
+        actions.push(json!(action));
+        state.run(action).unwrap();
+        states.push(json!(state));
+    }
+    use ResourceKind::*;
+    let built = [
+        Action::PickThree {
+            one: Red,
+            two: Blue,
+            three: Green,
+        },
+        Action::PickTwo { color: White },
+        Action::PickFewer {
+            colors: vec![Black, Red],
+        },
+        Action::Purchase {
+            deck: Tier::One,
+            card: 1,
+            payment: None,
+            wilds: None,
+            id: None,
+        },
+        Action::PurchaseReserved {
+            index: 2,
+            payment: None,
+            wilds: None,
+        },
+        Action::Reserve {
+            deck: Tier::Two,
+            card: 3,
+            id: None,
+        },
+        Action::ReserveBlind { deck: Tier::Three },
+        Action::Discard {
+            coins: ResourceMap::from_code("1r"),
+            wilds: 1,
+        },
+        Action::ChooseNobel { index: 0 },
+        Action::Skip,
+    ];
+    let winner = match state.result() {
+        game_def::GameResult::Won(player) => json!(player),
+        _ => Value::Null,
+    };
+    json!({
+        "states": states,
+        "legal": legal,
+        "actions": actions,
+        "result": state.result(),
+        "winner": winner,
+        "built": built,
+    })
+}
+
+/// The library, built fresh: tests can't link a cdylib, so cargo doesn't
+/// build it for them.
+fn library() -> PathBuf {
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "-p", "game-def-py"])
+        .status()
+        .unwrap();
+    assert!(status.success(), "Failed to build game-def-py");
+    let dir = std::env::current_exe().unwrap();
+    let dir = dir.parent().unwrap().parent().unwrap();
+    let name = if cfg!(target_os = "macos") {
+        "libgame_def_py.dylib"
+    } else if cfg!(windows) {
+        "game_def_py.dll"
+    } else {
+        "libgame_def_py.so"
+    };
+    dir.join(name)
+}
+
+#[test]
+fn python_bindings_replay_a_game_like_the_engine() {
+    let python = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("python");
+    if Command::new("python3").arg("--version").output().is_err() {
+        println!("python3 is not installed, skipping");
+        return;
+    }
+    let fixture = std::env::temp_dir().join(format!("splendor-parity-{}.json", std::process::id()));
+    std::fs::write(&fixture, recorded_game().to_string()).unwrap();
+    let has_pytest = Command::new("python3")
+        .args(["-c", "import pytest"])
+        .output()
+        .is_ok_and(|o| o.status.success());
+    let mut command = Command::new("python3");
+    match has_pytest {
+        true => command.args(["-m", "pytest", "-q", "-p", "no:cacheprovider"]),
+        false => &mut command,
+    };
+    let output = command
+        .arg("test_splendor.py")
+        .current_dir(&python)
+        .env("SPLENDOR_LIB", library())
+        .env("SPLENDOR_PARITY", &fixture)
+        .env("PYTHONDONTWRITEBYTECODE", "1")
+        .output()
+        .unwrap();
+    std::fs::remove_file(&fixture).unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    println!("{stdout}");
+    assert!(output.status.success(), "{stdout}\n{stderr}");
+}
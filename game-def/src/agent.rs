@@ -0,0 +1,96 @@
+//! Agents that run inside the calling process instead of behind the runner's
+//! protocol, for tests and for playing many games quickly.
+
+use crate::{Action, AgentHandler, GameResult, Observation, Replay, State};
+
+/// Picks the move of the player to move. In [`play_game`] the state is what
+/// that player can see: decks hold only their face up cards and other
+/// players' blind reserves are missing, like the `state` of an
+/// [`Observation`](crate::Observation).
+///
+/// Closures taking a `&State` are agents too:
+///
+/// ```
+/// use game_def::{play_game, Action, Agent, GameConfig, RandomAgent, State};
+///
+/// let state = State::setup(&["a", "b"], GameConfig::default(), &mut rand::thread_rng());
+/// let last = |state: &State| state.legal_actions().last().unwrap_or(Action::Skip);
+/// let mut agents: Vec<Box<dyn Agent>> = vec![Box::new(RandomAgent), Box::new(last)];
+/// play_game(state, &mut agents, 100);
+/// ```
+pub trait Agent {
+    fn act(&mut self, state: &State) -> Action;
+}
+
+impl<F: FnMut(&State) -> Action> Agent for F {
+    fn act(&mut self, state: &State) -> Action {
+        self(state)
+    }
+}
+
+/// An agent of the protocol playing in [`play_game`], on what
+/// [`Observation::from_shown`] makes of the state it is shown. Resigns when
+/// the agent fails to answer.
+///
+/// ```
+/// use game_def::{play_game, Action, Agent, AgentHandler, GameConfig, Handled, Observation, State};
+///
+/// struct First;
+///
+/// impl AgentHandler for First {
+///     fn act(&mut self, observation: Observation) -> anyhow::Result<Action> {
+///         Ok(observation.state.legal_actions().next().unwrap())
+///     }
+/// }
+///
+/// let state = State::setup(&["a", "b"], GameConfig::default(), &mut rand::thread_rng());
+/// let mut agents: Vec<Box<dyn Agent>> = vec![Box::new(Handled(First)), Box::new(Handled(First))];
+/// play_game(state, &mut agents, 100);
+/// ```
+pub struct Handled<H>(pub H);
+
+impl<H: AgentHandler> Agent for Handled<H> {
+    fn act(&mut self, state: &State) -> Action {
+        self.0
+            .act(Observation::from_shown(state))
+            .unwrap_or(Action::Resign)
+    }
+}
+
+/// Plays the first legal move, skipping when there is none. Predictable, which
+/// makes it a handy opponent in tests.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomAgent;
+
+impl Agent for RandomAgent {
+    fn act(&mut self, state: &State) -> Action {
+        state.legal_actions().next().unwrap_or(Action::Skip)
+    }
+}
+
+/// Plays `state` out with `agents[i]` moving for player `i`, for at most
+/// `max_rounds` more rounds. Like the runner, an invalid action ends the game
+/// on the spot; the result is then [`GameResult::Ongoing`], as it is when the
+/// round cap is hit.
+pub fn play_game(state: State, agents: &mut [Box<dyn Agent>], max_rounds: usize) -> GameResult {
+    record_game(state, agents, max_rounds).result
+}
+
+/// Like [`play_game`], but keeps every valid action in a [`Replay`].
+pub fn record_game(mut state: State, agents: &mut [Box<dyn Agent>], max_rounds: usize) -> Replay {
+    assert_eq!(agents.len(), state.players.len(), "one agent per player");
+    let mut replay = Replay::new(state.clone());
+    let round_cap = state.round.saturating_add(max_rounds);
+    while !state.is_finished() && state.round < round_cap {
+        let observed = state.observation_for(state.turn).state;
+        let turn = state.turn;
+        let action = agents[turn].act(&observed);
+        if state.run(action.clone()).is_err() {
+            return replay;
+        }
+        replay.push(turn, action);
+    }
+    replay.result = state.result();
+    replay.end_reason = state.end_reason();
+    replay
+}
@@ -0,0 +1,99 @@
+//! Planning estimates for heuristics.
+//!
+//! Numbers of the standard rules are assumed, like in [`features`](crate::features):
+//! taking two coins of a color needs four of it in the bank, and a player
+//! reserves at most three cards.
+
+use enum_map::EnumMap;
+
+use crate::{CoinMap, Player, ResourceKind, ResourceMap};
+
+const PICK_TWO_MINIMUM: usize = 4;
+const MAX_RESERVED: usize = 3;
+
+/// A lower bound on the turns `player` needs before they can pay `cost`,
+/// 0 if they can pay it now.
+///
+/// The player is assumed to act alone: the bank only changes by what they
+/// take, and each turn they either take up to three coins of different
+/// colors, take two of a color the bank has at least four of, or reserve a
+/// card for one gold while the bank has gold and they have room. Within
+/// that model the count is exact. The hand limit is ignored, which can only
+/// make the real count higher. Buying other cards along the way gains one
+/// discount a turn, never more than taking coins, so it isn't counted.
+///
+/// `None` when the colors the bank lacks are more than the player's gold
+/// and the gold left to reserve for can cover. Buying other cards for their
+/// discounts might still get there.
+///
+/// ```
+/// use game_def::{analysis::turns_to_afford, CoinMap, Player, ResourceMap};
+///
+/// let player = Player::new("a");
+/// let bank = CoinMap { gems: ResourceMap::uniform(4), gold: 5 };
+/// let cost = |code| ResourceMap::from_code(code);
+/// assert_eq!(turns_to_afford(&player, &cost(""), &bank), Some(0));
+/// assert_eq!(turns_to_afford(&player, &cost("1r+1g+1u"), &bank), Some(1));
+/// // Two turns of two reds would empty the bank below four after the first.
+/// assert_eq!(turns_to_afford(&player, &cost("4r"), &bank), Some(3));
+/// // The fifth red can only be gold.
+/// assert_eq!(turns_to_afford(&player, &cost("5r"), &bank), Some(4));
+/// assert_eq!(turns_to_afford(&player, &cost("7r"), &bank), Some(6));
+/// // Only three reserves.
+/// assert_eq!(turns_to_afford(&player, &cost("8r"), &bank), None);
+/// ```
+pub fn turns_to_afford(player: &Player, cost: &ResourceMap, bank: &CoinMap) -> Option<u32> {
+    let need = player.mortal.gems.deficit(&player.immortal.deficit(cost));
+    let total: usize = need.iter().map(|(_, n)| n).sum();
+    let gold = player.mortal.gold;
+    let reserves = bank
+        .gold
+        .min(MAX_RESERVED.saturating_sub(player.reserved.len()));
+    let lacking: usize = need.iter().map(|(c, n)| n.saturating_sub(bank[c])).sum();
+    if lacking > gold + reserves {
+        return None;
+    }
+    // Coins the bank can give towards the cost, and how often each color
+    // can be doubled, doing those first.
+    let cap = EnumMap::from_fn(|c| need[c].min(bank[c]));
+    let doubles = EnumMap::from_fn(|c: ResourceKind| {
+        (bank[c].saturating_sub(PICK_TWO_MINIMUM - 2) / 2).min(cap[c] / 2)
+    });
+    // Each turn covers at least one more coin until it's affordable.
+    (0..=total).find_map(|turns| {
+        let affordable = (0..=reserves.min(turns))
+            .any(|r| gold + r + coverage(&cap, &doubles, turns - r) >= total);
+        affordable.then_some(turns as u32)
+    })
+}
+
+/// The most of `cap` that `turns` turns of taking coins can cover.
+fn coverage(
+    cap: &EnumMap<ResourceKind, usize>,
+    doubles: &EnumMap<ResourceKind, usize>,
+    turns: usize,
+) -> usize {
+    let colors: Vec<ResourceKind> = doubles
+        .iter()
+        .filter(|(_, d)| **d > 0)
+        .map(|(c, _)| c)
+        .collect();
+    let mut best = 0;
+    let mut used = EnumMap::<ResourceKind, usize>::default();
+    // Tries every mix of doubling turns, which are few.
+    loop {
+        let spent: usize = used.values().sum();
+        if spent <= turns {
+            let left = turns - spent;
+            let singles: usize = cap.iter().map(|(c, n)| (n - 2 * used[c]).min(left)).sum();
+            best = best.max(2 * spent + singles.min(3 * left));
+        }
+        let Some(i) = colors.iter().position(|&c| used[c] < doubles[c]) else {
+            return best;
+        };
+        used[colors[i]] += 1;
+        for &c in &colors[..i] {
+            used[c] = 0;
+        }
+    }
+}
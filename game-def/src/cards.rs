@@ -0,0 +1,360 @@
+//! The cards and nobels of the official game, and [`CardSet`]s to play with
+//! instead.
+
+use anyhow::{bail, Context};
+use enum_map::{enum_map, EnumMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{Card, Objective, ResourceKind, ResourceMap, Tier};
+
+type Table = EnumMap<ResourceKind, Vec<(u8, &'static str)>>;
+
+fn tables() -> [Table; 3] {
+    let deck0 = enum_map![
+        ResourceKind::Black => vec![
+            (0, "1w+1u+1g+1r"),
+            (0, "1w+2u+1g+1r"),
+            (0, "2w+2u+1r"),
+            (0, "1g+3r+1k"),
+            (0, "2g+1r"),
+            (0, "2w+2g"),
+            (0, "3g"),
+            (1, "4u"),
+        ],
+        ResourceKind::Blue => vec![
+            (0, "1w+1g+1r+1k"),
+            (0, "1w+1g+2r+1k"),
+            (0, "1w+2g+2r"),
+            (0, "1u+3g+1r"),
+            (0, "1w+2k"),
+            (0, "2g+2k"),
+            (0, "3k"),
+            (1, "4r"),
+        ],
+        ResourceKind::White => vec![
+            (0, "1u+1g+1r+1k"),
+            (0, "1u+2g+1r+1k"),
+            (0, "2u+2g+1k"),
+            (0, "3w+1u+1k"),
+            (0, "2r+1k"),
+            (0, "2u+2k"),
+            (0, "3u"),
+            (1, "4g"),
+        ],
+        ResourceKind::Green => vec![
+            (0, "1w+1u+1r+1k"),
+            (0, "1w+1u+1r+2k"),
+            (0, "1u+2r+2k"),
+            (0, "1w+3u+1g"),
+            (0, "2w+1u"),
+            (0, "2u+2r"),
+            (0, "3r"),
+            (1, "4k"),
+        ],
+        ResourceKind::Red => vec![
+            (0, "1w+1u+1g+1k"),
+            (0, "2w+1u+1g+1k"),
+            (0, "2w+1g+2k"),
+            (0, "1w+1r+3k"),
+            (0, "2u+1g"),
+            (0, "2w+2r"),
+            (0, "3w"),
+            (1, "4w"),
+        ],
+    ];
+    let deck1 = enum_map![
+        ResourceKind::Black => vec![
+            (1, "3w+2u+2g"),
+            (1, "3w+3g+2k"),
+            (2, "1u+4g+2r"),
+            (2, "5g+3r"),
+            (2, "5w"),
+            (3, "6k"),
+        ],
+        ResourceKind::Blue => vec![
+            (1, "2u+2g+3r"),
+            (1, "2u+3g+3k"),
+            (2, "5w+3u"),
+            (2, "2w+1r+4k"),
+            (2, "5u"),
+            (3, "6u"),
+        ],
+        ResourceKind::White => vec![
+            (1, "3g+2r+2k"),
+            (1, "2w+3u+3r"),
+            (2, "1g+4r+2k"),
+            (2, "5r+3k"),
+            (2, "5r"),
+            (3, "6w"),
+        ],
+        ResourceKind::Green => vec![
+            (1, "3w+2g+3r"),
+            (1, "2w+3u+2k"),
+            (2, "4w+2u+1k"),
+            (2, "5u+3g"),
+            (2, "5g"),
+            (3, "6g"),
+        ],
+        ResourceKind::Red => vec![
+            (1, "2w+2r+3k"),
+            (1, "3u+2r+3k"),
+            (2, "1w+4u+2g"),
+            (2, "3w+5k"),
+            (2, "5k"),
+            (3, "6r"),
+        ],
+    ];
+    let deck2 = enum_map![
+        ResourceKind::Black => vec![
+            (3, "3w+3u+5g+3r"),
+            (4, "7r"),
+            (4, "3g+6r+3k"),
+            (5, "7r+3k"),
+        ],
+        ResourceKind::Blue => vec![
+            (3, "3w+3g+3r+5k"),
+            (4, "7w"),
+            (4, "6w+3u+3k"),
+            (5, "7w+3u"),
+        ],
+        ResourceKind::White => vec![
+            (3, "3u+3g+5r+3k"),
+            (4, "7k"),
+            (4, "3w+3r+6k"),
+            (5, "3w+7k"),
+        ],
+        ResourceKind::Green => vec![
+            (3, "5w+3u+3r+3k"),
+            (4, "7u"),
+            (4, "3w+6u+3g"),
+            (5, "7u+3g"),
+        ],
+        ResourceKind::Red => vec![
+            (3, "3w+5u+3g+3k"),
+            (4, "7g"),
+            (4, "3u+6g+3r"),
+            (5, "7g+3r"),
+        ],
+    ];
+    [deck0, deck1, deck2]
+}
+
+/// Minimum score and cards of each city. Made up in the spirit of Cities of
+/// Splendor rather than copied from it.
+const CITIES: [(u8, &str); 7] = [
+    (11, "4r+4g"),
+    (11, "4u+4w"),
+    (11, "3k+3r+2u"),
+    (12, "3g+3u+3w"),
+    (12, "3k+3r+3g"),
+    (13, "5k+2w"),
+    (13, "2r+2u+2g+2w+2k"),
+];
+
+const NOBELS: [&str; 10] = [
+    "4r+4g", "4u+4w", "4k+4w", "4u+4g", "4k+4r", "3k+3r+3w", "3g+3u+3r", "3g+3u+3w", "3k+3u+3w",
+    "3k+3r+3g",
+];
+
+/// Cards in each deck of the official game.
+const DECK_SIZES: [usize; 3] = [40, 30, 20];
+
+/// Which official deck the card numbered `id` by [`standard_decks`] is from.
+pub fn tier_of(id: usize) -> Option<Tier> {
+    let mut end = 0;
+    let index = DECK_SIZES.iter().position(|size| {
+        end += size;
+        id < end
+    })?;
+    Some(Tier::ALL[index])
+}
+
+/// The three decks of the official game, cheapest first and not shuffled.
+/// Cards are numbered from 0 in this order.
+pub fn standard_decks() -> EnumMap<Tier, Vec<Card>> {
+    let mut next_id = 0;
+    EnumMap::from_array(tables().map(|d| {
+        d.into_iter()
+            .flat_map(|(c, l)| l.into_iter().map(move |(s, d)| (c, s, d)))
+            .map(|(c, s, d)| {
+                next_id += 1;
+                Card::new(next_id - 1, c, s, ResourceMap::from_code(d))
+            })
+            .collect()
+    }))
+}
+
+/// All ten nobels of the official game, not shuffled.
+pub fn standard_nobels() -> Vec<Objective> {
+    NOBELS
+        .iter()
+        .map(|x| Objective {
+            cost: ResourceMap::from_code(x),
+            score: 3,
+            min_score: None,
+            ends_game: false,
+        })
+        .collect()
+}
+
+/// The cities dealt in [`ObjectiveMode::Cities`](crate::ObjectiveMode::Cities),
+/// not shuffled. They give no points; claiming one ends the game.
+pub fn standard_cities() -> Vec<Objective> {
+    CITIES
+        .iter()
+        .map(|(min_score, x)| Objective {
+            cost: ResourceMap::from_code(x),
+            score: 0,
+            min_score: Some(*min_score),
+            ends_game: true,
+        })
+        .collect()
+}
+
+/// Cards and nobels to play with instead of the official ones, see
+/// [`GameConfig::cards`](crate::GameConfig::cards).
+///
+/// Read from a JSON file like `fixtures/cards/teaching.json`: three `tiers`,
+/// cheapest first, of cards with a `color`, `points` (0 when left out) and a
+/// `cost` in the code of [`ResourceMap::try_from_code`], and the `nobles`,
+/// with `points` and a `cost` in cards. Colors are codes or names. Cities
+/// stay the standard ones.
+///
+/// ```
+/// use game_def::{cards::CardSet, GameConfig, State, Tier};
+///
+/// let path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/cards/teaching.json");
+/// let set = CardSet::load(path).unwrap();
+/// assert_eq!(set.name, "teaching.json");
+/// assert_eq!(set.decks[Tier::One].len(), 15);
+/// assert_eq!(set.nobels[0].cost.to_string(), "3r+3k");
+///
+/// let config = GameConfig { cards: Some(set.into()), ..GameConfig::default() };
+/// let state = State::setup(&["a", "b"], config, &mut rand::thread_rng());
+/// assert_eq!(state.decks[Tier::Three].len(), 6);
+///
+/// let error = |json: &str| format!("{:#}", CardSet::parse("bad", json).unwrap_err());
+/// let card = |tier: &str| format!(r#"{{"tiers": [[], [], [{tier}]], "nobles": []}}"#);
+/// assert!(error(r#"{"tiers": [[], []], "nobles": []}"#).contains("3 tiers, not 2"));
+/// assert!(error(&card(r#"{"color": "x", "cost": "1r"}"#))
+///     .starts_with("Card 1 of tier 3: Unknown color \"x\""));
+/// assert_eq!(
+///     error(&card(r#"{"color": "r", "cost": "2w+0u"}"#)),
+///     "Card 1 of tier 3: Cost \"2w+0u\" has a count that isn't positive"
+/// );
+/// assert!(error(&card(r#"{"color": "r", "cost": "-1w"}"#)).contains("invalid count \"-1\""));
+/// assert!(error(&card(r#"{"color": "r", "cost": ""}"#)).contains("costs nothing"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CardSet {
+    /// The name of the file, to tell results apart.
+    pub name: String,
+    /// FNV-1a of the file, in hex, for the same.
+    pub hash: String,
+    pub decks: EnumMap<Tier, Vec<Card>>,
+    pub nobels: Vec<Objective>,
+}
+
+/// What the file holds.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CardFile {
+    tiers: Vec<Vec<CardEntry>>,
+    nobles: Vec<NobleEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CardEntry {
+    color: String,
+    #[serde(default)]
+    points: u8,
+    cost: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NobleEntry {
+    points: u8,
+    cost: String,
+}
+
+/// Parses a cost, which has to be something and can't count a color zero
+/// times.
+fn cost(code: &str) -> anyhow::Result<ResourceMap> {
+    let cost = ResourceMap::try_from_code(code)?;
+    if cost.sum() == 0 {
+        bail!("Cost {code:?} costs nothing");
+    }
+    if cost.iter().filter(|(_, n)| **n > 0).count() != code.split('+').count() {
+        bail!("Cost {code:?} has a count that isn't positive");
+    }
+    Ok(cost)
+}
+
+impl CardSet {
+    pub fn load(path: &str) -> anyhow::Result<CardSet> {
+        let json =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+        let name = std::path::Path::new(path)
+            .file_name()
+            .map_or(path.into(), |name| name.to_string_lossy());
+        Self::parse(&name, &json).with_context(|| format!("Invalid cards in {path}"))
+    }
+
+    /// Reads the JSON of a card file called `name`. Cards are numbered from 0
+    /// in the order they come.
+    pub fn parse(name: &str, json: &str) -> anyhow::Result<CardSet> {
+        let file: CardFile = serde_json::from_str(json)?;
+        if file.tiers.len() != 3 {
+            bail!("A card file needs 3 tiers, not {}", file.tiers.len());
+        }
+        let mut decks = EnumMap::<Tier, Vec<Card>>::default();
+        let mut next_id = 0;
+        for (tier, entries) in Tier::ALL.into_iter().zip(&file.tiers) {
+            for (i, entry) in entries.iter().enumerate() {
+                let card = entry
+                    .color
+                    .parse()
+                    .and_then(|color| {
+                        Ok(Card::new(next_id, color, entry.points, cost(&entry.cost)?))
+                    })
+                    .with_context(|| format!("Card {} of tier {tier}", i + 1))?;
+                decks[tier].push(card);
+                next_id += 1;
+            }
+        }
+        let nobels = file
+            .nobles
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                Ok(Objective {
+                    cost: cost(&entry.cost).with_context(|| format!("Noble {}", i + 1))?,
+                    score: entry.points,
+                    min_score: None,
+                    ends_game: false,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        // FNV-1a, which stays the same across Rust versions unlike the std hasher.
+        let hash = json.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        });
+        Ok(CardSet {
+            name: name.to_owned(),
+            hash: format!("{hash:016x}"),
+            decks,
+            nobels,
+        })
+    }
+
+    /// Which deck the card numbered `id` is from.
+    pub fn tier_of(&self, id: usize) -> Option<Tier> {
+        let mut end = 0;
+        Tier::ALL.into_iter().find(|&tier| {
+            end += self.decks[tier].len();
+            id < end
+        })
+    }
+}
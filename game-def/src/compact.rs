@@ -0,0 +1,110 @@
+//! [`State::render_compact`]: a few lines per move instead of the whole
+//! board, for games too fast to read board by board.
+
+use std::fmt::Write;
+
+use crate::{diff, Card, CoinMap, ResourceKind, ResourceMap, State};
+
+/// Like `red 4→3, gold 0→1`, the colors of `after` that differ from `before`.
+fn coin_changes(before: &CoinMap, after: &CoinMap) -> Vec<String> {
+    let mut changes = card_changes(&before.gems, &after.gems);
+    if before.gold != after.gold {
+        changes.push(format!("gold {}→{}", before.gold, after.gold));
+    }
+    changes
+}
+
+fn card_changes(before: &ResourceMap, after: &ResourceMap) -> Vec<String> {
+    ResourceKind::ALL
+        .iter()
+        .filter(|&&kind| before[kind] != after[kind])
+        .map(|&kind| format!("{kind} {}→{}", before[kind], after[kind]))
+        .collect()
+}
+
+/// Like `card 7 (1 point, red, 4w)`.
+fn card(card: &Card) -> String {
+    let points = match card.score {
+        0 => String::new(),
+        1 => "1 point, ".to_owned(),
+        n => format!("{n} points, "),
+    };
+    let color = card.color().map(|c| format!("{c}, ")).unwrap_or_default();
+    format!("card {} ({points}{color}{})", card.id, card.cost)
+}
+
+impl State {
+    /// What the last move changed on the board, `before` being the state
+    /// it was played in: the rows of [`State::render`] that changed, with
+    /// `old→new` values, then the scores and whose turn it is.
+    ///
+    /// ```
+    /// use game_def::{Action, State};
+    ///
+    /// let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/render");
+    /// let json = std::fs::read_to_string(format!("{dir}/state.json")).unwrap();
+    /// let before: State = serde_json::from_str(&json).unwrap();
+    /// let mut taken = before.clone();
+    /// taken.run("take r u g".parse::<Action>().unwrap()).unwrap();
+    /// let mut bought = taken.clone();
+    /// bought.run("buy 1.3".parse::<Action>().unwrap()).unwrap();
+    /// let expected = std::fs::read_to_string(format!("{dir}/compact.txt")).unwrap();
+    /// let compact = taken.render_compact(&before) + &bought.render_compact(&taken);
+    /// assert_eq!(compact, expected);
+    /// ```
+    pub fn render_compact(&self, before: &State) -> String {
+        let mut out = String::new();
+        let d = diff(before, self);
+        let bank = coin_changes(&before.coins, &self.coins);
+        if !bank.is_empty() {
+            writeln!(out, "   Bank: {}", bank.join(", ")).unwrap();
+        }
+        for (i, (p, pd)) in self.players.iter().zip(&d.players).enumerate() {
+            let Some(b) = before.players.get(i) else {
+                continue;
+            };
+            let name = &p.display_name;
+            let coins = coin_changes(&b.mortal, &p.mortal);
+            if !coins.is_empty() {
+                writeln!(out, "   {name} coins: {}", coins.join(", ")).unwrap();
+            }
+            let cards = card_changes(&b.immortal, &p.immortal);
+            if !cards.is_empty() {
+                writeln!(out, "   {name} cards: {}", cards.join(", ")).unwrap();
+            }
+            for c in &pd.reserved {
+                writeln!(out, "   {name} reserved {}", card(c)).unwrap();
+            }
+            for c in &pd.unreserved {
+                writeln!(out, "   {name} bought reserved {}", card(c)).unwrap();
+            }
+            if p.resigned && !b.resigned {
+                writeln!(out, "   {name} resigned").unwrap();
+            }
+        }
+        for s in &d.slots {
+            let new = s.replaced_by.as_ref().map_or("empty".to_owned(), card);
+            let old = card(&s.card);
+            writeln!(out, "   Deck {} card {}: {old} → {new}", s.deck, s.slot).unwrap();
+        }
+        for n in &d.nobels {
+            match n.player.and_then(|i| self.players.get(i)) {
+                Some(p) => writeln!(out, "   Nobel {} → {}", n.nobel.cost, p.display_name),
+                None => writeln!(out, "   Nobel {} left", n.nobel.cost),
+            }
+            .unwrap();
+        }
+        let scores: Vec<String> = self
+            .players
+            .iter()
+            .zip(&before.players)
+            .map(|(p, b)| match p.score == b.score {
+                true => format!("{} {}", p.display_name, p.score),
+                false => format!("{} {}→{}", p.display_name, b.score, p.score),
+            })
+            .collect();
+        writeln!(out, "   Scores: {}", scores.join(", ")).unwrap();
+        self.render_turn(&mut out);
+        out
+    }
+}
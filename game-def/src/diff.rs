@@ -0,0 +1,269 @@
+//! What changed between two states, worked out from the states alone so it
+//! also works on snapshots loaded from files.
+
+use std::fmt::Display;
+
+use enum_map::EnumMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{Card, CoinMap, Objective, ResourceKind, ResourceMap, State, Tier};
+
+/// Signed change of a coin pile.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoinDelta {
+    pub gems: EnumMap<ResourceKind, i32>,
+    pub gold: i32,
+}
+
+impl CoinDelta {
+    fn between(before: &CoinMap, after: &CoinMap) -> Self {
+        CoinDelta {
+            gems: EnumMap::from_fn(|c| after[c] as i32 - before[c] as i32),
+            gold: after.gold as i32 - before.gold as i32,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.gold == 0 && self.gems.values().all(|v| *v == 0)
+    }
+}
+
+/// Like `+1r+1g-2u`, with `+1 gold` after the colors.
+impl Display for CoinDelta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut colors = false;
+        for (c, v) in &self.gems {
+            if *v != 0 {
+                write!(f, "{v:+}{}", c.code())?;
+                colors = true;
+            }
+        }
+        if colors && self.gold != 0 {
+            write!(f, " ")?;
+        }
+        match self.gold {
+            0 => Ok(()),
+            n => write!(f, "{n:+} gold"),
+        }
+    }
+}
+
+/// How one player changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerDiff {
+    pub display_name: String,
+    pub coins: CoinDelta,
+    /// Card discounts gained, one per card bought.
+    pub cards: ResourceMap,
+    pub score: i32,
+    pub reserved: Vec<Card>,
+    /// Reserved cards no longer held, which means they were bought.
+    pub unreserved: Vec<Card>,
+}
+
+impl PlayerDiff {
+    pub fn is_empty(&self) -> bool {
+        self.coins.is_zero()
+            && self.cards.sum() == 0
+            && self.score == 0
+            && self.reserved.is_empty()
+            && self.unreserved.is_empty()
+    }
+}
+
+/// A face up card that left the board.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotChange {
+    pub deck: Tier,
+    /// Where the card was in `before`.
+    pub slot: usize,
+    pub card: Card,
+    /// The new card dealt into the slot, if any. A card moving down from a
+    /// later slot once the pile is empty doesn't count.
+    pub replaced_by: Option<Card>,
+}
+
+/// A nobel that left the board.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NobelClaim {
+    pub nobel: Objective,
+    /// Who got it, when [`PlayerRecord`](crate::PlayerRecord)s show it.
+    pub player: Option<usize>,
+}
+
+/// Everything [`diff`] found. The `Display` form is a one line summary.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDiff {
+    pub bank: CoinDelta,
+    /// One per player, in seat order.
+    pub players: Vec<PlayerDiff>,
+    pub slots: Vec<SlotChange>,
+    pub nobels: Vec<NobelClaim>,
+}
+
+/// What changed from `before` to `after`, which should be the same game
+/// with the same players.
+///
+/// ```
+/// use game_def::{diff, Action, GameConfig, State};
+///
+/// let before = State::setup(&["a", "b"], GameConfig::default(), &mut rand::thread_rng());
+/// let mut after = before.clone();
+/// after.run("take r g u".parse::<Action>().unwrap()).unwrap();
+/// let d = diff(&before, &after);
+/// assert_eq!(d.players[0].coins.to_string(), "+1r+1u+1g");
+/// assert_eq!(d.bank.to_string(), "-1r-1u-1g");
+/// assert!(d.players[1].is_empty() && d.slots.is_empty());
+/// ```
+pub fn diff(before: &State, after: &State) -> StateDiff {
+    let players = after
+        .players
+        .iter()
+        .enumerate()
+        .map(|(i, a)| {
+            let Some(b) = before.players.get(i) else {
+                return PlayerDiff {
+                    display_name: a.display_name.clone(),
+                    coins: CoinDelta::between(&CoinMap::new(), &a.mortal),
+                    cards: a.immortal.clone(),
+                    score: a.score.into(),
+                    reserved: a.reserved.clone(),
+                    unreserved: vec![],
+                };
+            };
+            PlayerDiff {
+                display_name: a.display_name.clone(),
+                coins: CoinDelta::between(&b.mortal, &a.mortal),
+                cards: a.immortal.clone() - b.immortal.clone(),
+                score: i32::from(a.score) - i32::from(b.score),
+                reserved: missing_from(&a.reserved, &b.reserved),
+                unreserved: missing_from(&b.reserved, &a.reserved),
+            }
+        })
+        .collect();
+    let visible = |s: &State, t: Tier| -> Vec<Card> {
+        s.decks[t]
+            .iter()
+            .take(s.config.visible_cards)
+            .cloned()
+            .collect()
+    };
+    let mut slots = vec![];
+    for tier in Tier::ALL {
+        let (old, new) = (visible(before, tier), visible(after, tier));
+        for (slot, card) in old.iter().enumerate() {
+            if new.iter().any(|c| c.id == card.id) {
+                continue;
+            }
+            let replaced_by = new
+                .get(slot)
+                .filter(|c| !old.iter().any(|o| o.id == c.id))
+                .cloned();
+            slots.push(SlotChange {
+                deck: tier,
+                slot,
+                card: card.clone(),
+                replaced_by,
+            });
+        }
+    }
+    let claimants: Vec<usize> = after
+        .players
+        .iter()
+        .enumerate()
+        .filter(|(i, p)| {
+            before
+                .players
+                .get(*i)
+                .is_some_and(|b| p.record.nobels > b.record.nobels)
+        })
+        .map(|(i, _)| i)
+        .collect();
+    let mut left = after.nobels.clone();
+    let mut nobels = vec![];
+    for nobel in &before.nobels {
+        match left.iter().position(|n| n == nobel) {
+            Some(i) => {
+                left.remove(i);
+            }
+            None => nobels.push(NobelClaim {
+                nobel: nobel.clone(),
+                player: None,
+            }),
+        }
+    }
+    // Attribution is only certain when one player took one nobel, which is
+    // all a single action can do.
+    if let ([claim], [player]) = (&mut nobels[..], &claimants[..]) {
+        claim.player = Some(*player);
+    }
+    StateDiff {
+        bank: CoinDelta::between(&before.coins, &after.coins),
+        players,
+        slots,
+        nobels,
+    }
+}
+
+/// Cards in `cards` whose id is not in `other`.
+fn missing_from(cards: &[Card], other: &[Card]) -> Vec<Card> {
+    cards
+        .iter()
+        .filter(|c| !other.iter().any(|o| o.id == c.id))
+        .cloned()
+        .collect()
+}
+
+/// Like `a +1r+1g, cards +1u, score +1; deck 1 slot 2: 12 -> 30`. Cards are
+/// named by id.
+impl Display for StateDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ids = |cards: &[Card]| {
+            cards
+                .iter()
+                .map(|c| c.id.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let mut parts = vec![];
+        for p in self.players.iter().filter(|p| !p.is_empty()) {
+            let mut items = vec![];
+            if !p.coins.is_zero() {
+                items.push(p.coins.to_string());
+            }
+            if p.cards.sum() > 0 {
+                items.push(format!("cards +{}", p.cards));
+            }
+            if p.score != 0 {
+                items.push(format!("score {:+}", p.score));
+            }
+            if !p.reserved.is_empty() {
+                items.push(format!("reserved {}", ids(&p.reserved)));
+            }
+            if !p.unreserved.is_empty() {
+                items.push(format!("bought reserved {}", ids(&p.unreserved)));
+            }
+            parts.push(format!("{} {}", p.display_name, items.join(", ")));
+        }
+        for s in &self.slots {
+            let new = s
+                .replaced_by
+                .as_ref()
+                .map_or("empty".to_owned(), |c| c.id.to_string());
+            parts.push(format!(
+                "deck {} slot {}: {} -> {new}",
+                s.deck, s.slot, s.card.id
+            ));
+        }
+        for n in &self.nobels {
+            match n.player.and_then(|i| self.players.get(i)) {
+                Some(p) => parts.push(format!("nobel {} to {}", n.nobel.cost, p.display_name)),
+                None => parts.push(format!("nobel {} left", n.nobel.cost)),
+            }
+        }
+        if parts.is_empty() {
+            return write!(f, "no change");
+        }
+        write!(f, "{}", parts.join("; "))
+    }
+}
@@ -0,0 +1,81 @@
+//! Why [`State::run`](crate::State::run) refused an action.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{Action, ResourceKind, ResourceMap, Tier};
+
+/// A broken rule, returned by [`State::check_action`](crate::State::check_action),
+/// [`State::run`](crate::State::run) and [`State::apply`](crate::State::apply).
+/// Serialized with a `"type"` tag like [`Action`], which makes the tag a stable
+/// code for other languages.
+///
+/// ```
+/// use game_def::{Action, GameConfig, ResourceKind, RuleError, State};
+///
+/// let mut state = State::setup(&["a", "b"], GameConfig::default(), &mut rand::thread_rng());
+/// let (red, green) = (ResourceKind::Red, ResourceKind::Green);
+/// let take = Action::PickThree { one: red, two: red, three: green };
+/// let err = state.run(take).unwrap_err();
+/// assert_eq!(err, RuleError::DuplicateColors);
+/// assert_eq!(serde_json::to_string(&err).unwrap(), r#"{"type":"duplicate_colors"}"#);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleError {
+    #[error("Nothing to discard")]
+    NothingToDiscard,
+    #[error("No nobel to choose")]
+    NotYourChoice,
+    #[error("You have to discard down to {max_coins} coins first")]
+    MustDiscard { max_coins: usize },
+    #[error("You have to choose a nobel first")]
+    MustChooseNobel,
+    #[error("The same color is picked twice")]
+    DuplicateColors,
+    #[error("No coin of {color} is left")]
+    CoinUnavailable { color: ResourceKind },
+    #[error("Enough colors are left to pick three")]
+    ThreeColorsLeft,
+    #[error("No coins are left to take")]
+    NoCoinsLeft,
+    #[error("You should pick one coin of each of the {available} colors left")]
+    MustPickAllColors { available: usize },
+    #[error("At least {minimum} coins of {color} should remain to pick two")]
+    PickTwoUnavailable { color: ResourceKind, minimum: usize },
+    /// The slot is face down, or past the end of the deck.
+    #[error("There is no face up card in slot {card} of deck {deck}")]
+    InvalidSlot { deck: Tier, card: usize },
+    #[error("Card {expected} is not in slot {card} of deck {deck}, {found} is")]
+    WrongCard {
+        deck: Tier,
+        card: usize,
+        expected: usize,
+        found: usize,
+    },
+    #[error("You have no reserved card {index}")]
+    InvalidReservedIndex { index: usize },
+    /// What the cost still asks for of each color after cards and colored
+    /// coins, more than the player's gold covers.
+    #[error("You don't have enough resources, missing {missing} with {gold} gold")]
+    NotEnoughResources { missing: ResourceMap, gold: usize },
+    #[error("You can not pay with coins you don't have")]
+    PaymentNotHeld,
+    #[error("You are paying too many {color} coins")]
+    Overpaying { color: ResourceKind },
+    #[error("The payment needs exactly {needed} gold")]
+    WrongGold { needed: usize },
+    #[error("Reserve limit of {max_reserved} reached")]
+    ReserveLimit { max_reserved: usize },
+    #[error("No face down card left in deck {deck}")]
+    EmptyDeck { deck: Tier },
+    #[error("You can not discard coins you don't have")]
+    DiscardNotHeld,
+    /// Discards have to bring the hand back to exactly the limit.
+    #[error("You should discard exactly {extra} coins")]
+    HandLimit { extra: usize },
+    #[error("Nobel {index} can not visit you")]
+    NobelCannotVisit { index: usize },
+    #[error("You can only skip when there is nothing else to do, you can `{possible}`")]
+    SkipNotAllowed { possible: Action },
+}
@@ -0,0 +1,158 @@
+//! A fixed-length numeric view of a [`State`] for learned evaluations.
+//!
+//! Values are raw counts (or 0/1 flags), not normalized. Players are listed
+//! starting with the perspective player, then in turn order, so the same
+//! position looks the same to a model whichever seat it plays. Slots for
+//! players, nobels or cards that don't exist are all zero, so the length is
+//! the same for every game with the standard layout.
+//!
+//! The layout, in order:
+//! - per player slot: present flag, coins per color, wilds, cards per color,
+//!   score, reserved cards
+//! - per nobel slot: present flag, then cards each player slot still misses
+//! - bank: coins per color, wilds
+//! - per deck and face up slot: present flag, score, then whether each player
+//!   slot can afford it
+//! - whose turn it is, one flag per player slot
+
+use enum_map::Enum;
+
+use crate::{ResourceKind, ResourceMap, State, Tier};
+
+pub const MAX_PLAYERS: usize = 4;
+pub const MAX_NOBELS: usize = MAX_PLAYERS + 1;
+pub const DECKS: usize = Tier::LENGTH;
+/// Face up slots per deck. Cards beyond this are left out.
+pub const VISIBLE_CARDS: usize = 4;
+
+const COLORS: usize = ResourceKind::LENGTH;
+const PER_PLAYER: usize = 1 + COLORS + 1 + COLORS + 1 + 1;
+const PER_NOBEL: usize = 1 + MAX_PLAYERS;
+const BANK: usize = COLORS + 1;
+const PER_CARD: usize = 1 + 1 + MAX_PLAYERS;
+
+/// Length of [`State::features`] and [`feature_names`].
+pub const FEATURE_COUNT: usize = MAX_PLAYERS * PER_PLAYER
+    + MAX_NOBELS * PER_NOBEL
+    + BANK
+    + DECKS * VISIBLE_CARDS * PER_CARD
+    + MAX_PLAYERS;
+
+fn colors() -> impl Iterator<Item = ResourceKind> {
+    (0..COLORS).map(ResourceKind::from_usize)
+}
+
+fn push_map(out: &mut Vec<f32>, map: &ResourceMap) {
+    out.extend(map.iter().map(|(_, &v)| v as f32));
+}
+
+fn flag(b: bool) -> f32 {
+    if b {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// What each entry of [`State::features`] means, e.g. `p1.cards.g` for the
+/// green cards of the player after the perspective player.
+pub fn feature_names() -> Vec<String> {
+    let mut r = vec![];
+    for p in 0..MAX_PLAYERS {
+        r.push(format!("p{p}.present"));
+        r.extend(colors().map(|c| format!("p{p}.coins.{}", c.code())));
+        r.push(format!("p{p}.wilds"));
+        r.extend(colors().map(|c| format!("p{p}.cards.{}", c.code())));
+        r.push(format!("p{p}.score"));
+        r.push(format!("p{p}.reserved"));
+    }
+    for n in 0..MAX_NOBELS {
+        r.push(format!("nobel{n}.present"));
+        r.extend((0..MAX_PLAYERS).map(|p| format!("nobel{n}.distance.p{p}")));
+    }
+    r.extend(colors().map(|c| format!("bank.{}", c.code())));
+    r.push("bank.wilds".to_owned());
+    for d in 0..DECKS {
+        for s in 0..VISIBLE_CARDS {
+            r.push(format!("card{d}.{s}.present"));
+            r.push(format!("card{d}.{s}.score"));
+            r.extend((0..MAX_PLAYERS).map(|p| format!("card{d}.{s}.affordable.p{p}")));
+        }
+    }
+    r.extend((0..MAX_PLAYERS).map(|p| format!("turn.p{p}")));
+    debug_assert_eq!(r.len(), FEATURE_COUNT);
+    r
+}
+
+impl State {
+    /// The state as seen from `perspective`, see the [module docs](crate::features)
+    /// for the layout. Always [`FEATURE_COUNT`] long.
+    pub fn features(&self, perspective: usize) -> Vec<f32> {
+        let mut r = Vec::with_capacity(FEATURE_COUNT);
+        self.features_into(perspective, &mut r);
+        r
+    }
+
+    /// Like [`State::features`], into `r` instead of a new vector, which
+    /// allocates nothing once `r` has room for [`FEATURE_COUNT`].
+    ///
+    /// ```
+    /// use game_def::{features::FEATURE_COUNT, GameConfig, State};
+    ///
+    /// let state = State::setup(&["a", "b"], GameConfig::default(), &mut rand::thread_rng());
+    /// let mut r = vec![7.0];
+    /// state.features_into(1, &mut r);
+    /// assert_eq!(r.len(), FEATURE_COUNT);
+    /// assert_eq!(r, state.features(1));
+    /// ```
+    pub fn features_into(&self, perspective: usize, r: &mut Vec<f32>) {
+        let n = self.players.len();
+        let seats: [Option<usize>; MAX_PLAYERS] =
+            std::array::from_fn(|p| (p < n).then(|| (perspective + p) % n));
+        r.clear();
+        for seat in &seats {
+            match seat {
+                Some(i) => {
+                    let player = &self.players[*i];
+                    r.push(1.0);
+                    push_map(r, &player.mortal.gems);
+                    r.push(player.mortal.gold as f32);
+                    push_map(r, &player.immortal);
+                    r.push(player.score as f32);
+                    r.push(player.reserved.len() as f32);
+                }
+                None => r.extend([0.0; PER_PLAYER]),
+            }
+        }
+        for i in 0..MAX_NOBELS {
+            let Some(nobel) = self.nobels.get(i) else {
+                r.extend([0.0; PER_NOBEL]);
+                continue;
+            };
+            r.push(1.0);
+            r.extend(seats.iter().map(|seat| {
+                seat.map_or(0.0, |p| {
+                    self.players[p].immortal.deficit(&nobel.cost).sum() as f32
+                })
+            }));
+        }
+        push_map(r, &self.coins.gems);
+        r.push(self.coins.gold as f32);
+        for deck in self.decks.values() {
+            for s in 0..VISIBLE_CARDS {
+                let card = deck.get(s).filter(|_| s < self.config.visible_cards);
+                let Some(card) = card else {
+                    r.extend([0.0; PER_CARD]);
+                    continue;
+                };
+                r.push(1.0);
+                r.push(card.score as f32);
+                r.extend(seats.iter().map(|seat| {
+                    seat.map_or(0.0, |p| flag(self.players[p].can_purchase(&card.cost)))
+                }));
+            }
+        }
+        r.extend(seats.iter().map(|seat| flag(*seat == Some(self.turn))));
+        debug_assert_eq!(r.len(), FEATURE_COUNT);
+    }
+}
@@ -0,0 +1,104 @@
+//! Random inputs for fuzzing agents and the rules.
+//!
+//! `rng.gen::<Action>()` gives an action of any kind with small, plausible
+//! fields, legal or not; [`random_reachable_state`] gives a position an
+//! actual game could reach.
+
+use enum_map::Enum;
+use rand::{
+    distributions::{Distribution, Standard},
+    Rng,
+};
+
+use crate::{Action, GameConfig, ResourceKind, ResourceMap, State, Tier};
+
+impl Distribution<ResourceKind> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> ResourceKind {
+        ResourceKind::from_usize(rng.gen_range(0..ResourceKind::LENGTH))
+    }
+}
+
+impl Distribution<Tier> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Tier {
+        Tier::from_usize(rng.gen_range(0..Tier::LENGTH))
+    }
+}
+
+/// Up to 3 of each color.
+impl Distribution<ResourceMap> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> ResourceMap {
+        let mut r = ResourceMap::new();
+        for (_, v) in &mut r {
+            *v = rng.gen_range(0..=3);
+        }
+        r
+    }
+}
+
+/// Indices are mostly in range for a standard game and sometimes one past it.
+impl Distribution<Action> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Action {
+        let deck = rng.gen();
+        let card = rng.gen_range(0..=4);
+        let index = rng.gen_range(0..=3);
+        let payment = rng.gen_bool(0.3).then(|| rng.gen());
+        let wilds = rng.gen_bool(0.3).then(|| rng.gen_range(0..=3));
+        let id = rng.gen_bool(0.3).then(|| rng.gen_range(0..90));
+        match rng.gen_range(0..11) {
+            0 => Action::PickThree {
+                one: rng.gen(),
+                two: rng.gen(),
+                three: rng.gen(),
+            },
+            1 => Action::PickTwo { color: rng.gen() },
+            2 => Action::PickFewer {
+                colors: (0..rng.gen_range(0..=2)).map(|_| rng.gen()).collect(),
+            },
+            3 => Action::Purchase {
+                deck,
+                card,
+                payment,
+                wilds,
+                id,
+            },
+            4 => Action::PurchaseReserved {
+                index,
+                payment,
+                wilds,
+            },
+            5 => Action::Reserve { deck, card, id },
+            6 => Action::ReserveBlind { deck },
+            7 => Action::Discard {
+                coins: rng.gen(),
+                wilds: rng.gen_range(0..=1),
+            },
+            8 => Action::ChooseNobel { index },
+            9 => Action::Skip,
+            _ => Action::Resign,
+        }
+    }
+}
+
+/// A standard game for 2 to 4 players after up to `moves` uniformly random
+/// legal actions, fewer if it ends first. Nobody resigns, which would end
+/// most games within a few moves.
+pub fn random_reachable_state(rng: &mut impl Rng, moves: usize) -> State {
+    let names = ["a", "b", "c", "d"];
+    let players = rng.gen_range(2..=names.len());
+    let mut state = State::setup(&names[..players], GameConfig::default(), rng);
+    let mut actions = vec![];
+    for _ in 0..moves {
+        if state.is_finished() {
+            break;
+        }
+        actions.clear();
+        state.legal_actions_into(&mut actions);
+        // Resign is always there and last, after at least one other action.
+        actions.pop();
+        let action = actions.swap_remove(rng.gen_range(0..actions.len()));
+        state
+            .run(action)
+            .expect("legal_actions yielded an action run rejects");
+    }
+    state
+}
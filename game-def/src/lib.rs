@@ -1,14 +1,66 @@
 use std::{
-    fmt::Debug,
-    ops::{Index, IndexMut},
+    collections::BTreeMap,
+    fmt::{Debug, Display},
+    ops::{Add, AddAssign, Deref, DerefMut, Index, IndexMut, Mul, Sub, SubAssign},
+    str::FromStr,
+    sync::Arc,
 };
 
 use anyhow::{bail, Context};
-use clap::ValueEnum;
-use enum_map::{enum_map, Enum, EnumMap};
+use enum_map::{Enum, EnumMap};
+use rand::{seq::SliceRandom, Rng};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Enum, ValueEnum, Serialize, Deserialize)]
+mod agent;
+pub mod analysis;
+pub mod cards;
+mod compact;
+mod diff;
+mod error;
+pub mod features;
+#[cfg(feature = "arbitrary")]
+pub mod fuzz;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+pub mod notation;
+mod observation;
+mod perft;
+mod playout;
+#[cfg(feature = "color")]
+mod pretty;
+mod protocol;
+#[cfg(feature = "cli")]
+pub mod repl;
+mod replay;
+pub mod swiss;
+pub mod transcript;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+pub mod zobrist;
+
+pub use agent::{play_game, record_game, Agent, Handled, RandomAgent};
+pub use diff::{diff, CoinDelta, NobelClaim, PlayerDiff, SlotChange, StateDiff};
+pub use error::RuleError;
+pub use observation::{Analysis, Observation};
+pub use perft::{perft, perft_divide};
+pub use playout::PlayoutResult;
+#[cfg(feature = "stdio")]
+pub use protocol::{run_agent, run_agent_over_tcp};
+pub use protocol::{
+    run_agent_over_io, AgentHandler, AgentMessage, Hello, ObserverMessage, RunnerMessage,
+};
+pub use replay::{Forfeit, InvalidAttempt, Replay, Restart, Substitution, Timing, Undone};
+
+/// Version of the JSON the runner and agents exchange. Bumped on any change
+/// to the serialized form of [`Observation`], [`State`] or [`Action`].
+pub const PROTOCOL_VERSION: u32 = 6;
+
+/// Serialized as the lowercase name, read back from anything [`FromStr`]
+/// accepts, which includes the capitalized names older versions wrote.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Enum, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ResourceKind {
     Red,
     Blue,
@@ -18,19 +70,155 @@ pub enum ResourceKind {
 }
 
 impl ResourceKind {
-    fn from_code(code: &str) -> Self {
-        match code {
+    pub const ALL: [ResourceKind; 5] = [
+        ResourceKind::Red,
+        ResourceKind::Blue,
+        ResourceKind::Green,
+        ResourceKind::White,
+        ResourceKind::Black,
+    ];
+
+    /// The one letter code used in [`ResourceMap`] codes.
+    pub fn code(self) -> char {
+        match self {
+            ResourceKind::Green => 'g',
+            ResourceKind::Red => 'r',
+            ResourceKind::White => 'w',
+            ResourceKind::Black => 'k',
+            ResourceKind::Blue => 'u',
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        Some(match code {
             "g" => ResourceKind::Green,
             "r" => ResourceKind::Red,
             "w" => ResourceKind::White,
             "k" => ResourceKind::Black,
             "u" => ResourceKind::Blue,
-            _ => unreachable!(),
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ResourceKind::Red => "red",
+            ResourceKind::Blue => "blue",
+            ResourceKind::Green => "green",
+            ResourceKind::White => "white",
+            ResourceKind::Black => "black",
         }
     }
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+/// The lowercase name.
+impl Display for ResourceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The one letter code or the name, in any case. `b` is neither blue nor
+/// black, whose codes are `u` and `k`.
+///
+/// ```
+/// use game_def::ResourceKind;
+///
+/// for text in ["u", "U", "blue", "Blue", "BLUE"] {
+///     assert_eq!(text.parse::<ResourceKind>().unwrap(), ResourceKind::Blue);
+/// }
+/// for text in ["b", "", "bl", "blu", "red ", "x", "rg"] {
+///     assert!(text.parse::<ResourceKind>().is_err());
+/// }
+/// ```
+impl FromStr for ResourceKind {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> anyhow::Result<Self> {
+        let lower = text.to_ascii_lowercase();
+        ResourceKind::from_code(&lower)
+            .or_else(|| ResourceKind::ALL.into_iter().find(|c| c.name() == lower))
+            .with_context(|| {
+                format!("Unknown color {text:?}, expected one of r, u, g, w, k or a color name")
+            })
+    }
+}
+
+impl<'de> Deserialize<'de> for ResourceKind {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = ResourceKind;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a color")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, text: &str) -> Result<ResourceKind, E> {
+                text.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(Visitor)
+    }
+}
+
+/// Writes the colors of an [`Action`] as their one letter [`ResourceKind::code`].
+/// Like any [`ResourceKind`], they are read back from codes or names.
+mod color_code {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::ResourceKind;
+
+    struct Code(ResourceKind);
+
+    impl Serialize for Code {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(self.0.code().encode_utf8(&mut [0; 4]))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Code {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            ResourceKind::deserialize(deserializer).map(Code)
+        }
+    }
+
+    pub fn serialize<S: Serializer>(
+        color: &ResourceKind,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        Code(*color).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<ResourceKind, D::Error> {
+        Code::deserialize(deserializer).map(|c| c.0)
+    }
+
+    /// The same for a list of colors.
+    pub mod list {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(
+            colors: &[ResourceKind],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(colors.iter().map(|c| Code(*c)))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<ResourceKind>, D::Error> {
+            let codes = Vec::<Code>::deserialize(deserializer)?;
+            Ok(codes.into_iter().map(|c| c.0).collect())
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ResourceMap(pub EnumMap<ResourceKind, usize>);
 
 impl Debug for ResourceMap {
@@ -46,34 +234,231 @@ impl Debug for ResourceMap {
     }
 }
 
+impl Default for ResourceMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ResourceMap {
     pub fn new() -> Self {
-        ResourceMap(enum_map! {
-            ResourceKind::Red => 0,
-            ResourceKind::Blue => 0,
-            ResourceKind::Green => 0,
-            ResourceKind::White => 0,
-            ResourceKind::Black => 0,
-        })
+        Self::uniform(0)
     }
 
-    pub fn add(&mut self, adds: &ResourceMap) {
-        for (r, x) in &adds.0 {
-            self.0[r] += *x;
-        }
+    /// `n` of every color, like the bank at the start of a game.
+    ///
+    /// ```
+    /// use game_def::{ResourceKind, ResourceMap};
+    ///
+    /// let bank = ResourceMap::uniform(7);
+    /// assert_eq!(bank[ResourceKind::Green], 7);
+    /// assert_eq!(bank.sum(), 35);
+    /// ```
+    pub fn uniform(n: usize) -> Self {
+        ResourceMap(EnumMap::from_fn(|_| n))
     }
 
+    /// Every color with its count, zeros included, in [`ResourceKind`] order.
+    ///
+    /// ```
+    /// use game_def::{ResourceKind, ResourceMap};
+    ///
+    /// let cost = ResourceMap::from_code("3w+2k");
+    /// let colors: Vec<_> = cost.iter().filter(|(_, n)| **n > 0).map(|(c, _)| c).collect();
+    /// assert_eq!(colors, [ResourceKind::White, ResourceKind::Black]);
+    /// ```
+    pub fn iter(&self) -> enum_map::Iter<'_, ResourceKind, usize> {
+        self.0.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> enum_map::IterMut<'_, ResourceKind, usize> {
+        self.0.iter_mut()
+    }
+
+    /// Like [`ResourceMap::try_from_code`], but panics on a bad code. Meant for
+    /// the built in card tables.
     pub fn from_code(code: &str) -> Self {
+        Self::try_from_code(code).unwrap()
+    }
+
+    /// Parses codes like `2w+1u`. Colors are `r`, `u` (blue), `g`, `w` and
+    /// `k` (black); each color may appear once. The empty string is the empty map.
+    ///
+    /// ```
+    /// use game_def::{ResourceKind, ResourceMap};
+    ///
+    /// let map = ResourceMap::try_from_code("2w+1u").unwrap();
+    /// assert_eq!((map[ResourceKind::White], map[ResourceKind::Blue]), (2, 1));
+    /// for code in ["2", "2x", "w", "2w+1w", "2é", "é", "2r+é", "+"] {
+    ///     assert!(ResourceMap::try_from_code(code).is_err(), "{code}");
+    /// }
+    /// ```
+    pub fn try_from_code(code: &str) -> anyhow::Result<Self> {
         let mut this = Self::new();
-        for c in code.split("+") {
-            let (num, color) = c.split_at(1);
-            this.0[ResourceKind::from_code(color)] = num.parse().unwrap();
+        if code.is_empty() {
+            return Ok(this);
+        }
+        let mut seen = vec![];
+        for c in code.split('+') {
+            let (num, color) = c.split_at(c.char_indices().last().map_or(0, |(k, _)| k));
+            let color = ResourceKind::from_code(color)
+                .with_context(|| format!("unknown color {color:?} in {code:?}"))?;
+            if seen.contains(&color) {
+                bail!("color {:?} appears twice in {code:?}", color.code());
+            }
+            seen.push(color);
+            this.0[color] = num
+                .parse()
+                .with_context(|| format!("invalid count {num:?} in {code:?}"))?;
         }
-        this
+        Ok(this)
     }
-    
+
+    /// The code [`ResourceMap::try_from_code`] parses back, skipping zero counts.
+    pub fn to_code(&self) -> String {
+        self.0
+            .iter()
+            .filter(|(_, v)| **v > 0)
+            .map(|(c, v)| format!("{v}{}", c.code()))
+            .collect::<Vec<_>>()
+            .join("+")
+    }
+
     pub fn sum(&self) -> i32 {
-        self.0.iter().map(|x| *x.1 as i32).sum()
+        self.0
+            .values()
+            .map(|x| i32::try_from(*x).unwrap_or(i32::MAX))
+            .fold(0, i32::saturating_add)
+    }
+
+    /// `None` if any color would go below zero.
+    pub fn checked_sub(&self, other: &ResourceMap) -> Option<ResourceMap> {
+        let mut r = self.clone();
+        for (c, v) in &other.0 {
+            r.0[c] = r.0[c].checked_sub(*v)?;
+        }
+        Some(r)
+    }
+
+    /// Whether there is at least as much of every color as in `other`.
+    pub fn covers(&self, other: &ResourceMap) -> bool {
+        other.0.iter().all(|(c, v)| self.0[c] >= *v)
+    }
+
+    /// What is missing from this to cover `cost`.
+    pub fn deficit(&self, cost: &ResourceMap) -> ResourceMap {
+        cost.clone() - self.clone()
+    }
+}
+
+impl Add for ResourceMap {
+    type Output = ResourceMap;
+
+    fn add(mut self, rhs: ResourceMap) -> ResourceMap {
+        self += rhs;
+        self
+    }
+}
+
+impl AddAssign for ResourceMap {
+    fn add_assign(&mut self, rhs: ResourceMap) {
+        *self += &rhs;
+    }
+}
+
+impl AddAssign<&ResourceMap> for ResourceMap {
+    fn add_assign(&mut self, rhs: &ResourceMap) {
+        for (r, x) in &rhs.0 {
+            self.0[r] += *x;
+        }
+    }
+}
+
+/// Saturates at zero; use [`ResourceMap::checked_sub`] to catch underflow.
+impl Sub for ResourceMap {
+    type Output = ResourceMap;
+
+    fn sub(mut self, rhs: ResourceMap) -> ResourceMap {
+        for (c, v) in &rhs.0 {
+            self.0[c] = self.0[c].saturating_sub(*v);
+        }
+        self
+    }
+}
+
+impl Mul<usize> for ResourceMap {
+    type Output = ResourceMap;
+
+    fn mul(mut self, rhs: usize) -> ResourceMap {
+        for v in self.0.values_mut() {
+            *v *= rhs;
+        }
+        self
+    }
+}
+
+impl FromStr for ResourceMap {
+    type Err = anyhow::Error;
+
+    fn from_str(code: &str) -> anyhow::Result<Self> {
+        Self::try_from_code(code)
+    }
+}
+
+impl Display for ResourceMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_code())
+    }
+}
+
+impl<'a> IntoIterator for &'a ResourceMap {
+    type Item = (ResourceKind, &'a usize);
+    type IntoIter = enum_map::Iter<'a, ResourceKind, usize>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut ResourceMap {
+    type Item = (ResourceKind, &'a mut usize);
+    type IntoIter = enum_map::IterMut<'a, ResourceKind, usize>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Colors left out are zero, and a color given more than once adds up.
+///
+/// ```
+/// use game_def::{ResourceKind, ResourceMap};
+///
+/// let picked = [ResourceKind::Red, ResourceKind::Blue, ResourceKind::Red];
+/// let coins: ResourceMap = picked.into_iter().map(|c| (c, 1)).collect();
+/// assert_eq!(coins, ResourceMap::from_code("2r+1u"));
+/// ```
+impl FromIterator<(ResourceKind, usize)> for ResourceMap {
+    fn from_iter<I: IntoIterator<Item = (ResourceKind, usize)>>(iter: I) -> Self {
+        let mut r = ResourceMap::new();
+        for (c, n) in iter {
+            r[c] += n;
+        }
+        r
+    }
+}
+
+/// Like collecting the pairs.
+///
+/// ```
+/// use game_def::{ResourceKind, ResourceMap};
+///
+/// let cost = ResourceMap::from([(ResourceKind::White, 3), (ResourceKind::Black, 2)]);
+/// assert_eq!(cost, ResourceMap::from_code("3w+2k"));
+/// ```
+impl<const N: usize> From<[(ResourceKind, usize); N]> for ResourceMap {
+    fn from(pairs: [(ResourceKind, usize); N]) -> Self {
+        pairs.into_iter().collect()
     }
 }
 
@@ -91,250 +476,2205 @@ impl IndexMut<ResourceKind> for ResourceMap {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A kind of coin: a gem of one of the five colors, or gold, the wild coin.
+/// Only coin piles hold gold; card costs and discounts are [`ResourceMap`]s.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Enum)]
+pub enum CoinKind {
+    Gem(ResourceKind),
+    Gold,
+}
+
+/// A pile of coins, like the bank or a player's hand. Serialized as one map,
+/// with `gold` next to the five colors.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoinMap {
+    #[serde(flatten)]
+    pub gems: ResourceMap,
+    pub gold: usize,
+}
+
+impl CoinMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every kind with its count, gold last.
+    pub fn iter(&self) -> impl Iterator<Item = (CoinKind, usize)> + '_ {
+        self.gems
+            .iter()
+            .map(|(c, n)| (CoinKind::Gem(c), *n))
+            .chain([(CoinKind::Gold, self.gold)])
+    }
+
+    /// All coins, gold included.
+    pub fn total(&self) -> usize {
+        self.iter().map(|(_, n)| n).sum()
+    }
+
+    /// Whether there are at least as many coins of every kind as in `other`.
+    pub fn covers(&self, other: &CoinMap) -> bool {
+        self.gems.covers(&other.gems) && self.gold >= other.gold
+    }
+}
+
+impl Debug for CoinMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut m = f.debug_map();
+        for (r, v) in &self.gems {
+            if *v > 0 {
+                m.entry(&r, v);
+            }
+        }
+        if self.gold > 0 {
+            m.entry(&format_args!("Gold"), &self.gold);
+        }
+        m.finish()
+    }
+}
+
+/// Like `2r+1u and 1 gold`, or `nothing`.
+impl Display for CoinMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let gems = self.gems.sum() > 0;
+        if gems {
+            write!(f, "{}", self.gems)?;
+        }
+        match (gems, self.gold) {
+            (false, 0) => write!(f, "nothing"),
+            (_, 0) => Ok(()),
+            (true, n) => write!(f, " and {n} gold"),
+            (false, n) => write!(f, "{n} gold"),
+        }
+    }
+}
+
+/// Panics if `rhs` has more of a kind than there is.
+impl SubAssign<&CoinMap> for CoinMap {
+    fn sub_assign(&mut self, rhs: &CoinMap) {
+        for (kind, n) in rhs.iter() {
+            self[kind] -= n;
+        }
+    }
+}
+
+impl AddAssign<&CoinMap> for CoinMap {
+    fn add_assign(&mut self, rhs: &CoinMap) {
+        for (kind, n) in rhs.iter() {
+            self[kind] += n;
+        }
+    }
+}
+
+impl Index<CoinKind> for CoinMap {
+    type Output = usize;
+
+    fn index(&self, index: CoinKind) -> &usize {
+        match index {
+            CoinKind::Gem(c) => &self.gems[c],
+            CoinKind::Gold => &self.gold,
+        }
+    }
+}
+
+impl IndexMut<CoinKind> for CoinMap {
+    fn index_mut(&mut self, index: CoinKind) -> &mut usize {
+        match index {
+            CoinKind::Gem(c) => &mut self.gems[c],
+            CoinKind::Gold => &mut self.gold,
+        }
+    }
+}
+
+impl Index<ResourceKind> for CoinMap {
+    type Output = usize;
+
+    fn index(&self, index: ResourceKind) -> &usize {
+        &self.gems[index]
+    }
+}
+
+impl IndexMut<ResourceKind> for CoinMap {
+    fn index_mut(&mut self, index: ResourceKind) -> &mut usize {
+        &mut self.gems[index]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Player {
-    pub mortal: ResourceMap,
+    /// Coins in hand.
+    pub mortal: CoinMap,
+    /// Discounts of the cards in `purchased`, kept alongside it.
     pub immortal: ResourceMap,
     pub score: u8,
+    /// Ids of the cards bought, in order. Empty in states saved before it
+    /// was added.
+    ///
+    /// ```
+    /// use game_def::{GameConfig, State};
+    ///
+    /// let mut state = State::setup(&["a", "b"], GameConfig::default(), &mut rand::thread_rng());
+    /// while !state.is_finished() {
+    ///     let action = state.legal_actions().next().unwrap();
+    ///     state.run(action).unwrap();
+    ///     for p in &state.players {
+    ///         assert_eq!(p.discounts(&state.config), Some(p.immortal.clone()));
+    ///     }
+    /// }
+    /// ```
+    #[serde(default)]
+    pub purchased: Vec<usize>,
     pub reserved: Vec<Card>,
-    pub wilds: usize,
+    /// Goes along with `reserved`: whether each card was taken face down from a deck.
+    #[serde(default)]
+    pub reserved_blind: Vec<bool>,
     pub display_name: String,
+    #[serde(default)]
+    pub record: PlayerRecord,
+    /// Out of the game after [`Action::Resign`]: holds no coins, is skipped
+    /// when turns pass and can't win.
+    #[serde(default)]
+    pub resigned: bool,
+}
+
+/// How a player got where they are, kept up to date by [`State::run`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerRecord {
+    pub card_points: u8,
+    pub nobel_points: u8,
+    /// Cards bought from each deck, reserved ones included.
+    pub cards_by_tier: [usize; 3],
+    pub nobels: usize,
+    /// Turns finished.
+    pub turns: usize,
+    /// Cities claimed, which are counted in `nobels` too.
+    #[serde(default)]
+    pub cities: usize,
+    /// Coins taken from the bank, leaving out the gold that comes with a
+    /// reserved card.
+    #[serde(default)]
+    pub coins_taken: usize,
+    /// Cards reserved, face up or blind.
+    #[serde(default)]
+    pub reserves: usize,
+    /// Gold paid for cards.
+    #[serde(default)]
+    pub wilds_spent: usize,
+}
+
+impl PlayerRecord {
+    fn bought(&mut self, card: &Card, tier: Option<Tier>) {
+        self.card_points += card.score;
+        if let Some(tier) = tier {
+            self.cards_by_tier[tier.into_usize()] += 1;
+        }
+    }
+}
+
+/// Where each player's points came from, see [`State::final_scores`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    pub card_points: u8,
+    pub noble_points: u8,
+    pub cards_by_tier: [usize; 3],
+    pub nobles_claimed: usize,
+    pub coins_left: usize,
+    pub wilds_left: usize,
+    pub turns_taken: usize,
+    pub coins_taken: usize,
+    pub reserves: usize,
+    pub wilds_spent: usize,
 }
 
 impl Player {
+    /// What `immortal` should be, worked out from `purchased`. `None` if it
+    /// holds an id that isn't one of the [`GameConfig::decks`] of `config`.
+    pub fn discounts(&self, config: &GameConfig) -> Option<ResourceMap> {
+        let decks = config.decks();
+        let all: Vec<&Card> = decks.values().flatten().collect();
+        let mut discounts = ResourceMap::new();
+        for &id in &self.purchased {
+            discounts += &all.get(id)?.adds;
+        }
+        Some(discounts)
+    }
+
     pub fn new(name: &str) -> Self {
         Player {
-            mortal: ResourceMap::new(),
+            mortal: CoinMap::new(),
             immortal: ResourceMap::new(),
             score: 0,
+            purchased: vec![],
             reserved: vec![],
-            wilds: 0,
+            reserved_blind: vec![],
             display_name: name.to_owned(),
+            record: PlayerRecord::default(),
+            resigned: false,
+        }
+    }
+    /// How this player would pay for `cost`: cards first, then colored coins,
+    /// then gold for whatever is left. `None` if they can't afford it.
+    pub fn payment_for(&self, cost: &ResourceMap) -> Option<CoinMap> {
+        let (missing, patched) = self.missing_for(cost);
+        let gold = missing.sum() as usize;
+        (gold == patched).then(|| CoinMap {
+            gems: self.immortal.deficit(cost) - missing,
+            gold,
+        })
+    }
+
+    /// What `cost` still asks for of each color once cards and colored coins
+    /// are used up, and how much of that the player's gold can cover.
+    pub fn missing_for(&self, cost: &ResourceMap) -> (ResourceMap, usize) {
+        let missing = self.mortal.gems.deficit(&self.immortal.deficit(cost));
+        let patched = (missing.sum() as usize).min(self.mortal.gold);
+        (missing, patched)
+    }
+
+    /// Checks that `payment` uses coins the player holds and covers exactly what
+    /// `cost` asks for after card discounts.
+    pub fn check_payment(&self, cost: &ResourceMap, payment: &CoinMap) -> Result<(), RuleError> {
+        if !self.mortal.covers(payment) {
+            return Err(RuleError::PaymentNotHeld);
         }
+        let mut needed = 0;
+        for (color, &t) in cost {
+            let t = t.saturating_sub(self.immortal[color]);
+            if payment[color] > t {
+                return Err(RuleError::Overpaying { color });
+            }
+            needed += t - payment[color];
+        }
+        if needed != payment.gold {
+            return Err(RuleError::WrongGold { needed });
+        }
+        Ok(())
     }
+
+    /// Moves a checked payment from the player to the bank.
+    pub fn pay(&mut self, payment: &CoinMap, bank: &mut CoinMap) {
+        self.mortal -= payment;
+        *bank += payment;
+    }
+
+    /// Pays for `cost` into the bank. Nothing changes if the player can't afford it.
     pub fn purchase(
         &mut self,
         cost: &ResourceMap,
-        state_coins: &mut ResourceMap,
-        state_wilds: &mut usize,
-    ) -> anyhow::Result<()> {
-        for (r, &t) in &cost.0 {
-            let t = t.saturating_sub(self.immortal[r]);
-            if self.mortal[r] < t {
-                if self.wilds < t - self.mortal[r] {
-                    bail!("Not enough resources");
-                }
-                *state_wilds += t - self.mortal[r];
-                self.wilds -= t - self.mortal[r];
-                state_coins[r] += self.mortal[r];
-                self.mortal[r] = 0;
-            } else {
-                self.mortal[r] -= t;
-                state_coins[r] += t;
-            }
+        bank: &mut CoinMap,
+    ) -> Result<CoinMap, RuleError> {
+        let payment = self.payment_for(cost).ok_or_else(|| self.shortfall(cost))?;
+        self.pay(&payment, bank);
+        Ok(payment)
+    }
+
+    /// The [`RuleError::NotEnoughResources`] for a `cost` the player can't afford.
+    fn shortfall(&self, cost: &ResourceMap) -> RuleError {
+        RuleError::NotEnoughResources {
+            missing: self.missing_for(cost).0,
+            gold: self.mortal.gold,
         }
-        Ok(())
+    }
+
+    /// All coins in hand, gold included.
+    pub fn coin_count(&self) -> usize {
+        self.mortal.total()
+    }
+
+    /// Whether `extra` more coins would take the player over `limit`, like
+    /// [`GameConfig::max_coins`].
+    pub fn would_exceed_hand_limit(&self, extra: usize, limit: usize) -> bool {
+        self.coin_count() + extra > limit
     }
 
     pub fn can_purchase(&self, cost: &ResourceMap) -> bool {
-        cost.0
-            .iter()
-            .map(|(r, &t)| t.saturating_sub(self.immortal[r] + self.mortal[r]))
-            .sum::<usize>()
-            <= self.wilds
+        let (missing, patched) = self.missing_for(cost);
+        missing.sum() as usize == patched
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Card {
-    cost: ResourceMap,
-    score: u8,
-    adds: ResourceMap,
+/// One of the three decks, cheapest first. Sent as `0`, `1` or `2` like the
+/// deck indices of older versions, written as `1`, `2` or `3` for humans.
+#[derive(
+    Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy, Enum, Serialize, Deserialize,
+)]
+#[serde(into = "usize", try_from = "usize")]
+pub enum Tier {
+    One,
+    Two,
+    Three,
 }
 
-impl Card {
-    pub fn new(color: ResourceKind, score: u8, cost: ResourceMap) -> Self {
-        Card {
-            cost,
-            score,
-            adds: {
-                let mut r = ResourceMap::new();
-                r[color] = 1;
-                r
-            },
-        }
+impl Tier {
+    pub const ALL: [Tier; 3] = [Tier::One, Tier::Two, Tier::Three];
+}
+
+impl From<Tier> for usize {
+    fn from(tier: Tier) -> usize {
+        tier.into_usize()
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Nobel {
-    pub cost: ResourceMap,
-    pub score: u8,
+impl TryFrom<usize> for Tier {
+    type Error = String;
+
+    fn try_from(index: usize) -> Result<Self, String> {
+        Tier::ALL
+            .get(index)
+            .copied()
+            .ok_or_else(|| format!("invalid deck {index}, expected 0, 1 or 2"))
+    }
 }
 
-#[derive(Clone, Serialize, Deserialize)]
-pub struct State {
-    pub decks: Vec<Vec<Card>>,
-    pub nobels: Vec<Nobel>,
-    pub players: Vec<Player>,
-    pub coins: ResourceMap,
-    pub wilds: usize,
-    pub turn: usize,
+impl Display for Tier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.into_usize() + 1)
+    }
 }
 
-const MAX_DECK_SHOW: usize = 4;
+impl FromStr for Tier {
+    type Err = anyhow::Error;
 
-impl State {
-    pub fn is_finished(&self) -> bool {
-        self.turn == 0 && self.players.iter().any(|x| x.score > 14)
+    fn from_str(text: &str) -> anyhow::Result<Self> {
+        Ok(match text {
+            "1" => Tier::One,
+            "2" => Tier::Two,
+            "3" => Tier::Three,
+            _ => bail!("Invalid deck {text:?}, expected 1, 2 or 3"),
+        })
     }
+}
+
+/// Writes a map keyed by [`Tier`] as a plain list, the way decks were sent
+/// when they were indexed by number.
+fn tier_list<V: Serialize, S: serde::Serializer>(
+    map: &EnumMap<Tier, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(map.values())
+}
+
+/// Reads what [`tier_list`] writes, which has to have exactly one entry per tier.
+fn from_tier_list<'de, V: Deserialize<'de>, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<EnumMap<Tier, V>, D::Error> {
+    let list = Vec::<V>::deserialize(deserializer)?;
+    let len = list.len();
+    <[V; 3]>::try_from(list)
+        .map(EnumMap::from_array)
+        .map_err(|_| serde::de::Error::invalid_length(len, &"one entry per deck"))
+}
+
+/// The cards of a deck, face up ones first. Clones share the cards until one
+/// of them changes, so copying a [`State`] doesn't copy the decks. Derefs to
+/// a `Vec<Card>` and is serialized as one.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Deck(Arc<Vec<Card>>);
+
+impl Deref for Deck {
+    type Target = Vec<Card>;
 
-    pub fn winner(&self) -> usize {
-        self.players.iter().enumerate().max_by_key(|x| x.1.score).unwrap().0
+    fn deref(&self) -> &Vec<Card> {
+        &self.0
     }
+}
 
-    pub fn run(&mut self, action: Action) -> anyhow::Result<()> {
-        let player = &mut self.players[self.turn];
-        match action {
-            Action::PickThree { one, two, three } => {
-                if one == two || one == three || two == three {
-                    bail!("No duplicate code in pick-tree");
-                }
-                for item in [one, two, three] {
-                    if self.coins[item] == 0 {
-                        bail!("No coin of {item:?} exists");
-                    }
-                    self.coins[item] -= 1;
-                    player.mortal[item] += 1;
-                }
-                self.change_player();
+impl DerefMut for Deck {
+    fn deref_mut(&mut self) -> &mut Vec<Card> {
+        Arc::make_mut(&mut self.0)
+    }
+}
+
+impl From<Vec<Card>> for Deck {
+    fn from(cards: Vec<Card>) -> Self {
+        Deck(Arc::new(cards))
+    }
+}
+
+impl<'a> IntoIterator for &'a Deck {
+    type Item = &'a Card;
+    type IntoIter = std::slice::Iter<'a, Card>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl Debug for Deck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Serialize for Deck {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Deck {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(Deck::from)
+    }
+}
+
+/// Where a card a player can buy lies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardSlot {
+    /// A face up card, as in [`Action::Purchase`].
+    Visible { deck: Tier, card: usize },
+    /// One of the player's reserved cards, as in [`Action::PurchaseReserved`].
+    Reserved { index: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Card {
+    #[serde(default)]
+    id: usize,
+    cost: ResourceMap,
+    score: u8,
+    adds: ResourceMap,
+}
+
+impl Card {
+    /// `id` should be unique among the cards of a game, so agents and replays
+    /// can refer to a card wherever it is.
+    pub fn new(id: usize, color: ResourceKind, score: u8, cost: ResourceMap) -> Self {
+        Card {
+            id,
+            cost,
+            score,
+            adds: ResourceMap::from([(color, 1)]),
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn cost(&self) -> &ResourceMap {
+        &self.cost
+    }
+
+    pub fn score(&self) -> u8 {
+        self.score
+    }
+
+    /// The permanent resources buying this card gives.
+    pub fn adds(&self) -> &ResourceMap {
+        &self.adds
+    }
+
+    /// The color of the card, `None` only for hand made cards that add nothing.
+    pub fn color(&self) -> Option<ResourceKind> {
+        self.adds.iter().find(|(_, v)| **v > 0).map(|(c, _)| c)
+    }
+
+    /// What `player` still has to pay in coins after their card discounts.
+    pub fn cost_for(&self, player: &Player) -> ResourceMap {
+        player.immortal.deficit(&self.cost)
+    }
+
+    /// How many coins `player` is missing to buy this card, wilds counted.
+    pub fn coins_short(&self, player: &Player) -> usize {
+        let (missing, patched) = player.missing_for(&self.cost);
+        missing.sum() as usize - patched
+    }
+}
+
+/// A nobel, or in [`ObjectiveMode::Cities`] a city. It goes to the first
+/// player whose cards cover `cost` and who has `min_score`, at the end of
+/// their turn.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Objective {
+    pub cost: ResourceMap,
+    pub score: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_score: Option<u8>,
+    /// Claiming it ends the game at the end of the round, like a city.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ends_game: bool,
+}
+
+/// Leaves out `min_score` and `ends_game` when they are not set.
+impl Debug for Objective {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut s = f.debug_struct("Objective");
+        s.field("cost", &self.cost).field("score", &self.score);
+        if let Some(min_score) = self.min_score {
+            s.field("min_score", &min_score);
+        }
+        if self.ends_game {
+            s.field("ends_game", &true);
+        }
+        s.finish()
+    }
+}
+
+impl Objective {
+    /// ```
+    /// use game_def::{cards, Player, ResourceKind};
+    ///
+    /// let city = &cards::standard_cities()[0];
+    /// let mut player = Player::new("a");
+    /// player.immortal = city.cost.clone();
+    /// assert!(!city.claimable_by(&player));
+    /// player.score = city.min_score.unwrap();
+    /// assert!(city.claimable_by(&player));
+    /// player.immortal[ResourceKind::Red] -= 1;
+    /// assert!(!city.claimable_by(&player));
+    /// ```
+    pub fn claimable_by(&self, player: &Player) -> bool {
+        player.immortal.covers(&self.cost) && self.min_score.is_none_or(|m| player.score >= m)
+    }
+}
+
+/// Something that happened while running an action, in the order it happened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GameEvent {
+    CoinsTaken {
+        player: usize,
+        coins: ResourceMap,
+    },
+    CoinsReturned {
+        player: usize,
+        coins: CoinMap,
+    },
+    CardPurchased {
+        player: usize,
+        card: Card,
+        payment: CoinMap,
+        reserved: bool,
+    },
+    CardReserved {
+        player: usize,
+        card: Card,
+        blind: bool,
+    },
+    WildReceived {
+        player: usize,
+    },
+    NobelVisited {
+        player: usize,
+        /// Where the nobel was in [`State::nobels`] before it left.
+        index: usize,
+        nobel: Objective,
+    },
+    /// Comes after the [`GameEvent::CoinsReturned`] of the player's coins.
+    Resigned {
+        player: usize,
+    },
+    GameFinished {
+        result: GameResult,
+    },
+}
+
+/// What [`State::apply`] changed, so that [`State::undo`] can put it back.
+#[derive(Debug, Clone)]
+pub struct UndoToken {
+    turn: usize,
+    phase: Phase,
+    coins: CoinMap,
+    mortal: CoinMap,
+    immortal: ResourceMap,
+    score: u8,
+    purchased: usize,
+    record: PlayerRecord,
+    /// Deck, slot, whether the slot was refilled from the pile, and the card taken.
+    deck: Option<(Tier, usize, bool, Card)>,
+    reserved: Option<ReservedChange>,
+    nobel: Option<(usize, Objective)>,
+    hash: Option<u64>,
+    first_player: usize,
+    round: usize,
+    passes: usize,
+}
+
+#[derive(Debug, Clone)]
+enum ReservedChange {
+    Added {
+        blind_len: usize,
+    },
+    Removed {
+        index: usize,
+        card: Card,
+        blind: Option<bool>,
+    },
+}
+
+/// What the player whose turn it is has to do next. Like [`ResourceKind`],
+/// the capitalized names older versions wrote are still read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    #[default]
+    #[serde(alias = "Play")]
+    Play,
+    /// The player holds more than [`GameConfig::max_coins`] coins and has to
+    /// discard down to it.
+    #[serde(alias = "Discard")]
+    Discard,
+    /// Several nobels want to visit the player, who has to pick one of
+    /// [`State::nobel_candidates`].
+    #[serde(alias = "ChooseNobel")]
+    ChooseNobel,
+}
+
+/// Two states are equal when every field matches, display names and the
+/// order of the face down cards included. [`State::observationally_eq`]
+/// ignores that order.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct State {
+    #[serde(serialize_with = "tier_list", deserialize_with = "from_tier_list")]
+    pub decks: EnumMap<Tier, Deck>,
+    /// Nobels, or cities in [`ObjectiveMode::Cities`].
+    pub nobels: Vec<Objective>,
+    pub players: Vec<Player>,
+    pub coins: CoinMap,
+    pub turn: usize,
+    #[serde(default)]
+    pub phase: Phase,
+    /// Who played the first turn. A round ends right before their turn comes again.
+    #[serde(default)]
+    pub first_player: usize,
+    /// Rounds completed so far.
+    #[serde(default)]
+    pub round: usize,
+    /// Skips in a row, reset by any other action.
+    #[serde(default)]
+    pub passes: usize,
+    #[serde(default)]
+    pub config: GameConfig,
+    /// Kept up to date by [`State::run`] once known, see [`State::zobrist`].
+    #[serde(skip)]
+    hash: Option<u64>,
+}
+
+/// The rules a game is played under. The default is the official game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameConfig {
+    /// Reaching this score ends the game at the end of the round.
+    pub target_score: u8,
+    /// Coins of each color in the bank at the start, by number of players.
+    #[serde(deserialize_with = "number_keys")]
+    pub coins_per_color: BTreeMap<usize, usize>,
+    /// Gold coins in the bank at the start.
+    pub wilds: usize,
+    /// Face up cards per deck.
+    pub visible_cards: usize,
+    /// Nobels dealt on top of one per player.
+    pub extra_nobels: usize,
+    /// Most coins (gold included) a player may hold at the end of their turn.
+    pub max_coins: usize,
+    /// Most cards a player may hold in reserve at once.
+    pub max_reserved: usize,
+    /// Coins of a color the bank needs to have for a player to pick two of them.
+    pub pick_two_minimum: usize,
+    /// The game ends in a draw once this many rounds are played.
+    #[serde(default)]
+    pub max_rounds: Option<usize>,
+    #[serde(default)]
+    pub objective_mode: ObjectiveMode,
+    /// Played with instead of the official cards and nobels, from `--cards`.
+    /// Shared, so states still clone cheaply.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cards: Option<Arc<cards::CardSet>>,
+    /// What players start with on top of the others, by seat. See
+    /// [`GameConfig::check_handicaps`].
+    #[serde(
+        default,
+        deserialize_with = "number_keys",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub handicaps: BTreeMap<usize, Handicap>,
+}
+
+/// A head start for one player, to even out a game between a strong and a
+/// weak one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Handicap {
+    /// Taken from the bank by [`State::setup`].
+    #[serde(default)]
+    pub coins: CoinMap,
+    /// Points without a card, which count like any others.
+    #[serde(default)]
+    pub score: u8,
+    /// Thinking time on top of the runner's time budget. The rules don't use it.
+    #[serde(default)]
+    pub time_ms: u64,
+}
+
+/// Like `1u+2w, 3 points, 30.0s more time`, leaving out what isn't given.
+impl Display for Handicap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = vec![];
+        if self.coins.total() > 0 {
+            parts.push(self.coins.to_string());
+        }
+        if self.score > 0 {
+            parts.push(format!("{} points", self.score));
+        }
+        if self.time_ms > 0 {
+            let time = std::time::Duration::from_millis(self.time_ms);
+            parts.push(format!("{time:.1?} more time"));
+        }
+        match parts.is_empty() {
+            true => write!(f, "nothing"),
+            false => write!(f, "{}", parts.join(", ")),
+        }
+    }
+}
+
+/// What [`State::nobels`] holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectiveMode {
+    /// The official nobels, and the game ends at [`GameConfig::target_score`].
+    #[default]
+    Nobles,
+    /// [`cards::standard_cities`] instead: the game ends at the end of the
+    /// round someone claims one, and [`GameConfig::target_score`] is not used.
+    ///
+    /// ```
+    /// use game_def::{GameConfig, GameResult, ObjectiveMode, State};
+    ///
+    /// let config = GameConfig {
+    ///     objective_mode: ObjectiveMode::Cities,
+    ///     ..GameConfig::default()
+    /// };
+    /// let mut state = State::setup(&["a", "b"], config, &mut rand::thread_rng());
+    /// let city = state.nobels[0].clone();
+    /// state.players[0].immortal = city.cost.clone();
+    /// state.players[0].score = city.min_score.unwrap();
+    /// state.players[1].score = 20;
+    /// state.rehash();
+    /// state.run("take r g u".parse().unwrap()).unwrap();
+    /// assert_eq!(state.players[0].record.cities, 1);
+    /// // b still gets a turn, and has more points but no city.
+    /// assert!(!state.is_finished());
+    /// state.run("take r g u".parse().unwrap()).unwrap();
+    /// assert_eq!(state.result(), GameResult::Won(0));
+    /// ```
+    Cities,
+}
+
+/// Reads map keys written either as numbers or as strings. Inside an
+/// internally tagged enum, like [`RunnerMessage`], serde hands the JSON keys
+/// over as strings, which the plain map impl rejects.
+fn number_keys<'de, V: Deserialize<'de>, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<usize, V>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Key {
+        Number(usize),
+        Text(String),
+    }
+    let entries: Vec<(Key, V)> = deserializer.deserialize_map(MapEntries(Default::default()))?;
+    entries
+        .into_iter()
+        .map(|(key, value)| match key {
+            Key::Number(key) => Ok((key, value)),
+            Key::Text(key) => key
+                .parse()
+                .map(|key| (key, value))
+                .map_err(serde::de::Error::custom),
+        })
+        .collect()
+}
+
+/// Collects the entries of a map in order, whatever the key type.
+struct MapEntries<K, V>(std::marker::PhantomData<(K, V)>);
+
+impl<'de, K: Deserialize<'de>, V: Deserialize<'de>> serde::de::Visitor<'de> for MapEntries<K, V> {
+    type Value = Vec<(K, V)>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a map")
+    }
+
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut r = vec![];
+        while let Some(entry) = map.next_entry()? {
+            r.push(entry);
+        }
+        Ok(r)
+    }
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            target_score: 15,
+            coins_per_color: BTreeMap::from([(2, 4), (3, 5), (4, 7)]),
+            wilds: 5,
+            visible_cards: 4,
+            extra_nobels: 1,
+            max_coins: 10,
+            max_reserved: 3,
+            pick_two_minimum: 4,
+            max_rounds: None,
+            objective_mode: ObjectiveMode::Nobles,
+            cards: None,
+            handicaps: BTreeMap::new(),
+        }
+    }
+}
+
+impl GameConfig {
+    /// Falls back to the entry for the closest smaller player count, then to 7.
+    pub fn coins_for(&self, players: usize) -> usize {
+        self.coins_per_color
+            .range(..=players)
+            .next_back()
+            .map_or(7, |(_, coins)| *coins)
+    }
+
+    /// The decks to deal, not shuffled: [`GameConfig::cards`] or the
+    /// official ones.
+    pub fn decks(&self) -> EnumMap<Tier, Vec<Card>> {
+        match &self.cards {
+            Some(set) => set.decks.clone(),
+            None => cards::standard_decks(),
+        }
+    }
+
+    /// Which of [`GameConfig::decks`] the card numbered `id` is from.
+    pub fn tier_of(&self, id: usize) -> Option<Tier> {
+        match &self.cards {
+            Some(set) => set.tier_of(id),
+            None => cards::tier_of(id),
+        }
+    }
+
+    /// Nobels dealt for `players`. Three cities are dealt whatever the count.
+    pub fn nobels_for(&self, players: usize) -> usize {
+        match self.objective_mode {
+            ObjectiveMode::Nobles => players + self.extra_nobels,
+            ObjectiveMode::Cities => 3,
+        }
+    }
+
+    /// Checks that [`GameConfig::handicaps`] fit a game of `players`: every
+    /// seat is in the game, the bank has the coins, no hand starts over
+    /// [`GameConfig::max_coins`] and no one starts at the target score.
+    ///
+    /// ```
+    /// use game_def::{CoinMap, GameConfig, Handicap};
+    ///
+    /// let coins = |code: &str| CoinMap {
+    ///     gems: code.parse().unwrap(),
+    ///     gold: 0,
+    /// };
+    /// let mut config = GameConfig::default();
+    /// let handicap = Handicap {
+    ///     coins: coins("2w+1u"),
+    ///     score: 3,
+    ///     ..Handicap::default()
+    /// };
+    /// assert_eq!(handicap.to_string(), "1u+2w, 3 points");
+    /// config.handicaps.insert(1, handicap);
+    /// config.check_handicaps(2)?;
+    /// assert!(config.check_handicaps(1).is_err());
+    /// // Two players share 4 white coins.
+    /// let handicap = Handicap {
+    ///     coins: coins("3w"),
+    ///     ..Handicap::default()
+    /// };
+    /// config.handicaps.insert(0, handicap);
+    /// assert!(config.check_handicaps(2).is_err());
+    /// # anyhow::Ok(())
+    /// ```
+    pub fn check_handicaps(&self, players: usize) -> anyhow::Result<()> {
+        let mut taken = CoinMap::new();
+        for (&seat, handicap) in &self.handicaps {
+            if seat >= players {
+                bail!(
+                    "There is no seat {seat} for a handicap, seats count from 0 to {}",
+                    players - 1
+                );
             }
-            Action::PickTwo { color } => {
-                if self.coins[color] < 4 {
-                    bail!("At least two coin of {color:?} should remain");
+            if handicap.coins.total() > self.max_coins {
+                bail!(
+                    "Seat {seat} can't start with {} coins, the most a hand holds is {}",
+                    handicap.coins.total(),
+                    self.max_coins
+                );
+            }
+            if handicap.score >= self.target_score && self.objective_mode == ObjectiveMode::Nobles {
+                bail!(
+                    "Seat {seat} can't start at the target score of {}",
+                    self.target_score
+                );
+            }
+            taken += &handicap.coins;
+        }
+        let bank = CoinMap {
+            gems: ResourceMap::uniform(self.coins_for(players)),
+            gold: self.wilds,
+        };
+        if !bank.covers(&taken) {
+            bail!("The handicaps take {taken} but the bank only has {bank}");
+        }
+        Ok(())
+    }
+}
+
+/// Why a game is over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndReason {
+    /// A round closed with someone at the target score.
+    TargetScore,
+    /// Every player skipped in a row, so nothing can change anymore.
+    Stalemate,
+    /// [`GameConfig::max_rounds`] rounds were played, so the game is
+    /// adjudicated by the scores as they are.
+    RoundLimit,
+    /// Every player but one resigned.
+    Resignation,
+    /// A round closed with someone holding a city.
+    CityClaimed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameResult {
+    Ongoing,
+    Won(usize),
+    /// Players sharing the victory.
+    Draw(Vec<usize>),
+}
+
+impl PartialEq for State {
+    fn eq(&self, other: &Self) -> bool {
+        // Everything but the cached hash.
+        let State {
+            decks,
+            nobels,
+            players,
+            coins,
+            turn,
+            phase,
+            first_player,
+            round,
+            passes,
+            config,
+            hash: _,
+        } = self;
+        *decks == other.decks
+            && *nobels == other.nobels
+            && *players == other.players
+            && *coins == other.coins
+            && *turn == other.turn
+            && *phase == other.phase
+            && *first_player == other.first_player
+            && *round == other.round
+            && *passes == other.passes
+            && *config == other.config
+    }
+}
+
+impl Eq for State {}
+
+impl State {
+    /// A fresh official game for the named players.
+    pub fn standard(names: &[&str], rng: &mut impl Rng) -> State {
+        Self::setup(names, GameConfig::default(), rng)
+    }
+
+    /// A fresh game under `config`: shuffled official decks, nobels dealt and
+    /// the bank filled for the number of players, then the handicaps handed
+    /// out. The first name plays first. Panics on handicaps
+    /// [`GameConfig::check_handicaps`] refuses.
+    pub fn setup(names: &[&str], config: GameConfig, rng: &mut impl Rng) -> State {
+        let decks = config.decks().map(|_, mut d| {
+            d.shuffle(rng);
+            Deck::from(d)
+        });
+        let mut nobels = match config.objective_mode {
+            ObjectiveMode::Nobles => match &config.cards {
+                Some(set) => set.nobels.clone(),
+                None => cards::standard_nobels(),
+            },
+            ObjectiveMode::Cities => cards::standard_cities(),
+        };
+        nobels.shuffle(rng);
+        nobels.truncate(config.nobels_for(names.len()));
+        let mut coins = CoinMap {
+            gems: ResourceMap::uniform(config.coins_for(names.len())),
+            gold: config.wilds,
+        };
+        let mut players: Vec<_> = names.iter().map(|name| Player::new(name)).collect();
+        for (&seat, handicap) in &config.handicaps {
+            let player = &mut players[seat];
+            player.mortal += &handicap.coins;
+            player.score += handicap.score;
+            coins -= &handicap.coins;
+        }
+        let mut state = State {
+            decks,
+            nobels,
+            players,
+            coins,
+            turn: 0,
+            phase: Phase::Play,
+            first_player: 0,
+            round: 0,
+            passes: 0,
+            config,
+            hash: None,
+        };
+        state.rehash();
+        state
+    }
+
+    /// Has `player` play the first turn of a fresh game instead of the first
+    /// name, so every seat keeps its number. Rounds close before their turn.
+    ///
+    /// ```
+    /// use game_def::{GameConfig, State};
+    ///
+    /// let names = ["a", "b", "c"];
+    /// let mut state = State::setup(&names, GameConfig::default(), &mut rand::thread_rng());
+    /// state.set_first_player(2);
+    /// let mut turns = vec![];
+    /// while state.round == 0 {
+    ///     turns.push(state.turn);
+    ///     let action = state.legal_actions().next().unwrap();
+    ///     state.run(action).unwrap();
+    /// }
+    /// assert_eq!(turns, [2, 0, 1]);
+    /// ```
+    pub fn set_first_player(&mut self, player: usize) {
+        self.first_player = player;
+        self.turn = player;
+        self.rehash();
+    }
+
+    /// The game ends once a round closes with someone at the target score, so
+    /// every player gets the same number of turns.
+    pub fn is_finished(&self) -> bool {
+        self.end_reason().is_some()
+    }
+
+    pub fn end_reason(&self) -> Option<EndReason> {
+        let active = self.active_players().count();
+        if active < self.players.len() && active <= 1 {
+            return Some(EndReason::Resignation);
+        }
+        if self.passes >= active {
+            return Some(EndReason::Stalemate);
+        }
+        if self.turn != self.first_player || self.phase != Phase::Play {
+            return None;
+        }
+        match self.config.objective_mode {
+            ObjectiveMode::Nobles => {
+                if self
+                    .players
+                    .iter()
+                    .any(|x| x.score >= self.config.target_score)
+                {
+                    return Some(EndReason::TargetScore);
+                }
+            }
+            ObjectiveMode::Cities => {
+                if self.players.iter().any(|x| x.record.cities > 0) {
+                    return Some(EndReason::CityClaimed);
+                }
+            }
+        }
+        if self.config.max_rounds.is_some_and(|m| self.round >= m) {
+            return Some(EndReason::RoundLimit);
+        }
+        None
+    }
+
+    /// Where each player's points came from, and what they were left with.
+    ///
+    /// ```
+    /// use game_def::{Action, State};
+    ///
+    /// let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/render");
+    /// let json = std::fs::read_to_string(format!("{dir}/state.json")).unwrap();
+    /// let mut state: State = serde_json::from_str(&json).unwrap();
+    /// let before = state.final_scores();
+    /// for action in ["take r u g", "reserve 2.1", "buy 1.3", "reserve-blind 3"] {
+    ///     state.run(action.parse::<Action>().unwrap()).unwrap();
+    /// }
+    /// let [alice, bob] = &state.final_scores()[..] else { panic!() };
+    /// // Alice paid for 1.3 with a gold coin.
+    /// assert_eq!((alice.coins_taken, alice.reserves, alice.wilds_spent), (3, 0, 1));
+    /// assert_eq!(alice.cards_by_tier, [1, 0, 0]);
+    /// assert_eq!((bob.coins_taken, bob.reserves, bob.wilds_spent), (0, 2, 0));
+    /// assert_eq!(bob.wilds_left, before[1].wilds_left + 2);
+    /// assert_eq!(bob.turns_taken, before[1].turns_taken + 2);
+    /// ```
+    pub fn final_scores(&self) -> Vec<ScoreBreakdown> {
+        self.players
+            .iter()
+            .map(|p| ScoreBreakdown {
+                card_points: p.record.card_points,
+                noble_points: p.record.nobel_points,
+                cards_by_tier: p.record.cards_by_tier,
+                nobles_claimed: p.record.nobels,
+                coins_left: p.mortal.gems.sum() as usize,
+                wilds_left: p.mortal.gold,
+                turns_taken: p.record.turns,
+                coins_taken: p.record.coins_taken,
+                reserves: p.record.reserves,
+                wilds_spent: p.record.wilds_spent,
+            })
+            .collect()
+    }
+
+    /// Reaching the target score, claiming a city or outlasting everyone else
+    /// wins, and at the round limit the best score by [`State::winners`]'s
+    /// order does. A stalemate is a draw between everyone who didn't resign.
+    /// Of several players claiming cities in the same round, the best one by
+    /// [`State::winners`]'s order wins.
+    pub fn result(&self) -> GameResult {
+        let winners = match self.end_reason() {
+            None => return GameResult::Ongoing,
+            Some(EndReason::TargetScore | EndReason::RoundLimit) => self.winners(),
+            Some(EndReason::CityClaimed) => {
+                let claimed = self.active_players();
+                self.best_of(
+                    claimed
+                        .filter(|&i| self.players[i].record.cities > 0)
+                        .collect(),
+                )
+            }
+            Some(EndReason::Stalemate | EndReason::Resignation) => self.active_players().collect(),
+        };
+        match winners[..] {
+            [winner] => GameResult::Won(winner),
+            _ => GameResult::Draw(winners),
+        }
+    }
+
+    /// Players who haven't resigned.
+    pub fn active_players(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.players.len()).filter(|&i| !self.players[i].resigned)
+    }
+
+    /// Players with the best score, ties broken by fewest purchased cards.
+    /// More than one player is returned only if that still ties. Resigned
+    /// players are left out.
+    pub fn winners(&self) -> Vec<usize> {
+        self.best_of(self.active_players().collect())
+    }
+
+    /// The place of each player, 1 for the winners of [`State::result`] or of
+    /// [`State::winners`] while the game goes on. The others follow by
+    /// score, ties broken by fewest purchased cards, and then those who
+    /// resigned. Players who tie share a place and the next place is skipped,
+    /// like 1, 2, 2, 4.
+    ///
+    /// ```
+    /// use game_def::{GameConfig, GameResult, State};
+    ///
+    /// let names = ["a", "b", "c", "d"];
+    /// let mut state = State::setup(&names, GameConfig::default(), &mut rand::thread_rng());
+    /// while !state.is_finished() {
+    ///     let action = state.legal_actions().next().unwrap();
+    ///     state.run(action).unwrap();
+    /// }
+    /// let places = state.placements();
+    /// match state.result() {
+    ///     GameResult::Won(winner) => {
+    ///         assert_eq!(places[winner], 1);
+    ///         assert_eq!(places.iter().filter(|&&p| p == 1).count(), 1);
+    ///     }
+    ///     GameResult::Draw(winners) => assert!(winners.iter().all(|&w| places[w] == 1)),
+    ///     GameResult::Ongoing => unreachable!(),
+    /// }
+    /// assert!(places.iter().all(|&p| (1..=4).contains(&p)));
+    /// ```
+    pub fn placements(&self) -> Vec<usize> {
+        let first = match self.result() {
+            GameResult::Won(winner) => vec![winner],
+            GameResult::Draw(winners) => winners,
+            GameResult::Ongoing => self.winners(),
+        };
+        let mut groups = vec![first.clone()];
+        let mut rest: Vec<usize> = self
+            .active_players()
+            .filter(|i| !first.contains(i))
+            .collect();
+        while !rest.is_empty() {
+            let best = self.best_of(rest.clone());
+            rest.retain(|i| !best.contains(i));
+            groups.push(best);
+        }
+        groups.push(
+            (0..self.players.len())
+                .filter(|&i| self.players[i].resigned && !first.contains(&i))
+                .collect(),
+        );
+        let mut places = vec![0; self.players.len()];
+        let mut place = 1;
+        for group in groups {
+            for &i in &group {
+                places[i] = place;
+            }
+            place += group.len();
+        }
+        places
+    }
+
+    /// The players of `candidates` with the best score, ties broken by
+    /// fewest purchased cards.
+    fn best_of(&self, candidates: Vec<usize>) -> Vec<usize> {
+        let key = |i: &usize| {
+            let p = &self.players[*i];
+            (p.score, std::cmp::Reverse(p.immortal.sum()))
+        };
+        let Some(best) = candidates.iter().map(key).max() else {
+            return vec![];
+        };
+        candidates.into_iter().filter(|i| key(i) == best).collect()
+    }
+
+    /// Checks the invariants every reachable state keeps.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let expected = CoinMap {
+            gems: ResourceMap::uniform(self.config.coins_for(self.players.len())),
+            gold: self.config.wilds,
+        };
+        if self.turn >= self.players.len() {
+            bail!("Turn {} is out of range", self.turn);
+        }
+        if self.first_player >= self.players.len() {
+            bail!("First player {} is out of range", self.first_player);
+        }
+        let mut total = self.coins.clone();
+        for p in &self.players {
+            total += &p.mortal;
+        }
+        for (kind, n) in total.iter() {
+            if n != expected[kind] {
+                bail!(
+                    "There are {n} {kind:?} coins in the game instead of {}",
+                    expected[kind]
+                );
+            }
+        }
+        for (i, p) in self.players.iter().enumerate() {
+            let discarding = i == self.turn && self.phase == Phase::Discard;
+            if p.coin_count() > self.config.max_coins && !discarding {
+                bail!("{} holds {} coins", p.display_name, p.coin_count());
+            }
+            if p.reserved.len() > self.config.max_reserved {
+                bail!("{} has {} reserved cards", p.display_name, p.reserved.len());
+            }
+            if p.reserved_blind.len() > p.reserved.len() {
+                bail!(
+                    "{} has more blind flags than reserved cards",
+                    p.display_name
+                );
+            }
+            // Points only come from cards and from nobels, which need cards
+            // too, past a handicap.
+            let head_start = self.config.handicaps.get(&i).map_or(0, |h| h.score);
+            if p.immortal.sum() == 0 && p.score > head_start {
+                bail!("{} has points without any card", p.display_name);
+            }
+            let old_save = p.purchased.is_empty();
+            if !old_save && p.discounts(&self.config).as_ref() != Some(&p.immortal) {
+                bail!(
+                    "{} has discounts other than their cards give",
+                    p.display_name
+                );
+            }
+            if p.resigned && p.coin_count() > 0 {
+                bail!("{} resigned but holds coins", p.display_name);
+            }
+        }
+        if !self.is_finished() {
+            if self.players[self.turn].resigned {
+                bail!("It is the turn of a resigned player");
+            }
+            if self.players[self.first_player].resigned {
+                bail!("The first player resigned");
+            }
+        }
+        match self.phase {
+            Phase::Play => (),
+            Phase::Discard => {
+                if self.players[self.turn].coin_count() <= self.config.max_coins {
+                    bail!("Discard phase without too many coins");
                 }
-                self.coins[color] -= 2;
-                player.mortal[color] += 2;
-                self.change_player();
             }
-            Action::Purchase { deck, card } => {
-                let d = self.decks.get(deck).context("Invalid deck")?;
-                if card >= MAX_DECK_SHOW {
-                    bail!("Can not purchase invisible card");
+            Phase::ChooseNobel => {
+                if self.nobel_candidates().len() < 2 {
+                    bail!("Nobel choice without several candidates");
                 }
-                let c = d.get(card).context("Invalid card")?;
-                if !player.can_purchase(&c.cost) {
-                    bail!("You don't have enough resources");
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that [`State::run`] would accept `action`, without changing anything.
+    pub fn check_action(&self, action: &Action) -> Result<(), RuleError> {
+        let player = &self.players[self.turn];
+        match (self.phase, action) {
+            (Phase::Play, Action::Discard { .. }) => return Err(RuleError::NothingToDiscard),
+            (Phase::Play, Action::ChooseNobel { .. }) => return Err(RuleError::NotYourChoice),
+            (Phase::Discard, Action::Discard { .. })
+            | (Phase::ChooseNobel, Action::ChooseNobel { .. })
+            | (_, Action::Resign)
+            | (Phase::Play, _) => (),
+            (Phase::Discard, _) => {
+                return Err(RuleError::MustDiscard {
+                    max_coins: self.config.max_coins,
+                })
+            }
+            (Phase::ChooseNobel, _) => return Err(RuleError::MustChooseNobel),
+        }
+        match action {
+            Action::PickThree { one, two, three } => {
+                if one == two || one == three || two == three {
+                    return Err(RuleError::DuplicateColors);
                 }
-                player.purchase(&c.cost, &mut self.coins, &mut self.wilds)?;
-                player.immortal.add(&c.adds);
-                player.score += c.score;
-                self.nobels.retain(|nobel| {
-                    if player.immortal.0.iter().all(|(c, v)| *v >= nobel.cost[c]) {
-                        player.score += nobel.score;
-                        false
-                    } else {
-                        true
+                for color in [one, two, three] {
+                    if self.coins[*color] == 0 {
+                        return Err(RuleError::CoinUnavailable { color: *color });
                     }
-                });
-                self.decks[deck].remove(card);
-                self.change_player();
+                }
+            }
+            Action::PickFewer { colors } => {
+                let available = self.coins.gems.iter().filter(|(_, n)| **n > 0).count();
+                if available >= 3 {
+                    return Err(RuleError::ThreeColorsLeft);
+                }
+                // Nothing to take is a turn with nothing to do, see `Action::Skip`.
+                if available == 0 {
+                    return Err(RuleError::NoCoinsLeft);
+                }
+                for (i, color) in colors.iter().enumerate() {
+                    if colors[..i].contains(color) {
+                        return Err(RuleError::DuplicateColors);
+                    }
+                    if self.coins[*color] == 0 {
+                        return Err(RuleError::CoinUnavailable { color: *color });
+                    }
+                }
+                if colors.len() != available {
+                    return Err(RuleError::MustPickAllColors { available });
+                }
+            }
+            Action::PickTwo { color } => {
+                let minimum = self.config.pick_two_minimum;
+                if self.coins[*color] < minimum {
+                    return Err(RuleError::PickTwoUnavailable {
+                        color: *color,
+                        minimum,
+                    });
+                }
             }
-            Action::PurchaseReserved { index } => {
+            Action::Purchase {
+                deck,
+                card,
+                payment,
+                wilds,
+                id,
+            } => {
+                let c =
+                    Self::visible_card(&self.decks, self.config.visible_cards, *deck, *card, *id)?;
+                chosen_payment(player, &c.cost, payment.clone(), *wilds)?;
+            }
+            Action::PurchaseReserved {
+                index,
+                payment,
+                wilds,
+            } => {
                 let c = player
                     .reserved
-                    .get(index)
-                    .context("Invalid reserved index")?;
-                if !player.can_purchase(&c.cost) {
-                    bail!("You don't have enough resources");
+                    .get(*index)
+                    .ok_or(RuleError::InvalidReservedIndex { index: *index })?;
+                chosen_payment(player, &c.cost, payment.clone(), *wilds)?;
+            }
+            Action::Reserve { deck, card, id } => {
+                self.check_reserve_limit(player)?;
+                Self::visible_card(&self.decks, self.config.visible_cards, *deck, *card, *id)?;
+            }
+            Action::ReserveBlind { deck } => {
+                self.check_reserve_limit(player)?;
+                if self.decks[*deck].len() <= self.config.visible_cards {
+                    return Err(RuleError::EmptyDeck { deck: *deck });
                 }
-                let c = player.reserved.remove(index);
-                player.purchase(&c.cost, &mut self.coins, &mut self.wilds)?;
-                player.immortal.add(&c.adds);
+            }
+            Action::Discard { coins, wilds } => {
+                let discarded = CoinMap {
+                    gems: coins.clone(),
+                    gold: *wilds,
+                };
+                if !player.mortal.covers(&discarded) {
+                    return Err(RuleError::DiscardNotHeld);
+                }
+                // Only a malformed state is in the discard phase within the limit.
+                let extra = player
+                    .coin_count()
+                    .checked_sub(self.config.max_coins)
+                    .ok_or(RuleError::NothingToDiscard)?;
+                if discarded.total() != extra {
+                    return Err(RuleError::HandLimit { extra });
+                }
+            }
+            Action::ChooseNobel { index } => {
+                if !self.nobel_candidates().contains(index) {
+                    return Err(RuleError::NobelCannotVisit { index: *index });
+                }
+            }
+            Action::Skip => {
+                let mut others = self
+                    .legal_actions()
+                    .filter(|a| !matches!(a, Action::Skip | Action::Resign));
+                if let Some(possible) = others.next() {
+                    return Err(RuleError::SkipNotAllowed { possible });
+                }
+            }
+            Action::Resign => (),
+        }
+        Ok(())
+    }
+
+    fn check_reserve_limit(&self, player: &Player) -> Result<(), RuleError> {
+        let max_reserved = self.config.max_reserved;
+        if player.reserved.len() >= max_reserved {
+            return Err(RuleError::ReserveLimit { max_reserved });
+        }
+        Ok(())
+    }
+
+    /// Plays `action` for the current player and reports what happened. On error
+    /// the state is left unchanged.
+    pub fn run(&mut self, action: Action) -> Result<Vec<GameEvent>, RuleError> {
+        self.check_action(&action)?;
+        let mut events = vec![];
+        let turn = self.turn;
+        let touched_deck = match action {
+            Action::Purchase { deck, .. }
+            | Action::Reserve { deck, .. }
+            | Action::ReserveBlind { deck } => Some(deck),
+            _ => None,
+        };
+        let hash = self
+            .hash
+            .map(|h| h ^ zobrist::local(self, turn, touched_deck));
+        self.passes = match action {
+            Action::Skip => self.passes + 1,
+            _ => 0,
+        };
+        let player = &mut self.players[self.turn];
+        match action {
+            Action::PickThree { one, two, three } => {
+                self.take_coins(&[one, two, three], &mut events);
+            }
+            Action::PickFewer { colors } => {
+                self.take_coins(&colors, &mut events);
+            }
+            Action::PickTwo { color } => {
+                self.take_coins(&[color, color], &mut events);
+            }
+            Action::Purchase {
+                deck,
+                card,
+                payment,
+                wilds,
+                ..
+            } => {
+                let c = &self.decks[deck][card];
+                let payment = chosen_payment(player, &c.cost, payment, wilds)?;
+                player.pay(&payment, &mut self.coins);
+                player.immortal += &c.adds;
                 player.score += c.score;
-                self.nobels.retain(|nobel| {
-                    if player.immortal.0.iter().all(|(c, v)| *v >= nobel.cost[c]) {
-                        player.score += nobel.score;
-                        false
-                    } else {
-                        true
-                    }
+                player.purchased.push(c.id);
+                player.record.bought(c, Some(deck));
+                player.record.wilds_spent += payment.gold;
+                let card = self.take_card(deck, card);
+                events.push(GameEvent::CardPurchased {
+                    player: turn,
+                    card,
+                    payment,
+                    reserved: false,
                 });
-                self.change_player();
+                self.change_player(&mut events);
             }
-            Action::Reserve { deck, card } => {
-                let d = self.decks.get(deck).context("Invalid deck")?;
-                if card >= MAX_DECK_SHOW {
-                    bail!("Can not purchase invisible card");
+            Action::PurchaseReserved {
+                index,
+                payment,
+                wilds,
+            } => {
+                let payment = chosen_payment(player, &player.reserved[index].cost, payment, wilds)?;
+                let c = player.reserved.remove(index);
+                if index < player.reserved_blind.len() {
+                    player.reserved_blind.remove(index);
                 }
-                _ = d.get(card).context("Invalid card")?;
-                let c = self.decks[deck].remove(card);
-                player.reserved.push(c);
-                self.change_player();
+                player.pay(&payment, &mut self.coins);
+                player.immortal += &c.adds;
+                player.score += c.score;
+                player.purchased.push(c.id);
+                player.record.bought(&c, self.config.tier_of(c.id));
+                player.record.wilds_spent += payment.gold;
+                events.push(GameEvent::CardPurchased {
+                    player: turn,
+                    card: c,
+                    payment,
+                    reserved: true,
+                });
+                self.change_player(&mut events);
+            }
+            Action::Reserve { deck, card, .. } => {
+                self.reserve(deck, card, false, &mut events);
+            }
+            Action::ReserveBlind { deck } => {
+                self.reserve(deck, self.config.visible_cards, true, &mut events);
+            }
+            Action::Discard { coins, wilds } => {
+                let coins = CoinMap {
+                    gems: coins,
+                    gold: wilds,
+                };
+                player.pay(&coins, &mut self.coins);
+                events.push(GameEvent::CoinsReturned {
+                    player: turn,
+                    coins,
+                });
+                self.phase = Phase::Play;
+                self.change_player(&mut events);
+            }
+            Action::ChooseNobel { index } => {
+                self.visit(index, &mut events);
+                self.phase = Phase::Play;
+                self.next_turn();
             }
             Action::Skip => {
-                self.change_player();
+                self.change_player(&mut events);
+            }
+            Action::Resign => {
+                let coins = player.mortal.clone();
+                if coins.total() > 0 {
+                    player.pay(&coins, &mut self.coins);
+                    events.push(GameEvent::CoinsReturned {
+                        player: turn,
+                        coins,
+                    });
+                }
+                player.resigned = true;
+                events.push(GameEvent::Resigned { player: turn });
+                self.phase = Phase::Play;
+                self.next_turn();
+                if self.players[self.first_player].resigned {
+                    // Only happens at the start of a round, which now starts here.
+                    self.first_player = self.turn;
+                }
             }
         }
-        Ok(())
+        if let Some(h) = hash {
+            self.hash = Some(h ^ zobrist::local(self, turn, touched_deck));
+        }
+        let result = self.result();
+        if result != GameResult::Ongoing {
+            events.push(GameEvent::GameFinished { result });
+        }
+        Ok(events)
+    }
+
+    /// Like [`State::run`], but returns what is needed to take the action back
+    /// with [`State::undo`] instead of the events.
+    pub fn apply(&mut self, action: &Action) -> Result<UndoToken, RuleError> {
+        let visible = self.config.visible_cards;
+        let slot = match *action {
+            Action::Purchase { deck, card, .. } | Action::Reserve { deck, card, .. } => {
+                Some((deck, card))
+            }
+            Action::ReserveBlind { deck } => Some((deck, visible)),
+            _ => None,
+        };
+        let refilled =
+            slot.is_some_and(|(deck, card)| card < visible && self.decks[deck].len() > visible);
+        let player = &self.players[self.turn];
+        let removed = match *action {
+            Action::PurchaseReserved { index, .. } => {
+                Some((index, player.reserved_blind.get(index).copied()))
+            }
+            _ => None,
+        };
+        let blind_len = player.reserved_blind.len();
+        let mut token = UndoToken {
+            turn: self.turn,
+            phase: self.phase,
+            coins: self.coins.clone(),
+            mortal: player.mortal.clone(),
+            immortal: player.immortal.clone(),
+            score: player.score,
+            purchased: player.purchased.len(),
+            record: player.record,
+            deck: None,
+            reserved: None,
+            nobel: None,
+            hash: self.hash,
+            first_player: self.first_player,
+            round: self.round,
+            passes: self.passes,
+        };
+        for event in self.run(action.clone())? {
+            match event {
+                GameEvent::CardPurchased { card, .. } => {
+                    if let Some((index, blind)) = removed {
+                        token.reserved = Some(ReservedChange::Removed { index, card, blind });
+                    } else if let Some((deck, slot)) = slot {
+                        token.deck = Some((deck, slot, refilled, card));
+                    }
+                }
+                GameEvent::CardReserved { card, .. } => {
+                    let (deck, slot) = slot.unwrap();
+                    token.deck = Some((deck, slot, refilled, card));
+                    token.reserved = Some(ReservedChange::Added { blind_len });
+                }
+                GameEvent::NobelVisited { index, nobel, .. } => token.nobel = Some((index, nobel)),
+                _ => (),
+            }
+        }
+        Ok(token)
+    }
+
+    /// Takes back the action `token` was returned for. Actions have to be
+    /// undone in the reverse order they were applied.
+    pub fn undo(&mut self, token: UndoToken) {
+        if let Some((index, nobel)) = token.nobel {
+            self.nobels.insert(index, nobel);
+        }
+        self.turn = token.turn;
+        self.phase = token.phase;
+        self.hash = token.hash;
+        self.first_player = token.first_player;
+        self.round = token.round;
+        self.passes = token.passes;
+        self.coins = token.coins;
+        let player = &mut self.players[self.turn];
+        player.mortal = token.mortal;
+        player.immortal = token.immortal;
+        player.score = token.score;
+        player.purchased.truncate(token.purchased);
+        // Resigned players never move, so the one who did wasn't before.
+        player.resigned = false;
+        player.record = token.record;
+        match token.reserved {
+            None => (),
+            Some(ReservedChange::Added { blind_len }) => {
+                player.reserved.pop();
+                player.reserved_blind.truncate(blind_len);
+            }
+            Some(ReservedChange::Removed { index, card, blind }) => {
+                player.reserved.insert(index, card);
+                if let Some(blind) = blind {
+                    player.reserved_blind.insert(index, blind);
+                }
+            }
+        }
+        if let Some((deck, slot, refilled, card)) = token.deck {
+            let d = &mut self.decks[deck];
+            if refilled {
+                let top = std::mem::replace(&mut d[slot], card);
+                d.insert(self.config.visible_cards, top);
+            } else {
+                d.insert(slot, card);
+            }
+        }
+    }
+
+    fn take_coins(&mut self, colors: &[ResourceKind], events: &mut Vec<GameEvent>) {
+        let player = &mut self.players[self.turn];
+        let mut taken = ResourceMap::new();
+        player.record.coins_taken += colors.len();
+        for &item in colors {
+            self.coins[item] -= 1;
+            player.mortal[item] += 1;
+            taken[item] += 1;
+        }
+        events.push(GameEvent::CoinsTaken {
+            player: self.turn,
+            coins: taken,
+        });
+        self.end_coin_turn(events);
+    }
+
+    /// The face up card in slot `card` of `deck`, checked against `id` if given.
+    fn visible_card(
+        decks: &EnumMap<Tier, Deck>,
+        visible: usize,
+        deck: Tier,
+        card: usize,
+        id: Option<usize>,
+    ) -> Result<&Card, RuleError> {
+        let c = decks[deck]
+            .get(card)
+            .filter(|_| card < visible)
+            .ok_or(RuleError::InvalidSlot { deck, card })?;
+        if let Some(expected) = id {
+            if c.id != expected {
+                return Err(RuleError::WrongCard {
+                    deck,
+                    card,
+                    expected,
+                    found: c.id,
+                });
+            }
+        }
+        Ok(c)
+    }
+
+    /// Takes a card off the board. An emptied face up slot is refilled from the
+    /// draw pile in place, so the other slots keep their cards; once the pile is
+    /// empty the later slots move down instead.
+    fn take_card(&mut self, deck: Tier, card: usize) -> Card {
+        let visible = self.config.visible_cards;
+        let d = &mut self.decks[deck];
+        if card < visible && d.len() > visible {
+            let top = d.remove(visible);
+            std::mem::replace(&mut d[card], top)
+        } else {
+            d.remove(card)
+        }
+    }
+
+    fn reserve(&mut self, deck: Tier, card: usize, blind: bool, events: &mut Vec<GameEvent>) {
+        let c = self.take_card(deck, card);
+        let player = &mut self.players[self.turn];
+        events.push(GameEvent::CardReserved {
+            player: self.turn,
+            card: c.clone(),
+            blind,
+        });
+        player.reserved.push(c);
+        player.record.reserves += 1;
+        player
+            .reserved_blind
+            .resize(player.reserved.len() - 1, false);
+        player.reserved_blind.push(blind);
+        if self.coins.gold > 0 {
+            self.coins.gold -= 1;
+            player.mortal.gold += 1;
+            events.push(GameEvent::WildReceived { player: self.turn });
+        }
+        self.end_coin_turn(events);
+    }
+
+    /// Ends a turn that gave the player coins, unless they went over the coin limit.
+    fn end_coin_turn(&mut self, events: &mut Vec<GameEvent>) {
+        if self.players[self.turn].would_exceed_hand_limit(0, self.config.max_coins) {
+            self.phase = Phase::Discard;
+        } else {
+            self.change_player(events);
+        }
     }
 
     pub fn json(&self) -> String {
         serde_json::to_string(self).unwrap()
     }
 
+    /// Like `==`, but the face down cards of each deck only have to be the
+    /// same cards, in any order.
+    pub fn observationally_eq(&self, other: &State) -> bool {
+        let visible = self.config.visible_cards;
+        let hidden_ids = |d: &[Card]| {
+            let mut ids: Vec<_> = d.iter().skip(visible).map(|c| c.id).collect();
+            ids.sort_unstable();
+            ids
+        };
+        let mut a = self.clone();
+        let mut b = other.clone();
+        for state in [&mut a, &mut b] {
+            for d in state.decks.values_mut() {
+                d.truncate(visible);
+            }
+        }
+        a == b
+            && self
+                .decks
+                .values()
+                .zip(other.decks.values())
+                .all(|(x, y)| hidden_ids(x) == hidden_ids(y))
+    }
+
+    #[cfg(feature = "msgpack")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        msgpack::to_vec(self).unwrap()
+    }
+
+    #[cfg(feature = "msgpack")]
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<State> {
+        msgpack::from_slice(bytes)
+    }
+
+    /// Hash of everything players can see. Maintained incrementally by
+    /// [`State::run`] and [`State::apply`] after [`State::rehash`], computed
+    /// from scratch otherwise.
+    pub fn zobrist(&self) -> u64 {
+        self.hash.unwrap_or_else(|| zobrist::full(self))
+    }
+
+    /// Recomputes the hash and keeps it up to date from now on. Has to be
+    /// called again after editing the fields directly.
+    pub fn rehash(&mut self) {
+        self.hash = Some(zobrist::full(self));
+    }
+
+    /// Prints [`State::render`] from the point of view of the player to move.
     pub fn print(&self) {
-        let player = &self.players[self.turn];
-        for (i, d) in self.decks.iter().enumerate() {
-            println!("Deck {i}:");
+        print!("{}", self.render(Some(self.turn)));
+    }
+
+    /// The board as the runner prints it. Cards `perspective` can afford are
+    /// marked with what they would pay; `None` leaves the marks out.
+    ///
+    /// ```
+    /// use game_def::State;
+    ///
+    /// let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/render");
+    /// let json = std::fs::read_to_string(format!("{dir}/state.json")).unwrap();
+    /// let state: State = serde_json::from_str(&json).unwrap();
+    /// let board = std::fs::read_to_string(format!("{dir}/board.txt")).unwrap();
+    /// assert_eq!(state.render(Some(0)), board);
+    /// assert!(!state.render(None).contains("You can purchase"));
+    /// ```
+    pub fn render(&self, perspective: Option<usize>) -> String {
+        self.render_hiding(perspective, &[])
+    }
+
+    /// [`State::render`] with a count of the reserves each player holds face
+    /// down that aren't in the state, like those of an [`Observation`].
+    pub(crate) fn render_hiding(&self, perspective: Option<usize>, hidden: &[usize]) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        let purchasable = perspective
+            .map(|p| self.purchasable_cards(p))
+            .unwrap_or_default();
+        let annotation = |slot: CardSlot| {
+            purchasable
+                .iter()
+                .find(|(s, _)| *s == slot)
+                .map(|(_, payment)| format!(" (You can purchase, paying {payment})"))
+                .unwrap_or_default()
+        };
+        for (tier, d) in &self.decks {
+            writeln!(out, "Deck {tier}:").unwrap();
             for (j, c) in d.iter().enumerate() {
-                if j == MAX_DECK_SHOW {
+                if j == self.config.visible_cards {
                     break;
                 }
-                print!("   Card {j}: {c:?}");
-                if player.can_purchase(&c.cost) {
-                    println!(" (You can purchase)");
-                } else {
-                    println!();
-                }
+                let note = annotation(CardSlot::Visible {
+                    deck: tier,
+                    card: j,
+                });
+                writeln!(out, "   Card {j}: {c:?}{note}").unwrap();
             }
         }
-        println!("Nobels: {:?}", self.nobels);
-        println!("Coins: {:?}", self.coins);
-        for p in &self.players {
-            println!("{}:", p.display_name);
-            println!("   Score: {}", p.score);
-            println!("   Resource Cards: {:?}", p.immortal);
-            println!("   Resource Coins: {:?}", p.mortal);
-            println!("   Wild Coins: {}", p.wilds);
+        writeln!(out, "Nobels: {:?}", self.nobels).unwrap();
+        writeln!(out, "Coins: {:?}", self.coins).unwrap();
+        for (i, p) in self.players.iter().enumerate() {
+            let resigned = if p.resigned { " (resigned)" } else { "" };
+            writeln!(out, "{}:{resigned}", p.display_name).unwrap();
+            writeln!(out, "   Score: {}", p.score).unwrap();
+            writeln!(out, "   Resource Cards: {:?}", p.immortal).unwrap();
+            writeln!(out, "   Resource Coins: {:?}", p.mortal.gems).unwrap();
+            writeln!(out, "   Gold Coins: {}", p.mortal.gold).unwrap();
             if !p.reserved.is_empty() {
-                println!("   Reserved Cards:");
-                for r in &p.reserved {
-                    println!("        {:?}", r);
+                writeln!(out, "   Reserved Cards:").unwrap();
+                for (index, r) in p.reserved.iter().enumerate() {
+                    let note = if perspective == Some(i) {
+                        annotation(CardSlot::Reserved { index })
+                    } else {
+                        String::new()
+                    };
+                    writeln!(out, "        {r:?}{note}").unwrap();
                 }
             }
+            if let Some(&n @ 1..) = hidden.get(i) {
+                writeln!(out, "   Face Down Reserved Cards: {n}").unwrap();
+            }
+        }
+        self.render_turn(&mut out);
+        out
+    }
+
+    /// Whose turn it is and what they have to do, the end of [`State::render`].
+    fn render_turn(&self, out: &mut String) {
+        use std::fmt::Write;
+
+        writeln!(out, "Turn {}", self.players[self.turn].display_name).unwrap();
+        match self.phase {
+            Phase::Play => (),
+            Phase::Discard => {
+                writeln!(out, "Discard down to {} coins", self.config.max_coins).unwrap()
+            }
+            Phase::ChooseNobel => {
+                writeln!(out, "Choose a nobel:").unwrap();
+                for i in self.nobel_candidates() {
+                    writeln!(out, "   Nobel {i}: {:?}", self.nobels[i]).unwrap();
+                }
+            }
+        }
+    }
+
+    /// Indices of the nobels the current player can claim.
+    pub fn nobel_candidates(&self) -> Vec<usize> {
+        let player = &self.players[self.turn];
+        (0..self.nobels.len())
+            .filter(|&i| self.nobels[i].claimable_by(player))
+            .collect()
+    }
+
+    /// Ends the current player's turn. One nobel visits per turn: it is given
+    /// right away if only one qualifies, otherwise the player has to choose.
+    pub fn change_player(&mut self, events: &mut Vec<GameEvent>) {
+        match self.nobel_candidates()[..] {
+            [] => (),
+            [index] => self.visit(index, events),
+            _ => {
+                self.phase = Phase::ChooseNobel;
+                return;
+            }
         }
-        println!("Turn {}", self.players[self.turn].display_name);
+        self.next_turn();
     }
 
-    pub fn change_player(&mut self) {
-        self.turn += 1;
-        if self.turn == self.players.len() {
-            self.turn = 0;
+    fn visit(&mut self, index: usize, events: &mut Vec<GameEvent>) {
+        let nobel = self.nobels.remove(index);
+        let player = &mut self.players[self.turn];
+        player.score += nobel.score;
+        player.record.nobel_points += nobel.score;
+        player.record.nobels += 1;
+        if nobel.ends_game {
+            player.record.cities += 1;
         }
+        events.push(GameEvent::NobelVisited {
+            player: self.turn,
+            index,
+            nobel,
+        });
     }
 
-    pub fn card_iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+    /// Passes the turn to the next player who hasn't resigned.
+    fn next_turn(&mut self) {
+        self.players[self.turn].record.turns += 1;
+        for _ in 0..self.players.len() {
+            self.turn += 1;
+            if self.turn == self.players.len() {
+                self.turn = 0;
+            }
+            if self.turn == self.first_player {
+                self.round += 1;
+            }
+            if !self.players[self.turn].resigned {
+                break;
+            }
+        }
+    }
+
+    /// Every action [`State::run`] accepts in this state, found without
+    /// running any of them.
+    pub fn legal_actions(&self) -> impl Iterator<Item = Action> + '_ {
+        let mut r = vec![];
+        self.legal_actions_into(&mut r);
+        r.into_iter()
+    }
+
+    /// [`State::legal_actions`] appended to `r`, so a caller can reuse the
+    /// buffer. In the play phase purchases come first and [`Action::Skip`] is
+    /// only there when nothing else is legal. [`Action::Resign`] is always last.
+    pub fn legal_actions_into(&self, r: &mut Vec<Action>) {
+        let start = r.len();
+        let player = &self.players[self.turn];
+        match self.phase {
+            Phase::Discard => {
+                r.extend(
+                    self.discard_iter()
+                        .map(|(coins, wilds)| Action::Discard { coins, wilds }),
+                );
+                r.push(Action::Resign);
+                return;
+            }
+            Phase::ChooseNobel => {
+                r.extend(
+                    self.nobel_candidates()
+                        .into_iter()
+                        .map(|index| Action::ChooseNobel { index }),
+                );
+                r.push(Action::Resign);
+                return;
+            }
+            Phase::Play => (),
+        }
+        for (deck, card) in self.card_iter() {
+            if player.can_purchase(&self.decks[deck][card].cost) {
+                r.push(Action::Purchase {
+                    deck,
+                    card,
+                    payment: None,
+                    wilds: None,
+                    id: None,
+                });
+            }
+        }
+        for (index, c) in player.reserved.iter().enumerate() {
+            if player.can_purchase(&c.cost) {
+                r.push(Action::PurchaseReserved {
+                    index,
+                    payment: None,
+                    wilds: None,
+                });
+            }
+        }
+        r.extend(
+            self.pick_three_iter()
+                .map(|(one, two, three)| Action::PickThree { one, two, three }),
+        );
+        r.extend(self.pick_two_iter().map(|color| Action::PickTwo { color }));
+        r.extend(
+            self.pick_fewer_iter()
+                .map(|colors| Action::PickFewer { colors }),
+        );
+        if player.reserved.len() < self.config.max_reserved {
+            r.extend(self.card_iter().map(|(deck, card)| Action::Reserve {
+                deck,
+                card,
+                id: None,
+            }));
+            r.extend(
+                self.blind_reserve_iter()
+                    .map(|deck| Action::ReserveBlind { deck }),
+            );
+        }
+        if r.len() == start {
+            r.push(Action::Skip);
+        }
+        r.push(Action::Resign);
+    }
+
+    /// [`State::legal_actions`] together with the state each one leads to.
+    pub fn successors(&self) -> impl Iterator<Item = (Action, State)> + '_ {
+        self.legal_actions().map(|action| {
+            let mut s = self.clone();
+            s.run(action.clone())
+                .expect("legal_actions yielded an action run rejects");
+            (action, s)
+        })
+    }
+
+    /// Every face up or reserved card `player` can afford right now, with the
+    /// payment [`Player::payment_for`] picks for it.
+    pub fn purchasable_cards(&self, player: usize) -> Vec<(CardSlot, CoinMap)> {
+        let p = &self.players[player];
+        let visible = self.card_iter().filter_map(|(deck, card)| {
+            let payment = p.payment_for(&self.decks[deck][card].cost)?;
+            Some((CardSlot::Visible { deck, card }, payment))
+        });
+        let reserved = p.reserved.iter().enumerate().filter_map(|(index, c)| {
+            let payment = p.payment_for(&c.cost)?;
+            Some((CardSlot::Reserved { index }, payment))
+        });
+        visible.chain(reserved).collect()
+    }
+
+    /// Visible cards that `player` can afford right now, highest score first.
+    pub fn affordable_cards(&self, player: usize) -> Vec<(Tier, usize)> {
+        let player = &self.players[player];
+        let mut r: Vec<_> = self
+            .card_iter()
+            .filter(|&(deck, card)| player.can_purchase(&self.decks[deck][card].cost))
+            .collect();
+        r.sort_by_key(|&(deck, card)| std::cmp::Reverse(self.decks[deck][card].score));
+        r
+    }
+
+    /// Nobels that `player` would get by buying one more card of the right color.
+    pub fn nobels_in_reach(&self, player: usize) -> Vec<usize> {
+        let player = &self.players[player];
+        (0..self.nobels.len())
+            .filter(|&i| player.immortal.deficit(&self.nobels[i].cost).sum() == 1)
+            .collect()
+    }
+
+    pub fn card_iter(&self) -> impl Iterator<Item = (Tier, usize)> + '_ {
         self.decks
             .iter()
-            .enumerate()
-            .flat_map(|(x, t)| (0..MAX_DECK_SHOW.min(t.len())).map(move |y| (x, y)))
+            .flat_map(|(x, t)| (0..self.config.visible_cards.min(t.len())).map(move |y| (x, y)))
     }
 
+    /// Decks that still have a face down card to reserve with [`Action::ReserveBlind`].
+    pub fn blind_reserve_iter(&self) -> impl Iterator<Item = Tier> + '_ {
+        self.decks
+            .iter()
+            .filter(|(_, d)| d.len() > self.config.visible_cards)
+            .map(|(tier, _)| tier)
+    }
+
+    /// Exactly the [`Action::PickTwo`] colors [`State::run`] accepts in the play phase.
     pub fn pick_two_iter(&self) -> impl Iterator<Item = ResourceKind> + '_ {
-        self.coins.0.iter().filter(|x| *x.1 >= 4).map(|x| x.0)
+        self.coins
+            .gems
+            .iter()
+            .filter(|x| *x.1 >= self.config.pick_two_minimum)
+            .map(|x| x.0)
     }
 
+    /// The [`Action::PickFewer`] colors, when the bank is down to one or two
+    /// colors. Together with [`State::pick_three_iter`] these are exactly the
+    /// one-coin-per-color picks [`State::run`] accepts in the play phase.
+    pub fn pick_fewer_iter(&self) -> impl Iterator<Item = Vec<ResourceKind>> {
+        let colors: Vec<_> = self
+            .coins
+            .gems
+            .iter()
+            .filter(|x| *x.1 > 0)
+            .map(|x| x.0)
+            .collect();
+        Some(colors)
+            .filter(|x| (1..3).contains(&x.len()))
+            .into_iter()
+    }
+
+    /// Every way the current player can get back down to [`GameConfig::max_coins`] coins,
+    /// as `(coins, wilds)` pairs.
+    pub fn discard_iter(&self) -> impl Iterator<Item = (ResourceMap, usize)> {
+        let player = &self.players[self.turn];
+        let held: Vec<(CoinKind, usize)> = player.mortal.iter().collect();
+        let mut r = vec![];
+        let mut current = CoinMap::new();
+        fn go(
+            held: &[(CoinKind, usize)],
+            left: usize,
+            current: &mut CoinMap,
+            r: &mut Vec<(ResourceMap, usize)>,
+        ) {
+            let Some(((kind, count), rest)) = held.split_first() else {
+                if left == 0 {
+                    r.push((current.gems.clone(), current.gold));
+                }
+                return;
+            };
+            for n in 0..=left.min(*count) {
+                current[*kind] = n;
+                go(rest, left - n, current, r);
+            }
+        }
+        go(
+            &held,
+            player.coin_count().saturating_sub(self.config.max_coins),
+            &mut current,
+            &mut r,
+        );
+        r.into_iter()
+    }
+
+    /// Exactly the [`Action::PickThree`] triples [`State::run`] accepts in the
+    /// play phase: those whose colors are all left in the bank.
     pub fn pick_three_iter(
         &self,
     ) -> impl Iterator<Item = (ResourceKind, ResourceKind, ResourceKind)> + '_ {
@@ -351,39 +2691,546 @@ impl State {
             (Green, White, Black),
             (Blue, Black, White),
         ];
-        CANDIDATES.into_iter()
+        CANDIDATES
+            .into_iter()
+            .filter(|(one, two, three)| [one, two, three].iter().all(|c| self.coins[**c] > 0))
     }
 }
 
-#[derive(Debug, Clone, clap::Parser, Serialize, Deserialize)]
+/// The payment given with a purchase, or the greedy one if none was given.
+fn chosen_payment(
+    player: &Player,
+    cost: &ResourceMap,
+    coins: Option<ResourceMap>,
+    wilds: Option<usize>,
+) -> Result<CoinMap, RuleError> {
+    if coins.is_none() && wilds.is_none() {
+        return player
+            .payment_for(cost)
+            .ok_or_else(|| player.shortfall(cost));
+    }
+    let payment = CoinMap {
+        gems: coins.unwrap_or_default(),
+        gold: wilds.unwrap_or(0),
+    };
+    player.check_payment(cost, &payment)?;
+    Ok(payment)
+}
+
+/// Serialized with a `"type"` tag, like `{"type":"pick_two","color":"r"}`.
+/// Colors are written as their [`ResourceKind::code`], but payments and
+/// discards are [`ResourceMap`]s keyed by color name as everywhere else.
+/// There is an example of each variant in `game-def/fixtures/actions`:
+///
+/// ```
+/// use game_def::Action;
+///
+/// let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/actions");
+/// for entry in std::fs::read_dir(dir).unwrap() {
+///     let json = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+///     let action: Action = serde_json::from_str(&json).unwrap();
+///     assert_eq!(serde_json::to_string(&action).unwrap(), json.trim());
+/// }
+///
+/// // Color names are read too.
+/// let old: Action = serde_json::from_str(r#"{"type":"pick_two","color":"red"}"#).unwrap();
+/// assert_eq!(old, "take r r".parse().unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::Parser))]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum Action {
     PickThree {
+        #[serde(with = "color_code")]
         one: ResourceKind,
+        #[serde(with = "color_code")]
         two: ResourceKind,
+        #[serde(with = "color_code")]
         three: ResourceKind,
     },
     PickTwo {
+        #[serde(with = "color_code")]
         color: ResourceKind,
     },
+    /// Takes one coin of every color left when the bank has fewer than three.
+    PickFewer {
+        #[cfg_attr(feature = "cli", arg(required = true, num_args = 1..=2))]
+        #[serde(with = "color_code::list")]
+        colors: Vec<ResourceKind>,
+    },
+    /// Without `payment` and `wilds`, pays with colored coins first and wilds
+    /// for the rest.
     Purchase {
-        deck: usize,
+        deck: Tier,
         card: usize,
+        #[cfg_attr(feature = "cli", arg(long))]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        payment: Option<ResourceMap>,
+        #[cfg_attr(feature = "cli", arg(long))]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        wilds: Option<usize>,
+        /// If given, the action fails unless this card is in the slot.
+        #[cfg_attr(feature = "cli", arg(long))]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<usize>,
     },
     PurchaseReserved {
         index: usize,
+        #[cfg_attr(feature = "cli", arg(long))]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        payment: Option<ResourceMap>,
+        #[cfg_attr(feature = "cli", arg(long))]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        wilds: Option<usize>,
     },
     Reserve {
-        deck: usize,
+        deck: Tier,
         card: usize,
+        /// If given, the action fails unless this card is in the slot.
+        #[cfg_attr(feature = "cli", arg(long))]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<usize>,
+    },
+    /// Reserves the top face down card of a deck.
+    ReserveBlind {
+        deck: Tier,
+    },
+    /// Returns coins to the bank after going over [`GameConfig::max_coins`].
+    Discard {
+        coins: ResourceMap,
+        #[cfg_attr(feature = "cli", arg(long, default_value_t = 0))]
+        #[serde(default)]
+        wilds: usize,
+    },
+    /// Picks the visiting nobel when more than one qualifies.
+    ChooseNobel {
+        index: usize,
     },
     Skip,
+    /// Leaves the game for good, returning all coins to the bank. Allowed in
+    /// every phase, so a pending discard or nobel choice is dropped. With one
+    /// player left, that player wins.
+    Resign,
 }
 
-pub fn ai_from_function(mut function: impl FnMut(State) -> Action) {
+/// Compact notation for logs and chat, like `take r g u` or `buy 2.3`, as
+/// [`notation`] describes it. [`FromStr`] parses the same notation back.
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn options(
+            f: &mut std::fmt::Formatter<'_>,
+            id: &Option<usize>,
+            payment: &Option<ResourceMap>,
+            wilds: &Option<usize>,
+        ) -> std::fmt::Result {
+            if let Some(id) = id {
+                write!(f, " id={id}")?;
+            }
+            if let Some(payment) = payment {
+                write!(f, " pay={payment}")?;
+            }
+            if let Some(wilds) = wilds {
+                write!(f, " wilds={wilds}")?;
+            }
+            Ok(())
+        }
+        match self {
+            Action::PickThree { one, two, three } => {
+                write!(f, "take {} {} {}", one.code(), two.code(), three.code())
+            }
+            Action::PickTwo { color } => write!(f, "take {0} {0}", color.code()),
+            Action::PickFewer { colors } => {
+                write!(f, "take")?;
+                for c in colors {
+                    write!(f, " {}", c.code())?;
+                }
+                Ok(())
+            }
+            Action::Purchase {
+                deck,
+                card,
+                payment,
+                wilds,
+                id,
+            } => {
+                write!(f, "buy {deck}.{card}")?;
+                options(f, id, payment, wilds)
+            }
+            Action::PurchaseReserved {
+                index,
+                payment,
+                wilds,
+            } => {
+                write!(f, "buy-reserved {index}")?;
+                options(f, &None, payment, wilds)
+            }
+            Action::Reserve { deck, card, id } => {
+                write!(f, "reserve {deck}.{card}")?;
+                options(f, id, &None, &None)
+            }
+            Action::ReserveBlind { deck } => write!(f, "reserve-blind {deck}"),
+            Action::Discard { coins, wilds } => {
+                write!(f, "discard")?;
+                if coins.sum() > 0 {
+                    write!(f, " {coins}")?;
+                }
+                if *wilds > 0 {
+                    write!(f, " wilds={wilds}")?;
+                }
+                Ok(())
+            }
+            Action::ChooseNobel { index } => write!(f, "nobel {index}"),
+            Action::Skip => write!(f, "skip"),
+            Action::Resign => write!(f, "resign"),
+        }
+    }
+}
+
+/// The notation [`Display`] writes. Anything else is an error, whatever a
+/// human types at the prompt:
+///
+/// ```
+/// use game_def::Action;
+///
+/// assert_eq!("discard 2r".parse::<Action>().unwrap().to_string(), "discard 2r");
+/// for text in ["discard 2é", "discard é", "buy 1.1 pay=2é", "buy-reserved 0 pay=2r+é"] {
+///     assert!(text.parse::<Action>().is_err(), "{text}");
+/// }
+/// ```
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> anyhow::Result<Self> {
+        let mut words = text.split_whitespace();
+        let keyword = words.next().context("Empty action")?;
+        let mut args = vec![];
+        let mut options = BTreeMap::new();
+        for word in words {
+            match word.split_once('=') {
+                Some((key, value)) => {
+                    if options.insert(key, value).is_some() {
+                        bail!("Option {key:?} is given twice in {text:?}");
+                    }
+                }
+                None => args.push(word),
+            }
+        }
+        let allowed: &[&str] = match keyword {
+            "buy" => &["id", "pay", "wilds"],
+            "buy-reserved" => &["pay", "wilds"],
+            "reserve" => &["id"],
+            "discard" => &["wilds"],
+            _ => &[],
+        };
+        if let Some(key) = options.keys().find(|k| !allowed.contains(k)) {
+            bail!("Unknown option {key:?} for {keyword:?}");
+        }
+        let number = |key: &str| -> anyhow::Result<Option<usize>> {
+            options
+                .get(key)
+                .map(|v| {
+                    v.parse()
+                        .with_context(|| format!("Invalid {key} {v:?} in {text:?}"))
+                })
+                .transpose()
+        };
+        let payment = options
+            .get("pay")
+            .map(|v| ResourceMap::try_from_code(v))
+            .transpose()?;
+        let positional = |count: std::ops::RangeInclusive<usize>| -> anyhow::Result<()> {
+            if !count.contains(&args.len()) {
+                bail!("Wrong number of arguments for {keyword:?} in {text:?}");
+            }
+            Ok(())
+        };
+        let index = |arg: &str| -> anyhow::Result<usize> {
+            arg.parse()
+                .with_context(|| format!("Invalid number {arg:?} in {text:?}"))
+        };
+        let slot = |arg: &str| -> anyhow::Result<(Tier, usize)> {
+            let (deck, card) = arg
+                .split_once('.')
+                .with_context(|| format!("Expected deck.card, found {arg:?}"))?;
+            Ok((deck.parse()?, index(card)?))
+        };
+        Ok(match keyword {
+            "take" => {
+                positional(0..=3)?;
+                let colors = args
+                    .iter()
+                    .map(|a| a.parse().with_context(|| format!("In {text:?}")))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                match colors[..] {
+                    [one, two] if one == two => Action::PickTwo { color: one },
+                    [one, two, three] => {
+                        if one == two || one == three || two == three {
+                            bail!("Taking three coins needs three different colors");
+                        }
+                        Action::PickThree { one, two, three }
+                    }
+                    _ => Action::PickFewer { colors },
+                }
+            }
+            "buy" => {
+                positional(1..=1)?;
+                let (deck, card) = slot(args[0])?;
+                Action::Purchase {
+                    deck,
+                    card,
+                    payment,
+                    wilds: number("wilds")?,
+                    id: number("id")?,
+                }
+            }
+            "buy-reserved" => {
+                positional(1..=1)?;
+                Action::PurchaseReserved {
+                    index: index(args[0])?,
+                    payment,
+                    wilds: number("wilds")?,
+                }
+            }
+            "reserve" => {
+                positional(1..=1)?;
+                let (deck, card) = slot(args[0])?;
+                Action::Reserve {
+                    deck,
+                    card,
+                    id: number("id")?,
+                }
+            }
+            "reserve-blind" => {
+                positional(1..=1)?;
+                Action::ReserveBlind {
+                    deck: args[0].parse()?,
+                }
+            }
+            "discard" => {
+                positional(0..=1)?;
+                Action::Discard {
+                    coins: args
+                        .first()
+                        .map_or(Ok(ResourceMap::new()), |c| ResourceMap::try_from_code(c))?,
+                    wilds: number("wilds")?.unwrap_or(0),
+                }
+            }
+            "nobel" => {
+                positional(1..=1)?;
+                Action::ChooseNobel {
+                    index: index(args[0])?,
+                }
+            }
+            "skip" => {
+                positional(0..=0)?;
+                Action::Skip
+            }
+            "resign" => {
+                positional(0..=0)?;
+                Action::Resign
+            }
+            _ => bail!("Unknown action {keyword:?}"),
+        })
+    }
+}
+
+/// Runs an agent over the runner's protocol. The runner sends an
+/// [`Observation`] each turn; anything it deserializes into can be taken here.
+///
+/// Speaks JSON lines, or [`msgpack`] frames when the agent was started with
+/// `--format msgpack`. Returns once the runner closes stdin, or with an error
+/// (naming the offending input) when a message can't be read.
+#[cfg(feature = "stdio")]
+pub fn ai_from_function<T: serde::de::DeserializeOwned>(
+    mut function: impl FnMut(T) -> Action,
+) -> anyhow::Result<()> {
+    try_ai_from_function(|x| Ok(function(x)))
+}
+
+/// [`ai_from_function`] for agent logic that can fail. The first error stops
+/// the loop and is returned.
+#[cfg(feature = "stdio")]
+pub fn try_ai_from_function<T: serde::de::DeserializeOwned>(
+    function: impl FnMut(T) -> anyhow::Result<Action>,
+) -> anyhow::Result<()> {
+    let msgpack = arg_is("--format", "msgpack");
+    ai_loop(
+        std::io::stdin().lock(),
+        std::io::stdout().lock(),
+        msgpack,
+        transcript::Transcript::from_env(msgpack, false).as_ref(),
+        function,
+    )
+}
+
+/// Whether the process was started with `flag value`.
+#[cfg(feature = "stdio")]
+fn arg_is(flag: &str, value: &str) -> bool {
+    let args: Vec<String> = std::env::args().collect();
+    args.windows(2).any(|w| w[0] == flag && w[1] == value)
+}
+
+/// The loop behind [`try_ai_from_function`], over any reader and writer.
+/// `msgpack` picks [`msgpack`] frames over JSON lines and needs the `msgpack`
+/// feature. The output is flushed after every action.
+pub fn ai_over_io<T: serde::de::DeserializeOwned>(
+    input: impl std::io::BufRead,
+    output: impl std::io::Write,
+    msgpack: bool,
+    function: impl FnMut(T) -> anyhow::Result<Action>,
+) -> anyhow::Result<()> {
+    ai_loop(input, output, msgpack, None, function)
+}
+
+/// [`ai_over_io`], writing down every message in `transcript`.
+fn ai_loop<T: serde::de::DeserializeOwned>(
+    mut input: impl std::io::BufRead,
+    mut output: impl std::io::Write,
+    msgpack: bool,
+    transcript: Option<&transcript::Transcript>,
+    mut function: impl FnMut(T) -> anyhow::Result<Action>,
+) -> anyhow::Result<()> {
+    while let Some(message) = read_message(&mut input, msgpack, transcript)? {
+        let action = function(message)?;
+        write_message(&mut output, msgpack, &action, transcript)?;
+    }
+    Ok(())
+}
+
+/// One JSON line or [`msgpack`] frame, `None` at the end of the input.
+fn read_message<T: serde::de::DeserializeOwned>(
+    input: &mut impl std::io::BufRead,
+    msgpack: bool,
+    transcript: Option<&transcript::Transcript>,
+) -> anyhow::Result<Option<T>> {
+    let bytes = if msgpack {
+        #[cfg(feature = "msgpack")]
+        {
+            let Some(frame) = msgpack::read_frame(input).context("Failed to read a frame")? else {
+                return Ok(None);
+            };
+            frame
+        }
+        #[cfg(not(feature = "msgpack"))]
+        bail!("Built without the msgpack feature");
+    } else {
+        let mut line = vec![];
+        if input
+            .read_until(b'\n', &mut line)
+            .context("Failed to read a line")?
+            == 0
+        {
+            return Ok(None);
+        }
+        line
+    };
+    if let Some(transcript) = transcript {
+        let wire = match msgpack {
+            true => [&(bytes.len() as u32).to_be_bytes()[..], &bytes].concat(),
+            false => bytes.clone(),
+        };
+        transcript.message(transcript::Direction::ToAgent, &wire);
+    }
+    decode_message(&bytes, msgpack).map(Some)
+}
+
+/// Writes and flushes one JSON line or [`msgpack`] frame.
+fn write_message(
+    output: &mut impl std::io::Write,
+    msgpack: bool,
+    message: &impl Serialize,
+    transcript: Option<&transcript::Transcript>,
+) -> anyhow::Result<()> {
+    let wire = encode_message(message, msgpack)?;
+    if let Some(transcript) = transcript {
+        transcript.message(transcript::Direction::FromAgent, &wire);
+    }
+    output.write_all(&wire)?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Parses one message as it comes off the wire: a JSON line, newline or not,
+/// or the body of a [`msgpack`] frame without its length prefix. The agent
+/// loops read with this, so a loop on another IO stack (an async runtime, a
+/// socket) can share it.
+pub fn decode_message<T: serde::de::DeserializeOwned>(
+    bytes: &[u8],
+    msgpack: bool,
+) -> anyhow::Result<T> {
+    if msgpack {
+        #[cfg(feature = "msgpack")]
+        return msgpack::from_slice(bytes).with_context(|| format!("Malformed frame {bytes:?}"));
+        #[cfg(not(feature = "msgpack"))]
+        bail!("Built without the msgpack feature");
+    }
+    serde_json::from_slice(bytes).with_context(|| {
+        format!(
+            "Malformed message {:?}",
+            String::from_utf8_lossy(bytes).trim_end()
+        )
+    })
+}
+
+/// The bytes to write for one message: a JSON line with its newline, or a
+/// [`msgpack`] frame with its length prefix. See [`decode_message`].
+pub fn encode_message(message: &impl Serialize, msgpack: bool) -> anyhow::Result<Vec<u8>> {
+    let mut r = vec![];
+    if msgpack {
+        #[cfg(feature = "msgpack")]
+        msgpack::write_frame(&mut r, &msgpack::to_vec(message)?)?;
+        #[cfg(not(feature = "msgpack"))]
+        bail!("Built without the msgpack feature");
+    } else {
+        serde_json::to_writer(&mut r, message)?;
+        r.push(b'\n');
+    }
+    Ok(r)
+}
+
+/// [`ai_from_function`] for agents that await while they think, on whatever
+/// async runtime they use. `read_line` gives the next JSON line the runner
+/// sent, `None` once its input is closed, which ends the loop. `write` writes
+/// and flushes the bytes of each reply. With tokio those are `next_line` on
+/// the lines of a `BufReader` over `stdin()`, and `write_all` then `flush`
+/// on `stdout()`.
+///
+/// `function` is also given when it should be done by, `soft_deadline`
+/// after its message was read, to time-box its own work. Nothing stops it
+/// when it runs past that.
+pub async fn ai_from_function_async<T: serde::de::DeserializeOwned>(
+    mut read_line: impl std::ops::AsyncFnMut() -> std::io::Result<Option<String>>,
+    mut write: impl std::ops::AsyncFnMut(Vec<u8>) -> std::io::Result<()>,
+    soft_deadline: Option<std::time::Duration>,
+    mut function: impl std::ops::AsyncFnMut(T, Option<std::time::Instant>) -> Action,
+) -> anyhow::Result<()> {
+    while let Some(line) = read_line().await.context("Failed to read a line")? {
+        let deadline = soft_deadline.map(|d| std::time::Instant::now() + d);
+        let message = decode_message(line.as_bytes(), false)?;
+        let action = function(message, deadline).await;
+        write(encode_message(&action, false)?)
+            .await
+            .context("Failed to write an action")?;
+    }
+    Ok(())
+}
+
+/// [`run_agent`] for an in-process [`Agent`], which gets the state of each
+/// [`Observation`].
+#[cfg(feature = "stdio")]
+pub fn ai_from_agent(mut agent: impl Agent) -> anyhow::Result<()> {
+    run_agent(move |observation: Observation| agent.act(&observation.state))
+}
+
+/// Like [`ai_from_function`], but for observers: the callback sees every
+/// state of the game and nothing is ever written back. The other
+/// [`ObserverMessage`]s are skipped.
+#[cfg(feature = "stdio")]
+pub fn observer_from_function(mut function: impl FnMut(State)) {
     for line in std::io::stdin().lines() {
         let line = line.unwrap();
-        let state = serde_json::from_str(&line).unwrap();
-        let action = function(state);
-        println!("{}", serde_json::to_string(&action).unwrap());
+        if let ObserverMessage::State { state } = serde_json::from_str(&line).unwrap() {
+            function(*state);
+        }
     }
 }
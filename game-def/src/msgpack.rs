@@ -0,0 +1,781 @@
+//! MessagePack encoding of the protocol messages, as a faster alternative to JSON
+//! lines. Structs are written as maps and enums the way serde_json writes them, so
+//! the two formats carry the same field names and tags.
+//!
+//! On the wire every message is a frame: a big endian `u32` length, then the bytes.
+
+use std::{
+    fmt::Display,
+    io::{self, Read, Write},
+};
+
+use serde::{
+    de::{self, DeserializeOwned, IntoDeserializer, Visitor},
+    ser::{self, Serialize},
+};
+
+pub fn to_vec<T: Serialize>(value: &T) -> anyhow::Result<Vec<u8>> {
+    let mut serializer = Serializer { out: vec![] };
+    value.serialize(&mut serializer)?;
+    Ok(serializer.out)
+}
+
+/// Arrays and maps nested deeper than this are an error instead of running
+/// out of stack, like in serde_json.
+pub const MAX_DEPTH: usize = 128;
+
+/// The most bytes [`read_frame`] reads in one frame, far more than the
+/// largest observation.
+pub const MAX_FRAME: usize = 4 << 20;
+
+/// Decodes one message, all of `bytes`.
+///
+/// ```
+/// use game_def::{msgpack, Action};
+///
+/// let skip = msgpack::to_vec(&Action::Skip).unwrap();
+/// assert_eq!(msgpack::from_slice::<Action>(&skip).unwrap(), Action::Skip);
+///
+/// // {"x": [[[...]]], "type": "skip"}, nested a million deep.
+/// let mut bytes = vec![0x82, 0xa1, b'x'];
+/// bytes.extend(std::iter::repeat(0x91).take(1_000_000));
+/// bytes.push(0xc0);
+/// bytes.extend([0xa4, b't', b'y', b'p', b'e', 0xa4, b's', b'k', b'i', b'p']);
+/// let error = msgpack::from_slice::<Action>(&bytes).unwrap_err();
+/// assert!(error.to_string().contains("nested"), "{error}");
+/// ```
+pub fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> anyhow::Result<T> {
+    let mut deserializer = Deserializer {
+        input: bytes,
+        depth: 0,
+    };
+    let value = T::deserialize(&mut deserializer)?;
+    if !deserializer.input.is_empty() {
+        anyhow::bail!(
+            "{} trailing bytes after the message",
+            deserializer.input.len()
+        );
+    }
+    Ok(value)
+}
+
+pub fn write_frame(writer: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(bytes.len()).map_err(io::Error::other)?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(bytes)?;
+    writer.flush()
+}
+
+/// `None` on a clean end of stream before a new frame, and an
+/// [`io::ErrorKind::InvalidData`] error for a frame over [`MAX_FRAME`]
+/// bytes, before reading or allocating it.
+///
+/// ```
+/// use std::io::ErrorKind;
+///
+/// use game_def::msgpack;
+///
+/// let mut frames = vec![];
+/// msgpack::write_frame(&mut frames, b"hi").unwrap();
+/// frames.extend(u32::MAX.to_be_bytes());
+/// let mut reader = &frames[..];
+/// assert_eq!(msgpack::read_frame(&mut reader).unwrap().unwrap(), b"hi");
+/// let error = msgpack::read_frame(&mut reader).unwrap_err();
+/// assert_eq!(error.kind(), ErrorKind::InvalidData);
+/// ```
+pub fn read_frame(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => (),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Frame of {len} bytes is over the limit of {MAX_FRAME}"),
+        ));
+    }
+    let mut bytes = vec![0; len];
+    reader.read_exact(&mut bytes)?;
+    Ok(Some(bytes))
+}
+
+#[derive(Debug)]
+pub struct Error(String);
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+struct Serializer {
+    out: Vec<u8>,
+}
+
+/// Array and map markers: the fix marker and its limit, then the 16 and 32 bit ones.
+const ARRAY: (u8, usize, u8, u8) = (0x90, 16, 0xdc, 0xdd);
+const MAP: (u8, usize, u8, u8) = (0x80, 16, 0xde, 0xdf);
+
+fn header(kind: (u8, usize, u8, u8), len: usize) -> Vec<u8> {
+    let (fix, limit, m16, m32) = kind;
+    if len < limit {
+        vec![fix | len as u8]
+    } else if let Ok(len) = u16::try_from(len) {
+        let mut r = vec![m16];
+        r.extend(len.to_be_bytes());
+        r
+    } else {
+        let mut r = vec![m32];
+        r.extend((len as u32).to_be_bytes());
+        r
+    }
+}
+
+impl Serializer {
+    fn uint(&mut self, x: u64) {
+        if x < 0x80 {
+            self.out.push(x as u8);
+        } else if let Ok(x) = u8::try_from(x) {
+            self.out.extend([0xcc, x]);
+        } else if let Ok(x) = u16::try_from(x) {
+            self.out.push(0xcd);
+            self.out.extend(x.to_be_bytes());
+        } else if let Ok(x) = u32::try_from(x) {
+            self.out.push(0xce);
+            self.out.extend(x.to_be_bytes());
+        } else {
+            self.out.push(0xcf);
+            self.out.extend(x.to_be_bytes());
+        }
+    }
+
+    fn int(&mut self, x: i64) {
+        if x >= 0 {
+            self.uint(x as u64);
+        } else if x >= -32 {
+            self.out.push(x as u8);
+        } else if let Ok(x) = i8::try_from(x) {
+            self.out.push(0xd0);
+            self.out.extend(x.to_be_bytes());
+        } else if let Ok(x) = i16::try_from(x) {
+            self.out.push(0xd1);
+            self.out.extend(x.to_be_bytes());
+        } else if let Ok(x) = i32::try_from(x) {
+            self.out.push(0xd2);
+            self.out.extend(x.to_be_bytes());
+        } else {
+            self.out.push(0xd3);
+            self.out.extend(x.to_be_bytes());
+        }
+    }
+
+    fn str(&mut self, s: &str) {
+        let len = s.len();
+        if len < 32 {
+            self.out.push(0xa0 | len as u8);
+        } else if let Ok(len) = u8::try_from(len) {
+            self.out.extend([0xd9, len]);
+        } else if let Ok(len) = u16::try_from(len) {
+            self.out.push(0xda);
+            self.out.extend(len.to_be_bytes());
+        } else {
+            self.out.push(0xdb);
+            self.out.extend((len as u32).to_be_bytes());
+        }
+        self.out.extend(s.as_bytes());
+    }
+
+    fn compound(&mut self, kind: (u8, usize, u8, u8), len: Option<usize>) -> Compound<'_> {
+        let start = self.out.len();
+        let header = header(kind, len.unwrap_or(0));
+        let header_len = header.len();
+        self.out.extend(header);
+        Compound {
+            ser: self,
+            kind,
+            start,
+            header_len,
+            declared: len.unwrap_or(0),
+            count: 0,
+        }
+    }
+}
+
+/// An array or map being written. The header is written up front from the
+/// length serde gives and fixed at the end if the real count differs.
+struct Compound<'a> {
+    ser: &'a mut Serializer,
+    kind: (u8, usize, u8, u8),
+    start: usize,
+    header_len: usize,
+    declared: usize,
+    count: usize,
+}
+
+impl Compound<'_> {
+    fn finish(self) -> Result<()> {
+        if self.count != self.declared {
+            let header = header(self.kind, self.count);
+            self.ser
+                .out
+                .splice(self.start..self.start + self.header_len, header);
+        }
+        Ok(())
+    }
+
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.count += 1;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn entry<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<()> {
+        self.count += 1;
+        self.ser.str(key);
+        value.serialize(&mut *self.ser)
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.out.push(if v { 0xc3 } else { 0xc2 });
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.int(v.into());
+        Ok(())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.int(v.into());
+        Ok(())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.int(v.into());
+        Ok(())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.int(v);
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.uint(v.into());
+        Ok(())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.uint(v.into());
+        Ok(())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.uint(v.into());
+        Ok(())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.uint(v);
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.out.push(0xca);
+        self.out.extend(v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.out.push(0xcb);
+        self.out.extend(v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.str(v.encode_utf8(&mut [0; 4]));
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.str(v);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        if let Ok(len) = u8::try_from(v.len()) {
+            self.out.extend([0xc4, len]);
+        } else if let Ok(len) = u16::try_from(v.len()) {
+            self.out.push(0xc5);
+            self.out.extend(len.to_be_bytes());
+        } else {
+            self.out.push(0xc6);
+            self.out.extend((v.len() as u32).to_be_bytes());
+        }
+        self.out.extend(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.out.push(0xc0);
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.out.push(0xc0);
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.str(variant);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.out.push(0x81);
+        self.str(variant);
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Compound<'a>> {
+        Ok(self.compound(ARRAY, len))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a>> {
+        Ok(self.compound(ARRAY, Some(len)))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Compound<'a>> {
+        Ok(self.compound(ARRAY, Some(len)))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a>> {
+        self.out.push(0x81);
+        self.str(variant);
+        Ok(self.compound(ARRAY, Some(len)))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Compound<'a>> {
+        Ok(self.compound(MAP, len))
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Compound<'a>> {
+        Ok(self.compound(MAP, Some(len)))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a>> {
+        self.out.push(0x81);
+        self.str(variant);
+        Ok(self.compound(MAP, Some(len)))
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        self.count += 1;
+        key.serialize(&mut *self.ser)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.entry(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.entry(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+struct Deserializer<'de> {
+    input: &'de [u8],
+    /// How many arrays and maps the value being read is in.
+    depth: usize,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Reads the content of an array or map with `read`, one level deeper.
+    fn nested<T>(&mut self, read: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= MAX_DEPTH {
+            return Err(Error(format!(
+                "Arrays and maps are nested more than {MAX_DEPTH} deep"
+            )));
+        }
+        self.depth += 1;
+        let value = read(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'de [u8]> {
+        if self.input.len() < n {
+            return Err(Error("Message ends in the middle of a value".into()));
+        }
+        let (head, rest) = self.input.split_at(n);
+        self.input = rest;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn peek(&self) -> Result<u8> {
+        self.input
+            .first()
+            .copied()
+            .ok_or_else(|| Error("Message ends before a value".into()))
+    }
+
+    fn len(&mut self, bytes: usize) -> Result<usize> {
+        Ok(match bytes {
+            1 => u8::from_be_bytes(self.array()?) as usize,
+            2 => u16::from_be_bytes(self.array()?) as usize,
+            _ => u32::from_be_bytes(self.array()?) as usize,
+        })
+    }
+
+    fn str(&mut self, len: usize) -> Result<&'de str> {
+        std::str::from_utf8(self.take(len)?).map_err(|_| Error("String is not UTF-8".into()))
+    }
+}
+
+struct Access<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    left: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for Access<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>> {
+        if self.left == 0 {
+            return Ok(None);
+        }
+        self.left -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Access<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.left == 0 {
+            return Ok(None);
+        }
+        self.left -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left)
+    }
+}
+
+/// An enum written as a one entry map from the variant name to its content.
+struct Enum<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de> de::EnumAccess<'de> for Enum<'_, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let variant = seed.deserialize(&mut *self.de)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Enum<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let marker = self.take(1)?[0];
+        match marker {
+            0x00..=0x7f => visitor.visit_u64(marker.into()),
+            0x80..=0x8f => self.nested(|de| {
+                visitor.visit_map(Access {
+                    de,
+                    left: (marker & 0x0f).into(),
+                })
+            }),
+            0x90..=0x9f => self.nested(|de| {
+                visitor.visit_seq(Access {
+                    de,
+                    left: (marker & 0x0f).into(),
+                })
+            }),
+            0xa0..=0xbf => visitor.visit_borrowed_str(self.str((marker & 0x1f).into())?),
+            0xc0 => visitor.visit_unit(),
+            0xc2 => visitor.visit_bool(false),
+            0xc3 => visitor.visit_bool(true),
+            0xc4..=0xc6 => {
+                let len = self.len(1 << (marker - 0xc4))?;
+                visitor.visit_borrowed_bytes(self.take(len)?)
+            }
+            0xca => visitor.visit_f32(f32::from_be_bytes(self.array()?)),
+            0xcb => visitor.visit_f64(f64::from_be_bytes(self.array()?)),
+            0xcc => visitor.visit_u8(u8::from_be_bytes(self.array()?)),
+            0xcd => visitor.visit_u16(u16::from_be_bytes(self.array()?)),
+            0xce => visitor.visit_u32(u32::from_be_bytes(self.array()?)),
+            0xcf => visitor.visit_u64(u64::from_be_bytes(self.array()?)),
+            0xd0 => visitor.visit_i8(i8::from_be_bytes(self.array()?)),
+            0xd1 => visitor.visit_i16(i16::from_be_bytes(self.array()?)),
+            0xd2 => visitor.visit_i32(i32::from_be_bytes(self.array()?)),
+            0xd3 => visitor.visit_i64(i64::from_be_bytes(self.array()?)),
+            0xd9..=0xdb => {
+                let len = self.len(1 << (marker - 0xd9))?;
+                visitor.visit_borrowed_str(self.str(len)?)
+            }
+            0xdc | 0xdd => {
+                let left = self.len(2 << (marker - 0xdc))?;
+                self.nested(|de| visitor.visit_seq(Access { de, left }))
+            }
+            0xde | 0xdf => {
+                let left = self.len(2 << (marker - 0xde))?;
+                self.nested(|de| visitor.visit_map(Access { de, left }))
+            }
+            0xe0..=0xff => visitor.visit_i8(marker as i8),
+            _ => Err(Error(format!(
+                "Unsupported MessagePack marker {marker:#04x}"
+            ))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.peek()? == 0xc0 {
+            self.take(1)?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.peek()? {
+            0x81 => {
+                self.take(1)?;
+                self.nested(|de| visitor.visit_enum(Enum { de }))
+            }
+            0xa0..=0xbf | 0xd9..=0xdb => {
+                let variant: &str = de::Deserialize::deserialize(&mut *self)?;
+                visitor.visit_enum(variant.into_deserializer())
+            }
+            marker => Err(Error(format!(
+                "Expected an enum, found marker {marker:#04x}"
+            ))),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
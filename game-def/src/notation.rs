@@ -0,0 +1,221 @@
+//! Games as plain text, for sharing in chat and writing up: a few `Key:
+//! value` header lines, then a numbered line for each round with the moves in
+//! the order they were played, separated by `;`.
+//!
+//! ```text
+//! Date: 2024-11-02
+//! Player 1: alice
+//! Agent 1: ./alice-agent
+//! Player 2: bob
+//! Agent 2: ./bob-agent
+//! First: alice
+//! Seed: 42
+//! Result: bob won
+//!
+//! 1. take r u g; reserve 2.1 (+gold)
+//! 2. take r r; take w k u
+//! ...
+//! 24. buy 2.0 (nobel 4r+4g); buy-reserved 0
+//! ```
+//!
+//! Each move is an [`Action`] in the notation of its `Display`, which its
+//! `FromStr` reads back:
+//!
+//! - `take r g u` takes three coins of different colors, `take r r` two of
+//!   one, and `take r` or `take r g` the colors left when there are fewer.
+//!   Colors are `r`ed, bl`u`e, `g`reen, `w`hite and blac`k`.
+//! - `buy 2.3` buys card 3 of deck 2, `reserve 2.3` reserves it, and
+//!   `reserve-blind 2` reserves the top face down card of deck 2.
+//! - `buy-reserved 0` buys the first reserved card.
+//! - `discard 2r+1u` returns coins over the limit, `discard wilds=1` gold.
+//! - `nobel 1` picks the second of the nobels that qualify at once.
+//! - `skip` and `resign`.
+//!
+//! Decks are numbered from 1 as in [`crate::Tier`]'s `Display`, everything
+//! else from 0. Optional fields follow as `key=value`, like `buy 2.3 pay=2r
+//! wilds=1 id=17`.
+//!
+//! Parentheses note what a move led to, `(+gold)` for the gold coin of a
+//! reserve and `(nobel 3r+3u+3k)` for a visit. They are left out when reading,
+//! as are blank lines and lines starting with `#`, so a game can be
+//! annotated. Who played each move isn't written down: playing the moves
+//! from the first state tells, see [`Notation::replay`].
+
+use std::fmt::Write;
+
+use anyhow::{bail, Context};
+
+use crate::{Action, GameEvent, GameResult, Replay, State};
+
+/// A game read from its notation.
+pub struct Notation {
+    /// The header lines, in order.
+    pub headers: Vec<(String, String)>,
+    pub actions: Vec<Action>,
+}
+
+impl Notation {
+    /// Reads `text`, checking the round numbers but not the moves, which
+    /// need the state they were played in.
+    pub fn parse(text: &str) -> anyhow::Result<Notation> {
+        let mut headers = vec![];
+        let mut actions = vec![];
+        let mut round = 0;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let numbered = line
+                .split_once(". ")
+                .and_then(|(n, moves)| Some((n.parse::<usize>().ok()?, moves)));
+            if let Some((n, moves)) = numbered {
+                if n <= round {
+                    bail!("Line {}: round {n} comes after round {round}", i + 1);
+                }
+                round = n;
+                for text in without_notes(moves).split(';') {
+                    let action = text
+                        .parse()
+                        .with_context(|| format!("Line {}: {:?}", i + 1, text.trim()))?;
+                    actions.push(action);
+                }
+            } else if round > 0 {
+                bail!("Line {}: expected a numbered round, found {line:?}", i + 1);
+            } else {
+                let (key, value) = line.split_once(':').with_context(|| {
+                    format!("Line {}: expected Key: value, found {line:?}", i + 1)
+                })?;
+                headers.push((key.trim().to_owned(), value.trim().to_owned()));
+            }
+        }
+        Ok(Notation { headers, actions })
+    }
+
+    /// The value of the first header called `key`.
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The game played from `initial_state`, with who played each move and
+    /// how it ended.
+    pub fn replay(&self, initial_state: State) -> anyhow::Result<Replay> {
+        let mut state = initial_state.clone();
+        let mut replay = Replay::new(initial_state);
+        for (ply, action) in self.actions.iter().enumerate() {
+            replay.push(state.turn, action.clone());
+            state
+                .run(action.clone())
+                .with_context(|| format!("Move {} `{action}` is invalid", ply + 1))?;
+        }
+        replay.result = state.result();
+        replay.end_reason = state.end_reason();
+        Ok(replay)
+    }
+}
+
+/// `moves` with the parenthesized notes cut out.
+fn without_notes(moves: &str) -> String {
+    let mut depth = 0usize;
+    moves
+        .chars()
+        .filter(|&c| {
+            match c {
+                '(' => depth += 1,
+                ')' => {
+                    depth = depth.saturating_sub(1);
+                    return false;
+                }
+                _ => (),
+            }
+            depth == 0
+        })
+        .collect()
+}
+
+impl Replay {
+    /// The game in notation. `headers` come first, like the date, then the
+    /// players with their agents, who moved first, the seed and the result
+    /// as far as the replay knows them.
+    ///
+    /// ```
+    /// use game_def::{notation::Notation, GameConfig, Replay, State};
+    ///
+    /// let mut state = State::setup(&["a", "b"], GameConfig::default(), &mut rand::thread_rng());
+    /// let mut replay = Replay::new(state.clone());
+    /// while !state.is_finished() {
+    ///     let action = state.legal_actions().next().unwrap();
+    ///     replay.push(state.turn, action.clone());
+    ///     state.run(action).unwrap();
+    /// }
+    /// replay.result = state.result();
+    /// replay.end_reason = state.end_reason();
+    /// let text = replay.to_notation(&[("Event", "Test".to_owned())]).unwrap();
+    /// assert!(text.starts_with("Event: Test\nPlayer 1: a\nPlayer 2: b\nFirst: a\nResult: "));
+    /// assert!(text.contains("\n1. "));
+    ///
+    /// let notation = Notation::parse(&text).unwrap();
+    /// assert_eq!(notation.header("Event"), Some("Test"));
+    /// assert!(notation.replay(replay.initial_state.clone()).unwrap() == replay);
+    /// ```
+    pub fn to_notation(&self, headers: &[(&str, String)]) -> anyhow::Result<String> {
+        if self.redacted {
+            bail!("A redacted replay can't be played back");
+        }
+        let mut out = String::new();
+        for (key, value) in headers {
+            writeln!(out, "{key}: {value}").unwrap();
+        }
+        let players = &self.initial_state.players;
+        for (i, player) in players.iter().enumerate() {
+            writeln!(out, "Player {}: {}", i + 1, player.display_name).unwrap();
+            if let Some(agent) = self.agents.get(i) {
+                writeln!(out, "Agent {}: {agent}", i + 1).unwrap();
+            }
+        }
+        let name = |i: usize| players[i].display_name.as_str();
+        writeln!(out, "First: {}", name(self.initial_state.turn)).unwrap();
+        if let Some(seed) = self.seed {
+            writeln!(out, "Seed: {seed}").unwrap();
+        }
+        let result = match &self.result {
+            GameResult::Ongoing => "unfinished".to_owned(),
+            GameResult::Won(winner) => format!("{} won", name(*winner)),
+            GameResult::Draw(drawn) => {
+                let drawn: Vec<&str> = drawn.iter().map(|&i| name(i)).collect();
+                format!("draw between {}", drawn.join(", "))
+            }
+        };
+        writeln!(out, "Result: {result}").unwrap();
+        let mut state = self.initial_state.clone();
+        let mut round = None;
+        for (ply, (_, action)) in self.actions.iter().enumerate() {
+            if round == Some(state.round) {
+                out.push_str("; ");
+            } else {
+                write!(out, "\n{}. ", state.round + 1).unwrap();
+                round = Some(state.round);
+            }
+            write!(out, "{action}").unwrap();
+            let events = state
+                .run(action.clone())
+                .with_context(|| format!("Action {ply} `{action}` is invalid"))?;
+            for event in events {
+                match event {
+                    GameEvent::WildReceived { .. } => out.push_str(" (+gold)"),
+                    GameEvent::NobelVisited { nobel, .. } => {
+                        write!(out, " (nobel {})", nobel.cost).unwrap()
+                    }
+                    _ => (),
+                }
+            }
+        }
+        if !self.actions.is_empty() {
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
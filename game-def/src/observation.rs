@@ -0,0 +1,371 @@
+//! What a single player is allowed to know about a game.
+
+use std::{collections::HashSet, fmt::Display};
+
+use anyhow::Context;
+
+use enum_map::{enum_map, EnumMap};
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{from_tier_list, tier_list, Action, Card, State, Tier, PROTOCOL_VERSION};
+
+/// A [`State`] with the face down cards taken out: decks keep only their face
+/// up cards and other players' blind reserves are removed. The hidden cards
+/// are only counted.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Observation {
+    /// [`PROTOCOL_VERSION`] of the runner that sent this.
+    pub protocol_version: u32,
+    pub state: State,
+    /// Face down cards left in each deck.
+    #[serde(serialize_with = "tier_list", deserialize_with = "from_tier_list")]
+    pub hidden_deck_cards: EnumMap<Tier, usize>,
+    /// Blind reserves of each player that were taken out of `state`.
+    pub hidden_reserved: Vec<usize>,
+}
+
+impl Observation {
+    /// What the player to move sees of `state` as [`play_game`](crate::play_game)
+    /// shows it: without the number of face down cards left, every card of
+    /// a deck nobody has seen may be dealt from it, unless it has run out.
+    pub fn from_shown(state: &State) -> Observation {
+        let mut observation = state.observation_for(state.turn);
+        for (tier, hidden) in &mut observation.hidden_deck_cards {
+            if state.decks[tier].len() >= state.config.visible_cards {
+                *hidden = usize::MAX;
+            }
+        }
+        observation
+    }
+}
+
+impl State {
+    /// What anyone watching the game knows: decks keep only their face up
+    /// cards and blind reserves are removed.
+    pub fn redacted(&self) -> State {
+        let mut state = self.clone();
+        let visible = state.config.visible_cards;
+        for deck in state.decks.values_mut() {
+            deck.truncate(visible);
+        }
+        for p in &mut state.players {
+            let mut blind = std::mem::take(&mut p.reserved_blind).into_iter();
+            p.reserved.retain(|_| !blind.next().unwrap_or(false));
+        }
+        state.rehash();
+        state
+    }
+
+    /// The same game with the face down cards of each deck, blind reserves
+    /// from it included, dealt again at random, for a position whose face
+    /// down cards may be known.
+    ///
+    /// ```
+    /// use game_def::{GameConfig, State};
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let mut state = State::setup(&["a", "b"], GameConfig::default(), &mut rng);
+    /// state.run("reserve-blind 2".parse().unwrap()).unwrap();
+    /// let reshuffled = state.reshuffled(&mut rng);
+    /// reshuffled.validate().unwrap();
+    /// assert!(reshuffled.redacted() == state.redacted());
+    /// assert!(reshuffled.players[0].reserved_blind == [true]);
+    /// ```
+    pub fn reshuffled(&self, rng: &mut impl Rng) -> State {
+        let mut state = self.clone();
+        let visible = state.config.visible_cards;
+        let mut hidden = enum_map! { tier => {
+            let deck = &state.decks[tier];
+            deck.get(visible..).unwrap_or_default().to_vec()
+        }};
+        for p in &state.players {
+            for (card, &blind) in p.reserved.iter().zip(&p.reserved_blind) {
+                if blind {
+                    hidden[state.config.tier_of(card.id()).unwrap()].push(card.clone());
+                }
+            }
+        }
+        for pool in hidden.values_mut() {
+            pool.shuffle(rng);
+        }
+        for p in &mut state.players {
+            for (card, &blind) in p.reserved.iter_mut().zip(&p.reserved_blind) {
+                if blind {
+                    *card = hidden[state.config.tier_of(card.id()).unwrap()]
+                        .pop()
+                        .unwrap();
+                }
+            }
+        }
+        for (tier, deck) in &mut state.decks {
+            deck.truncate(visible);
+            deck.append(&mut hidden[tier]);
+        }
+        state.rehash();
+        state
+    }
+
+    pub fn observation_for(&self, player: usize) -> Observation {
+        let mut state = self.clone();
+        let visible = self.config.visible_cards;
+        let hidden_deck_cards = enum_map! { tier => {
+            let d = &mut state.decks[tier];
+            let hidden = d.len().saturating_sub(visible);
+            d.truncate(visible);
+            hidden
+        }};
+        let hidden_reserved = state
+            .players
+            .iter_mut()
+            .enumerate()
+            .map(|(i, p)| {
+                if i == player {
+                    return 0;
+                }
+                let blind = std::mem::take(&mut p.reserved_blind);
+                let mut flags = blind.iter();
+                let before = p.reserved.len();
+                p.reserved
+                    .retain(|_| !flags.next().copied().unwrap_or(false));
+                p.reserved_blind = vec![false; p.reserved.len()];
+                before - p.reserved.len()
+            })
+            .collect();
+        state.rehash();
+        Observation {
+            protocol_version: PROTOCOL_VERSION,
+            state,
+            hidden_deck_cards,
+            hidden_reserved,
+        }
+    }
+}
+
+impl Observation {
+    pub fn json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Reads a position from a JSON file holding either an observation, as
+    /// agents are sent, or a whole [`State`], as the replay prompt saves,
+    /// which is seen by the player to move.
+    ///
+    /// ```
+    /// use game_def::Observation;
+    ///
+    /// let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/render");
+    /// let observation = Observation::load(&format!("{dir}/state.json")).unwrap();
+    /// let path = std::env::temp_dir().join(format!("observation-{}.json", std::process::id()));
+    /// std::fs::write(&path, observation.json()).unwrap();
+    /// let again = Observation::load(path.to_str().unwrap()).unwrap();
+    /// assert_eq!(again.state.zobrist(), observation.state.zobrist());
+    /// assert_eq!(again.hidden_deck_cards, observation.hidden_deck_cards);
+    ///
+    /// let Err(error) = Observation::load(&format!("{dir}/board.txt")) else {
+    ///     panic!("a board drawing loaded");
+    /// };
+    /// assert!(format!("{error:#}").contains("is neither a state nor an observation"));
+    /// ```
+    pub fn load(path: &str) -> anyhow::Result<Observation> {
+        let json =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+        if let Ok(observation) = serde_json::from_str::<Observation>(&json) {
+            return Ok(observation);
+        }
+        let state: State = serde_json::from_str(&json)
+            .with_context(|| format!("{path} is neither a state nor an observation"))?;
+        Ok(state.observation_for(state.turn))
+    }
+
+    /// The board as `player`, whose observation this is, may see it: like
+    /// [`State::render`], with the blind reserves of the others only counted.
+    ///
+    /// ```
+    /// use game_def::{Action, State};
+    ///
+    /// let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/render");
+    /// let json = std::fs::read_to_string(format!("{dir}/state.json")).unwrap();
+    /// let mut state: State = serde_json::from_str(&json).unwrap();
+    /// for action in ["take r u g", "reserve-blind 3"] {
+    ///     state.run(action.parse::<Action>().unwrap()).unwrap();
+    /// }
+    /// let card = format!("{:?}", state.players[1].reserved.last().unwrap());
+    /// assert!(state.render(Some(0)).contains(&card));
+    /// let alice = state.observation_for(0).render(0);
+    /// assert!(!alice.contains(&card));
+    /// assert!(alice.contains("Face Down Reserved Cards: 2"));
+    /// assert!(state.observation_for(1).render(1).contains(&card));
+    /// ```
+    pub fn render(&self, player: usize) -> String {
+        self.state
+            .render_hiding(Some(player), &self.hidden_reserved)
+    }
+
+    /// Like [`Observation::render`], but as [`State::render_pretty`] draws it.
+    ///
+    /// ```
+    /// use game_def::{Action, State};
+    ///
+    /// let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/render");
+    /// let json = std::fs::read_to_string(format!("{dir}/state.json")).unwrap();
+    /// let mut state: State = serde_json::from_str(&json).unwrap();
+    /// for action in ["take r u g", "reserve-blind 3"] {
+    ///     state.run(action.parse::<Action>().unwrap()).unwrap();
+    /// }
+    /// let board = state.render_pretty(Some(0), false);
+    /// let alice = state.observation_for(0).render_pretty(0, false);
+    /// assert!(alice.contains("Reserved face down  2"));
+    /// // The decks still count their face down cards.
+    /// assert_eq!(alice.lines().next(), board.lines().next());
+    /// ```
+    #[cfg(feature = "color")]
+    pub fn render_pretty(&self, player: usize, color: bool) -> String {
+        self.state.render_pretty_hiding(
+            Some(player),
+            color,
+            self.hidden_deck_cards,
+            &self.hidden_reserved,
+        )
+    }
+
+    /// The cards of each deck that the player whose observation this is
+    /// can't see: the cards of the game but those face up, reserved where it
+    /// can see them and bought. Face down cards and blind reserves of the
+    /// others are among them, and in states saved before purchases were
+    /// recorded the cards bought are too.
+    ///
+    /// ```
+    /// use game_def::{GameConfig, State, Tier};
+    ///
+    /// let mut state = State::setup(&["a", "b"], GameConfig::default(), &mut rand::thread_rng());
+    /// state.run("reserve-blind 1".parse().unwrap()).unwrap();
+    /// let observation = state.observation_for(1);
+    /// let unseen = observation.unseen_cards();
+    /// // The deck of tier 1 less the card reserved blind, and that card.
+    /// assert_eq!(unseen[Tier::One].len(), observation.hidden_deck_cards[Tier::One] + 1);
+    /// let blind = &state.players[0].reserved[0];
+    /// assert!(unseen[Tier::One].iter().any(|card| card.id() == blind.id()));
+    /// ```
+    pub fn unseen_cards(&self) -> EnumMap<Tier, Vec<Card>> {
+        let state = &self.state;
+        let seen: HashSet<usize> = state
+            .decks
+            .values()
+            .flat_map(|d| d.iter())
+            .chain(state.players.iter().flat_map(|p| &p.reserved))
+            .map(|c| c.id())
+            .chain(
+                state
+                    .players
+                    .iter()
+                    .flat_map(|p| p.purchased.iter().copied()),
+            )
+            .collect();
+        state
+            .config
+            .decks()
+            .map(|_, d| d.into_iter().filter(|c| !seen.contains(&c.id())).collect())
+    }
+
+    /// A full state consistent with what is visible, with the hidden cards
+    /// drawn at random from [`Observation::unseen_cards`].
+    pub fn to_determinized_state(&self, rng: &mut impl Rng) -> State {
+        let mut state = self.state.clone();
+        let mut pools = self.unseen_cards();
+        for pool in pools.values_mut() {
+            pool.shuffle(rng);
+        }
+        for (deck, &count) in &self.hidden_deck_cards {
+            for _ in 0..count {
+                let Some(card) = pools[deck].pop() else {
+                    break;
+                };
+                state.decks[deck].push(card);
+            }
+        }
+        for (player, &count) in self.hidden_reserved.iter().enumerate() {
+            for _ in 0..count {
+                let tiers: Vec<_> = pools
+                    .iter()
+                    .filter(|(_, p)| !p.is_empty())
+                    .map(|(t, _)| t)
+                    .collect();
+                let Some(&tier) = tiers.choose(rng) else {
+                    break;
+                };
+                let card = pools[tier].pop().unwrap();
+                let p = &mut state.players[player];
+                p.reserved_blind.resize(p.reserved.len(), false);
+                p.reserved.push(card);
+                p.reserved_blind.push(true);
+            }
+        }
+        state.rehash();
+        state
+    }
+}
+
+/// What an agent's `--analyze` prints about its search of one position,
+/// loaded by [`Observation::load`]. The score and what was searched are in
+/// the terms of the agent, and so are the moves at the root, best first.
+///
+/// ```
+/// use game_def::{Action, Analysis, GameConfig, State};
+///
+/// let state = State::setup(&["a", "b"], GameConfig::default(), &mut rand::thread_rng());
+/// let observation = state.observation_for(0);
+/// let pv = ["take r g u".parse::<Action>().unwrap(), "take w k r".parse().unwrap()];
+/// let report = Analysis {
+///     observation: &observation,
+///     best: &pv[0],
+///     pv: &pv,
+///     score: "120".into(),
+///     root: vec!["take r g u 120".into(), "take w k r 90".into()],
+///     top: 1,
+///     searched: "depth 2".into(),
+/// }
+/// .to_string();
+/// assert_eq!(
+///     report,
+///     "Position: round 1, a to move\n\
+///      Best move: take r g u\n\
+///      Score: 120\n\
+///      Principal variation: take r g u / take w k r\n\
+///      Top moves:\n  1. take r g u 120\n\
+///      Searched: depth 2\n"
+/// );
+/// ```
+pub struct Analysis<'a> {
+    pub observation: &'a Observation,
+    pub best: &'a Action,
+    pub pv: &'a [Action],
+    pub score: String,
+    pub root: Vec<String>,
+    /// How many of `root` to list.
+    pub top: usize,
+    pub searched: String,
+}
+
+impl Display for Analysis<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = &self.observation.state;
+        writeln!(
+            f,
+            "Position: round {}, {} to move",
+            state.round + 1,
+            state.players[state.turn].display_name
+        )?;
+        writeln!(f, "Best move: {}", self.best)?;
+        writeln!(f, "Score: {}", self.score)?;
+        let pv: Vec<_> = self.pv.iter().map(|ac| ac.to_string()).collect();
+        writeln!(f, "Principal variation: {}", pv.join(" / "))?;
+        if self.top > 0 && !self.root.is_empty() {
+            writeln!(f, "Top moves:")?;
+            for (k, root) in self.root.iter().take(self.top).enumerate() {
+                writeln!(f, "  {}. {root}", k + 1)?;
+            }
+        }
+        writeln!(f, "Searched: {}", self.searched)
+    }
+}
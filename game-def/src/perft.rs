@@ -0,0 +1,93 @@
+//! Counting the positions [`State::legal_actions`] leads to, to catch changes
+//! to the move generation, like perft does for chess engines.
+
+use crate::{Action, State};
+
+/// The number of action sequences of length `depth` from `state`, each one
+/// legal in the position it is played in. A game that ends earlier counts
+/// once, as it has no moves left.
+///
+/// Actions are taken back with [`State::undo`], so the decks are never copied.
+///
+/// ```
+/// use game_def::{fuzz::random_reachable_state, perft, GameConfig, State};
+/// use rand::{rngs::StdRng, SeedableRng};
+///
+/// let state = State::setup(&["a", "b"], GameConfig::default(), &mut StdRng::seed_from_u64(1));
+/// assert_eq!(perft(&state, 0), 1);
+/// assert_eq!(perft(&state, 1), state.legal_actions().count() as u64);
+/// assert_eq!(perft(&state, 3), 25086);
+///
+/// let three = ["a", "b", "c"];
+/// let state = State::setup(&three, GameConfig::default(), &mut StdRng::seed_from_u64(2));
+/// assert_eq!(perft(&state, 3), 29126);
+///
+/// // Later positions, the first one in the middle of a discard.
+/// let state = random_reachable_state(&mut StdRng::seed_from_u64(2), 30);
+/// assert_eq!(perft(&state, 3), 136);
+/// let state = random_reachable_state(&mut StdRng::seed_from_u64(4), 60);
+/// assert_eq!(perft(&state, 3), 233);
+/// ```
+pub fn perft(state: &State, depth: u32) -> u64 {
+    let mut state = state.clone();
+    let mut buffers = vec![vec![]; depth as usize];
+    count(&mut state, &mut buffers)
+}
+
+/// [`perft`] of each legal action, in the order of [`State::legal_actions`],
+/// to find which move a changed count comes from. The counts add up to
+/// `perft(state, depth)`; a `depth` of 0 gives no actions.
+///
+/// ```
+/// use game_def::{perft, perft_divide, GameConfig, State};
+///
+/// let state = State::setup(&["a", "b"], GameConfig::default(), &mut rand::thread_rng());
+/// let divided = perft_divide(&state, 2);
+/// for (action, nodes) in &divided {
+///     println!("{action}: {nodes}");
+/// }
+/// assert_eq!(divided.iter().map(|(_, n)| n).sum::<u64>(), perft(&state, 2));
+/// ```
+pub fn perft_divide(state: &State, depth: u32) -> Vec<(Action, u64)> {
+    let Some(rest) = depth.checked_sub(1) else {
+        return vec![];
+    };
+    let mut state = state.clone();
+    let mut buffers = vec![vec![]; rest as usize];
+    let actions: Vec<_> = state.legal_actions().collect();
+    actions
+        .into_iter()
+        .map(|action| {
+            let token = state
+                .apply(&action)
+                .expect("legal_actions yielded an action run rejects");
+            let nodes = count(&mut state, &mut buffers);
+            state.undo(token);
+            (action, nodes)
+        })
+        .collect()
+}
+
+/// Counts to the depth of `buffers`, which holds the actions of each ply.
+fn count(state: &mut State, buffers: &mut [Vec<Action>]) -> u64 {
+    let Some((actions, rest)) = buffers.split_first_mut() else {
+        return 1;
+    };
+    if state.is_finished() {
+        return 1;
+    }
+    actions.clear();
+    state.legal_actions_into(actions);
+    if rest.is_empty() {
+        return actions.len() as u64;
+    }
+    let mut nodes = 0;
+    for action in actions.iter() {
+        let token = state
+            .apply(action)
+            .expect("legal_actions yielded an action run rejects");
+        nodes += count(state, rest);
+        state.undo(token);
+    }
+    nodes
+}
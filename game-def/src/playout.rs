@@ -0,0 +1,65 @@
+//! Random games to the end, for Monte-Carlo style agents.
+
+use rand::Rng;
+
+use crate::{Action, GameResult, State};
+
+/// How a [`State::random_playout`] ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayoutResult {
+    /// [`GameResult::Ongoing`] if the round cap was hit first.
+    pub result: GameResult,
+    /// Score of each player at the end.
+    pub scores: Vec<u8>,
+    /// Rounds played by the playout itself.
+    pub rounds: usize,
+}
+
+impl State {
+    /// Plays random legal moves until the game is over or `max_rounds` more
+    /// rounds are played. Half of the time a purchase is available one of
+    /// them is picked, otherwise any move but resigning is equally likely.
+    /// The same `rng` state gives the same playout.
+    ///
+    /// ```
+    /// use game_def::{GameConfig, GameResult, State};
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let state = State::setup(&["a", "b"], GameConfig::default(), &mut rng);
+    /// for _ in 0..10 {
+    ///     // Nobody resigns, so the game goes on to the target score.
+    ///     let playout = state.random_playout(&mut rng, 1000);
+    ///     assert!(matches!(playout.result, GameResult::Won(_) | GameResult::Draw(_)));
+    ///     assert!(playout.scores.iter().any(|&score| score >= 15));
+    /// }
+    /// ```
+    pub fn random_playout(&self, rng: &mut impl Rng, max_rounds: usize) -> PlayoutResult {
+        let mut state = self.clone();
+        let mut actions = vec![];
+        let round_cap = self.round.saturating_add(max_rounds);
+        while !state.is_finished() && state.round < round_cap {
+            actions.clear();
+            state.legal_actions_into(&mut actions);
+            actions.retain(|a| *a != Action::Resign);
+            let purchases = actions
+                .iter()
+                .take_while(|a| {
+                    matches!(a, Action::Purchase { .. } | Action::PurchaseReserved { .. })
+                })
+                .count();
+            let index = if purchases > 0 && rng.gen_bool(0.5) {
+                rng.gen_range(0..purchases)
+            } else {
+                rng.gen_range(0..actions.len())
+            };
+            state
+                .run(actions.swap_remove(index))
+                .expect("legal_actions yielded an action run rejects");
+        }
+        PlayoutResult {
+            result: state.result(),
+            scores: state.players.iter().map(|p| p.score).collect(),
+            rounds: state.round - self.round,
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! [`State::render_pretty`]: the board for people playing in a terminal,
+//! cards drawn as boxes and counts in the colors they count.
+
+use std::fmt::Write;
+
+use crossterm::style::{Color, Stylize};
+use enum_map::{enum_map, EnumMap};
+
+use crate::{Card, CardSlot, ObjectiveMode, ResourceKind, ResourceMap, State, Tier};
+
+/// Inside width of a card.
+const WIDTH: usize = 9;
+
+/// Paints text, or leaves it plain without colors.
+#[derive(Clone, Copy)]
+struct Painter {
+    color: bool,
+}
+
+impl Painter {
+    fn paint(self, text: &str, color: Color) -> String {
+        match self.color {
+            true => text.with(color).to_string(),
+            false => text.to_owned(),
+        }
+    }
+
+    fn bold(self, text: &str) -> String {
+        match self.color {
+            true => text.bold().to_string(),
+            false => text.to_owned(),
+        }
+    }
+
+    /// Like `3r`, in red, and its width.
+    fn count(self, kind: ResourceKind, n: usize) -> (String, usize) {
+        let text = format!("{n}{}", kind.code());
+        let width = text.len();
+        (self.paint(&text, color_of(kind)), width)
+    }
+
+    /// The colors of `map` with a count, or all five with `zeros`.
+    fn counts(self, map: &ResourceMap, zeros: bool) -> Vec<(String, usize)> {
+        map.iter()
+            .filter(|(_, n)| zeros || **n > 0)
+            .map(|(kind, n)| self.count(kind, *n))
+            .collect()
+    }
+
+    /// `counts` in a line.
+    fn line(self, map: &ResourceMap, zeros: bool) -> String {
+        let counts: Vec<String> = self.counts(map, zeros).into_iter().map(|c| c.0).collect();
+        counts.join(" ")
+    }
+
+    fn gold(self, n: usize) -> String {
+        self.paint(&format!("{n} gold"), Color::Yellow)
+    }
+
+    /// The 5 lines of `card`, labelled with where it lies.
+    fn card(self, card: &Card, label: &str, affordable: bool) -> [String; 5] {
+        let top = format!(
+            "┌ {label} {}┐",
+            "─".repeat(WIDTH.saturating_sub(label.len() + 2))
+        );
+        let score = match card.score() {
+            0 => String::new(),
+            n => n.to_string(),
+        };
+        let bonus = match card.color() {
+            Some(kind) => {
+                let code = kind.code().to_ascii_uppercase().to_string();
+                match self.color {
+                    true => code.with(color_of(kind)).bold().to_string(),
+                    false => code,
+                }
+            }
+            None => " ".to_owned(),
+        };
+        let first = format!("│ {score:<6}{bonus} │");
+        let counts = self.counts(card.cost(), false);
+        let [second, third] = [0, 2].map(|from| {
+            let pair = counts
+                .get(from..(from + 2).min(counts.len()))
+                .unwrap_or(&[]);
+            let width: usize =
+                pair.iter().map(|c| c.1).sum::<usize>() + pair.len().saturating_sub(1);
+            let text: Vec<&str> = pair.iter().map(|c| c.0.as_str()).collect();
+            format!(
+                "│ {}{} │",
+                text.join(" "),
+                " ".repeat((WIDTH - 2).saturating_sub(width))
+            )
+        });
+        let bottom = match affordable {
+            true => format!("└{}┘", self.paint("─can buy─", Color::Green)),
+            false => format!("└{}┘", "─".repeat(WIDTH)),
+        };
+        [top, first, second, third, bottom]
+    }
+}
+
+fn color_of(kind: ResourceKind) -> Color {
+    match kind {
+        ResourceKind::Red => Color::Red,
+        ResourceKind::Blue => Color::Blue,
+        ResourceKind::Green => Color::Green,
+        ResourceKind::White => Color::White,
+        // Black text vanishes on the usual dark terminal.
+        ResourceKind::Black => Color::DarkGrey,
+    }
+}
+
+/// Writes `cards` side by side, indented.
+fn write_row(out: &mut String, cards: &[[String; 5]]) {
+    for line in 0..5 {
+        let line: Vec<&str> = cards.iter().map(|c| c[line].as_str()).collect();
+        writeln!(out, "   {}", line.join(" ")).unwrap();
+    }
+}
+
+impl State {
+    /// The board as [`State::render`] shows it, with every card in a box:
+    /// where it lies (`1.0` is card 0 of deck 1, `R0` the first reserved
+    /// card) for `buy`, its points, the color it adds and its cost. Cards
+    /// `perspective` can afford say so at the bottom. With `color`, counts
+    /// are painted in their colors with ANSI codes.
+    ///
+    /// ```
+    /// use game_def::State;
+    ///
+    /// let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/render");
+    /// let json = std::fs::read_to_string(format!("{dir}/state.json")).unwrap();
+    /// let state: State = serde_json::from_str(&json).unwrap();
+    /// let board = std::fs::read_to_string(format!("{dir}/pretty.txt")).unwrap();
+    /// assert_eq!(state.render_pretty(Some(0), false), board);
+    /// assert!(!state.render_pretty(None, false).contains("can buy"));
+    /// let colored = state.render_pretty(Some(0), true);
+    /// assert!(colored.contains("\x1b["));
+    /// assert!(!board.contains("\x1b["));
+    /// ```
+    pub fn render_pretty(&self, perspective: Option<usize>, color: bool) -> String {
+        let visible = self.config.visible_cards;
+        let face_down = enum_map! { tier => self.decks[tier].len().saturating_sub(visible) };
+        self.render_pretty_hiding(perspective, color, face_down, &[])
+    }
+
+    /// [`State::render_pretty`] with the face down cards of each deck and the
+    /// reserves each player holds face down counted apart from the state.
+    pub(crate) fn render_pretty_hiding(
+        &self,
+        perspective: Option<usize>,
+        color: bool,
+        face_down: EnumMap<Tier, usize>,
+        hidden: &[usize],
+    ) -> String {
+        let painter = Painter { color };
+        let mut out = String::new();
+        let purchasable = perspective
+            .map(|p| self.purchasable_cards(p))
+            .unwrap_or_default();
+        let affordable = |slot: CardSlot| purchasable.iter().any(|(s, _)| *s == slot);
+        for (tier, deck) in &self.decks {
+            writeln!(out, "Deck {tier}, {} face down", face_down[tier]).unwrap();
+            let cards: Vec<_> = deck
+                .iter()
+                .take(self.config.visible_cards)
+                .enumerate()
+                .map(|(card, c)| {
+                    let slot = CardSlot::Visible { deck: tier, card };
+                    painter.card(c, &format!("{tier}.{card}"), affordable(slot))
+                })
+                .collect();
+            write_row(&mut out, &cards);
+        }
+        let objectives = match self.config.objective_mode {
+            ObjectiveMode::Cities => "Cities",
+            _ => "Nobels",
+        };
+        let objectives_line: Vec<String> = self
+            .nobels
+            .iter()
+            .map(|o| {
+                let min = o.min_score.map(|m| format!(" at {m}")).unwrap_or_default();
+                format!("[{} for {}{min}]", o.score, painter.line(&o.cost, false))
+            })
+            .collect();
+        writeln!(out, "{objectives}: {}", objectives_line.join(" ")).unwrap();
+        writeln!(
+            out,
+            "Bank: {}  {}",
+            painter.line(&self.coins.gems, true),
+            painter.gold(self.coins.gold)
+        )
+        .unwrap();
+        for (i, p) in self.players.iter().enumerate() {
+            let marker = if i == self.turn { "▶ " } else { "  " };
+            let resigned = if p.resigned { ", resigned" } else { "" };
+            let points = match p.score {
+                1 => "1 point".to_owned(),
+                n => format!("{n} points"),
+            };
+            let name = match i == self.turn {
+                true => painter.bold(&p.display_name),
+                false => p.display_name.clone(),
+            };
+            writeln!(out, "{marker}{name}, {points}{resigned}").unwrap();
+            writeln!(out, "   Cards  {}", painter.line(&p.immortal, true)).unwrap();
+            writeln!(
+                out,
+                "   Coins  {}  {}, {} of {}",
+                painter.line(&p.mortal.gems, true),
+                painter.gold(p.mortal.gold),
+                p.mortal.total(),
+                self.config.max_coins
+            )
+            .unwrap();
+            if !p.reserved.is_empty() {
+                writeln!(out, "   Reserved").unwrap();
+                let cards: Vec<_> = p
+                    .reserved
+                    .iter()
+                    .enumerate()
+                    .map(|(index, c)| {
+                        let mine = perspective == Some(i);
+                        let slot = CardSlot::Reserved { index };
+                        painter.card(c, &format!("R{index}"), mine && affordable(slot))
+                    })
+                    .collect();
+                write_row(&mut out, &cards);
+            }
+            if let Some(&n @ 1..) = hidden.get(i) {
+                writeln!(out, "   Reserved face down  {n}").unwrap();
+            }
+        }
+        self.render_turn(&mut out);
+        out
+    }
+}
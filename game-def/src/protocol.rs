@@ -0,0 +1,297 @@
+//! The message protocol: unlike the plain stream of observations, the runner
+//! tells an agent its seat before the game, what the others play as they
+//! play it, and the result after it.
+//!
+//! The runner speaks it to agents it starts with `--protocol messages`. Each
+//! [`RunnerMessage::MoveRequest`] and [`RunnerMessage::Rejected`] is answered
+//! with an [`Action`]; the other messages get no answer.
+//!
+//! Agents that connect over TCP instead of being started by the runner first
+//! send a [`Hello`], then speak whichever protocol the runner expects of them.
+//! Over WebSocket, see `game_def::websocket` with the `websocket` feature.
+//!
+//! An agent that can play one game after another in the same process answers
+//! an [`RunnerMessage::Init`] offering `new_games` with
+//! [`AgentMessage::NewGames`]. The runner may then start its next game with
+//! [`RunnerMessage::NewGame`] instead of starting it again, see
+//! [`AgentHandler::new_games`].
+//!
+//! Observers, which watch a game without a seat, get [`ObserverMessage`]s
+//! and never answer.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    read_message, transcript::Transcript, write_message, Action, GameConfig, GameResult,
+    Observation, RuleError, State,
+};
+
+/// What the runner sends to an agent.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RunnerMessage {
+    /// Sent once, before anything else.
+    Init {
+        protocol_version: u32,
+        player_index: usize,
+        config: GameConfig,
+        /// Whether the runner would go on with [`RunnerMessage::NewGame`]
+        /// after this game, if the agent answers [`AgentMessage::NewGames`].
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        new_games: bool,
+    },
+    /// Sent after [`RunnerMessage::GameOver`] to an agent that answered
+    /// [`AgentMessage::NewGames`], to play another game like after
+    /// [`RunnerMessage::Init`]. It may be in another seat, against another
+    /// process of the same opponent.
+    NewGame {
+        player_index: usize,
+        config: GameConfig,
+    },
+    /// Sent when it is the agent's turn. With a time budget, `time_left_ms`
+    /// is what is left of it for the rest of the game, this move included.
+    MoveRequest {
+        observation: Box<Observation>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        time_left_ms: Option<u64>,
+    },
+    /// Sent instead of the next move request when the runner refused `action`
+    /// and gives the agent another try, with `--invalid-retries`. The
+    /// observation is the same as before, and the time the refused action
+    /// took counts against the move.
+    Rejected {
+        action: Action,
+        error: RuleError,
+        observation: Box<Observation>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        time_left_ms: Option<u64>,
+    },
+    /// Sent after each action of another player the runner accepted, forced
+    /// resignations included, before the next move request. An agent started
+    /// again after a crash is sent every action of the game so far, its own
+    /// included, right after [`RunnerMessage::Init`].
+    Played { player: usize, action: Action },
+    /// Sent once the game is over, or [`GameResult::Ongoing`] if it was
+    /// stopped early.
+    GameOver { result: GameResult },
+}
+
+/// What the runner sends to an observer, one JSON line each.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ObserverMessage {
+    /// Sent once, before anything else.
+    Init {
+        protocol_version: u32,
+        config: GameConfig,
+        /// The names of the players, by seat.
+        players: Vec<String>,
+    },
+    /// Sent after each action the runner accepted, before the state it led
+    /// to.
+    Played { player: usize, action: Action },
+    /// The state at the start and after each action, or after a human took
+    /// moves back. Without the face down cards and blind reserves if the
+    /// runner was told `--observer-sees public`.
+    State { state: Box<State> },
+    /// Sent once the game is over, or [`GameResult::Ongoing`] if it was
+    /// stopped early.
+    GameOver { result: GameResult },
+}
+
+/// What an agent sends besides its actions.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentMessage {
+    /// The answer to an [`RunnerMessage::Init`] offering `new_games`, from
+    /// an agent that can play them.
+    NewGames,
+}
+
+/// What [`answer`] sends back.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub(crate) enum Answer {
+    Action(Action),
+    Message(AgentMessage),
+}
+
+/// The first message of an agent over TCP, in the format it talks.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Hello {
+    /// What the runner calls the agent, unless it is given `--name`.
+    pub name: String,
+}
+
+/// An agent for [`run_agent`]. Only `act` is required.
+pub trait AgentHandler {
+    fn init(&mut self, _player_index: usize, _config: &GameConfig) {}
+
+    /// Whether it can play more games in the same process, each one started
+    /// with `on_new_game`. Leaving nothing of a game behind for the next is
+    /// up to the agent.
+    fn new_games(&self) -> bool {
+        false
+    }
+
+    /// Called instead of `init` for every game after the first, if
+    /// `new_games` says so.
+    fn on_new_game(&mut self, player_index: usize, config: &GameConfig) {
+        self.init(player_index, config);
+    }
+
+    /// Called before `act` when the runner keeps a clock for the agent.
+    fn time_left(&mut self, _left: Duration) {}
+
+    fn act(&mut self, observation: Observation) -> anyhow::Result<Action>;
+
+    /// Called when the runner refused `action`, before `act` is asked again
+    /// for the same observation.
+    fn rejected(&mut self, _action: &Action, _error: &RuleError) {}
+
+    /// Called with each action of the other players as it is played.
+    fn on_opponent_action(&mut self, _player: usize, _action: &Action) {}
+
+    fn game_over(&mut self, _result: &GameResult) {}
+}
+
+impl<F: FnMut(Observation) -> Action> AgentHandler for F {
+    fn act(&mut self, observation: Observation) -> anyhow::Result<Action> {
+        Ok(self(observation))
+    }
+}
+
+/// Runs `handler` over stdin and stdout. Speaks the message protocol when
+/// started with `--protocol messages`, and the plain one that only calls
+/// [`AgentHandler::act`] otherwise. `--format msgpack` works with both.
+#[cfg(feature = "stdio")]
+pub fn run_agent(mut handler: impl AgentHandler) -> anyhow::Result<()> {
+    let input = std::io::stdin().lock();
+    let output = std::io::stdout().lock();
+    let msgpack = crate::arg_is("--format", "msgpack");
+    let messages = crate::arg_is("--protocol", "messages");
+    let transcript = Transcript::from_env(msgpack, messages);
+    if messages {
+        messages_loop(input, output, msgpack, transcript.as_ref(), handler)
+    } else {
+        crate::ai_loop(input, output, msgpack, transcript.as_ref(), |o| {
+            handler.act(o)
+        })
+    }
+}
+
+/// [`run_agent`] over a TCP connection to a runner, after saying [`Hello`]
+/// as `name`. Whether it talks MessagePack or the message protocol is up to
+/// the caller here, to match how the runner is set up.
+///
+/// ```no_run
+/// use game_def::{run_agent_over_tcp, Agent, Observation, RandomAgent};
+///
+/// let stream = std::net::TcpStream::connect("race.example.com:7001")?;
+/// run_agent_over_tcp(stream, "random", false, false, |o: Observation| {
+///     RandomAgent.act(&o.state)
+/// })?;
+/// # anyhow::Ok(())
+/// ```
+#[cfg(feature = "stdio")]
+pub fn run_agent_over_tcp(
+    stream: std::net::TcpStream,
+    name: &str,
+    msgpack: bool,
+    messages: bool,
+    mut handler: impl AgentHandler,
+) -> anyhow::Result<()> {
+    stream.set_nodelay(true)?;
+    let input = std::io::BufReader::new(stream.try_clone()?);
+    let mut output = stream;
+    let hello = Hello {
+        name: name.to_owned(),
+    };
+    let transcript = Transcript::from_env(msgpack, messages);
+    write_message(&mut output, msgpack, &hello, transcript.as_ref())?;
+    if messages {
+        messages_loop(input, output, msgpack, transcript.as_ref(), handler)
+    } else {
+        crate::ai_loop(input, output, msgpack, transcript.as_ref(), |o| {
+            handler.act(o)
+        })
+    }
+}
+
+/// The message protocol loop behind [`run_agent`], over any reader and
+/// writer. Returns when the input ends.
+pub fn run_agent_over_io(
+    input: impl std::io::BufRead,
+    output: impl std::io::Write,
+    msgpack: bool,
+    handler: impl AgentHandler,
+) -> anyhow::Result<()> {
+    messages_loop(input, output, msgpack, None, handler)
+}
+
+/// [`run_agent_over_io`], writing down every message in `transcript`.
+fn messages_loop(
+    mut input: impl std::io::BufRead,
+    mut output: impl std::io::Write,
+    msgpack: bool,
+    transcript: Option<&Transcript>,
+    mut handler: impl AgentHandler,
+) -> anyhow::Result<()> {
+    while let Some(message) = read_message(&mut input, msgpack, transcript)? {
+        if let Some(reply) = answer(&mut handler, message)? {
+            write_message(&mut output, msgpack, &reply, transcript)?;
+        }
+    }
+    Ok(())
+}
+
+/// Passes `message` on to `handler`, and gives what to send back if the
+/// runner wants something.
+pub(crate) fn answer(
+    handler: &mut impl AgentHandler,
+    message: RunnerMessage,
+) -> anyhow::Result<Option<Answer>> {
+    match message {
+        RunnerMessage::Init {
+            player_index,
+            config,
+            new_games,
+            ..
+        } => {
+            handler.init(player_index, &config);
+            if new_games && handler.new_games() {
+                return Ok(Some(Answer::Message(AgentMessage::NewGames)));
+            }
+        }
+        RunnerMessage::NewGame {
+            player_index,
+            config,
+        } => handler.on_new_game(player_index, &config),
+        RunnerMessage::MoveRequest {
+            observation,
+            time_left_ms,
+        } => {
+            if let Some(ms) = time_left_ms {
+                handler.time_left(Duration::from_millis(ms));
+            }
+            return handler.act(*observation).map(|a| Some(Answer::Action(a)));
+        }
+        RunnerMessage::Rejected {
+            action,
+            error,
+            observation,
+            time_left_ms,
+        } => {
+            handler.rejected(&action, &error);
+            if let Some(ms) = time_left_ms {
+                handler.time_left(Duration::from_millis(ms));
+            }
+            return handler.act(*observation).map(|a| Some(Answer::Action(a)));
+        }
+        RunnerMessage::Played { player, action } => handler.on_opponent_action(player, &action),
+        RunnerMessage::GameOver { result } => handler.game_over(&result),
+    }
+    Ok(None)
+}
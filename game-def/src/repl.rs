@@ -0,0 +1,165 @@
+//! What a human types at the runner's prompt: an [`Action`], in the short
+//! notation of its `Display` or as a subcommand like `pick-three r g u`, or
+//! one of the prompt's own commands.
+//!
+//! ```
+//! use game_def::{repl::Command, Action, ResourceKind, Tier};
+//!
+//! let parse = |line: &str| line.parse::<Command>().unwrap();
+//! assert_eq!(parse("moves"), Command::Moves);
+//! assert_eq!(parse(" board "), Command::Board);
+//! assert_eq!(parse("hint"), Command::Hint);
+//! assert_eq!(parse("undo"), Command::Undo);
+//! assert_eq!(parse("history"), Command::History(None));
+//! assert_eq!(parse("history 5"), Command::History(Some(5)));
+//! assert!("history five".parse::<Command>().is_err());
+//! assert_eq!(parse("save my game.json"), Command::Save("my game.json".into()));
+//! assert!("save".parse::<Command>().is_err());
+//! assert_eq!(parse("takeover 1"), Command::Takeover(1));
+//! assert_eq!(
+//!     parse("handoff 0 python3 bot.py --depth 2"),
+//!     Command::Handoff(0, "python3 bot.py --depth 2".into())
+//! );
+//! assert!("takeover".parse::<Command>().is_err());
+//! assert!("handoff 1".parse::<Command>().is_err());
+//! assert_eq!(parse("help"), Command::Help);
+//! assert_eq!(parse("?"), Command::Help);
+//! assert_eq!(parse("3"), Command::Play(3));
+//! assert_eq!(parse("take r r"), Command::Action("take r r".parse().unwrap()));
+//! assert_eq!(
+//!     parse("pick-three r g blue"),
+//!     Command::Action(Action::PickThree {
+//!         one: ResourceKind::Red,
+//!         two: ResourceKind::Green,
+//!         three: ResourceKind::Blue,
+//!     })
+//! );
+//! assert_eq!(
+//!     parse("pick-two K"),
+//!     Command::Action(Action::PickTwo { color: ResourceKind::Black })
+//! );
+//! assert_eq!(
+//!     parse("reserve-blind 2"),
+//!     Command::Action(Action::ReserveBlind { deck: Tier::Two })
+//! );
+//! for (long, short) in [
+//!     ("purchase 2 3", "buy 2.3"),
+//!     ("purchase-reserved 0 --wilds 1", "buy-reserved 0 wilds=1"),
+//!     ("discard 2r+1u", "discard 2r+1u"),
+//!     ("choose-nobel 1", "nobel 1"),
+//! ] {
+//!     assert_eq!(parse(long), parse(short));
+//! }
+//!
+//! let error = "pick-two x".parse::<Command>().unwrap_err();
+//! assert!(error.to_string().contains("Unknown color \"x\""), "{error}");
+//! assert!("".parse::<Command>().is_err());
+//! ```
+
+use std::str::FromStr;
+
+use clap::Parser;
+
+use crate::Action;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Action(Action),
+    /// Plays the action with this number in the last `moves` list.
+    Play(usize),
+    /// Lists the legal actions, numbered.
+    Moves,
+    /// Prints the board again.
+    Board,
+    /// Lists the moves played so far, or the last so many.
+    History(Option<usize>),
+    /// Shows what an advisor would play, without playing it.
+    Hint,
+    /// Takes back the last move of a human.
+    Undo,
+    /// Writes the game to this file, to go on with it later.
+    Save(String),
+    /// Puts a human in this seat, from its next turn on.
+    Takeover(usize),
+    /// Starts this agent command for the seat, from its next turn on.
+    Handoff(usize, String),
+    Help,
+}
+
+impl FromStr for Command {
+    type Err = clap::Error;
+
+    fn from_str(line: &str) -> Result<Self, clap::Error> {
+        match line.trim() {
+            "moves" => return Ok(Command::Moves),
+            "board" => return Ok(Command::Board),
+            "history" => return Ok(Command::History(None)),
+            "hint" => return Ok(Command::Hint),
+            "undo" => return Ok(Command::Undo),
+            "help" | "?" => return Ok(Command::Help),
+            "save" => {
+                return Err(clap::Error::raw(
+                    clap::error::ErrorKind::MissingRequiredArgument,
+                    "save needs a file\n",
+                ))
+            }
+            "takeover" => {
+                return Err(clap::Error::raw(
+                    clap::error::ErrorKind::MissingRequiredArgument,
+                    "takeover needs a seat number\n",
+                ))
+            }
+            "handoff" => {
+                return Err(clap::Error::raw(
+                    clap::error::ErrorKind::MissingRequiredArgument,
+                    "handoff needs a seat number and an agent command\n",
+                ))
+            }
+            line => {
+                if let Some(path) = line.strip_prefix("save ") {
+                    return Ok(Command::Save(path.trim().to_owned()));
+                }
+                if let Some(seat) = line.strip_prefix("takeover ") {
+                    return match seat.trim().parse() {
+                        Ok(seat) => Ok(Command::Takeover(seat)),
+                        Err(_) => Err(clap::Error::raw(
+                            clap::error::ErrorKind::InvalidValue,
+                            "takeover needs a seat number\n",
+                        )),
+                    };
+                }
+                if let Some(rest) = line.strip_prefix("handoff ") {
+                    let handoff = rest.trim().split_once(' ').and_then(|(seat, command)| {
+                        Some(Command::Handoff(
+                            seat.parse().ok()?,
+                            command.trim().to_owned(),
+                        ))
+                    });
+                    return handoff.ok_or_else(|| {
+                        clap::Error::raw(
+                            clap::error::ErrorKind::InvalidValue,
+                            "handoff needs a seat number and an agent command\n",
+                        )
+                    });
+                }
+                if let Some(n) = line.strip_prefix("history ") {
+                    return match n.trim().parse() {
+                        Ok(n) => Ok(Command::History(Some(n))),
+                        Err(_) => Err(clap::Error::raw(
+                            clap::error::ErrorKind::InvalidValue,
+                            "history needs a number of moves\n",
+                        )),
+                    };
+                }
+            }
+        }
+        if let Ok(n) = line.trim().parse() {
+            return Ok(Command::Play(n));
+        }
+        if let Ok(action) = line.parse() {
+            return Ok(Command::Action(action));
+        }
+        Action::try_parse_from(std::iter::once("").chain(line.split_whitespace()))
+            .map(Command::Action)
+    }
+}
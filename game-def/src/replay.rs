@@ -0,0 +1,300 @@
+//! Recorded games that can be played back and checked.
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::{Action, EndReason, GameConfig, GameResult, RuleError, State};
+
+/// A game as the state it started from and every action after it. The
+/// initial state has the full order of the decks, so running the actions
+/// again gives exactly the same game.
+///
+/// ```
+/// use game_def::{Action, GameConfig, Replay, State};
+/// use rand::seq::IteratorRandom;
+///
+/// let mut rng = rand::thread_rng();
+/// let mut state = State::setup(&["a", "b", "c"], GameConfig::default(), &mut rng);
+/// let mut replay = Replay::new(state.clone());
+/// while !state.is_finished() && state.round < 100 {
+///     let mut legal = state.legal_actions().filter(|a| *a != Action::Resign);
+///     let action = legal.choose(&mut rng).unwrap();
+///     replay.push(state.turn, action.clone());
+///     state.run(action).unwrap();
+/// }
+/// replay.result = state.result();
+/// replay.end_reason = state.end_reason();
+///
+/// let json = serde_json::to_string(&replay).unwrap();
+/// let loaded: Replay = serde_json::from_str(&json).unwrap();
+/// assert!(loaded == replay);
+/// loaded.verify().unwrap();
+/// assert!(loaded.states().last().unwrap() == state);
+/// ```
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replay {
+    pub config: GameConfig,
+    pub initial_state: State,
+    /// Who played each action, and the action.
+    pub actions: Vec<(usize, Action)>,
+    /// How the game ended, [`GameResult::Ongoing`] if it was cut short.
+    pub result: GameResult,
+    /// Why the game ended, to tell a game adjudicated at the round limit from
+    /// one played out. Older replays don't have it.
+    #[serde(default)]
+    pub end_reason: Option<EndReason>,
+    /// Set by [`Replay::redacted`]. The face down cards are gone, so the game
+    /// can't be played back past the first one dealt.
+    #[serde(default)]
+    pub redacted: bool,
+    /// What the decks were shuffled with, if it is known.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// How each player was run, like the agent command the runner started.
+    #[serde(default)]
+    pub agents: Vec<String>,
+    /// How long each action took to choose, in milliseconds, when recorded.
+    #[serde(default)]
+    pub think_ms: Vec<u64>,
+    /// Actions that were refused and so aren't in `actions`.
+    #[serde(default)]
+    pub invalid: Vec<InvalidAttempt>,
+    /// Players resigned by the runner, whose [`Action::Resign`] in `actions`
+    /// they didn't choose.
+    #[serde(default)]
+    pub forfeits: Vec<Forfeit>,
+    /// Actions a human took back with `undo`, which aren't in `actions` any
+    /// more, in the order they were taken back.
+    #[serde(default)]
+    pub undone: Vec<Undone>,
+    /// Seats that changed hands during the game, in order.
+    #[serde(default)]
+    pub substitutions: Vec<Substitution>,
+    /// Agents that crashed and were started again, in order.
+    #[serde(default)]
+    pub restarts: Vec<Restart>,
+    /// The options and agents the runner's `--config` file came to, if it
+    /// had one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_config: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Forfeit {
+    pub player: usize,
+    /// Like a timeout or an invalid action, for people to read.
+    pub reason: String,
+}
+
+/// An action [`State::run`] refused, tried instead of `actions[ply]`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InvalidAttempt {
+    pub ply: usize,
+    pub player: usize,
+    pub action: Action,
+    pub error: RuleError,
+}
+
+/// An action played after `actions[..ply]` and then taken back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Undone {
+    pub ply: usize,
+    pub player: usize,
+    pub action: Action,
+}
+
+/// A seat taken over by a human or handed to another agent, which plays
+/// `actions[ply..]` of it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Substitution {
+    pub ply: usize,
+    pub player: usize,
+    /// Like in [`Replay::agents`].
+    pub agent: String,
+}
+
+/// An agent started again after it crashed asked for `actions[ply]`, which
+/// it was then asked for again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Restart {
+    pub ply: usize,
+    pub player: usize,
+    /// How it crashed, for people to read.
+    pub reason: String,
+}
+
+/// How long a player took to choose its actions, from
+/// [`Replay::think_ms`] or the runner's clock.
+///
+/// ```
+/// use game_def::Timing;
+///
+/// let timing = Timing::of(&[100, 400, 250]);
+/// assert_eq!(timing, Timing { moves: 3, total_ms: 750, mean_ms: 250, max_ms: 400 });
+/// assert_eq!(Timing::of(&[]), Timing::default());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timing {
+    pub moves: usize,
+    pub total_ms: u64,
+    pub mean_ms: u64,
+    pub max_ms: u64,
+}
+
+impl Timing {
+    pub fn of(move_ms: &[u64]) -> Timing {
+        let total_ms = move_ms.iter().sum();
+        Timing {
+            moves: move_ms.len(),
+            total_ms,
+            mean_ms: total_ms.checked_div(move_ms.len() as u64).unwrap_or(0),
+            max_ms: move_ms.iter().copied().max().unwrap_or(0),
+        }
+    }
+}
+
+impl Replay {
+    /// An empty record of a game starting at `initial_state`.
+    pub fn new(initial_state: State) -> Self {
+        Replay {
+            config: initial_state.config.clone(),
+            initial_state,
+            actions: vec![],
+            result: GameResult::Ongoing,
+            end_reason: None,
+            redacted: false,
+            seed: None,
+            agents: vec![],
+            think_ms: vec![],
+            invalid: vec![],
+            forfeits: vec![],
+            undone: vec![],
+            substitutions: vec![],
+            restarts: vec![],
+            run_config: None,
+        }
+    }
+
+    pub fn push(&mut self, player: usize, action: Action) {
+        self.actions.push((player, action));
+    }
+
+    /// Takes back the last `count` actions, keeping them in `undone`.
+    ///
+    /// ```
+    /// use game_def::{GameConfig, Replay, State};
+    ///
+    /// let mut state = State::setup(&["a", "b"], GameConfig::default(), &mut rand::thread_rng());
+    /// let mut replay = Replay::new(state.clone());
+    /// for _ in 0..3 {
+    ///     let action = state.legal_actions().next().unwrap();
+    ///     replay.push(state.turn, action.clone());
+    ///     state.run(action).unwrap();
+    /// }
+    /// replay.undo(2);
+    /// assert_eq!(replay.actions.len(), 1);
+    /// let plies: Vec<_> = replay.undone.iter().map(|u| (u.ply, u.player)).collect();
+    /// assert_eq!(plies, [(2, 0), (1, 1)]);
+    /// ```
+    pub fn undo(&mut self, count: usize) {
+        let ply = self.actions.len().saturating_sub(count);
+        for (i, (player, action)) in self.actions.drain(ply..).enumerate().rev() {
+            self.undone.push(Undone {
+                ply: ply + i,
+                player,
+                action,
+            });
+        }
+        self.think_ms.truncate(ply);
+    }
+
+    /// The [`Timing`] of each player, over the actions with a think time.
+    pub fn timings(&self) -> Vec<Timing> {
+        let mut move_ms = vec![vec![]; self.initial_state.players.len()];
+        for ((player, _), &ms) in self.actions.iter().zip(&self.think_ms) {
+            move_ms[*player].push(ms);
+        }
+        move_ms.iter().map(|ms| Timing::of(ms)).collect()
+    }
+
+    /// The state after each action, in order. Stops early at an action
+    /// [`State::run`] rejects, which [`Replay::verify`] reports.
+    pub fn states(&self) -> impl Iterator<Item = State> + '_ {
+        let mut state = self.initial_state.clone();
+        self.actions.iter().map_while(move |(_, action)| {
+            state.run(action.clone()).ok()?;
+            Some(state.clone())
+        })
+    }
+
+    /// Runs the actions again and checks that each was played by the player
+    /// to move, was legal, and that the game ended as recorded.
+    pub fn verify(&self) -> anyhow::Result<()> {
+        let states = self.checked_states()?;
+        let last = states.last().unwrap();
+        let result = last.result();
+        if result != self.result {
+            bail!("The game ends with {result:?}, not {:?}", self.result);
+        }
+        if self.end_reason.is_some() && last.end_reason() != self.end_reason {
+            bail!(
+                "The game ends by {:?}, not {:?}",
+                last.end_reason(),
+                self.end_reason
+            );
+        }
+        Ok(())
+    }
+
+    /// The initial state and the state after each action, or which action
+    /// [`Replay::verify`] fails at. The result isn't checked, so a game cut
+    /// short can still be looked at.
+    pub fn checked_states(&self) -> anyhow::Result<Vec<State>> {
+        if self.redacted {
+            bail!("A redacted replay can't be played back");
+        }
+        if self.config != self.initial_state.config {
+            bail!("The config differs from the one of the initial state");
+        }
+        let mut states = vec![self.initial_state.clone()];
+        for (ply, (player, action)) in self.actions.iter().enumerate() {
+            let mut state = states.last().unwrap().clone();
+            if *player != state.turn {
+                bail!(
+                    "Action {ply} `{action}` is by player {player}, but it is the turn of {}",
+                    state.turn
+                );
+            }
+            state
+                .run(action.clone())
+                .with_context(|| format!("Action {ply} `{action}` is invalid"))?;
+            states.push(state);
+        }
+        Ok(states)
+    }
+
+    /// A copy safe to publish: the initial state keeps only the face up cards
+    /// of each deck, and blind reserves are removed.
+    pub fn redacted(&self) -> Replay {
+        let mut replay = self.clone();
+        replay.initial_state = self.initial_state.redacted();
+        replay.redacted = true;
+        replay
+    }
+
+    pub fn load(path: &str) -> anyhow::Result<Replay> {
+        let json =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+        serde_json::from_str(&json).with_context(|| format!("{path} is not a replay"))
+    }
+
+    /// Writes to a temporary file first, so `path` always holds a whole
+    /// replay, even when saving after each move is cut short.
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        let json = serde_json::to_string(self)?;
+        let temporary = format!("{path}.tmp");
+        std::fs::write(&temporary, json + "\n")
+            .with_context(|| format!("Failed to write {temporary}"))?;
+        std::fs::rename(&temporary, path).with_context(|| format!("Failed to write {path}"))
+    }
+}
@@ -0,0 +1,175 @@
+//! Swiss-system pairing, for tournaments with too many entrants for
+//! everyone to play everyone. Each round pairs entrants with about the same
+//! score who haven't met yet, so a few rounds are enough to tell the best
+//! from the rest.
+
+/// How an entrant stands before a round.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Entrant {
+    /// What it scored in each round so far added up, 1 for a bye.
+    pub score: f64,
+    /// Who it played in each round, by index.
+    pub opponents: Vec<usize>,
+    /// The games it moved first in, less those it moved second in.
+    pub first_balance: i32,
+    pub byes: usize,
+}
+
+/// The pairs of a round, who moves first before who moves second, and the
+/// entrant left out if there is an odd number of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Round {
+    pub pairs: Vec<(usize, usize)>,
+    pub bye: Option<usize>,
+}
+
+/// Tries at pairing a round without rematches before giving up on it.
+const MAX_STEPS: usize = 100_000;
+
+/// The scores of everyone `entrants[i]` played added up, which tells apart
+/// those with the same score: a score against strong opponents ranks higher.
+pub fn buchholz(entrants: &[Entrant], i: usize) -> f64 {
+    entrants[i]
+        .opponents
+        .iter()
+        .fold(0.0, |sum, &o| sum + entrants[o].score)
+}
+
+/// The indices of `entrants`, best first: by score, then by [`buchholz`],
+/// then by index.
+pub fn ranking(entrants: &[Entrant]) -> Vec<usize> {
+    let buchholz: Vec<_> = (0..entrants.len()).map(|i| buchholz(entrants, i)).collect();
+    let mut order: Vec<_> = (0..entrants.len()).collect();
+    order.sort_by(|&a, &b| {
+        let score = entrants[b].score.total_cmp(&entrants[a].score);
+        score.then(buchholz[b].total_cmp(&buchholz[a]))
+    });
+    order
+}
+
+/// Pairs the next round. Going down the [`ranking`], each entrant left
+/// plays the best ranked one it hasn't met, going back on earlier pairs
+/// when the rest can't be paired that way. Only when there is no way at all
+/// do those who met play again, each against whoever it met the fewest
+/// times. With an odd number, the lowest ranked of those with the fewest
+/// byes sits out. Of each pair, the one that moved first less often moves
+/// first, or the better ranked if they did as often.
+///
+/// ```
+/// use game_def::swiss::{pair, Entrant, Round};
+///
+/// let entrant = |score, opponents: &[usize], first_balance| Entrant {
+///     score,
+///     opponents: opponents.to_vec(),
+///     first_balance,
+///     byes: 0,
+/// };
+/// // 0 beat 1 moving first, and 3 beat 2 moving second.
+/// let entrants = [
+///     entrant(1.0, &[1], 1),
+///     entrant(0.0, &[0], -1),
+///     entrant(0.0, &[3], 1),
+///     entrant(1.0, &[2], -1),
+/// ];
+/// let round = Round { pairs: vec![(3, 0), (1, 2)], bye: None };
+/// assert_eq!(pair(&entrants), round);
+///
+/// // 0 against 1 would leave 2 and 3, who met already.
+/// let entrants = [
+///     entrant(3.0, &[], 0),
+///     entrant(2.0, &[], 0),
+///     entrant(1.0, &[3], 0),
+///     entrant(0.0, &[2], 0),
+/// ];
+/// let round = Round { pairs: vec![(0, 2), (1, 3)], bye: None };
+/// assert_eq!(pair(&entrants), round);
+///
+/// // 2 had a bye, so the last of the others has this one.
+/// let mut entrants = [entrant(1.0, &[1], 1), entrant(0.0, &[0], -1), entrant(1.0, &[], 0)];
+/// entrants[2].byes = 1;
+/// let round = Round { pairs: vec![(2, 0)], bye: Some(1) };
+/// assert_eq!(pair(&entrants), round);
+///
+/// // Two of 0, 1 and 2 have to meet again, so each plays whoever it met least.
+/// let entrants = [
+///     entrant(2.0, &[1, 2], 0),
+///     entrant(1.0, &[0, 2], 0),
+///     entrant(0.0, &[0, 1], 0),
+///     entrant(0.0, &[], 0),
+/// ];
+/// let round = Round { pairs: vec![(0, 3), (1, 2)], bye: None };
+/// assert_eq!(pair(&entrants), round);
+/// ```
+pub fn pair(entrants: &[Entrant]) -> Round {
+    let order = ranking(entrants);
+    let met = |a: usize, b: usize| entrants[a].opponents.iter().filter(|&&o| o == b).count();
+    let byes: Vec<_> = if order.len() % 2 == 1 {
+        let mut last_first: Vec<_> = order.iter().rev().copied().collect();
+        last_first.sort_by_key(|&i| entrants[i].byes);
+        last_first.into_iter().map(Some).collect()
+    } else {
+        vec![None]
+    };
+    let rest = |bye| -> Vec<_> { order.iter().copied().filter(|&i| Some(i) != bye).collect() };
+    let mut steps = 0;
+    let mut found = None;
+    for &bye in &byes {
+        if let Some(pairs) = pair_fresh(&rest(bye), &|a, b| met(a, b) > 0, &mut steps) {
+            found = Some((pairs, bye));
+            break;
+        }
+    }
+    let (pairs, bye) = found.unwrap_or_else(|| {
+        let mut left = rest(byes[0]);
+        let mut pairs = vec![];
+        while !left.is_empty() {
+            let a = left.remove(0);
+            let (k, _) = left
+                .iter()
+                .enumerate()
+                .min_by_key(|&(_, &b)| met(a, b))
+                .unwrap();
+            pairs.push((a, left.remove(k)));
+        }
+        (pairs, byes[0])
+    });
+    let pairs = pairs
+        .into_iter()
+        .map(|(a, b)| {
+            if entrants[b].first_balance < entrants[a].first_balance {
+                (b, a)
+            } else {
+                (a, b)
+            }
+        })
+        .collect();
+    Round { pairs, bye }
+}
+
+/// Pairs `rest`, best ranked first, so that no pair has `met`, or `None`
+/// if that can't be done in [`MAX_STEPS`] tries.
+fn pair_fresh(
+    rest: &[usize],
+    met: &impl Fn(usize, usize) -> bool,
+    steps: &mut usize,
+) -> Option<Vec<(usize, usize)>> {
+    let Some((&a, others)) = rest.split_first() else {
+        return Some(vec![]);
+    };
+    for (k, &b) in others.iter().enumerate() {
+        if met(a, b) {
+            continue;
+        }
+        *steps += 1;
+        if *steps > MAX_STEPS {
+            return None;
+        }
+        let mut left = others.to_vec();
+        left.remove(k);
+        if let Some(mut pairs) = pair_fresh(&left, met, steps) {
+            pairs.insert(0, (a, b));
+            return Some(pairs);
+        }
+    }
+    None
+}
@@ -0,0 +1,184 @@
+//! Transcripts: every message between the runner and an agent as it went
+//! over the wire, to file a bug against an agent with, or to send it again
+//! with `splendor-ai test-agent --transcript`.
+//!
+//! The runner writes one per agent with `--transcript-dir`, and an agent
+//! run by [`crate::ai_from_function`] or [`crate::run_agent`] writes its own
+//! side to the path in [`ENV`]. Both look the same: a header naming the
+//! format and protocol, then a line per message, each the time in
+//! milliseconds since the Unix epoch, a [`Direction`] and the message. JSON
+//! messages are kept as they are, and MessagePack frames in hex, length
+//! prefix and all. Lines starting with `#` are notes, like an action the
+//! runner refused.
+//!
+//! ```text
+//! # splendor transcript, json, messages
+//! 1729000000120 > {"type":"init","protocol_version":6,...}
+//! 1729000000123 > {"type":"move_request","observation":{...}}
+//! 1729000000130 < {"type":"pick_three","one":"red",...}
+//! 1729000000131 # take r g u is invalid: ..., asking again
+//! ```
+//!
+//! Each line is written with one write to an unbuffered file, so an agent
+//! that crashes doesn't take the last of it along.
+
+use std::{
+    fs::File,
+    io::Write,
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context};
+
+/// The variable an agent's transcript path is read from.
+pub const ENV: &str = "SPLENDOR_TRANSCRIPT";
+
+const HEADER: &str = "# splendor transcript";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// `>`, from the runner to the agent.
+    ToAgent,
+    /// `<`, from the agent to the runner.
+    FromAgent,
+}
+
+impl Direction {
+    fn marker(self) -> char {
+        match self {
+            Direction::ToAgent => '>',
+            Direction::FromAgent => '<',
+        }
+    }
+}
+
+/// A transcript being written, shared by the threads that read and write
+/// the agent's messages.
+pub struct Transcript {
+    file: Mutex<File>,
+    msgpack: bool,
+}
+
+impl Transcript {
+    /// Creates `path`, for an agent talking MessagePack if `msgpack` and the
+    /// message protocol if `messages`.
+    pub fn create(path: impl AsRef<Path>, msgpack: bool, messages: bool) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let mut file =
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let format = if msgpack { "msgpack" } else { "json" };
+        let protocol = if messages { "messages" } else { "states" };
+        writeln!(file, "{HEADER}, {format}, {protocol}")?;
+        Ok(Transcript {
+            file: Mutex::new(file),
+            msgpack,
+        })
+    }
+
+    /// The transcript [`ENV`] asks for, if any. One that can't be created
+    /// is left out with a word on stderr, as the agent can play without it.
+    pub fn from_env(msgpack: bool, messages: bool) -> Option<Self> {
+        let path = std::env::var_os(ENV).filter(|path| !path.is_empty())?;
+        Transcript::create(&path, msgpack, messages)
+            .map_err(|e| eprintln!("{e:#}"))
+            .ok()
+    }
+
+    /// Writes down a message as it went over the wire, a JSON line or a whole
+    /// MessagePack frame.
+    pub fn message(&self, direction: Direction, wire: &[u8]) {
+        let text = match self.msgpack {
+            true => wire.iter().map(|b| format!("{b:02x}")).collect(),
+            false => String::from_utf8_lossy(wire)
+                .trim_end_matches(['\r', '\n'])
+                .to_owned(),
+        };
+        self.line(direction.marker(), &text);
+    }
+
+    pub fn note(&self, text: &str) {
+        self.line('#', text);
+    }
+
+    fn line(&self, marker: char, text: &str) {
+        let ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis());
+        let line = format!("{ms} {marker} {text}\n");
+        // Losing the transcript is no reason to stop the game.
+        _ = self.file.lock().unwrap().write_all(line.as_bytes());
+    }
+}
+
+/// A transcript read back.
+pub struct Recorded {
+    pub msgpack: bool,
+    pub messages: bool,
+    /// Each message in order, as it went over the wire. Notes are left out.
+    pub lines: Vec<(Direction, Vec<u8>)>,
+}
+
+/// Reads a transcript [`Transcript`] wrote.
+///
+/// ```
+/// use game_def::transcript::{self, Direction, Transcript};
+///
+/// let path = std::env::temp_dir().join(format!("transcript-{}", std::process::id()));
+/// let transcript = Transcript::create(&path, false, true)?;
+/// transcript.message(Direction::ToAgent, b"{\"type\":\"game_over\"}\n");
+/// transcript.note("the agent didn't answer");
+/// let recorded = transcript::read(&path)?;
+/// assert!(recorded.messages && !recorded.msgpack);
+/// assert_eq!(
+///     recorded.lines,
+///     [(Direction::ToAgent, b"{\"type\":\"game_over\"}\n".to_vec())]
+/// );
+/// # std::fs::remove_file(path)?;
+/// # anyhow::Ok(())
+/// ```
+pub fn read(path: impl AsRef<Path>) -> anyhow::Result<Recorded> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut lines = text.lines();
+    let header = lines.next().and_then(|line| line.strip_prefix(HEADER));
+    let (msgpack, messages) = match header {
+        Some(", json, states") => (false, false),
+        Some(", json, messages") => (false, true),
+        Some(", msgpack, states") => (true, false),
+        Some(", msgpack, messages") => (true, true),
+        _ => bail!("{} is not a transcript", path.display()),
+    };
+    let mut recorded = Recorded {
+        msgpack,
+        messages,
+        lines: vec![],
+    };
+    for (i, line) in lines.enumerate() {
+        let bad = || format!("Line {} of {} is not a message", i + 2, path.display());
+        let (_ms, rest) = line.split_once(' ').with_context(bad)?;
+        let (marker, text) = rest.split_at_checked(1).with_context(bad)?;
+        let direction = match marker {
+            ">" => Direction::ToAgent,
+            "<" => Direction::FromAgent,
+            "#" => continue,
+            _ => bail!(bad()),
+        };
+        let text = text.strip_prefix(' ').unwrap_or(text);
+        let wire = match msgpack {
+            true => (0..text.len())
+                .step_by(2)
+                .map(|i| {
+                    text.get(i..i + 2)
+                        .and_then(|b| u8::from_str_radix(b, 16).ok())
+                })
+                .collect::<Option<Vec<u8>>>()
+                .with_context(bad)?,
+            false => format!("{text}\n").into_bytes(),
+        };
+        recorded.lines.push((direction, wire));
+    }
+    Ok(recorded)
+}
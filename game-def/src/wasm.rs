@@ -0,0 +1,14 @@
+//! String in, string out entry points for the browser, so a wasm-bindgen wrapper
+//! only has to forward them.
+
+use crate::{Action, State};
+
+/// Runs a JSON [`Action`] on a JSON [`State`] and returns the new state as JSON.
+pub fn run_json(state_json: &str, action_json: &str) -> Result<String, String> {
+    let mut state: State =
+        serde_json::from_str(state_json).map_err(|e| format!("Invalid state: {e}"))?;
+    let action: Action =
+        serde_json::from_str(action_json).map_err(|e| format!("Invalid action: {e}"))?;
+    state.run(action).map_err(|e| format!("{e:#}"))?;
+    Ok(state.json())
+}
@@ -0,0 +1,407 @@
+//! Just enough of WebSocket (RFC 6455) over std's TCP for agents in a
+//! browser or Node: the handshake on both ends, and unfragmented frames out,
+//! any frames in. No extensions and no TLS, put a proxy in front for `wss:`.
+//!
+//! Over WebSocket, each message of the agent protocol is one text frame
+//! holding the JSON, without the newline. An agent first sends a
+//! [`Hello`](crate::Hello), like over TCP.
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context};
+
+use crate::{
+    protocol::{answer, Answer},
+    AgentHandler, Hello, Observation, RunnerMessage,
+};
+
+/// Appended to the client's key to prove the server speaks WebSocket.
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// Larger messages are refused, a whole state is a few kilobytes.
+const MAX_MESSAGE: usize = 16 << 20;
+/// Lines of a handshake before it is refused.
+const MAX_HEADERS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close,
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut padded = data.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend((data.len() as u64 * 8).to_be_bytes());
+    for chunk in padded.chunks(64) {
+        let mut w = [0u32; 80];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes(chunk[4 * i..4 * i + 4].try_into().unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &wi) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..20 => ((b & c) | (!b & d), 0x5A827999),
+                20..40 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..60 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(wi);
+            (e, d, c, b, a) = (d, c, b.rotate_left(30), a, t);
+        }
+        for (h, x) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(x);
+        }
+    }
+    let mut digest = [0; 20];
+    for (bytes, h) in digest.chunks_mut(4).zip(h) {
+        bytes.copy_from_slice(&h.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut r = String::new();
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                r.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                r.push('=');
+            }
+        }
+    }
+    r
+}
+
+/// The `Sec-WebSocket-Accept` answer to a client's `Sec-WebSocket-Key`.
+///
+/// ```
+/// use game_def::websocket::accept_key;
+///
+/// // The example of RFC 6455.
+/// assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+/// ```
+pub fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{key}{GUID}").as_bytes()))
+}
+
+/// Reads the header lines of an HTTP request or response, after its first
+/// line, as lowercase names and their values.
+fn read_headers(reader: &mut impl BufRead) -> io::Result<Vec<(String, String)>> {
+    let mut headers = vec![];
+    for _ in 0..MAX_HEADERS {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Ok(headers);
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_owned()));
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "Too many headers",
+    ))
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.as_str())
+}
+
+/// The server side of the handshake: reads the client's upgrade request and
+/// answers it. Returns the path it asked for, like `/agent`.
+pub fn accept(reader: &mut impl BufRead, writer: &mut impl Write) -> io::Result<String> {
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    let headers = read_headers(reader)?;
+    let path = match request.split_whitespace().collect::<Vec<_>>()[..] {
+        ["GET", path, _] => Some(path.to_owned()),
+        _ => None,
+    };
+    let upgrade = header(&headers, "upgrade").is_some_and(|u| u.eq_ignore_ascii_case("websocket"));
+    let (Some(path), Some(key), true) = (path, header(&headers, "sec-websocket-key"), upgrade)
+    else {
+        writer.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")?;
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not a WebSocket upgrade",
+        ));
+    };
+    write!(
+        writer,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key)
+    )?;
+    writer.flush()?;
+    Ok(path)
+}
+
+/// Changes every time, as clients must mask with keys a proxy can't guess.
+/// Not cryptographic, nothing here is secret.
+fn random_bytes() -> [u8; 16] {
+    use std::hash::BuildHasher;
+    let state = std::collections::hash_map::RandomState::new();
+    let mut bytes = [0; 16];
+    for (i, half) in bytes.chunks_mut(8).enumerate() {
+        let hash = state.hash_one((SystemTime::now().duration_since(UNIX_EPOCH).ok(), i));
+        half.copy_from_slice(&hash.to_le_bytes());
+    }
+    bytes
+}
+
+/// A client connection to a WebSocket server, as an agent opens to a runner
+/// listening with `ws:`.
+pub struct Client {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Client {
+    /// Connects to `address`, like `localhost:7002`, and upgrades to
+    /// WebSocket at `path`.
+    pub fn connect(address: &str, path: &str) -> io::Result<Client> {
+        let mut writer = TcpStream::connect(address)?;
+        writer.set_nodelay(true)?;
+        let mut reader = BufReader::new(writer.try_clone()?);
+        let key = base64(&random_bytes());
+        write!(
+            writer,
+            "GET {path} HTTP/1.1\r\nHost: {address}\r\nUpgrade: websocket\r\n\
+             Connection: Upgrade\r\nSec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n\r\n"
+        )?;
+        writer.flush()?;
+        let mut status = String::new();
+        reader.read_line(&mut status)?;
+        let headers = read_headers(&mut reader)?;
+        if status.split_whitespace().nth(1) != Some("101")
+            || header(&headers, "sec-websocket-accept") != Some(&accept_key(&key))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("The server refused the upgrade: {}", status.trim_end()),
+            ));
+        }
+        Ok(Client { reader, writer })
+    }
+
+    pub fn read(&mut self) -> io::Result<Option<Message>> {
+        read_message(&mut self.reader)
+    }
+
+    pub fn send(&mut self, message: &Message) -> io::Result<()> {
+        let mask = random_bytes()[..4].try_into().unwrap();
+        write_message(&mut self.writer, message, Some(mask))
+    }
+}
+
+/// Reads one frame: whether it is the last of its message, its opcode and
+/// the unmasked payload. `None` if the connection ends before it.
+fn read_frame(reader: &mut impl Read) -> io::Result<Option<(bool, u8, Vec<u8>)>> {
+    let mut head = [0; 2];
+    match reader.read_exact(&mut head) {
+        Ok(()) => (),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let fin = head[0] & 0x80 != 0;
+    let opcode = head[0] & 0x0F;
+    let len = match head[1] & 0x7F {
+        126 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len)?;
+            u16::from_be_bytes(len) as u64
+        }
+        127 => {
+            let mut len = [0; 8];
+            reader.read_exact(&mut len)?;
+            u64::from_be_bytes(len)
+        }
+        len => len as u64,
+    };
+    if len > MAX_MESSAGE as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Frame too large",
+        ));
+    }
+    let mut mask = None;
+    if head[1] & 0x80 != 0 {
+        let mut key = [0; 4];
+        reader.read_exact(&mut key)?;
+        mask = Some(key);
+    }
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload)?;
+    if let Some(key) = mask {
+        for (i, b) in payload.iter_mut().enumerate() {
+            *b ^= key[i % 4];
+        }
+    }
+    Ok(Some((fin, opcode, payload)))
+}
+
+/// Reads the next message, putting fragmented ones back together. Control
+/// messages in between come out first. `None` if the connection ends.
+pub fn read_message(reader: &mut impl Read) -> io::Result<Option<Message>> {
+    let mut data: Option<(u8, Vec<u8>)> = None;
+    loop {
+        let Some((fin, opcode, payload)) = read_frame(reader)? else {
+            return Ok(None);
+        };
+        let (opcode, payload) = match opcode {
+            0x8 => return Ok(Some(Message::Close)),
+            0x9 => return Ok(Some(Message::Ping(payload))),
+            0xA => return Ok(Some(Message::Pong(payload))),
+            0x0 => {
+                let Some((opcode, mut so_far)) = data.take() else {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Continuation without a message",
+                    ));
+                };
+                if so_far.len() + payload.len() > MAX_MESSAGE {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Message too large",
+                    ));
+                }
+                so_far.extend(payload);
+                (opcode, so_far)
+            }
+            0x1 | 0x2 => (opcode, payload),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown opcode {opcode}"),
+                ))
+            }
+        };
+        if !fin {
+            data = Some((opcode, payload));
+            continue;
+        }
+        return Ok(Some(match opcode {
+            0x1 => Message::Text(
+                String::from_utf8(payload)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            ),
+            _ => Message::Binary(payload),
+        }));
+    }
+}
+
+/// Writes `message` as a single frame and flushes. Clients mask their
+/// frames, servers don't.
+pub fn write_message(
+    writer: &mut impl Write,
+    message: &Message,
+    mask: Option<[u8; 4]>,
+) -> io::Result<()> {
+    let (opcode, payload) = match message {
+        Message::Text(text) => (0x1, text.as_bytes()),
+        Message::Binary(bytes) => (0x2, &bytes[..]),
+        Message::Close => (0x8, &[][..]),
+        Message::Ping(bytes) => (0x9, &bytes[..]),
+        Message::Pong(bytes) => (0xA, &bytes[..]),
+    };
+    let mut frame = vec![0x80 | opcode];
+    let masked = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        len @ 0..126 => frame.push(masked | len as u8),
+        len @ 126..65536 => {
+            frame.push(masked | 126);
+            frame.extend((len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(masked | 127);
+            frame.extend((len as u64).to_be_bytes());
+        }
+    }
+    match mask {
+        Some(key) => {
+            frame.extend(key);
+            frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ key[i % 4]));
+        }
+        None => frame.extend(payload),
+    }
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+/// Like [`run_agent_over_tcp`](crate::run_agent_over_tcp), over a WebSocket
+/// to a runner listening with `ws:`. Always JSON.
+///
+/// ```no_run
+/// use game_def::{websocket, Agent, Observation, RandomAgent};
+///
+/// let client = websocket::Client::connect("race.example.com:7002", "/agent")?;
+/// websocket::run_agent(client, "random", false, |o: Observation| {
+///     RandomAgent.act(&o.state)
+/// })?;
+/// # anyhow::Ok(())
+/// ```
+pub fn run_agent(
+    mut client: Client,
+    name: &str,
+    messages: bool,
+    mut handler: impl AgentHandler,
+) -> anyhow::Result<()> {
+    let hello = Hello {
+        name: name.to_owned(),
+    };
+    client.send(&Message::Text(serde_json::to_string(&hello)?))?;
+    loop {
+        let text = match client.read().context("Failed to read a message")? {
+            Some(Message::Text(text)) => text,
+            Some(Message::Ping(payload)) => {
+                client.send(&Message::Pong(payload))?;
+                continue;
+            }
+            Some(Message::Pong(_)) => continue,
+            Some(Message::Binary(_)) => bail!("Got a binary message"),
+            Some(Message::Close) | None => return Ok(()),
+        };
+        let reply = if messages {
+            let message: RunnerMessage = crate::decode_message(text.as_bytes(), false)?;
+            answer(&mut handler, message)?
+        } else {
+            let observation: Observation = crate::decode_message(text.as_bytes(), false)?;
+            Some(Answer::Action(handler.act(observation)?))
+        };
+        if let Some(reply) = reply {
+            client.send(&Message::Text(serde_json::to_string(&reply)?))?;
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! Zobrist hashing of [`State`]. Keys are derived from a fixed mixing function
+//! instead of a table, so they are the same in every process and need no setup.
+//!
+//! Only what players can see goes into the hash: the face up slots and the
+//! size of each deck, not the order of the draw pile. Besides the board, the
+//! first player and the skips in a row go in, as they decide when the game
+//! ends, and so does the round when [`GameConfig::max_rounds`] is set. Without
+//! a round limit the round changes nothing, and leaving it out lets the same
+//! position reached in different rounds share a hash.
+//!
+//! [`GameConfig::max_rounds`]: crate::GameConfig::max_rounds
+
+use enum_map::Enum;
+
+use crate::{Phase, ResourceMap, State, Tier};
+
+const BANK: u64 = 1;
+const BANK_WILDS: u64 = 2;
+const MORTAL: u64 = 3;
+const IMMORTAL: u64 = 4;
+const WILDS: u64 = 5;
+const SCORE: u64 = 6;
+const RESERVED: u64 = 7;
+const SLOT: u64 = 8;
+const DECK_LEN: u64 = 9;
+const NOBEL: u64 = 10;
+const TURN: u64 = 11;
+const PHASE: u64 = 12;
+const RESIGNED: u64 = 13;
+const FIRST_PLAYER: u64 = 14;
+const PASSES: u64 = 15;
+const ROUND: u64 = 16;
+
+/// The pseudo random key of one (tag, a, b, c) feature.
+pub fn key(tag: u64, a: u64, b: u64, c: u64) -> u64 {
+    [a, b, c]
+        .into_iter()
+        .fold(splitmix(tag), |h, x| splitmix(h ^ x))
+}
+
+fn splitmix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn resources(tag: u64, owner: u64, map: &ResourceMap) -> u64 {
+    map.iter().fold(0, |h, (c, v)| {
+        h ^ key(tag, owner, c.into_usize() as u64, *v as u64)
+    })
+}
+
+/// The whole hash, computed from scratch.
+pub fn full(state: &State) -> u64 {
+    let players = (0..state.players.len()).fold(0, |h, p| h ^ player(state, p));
+    let decks = Tier::ALL.into_iter().fold(0, |h, d| h ^ deck(state, d));
+    common(state) ^ players ^ decks
+}
+
+/// The part of the hash an action by `player` taking from `deck` can change.
+pub(crate) fn local(state: &State, player_index: usize, tier: Option<Tier>) -> u64 {
+    common(state) ^ player(state, player_index) ^ tier.map_or(0, |d| deck(state, d))
+}
+
+/// Bank, nobels, turn, phase and what decides the end of the game.
+fn common(state: &State) -> u64 {
+    let phase = match state.phase {
+        Phase::Play => 0,
+        Phase::Discard => 1,
+        Phase::ChooseNobel => 2,
+    };
+    let nobels = state.nobels.iter().fold(0, |h, n| {
+        let cost = n
+            .cost
+            .iter()
+            .fold(0u64, |x, (_, v)| x.wrapping_mul(31).wrapping_add(*v as u64));
+        h ^ key(NOBEL, cost, n.score.into(), n.min_score.unwrap_or(0).into())
+    });
+    resources(BANK, 0, &state.coins.gems)
+        ^ key(BANK_WILDS, state.coins.gold as u64, 0, 0)
+        ^ nobels
+        ^ key(TURN, state.turn as u64, 0, 0)
+        ^ key(PHASE, phase, 0, 0)
+        ^ key(FIRST_PLAYER, state.first_player as u64, 0, 0)
+        ^ key(PASSES, state.passes as u64, 0, 0)
+        ^ state
+            .config
+            .max_rounds
+            .map_or(0, |_| key(ROUND, state.round as u64, 0, 0))
+}
+
+fn player(state: &State, index: usize) -> u64 {
+    let p = &state.players[index];
+    let i = index as u64;
+    let reserved = p.reserved.iter().enumerate().fold(0, |h, (j, c)| {
+        let blind = p.reserved_blind.get(j).copied().unwrap_or(false);
+        h ^ key(RESERVED, i, c.id() as u64, blind.into())
+    });
+    resources(MORTAL, i, &p.mortal.gems)
+        ^ resources(IMMORTAL, i, &p.immortal)
+        ^ key(WILDS, i, p.mortal.gold as u64, 0)
+        ^ key(SCORE, i, p.score.into(), 0)
+        ^ key(RESIGNED, i, p.resigned.into(), 0)
+        ^ reserved
+}
+
+fn deck(state: &State, tier: Tier) -> u64 {
+    let d = &state.decks[tier];
+    let i = tier.into_usize() as u64;
+    let slots = d
+        .iter()
+        .take(state.config.visible_cards)
+        .enumerate()
+        .fold(0, |h, (j, c)| h ^ key(SLOT, i, j as u64, c.id() as u64));
+    slots ^ key(DECK_LEN, i, d.len() as u64, 0)
+}
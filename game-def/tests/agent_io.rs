@@ -0,0 +1,234 @@
+//! The agent loops of `ai_from_function` and `ai_from_function_async`, over
+//! in-memory IO.
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    future::Future,
+    io::Write,
+    pin::pin,
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use game_def::{ai_from_function_async, ai_over_io, Action, State};
+
+mod common;
+
+use common::game;
+
+/// Records what was written, and how much of it when each flush came.
+#[derive(Default)]
+struct Recorder {
+    written: Vec<u8>,
+    flushed_at: Vec<usize>,
+}
+
+impl Write for Recorder {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.written.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.flushed_at.push(self.written.len());
+        Ok(())
+    }
+}
+
+fn first_legal(state: State) -> anyhow::Result<Action> {
+    Ok(state.legal_actions().next().unwrap())
+}
+
+fn lines(states: &[State]) -> String {
+    states.iter().map(|s| s.json() + "\n").collect()
+}
+
+fn replies(recorder: &Recorder) -> Vec<Action> {
+    String::from_utf8(recorder.written.clone())
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn answers_every_state_and_returns_at_the_end_of_the_input() {
+    let states = [game(26, 2), game(27, 3)];
+    let mut output = Recorder::default();
+    ai_over_io(lines(&states).as_bytes(), &mut output, false, first_legal).unwrap();
+    let expected: Vec<Action> = states
+        .iter()
+        .map(|s| s.legal_actions().next().unwrap())
+        .collect();
+    assert_eq!(replies(&output), expected);
+    // Flushed after each action, as soon as it is written.
+    let first_line = output.written.iter().position(|&b| b == b'\n').unwrap() + 1;
+    assert_eq!(output.flushed_at, [first_line, output.written.len()]);
+}
+
+#[test]
+fn nothing_to_read_is_not_an_error() {
+    let mut output = Recorder::default();
+    ai_over_io(&b""[..], &mut output, false, first_legal).unwrap();
+    assert!(output.written.is_empty());
+}
+
+#[test]
+fn a_malformed_line_is_an_error_naming_it() {
+    let input = lines(&[game(28, 2)]) + "{\"decks\": oops}\n" + &lines(&[game(29, 2)]);
+    let mut output = Recorder::default();
+    let error = ai_over_io(input.as_bytes(), &mut output, false, first_legal).unwrap_err();
+    let message = format!("{error:#}");
+    assert!(
+        message.starts_with(r#"Malformed message "{\"decks\": oops}""#),
+        "{message}"
+    );
+    // The state before it was answered, and nothing after it.
+    assert_eq!(replies(&output).len(), 1);
+}
+
+#[test]
+fn an_error_of_the_agent_stops_the_loop() {
+    let input = lines(&[game(30, 2), game(31, 2)]);
+    let mut output = Recorder::default();
+    let mut calls = 0;
+    let error = ai_over_io(input.as_bytes(), &mut output, false, |_: State| {
+        calls += 1;
+        anyhow::bail!("No idea")
+    })
+    .unwrap_err();
+    assert_eq!(error.to_string(), "No idea");
+    assert_eq!(calls, 1);
+    assert!(output.written.is_empty());
+}
+
+/// Polls `future` until it is done, for futures that wake themselves.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+/// Pending once, like IO that isn't ready yet.
+async fn yield_once() {
+    let mut yielded = false;
+    std::future::poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+
+/// What the async loop wrote, one write for each reply.
+fn async_replies(written: &[Vec<u8>]) -> Vec<Action> {
+    written
+        .iter()
+        .map(|bytes| {
+            assert_eq!(bytes.last(), Some(&b'\n'));
+            serde_json::from_slice(bytes).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn the_async_loop_answers_every_state_and_returns_at_the_end_of_the_input() {
+    let states = [game(32, 2), game(33, 4)];
+    let input = RefCell::new(states.iter().map(State::json).collect::<VecDeque<_>>());
+    let written = RefCell::new(vec![]);
+    let mut deadlines = vec![];
+    block_on(ai_from_function_async(
+        async || {
+            yield_once().await;
+            Ok(input.borrow_mut().pop_front())
+        },
+        async |bytes| {
+            yield_once().await;
+            written.borrow_mut().push(bytes);
+            Ok(())
+        },
+        Some(Duration::from_secs(60)),
+        async |state: State, deadline| {
+            deadlines.push(deadline);
+            yield_once().await;
+            state.legal_actions().next().unwrap()
+        },
+    ))
+    .unwrap();
+    let expected: Vec<Action> = states
+        .iter()
+        .map(|s| s.legal_actions().next().unwrap())
+        .collect();
+    assert_eq!(async_replies(&written.borrow()), expected);
+    // The deadline is a minute after each message came.
+    assert_eq!(deadlines.len(), 2);
+    for deadline in deadlines {
+        let left = deadline.unwrap() - Instant::now();
+        assert!(left > Duration::from_secs(50), "{left:?}");
+    }
+}
+
+#[test]
+fn the_async_loop_passes_no_deadline_without_one() {
+    let mut input = VecDeque::from([game(34, 2).json()]);
+    let mut written = vec![];
+    block_on(ai_from_function_async(
+        async || Ok(input.pop_front()),
+        async |bytes| {
+            written.push(bytes);
+            Ok(())
+        },
+        None,
+        async |state: State, deadline| {
+            assert_eq!(deadline, None);
+            state.legal_actions().next().unwrap()
+        },
+    ))
+    .unwrap();
+    assert_eq!(async_replies(&written).len(), 1);
+}
+
+#[test]
+fn the_async_loop_stops_at_a_malformed_line_or_a_failed_write() {
+    let mut input = VecDeque::from([game(35, 2).json(), "{\"decks\": oops}".to_owned()]);
+    let mut written = vec![];
+    let error = block_on(ai_from_function_async(
+        async || Ok(input.pop_front()),
+        async |bytes| {
+            written.push(bytes);
+            Ok(())
+        },
+        None,
+        async |state: State, _| state.legal_actions().next().unwrap(),
+    ))
+    .unwrap_err();
+    let message = format!("{error:#}");
+    assert!(
+        message.starts_with(r#"Malformed message "{\"decks\": oops}""#),
+        "{message}"
+    );
+    assert_eq!(async_replies(&written).len(), 1);
+
+    let mut input = VecDeque::from([game(36, 2).json(), game(37, 2).json()]);
+    let error = block_on(ai_from_function_async(
+        async || Ok(input.pop_front()),
+        async |_| Err(std::io::ErrorKind::BrokenPipe.into()),
+        None,
+        async |state: State, _| state.legal_actions().next().unwrap(),
+    ))
+    .unwrap_err();
+    assert_eq!(
+        format!("{error:#}"),
+        "Failed to write an action: broken pipe"
+    );
+    // The runner is gone, so the next line is never asked for.
+    assert_eq!(input.len(), 1);
+}
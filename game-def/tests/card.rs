@@ -0,0 +1,60 @@
+//! What a card costs a given player.
+
+use game_def::{Card, CoinMap, Player, ResourceKind, ResourceMap};
+
+fn map(code: &str) -> ResourceMap {
+    ResourceMap::from_code(code)
+}
+
+fn card() -> Card {
+    Card::new(7, ResourceKind::Green, 2, map("3r+2u+1k"))
+}
+
+fn player(cards: &str, gems: &str, gold: usize) -> Player {
+    let mut player = Player::new("a");
+    player.immortal = map(cards);
+    player.mortal = CoinMap {
+        gems: map(gems),
+        gold,
+    };
+    player
+}
+
+#[test]
+fn accessors() {
+    let card = card();
+    assert_eq!(card.id(), 7);
+    assert_eq!(card.score(), 2);
+    assert_eq!(card.cost(), &map("3r+2u+1k"));
+    assert_eq!(card.color(), Some(ResourceKind::Green));
+    assert_eq!(card.adds(), &map("1g"));
+}
+
+#[test]
+fn cards_discount_the_cost() {
+    let card = card();
+    assert_eq!(card.cost_for(&player("", "", 0)), map("3r+2u+1k"));
+    assert_eq!(card.cost_for(&player("1r+1u+5w", "", 0)), map("2r+1u+1k"));
+    // More cards of a color than the cost asks for are no use.
+    assert_eq!(
+        card.cost_for(&player("5r+3u+1k", "", 0)),
+        ResourceMap::new()
+    );
+}
+
+#[test]
+fn coins_short_counts_gold() {
+    let card = card();
+    assert_eq!(card.coins_short(&player("", "", 0)), 6);
+    assert_eq!(card.coins_short(&player("1r", "2r+1u", 0)), 2);
+    assert_eq!(card.coins_short(&player("1r", "2r+1u", 1)), 1);
+    assert_eq!(card.coins_short(&player("1r", "2r+1u", 2)), 0);
+    // Gold beyond what is missing doesn't go negative.
+    assert_eq!(card.coins_short(&player("1r", "2r+1u", 5)), 0);
+    // Coins of other colors don't help.
+    assert_eq!(card.coins_short(&player("", "5w", 0)), 6);
+    for gold in 0..8 {
+        let p = player("1u", "1r+1k", gold);
+        assert_eq!(p.can_purchase(card.cost()), card.coins_short(&p) == 0);
+    }
+}
@@ -0,0 +1,75 @@
+//! The official cards and nobels.
+
+use std::collections::BTreeMap;
+
+use game_def::{
+    cards::{standard_decks, standard_nobels, tier_of},
+    ResourceKind, Tier,
+};
+
+/// How many cards of `deck` are worth each score.
+fn scores(deck: Tier) -> Vec<(u8, usize)> {
+    let mut count = BTreeMap::new();
+    for card in &standard_decks()[deck] {
+        *count.entry(card.score()).or_insert(0) += 1;
+    }
+    count.into_iter().collect()
+}
+
+#[test]
+fn decks_have_the_official_sizes_and_scores() {
+    let decks = standard_decks();
+    assert_eq!(Tier::ALL.map(|d| decks[d].len()), [40, 30, 20]);
+    assert_eq!(scores(Tier::One), [(0, 35), (1, 5)]);
+    assert_eq!(scores(Tier::Two), [(1, 10), (2, 15), (3, 5)]);
+    assert_eq!(scores(Tier::Three), [(3, 5), (4, 10), (5, 5)]);
+}
+
+#[test]
+fn every_color_has_its_share_of_each_deck() {
+    let decks = standard_decks();
+    for (deck, per_color) in Tier::ALL.into_iter().zip([8, 6, 4]) {
+        for color in ResourceKind::ALL {
+            let count = decks[deck]
+                .iter()
+                .filter(|c| c.color() == Some(color))
+                .count();
+            assert_eq!(count, per_color, "{color:?} in deck {deck:?}");
+        }
+    }
+}
+
+#[test]
+fn cards_are_numbered_in_deck_order() {
+    let decks = standard_decks();
+    let all: Vec<_> = Tier::ALL.iter().flat_map(|&d| &decks[d]).collect();
+    for (id, card) in all.iter().enumerate() {
+        assert_eq!(card.id(), id);
+        assert_eq!(
+            tier_of(id),
+            Tier::ALL.into_iter().find(|&d| decks[d].contains(card))
+        );
+    }
+    assert_eq!(tier_of(all.len()), None);
+    // No two cards are the same.
+    for (i, a) in all.iter().enumerate() {
+        assert!(all[..i]
+            .iter()
+            .all(|b| (b.color(), b.cost()) != (a.color(), a.cost())));
+    }
+}
+
+#[test]
+fn nobels_are_the_official_ten() {
+    let nobels = standard_nobels();
+    assert_eq!(nobels.len(), 10);
+    for nobel in &nobels {
+        assert_eq!(nobel.score, 3);
+        let counts: Vec<usize> = ResourceKind::ALL
+            .into_iter()
+            .map(|c| nobel.cost[c])
+            .filter(|&n| n > 0)
+            .collect();
+        assert!(counts == [4, 4] || counts == [3, 3, 3], "{}", nobel.cost);
+    }
+}
@@ -0,0 +1,54 @@
+//! `check_action` against what `run` and `apply` do, see `game_def::fuzz`.
+
+use game_def::{fuzz::random_reachable_state, Action, Phase, ResourceMap, RuleError};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+mod common;
+
+use common::{game, give};
+
+#[test]
+fn check_action_agrees_with_run_and_apply() {
+    let mut rng = StdRng::seed_from_u64(43);
+    let mut accepted = 0;
+    for _ in 0..300 {
+        let moves = rng.gen_range(0..150);
+        let state = random_reachable_state(&mut rng, moves);
+        for _ in 0..200 {
+            // Legal ones half of the time, as random ones rarely are.
+            let action = match rng.gen_bool(0.5) {
+                true => {
+                    let legal: Vec<_> = state.legal_actions().collect();
+                    legal[rng.gen_range(0..legal.len())].clone()
+                }
+                false => rng.gen(),
+            };
+            let checked = state.check_action(&action);
+            let run = state.clone().run(action.clone()).map(|_| ());
+            let applied = state.clone().apply(&action).map(|_| ());
+            assert_eq!(checked, run, "{action} in {}", state.json());
+            assert_eq!(checked, applied, "{action} in {}", state.json());
+            accepted += checked.is_ok() as usize;
+        }
+    }
+    assert!(accepted > 10_000, "{accepted}");
+}
+
+#[test]
+fn a_discard_phase_within_the_limit_is_an_error_not_a_panic() {
+    let mut state = game(44, 2);
+    give(&mut state, 0, "2r", 0);
+    state.phase = Phase::Discard;
+    let discard = Action::Discard {
+        coins: ResourceMap::from_code("1r"),
+        wilds: 0,
+    };
+    assert_eq!(
+        state.check_action(&discard),
+        Err(RuleError::NothingToDiscard)
+    );
+    assert_eq!(
+        state.clone().run(discard).unwrap_err(),
+        RuleError::NothingToDiscard
+    );
+}
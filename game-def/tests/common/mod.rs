@@ -0,0 +1,85 @@
+//! Positions and actions the tests of the rules share.
+
+// Each test file uses some of these.
+#![allow(dead_code)]
+
+use game_def::{Action, Card, CoinMap, GameConfig, ResourceMap, State, Tier};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// A fresh game for `players` players dealt from `seed`.
+pub fn game(seed: u64, players: usize) -> State {
+    let names = ["a", "b", "c", "d"];
+    State::setup(
+        &names[..players],
+        GameConfig::default(),
+        &mut StdRng::seed_from_u64(seed),
+    )
+}
+
+/// Moves `gems`, a [`ResourceMap`] code, and `gold` from the bank into the
+/// hand of `player`.
+pub fn give(state: &mut State, player: usize, gems: &str, gold: usize) {
+    let coins = CoinMap {
+        gems: ResourceMap::from_code(gems),
+        gold,
+    };
+    state.coins -= &coins;
+    state.players[player].mortal += &coins;
+}
+
+/// Moves the card of `deck` that costs `cost` to face up slot `slot`, and
+/// returns it.
+pub fn put(state: &mut State, deck: Tier, slot: usize, cost: &str) -> Card {
+    let cost = ResourceMap::from_code(cost);
+    let d = &mut state.decks[deck];
+    let k = d.iter().position(|card| *card.cost() == cost).unwrap();
+    d.swap(slot, k);
+    d[slot].clone()
+}
+
+/// Buying the face up card in `slot` of `deck`, paid the default way.
+pub fn buy(deck: Tier, slot: usize) -> Action {
+    Action::Purchase {
+        deck,
+        card: slot,
+        payment: None,
+        wilds: None,
+        id: None,
+    }
+}
+
+/// Buying reserved card `index`, paid the default way.
+pub fn buy_reserved(index: usize) -> Action {
+    Action::PurchaseReserved {
+        index,
+        payment: None,
+        wilds: None,
+    }
+}
+
+/// Reserving the face up card in `slot` of `deck`.
+pub fn reserve(deck: Tier, slot: usize) -> Action {
+    Action::Reserve {
+        deck,
+        card: slot,
+        id: None,
+    }
+}
+
+/// Plays the turn of the player to move without buying anything, so no
+/// score changes.
+pub fn pass_turn(state: &mut State) {
+    let turn = state.turn;
+    while state.turn == turn && !state.is_finished() {
+        let action = state
+            .legal_actions()
+            .find(|a| {
+                !matches!(
+                    a,
+                    Action::Purchase { .. } | Action::PurchaseReserved { .. } | Action::Resign
+                )
+            })
+            .unwrap();
+        state.run(action).unwrap();
+    }
+}
@@ -0,0 +1,82 @@
+//! When the game ends and who wins it.
+
+use game_def::{EndReason, GameResult, ResourceMap, State};
+
+mod common;
+
+use common::{game, pass_turn};
+
+/// A game of three where player 1 starts.
+fn second_player_starts() -> State {
+    let mut state = game(3, 3);
+    state.first_player = 1;
+    state.turn = 1;
+    state
+}
+
+#[test]
+fn the_round_is_played_out_after_someone_reaches_the_target() {
+    let mut state = second_player_starts();
+    pass_turn(&mut state);
+    // Player 2 crosses 15 in the middle of the round.
+    state.players[2].score = 15;
+    pass_turn(&mut state);
+    assert_eq!(state.result(), GameResult::Ongoing);
+    // Player 0 still plays, since player 1 started.
+    assert_eq!(state.turn, 0);
+    pass_turn(&mut state);
+    assert_eq!(state.turn, 1);
+    assert_eq!(state.end_reason(), Some(EndReason::TargetScore));
+    assert_eq!(state.result(), GameResult::Won(2));
+}
+
+#[test]
+fn an_opponent_can_catch_up_before_the_round_closes() {
+    let mut state = second_player_starts();
+    pass_turn(&mut state);
+    state.players[1].score = 15;
+    pass_turn(&mut state);
+    assert!(!state.is_finished());
+    state.players[0].score = 17;
+    pass_turn(&mut state);
+    assert_eq!(state.result(), GameResult::Won(0));
+}
+
+#[test]
+fn the_target_comes_from_the_config() {
+    let mut state = game(4, 2);
+    state.config.target_score = 5;
+    pass_turn(&mut state);
+    state.players[1].score = 5;
+    assert!(!state.is_finished());
+    pass_turn(&mut state);
+    assert_eq!(state.result(), GameResult::Won(1));
+}
+
+/// Both players of a fresh game of two on 15 points, at the end of a round,
+/// with the cards of `first` and `second`.
+fn tied_on_points(first: &str, second: &str) -> State {
+    let mut state = game(5, 2);
+    for (player, cards) in [first, second].into_iter().enumerate() {
+        state.players[player].score = 15;
+        state.players[player].immortal = ResourceMap::from_code(cards);
+    }
+    state
+}
+
+#[test]
+fn a_tie_on_points_goes_to_fewer_cards() {
+    let state = tied_on_points("3r+2g+2u", "3r+3g+2u");
+    assert_eq!(state.winners(), [0]);
+    assert_eq!(state.result(), GameResult::Won(0));
+    let state = tied_on_points("3r+3g+2u", "5w+1k");
+    assert_eq!(state.result(), GameResult::Won(1));
+}
+
+#[test]
+fn a_tie_on_points_and_cards_is_a_draw() {
+    let state = tied_on_points("3r+3g", "2u+4k");
+    assert_eq!(state.winners(), [0, 1]);
+    assert_eq!(state.result(), GameResult::Draw(vec![0, 1]));
+    assert_eq!(state.placements(), [1, 1]);
+}
@@ -0,0 +1,70 @@
+//! What makes two states equal.
+
+use game_def::{Action, ResourceMap, State, Tier};
+
+mod common;
+
+use common::{buy, game, give, put};
+
+#[test]
+fn paying_the_default_way_explicitly_reaches_the_same_state() {
+    let mut state = game(17, 2);
+    put(&mut state, Tier::One, 0, "3u");
+    give(&mut state, 0, "3u", 1);
+    let mut explicit = state.clone();
+    state.run(buy(Tier::One, 0)).unwrap();
+    explicit
+        .run(Action::Purchase {
+            deck: Tier::One,
+            card: 0,
+            payment: Some(ResourceMap::from_code("3u")),
+            wilds: Some(0),
+            id: None,
+        })
+        .unwrap();
+    assert!(state == explicit);
+    let mut with_gold = game(17, 2);
+    put(&mut with_gold, Tier::One, 0, "3u");
+    give(&mut with_gold, 0, "3u", 1);
+    with_gold
+        .run("buy 1.0 pay=2u wilds=1".parse().unwrap())
+        .unwrap();
+    assert!(state != with_gold);
+}
+
+#[test]
+fn the_cached_hash_does_not_count() {
+    let state = game(18, 3);
+    let read: State = serde_json::from_str(&state.json()).unwrap();
+    assert!(read == state);
+    assert_eq!(read.zobrist(), state.zobrist());
+}
+
+#[test]
+fn names_count() {
+    let state = game(19, 2);
+    let mut renamed = state.clone();
+    renamed.players[1].display_name = "z".to_owned();
+    assert!(state != renamed);
+    assert!(!state.observationally_eq(&renamed));
+}
+
+#[test]
+fn observational_equality_ignores_the_order_of_face_down_cards() {
+    let state = game(20, 2);
+    let mut shuffled = state.clone();
+    let deck = &mut shuffled.decks[Tier::Two];
+    let last = deck.len() - 1;
+    deck.swap(4, last);
+    assert!(state != shuffled);
+    assert!(state.observationally_eq(&shuffled));
+    // Face up cards are seen by everyone.
+    let mut swapped = state.clone();
+    swapped.decks[Tier::Two].swap(0, 4);
+    assert!(state != swapped);
+    assert!(!state.observationally_eq(&swapped));
+    // Both have to be the same cards.
+    let mut fewer = state.clone();
+    fewer.decks[Tier::Two].pop();
+    assert!(!state.observationally_eq(&fewer));
+}
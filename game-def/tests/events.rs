@@ -0,0 +1,87 @@
+//! The events `State::run` reports.
+
+use game_def::{CoinMap, GameEvent, GameResult, Objective, ResourceKind, ResourceMap, Tier};
+
+mod common;
+
+use common::{buy, game, give, pass_turn};
+
+fn json(events: &[GameEvent]) -> serde_json::Value {
+    serde_json::to_value(events).unwrap()
+}
+
+#[test]
+fn a_purchase_paid_partly_with_gold_that_ends_the_game() {
+    let mut state = game(13, 2);
+    pass_turn(&mut state);
+    // Player 1 buys a card worth a point with two gold coins standing in
+    // for two of its coins, gets the only nobel and reaches the target.
+    let deck = &mut state.decks[Tier::One];
+    let k = deck.iter().position(|c| c.score() == 1).unwrap();
+    deck.swap(0, k);
+    let card = deck[0].clone();
+    let color = card.color().unwrap();
+    let short = ResourceKind::ALL
+        .into_iter()
+        .find(|&c| card.cost()[c] >= 2)
+        .unwrap();
+    let mut coins = card.cost().clone();
+    coins[short] -= 2;
+    give(&mut state, 1, &coins.to_code(), 2);
+    let nobel = Objective {
+        cost: ResourceMap::from_code(&format!("3{}", color.code())),
+        score: 3,
+        min_score: None,
+        ends_game: false,
+    };
+    state.nobels = vec![nobel.clone()];
+    state.players[1].immortal[color] = 2;
+    state.players[1].score = 11;
+    let events = state.run(buy(Tier::One, 0)).unwrap();
+    let expected = [
+        GameEvent::CardPurchased {
+            player: 1,
+            card,
+            payment: CoinMap {
+                gems: coins,
+                gold: 2,
+            },
+            reserved: false,
+        },
+        GameEvent::NobelVisited {
+            player: 1,
+            index: 0,
+            nobel,
+        },
+        GameEvent::GameFinished {
+            result: GameResult::Won(1),
+        },
+    ];
+    assert_eq!(json(&events), json(&expected));
+    assert_eq!(state.players[1].score, 15);
+    assert_eq!(state.players[1].mortal, CoinMap::new());
+}
+
+#[test]
+fn taking_coins_over_the_limit_and_returning_them() {
+    let mut state = game(14, 2);
+    give(&mut state, 0, "2r+2u+2g+2w", 0);
+    let take = "take r u g".parse().unwrap();
+    let events = state.run(take).unwrap();
+    let expected = [GameEvent::CoinsTaken {
+        player: 0,
+        coins: ResourceMap::from_code("1r+1u+1g"),
+    }];
+    assert_eq!(json(&events), json(&expected));
+    let discard = "discard 1r".parse().unwrap();
+    let events = state.run(discard).unwrap();
+    let expected = [GameEvent::CoinsReturned {
+        player: 0,
+        coins: CoinMap {
+            gems: ResourceMap::from_code("1r"),
+            gold: 0,
+        },
+    }];
+    assert_eq!(json(&events), json(&expected));
+    assert_eq!(state.turn, 1);
+}
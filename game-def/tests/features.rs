@@ -0,0 +1,65 @@
+//! The feature vector of a state.
+
+use game_def::{
+    features::{feature_names, FEATURE_COUNT},
+    Objective, ResourceMap, Tier,
+};
+
+mod common;
+
+use common::{game, give, put};
+
+#[test]
+fn the_length_is_fixed() {
+    assert_eq!(FEATURE_COUNT, 163);
+    let names = feature_names();
+    assert_eq!(names.len(), FEATURE_COUNT);
+    let mut unique = names.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), FEATURE_COUNT);
+    for players in 2..=4 {
+        let state = game(21, players);
+        for p in 0..players {
+            assert_eq!(state.features(p).len(), FEATURE_COUNT);
+        }
+    }
+}
+
+#[test]
+fn entries_on_a_constructed_state() {
+    let mut state = game(22, 3);
+    give(&mut state, 1, "2r+1k", 2);
+    state.players[1].immortal = ResourceMap::from_code("3g+1w");
+    state.players[1].score = 4;
+    state.nobels[0] = Objective {
+        cost: ResourceMap::from_code("4g+4w"),
+        score: 3,
+        min_score: None,
+        ends_game: false,
+    };
+    put(&mut state, Tier::One, 2, "3r");
+    let names = feature_names();
+    // Seen by player 1, who is first, then players 2 and 0.
+    let features = state.features(1);
+    let get = |name: &str| features[names.iter().position(|n| n == name).unwrap()];
+    assert_eq!(get("p0.present"), 1.0);
+    assert_eq!(get("p0.coins.r"), 2.0);
+    assert_eq!(get("p0.coins.k"), 1.0);
+    assert_eq!(get("p0.wilds"), 2.0);
+    assert_eq!(get("p0.cards.g"), 3.0);
+    assert_eq!(get("p0.score"), 4.0);
+    assert_eq!(get("p3.present"), 0.0);
+    assert_eq!(get("nobel0.distance.p0"), 4.0);
+    assert_eq!(get("nobel0.distance.p1"), 8.0);
+    assert_eq!(get("nobel4.present"), 0.0);
+    assert_eq!(get("bank.r"), 3.0);
+    assert_eq!(get("bank.wilds"), 3.0);
+    assert_eq!(get("card0.2.present"), 1.0);
+    // Two red coins and two gold ones make three red.
+    assert_eq!(get("card0.2.affordable.p0"), 1.0);
+    assert_eq!(get("card0.2.affordable.p1"), 0.0);
+    // Player 0 is to move, the last of the three seen from player 1.
+    assert_eq!(get("turn.p2"), 1.0);
+    assert_eq!(get("turn.p0"), 0.0);
+}
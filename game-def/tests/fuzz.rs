@@ -0,0 +1,50 @@
+//! The rules on random actions in random positions, see `game_def::fuzz`.
+
+use game_def::{fuzz::random_reachable_state, Action, Phase};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[test]
+fn run_keeps_the_invariants_on_random_actions() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut accepted = 0;
+    for _ in 0..300 {
+        let moves = rng.gen_range(0..120);
+        let mut state = random_reachable_state(&mut rng, moves);
+        state.validate().unwrap();
+        for _ in 0..30 {
+            if state.is_finished() {
+                break;
+            }
+            let action: Action = rng.gen();
+            let before = state.clone();
+            match state.run(action.clone()) {
+                Ok(_) => {
+                    accepted += 1;
+                    // Coins are only moved around, and nobody holds more
+                    // than they may after their turn.
+                    state
+                        .validate()
+                        .unwrap_or_else(|e| panic!("{action} in {}: {e}", before.json()));
+                }
+                Err(_) => assert!(state == before, "{action} changed the state"),
+            }
+        }
+    }
+    // Most random actions are illegal, but enough of them aren't.
+    assert!(accepted > 300, "{accepted}");
+}
+
+#[test]
+fn reachable_states_go_beyond_the_opening() {
+    let mut rng = StdRng::seed_from_u64(2);
+    let mut phases = vec![];
+    for _ in 0..100 {
+        let state = random_reachable_state(&mut rng, 80);
+        state.validate().unwrap();
+        assert!(state.players.iter().all(|p| !p.resigned));
+        // Up to 4 players, and some moves are discards in the same turn.
+        assert!(state.round >= 10 || state.is_finished(), "{}", state.round);
+        phases.push(state.phase);
+    }
+    assert!(phases.contains(&Phase::Discard));
+}
@@ -0,0 +1,98 @@
+//! `legal_actions` against what `check_action` and `run` accept, on random
+//! reachable positions, see `game_def::fuzz`.
+
+use game_def::{fuzz::random_reachable_state, Action, ResourceKind, State};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+mod common;
+
+use common::{game, give};
+
+fn sorted(mut colors: Vec<ResourceKind>) -> Vec<ResourceKind> {
+    colors.sort_by_key(|c| ResourceKind::ALL.iter().position(|x| x == c));
+    colors
+}
+
+/// `action` the way [`State::legal_actions`] lists it: paid the default way,
+/// without card ids and with the colors in order.
+fn listed_form(action: Action) -> Action {
+    match action {
+        Action::PickThree { one, two, three } => {
+            let [one, two, three] = sorted(vec![one, two, three])[..] else {
+                unreachable!()
+            };
+            Action::PickThree { one, two, three }
+        }
+        Action::PickFewer { colors } => Action::PickFewer {
+            colors: sorted(colors),
+        },
+        Action::Purchase { deck, card, .. } => Action::Purchase {
+            deck,
+            card,
+            payment: None,
+            wilds: None,
+            id: None,
+        },
+        Action::PurchaseReserved { index, .. } => Action::PurchaseReserved {
+            index,
+            payment: None,
+            wilds: None,
+        },
+        Action::Reserve { deck, card, .. } => Action::Reserve {
+            deck,
+            card,
+            id: None,
+        },
+        action => action,
+    }
+}
+
+fn states(seed: u64) -> impl Iterator<Item = State> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..300).map(move |_| {
+        let moves = rng.gen_range(0..150);
+        random_reachable_state(&mut rng, moves)
+    })
+}
+
+#[test]
+fn every_legal_action_is_accepted() {
+    for state in states(39) {
+        for action in state.legal_actions() {
+            state
+                .check_action(&action)
+                .unwrap_or_else(|e| panic!("{action} in {}: {e}", state.json()));
+            state.clone().run(action).unwrap();
+        }
+    }
+}
+
+#[test]
+fn no_accepted_action_is_missing() {
+    let mut rng = StdRng::seed_from_u64(40);
+    let mut accepted = 0;
+    for state in states(41) {
+        let listed: Vec<Action> = state.legal_actions().map(listed_form).collect();
+        for _ in 0..300 {
+            let action: Action = rng.gen();
+            if state.clone().run(action.clone()).is_ok() {
+                accepted += 1;
+                assert!(
+                    listed.contains(&listed_form(action.clone())),
+                    "{action} is accepted but not listed in {}",
+                    state.json()
+                );
+            }
+        }
+    }
+    assert!(accepted > 1000, "{accepted}");
+}
+
+#[test]
+fn taking_nothing_is_not_a_move() {
+    let mut state = game(42, 2);
+    give(&mut state, 1, "4r+4u+4g+4w+4k", 0);
+    let nothing = Action::PickFewer { colors: vec![] };
+    assert!(state.check_action(&nothing).is_err());
+    assert!(!state.legal_actions().any(|a| a == nothing));
+}
@@ -0,0 +1,86 @@
+//! Nobels visiting at the end of a turn.
+
+use game_def::{Action, GameEvent, Objective, ResourceKind, ResourceMap, State, Tier};
+
+mod common;
+
+use common::{buy, game, give};
+
+fn nobel(cost: &str) -> Objective {
+    Objective {
+        cost: ResourceMap::from_code(cost),
+        score: 3,
+        min_score: None,
+        ends_game: false,
+    }
+}
+
+/// A game where player 0, to move, has the cards of `immortal` and can buy
+/// the green card face up first in the first deck, and the nobels are
+/// those of `nobels`.
+fn before_buying_green(immortal: &str, nobels: &[&str]) -> State {
+    let mut state = game(1, 2);
+    state.nobels = nobels.iter().map(|cost| nobel(cost)).collect();
+    state.players[0].immortal = ResourceMap::from_code(immortal);
+    let deck = &mut state.decks[Tier::One];
+    let k = deck
+        .iter()
+        .position(|c| c.color() == Some(ResourceKind::Green) && c.score() == 0)
+        .unwrap();
+    deck.swap(0, k);
+    let cost = deck[0].cost_for(&state.players[0]).to_code();
+    give(&mut state, 0, &cost, 0);
+    state
+}
+
+fn visits(events: &[GameEvent]) -> Vec<usize> {
+    events
+        .iter()
+        .filter_map(|e| match e {
+            GameEvent::NobelVisited { index, .. } => Some(*index),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn a_purchase_brings_exactly_the_nobel_it_completes() {
+    let mut state = before_buying_green("3r+2g", &["4u+4w", "3r+3g", "3r+3w"]);
+    let events = state.run(buy(Tier::One, 0)).unwrap();
+    assert_eq!(visits(&events), [1]);
+    let player = &state.players[0];
+    assert_eq!((player.score, player.record.nobels), (3, 1));
+    assert_eq!(state.nobels, [nobel("4u+4w"), nobel("3r+3w")]);
+    // Only at the end of the turn, which is over.
+    assert_eq!(state.turn, 1);
+}
+
+#[test]
+fn a_purchase_short_of_every_nobel_brings_none() {
+    let mut state = before_buying_green("3r+1g", &["4u+4w", "3r+3g"]);
+    let events = state.run(buy(Tier::One, 0)).unwrap();
+    assert!(visits(&events).is_empty());
+    assert_eq!(state.players[0].score, 0);
+    assert_eq!(state.nobels.len(), 2);
+}
+
+#[test]
+fn cards_bought_on_earlier_turns_count_and_a_nobel_visits_once() {
+    let mut state = game(2, 2);
+    state.nobels = vec![nobel("3r+3g")];
+    state.players[0].immortal = ResourceMap::from_code("3r+3g");
+    let take = Action::PickThree {
+        one: ResourceKind::Red,
+        two: ResourceKind::Blue,
+        three: ResourceKind::White,
+    };
+    // Taking coins ends the turn too.
+    let events = state.run(take.clone()).unwrap();
+    assert_eq!(visits(&events), [0]);
+    assert_eq!(state.players[0].score, 3);
+    assert!(state.nobels.is_empty());
+    state.run(take.clone()).unwrap();
+    let events = state.run(take).unwrap();
+    assert!(visits(&events).is_empty());
+    assert_eq!(state.players[0].score, 3);
+}
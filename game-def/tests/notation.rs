@@ -0,0 +1,64 @@
+//! The text notation of actions.
+
+use game_def::{Action, ResourceKind};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Picking the same color twice other than by [`Action::PickTwo`] is never
+/// legal, and can't be written down.
+fn repeats_a_color(action: &Action) -> bool {
+    let colors: Vec<ResourceKind> = match action {
+        Action::PickThree { one, two, three } => vec![*one, *two, *three],
+        Action::PickFewer { colors } => colors.clone(),
+        _ => return false,
+    };
+    (1..colors.len()).any(|i| colors[..i].contains(&colors[i]))
+}
+
+#[test]
+fn every_action_reads_back_as_written() {
+    let mut rng = StdRng::seed_from_u64(15);
+    for _ in 0..10_000 {
+        let action: Action = rng.gen();
+        if repeats_a_color(&action) {
+            continue;
+        }
+        let text = action.to_string();
+        let read: Action = text
+            .parse()
+            .unwrap_or_else(|e| panic!("{text:?} doesn't read back: {e}"));
+        assert_eq!(read, action, "{text:?}");
+    }
+}
+
+/// Never legal, but written down like the rest.
+#[test]
+fn taking_no_coins_reads_back() {
+    let action = Action::PickFewer { colors: vec![] };
+    assert_eq!(action.to_string(), "take");
+    assert_eq!("take".parse::<Action>().unwrap(), action);
+}
+
+#[test]
+fn malformed_actions_are_rejected() {
+    for text in [
+        "",
+        "take u k k",
+        "take r g u w",
+        "take x",
+        "buy",
+        "buy 1",
+        "buy 4.1",
+        "buy 1.x",
+        "buy 1.1 1.2",
+        "buy 1.1 wilds=-1",
+        "buy 1.1 pay=2r pay=1r",
+        "buy-reserved",
+        "reserve 1.1 pay=2r",
+        "reserve-blind",
+        "nobel",
+        "skip now",
+        "jump",
+    ] {
+        assert!(text.parse::<Action>().is_err(), "{text:?}");
+    }
+}
@@ -0,0 +1,144 @@
+//! Paying for cards, the default way and with an explicit payment.
+
+use game_def::{Action, CoinMap, Player, ResourceKind, ResourceMap, RuleError, State, Tier};
+
+mod common;
+
+use common::{buy, game, give, put};
+
+fn holding(gems: &str, gold: usize) -> Player {
+    let mut player = Player::new("a");
+    player.mortal = CoinMap {
+        gems: ResourceMap::from_code(gems),
+        gold,
+    };
+    player
+}
+
+#[test]
+fn a_purchase_that_cant_be_afforded_changes_nothing() {
+    let cost = ResourceMap::from_code("3r+3g");
+    let mut player = holding("3r+1g", 1);
+    let mut bank = CoinMap::new();
+    assert!(!player.can_purchase(&cost));
+    let before = player.clone();
+    assert_eq!(
+        player.purchase(&cost, &mut bank),
+        Err(RuleError::NotEnoughResources {
+            missing: ResourceMap::from_code("2g"),
+            gold: 1,
+        })
+    );
+    assert_eq!(player, before);
+    assert_eq!(bank, CoinMap::new());
+}
+
+#[test]
+fn purchase_spends_what_can_purchase_counts_on() {
+    let cost = ResourceMap::from_code("3r+3g");
+    let mut player = holding("3r+1g+1u", 2);
+    let mut bank = CoinMap::new();
+    assert!(player.can_purchase(&cost));
+    let paid = player.purchase(&cost, &mut bank).unwrap();
+    let expected = CoinMap {
+        gems: ResourceMap::from_code("3r+1g"),
+        gold: 2,
+    };
+    assert_eq!(paid, expected);
+    assert_eq!(bank, expected);
+    assert_eq!(player.mortal, holding("1u", 0).mortal);
+}
+
+#[test]
+fn purchase_and_can_purchase_agree() {
+    let costs = ["3r+3g", "2w+2k+1u", "4g", "1r+1g+1u+1w+1k", "7k"];
+    let hands = ["3r+1g", "2w+2k", "4g+1r", "1r+1g+1u+1w", "5k", ""];
+    for cost in costs.map(ResourceMap::from_code) {
+        for hand in hands {
+            for gold in 0..4 {
+                let mut player = holding(hand, gold);
+                player.immortal = ResourceMap::from_code("1k");
+                let mut bank = CoinMap::new();
+                let affordable = player.can_purchase(&cost);
+                let before = player.clone();
+                match player.purchase(&cost, &mut bank) {
+                    Ok(paid) => {
+                        assert!(affordable, "{cost} with {hand} and {gold} gold");
+                        before.check_payment(&cost, &paid).unwrap();
+                        assert_eq!(bank, paid);
+                        let mut left = before.mortal.clone();
+                        left -= &paid;
+                        assert_eq!(player.mortal, left);
+                    }
+                    Err(_) => {
+                        assert!(!affordable, "{cost} with {hand} and {gold} gold");
+                        assert_eq!(player, before);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A game where player 0 holds 3 blue coins and a gold one, and a card
+/// costing 3 blue is face up first in the first deck.
+fn three_blue_for_sale() -> State {
+    let mut state = game(6, 2);
+    put(&mut state, Tier::One, 0, "3u");
+    give(&mut state, 0, "3u", 1);
+    state
+}
+
+fn pay(gems: &str, wilds: usize) -> Action {
+    Action::Purchase {
+        deck: Tier::One,
+        card: 0,
+        payment: Some(ResourceMap::from_code(gems)),
+        wilds: Some(wilds),
+        id: None,
+    }
+}
+
+#[test]
+fn gold_can_be_spent_instead_of_colored_coins() {
+    let mut state = three_blue_for_sale();
+    state.run(pay("2u", 1)).unwrap();
+    let player = &state.players[0];
+    assert_eq!(
+        (player.mortal.gems.clone(), player.mortal.gold),
+        (ResourceMap::from_code("1u"), 0)
+    );
+    assert_eq!(player.record.wilds_spent, 1);
+}
+
+#[test]
+fn a_payment_has_to_cover_the_cost_exactly() {
+    let state = three_blue_for_sale();
+    for (payment, error) in [
+        (pay("3u", 1), RuleError::WrongGold { needed: 0 }),
+        (pay("2u", 0), RuleError::WrongGold { needed: 1 }),
+        (pay("1u", 2), RuleError::PaymentNotHeld),
+        (pay("3u+1r", 0), RuleError::PaymentNotHeld),
+    ] {
+        let mut state = state.clone();
+        assert_eq!(state.run(payment).unwrap_err(), error);
+    }
+    // Cards count before coins.
+    let mut state = state.clone();
+    state.players[0].immortal = ResourceMap::from_code("1u");
+    assert_eq!(
+        state.run(pay("3u", 0)).unwrap_err(),
+        RuleError::Overpaying {
+            color: ResourceKind::Blue
+        }
+    );
+    state.run(pay("2u", 0)).unwrap();
+}
+
+#[test]
+fn without_a_payment_colored_coins_go_first() {
+    let mut state = three_blue_for_sale();
+    state.run(buy(Tier::One, 0)).unwrap();
+    assert_eq!(state.players[0].mortal.gold, 1);
+    assert_eq!(state.players[0].mortal.gems, ResourceMap::new());
+}
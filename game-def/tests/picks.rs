@@ -0,0 +1,91 @@
+//! The coin pick iterators against every pick tried on the rules.
+
+use game_def::{fuzz::random_reachable_state, Action, Phase, ResourceKind, State};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// `colors` in the order of [`ResourceKind::ALL`].
+fn sorted(mut colors: Vec<ResourceKind>) -> Vec<ResourceKind> {
+    colors.sort_by_key(|c| ResourceKind::ALL.iter().position(|x| x == c));
+    colors
+}
+
+fn accepted(state: &State, action: Action) -> bool {
+    state.clone().run(action).is_ok()
+}
+
+/// Each set of distinct colors the rules let the player to move take one
+/// coin of, and each color they let them take two of.
+fn brute_force(state: &State) -> (Vec<Vec<ResourceKind>>, Vec<ResourceKind>) {
+    let mut ones = vec![];
+    for set in 0..1 << ResourceKind::ALL.len() {
+        let colors: Vec<_> = ResourceKind::ALL
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| set >> i & 1 == 1)
+            .map(|(_, c)| c)
+            .collect();
+        let action = match colors[..] {
+            [one, two, three] => Action::PickThree { one, two, three },
+            _ => Action::PickFewer {
+                colors: colors.clone(),
+            },
+        };
+        if accepted(state, action) {
+            ones.push(colors);
+        }
+    }
+    let twos = ResourceKind::ALL
+        .into_iter()
+        .filter(|&color| accepted(state, Action::PickTwo { color }))
+        .collect();
+    (ones, twos)
+}
+
+fn listed(state: &State) -> (Vec<Vec<ResourceKind>>, Vec<ResourceKind>) {
+    let mut ones: Vec<_> = state
+        .pick_three_iter()
+        .map(|(one, two, three)| sorted(vec![one, two, three]))
+        .chain(state.pick_fewer_iter().map(sorted))
+        .collect();
+    ones.sort_by_key(|colors| {
+        (0..ResourceKind::ALL.len())
+            .filter(|&i| colors.contains(&ResourceKind::ALL[i]))
+            .map(|i| 1 << i)
+            .sum::<usize>()
+    });
+    (ones, state.pick_two_iter().collect())
+}
+
+#[test]
+fn pick_iterators_list_exactly_the_picks_run_accepts() {
+    let mut rng = StdRng::seed_from_u64(54);
+    let mut seen = [0; 4];
+    for _ in 0..300 {
+        let moves = rng.gen_range(0..100);
+        let mut state = random_reachable_state(&mut rng, moves);
+        if state.is_finished() || state.phase != Phase::Play {
+            continue;
+        }
+        for _ in 0..5 {
+            // Any bank, with colors often running out.
+            for color in ResourceKind::ALL {
+                state.coins.gems[color] = match rng.gen_bool(0.5) {
+                    true => 0,
+                    false => rng.gen_range(1..=5),
+                };
+            }
+            let (ones, twos) = listed(&state);
+            assert!(
+                (ones.clone(), twos.clone()) == brute_force(&state),
+                "{ones:?} {twos:?} in {}",
+                state.json()
+            );
+            for colors in &ones {
+                seen[colors.len()] += 1;
+            }
+            seen[0] += twos.len();
+        }
+    }
+    // Picks of one, two and three colors, and of two coins, all came up.
+    assert!(seen.iter().all(|&n| n > 10), "{seen:?}");
+}
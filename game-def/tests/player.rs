@@ -0,0 +1,67 @@
+//! The small quantities agents ask of a player.
+
+use game_def::{CoinMap, Player, ResourceMap};
+
+fn map(code: &str) -> ResourceMap {
+    ResourceMap::from_code(code)
+}
+
+fn player(cards: &str, gems: &str, gold: usize) -> Player {
+    let mut player = Player::new("a");
+    player.immortal = map(cards);
+    player.mortal = CoinMap {
+        gems: map(gems),
+        gold,
+    };
+    player
+}
+
+#[test]
+fn coin_count_includes_gold() {
+    assert_eq!(player("", "", 0).coin_count(), 0);
+    assert_eq!(player("5r", "2r+1k", 0).coin_count(), 3);
+    assert_eq!(player("", "2r+1k", 2).coin_count(), 5);
+}
+
+#[test]
+fn hand_limit() {
+    let p = player("", "3r+2u+2g", 1);
+    assert!(!p.would_exceed_hand_limit(0, 10));
+    assert!(!p.would_exceed_hand_limit(2, 10));
+    assert!(p.would_exceed_hand_limit(3, 10));
+    assert!(p.would_exceed_hand_limit(0, 7));
+}
+
+#[test]
+fn missing_for_applies_cards_then_coins() {
+    // 4r+2u+1k, less 1r+2u of cards is 3r+1k, less 1r of coins is 2r+1k.
+    let cost = map("4r+2u+1k");
+    assert_eq!(
+        player("1r+2u", "1r+3g", 0).missing_for(&cost),
+        (map("2r+1k"), 0)
+    );
+    assert_eq!(
+        player("1r+2u", "1r+3g", 1).missing_for(&cost),
+        (map("2r+1k"), 1)
+    );
+    // Gold beyond what is missing doesn't count.
+    assert_eq!(
+        player("1r+2u", "1r+3g", 5).missing_for(&cost),
+        (map("2r+1k"), 3)
+    );
+    assert_eq!(
+        player("4r+2u+1k", "", 0).missing_for(&cost),
+        (ResourceMap::new(), 0)
+    );
+}
+
+#[test]
+fn can_purchase_is_whether_gold_patches_everything() {
+    let cost = map("4r+2u+1k");
+    for gold in 0..5 {
+        let p = player("1r+2u", "1r+3g", gold);
+        let (missing, patched) = p.missing_for(&cost);
+        assert_eq!(p.can_purchase(&cost), missing.sum() as usize == patched);
+        assert_eq!(p.can_purchase(&cost), gold >= 3);
+    }
+}
@@ -0,0 +1,51 @@
+//! Random playouts.
+
+use std::time::Instant;
+
+use game_def::GameResult;
+use rand::{rngs::StdRng, SeedableRng};
+
+mod common;
+
+use common::game;
+
+#[test]
+fn the_same_rng_plays_the_same_playout() {
+    let state = game(23, 3);
+    let before = state.clone();
+    let a = state.random_playout(&mut StdRng::seed_from_u64(1), 1000);
+    let b = state.random_playout(&mut StdRng::seed_from_u64(1), 1000);
+    assert_eq!(a, b);
+    assert!(state == before);
+    assert_ne!(a.result, GameResult::Ongoing);
+    let max = *a.scores.iter().max().unwrap();
+    assert!(max >= 15, "{:?}", a.scores);
+}
+
+#[test]
+fn the_round_cap_stops_the_playout() {
+    let state = game(24, 2);
+    let mut rng = StdRng::seed_from_u64(2);
+    for cap in [0, 1, 3] {
+        let playout = state.random_playout(&mut rng, cap);
+        assert_eq!(playout.rounds, cap);
+        assert_eq!(playout.result, GameResult::Ongoing);
+    }
+}
+
+#[test]
+fn playouts_are_fast() {
+    let state = game(25, 2);
+    let mut rng = StdRng::seed_from_u64(3);
+    let start = Instant::now();
+    let mut playouts = 0;
+    while start.elapsed().as_millis() < 500 {
+        state.random_playout(&mut rng, 1000);
+        playouts += 1;
+    }
+    let per_second = playouts as f64 / start.elapsed().as_secs_f64();
+    println!("{per_second:.0} playouts per second");
+    // Over ten thousand in release builds and hundreds in debug builds, with
+    // room left for a busy machine.
+    assert!(per_second > 50.0, "{per_second:.0} playouts per second");
+}
@@ -0,0 +1,94 @@
+//! Cards a player can buy, and how.
+
+use game_def::{Action, CardSlot, CoinMap, ResourceMap, State, Tier};
+
+mod common;
+
+use common::{game, give, put};
+
+fn coins(gems: &str, gold: usize) -> CoinMap {
+    CoinMap {
+        gems: ResourceMap::from_code(gems),
+        gold,
+    }
+}
+
+/// Player 0 holds a blue coin and two gold ones and has three red cards.
+/// The first card of the first deck costs 3 blue and the second 3 red.
+fn position() -> State {
+    let mut state = game(36, 2);
+    put(&mut state, Tier::One, 0, "3u");
+    put(&mut state, Tier::One, 1, "3r");
+    give(&mut state, 0, "1u", 2);
+    state.players[0].immortal = ResourceMap::from_code("3r");
+    state
+}
+
+fn payment_of(state: &State, slot: CardSlot) -> Option<CoinMap> {
+    let cards = state.purchasable_cards(0);
+    cards.into_iter().find(|(s, _)| *s == slot).map(|(_, p)| p)
+}
+
+#[test]
+fn a_card_affordable_only_with_gold() {
+    let state = position();
+    let slot = CardSlot::Visible {
+        deck: Tier::One,
+        card: 0,
+    };
+    assert_eq!(payment_of(&state, slot), Some(coins("1u", 2)));
+    let mut poorer = state.clone();
+    poorer.players[0].mortal.gold = 1;
+    assert_eq!(payment_of(&poorer, slot), None);
+}
+
+#[test]
+fn a_card_affordable_only_with_cards() {
+    let state = position();
+    let slot = CardSlot::Visible {
+        deck: Tier::One,
+        card: 1,
+    };
+    assert_eq!(payment_of(&state, slot), Some(CoinMap::new()));
+    let mut without_cards = state.clone();
+    without_cards.players[0].immortal = ResourceMap::new();
+    assert_eq!(payment_of(&without_cards, slot), None);
+}
+
+#[test]
+fn reserved_cards_are_listed_too() {
+    let mut state = position();
+    state.run("reserve 1.0".parse().unwrap()).unwrap();
+    state.run("take r g w".parse().unwrap()).unwrap();
+    // The gold coin the reserve brought is left over.
+    assert_eq!(
+        payment_of(&state, CardSlot::Reserved { index: 0 }),
+        Some(coins("1u", 2))
+    );
+}
+
+#[test]
+fn every_card_listed_can_be_bought_with_its_payment() {
+    let state = position();
+    let listed = state.purchasable_cards(0);
+    let affordable = state
+        .card_iter()
+        .filter(|&(deck, card)| state.players[0].can_purchase(state.decks[deck][card].cost()))
+        .count();
+    assert_eq!(listed.len(), affordable);
+    for (slot, payment) in listed {
+        let CardSlot::Visible { deck, card } = slot else {
+            unreachable!()
+        };
+        let mut state = state.clone();
+        state
+            .run(Action::Purchase {
+                deck,
+                card,
+                payment: Some(payment.gems.clone()),
+                wilds: Some(payment.gold),
+                id: None,
+            })
+            .unwrap();
+    }
+}
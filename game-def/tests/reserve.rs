@@ -0,0 +1,68 @@
+//! The limit on reserved cards.
+
+use game_def::{Action, GameConfig, RuleError, State, Tier};
+use rand::{rngs::StdRng, SeedableRng};
+
+mod common;
+
+use common::{buy_reserved, game, give, reserve};
+
+/// Has the player to move reserve, and gives the turn back to them.
+fn reserve_again(state: &mut State, action: Action) -> Result<(), RuleError> {
+    let me = state.turn;
+    state.run(action)?;
+    state.turn = me;
+    Ok(())
+}
+
+#[test]
+fn the_third_reserve_is_allowed_and_the_fourth_is_not() {
+    let mut state = game(1, 2);
+    for _ in 0..3 {
+        reserve_again(&mut state, reserve(Tier::One, 0)).unwrap();
+    }
+    assert_eq!(state.players[0].reserved.len(), 3);
+    let before = state.clone();
+    for action in [
+        reserve(Tier::Two, 1),
+        Action::ReserveBlind { deck: Tier::Three },
+    ] {
+        assert_eq!(
+            state.run(action).unwrap_err(),
+            RuleError::ReserveLimit { max_reserved: 3 }
+        );
+        assert!(state == before);
+    }
+    // Move generation stops offering them too.
+    assert!(state
+        .legal_actions()
+        .all(|a| !matches!(a, Action::Reserve { .. } | Action::ReserveBlind { .. })));
+}
+
+#[test]
+fn buying_a_reserved_card_makes_room_for_another() {
+    let mut state = game(2, 2);
+    for _ in 0..3 {
+        reserve_again(&mut state, reserve(Tier::One, 0)).unwrap();
+    }
+    let cost = state.players[0].reserved[1].cost().to_code();
+    give(&mut state, 0, &cost, 0);
+    reserve_again(&mut state, buy_reserved(1)).unwrap();
+    assert_eq!(state.players[0].reserved.len(), 2);
+    reserve_again(&mut state, reserve(Tier::Two, 0)).unwrap();
+    assert_eq!(state.players[0].reserved.len(), 3);
+}
+
+#[test]
+fn the_limit_comes_from_the_config() {
+    let config = GameConfig {
+        max_reserved: 1,
+        ..GameConfig::default()
+    };
+    let mut state = State::setup(&["a", "b"], config, &mut StdRng::seed_from_u64(3));
+    reserve_again(&mut state, reserve(Tier::One, 0)).unwrap();
+    assert_eq!(
+        state.run(reserve(Tier::One, 0)).unwrap_err(),
+        RuleError::ReserveLimit { max_reserved: 1 }
+    );
+}
@@ -0,0 +1,58 @@
+//! Arithmetic on [`ResourceMap`].
+
+use game_def::{ResourceKind, ResourceMap};
+
+fn map(code: &str) -> ResourceMap {
+    ResourceMap::from_code(code)
+}
+
+#[test]
+fn adding_and_scaling() {
+    assert_eq!(map("2r+1u") + map("1u+3k"), map("2r+2u+3k"));
+    let mut m = map("1w");
+    m += map("1w+1g");
+    m += &map("2g");
+    assert_eq!(m, map("2w+3g"));
+    assert_eq!(map("2r+1u") * 3, map("6r+3u"));
+    assert_eq!(map("2r+1u+3k").sum(), 6);
+}
+
+#[test]
+fn subtracting_saturates_at_zero() {
+    assert_eq!(map("3r+2u") - map("1r+1u"), map("2r+1u"));
+    assert_eq!(map("1r") - map("3r+2g"), ResourceMap::new());
+    assert_eq!(map("2r+1u") - map("3r"), map("1u"));
+}
+
+#[test]
+fn checked_subtraction_catches_underflow() {
+    assert_eq!(map("3r+2u").checked_sub(&map("1r+2u")), Some(map("2r")));
+    assert_eq!(map("3r+2u").checked_sub(&map("1r+3u")), None);
+    assert_eq!(ResourceMap::new().checked_sub(&map("1k")), None);
+    assert_eq!(map("1k").checked_sub(&ResourceMap::new()), Some(map("1k")));
+}
+
+#[test]
+fn covering_and_what_is_missing() {
+    let hand = map("3r+1g+2w");
+    assert!(hand.covers(&map("3r+1g")));
+    assert!(hand.covers(&ResourceMap::new()));
+    assert!(!hand.covers(&map("4r")));
+    assert!(!hand.covers(&map("1u")));
+    assert_eq!(hand.deficit(&map("3r+1g")), ResourceMap::new());
+    assert_eq!(hand.deficit(&map("5r+1g+2u")), map("2r+2u"));
+    // Covering is the same as missing nothing.
+    for cost in ["4r", "1g+2w", "1u+1k", ""] {
+        let cost = map(cost);
+        assert_eq!(hand.covers(&cost), hand.deficit(&cost).sum() == 0);
+        assert_eq!(hand.covers(&cost), hand.checked_sub(&cost).is_some());
+    }
+}
+
+#[test]
+fn indexing_by_color() {
+    let mut m = map("2r");
+    m[ResourceKind::Blue] += 1;
+    assert_eq!(m, map("2r+1u"));
+    assert_eq!(m[ResourceKind::Green], 0);
+}
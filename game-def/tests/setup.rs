@@ -0,0 +1,39 @@
+//! Setting up a game.
+
+use game_def::{ResourceMap, State, Tier};
+use rand::{rngs::StdRng, SeedableRng};
+
+fn standard(seed: u64, players: usize) -> State {
+    let names = ["a", "b", "c", "d"];
+    State::standard(&names[..players], &mut StdRng::seed_from_u64(seed))
+}
+
+#[test]
+fn the_same_seed_sets_up_the_same_game() {
+    for players in 2..=4 {
+        assert!(standard(10, players) == standard(10, players));
+        assert_eq!(
+            standard(10, players).zobrist(),
+            standard(10, players).zobrist()
+        );
+        assert!(standard(10, players) != standard(11, players));
+    }
+}
+
+#[test]
+fn the_bank_and_nobels_follow_the_number_of_players() {
+    for (players, coins) in [(2, 4), (3, 5), (4, 7)] {
+        let state = standard(12, players);
+        state.validate().unwrap();
+        assert_eq!(state.players.len(), players);
+        assert_eq!(state.nobels.len(), players + 1);
+        assert_eq!(state.coins.gems, ResourceMap::uniform(coins));
+        assert_eq!(state.coins.gold, 5);
+        assert_eq!(Tier::ALL.map(|d| state.decks[d].len()), [40, 30, 20]);
+        assert_eq!((state.turn, state.first_player, state.round), (0, 0, 0));
+        for player in &state.players {
+            assert_eq!(player.coin_count(), 0);
+            assert_eq!(player.score, 0);
+        }
+    }
+}
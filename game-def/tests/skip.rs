@@ -0,0 +1,47 @@
+//! Skipping a turn, only allowed when there is nothing else to do.
+
+use game_def::{Action, RuleError, State, Tier};
+
+mod common;
+
+use common::{game, give};
+
+/// Player 0 to move with no coins in the bank to take, nothing they can
+/// buy and no room to reserve.
+fn stuck() -> State {
+    let mut state = game(37, 2);
+    give(&mut state, 1, "4r+4u+4g+4w+4k", 0);
+    for _ in 0..state.config.max_reserved {
+        let card = state.decks[Tier::Three].pop().unwrap();
+        state.players[0].reserved.push(card);
+        state.players[0].reserved_blind.push(true);
+    }
+    state
+}
+
+#[test]
+fn skip_is_refused_while_something_else_can_be_done() {
+    let mut state = game(38, 2);
+    let possible = state.legal_actions().next().unwrap();
+    assert_eq!(
+        state.clone().run(Action::Skip).unwrap_err(),
+        RuleError::SkipNotAllowed { possible }
+    );
+    // Even with nothing left to buy or take, reserving is something.
+    give(&mut state, 1, "4r+4u+4g+4w+4k", 0);
+    let Err(RuleError::SkipNotAllowed { possible }) = state.run(Action::Skip) else {
+        panic!("Skip was allowed");
+    };
+    assert!(matches!(possible, Action::Reserve { .. }), "{possible}");
+}
+
+#[test]
+fn skip_is_allowed_when_stuck() {
+    let mut state = stuck();
+    assert_eq!(
+        state.legal_actions().collect::<Vec<_>>(),
+        [Action::Skip, Action::Resign]
+    );
+    state.run(Action::Skip).unwrap();
+    assert_eq!((state.turn, state.passes), (1, 1));
+}
@@ -0,0 +1,72 @@
+//! Face up slots, refilled in place, and cards referred to by id.
+
+use game_def::{Action, RuleError, State, Tier};
+
+mod common;
+
+use common::{game, reserve};
+
+fn ids(state: &State, deck: Tier) -> Vec<usize> {
+    state.decks[deck].iter().map(|c| c.id()).collect()
+}
+
+#[test]
+fn an_emptied_slot_is_refilled_from_the_pile_in_place() {
+    let mut state = game(7, 2);
+    let before = ids(&state, Tier::Two);
+    state.run(reserve(Tier::Two, 1)).unwrap();
+    let after = ids(&state, Tier::Two);
+    assert_eq!(&after[..4], [before[0], before[4], before[2], before[3]]);
+    assert_eq!(&after[4..], &before[5..]);
+    assert_eq!(state.players[0].reserved[0].id(), before[1]);
+}
+
+#[test]
+fn without_a_pile_the_later_slots_move_down() {
+    let mut state = game(7, 2);
+    state.decks[Tier::Three].truncate(4);
+    let before = ids(&state, Tier::Three);
+    state.run(reserve(Tier::Three, 1)).unwrap();
+    assert_eq!(ids(&state, Tier::Three), [before[0], before[2], before[3]]);
+    assert_eq!(
+        state.clone().run(reserve(Tier::Three, 3)).unwrap_err(),
+        RuleError::InvalidSlot {
+            deck: Tier::Three,
+            card: 3
+        }
+    );
+}
+
+#[test]
+fn a_card_that_just_left_the_board_is_not_taken_by_mistake() {
+    let mut state = game(8, 2);
+    let taken = state.decks[Tier::One][2].id();
+    let by_id = |id| Action::Reserve {
+        deck: Tier::One,
+        card: 2,
+        id: Some(id),
+    };
+    state.run(by_id(taken)).unwrap();
+    let refill = state.decks[Tier::One][2].id();
+    assert_eq!(
+        state.clone().run(by_id(taken)).unwrap_err(),
+        RuleError::WrongCard {
+            deck: Tier::One,
+            card: 2,
+            expected: taken,
+            found: refill,
+        }
+    );
+    state.run(by_id(refill)).unwrap();
+    assert_eq!(state.players[1].reserved[0].id(), refill);
+}
+
+#[test]
+fn ids_are_unique() {
+    let state = game(9, 4);
+    let mut all: Vec<usize> = Tier::ALL.iter().flat_map(|&d| ids(&state, d)).collect();
+    let count = all.len();
+    all.sort();
+    all.dedup();
+    assert_eq!(all.len(), count);
+}
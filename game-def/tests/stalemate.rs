@@ -0,0 +1,72 @@
+//! Games where nobody can do anything but skip end in a draw.
+
+use game_def::{Action, CoinMap, EndReason, GameResult, State, Tier};
+
+mod common;
+
+use common::{game, reserve};
+
+/// A game with nothing left in the bank, where `stuck` players have no room
+/// to reserve either, so they can only skip.
+fn empty_bank(players: usize, stuck: &[usize]) -> State {
+    let mut state = game(53, players);
+    // Nobody holds a coin, so nothing can be bought.
+    state.coins = CoinMap::new();
+    for &player in stuck {
+        for _ in 0..state.config.max_reserved {
+            let card = state.decks[Tier::Three].pop().unwrap();
+            state.players[player].reserved.push(card);
+            state.players[player].reserved_blind.push(true);
+        }
+    }
+    state.rehash();
+    state
+}
+
+#[test]
+fn a_round_of_skips_ends_the_game_in_a_draw() {
+    let mut state = empty_bank(3, &[0, 1, 2]);
+    for player in 0..3 {
+        assert!(!state.is_finished());
+        assert_eq!(state.turn, player);
+        state.run(Action::Skip).unwrap();
+        assert_eq!(state.passes, player + 1);
+    }
+    assert_eq!(state.end_reason(), Some(EndReason::Stalemate));
+    assert_eq!(state.result(), GameResult::Draw(vec![0, 1, 2]));
+}
+
+#[test]
+fn a_stalemate_is_not_decided_by_points() {
+    let mut state = empty_bank(2, &[0, 1]);
+    state.players[1].score = 5;
+    state.run(Action::Skip).unwrap();
+    state.run(Action::Skip).unwrap();
+    assert_eq!(state.result(), GameResult::Draw(vec![0, 1]));
+}
+
+#[test]
+fn any_other_action_starts_the_count_again() {
+    let mut state = empty_bank(2, &[0]);
+    state.run(Action::Skip).unwrap();
+    assert_eq!(state.passes, 1);
+    // Player 1 still has room to reserve, even without gold for it.
+    state.run(reserve(Tier::One, 0)).unwrap();
+    assert_eq!(state.passes, 0);
+    state.run(Action::Skip).unwrap();
+    assert_eq!((state.passes, state.is_finished()), (1, false));
+}
+
+#[test]
+fn resigned_players_are_not_waited_for() {
+    let mut state = empty_bank(3, &[0, 1, 2]);
+    state.run(Action::Skip).unwrap();
+    // Resigning is not a skip, so the count starts again.
+    state.run(Action::Resign).unwrap();
+    assert_eq!(state.passes, 0);
+    state.run(Action::Skip).unwrap();
+    assert!(!state.is_finished());
+    state.run(Action::Skip).unwrap();
+    assert_eq!(state.end_reason(), Some(EndReason::Stalemate));
+    assert_eq!(state.result(), GameResult::Draw(vec![0, 2]));
+}
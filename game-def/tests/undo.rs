@@ -0,0 +1,86 @@
+//! `apply` then `undo` gives back the state it started from, see
+//! `game_def::fuzz`.
+
+use game_def::{fuzz::random_reachable_state, Action, Phase, State, Tier};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+mod common;
+
+use common::{buy, game, give, put};
+
+/// Applies `action` to `state`, checks it leads where `run` does, and undoes
+/// it again.
+fn apply_and_undo(state: &State, action: &Action) {
+    let mut applied = state.clone();
+    let token = applied.apply(action).unwrap();
+    let mut run = state.clone();
+    run.run(action.clone()).unwrap();
+    assert!(applied == run, "{action} in {}", state.json());
+    applied.undo(token);
+    assert!(applied == *state, "{action} in {}", state.json());
+    assert_eq!(applied.zobrist(), state.zobrist());
+}
+
+#[derive(Default, Debug)]
+struct Seen {
+    wilds_spent: usize,
+    refills: usize,
+    blind_reserves: usize,
+}
+
+#[test]
+fn undo_restores_random_reachable_states() {
+    let mut rng = StdRng::seed_from_u64(45);
+    let mut seen = Seen::default();
+    for _ in 0..400 {
+        let moves = rng.gen_range(0..150);
+        let state = random_reachable_state(&mut rng, moves);
+        let visible = state.config.visible_cards;
+        for action in state.legal_actions() {
+            apply_and_undo(&state, &action);
+            let player = &state.players[state.turn];
+            match &action {
+                Action::Purchase { deck, card, .. } => {
+                    let cost = state.decks[*deck][*card].cost();
+                    seen.wilds_spent += (player.payment_for(cost).unwrap().gold > 0) as usize;
+                    seen.refills += (state.decks[*deck].len() > visible) as usize;
+                }
+                Action::PurchaseReserved { index, .. } => {
+                    let cost = player.reserved[*index].cost();
+                    seen.wilds_spent += (player.payment_for(cost).unwrap().gold > 0) as usize;
+                }
+                Action::Reserve { deck, .. } => {
+                    seen.refills += (state.decks[*deck].len() > visible) as usize;
+                }
+                Action::ReserveBlind { .. } => seen.blind_reserves += 1,
+                _ => (),
+            }
+        }
+    }
+    assert!(seen.wilds_spent > 0, "{seen:?}");
+    assert!(seen.refills > 0, "{seen:?}");
+    assert!(seen.blind_reserves > 0, "{seen:?}");
+    // Two nobels at once are rare in random games, see `undo_a_nobel_choice`.
+}
+
+#[test]
+fn undo_a_purchase_paid_with_gold_that_empties_a_slot() {
+    let mut state = game(46, 2);
+    put(&mut state, Tier::Three, 2, "7w");
+    give(&mut state, 0, "4w", 3);
+    // No card left to refill the slot with.
+    state.decks[Tier::Three].truncate(4);
+    apply_and_undo(&state, &buy(Tier::Three, 2));
+}
+
+#[test]
+fn undo_a_nobel_choice() {
+    let mut state = game(47, 2);
+    let nobels = state.nobels.clone();
+    state.players[0].immortal = nobels[0].cost.clone() + nobels[1].cost.clone();
+    state.run("take r g u".parse().unwrap()).unwrap();
+    assert_eq!(state.phase, Phase::ChooseNobel);
+    for index in [0, 1] {
+        apply_and_undo(&state, &Action::ChooseNobel { index });
+    }
+}
@@ -0,0 +1,78 @@
+//! The hash kept up to date by `run`, `apply` and `undo` against the hash
+//! computed from scratch.
+
+use game_def::{fuzz::random_reachable_state, Action, State};
+use rand::{rngs::StdRng, seq::IteratorRandom, Rng, SeedableRng};
+
+mod common;
+
+use common::game;
+
+fn from_scratch(state: &State) -> u64 {
+    let mut state = state.clone();
+    state.rehash();
+    state.zobrist()
+}
+
+#[test]
+fn run_keeps_the_hash_up_to_date_over_a_playout() {
+    let mut rng = StdRng::seed_from_u64(48);
+    for players in 2..=4 {
+        let mut state = game(49, players);
+        while !state.is_finished() {
+            let action = state
+                .legal_actions()
+                .filter(|a| *a != Action::Resign)
+                .choose(&mut rng)
+                .unwrap();
+            state.run(action.clone()).unwrap();
+            assert_eq!(state.zobrist(), from_scratch(&state), "after {action}");
+        }
+    }
+}
+
+#[test]
+fn apply_and_undo_keep_the_hash_up_to_date() {
+    let mut rng = StdRng::seed_from_u64(50);
+    for _ in 0..300 {
+        let moves = rng.gen_range(0..150);
+        let state = random_reachable_state(&mut rng, moves);
+        for action in state.legal_actions() {
+            let mut applied = state.clone();
+            let token = applied.apply(&action).unwrap();
+            assert_eq!(applied.zobrist(), from_scratch(&applied), "{action}");
+            applied.undo(token);
+            assert_eq!(applied.zobrist(), state.zobrist(), "{action}");
+        }
+    }
+}
+
+#[test]
+fn what_decides_the_end_of_the_game_is_hashed() {
+    let state = game(51, 3);
+    let mut passed = state.clone();
+    passed.passes = 1;
+    assert_ne!(from_scratch(&passed), from_scratch(&state));
+    let mut started_later = state.clone();
+    started_later.first_player = 1;
+    assert_ne!(from_scratch(&started_later), from_scratch(&state));
+    // The round only counts with a round limit.
+    let mut later = state.clone();
+    later.round = 5;
+    assert_eq!(from_scratch(&later), from_scratch(&state));
+    let mut limited = state.clone();
+    limited.config.max_rounds = Some(10);
+    let mut limited_later = limited.clone();
+    limited_later.round = 5;
+    assert_ne!(from_scratch(&limited_later), from_scratch(&limited));
+}
+
+#[test]
+fn the_order_of_the_draw_pile_is_not_hashed() {
+    let state = game(52, 2);
+    let mut shuffled = state.clone();
+    let pile = &mut shuffled.decks[game_def::Tier::One];
+    let last = pile.len() - 1;
+    pile.swap(4, last);
+    assert_eq!(from_scratch(&shuffled), from_scratch(&state));
+}
@@ -0,0 +1,203 @@
+//! A greedy agent that looks one move ahead, the agent of the greedy-agent
+//! program: stronger than the random agent and quick enough for playing
+//! games by the thousand, see [`best_action`].
+
+use game_def::{Action, Agent, AgentHandler, Card, Observation, Player, ResourceMap, State};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Points a card needs for a reserve to be worth a turn.
+const HIGH_VALUE: u8 = 3;
+/// What sets the kinds of moves of [`score`] apart, more than any move
+/// scores within its kind.
+const BAND: i64 = 1_000_000_000;
+
+/// The move of the player to move that scores best by [`score`], the first
+/// listed of those that tie, so the same state always gets the same move.
+///
+/// ```
+/// use game_def::{Action, GameConfig, State, Tier};
+///
+/// let mut state = State::setup(&["a", "b"], GameConfig::default(), &mut rand::thread_rng());
+/// // Whatever the first card of tier 1 costs, its cards pay for it.
+/// let cost = state.decks[Tier::One][0].cost().clone();
+/// state.players[state.turn].immortal = cost;
+/// let buy = greedy_agent::best_action(&state);
+/// assert!(matches!(buy, Action::Purchase { .. }), "{buy}");
+/// ```
+pub fn best_action(state: &State) -> Action {
+    let target = target(state);
+    let mut best: Option<(i64, Action)> = None;
+    for ac in state.legal_actions() {
+        if ac == Action::Resign {
+            continue;
+        }
+        let score = score(state, &ac, target);
+        if best.as_ref().is_none_or(|(best, _)| score > *best) {
+            best = Some((score, ac));
+        }
+    }
+    best.map_or(Action::Skip, |(_, ac)| ac)
+}
+
+/// How good `ac` looks, the higher the better. In order: a purchase that
+/// reaches the target score, a reserve that stops another player reaching
+/// it, the other purchases by the points and nobel progress they bring for
+/// each coin paid, coin picks by how much closer they bring `target`,
+/// reserves of cards worth at least [`HIGH_VALUE`] points that another
+/// player can buy or that the player to move can't, and then anything else.
+fn score(state: &State, ac: &Action, target: Option<&Card>) -> i64 {
+    let me = state.turn;
+    let player = &state.players[me];
+    let goal = state.config.target_score;
+    let others_can_buy = |card: &Card| {
+        (0..state.players.len())
+            .filter(|&i| i != me)
+            .map(|i| &state.players[i])
+            .filter(|p| p.payment_for(card.cost()).is_some())
+            .map(|p| p.score.saturating_add(card.score()))
+            .max()
+    };
+    match ac {
+        Action::Purchase { deck, card, .. } => {
+            purchase(state, player, &state.decks[*deck][*card], goal)
+        }
+        Action::PurchaseReserved { index, .. } => {
+            purchase(state, player, &player.reserved[*index], goal)
+        }
+        Action::Reserve { deck, card, .. } => {
+            let card = &state.decks[*deck][*card];
+            let points = i64::from(card.score());
+            match others_can_buy(card) {
+                Some(score) if score >= goal => 5 * BAND + points,
+                Some(_) if card.score() >= HIGH_VALUE => 2 * BAND + points,
+                None if card.score() >= HIGH_VALUE && player.payment_for(card.cost()).is_none() => {
+                    2 * BAND + points
+                }
+                _ => 0,
+            }
+        }
+        Action::PickThree { one, two, three } => pick(
+            player,
+            ResourceMap::from([(*one, 1), (*two, 1), (*three, 1)]),
+            target,
+        ),
+        Action::PickTwo { color } => pick(player, ResourceMap::from([(*color, 2)]), target),
+        Action::PickFewer { colors } => {
+            pick(player, colors.iter().map(|c| (*c, 1)).collect(), target)
+        }
+        Action::Discard { coins, wilds } => {
+            // Gold pays for anything, and the coins `target` needs come next.
+            let needed = target.map_or(0, |card| {
+                let need = player.mortal.gems.deficit(&card.cost_for(player));
+                let kept = player.mortal.gems.clone() - coins.clone();
+                let short = kept.deficit(&card.cost_for(player));
+                short.sum() - need.sum()
+            });
+            BAND - 100 * *wilds as i64 - 10 * i64::from(needed) - i64::from(coins.sum())
+        }
+        Action::ChooseNobel { index } => BAND + i64::from(state.nobels[*index].score),
+        _ => 0,
+    }
+}
+
+/// Points and nobel progress for each coin paid, with a purchase that
+/// reaches `goal` above every other move.
+fn purchase(state: &State, player: &Player, card: &Card, goal: u8) -> i64 {
+    let nobels = state
+        .nobels
+        .iter()
+        .filter(|n| {
+            card.color()
+                .is_some_and(|c| player.immortal.deficit(&n.cost)[c] > 0)
+        })
+        .count() as i64;
+    let paid = i64::from(card.cost_for(player).sum());
+    let won = player.score.saturating_add(card.score()) >= goal;
+    let value = (i64::from(card.score()) * 10 + nobels * 3 + 1) * 1000 / (paid + 1);
+    match won {
+        true => 6 * BAND + value,
+        false => 4 * BAND + value,
+    }
+}
+
+/// Coins toward `target` first, then the most coins.
+fn pick(player: &Player, picked: ResourceMap, target: Option<&Card>) -> i64 {
+    let closer = target.map_or(0, |card| {
+        let need = player.mortal.gems.deficit(&card.cost_for(player));
+        picked.iter().map(|(c, n)| (*n).min(need[c])).sum::<usize>()
+    });
+    let band = if closer > 0 { 3 } else { 1 };
+    band * BAND + closer as i64 * 100 + i64::from(picked.sum())
+}
+
+/// The card the player to move is saving for: of those face up or in its
+/// reserve that it can't buy yet, the one with the most points and nobel
+/// progress for each coin it is still short of.
+fn target(state: &State) -> Option<&Card> {
+    let player = &state.players[state.turn];
+    let visible = state
+        .card_iter()
+        .map(|(deck, card)| &state.decks[deck][card]);
+    let mut best: Option<(i64, &Card)> = None;
+    for card in visible.chain(&player.reserved) {
+        if player.payment_for(card.cost()).is_some() {
+            continue;
+        }
+        let nobels = state
+            .nobels
+            .iter()
+            .filter(|n| {
+                card.color()
+                    .is_some_and(|c| player.immortal.deficit(&n.cost)[c] > 0)
+            })
+            .count() as i64;
+        let short = card.coins_short(player) as i64;
+        let value = (i64::from(card.score()) * 10 + nobels * 3 + 1) * 1000 / (short + 1);
+        if best.is_none_or(|(best, _)| value > best) {
+            best = Some((value, card));
+        }
+    }
+    best.map(|(_, card)| card)
+}
+
+/// [`best_action`], or with a chance of `epsilon` any move but resigning,
+/// for games that don't all go the same way. The same `seed` makes the same
+/// choices.
+pub struct Greedy {
+    pub epsilon: f64,
+    rng: StdRng,
+}
+
+impl Greedy {
+    pub fn new(epsilon: f64, seed: u64) -> Greedy {
+        Greedy {
+            epsilon,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    fn play(&mut self, state: &State) -> Action {
+        if self.epsilon > 0.0 && self.rng.gen_bool(self.epsilon.min(1.0)) {
+            let moves: Vec<_> = state
+                .legal_actions()
+                .filter(|ac| *ac != Action::Resign)
+                .collect();
+            if !moves.is_empty() {
+                return moves[self.rng.gen_range(0..moves.len())].clone();
+            }
+        }
+        best_action(state)
+    }
+}
+
+impl Agent for Greedy {
+    fn act(&mut self, state: &State) -> Action {
+        self.play(state)
+    }
+}
+
+impl AgentHandler for Greedy {
+    fn act(&mut self, observation: Observation) -> anyhow::Result<Action> {
+        Ok(self.play(&observation.state))
+    }
+}
@@ -0,0 +1,41 @@
+//! Usage: `greedy-agent [--epsilon <rate>] [--seed <n>]`
+//!
+//! Plays the move that looks best one move ahead, see
+//! [`best_action`](greedy_agent::best_action), or
+//! with a chance of `--epsilon`, 0 by default, any move at random, picked
+//! with `--seed`. `--format` and `--protocol`, which the runner adds, are
+//! for [`run_agent`].
+
+use anyhow::Context;
+use game_def::run_agent;
+use greedy_agent::Greedy;
+
+const USAGE: &str = "usage: greedy-agent [--epsilon <rate>] [--seed <n>]";
+
+fn main() -> anyhow::Result<()> {
+    let mut epsilon = 0.0;
+    let mut seed = 0;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--epsilon" => {
+                epsilon = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .filter(|rate| (0.0..=1.0).contains(rate))
+                    .context("--epsilon needs a rate between 0 and 1")?
+            }
+            "--seed" => {
+                seed = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .context("--seed needs a number")?
+            }
+            "--format" | "--protocol" => {
+                args.next();
+            }
+            _ => anyhow::bail!("Unknown argument {arg}, {USAGE}"),
+        }
+    }
+    run_agent(Greedy::new(epsilon, seed))
+}
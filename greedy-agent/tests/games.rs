@@ -0,0 +1,43 @@
+//! Whole games against the random agent, played in process.
+
+use game_def::{play_game, record_game, Agent, GameConfig, GameResult, RandomAgent, State};
+use greedy_agent::Greedy;
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn beats_the_random_agent() {
+    const GAMES: u64 = 200;
+    let mut won = 0;
+    for game in 0..GAMES {
+        let state = State::setup(
+            &["a", "b"],
+            GameConfig::default(),
+            &mut StdRng::seed_from_u64(game),
+        );
+        // Taking turns at moving first.
+        let seat = (game % 2) as usize;
+        let mut agents: Vec<Box<dyn Agent>> = vec![Box::new(RandomAgent), Box::new(RandomAgent)];
+        agents[seat] = Box::new(Greedy::new(0.0, game));
+        if play_game(state, &mut agents, 100) == GameResult::Won(seat) {
+            won += 1;
+        }
+    }
+    println!("Won {won} of {GAMES}");
+    assert!(won * 10 >= GAMES * 9, "won {won} of {GAMES}");
+}
+
+#[test]
+fn random_moves_come_from_the_seed() {
+    let state = State::setup(
+        &["a", "b"],
+        GameConfig::default(),
+        &mut StdRng::seed_from_u64(1),
+    );
+    let play = |epsilon, seed| {
+        let mut agents: Vec<Box<dyn Agent>> =
+            vec![Box::new(Greedy::new(epsilon, seed)), Box::new(RandomAgent)];
+        record_game(state.clone(), &mut agents, 100).actions
+    };
+    assert_eq!(play(0.3, 5), play(0.3, 5));
+    assert_ne!(play(0.3, 5), play(0.0, 5));
+}
@@ -0,0 +1,417 @@
+//! Monte-Carlo tree search with UCT, the agent of the mcts-agent program and
+//! a baseline of another kind than alpha-beta.
+//!
+//! Each iteration walks down the tree from the position to move in, picking
+//! the child with the best UCB1 bound for whoever moves there, adds one move
+//! not tried yet, plays the game out at random from it with
+//! [`State::random_playout`] and adds the result to every node on the way.
+//! Results are kept for every player, so it works for any number of them:
+//! a win counts 1, a draw the share of it and a loss nothing. It plays the
+//! move it tried most.
+//!
+//! Like the alpha-beta search, it searches one determinized state, the face
+//! down cards made up with a fixed seed. With `reuse`, the part of the tree
+//! below the position it is asked about next is kept, when it finds it there.
+
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use game_def::{Action, AgentHandler, GameResult, Observation, State};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+pub const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(1);
+pub const DEFAULT_ROLLOUT_ROUNDS: usize = 10;
+/// Below the √2 of the books, which tries too many moves for the few
+/// playouts a move gets here.
+pub const DEFAULT_EXPLORATION: f64 = 0.5;
+/// Answers by then whatever the budget, like the alpha-beta search.
+const MAX_THINK_TIME: Duration = Duration::from_secs(20);
+
+/// Searches for `time_limit`, or for `iterations` playouts if given, which
+/// plays the same every time for the same `seed`. `exploration` is the
+/// constant of UCB1, more of it tries the moves that look worse more often.
+/// Playouts stop after `rollout_rounds` rounds, the players with the best
+/// score then counting as winning. With `reuse`, what was found below the
+/// next position is kept for it, see [`MctsAgent`].
+#[derive(Debug, Clone, Copy)]
+pub struct Mcts {
+    pub iterations: Option<usize>,
+    pub time_limit: Duration,
+    pub exploration: f64,
+    pub rollout_rounds: usize,
+    pub seed: u64,
+    pub reuse: bool,
+}
+
+impl Default for Mcts {
+    fn default() -> Self {
+        Mcts {
+            iterations: None,
+            time_limit: DEFAULT_TIME_LIMIT,
+            exploration: DEFAULT_EXPLORATION,
+            rollout_rounds: DEFAULT_ROLLOUT_ROUNDS,
+            seed: 0,
+            reuse: false,
+        }
+    }
+}
+
+/// What [`Mcts::think`] found.
+#[derive(Debug, Clone)]
+pub struct Thought {
+    pub action: Action,
+    /// Playouts of this move.
+    pub iterations: usize,
+    pub elapsed: Duration,
+    /// How often `action` was tried, at this move and before.
+    pub visits: u32,
+    /// The share of the playouts through `action` won by the player to move.
+    pub value: f64,
+    /// Playouts kept from the moves before.
+    pub reused: u32,
+}
+
+/// The line logged for each move, like `2000 iterations in 1.0s, playing
+/// take r g u, tried 812 times, 57% won, 300 reused`.
+impl Display for Thought {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} iterations in {:.1?}, playing {}, tried {} times, {:.0}% won",
+            self.iterations,
+            self.elapsed,
+            self.action,
+            self.visits,
+            self.value * 100.0
+        )?;
+        if self.reused > 0 {
+            write!(f, ", {} reused", self.reused)?;
+        }
+        Ok(())
+    }
+}
+
+/// A move at the root of a [`Tree`], like `take r g u, tried 812 times,
+/// 57% won`.
+#[derive(Debug, Clone)]
+pub struct RootMove {
+    pub action: Action,
+    pub visits: u32,
+    /// The share of the playouts through it won by the player to move.
+    pub value: f64,
+}
+
+impl Display for RootMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}, tried {} times, {:.0}% won",
+            self.action,
+            self.visits,
+            self.value * 100.0
+        )
+    }
+}
+
+/// The moves searched from one determinized position.
+pub struct Tree {
+    /// The position at the root, which every iteration starts from.
+    state: State,
+    /// The root first.
+    nodes: Vec<Node>,
+}
+
+struct Node {
+    /// The move from the parent here, [`Action::Skip`] at the root.
+    action: Action,
+    /// Who played `action`, whose results its parent picks it by.
+    mover: usize,
+    children: Vec<usize>,
+    /// Moves without a child yet, `None` until the node is first left.
+    untried: Option<Vec<Action>>,
+    visits: u32,
+    /// What the playouts through here were worth to each player.
+    results: Vec<f64>,
+}
+
+impl Node {
+    fn new(action: Action, mover: usize, players: usize) -> Node {
+        Node {
+            action,
+            mover,
+            children: vec![],
+            untried: None,
+            visits: 0,
+            results: vec![0.0; players],
+        }
+    }
+}
+
+impl Tree {
+    /// The moves tried at the root, the most tried first, and of those
+    /// tried as often the one [`Mcts::think`] plays.
+    pub fn root_moves(&self) -> Vec<RootMove> {
+        let mut moves: Vec<_> = self.nodes[0]
+            .children
+            .iter()
+            .rev()
+            .map(|&child| {
+                let node = &self.nodes[child];
+                RootMove {
+                    action: node.action.clone(),
+                    visits: node.visits,
+                    value: node.results[node.mover] / f64::from(node.visits),
+                }
+            })
+            .collect();
+        moves.sort_by_key(|m| std::cmp::Reverse(m.visits));
+        moves
+    }
+
+    /// The moves it expects next: the most tried at each node from the root
+    /// down, as long as there is one.
+    pub fn principal_variation(&self) -> Vec<Action> {
+        let mut pv = vec![];
+        let mut node = 0;
+        while let Some(&child) = self.nodes[node]
+            .children
+            .iter()
+            .max_by_key(|&&child| self.nodes[child].visits)
+        {
+            pv.push(self.nodes[child].action.clone());
+            node = child;
+        }
+        pv
+    }
+
+    fn new(state: State) -> Tree {
+        let root = Node::new(Action::Skip, state.turn, state.players.len());
+        Tree {
+            state,
+            nodes: vec![root],
+        }
+    }
+
+    /// The tree below the position `observation` shows, if it was reached
+    /// within a round of the root, with the same face down cards as before.
+    fn descend(self, observation: &Observation) -> Option<Tree> {
+        let me = observation.state.turn;
+        let wanted = observation.state.zobrist();
+        let plies = 2 * self.state.players.len();
+        let mut stack = vec![(0, self.state.clone(), 0)];
+        while let Some((node, state, depth)) = stack.pop() {
+            if node != 0 && state.observation_for(me).state.zobrist() == wanted {
+                return Some(self.subtree(node, state));
+            }
+            if depth == plies {
+                continue;
+            }
+            for &child in &self.nodes[node].children {
+                let mut state = state.clone();
+                state
+                    .apply(&self.nodes[child].action)
+                    .expect("the tree holds an illegal action");
+                stack.push((child, state, depth + 1));
+            }
+        }
+        None
+    }
+
+    /// The nodes below `root`, whose position is `state`, as a tree of their own.
+    fn subtree(&self, root: usize, state: State) -> Tree {
+        let mut tree = Tree {
+            state,
+            nodes: vec![],
+        };
+        let mut queue = VecDeque::from([(root, None::<usize>)]);
+        while let Some((old, parent)) = queue.pop_front() {
+            let node = &self.nodes[old];
+            let new = tree.nodes.len();
+            tree.nodes.push(Node {
+                action: node.action.clone(),
+                mover: node.mover,
+                children: vec![],
+                untried: node.untried.clone(),
+                visits: node.visits,
+                results: node.results.clone(),
+            });
+            if let Some(parent) = parent {
+                tree.nodes[parent].children.push(new);
+            }
+            queue.extend(node.children.iter().map(|&child| (child, Some(new))));
+        }
+        tree.nodes[0].action = Action::Skip;
+        tree
+    }
+
+    /// One playout: down the tree by UCB1, one new move, a random game from
+    /// there and its results back up.
+    fn iterate(&mut self, mcts: &Mcts, rng: &mut StdRng) {
+        let mut state = self.state.clone();
+        let mut path = vec![0];
+        let mut node = 0;
+        while !state.is_finished() {
+            let untried = self.nodes[node]
+                .untried
+                .get_or_insert_with(|| moves(&state));
+            if !untried.is_empty() {
+                let action = untried.swap_remove(rng.gen_range(0..untried.len()));
+                let mover = state.turn;
+                state
+                    .apply(&action)
+                    .expect("legal_actions yielded an illegal action");
+                let child = self.nodes.len();
+                self.nodes
+                    .push(Node::new(action, mover, state.players.len()));
+                self.nodes[node].children.push(child);
+                path.push(child);
+                break;
+            }
+            let Some(child) = self.select(node, mcts.exploration) else {
+                break;
+            };
+            state
+                .apply(&self.nodes[child].action)
+                .expect("the tree holds an illegal action");
+            path.push(child);
+            node = child;
+        }
+        let results = playout_results(&state, rng, mcts.rollout_rounds);
+        for node in path {
+            let node = &mut self.nodes[node];
+            node.visits += 1;
+            for (sum, result) in node.results.iter_mut().zip(&results) {
+                *sum += result;
+            }
+        }
+    }
+
+    /// The child of `node` with the best UCB1 bound for whoever moves there.
+    fn select(&self, node: usize, exploration: f64) -> Option<usize> {
+        let parent = &self.nodes[node];
+        let log = f64::from(parent.visits).ln();
+        let bound = |&child: &usize| {
+            let child = &self.nodes[child];
+            let visits = f64::from(child.visits);
+            child.results[child.mover] / visits + exploration * (log / visits).sqrt()
+        };
+        parent
+            .children
+            .iter()
+            .copied()
+            .max_by(|a, b| bound(a).total_cmp(&bound(b)))
+    }
+}
+
+/// Every legal move but resigning.
+fn moves(state: &State) -> Vec<Action> {
+    state
+        .legal_actions()
+        .filter(|action| *action != Action::Resign)
+        .collect()
+}
+
+/// What a random game from `state` is worth to each player: 1 to the
+/// winner, a share of 1 to each of those who draw, and if the game goes on
+/// past `rounds`, a share to each of the players with the best score.
+fn playout_results(state: &State, rng: &mut StdRng, rounds: usize) -> Vec<f64> {
+    let playout = state.random_playout(rng, rounds);
+    let winners = match playout.result {
+        GameResult::Won(winner) => vec![winner],
+        GameResult::Draw(winners) => winners,
+        GameResult::Ongoing => {
+            let best = playout.scores.iter().max().copied().unwrap_or(0);
+            (0..playout.scores.len())
+                .filter(|&i| playout.scores[i] == best)
+                .collect()
+        }
+    };
+    let mut results = vec![0.0; state.players.len()];
+    for &winner in &winners {
+        results[winner] = 1.0 / winners.len() as f64;
+    }
+    results
+}
+
+impl Mcts {
+    /// Searches for the best move in `observation`, going on with
+    /// `previous` if it was searched before and the position is in it.
+    /// Returns the tree too, for the next move.
+    pub fn think(&self, observation: Observation, previous: Option<Tree>) -> (Thought, Tree) {
+        let started = Instant::now();
+        let mut tree = previous
+            .and_then(|tree| tree.descend(&observation))
+            .unwrap_or_else(|| {
+                let mut rng = StdRng::seed_from_u64(self.seed);
+                Tree::new(observation.to_determinized_state(&mut rng))
+            });
+        let reused = tree.nodes[0].visits;
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let time_limit = match self.iterations {
+            Some(_) => MAX_THINK_TIME,
+            None => self.time_limit.min(MAX_THINK_TIME),
+        };
+        let mut iterations = 0;
+        while self.iterations.is_none_or(|n| iterations < n) && started.elapsed() < time_limit {
+            tree.iterate(self, &mut rng);
+            iterations += 1;
+        }
+        let root = &tree.nodes[0];
+        let best = root
+            .children
+            .iter()
+            .map(|&child| &tree.nodes[child])
+            .max_by_key(|child| child.visits);
+        let thought = match best {
+            Some(best) => Thought {
+                action: best.action.clone(),
+                iterations,
+                elapsed: started.elapsed(),
+                visits: best.visits,
+                value: best.results[best.mover] / f64::from(best.visits),
+                reused,
+            },
+            None => Thought {
+                action: moves(&tree.state).pop().unwrap_or(Action::Skip),
+                iterations,
+                elapsed: started.elapsed(),
+                visits: 0,
+                value: 0.0,
+                reused,
+            },
+        };
+        (thought, tree)
+    }
+}
+
+/// [`Mcts`] playing a game, keeping its tree from one move to the next
+/// when it reuses it.
+pub struct MctsAgent {
+    pub mcts: Mcts,
+    tree: Option<Tree>,
+}
+
+impl MctsAgent {
+    pub fn new(mcts: Mcts) -> MctsAgent {
+        MctsAgent { mcts, tree: None }
+    }
+
+    pub fn think(&mut self, observation: Observation) -> Thought {
+        let (thought, tree) = self.mcts.think(observation, self.tree.take());
+        if self.mcts.reuse {
+            self.tree = Some(tree);
+        }
+        thought
+    }
+}
+
+impl AgentHandler for MctsAgent {
+    fn act(&mut self, observation: Observation) -> anyhow::Result<Action> {
+        let thought = self.think(observation);
+        eprintln!("mcts: {thought}");
+        Ok(thought.action)
+    }
+}
@@ -0,0 +1,124 @@
+//! Usage: `mcts-agent [--iterations <n>] [--time-limit <seconds>]
+//! [--time-ms <milliseconds>] [--exploration <c>] [--rollout-rounds <n>]
+//! [--seed <n>] [--reuse] [--analyze <file> [--top <n>]]`
+//!
+//! Searches each move for `--time-limit` seconds, or `--time-ms`
+//! milliseconds, 1 second by default, or for `--iterations` playouts
+//! whatever they take. `--exploration` is the constant of UCB1, 0.5 by
+//! default, and `--rollout-rounds` the most rounds a playout goes on for, 10
+//! by default. `--seed` picks the cards it imagines where it can't see
+//! them, and the moves of its playouts. With `--reuse` it keeps what it
+//! found below the position it is asked about next. For each move it logs
+//! the playouts it ran and how the move it plays did in them to stderr.
+//!
+//! `--analyze` searches the one position of a JSON file, a state or an
+//! observation, see [`Observation::load`], prints what it found and exits:
+//! the move it plays, how it did, the moves tried most after it, the
+//! `--top` moves at the root, 5 by default, and the playouts it ran.
+//!
+//! Bad arguments fail before the first state is read. `--format` and
+//! `--protocol`, which the runner adds, are for [`run_agent`].
+
+use std::time::Duration;
+
+use anyhow::Context;
+use game_def::{run_agent, Analysis, Observation};
+use mcts_agent::{Mcts, MctsAgent, Thought, Tree};
+
+const USAGE: &str = "usage: mcts-agent [--iterations <n>] [--time-limit <seconds>] [--time-ms <milliseconds>] [--exploration <c>] [--rollout-rounds <n>] [--seed <n>] [--reuse] [--analyze <file> [--top <n>]]";
+
+fn main() -> anyhow::Result<()> {
+    let mut mcts = Mcts::default();
+    let mut analyze = None;
+    let mut top = 5;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--iterations" => {
+                mcts.iterations = Some(
+                    args.next()
+                        .and_then(|x| x.parse().ok())
+                        .filter(|&n| n > 0)
+                        .context("--iterations needs a positive number")?,
+                )
+            }
+            "--time-limit" => {
+                mcts.time_limit = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .and_then(|x| Duration::try_from_secs_f64(x).ok())
+                    .context("--time-limit needs a positive number of seconds")?
+            }
+            "--time-ms" => {
+                mcts.time_limit = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .map(Duration::from_millis)
+                    .context("--time-ms needs a number of milliseconds")?
+            }
+            "--exploration" => {
+                mcts.exploration = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .filter(|c: &f64| c.is_finite() && *c >= 0.0)
+                    .context("--exploration needs a number of at least 0")?
+            }
+            "--rollout-rounds" => {
+                mcts.rollout_rounds = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .context("--rollout-rounds needs a number of rounds")?
+            }
+            "--seed" => {
+                mcts.seed = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .context("--seed needs a number")?
+            }
+            "--reuse" => mcts.reuse = true,
+            "--analyze" => analyze = Some(args.next().context("--analyze needs a file")?),
+            "--top" => {
+                top = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .context("--top needs a number of moves")?
+            }
+            "--format" | "--protocol" => {
+                args.next();
+            }
+            _ => anyhow::bail!("Unknown argument {arg}, {USAGE}"),
+        }
+    }
+    if let Some(path) = analyze {
+        let observation = Observation::load(&path)?;
+        let (thought, tree) = mcts.think(observation.clone(), None);
+        print!("{}", report(&observation, &thought, &tree, top));
+        return Ok(());
+    }
+    run_agent(MctsAgent::new(mcts))
+}
+
+/// What `--analyze` prints about the search of `observation`.
+fn report(observation: &Observation, thought: &Thought, tree: &Tree, top: usize) -> String {
+    Analysis {
+        observation,
+        best: &thought.action,
+        pv: &tree.principal_variation(),
+        score: format!(
+            "tried {} times, {:.0}% won",
+            thought.visits,
+            thought.value * 100.0
+        ),
+        root: tree
+            .root_moves()
+            .iter()
+            .map(|root| root.to_string())
+            .collect(),
+        top,
+        searched: format!(
+            "{} iterations in {:.1?}",
+            thought.iterations, thought.elapsed
+        ),
+    }
+    .to_string()
+}
@@ -0,0 +1,49 @@
+//! The mcts-agent program, started from the command line.
+
+use std::process::{Command, Output};
+
+use game_def::{Action, State};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mcts-agent"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn analyze_reports_on_a_saved_position() {
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../game-def/fixtures/positions/midgame.json"
+    );
+    let state: State = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    let output = run(&["--iterations", "300", "--analyze", path, "--top", "4"]);
+    assert!(output.status.success(), "{output:?}");
+    let report = String::from_utf8(output.stdout).unwrap();
+    let line = |label: &str| {
+        let line = report.lines().find(|l| l.starts_with(label));
+        line.unwrap_or_else(|| panic!("{report}"))[label.len()..].to_owned()
+    };
+    assert_eq!(line("Position: "), "round 13, alice to move");
+    let best: Action = line("Best move: ").parse().unwrap();
+    assert!(state.legal_actions().any(|legal| legal == best), "{report}");
+    let pv = line("Principal variation: ");
+    let pv: Vec<Action> = pv.split(" / ").map(|ac| ac.parse().unwrap()).collect();
+    assert_eq!(pv[0], best, "{report}");
+    let top: Vec<_> = report.lines().filter(|l| l.starts_with("  ")).collect();
+    assert_eq!(top.len(), 4, "{report}");
+    assert!(
+        top[0].starts_with(&format!("  1. {best}, tried ")),
+        "{report}"
+    );
+    assert!(
+        line("Searched: ").starts_with("300 iterations in "),
+        "{report}"
+    );
+
+    let output = run(&["--analyze"]);
+    assert!(!output.status.success());
+    let error = String::from_utf8(output.stderr).unwrap();
+    assert!(error.contains("--analyze needs a file"), "{error}");
+}
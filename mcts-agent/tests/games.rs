@@ -0,0 +1,33 @@
+//! Whole games against the random agent, played in process.
+
+use game_def::{play_game, Agent, GameConfig, GameResult, Handled, RandomAgent, State};
+use mcts_agent::{Mcts, MctsAgent};
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn beats_the_random_agent() {
+    const GAMES: u64 = 50;
+    let mut won = 0;
+    for game in 0..GAMES {
+        let state = State::setup(
+            &["a", "b"],
+            GameConfig::default(),
+            &mut StdRng::seed_from_u64(game),
+        );
+        // Taking turns at moving first.
+        let seat = (game % 2) as usize;
+        let mcts = MctsAgent::new(Mcts {
+            iterations: Some(80),
+            seed: game,
+            reuse: true,
+            ..Mcts::default()
+        });
+        let mut agents: Vec<Box<dyn Agent>> = vec![Box::new(RandomAgent), Box::new(RandomAgent)];
+        agents[seat] = Box::new(Handled(mcts));
+        if play_game(state, &mut agents, 100) == GameResult::Won(seat) {
+            won += 1;
+        }
+    }
+    println!("Won {won} of {GAMES}");
+    assert!(won * 10 >= GAMES * 9, "won {won} of {GAMES}");
+}
@@ -0,0 +1,329 @@
+//! Which phase a position is in, and what to play in each, read from a
+//! TOML file like `examples/phases.toml`:
+//!
+//! ```toml
+//! [phases]
+//! midgame_round = 6
+//! endgame_points = 3
+//!
+//! [midgame]
+//! strategies = ["alphabeta", "greedy"]
+//! budget_ms = 1000
+//! ```
+//!
+//! `[phases]` sets where phases start, see [`Config`], and a table named
+//! after a phase what is played in it, see [`Plan`]. What the file leaves
+//! out keeps its default.
+
+use std::{fmt::Display, str::FromStr, time::Duration};
+
+use anyhow::{bail, Context};
+use config_toml::Value;
+use game_def::State;
+
+/// The part of the game a position is in, each played its own way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Before any of the others: building up cards.
+    Opening,
+    /// Cards worth points come within reach, or the game is long enough.
+    Midgame,
+    /// Someone is close to the target score.
+    Endgame,
+    /// The clock is running out, whatever the board.
+    Scramble,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 4] = [
+        Phase::Opening,
+        Phase::Midgame,
+        Phase::Endgame,
+        Phase::Scramble,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Opening => "opening",
+            Phase::Midgame => "midgame",
+            Phase::Endgame => "endgame",
+            Phase::Scramble => "scramble",
+        }
+    }
+}
+
+impl Display for Phase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A way to pick a move, each the code of one of the other agents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// [`greedy_agent::best_action`], right away.
+    Greedy,
+    /// [`beam_agent::Beam`] with its defaults, which takes as long as it
+    /// takes.
+    Beam,
+    /// [`alpha_beta_agent::AlphaBeta`], deepening for the budget.
+    AlphaBeta,
+    /// Only the search to the end of the game of
+    /// [`alpha_beta_agent::AlphaBeta::solve`], which finds no move unless
+    /// it proves a win.
+    Solver,
+    /// [`mcts_agent::Mcts`], running playouts for the budget.
+    Mcts,
+    /// Any move but resigning.
+    Random,
+}
+
+impl Strategy {
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::Greedy => "greedy",
+            Strategy::Beam => "beam",
+            Strategy::AlphaBeta => "alphabeta",
+            Strategy::Solver => "solver",
+            Strategy::Mcts => "mcts",
+            Strategy::Random => "random",
+        }
+    }
+}
+
+impl Display for Strategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Strategy> {
+        match s {
+            "greedy" => Ok(Strategy::Greedy),
+            "beam" => Ok(Strategy::Beam),
+            "alphabeta" => Ok(Strategy::AlphaBeta),
+            "solver" => Ok(Strategy::Solver),
+            "mcts" => Ok(Strategy::Mcts),
+            "random" => Ok(Strategy::Random),
+            _ => bail!("Unknown strategy {s}, try greedy, beam, alphabeta, solver, mcts or random"),
+        }
+    }
+}
+
+/// What is played in a phase: the move of the first of `strategies` that
+/// comes up with a legal one, each searching for at most `budget`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+    pub strategies: Vec<Strategy>,
+    pub budget: Duration,
+}
+
+/// Like `solver, alphabeta, greedy in 1s`.
+impl Display for Plan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<_> = self.strategies.iter().map(|s| s.name()).collect();
+        write!(f, "{} in {:?}", names.join(", "), self.budget)
+    }
+}
+
+/// Where each phase starts and what is played in it. The phase of a
+/// position is the last of these that holds, see [`Config::phase`]:
+/// the opening, the midgame once `midgame_round` rounds are played,
+/// someone has `midgame_score` points or the player to move can buy a
+/// card worth `point_card` points, the endgame once someone is within
+/// `endgame_points` of the target score, and the scramble once less than
+/// `scramble_time` is left on the clock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub midgame_round: usize,
+    pub midgame_score: u8,
+    pub point_card: u8,
+    pub endgame_points: u8,
+    pub scramble_time: Duration,
+    pub opening: Plan,
+    pub midgame: Plan,
+    pub endgame: Plan,
+    pub scramble: Plan,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let plan = |strategies: &[Strategy], ms| Plan {
+            strategies: strategies.to_vec(),
+            budget: Duration::from_millis(ms),
+        };
+        use Strategy::*;
+        Config {
+            midgame_round: 6,
+            midgame_score: 5,
+            point_card: 2,
+            endgame_points: 3,
+            scramble_time: Duration::from_secs(2),
+            opening: plan(&[Greedy], 200),
+            midgame: plan(&[AlphaBeta, Greedy], 1000),
+            endgame: plan(&[Solver, AlphaBeta, Greedy], 1000),
+            scramble: plan(&[Greedy], 50),
+        }
+    }
+}
+
+/// The keys of `[phases]`.
+const PHASE_KEYS: &str = "midgame_round, midgame_score, point_card, endgame_points or scramble_ms";
+
+impl Config {
+    /// The phase `state` is in for the player to move, who has `time_left`
+    /// on the clock if the runner keeps one.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use game_def::{GameConfig, State};
+    /// use portfolio_agent::{Config, Phase};
+    /// use rand::{rngs::StdRng, SeedableRng};
+    ///
+    /// let config = Config::default();
+    /// let mut state = State::setup(&["a", "b"], GameConfig::default(), &mut StdRng::seed_from_u64(1));
+    /// assert_eq!(config.phase(&state, None), Phase::Opening);
+    /// state.players[1].score = 12;
+    /// assert_eq!(config.phase(&state, None), Phase::Endgame);
+    /// assert_eq!(config.phase(&state, Some(Duration::from_secs(1))), Phase::Scramble);
+    /// ```
+    pub fn phase(&self, state: &State, time_left: Option<Duration>) -> Phase {
+        if time_left.is_some_and(|left| left < self.scramble_time) {
+            return Phase::Scramble;
+        }
+        let best = state.players.iter().map(|p| p.score).max().unwrap_or(0);
+        if best.saturating_add(self.endgame_points) >= state.config.target_score {
+            return Phase::Endgame;
+        }
+        if state.round >= self.midgame_round
+            || best >= self.midgame_score
+            || self.point_card_in_reach(state)
+        {
+            return Phase::Midgame;
+        }
+        Phase::Opening
+    }
+
+    /// Whether the player to move can buy a face up or reserved card worth
+    /// at least `point_card` points.
+    fn point_card_in_reach(&self, state: &State) -> bool {
+        let player = &state.players[state.turn];
+        let visible = state.config.visible_cards;
+        state
+            .decks
+            .values()
+            .flat_map(|deck| deck.iter().take(visible))
+            .chain(&player.reserved)
+            .filter(|card| card.score() >= self.point_card)
+            .any(|card| player.payment_for(card.cost()).is_some())
+    }
+
+    pub fn plan(&self, phase: Phase) -> &Plan {
+        match phase {
+            Phase::Opening => &self.opening,
+            Phase::Midgame => &self.midgame,
+            Phase::Endgame => &self.endgame,
+            Phase::Scramble => &self.scramble,
+        }
+    }
+
+    fn plan_mut(&mut self, phase: Phase) -> &mut Plan {
+        match phase {
+            Phase::Opening => &mut self.opening,
+            Phase::Midgame => &mut self.midgame,
+            Phase::Endgame => &mut self.endgame,
+            Phase::Scramble => &mut self.scramble,
+        }
+    }
+
+    pub fn load(path: &str) -> anyhow::Result<Config> {
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+        Config::parse(path, &text)
+    }
+
+    /// The defaults with what the TOML `text` of the file `path` sets over
+    /// them. A table or a key it doesn't know, or a value of the wrong
+    /// type, fails with the line it is on.
+    pub fn parse(path: &str, text: &str) -> anyhow::Result<Config> {
+        let mut config = Config::default();
+        for table in config_toml::parse(path, text)? {
+            let at = |line: usize| format!("{path}:{line}");
+            if table.array {
+                bail!(
+                    "{}: [[{}]] is for a list, not a phase",
+                    at(table.line),
+                    table.name
+                );
+            }
+            if table.name.is_empty() {
+                if let Some((key, _, line)) = table.entries.first() {
+                    bail!("{}: {key} needs to be in a table like [phases]", at(*line));
+                }
+                continue;
+            }
+            if table.name == "phases" {
+                for (key, value, line) in &table.entries {
+                    let at = at(*line);
+                    let number = |value: &Value| match *value {
+                        Value::Integer(n) if n >= 0 => Some(n as u64),
+                        _ => None,
+                    };
+                    let n = number(value).with_context(|| {
+                        format!("{at}: {key} needs a whole number, not {value}")
+                    })?;
+                    let points = || {
+                        u8::try_from(n).with_context(|| format!("{at}: {key} is too many points"))
+                    };
+                    match key.as_str() {
+                        "midgame_round" => config.midgame_round = n as usize,
+                        "midgame_score" => config.midgame_score = points()?,
+                        "point_card" => config.point_card = points()?,
+                        "endgame_points" => config.endgame_points = points()?,
+                        "scramble_ms" => config.scramble_time = Duration::from_millis(n),
+                        _ => bail!("{at}: unknown key {key} in [phases], try {PHASE_KEYS}"),
+                    }
+                }
+                continue;
+            }
+            let Some(&phase) = Phase::ALL.iter().find(|p| p.name() == table.name) else {
+                bail!(
+                    "{}: unknown table {}, try [phases], [opening], [midgame], [endgame] or [scramble]",
+                    at(table.line),
+                    table.name
+                );
+            };
+            let plan = config.plan_mut(phase);
+            for (key, value, line) in &table.entries {
+                let at = at(*line);
+                match (key.as_str(), value) {
+                    ("strategies", Value::Array(items)) if !items.is_empty() => {
+                        plan.strategies = vec![];
+                        for item in items {
+                            let Value::Text(name) = item else {
+                                bail!("{at}: strategies are names, not {item}");
+                            };
+                            plan.strategies
+                                .push(name.parse().with_context(|| at.clone())?);
+                        }
+                    }
+                    ("strategies", _) => {
+                        bail!("{at}: strategies needs an array of at least one name, not {value}")
+                    }
+                    ("budget_ms", &Value::Integer(ms)) if ms > 0 => {
+                        plan.budget = Duration::from_millis(ms as u64)
+                    }
+                    ("budget_ms", _) => {
+                        bail!("{at}: budget_ms needs a positive number, not {value}")
+                    }
+                    _ => bail!("{at}: unknown key {key} in [{phase}], try strategies or budget_ms"),
+                }
+            }
+        }
+        Ok(config)
+    }
+}
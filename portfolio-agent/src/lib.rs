@@ -0,0 +1,192 @@
+//! An agent playing each move with the code of another, picked by the phase
+//! of the game: the greedy agent building up cards in the opening, the
+//! alpha-beta search once cards worth points are in reach, and its search
+//! to the end of the game near the target score, see [`Config`].
+//!
+//! Each phase has a list of strategies. When one finds no move, panics or
+//! comes up with an illegal one, the next is tried, and if none is left
+//! it plays its first legal move. Every move is logged with the phase, the
+//! strategy that played it and those that failed before it, like `endgame,
+//! alphabeta played buy 1.2 in 1.0s, after solver found no move`.
+
+use std::{
+    fmt::Display,
+    panic::{catch_unwind, AssertUnwindSafe},
+    time::{Duration, Instant},
+};
+
+use alpha_beta_agent::AlphaBeta;
+use beam_agent::Beam;
+use game_def::{Action, AgentHandler, Observation};
+use mcts_agent::Mcts;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+mod config;
+
+pub use config::{Config, Phase, Plan, Strategy};
+
+/// What [`Portfolio::choose`] played.
+#[derive(Debug, Clone)]
+pub struct Choice {
+    pub phase: Phase,
+    pub action: Action,
+    /// The strategy that found `action`, `None` if every one failed.
+    pub strategy: Option<Strategy>,
+    /// Those that failed before it, and why.
+    pub failed: Vec<(Strategy, String)>,
+    pub elapsed: Duration,
+}
+
+/// The line logged for each move, like `midgame, alphabeta played take r g
+/// u in 1.0s` or `endgame, greedy played buy 1.2 in 2.1s, after solver found
+/// no move; alphabeta panicked`.
+impl Display for Choice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.strategy {
+            Some(strategy) => write!(
+                f,
+                "{}, {strategy} played {} in {:.1?}",
+                self.phase, self.action, self.elapsed
+            )?,
+            None => write!(
+                f,
+                "{}, every strategy failed, playing {} in {:.1?}",
+                self.phase, self.action, self.elapsed
+            )?,
+        }
+        if !self.failed.is_empty() {
+            let failed: Vec<_> = self
+                .failed
+                .iter()
+                .map(|(strategy, why)| format!("{strategy} {why}"))
+                .collect();
+            write!(f, ", after {}", failed.join("; "))?;
+        }
+        Ok(())
+    }
+}
+
+/// The agent, switching strategies as [`Config`] says. `seed` picks the
+/// cards the searches imagine where they can't see them, and the moves of
+/// [`Strategy::Random`].
+pub struct Portfolio {
+    pub config: Config,
+    pub seed: u64,
+    rng: StdRng,
+    /// What the runner said was left on the clock before this move.
+    time_left: Option<Duration>,
+}
+
+impl Portfolio {
+    pub fn new(config: Config, seed: u64) -> Portfolio {
+        Portfolio {
+            config,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            time_left: None,
+        }
+    }
+
+    /// The move of the first strategy of the phase of `observation` that
+    /// finds a legal one.
+    pub fn choose(&mut self, observation: Observation) -> Choice {
+        let started = Instant::now();
+        let phase = self.config.phase(&observation.state, self.time_left);
+        let plan = self.config.plan(phase).clone();
+        // The face down cards as the searches make them up, to check moves
+        // like blind reserves on.
+        let determinized = observation.to_determinized_state(&mut StdRng::seed_from_u64(self.seed));
+        let mut failed = vec![];
+        for &strategy in &plan.strategies {
+            let played = catch_unwind(AssertUnwindSafe(|| {
+                self.play(strategy, &observation, plan.budget)
+            }));
+            let why = match played {
+                Ok(Some(action)) => match determinized.clone().run(action.clone()) {
+                    Ok(_) => {
+                        return Choice {
+                            phase,
+                            action,
+                            strategy: Some(strategy),
+                            failed,
+                            elapsed: started.elapsed(),
+                        }
+                    }
+                    Err(e) => format!("played {action}, which is illegal: {e}"),
+                },
+                Ok(None) => "found no move".to_owned(),
+                Err(_) => "panicked".to_owned(),
+            };
+            failed.push((strategy, why));
+        }
+        let action = determinized
+            .legal_actions()
+            .find(|action| *action != Action::Resign)
+            .unwrap_or(Action::Skip);
+        Choice {
+            phase,
+            action,
+            strategy: None,
+            failed,
+            elapsed: started.elapsed(),
+        }
+    }
+
+    /// What `strategy` plays in `observation`, searching for at most
+    /// `budget`.
+    fn play(
+        &mut self,
+        strategy: Strategy,
+        observation: &Observation,
+        budget: Duration,
+    ) -> Option<Action> {
+        let searching = AlphaBeta {
+            time_limit: budget,
+            seed: self.seed,
+            endgame: self.config.endgame_points,
+            ..AlphaBeta::default()
+        };
+        match strategy {
+            Strategy::Greedy => Some(greedy_agent::best_action(&observation.state)),
+            Strategy::Beam => {
+                let beam = Beam {
+                    seed: self.seed,
+                    ..Beam::default()
+                };
+                Some(beam.think(observation.clone()).action)
+            }
+            Strategy::AlphaBeta => Some(searching.think(observation.clone()).action),
+            Strategy::Solver => searching
+                .solve(observation.clone())
+                .map(|(action, _)| action),
+            Strategy::Mcts => {
+                let mcts = Mcts {
+                    time_limit: budget,
+                    seed: self.seed,
+                    ..Mcts::default()
+                };
+                Some(mcts.think(observation.clone(), None).0.action)
+            }
+            Strategy::Random => {
+                let moves: Vec<_> = observation
+                    .state
+                    .legal_actions()
+                    .filter(|action| *action != Action::Resign)
+                    .collect();
+                moves.choose(&mut self.rng).cloned()
+            }
+        }
+    }
+}
+
+impl AgentHandler for Portfolio {
+    fn time_left(&mut self, left: Duration) {
+        self.time_left = Some(left);
+    }
+
+    fn act(&mut self, observation: Observation) -> anyhow::Result<Action> {
+        let choice = self.choose(observation);
+        eprintln!("portfolio: {choice}");
+        Ok(choice.action)
+    }
+}
@@ -0,0 +1,44 @@
+//! Usage: `portfolio-agent [--config <file.toml>] [--seed <n>]`
+//!
+//! Plays each move with the strategy its phase of the game calls for,
+//! falling back on the next when one fails, see [`Portfolio`]. `--config`
+//! reads where phases start and what is played in each from a TOML file,
+//! like `examples/phases.toml`, which holds the defaults, see [`Config`].
+//! `--seed` picks the cards its searches imagine where they can't see
+//! them. The plan of each phase is printed to stderr first thing, and then
+//! for each move the phase, the strategy that played it and those that
+//! failed before.
+//!
+//! Bad arguments fail before the first state is read. `--format` and
+//! `--protocol`, which the runner adds, are for [`run_agent`].
+
+use anyhow::Context;
+use game_def::run_agent;
+use portfolio_agent::{Config, Phase, Portfolio};
+
+const USAGE: &str = "usage: portfolio-agent [--config <file.toml>] [--seed <n>]";
+
+fn main() -> anyhow::Result<()> {
+    let mut config = Config::default();
+    let mut seed = 0;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => config = Config::load(&args.next().context("--config needs a file")?)?,
+            "--seed" => {
+                seed = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .context("--seed needs a number")?
+            }
+            "--format" | "--protocol" => {
+                args.next();
+            }
+            _ => anyhow::bail!("Unknown argument {arg}, {USAGE}"),
+        }
+    }
+    for phase in Phase::ALL {
+        eprintln!("portfolio: {phase} {}", config.plan(phase));
+    }
+    run_agent(Portfolio::new(config, seed))
+}
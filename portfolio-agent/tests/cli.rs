@@ -0,0 +1,77 @@
+//! The portfolio-agent program, started like a runner starts it.
+
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+use game_def::{Action, GameConfig, State};
+use rand::{rngs::StdRng, SeedableRng};
+
+fn run(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_portfolio-agent"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn logs_its_plans_and_each_move_and_bad_configs_fail_first() {
+    let state = State::setup(
+        &["a", "b"],
+        GameConfig::default(),
+        &mut StdRng::seed_from_u64(2),
+    );
+    let observation = state.observation_for(state.turn).json();
+    let config = std::env::temp_dir().join(format!("portfolio-{}.toml", std::process::id()));
+    std::fs::write(
+        &config,
+        "[opening]\nstrategies = ['solver', 'greedy']\nbudget_ms = 30\n",
+    )
+    .unwrap();
+    let output = run(
+        &["--config", config.to_str().unwrap(), "--seed", "3"],
+        &format!("{observation}\n"),
+    );
+    assert!(output.status.success(), "{output:?}");
+    let action: Action = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(state.legal_actions().any(|legal| legal == action));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let lines: Vec<_> = stderr.lines().collect();
+    assert_eq!(lines[0], "portfolio: opening solver, greedy in 30ms");
+    assert_eq!(lines[1], "portfolio: midgame alphabeta, greedy in 1s");
+    assert!(
+        lines[4].starts_with(&format!("portfolio: opening, greedy played {action} in ")),
+        "{stderr}"
+    );
+    assert!(
+        lines[4].ends_with(", after solver found no move"),
+        "{stderr}"
+    );
+
+    std::fs::write(&config, "[opening]\nbudget = 30\n").unwrap();
+    let output = run(&["--config", config.to_str().unwrap()], "");
+    std::fs::remove_file(&config).unwrap();
+    assert!(!output.status.success());
+    let error = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        error.contains(":2: unknown key budget in [opening], try strategies or budget_ms"),
+        "{error}"
+    );
+    let output = run(&["--budget", "1"], "");
+    let error = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        error.contains("Unknown argument --budget, usage: "),
+        "{error}"
+    );
+}
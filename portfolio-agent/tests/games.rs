@@ -0,0 +1,64 @@
+//! Whole games against random moves, played in process, by the portfolio
+//! and by the strategies it is made of on their own.
+
+use std::time::Duration;
+
+use alpha_beta_agent::AlphaBeta;
+use game_def::{play_game, Action, Agent, GameConfig, GameResult, Handled, State};
+use portfolio_agent::{Config, Portfolio};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+const GAMES: u64 = 6;
+const BUDGET: Duration = Duration::from_millis(100);
+
+/// Games `agent` wins of [`GAMES`] against any move but resigning, taking
+/// turns at moving first.
+fn wins(agent: impl Fn(u64) -> Box<dyn Agent>) -> u64 {
+    let mut won = 0;
+    for game in 0..GAMES {
+        let seat = (game % 2) as usize;
+        let mut rng = StdRng::seed_from_u64(game);
+        let random = move |state: &State| {
+            let moves: Vec<_> = state
+                .legal_actions()
+                .filter(|action| *action != Action::Resign)
+                .collect();
+            moves.choose(&mut rng).cloned().unwrap_or(Action::Skip)
+        };
+        let mut agents: Vec<Box<dyn Agent>> = vec![Box::new(random), agent(game)];
+        agents.swap(0, 1 - seat);
+        let state = State::setup(
+            &["a", "b"],
+            GameConfig::default(),
+            &mut StdRng::seed_from_u64(game),
+        );
+        if play_game(state, &mut agents, 100) == GameResult::Won(seat) {
+            won += 1;
+        }
+    }
+    won
+}
+
+#[test]
+fn at_least_as_strong_as_its_strongest_strategy() {
+    let greedy = wins(|_| Box::new(|state: &State| greedy_agent::best_action(state)));
+    let searching = wins(|game| {
+        Box::new(Handled(AlphaBeta {
+            time_limit: BUDGET,
+            seed: game,
+            ..AlphaBeta::default()
+        }))
+    });
+    let portfolio = wins(|game| {
+        let mut config = Config::default();
+        for plan in [&mut config.midgame, &mut config.endgame] {
+            plan.budget = BUDGET;
+        }
+        Box::new(Handled(Portfolio::new(config, game)))
+    });
+    println!("Of {GAMES}: greedy {greedy}, alphabeta {searching}, portfolio {portfolio}");
+    assert!(
+        portfolio >= greedy.max(searching),
+        "greedy {greedy}, alphabeta {searching}, portfolio {portfolio}"
+    );
+}
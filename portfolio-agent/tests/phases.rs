@@ -0,0 +1,187 @@
+//! Phases of constructed positions, configs, and the strategies of a phase
+//! falling back on each other.
+
+use std::time::Duration;
+
+use game_def::{GameConfig, State, Tier};
+use portfolio_agent::{Config, Phase, Plan, Portfolio, Strategy};
+use rand::{rngs::StdRng, SeedableRng};
+
+fn setup() -> State {
+    State::setup(
+        &["a", "b"],
+        GameConfig::default(),
+        &mut StdRng::seed_from_u64(4),
+    )
+}
+
+#[test]
+fn phases_start_at_their_boundaries() {
+    let config = Config::default();
+    let phase = |state: &State| config.phase(state, None);
+    assert_eq!(phase(&setup()), Phase::Opening);
+
+    let mut state = setup();
+    state.round = config.midgame_round - 1;
+    assert_eq!(phase(&state), Phase::Opening);
+    state.round = config.midgame_round;
+    assert_eq!(phase(&state), Phase::Midgame);
+
+    // Whoever has the points, not only the player to move.
+    let mut state = setup();
+    let other = 1 - state.turn;
+    state.players[other].score = config.midgame_score - 1;
+    assert_eq!(phase(&state), Phase::Opening);
+    state.players[other].score = config.midgame_score;
+    assert_eq!(phase(&state), Phase::Midgame);
+    let target = state.config.target_score;
+    state.players[other].score = target - config.endgame_points - 1;
+    assert_eq!(phase(&state), Phase::Midgame);
+    state.players[other].score = target - config.endgame_points;
+    assert_eq!(phase(&state), Phase::Endgame);
+
+    // A card worth points the player to move can pay for, face up or
+    // reserved; cheaper ones don't count.
+    let visible = setup().config.visible_cards;
+    for tier in [Tier::One, Tier::Two, Tier::Three] {
+        for card in setup().decks[tier].iter().take(visible) {
+            let mut state = setup();
+            let me = state.turn;
+            state.players[me].immortal = card.cost().clone();
+            let expected = match card.score() >= config.point_card {
+                true => Phase::Midgame,
+                false => Phase::Opening,
+            };
+            assert_eq!(phase(&state), expected, "{card:?}");
+            // Not when it's the other player who can.
+            state.players.swap(0, 1);
+            assert_eq!(phase(&state), Phase::Opening, "{card:?}");
+        }
+    }
+    let mut state = setup();
+    let me = state.turn;
+    let card = state.decks[Tier::Three].pop().unwrap();
+    state.players[me].immortal = card.cost().clone();
+    state.players[me].reserved.push(card);
+    assert_eq!(phase(&state), Phase::Midgame);
+
+    // The clock over everything.
+    let state = setup();
+    assert_eq!(
+        config.phase(&state, Some(config.scramble_time)),
+        Phase::Opening
+    );
+    let low = config.scramble_time - Duration::from_millis(1);
+    assert_eq!(config.phase(&state, Some(low)), Phase::Scramble);
+    let mut state = setup();
+    state.players[0].score = state.config.target_score - 1;
+    assert_eq!(config.phase(&state, Some(low)), Phase::Scramble);
+}
+
+#[test]
+fn example_config_holds_the_defaults() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/phases.toml");
+    assert_eq!(Config::load(path).unwrap(), Config::default());
+
+    let config = Config::parse(
+        "a.toml",
+        "[phases]\nendgame_points = 5\n\n[opening]\nstrategies = ['mcts', 'random']\n",
+    )
+    .unwrap();
+    assert_eq!(config.endgame_points, 5);
+    assert_eq!(
+        config.opening.strategies,
+        [Strategy::Mcts, Strategy::Random]
+    );
+    assert_eq!(config.opening.budget, Config::default().opening.budget);
+    assert_eq!(config.midgame, Config::default().midgame);
+}
+
+#[test]
+fn bad_configs_fail_with_their_line() {
+    for (text, expected) in [
+        (
+            "[phases]\nmidgame = 3\n",
+            "a.toml:2: unknown key midgame in [phases]",
+        ),
+        ("[openings]\n", "a.toml:1: unknown table openings"),
+        (
+            "[endgame]\nstrategies = ['solver', 'oracle']\n",
+            "a.toml:2: Unknown strategy oracle",
+        ),
+        (
+            "[endgame]\nstrategies = []\n",
+            "a.toml:2: strategies needs an array of at least one name",
+        ),
+        (
+            "[midgame]\nbudget_ms = 0\n",
+            "a.toml:2: budget_ms needs a positive number",
+        ),
+        (
+            "[phases]\npoint_card = 300\n",
+            "a.toml:2: point_card is too many points",
+        ),
+        ("seed = 1\n", "a.toml:1: seed needs to be in a table"),
+    ] {
+        let error = Config::parse("a.toml", text).unwrap_err();
+        let error = format!("{error:#}");
+        assert!(error.starts_with(expected), "{text:?}: {error}");
+    }
+}
+
+#[test]
+fn a_strategy_finding_no_move_falls_back_on_the_next() {
+    let state = setup();
+    let mut config = Config::default();
+    // Far from the end of the game, the solver proves nothing.
+    config.opening.strategies = vec![Strategy::Solver, Strategy::Greedy];
+    let mut portfolio = Portfolio::new(config, 0);
+    let choice = portfolio.choose(state.observation_for(state.turn));
+    assert_eq!(choice.phase, Phase::Opening);
+    assert_eq!(choice.strategy, Some(Strategy::Greedy));
+    assert_eq!(choice.action, greedy_agent::best_action(&state));
+    assert_eq!(
+        choice.failed,
+        [(Strategy::Solver, "found no move".to_owned())]
+    );
+    assert!(
+        choice
+            .to_string()
+            .starts_with(&format!("opening, greedy played {} in ", choice.action)),
+        "{choice}"
+    );
+    assert!(
+        choice.to_string().ends_with(", after solver found no move"),
+        "{choice}"
+    );
+
+    // With nothing to fall back on, its first legal move.
+    let mut config = Config::default();
+    config.opening.strategies = vec![Strategy::Solver];
+    let choice = Portfolio::new(config, 0).choose(state.observation_for(state.turn));
+    assert_eq!(choice.strategy, None);
+    assert!(state.legal_actions().any(|legal| legal == choice.action));
+}
+
+#[test]
+fn every_strategy_plays_a_legal_move() {
+    let state = setup();
+    for strategy in [
+        Strategy::Greedy,
+        Strategy::Beam,
+        Strategy::AlphaBeta,
+        Strategy::Mcts,
+        Strategy::Random,
+    ] {
+        let config = Config {
+            opening: Plan {
+                strategies: vec![strategy],
+                budget: Duration::from_millis(50),
+            },
+            ..Config::default()
+        };
+        let choice = Portfolio::new(config, 0).choose(state.observation_for(state.turn));
+        assert_eq!(choice.strategy, Some(strategy), "{choice}");
+        assert!(state.legal_actions().any(|legal| legal == choice.action));
+    }
+}
@@ -1,43 +1,89 @@
-use game_def::{ai_from_function, Action, State};
+//! Usage: `random-agent [--seed <n>] [--purchase-rate <rate>] [--legacy-first]`
+//!
+//! Plays a legal move at random, any but resigning. When it can buy a card,
+//! it buys one with a chance of `--purchase-rate`, 0.5 by default, like
+//! [`State::random_playout`], so its games get to an end; 0 picks among
+//! all moves alike. The moves come from `--seed`, or the `SPLENDOR_SEED`
+//! environment variable, or else a seed of its own, which it prints to
+//! stderr so the game can be played again. With `--legacy-first` it plays
+//! the first legal move every time instead, like [`RandomAgent`].
 
-fn logic(state: State) -> Action {
-    for (deck, card) in state.card_iter() {
-        let action = Action::Purchase { deck, card };
-        if state.clone().run(action.clone()).is_ok() {
-            return action;
-        }
-    }
+use anyhow::Context;
+use game_def::{ai_from_agent, Action, Agent, RandomAgent, State};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
-    for index in 0..state.players[state.turn].reserved.len() {
-        let action = Action::PurchaseReserved { index };
-        if state.clone().run(action.clone()).is_ok() {
-            return action;
-        }
-    }
+const USAGE: &str = "usage: random-agent [--seed <n>] [--purchase-rate <rate>] [--legacy-first]";
 
-    for (one, two, three) in state.pick_three_iter() {
-        let action = Action::PickThree { one, two, three };
-        if state.clone().run(action.clone()).is_ok() {
-            return action;
-        }
-    }
+/// Picks one of the purchases with a chance of `purchase_rate` when there
+/// are any, and otherwise any legal move but resigning.
+struct Sampler {
+    rng: StdRng,
+    purchase_rate: f64,
+}
 
-    for color in state.pick_two_iter() {
-        let action = Action::PickTwo { color };
-        if state.clone().run(action.clone()).is_ok() {
-            return action;
-        }
+impl Agent for Sampler {
+    fn act(&mut self, state: &State) -> Action {
+        let moves: Vec<_> = state
+            .legal_actions()
+            .filter(|action| *action != Action::Resign)
+            .collect();
+        // Purchases come first.
+        let purchases = moves
+            .iter()
+            .take_while(|a| matches!(a, Action::Purchase { .. } | Action::PurchaseReserved { .. }))
+            .count();
+        let index = if purchases > 0 && self.rng.gen_bool(self.purchase_rate) {
+            self.rng.gen_range(0..purchases)
+        } else if moves.is_empty() {
+            return Action::Skip;
+        } else {
+            self.rng.gen_range(0..moves.len())
+        };
+        moves[index].clone()
     }
+}
 
-    for (deck, card) in state.card_iter() {
-        let action = Action::Reserve { deck, card };
-        if state.clone().run(action.clone()).is_ok() {
-            return action;
+fn main() -> anyhow::Result<()> {
+    let mut seed = None;
+    let mut purchase_rate = 0.5;
+    let mut legacy = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => {
+                seed = Some(
+                    args.next()
+                        .and_then(|x| x.parse().ok())
+                        .context("--seed needs a number")?,
+                )
+            }
+            "--purchase-rate" => {
+                purchase_rate = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .filter(|rate| (0.0..=1.0).contains(rate))
+                    .context("--purchase-rate needs a rate between 0 and 1")?
+            }
+            "--legacy-first" => legacy = true,
+            "--format" | "--protocol" => {
+                args.next();
+            }
+            _ => anyhow::bail!("Unknown argument {arg}, {USAGE}"),
         }
     }
-    Action::Skip
-}
-
-fn main() {
-    ai_from_function(logic);
+    if legacy {
+        return ai_from_agent(RandomAgent);
+    }
+    let seed = match seed {
+        Some(seed) => seed,
+        None => match std::env::var("SPLENDOR_SEED") {
+            Ok(seed) => seed.parse().context("SPLENDOR_SEED needs a number")?,
+            Err(_) => rand::random(),
+        },
+    };
+    eprintln!("random-agent: seed {seed}");
+    ai_from_agent(Sampler {
+        rng: StdRng::seed_from_u64(seed),
+        purchase_rate,
+    })
 }
@@ -0,0 +1,91 @@
+//! The random-agent program, started like a runner starts it.
+
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+use game_def::{Action, Agent, GameConfig, RandomAgent, State};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Runs the program with `args`, and `SPLENDOR_SEED` set to `env` if given,
+/// on `input` until its input ends.
+fn run(args: &[&str], env: Option<&str>, input: &str) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_random-agent"));
+    command.env_remove("SPLENDOR_SEED");
+    if let Some(seed) = env {
+        command.env("SPLENDOR_SEED", seed);
+    }
+    let mut child = command
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// The positions of the first `moves` moves of a game played by
+/// [`RandomAgent`], one observation a line, and the moves it played in them.
+fn positions(moves: usize) -> (String, Vec<Action>) {
+    let mut state = State::setup(
+        &["a", "b"],
+        GameConfig::default(),
+        &mut StdRng::seed_from_u64(5),
+    );
+    let mut input = String::new();
+    let mut played = vec![];
+    for _ in 0..moves {
+        input += &serde_json::to_string(&state.observation_for(state.turn)).unwrap();
+        input += "\n";
+        let action = RandomAgent.act(&state);
+        state.apply(&action).unwrap();
+        played.push(action);
+    }
+    (input, played)
+}
+
+/// The moves the program played, one a line.
+fn moves(output: &Output) -> Vec<Action> {
+    assert!(output.status.success(), "{output:?}");
+    serde_json::Deserializer::from_slice(&output.stdout)
+        .into_iter()
+        .map(Result::unwrap)
+        .collect()
+}
+
+#[test]
+fn seeds_pick_the_moves() {
+    let (input, _) = positions(6);
+    let one = moves(&run(&["--seed", "1"], None, &input));
+    assert_eq!(one.len(), 6);
+    assert_eq!(one, moves(&run(&["--seed", "1"], None, &input)));
+    assert_eq!(one, moves(&run(&[], Some("1"), &input)));
+    assert_ne!(one, moves(&run(&["--seed", "2"], None, &input)));
+    assert!(one.iter().all(|action| *action != Action::Resign));
+}
+
+#[test]
+fn legacy_first_plays_the_first_legal_move() {
+    let (input, played) = positions(6);
+    assert_eq!(moves(&run(&["--legacy-first"], None, &input)), played);
+}
+
+#[test]
+fn bad_arguments_fail() {
+    for args in [
+        &["--seed"][..],
+        &["--purchase-rate", "2"],
+        &["--depth", "3"],
+    ] {
+        let output = run(args, None, "");
+        assert!(!output.status.success(), "{args:?}");
+    }
+}
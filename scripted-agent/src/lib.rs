@@ -0,0 +1,59 @@
+//! Scripts of moves for the scripted-agent program, a test double for the
+//! runner that plays the moves it is given whatever the state.
+
+use std::str::FromStr;
+
+use anyhow::Context;
+use game_def::Action;
+
+/// The actions of a script, one a line in the notation of
+/// [`Action`]'s `FromStr`, like `take r g u` or `buy 1.2`. Blank lines and
+/// those starting with `#` are skipped. A line that isn't an action fails
+/// with its number.
+///
+/// ```
+/// use game_def::{Action, ResourceKind, Tier};
+///
+/// let script = "# opening\ntake r g u\n\nbuy 1.2\n";
+/// let actions = scripted_agent::parse(script).unwrap();
+/// assert_eq!(actions.len(), 2);
+/// assert_eq!(actions[0].to_string(), "take r g u");
+/// assert!(matches!(actions[1], Action::Purchase { deck: Tier::One, card: 2, .. }));
+///
+/// let error = scripted_agent::parse("take r g u\nfly away\n").unwrap_err();
+/// assert!(format!("{error:#}").starts_with("Line 2: "), "{error:#}");
+/// ```
+pub fn parse(script: &str) -> anyhow::Result<Vec<Action>> {
+    let mut actions = vec![];
+    for (k, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let action = line.parse().with_context(|| format!("Line {}", k + 1))?;
+        actions.push(action);
+    }
+    Ok(actions)
+}
+
+/// What the agent does once it has played every move of its script.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Then {
+    /// Skips every move after.
+    #[default]
+    Skip,
+    /// Exits with status 0, like an agent that quit.
+    Exit,
+}
+
+impl FromStr for Then {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Then> {
+        match s {
+            "skip" => Ok(Then::Skip),
+            "exit" => Ok(Then::Exit),
+            _ => anyhow::bail!("Unknown --then {s}, try skip or exit"),
+        }
+    }
+}
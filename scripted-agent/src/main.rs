@@ -0,0 +1,101 @@
+//! Usage: `scripted-agent --script <file> [--then skip|exit]
+//! [--sleep-ms <n>] [--crash-after <moves>] [--invalid-after <moves>]`
+//!
+//! A test double for the runner: plays the actions of the `--script` file
+//! in order, see [`parse`], whatever the state it is sent. Once they are
+//! played it skips every move, or exits with `--then exit`. It sleeps
+//! `--sleep-ms` milliseconds before each move, to run out of time.
+//! `--crash-after` exits with status 7 when asked for one more move than
+//! that, and `--invalid-after` answers every move after that many with
+//! `buy 1.9`, a card that is never there, to forfeit.
+//!
+//! The script is read before the first state, so a bad one fails right
+//! away. It speaks the plain protocol of [`ai_from_function`], in JSON or
+//! with `--format msgpack`.
+
+use std::time::Duration;
+
+use anyhow::Context;
+use game_def::{ai_from_function, Action, Observation, Tier};
+use scripted_agent::{parse, Then};
+
+const USAGE: &str = "usage: scripted-agent --script <file> [--then skip|exit] [--sleep-ms <n>] [--crash-after <moves>] [--invalid-after <moves>]";
+
+fn main() -> anyhow::Result<()> {
+    let mut script = None;
+    let mut then = Then::default();
+    let mut sleep = Duration::ZERO;
+    let mut crash_after: Option<usize> = None;
+    let mut invalid_after: Option<usize> = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--script" => {
+                let path = args.next().context("--script needs a file")?;
+                let text = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read the script {path}"))?;
+                script = Some(parse(&text).with_context(|| format!("In the script {path}"))?);
+            }
+            "--then" => then = args.next().context("--then needs skip or exit")?.parse()?,
+            "--sleep-ms" => {
+                sleep = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .map(Duration::from_millis)
+                    .context("--sleep-ms needs a number of milliseconds")?
+            }
+            "--crash-after" => {
+                crash_after = Some(
+                    args.next()
+                        .and_then(|x| x.parse().ok())
+                        .context("--crash-after needs a number of moves")?,
+                )
+            }
+            "--invalid-after" => {
+                invalid_after = Some(
+                    args.next()
+                        .and_then(|x| x.parse().ok())
+                        .context("--invalid-after needs a number of moves")?,
+                )
+            }
+            "--protocol" => {
+                let protocol = args.next();
+                if protocol.as_deref() == Some("messages") {
+                    anyhow::bail!("scripted-agent only speaks the plain protocol");
+                }
+            }
+            "--format" => {
+                args.next();
+            }
+            _ => anyhow::bail!("Unknown argument {arg}, {USAGE}"),
+        }
+    }
+    let mut script = script.context(USAGE)?.into_iter();
+    let mut moves: usize = 0;
+    ai_from_function(move |_: Observation| {
+        std::thread::sleep(sleep);
+        if crash_after == Some(moves) {
+            eprintln!("crashing after {moves} moves");
+            std::process::exit(7);
+        }
+        let invalid = invalid_after.is_some_and(|after| moves >= after);
+        moves += 1;
+        if invalid {
+            return Action::Purchase {
+                deck: Tier::One,
+                card: 9,
+                payment: None,
+                wilds: None,
+                id: None,
+            };
+        }
+        match (script.next(), then) {
+            (Some(action), _) => action,
+            (None, Then::Skip) => Action::Skip,
+            (None, Then::Exit) => {
+                eprintln!("script over after {} moves", moves - 1);
+                std::process::exit(0);
+            }
+        }
+    })
+}
@@ -0,0 +1,139 @@
+//! The scripted-agent program, fed states like a runner feeds it.
+
+use std::{
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+    time::{Duration, Instant},
+};
+
+use game_def::{Action, GameConfig, State};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Writes `text` to a script file named after `name`.
+fn script(name: &str, text: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("scripted-{name}-{}.txt", std::process::id()));
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+/// Runs the program with `args`, sending it the same state `states` times.
+fn run(args: &[&str], states: usize) -> Output {
+    let state = State::setup(
+        &["a", "b"],
+        GameConfig::default(),
+        &mut StdRng::seed_from_u64(1),
+    );
+    let line = serde_json::to_string(&state.observation_for(state.turn)).unwrap() + "\n";
+    let mut child = Command::new(env!("CARGO_BIN_EXE_scripted-agent"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // It may be gone before the last states, when it exits or crashes.
+    _ = child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(line.repeat(states).as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+/// The actions it answered with, in notation.
+fn actions(output: &Output) -> Vec<String> {
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str::<Action>(line).unwrap().to_string())
+        .collect()
+}
+
+const OPENING: &str = "# Whatever the state.\ntake r g u\n\nreserve 3.0\n";
+
+#[test]
+fn plays_the_script_then_skips() {
+    let path = script("skip", OPENING);
+    let output = run(&["--script", path.to_str().unwrap()], 4);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        actions(&output),
+        ["take r g u", "reserve 3.0", "skip", "skip"]
+    );
+}
+
+#[test]
+fn then_exit_quits_once_the_script_is_played() {
+    let path = script("exit", OPENING);
+    let output = run(&["--script", path.to_str().unwrap(), "--then", "exit"], 4);
+    assert_eq!(output.status.code(), Some(0), "{output:?}");
+    assert_eq!(actions(&output), ["take r g u", "reserve 3.0"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("script over after 2 moves"), "{stderr}");
+}
+
+#[test]
+fn sleeps_before_each_move() {
+    let path = script("sleep", OPENING);
+    let started = Instant::now();
+    let output = run(
+        &["--script", path.to_str().unwrap(), "--sleep-ms", "150"],
+        2,
+    );
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(actions(&output).len(), 2);
+    assert!(started.elapsed() >= Duration::from_millis(300));
+}
+
+#[test]
+fn crashes_after_its_moves() {
+    let path = script("crash", OPENING);
+    let output = run(
+        &["--script", path.to_str().unwrap(), "--crash-after", "1"],
+        3,
+    );
+    assert_eq!(output.status.code(), Some(7), "{output:?}");
+    assert_eq!(actions(&output), ["take r g u"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr, "crashing after 1 moves\n");
+}
+
+#[test]
+fn invalid_after_its_moves() {
+    let path = script("invalid", OPENING);
+    let output = run(
+        &["--script", path.to_str().unwrap(), "--invalid-after", "1"],
+        3,
+    );
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(actions(&output), ["take r g u", "buy 1.9", "buy 1.9"]);
+}
+
+#[test]
+fn bad_scripts_and_arguments_fail_before_reading() {
+    let path = script("bad", "take r g u\ntake r r r\n");
+    let output = run(&["--script", path.to_str().unwrap()], 0);
+    assert!(!output.status.success());
+    let error = String::from_utf8(output.stderr).unwrap();
+    assert!(error.contains("Line 2"), "{error}");
+
+    for (args, expected) in [
+        (&[][..], "usage: scripted-agent --script <file>"),
+        (&["--script"][..], "--script needs a file"),
+        (&["--then", "crash"][..], "Unknown --then crash"),
+        (
+            &["--crash-after", "soon"][..],
+            "--crash-after needs a number",
+        ),
+        (
+            &["--protocol", "messages"][..],
+            "only speaks the plain protocol",
+        ),
+        (&["--dance"][..], "Unknown argument --dance, usage: "),
+    ] {
+        let output = run(args, 0);
+        assert!(!output.status.success(), "{args:?}");
+        let error = String::from_utf8(output.stderr).unwrap();
+        assert!(error.contains(expected), "{args:?}: {error}");
+    }
+}
@@ -0,0 +1,598 @@
+//! `splendor-ai arena <dir> [options]`: a host for a race that runs until it
+//! is stopped. Contestants put their agents in the submissions directory
+//! `<dir>`, and the arena keeps playing games among them, rating each game
+//! like a tournament does.
+//!
+//! A submission is a program, run as it is, or a manifest `<name>.agent`
+//! with a line `command = <command line>` and maybe `protocol = messages`.
+//! Either way its agent goes by the file name without the extension. Hidden
+//! files and those neither executable nor manifests are left alone.
+//!
+//! The directory is scanned every `--interval` seconds (30 by default). A
+//! new submission has to pass the positions of `splendor-ai test-agent`
+//! before it plays, and one that fails is listed with why until it is
+//! replaced. Replacing a file starts its agent over, tested again and rated
+//! from scratch, and removing it retires the agent from the leaderboard.
+//!
+//! Each batch of games pairs the agent with the fewest games against the
+//! one rated closest to it, then the next of those left, and so on, and
+//! plays each pair twice on the same board with the seats swapped.
+//! `--jobs` games run at once. An agent that crashes, times out or plays an
+//! invalid action loses the game, and the arena goes on.
+//!
+//! Everything is kept in `--data <dir>` (`arena` by default), so a restarted
+//! arena goes on where it stopped: `entries.json` with the submissions seen
+//! and whether they were admitted, `ratings.json` (see [`elo`]), `games.csv`
+//! with a row for each game (see [`stats`]) and the leaderboard as
+//! `leaderboard.md` and `leaderboard.json`, written again after every game.
+//! `--serve <addr>` serves those two at `/` and `/leaderboard.json`.
+//!
+//! `--dry-run` scans once and prints which submissions would be tested and
+//! retired and the next `--games` pairs (10 by default) the scheduler would
+//! pick, counting each as played, without starting an agent or writing a
+//! file. Otherwise `--games <n>` stops after that many games.
+//!
+//! `--seed`, the limits of [`limits`] and the other settings of each game
+//! work like for a tournament.
+
+use std::{
+    path::Path,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    conformance,
+    elo::{self, Ratings},
+    number_arg, spectator, stats,
+    tournament::{self, Settings, MAX_AGENT_PROCESSES},
+};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+/// The pairs `--dry-run` prints without `--games`.
+const DRY_RUN_GAMES: usize = 10;
+/// The extension of a manifest.
+const MANIFEST: &str = "agent";
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Entry {
+    name: String,
+    /// The submitted file.
+    path: String,
+    command: String,
+    messages: bool,
+    /// The size of the file and when it was modified, in nanoseconds since
+    /// the Unix epoch, which change when it is replaced.
+    version: (u64, u64),
+    /// Why it wasn't admitted, if it wasn't.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rejected: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Standing {
+    rank: usize,
+    name: String,
+    rating: f64,
+    uncertainty: f64,
+    games: u32,
+}
+
+#[derive(Serialize)]
+struct Rejected {
+    name: String,
+    reason: String,
+}
+
+#[derive(Serialize)]
+struct Leaderboard {
+    /// Seconds since the Unix epoch.
+    updated: u64,
+    agents: Vec<Standing>,
+    rejected: Vec<Rejected>,
+}
+
+/// The leaderboard as served, in markdown and in JSON.
+type Served = Arc<RwLock<(String, String)>>;
+
+struct Arena {
+    dir: String,
+    data: String,
+    /// For the positions new submissions are tested in.
+    move_timeout: Duration,
+    dry_run: bool,
+    entries: Vec<Entry>,
+    served: Served,
+}
+
+#[cfg(unix)]
+fn executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn executable(_: &std::fs::Metadata) -> bool {
+    true
+}
+
+/// The submissions in `dir`, not tested yet.
+fn scan(dir: &str) -> anyhow::Result<Vec<Entry>> {
+    let files = std::fs::read_dir(dir).with_context(|| format!("Failed to read {dir}"))?;
+    let mut paths: Vec<_> = files.flatten().map(|file| file.path()).collect();
+    paths.sort();
+    let mut found: Vec<Entry> = vec![];
+    for path in paths {
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        let manifest = path.extension().is_some_and(|e| e == MANIFEST);
+        if name.starts_with('.') || !metadata.is_file() || !(manifest || executable(&metadata)) {
+            continue;
+        }
+        let modified = metadata.modified().ok();
+        let modified = modified.and_then(|t| t.duration_since(UNIX_EPOCH).ok());
+        let mut entry = Entry {
+            name: name.to_owned(),
+            path: path.display().to_string(),
+            command: path.display().to_string(),
+            messages: false,
+            version: (metadata.len(), modified.map_or(0, |d| d.as_nanos() as u64)),
+            rejected: None,
+        };
+        if manifest {
+            if let Err(e) = read_manifest(&path, &mut entry) {
+                entry.rejected = Some(format!("{e:#}"));
+            }
+        }
+        if found.iter().any(|other| other.name == entry.name) {
+            entry.rejected = Some(format!("another submission is called {}", entry.name));
+        }
+        found.push(entry);
+    }
+    Ok(found)
+}
+
+/// Reads the `command` and `protocol` of the manifest at `path` into `entry`.
+fn read_manifest(path: &Path, entry: &mut Entry) -> anyhow::Result<()> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut command = None;
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            bail!("line {} is not `key = value`", n + 1);
+        };
+        match (key.trim(), value.trim()) {
+            ("command", value) => command = Some(value.to_owned()),
+            ("protocol", "states") => entry.messages = false,
+            ("protocol", "messages") => entry.messages = true,
+            ("protocol", value) => {
+                bail!(
+                    "line {}: protocol needs states or messages, not {value}",
+                    n + 1
+                )
+            }
+            (key, _) => bail!("line {}: unknown key {key}", n + 1),
+        }
+    }
+    entry.command = command.context("the manifest has no command")?;
+    Ok(())
+}
+
+/// Picks up to `count` pairs of the agents with these ratings and games,
+/// none in two pairs: the agent with the fewest games against the one rated
+/// closest to it, then the same for those left.
+fn pairings(agents: &[(f64, u32)], count: usize) -> Vec<(usize, usize)> {
+    let mut free: Vec<usize> = (0..agents.len()).collect();
+    let mut pairs = vec![];
+    while pairs.len() < count && free.len() >= 2 {
+        let (k, _) = free
+            .iter()
+            .enumerate()
+            .min_by_key(|&(_, &i)| agents[i].1)
+            .unwrap();
+        let a = free.remove(k);
+        let gap = |i: usize| (agents[i].0 - agents[a].0).abs();
+        let (k, _) = free
+            .iter()
+            .enumerate()
+            .min_by(|&(_, &x), &(_, &y)| {
+                gap(x)
+                    .total_cmp(&gap(y))
+                    .then(agents[x].1.cmp(&agents[y].1))
+            })
+            .unwrap();
+        let b = free.remove(k);
+        pairs.push((a, b));
+    }
+    pairs
+}
+
+impl Arena {
+    fn file(&self, name: &str) -> String {
+        format!("{}/{name}", self.data)
+    }
+
+    fn admitted(&self) -> Vec<&Entry> {
+        self.entries
+            .iter()
+            .filter(|e| e.rejected.is_none())
+            .collect()
+    }
+
+    /// The rating and games of each of `entries`.
+    fn ratings(&self, entries: &[&Entry]) -> anyhow::Result<Vec<(f64, u32)>> {
+        let ratings = elo::load(&self.file("ratings.json"))?;
+        let rating = |name| ratings.get(name).cloned().unwrap_or_default();
+        Ok(entries
+            .iter()
+            .map(|e| (rating(&e.name).rating, rating(&e.name).games))
+            .collect())
+    }
+
+    /// Scans the submissions, testing those that are new or replaced and
+    /// retiring those removed. Returns whether anything changed.
+    fn scan(&mut self) -> anyhow::Result<bool> {
+        let found = scan(&self.dir)?;
+        let mut changed = false;
+        let mut reset = vec![];
+        for old in &self.entries {
+            if !found.iter().any(|e| e.path == old.path) {
+                match self.dry_run {
+                    true => println!("Would retire {}", old.name),
+                    false => println!("Retired {}", old.name),
+                }
+                reset.push(old.name.clone());
+                changed = true;
+            }
+        }
+        let mut entries = vec![];
+        for mut entry in found {
+            let old = self.entries.iter().find(|e| e.path == entry.path);
+            if let Some(old) = old.filter(|old| old.version == entry.version) {
+                entries.push(old.clone());
+                continue;
+            }
+            changed = true;
+            reset.push(entry.name.clone());
+            if old.is_some() {
+                println!("{} was replaced", entry.name);
+            }
+            if self.dry_run {
+                match &entry.rejected {
+                    None => println!("Would test {}: {}", entry.name, entry.command),
+                    Some(reason) => println!("Would reject {}: {reason}", entry.name),
+                }
+            } else {
+                if entry.rejected.is_none() {
+                    let timeout = self.move_timeout;
+                    if let Err(e) = conformance::passes(&entry.command, entry.messages, timeout) {
+                        entry.rejected = Some(format!("{e:#}"));
+                    }
+                }
+                match &entry.rejected {
+                    None => println!("Admitted {}", entry.name),
+                    Some(reason) => println!("Rejected {}: {reason}", entry.name),
+                }
+            }
+            entries.push(entry);
+        }
+        self.entries = entries;
+        if changed && !self.dry_run {
+            elo::modify(&self.file("ratings.json"), |ratings| {
+                for name in &reset {
+                    ratings.remove(name);
+                }
+            })?;
+            let json = serde_json::to_string_pretty(&self.entries)?;
+            let path = self.file("entries.json");
+            let temporary = format!("{path}.tmp");
+            std::fs::write(&temporary, json + "\n")
+                .with_context(|| format!("Failed to write {temporary}"))?;
+            std::fs::rename(&temporary, &path)
+                .with_context(|| format!("Failed to write {path}"))?;
+        }
+        Ok(changed)
+    }
+
+    /// Writes the leaderboard for `ratings` and serves it.
+    fn publish(&self, ratings: &Ratings) -> anyhow::Result<()> {
+        let mut agents: Vec<_> = self
+            .admitted()
+            .into_iter()
+            .map(|e| (e, ratings.get(&e.name).cloned().unwrap_or_default()))
+            .collect();
+        agents.sort_by(|a, b| b.1.rating.total_cmp(&a.1.rating));
+        let leaderboard = Leaderboard {
+            updated: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            agents: agents
+                .into_iter()
+                .enumerate()
+                .map(|(rank, (e, r))| Standing {
+                    rank: rank + 1,
+                    name: e.name.clone(),
+                    rating: r.rating,
+                    uncertainty: r.uncertainty(),
+                    games: r.games,
+                })
+                .collect(),
+            rejected: self
+                .entries
+                .iter()
+                .filter_map(|e| {
+                    let reason = e.rejected.clone()?;
+                    Some(Rejected {
+                        name: e.name.clone(),
+                        reason,
+                    })
+                })
+                .collect(),
+        };
+        let mut markdown = "# Leaderboard\n\n| Rank | Agent | Rating | ± | Games |\n\
+                            |---:|---|---:|---:|---:|\n"
+            .to_owned();
+        for s in &leaderboard.agents {
+            markdown += &format!(
+                "| {} | {} | {:.0} | {:.0} | {} |\n",
+                s.rank, s.name, s.rating, s.uncertainty, s.games
+            );
+        }
+        if !leaderboard.rejected.is_empty() {
+            markdown += "\nNot admitted:\n\n";
+            for r in &leaderboard.rejected {
+                markdown += &format!("- {}: {}\n", r.name, r.reason);
+            }
+        }
+        let json = serde_json::to_string_pretty(&leaderboard)? + "\n";
+        for (name, text) in [("leaderboard.md", &markdown), ("leaderboard.json", &json)] {
+            let path = self.file(name);
+            std::fs::write(&path, text).with_context(|| format!("Failed to write {path}"))?;
+        }
+        *self.served.write().unwrap_or_else(|e| e.into_inner()) = (markdown, json);
+        Ok(())
+    }
+
+    /// Prints the next `games` pairs the scheduler would pick.
+    fn dry_run(&self, games: usize) -> anyhow::Result<()> {
+        let admitted: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|e| e.rejected.is_none())
+            .collect();
+        let mut agents = self.ratings(&admitted)?;
+        let mut picked = 0;
+        while picked < games {
+            let pairs = pairings(&agents, games - picked);
+            if pairs.is_empty() {
+                println!("Fewer than two agents to play");
+                break;
+            }
+            for (a, b) in pairs {
+                println!("Would play {} vs {}", admitted[a].name, admitted[b].name);
+                agents[a].1 += 2;
+                agents[b].1 += 2;
+                picked += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn main(mut argv: impl Iterator<Item = String>) {
+    let mut dir = None;
+    let mut data = "arena".to_owned();
+    let mut interval = DEFAULT_INTERVAL;
+    let mut jobs = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut seed = rand::random();
+    let mut games = None;
+    let mut serve = None;
+    let mut dry_run = false;
+    let mut settings = Settings::default();
+    while let Some(arg) = argv.next() {
+        match settings.flag(&arg, &mut argv) {
+            Some(true) => continue,
+            Some(false) => (),
+            None => return,
+        }
+        if arg == "--data" {
+            let Some(path) = argv.next() else {
+                println!("--data needs a directory");
+                return;
+            };
+            data = path;
+        } else if arg == "--interval" {
+            let x = number_arg(&mut argv, &arg);
+            let Some(x) = x.and_then(|x| Duration::try_from_secs_f64(x).ok()) else {
+                println!("--interval needs a positive number of seconds");
+                return;
+            };
+            interval = x;
+        } else if arg == "--jobs" {
+            match number_arg(&mut argv, &arg) {
+                Some(0) => {
+                    println!("--jobs needs at least 1");
+                    return;
+                }
+                Some(n) => jobs = n,
+                None => return,
+            }
+        } else if arg == "--seed" {
+            let Some(x) = number_arg(&mut argv, &arg) else {
+                return;
+            };
+            seed = x;
+        } else if arg == "--games" {
+            let Some(x) = number_arg(&mut argv, &arg) else {
+                return;
+            };
+            games = Some(x);
+        } else if arg == "--serve" {
+            let Some(address) = argv.next() else {
+                println!("--serve needs an address");
+                return;
+            };
+            serve = Some(address);
+        } else if arg == "--dry-run" {
+            dry_run = true;
+        } else if dir.is_none() {
+            dir = Some(arg);
+        } else {
+            println!("Unknown argument {arg}, the arena takes one submissions directory");
+            return;
+        }
+    }
+    let Some(dir) = dir else {
+        println!("Usage: splendor-ai arena <dir> [--data <dir>] [--interval <seconds>] [--jobs <n>] [--games <n>] [--serve <addr>] [--dry-run]");
+        return;
+    };
+    if !Path::new(&dir).is_dir() {
+        println!("{dir} is not a directory");
+        return;
+    }
+    if !dry_run {
+        if let Err(e) = std::fs::create_dir_all(&data) {
+            println!("Failed to create {data}: {e}");
+            return;
+        }
+    }
+    let entries_path = format!("{data}/entries.json");
+    let entries = match std::fs::read_to_string(&entries_path) {
+        Ok(json) => match serde_json::from_str(&json) {
+            Ok(entries) => entries,
+            Err(e) => {
+                println!("{entries_path} is not a list of arena entries: {e}");
+                return;
+            }
+        },
+        Err(_) => vec![],
+    };
+    let mut arena = Arena {
+        dir,
+        data,
+        move_timeout: settings.move_timeout,
+        dry_run,
+        entries,
+        served: Served::default(),
+    };
+    if dry_run {
+        if let Err(e) = arena
+            .scan()
+            .and_then(|_| arena.dry_run(games.unwrap_or(DRY_RUN_GAMES)))
+        {
+            println!("{e:#}");
+        }
+        return;
+    }
+    if let Some(address) = serve {
+        let served = arena.served.clone();
+        let page = move |path: &str| {
+            let (markdown, json) = &*served.read().unwrap_or_else(|e| e.into_inner());
+            match path {
+                "/" => Some(("text/markdown; charset=utf-8", markdown.clone())),
+                "/leaderboard.json" => Some(("application/json", json.clone())),
+                _ => None,
+            }
+        };
+        match spectator::serve(&address, page) {
+            Ok(address) => println!("Leaderboard on http://{address}"),
+            Err(e) => {
+                println!("Failed to serve on {address}: {e}");
+                return;
+            }
+        }
+    }
+    let jobs = jobs.min(MAX_AGENT_PROCESSES / 2);
+    let ratings_path = arena.file("ratings.json");
+    let games_path = arena.file("games.csv");
+    // Games of earlier runs keep their numbers.
+    let mut number = stats::read(&games_path).map_or(0, |rows| rows.len());
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut played = 0;
+    let mut scanned: Option<Instant> = None;
+    println!(
+        "Arena seed {seed}, scanning {} every {interval:?}",
+        arena.dir
+    );
+    while games.is_none_or(|games| played < games) {
+        if scanned.is_none_or(|t| t.elapsed() >= interval) {
+            let first = scanned.is_none();
+            scanned = Some(Instant::now());
+            let changed = arena.scan().unwrap_or_else(|e| {
+                println!("{e:#}");
+                false
+            });
+            if changed || first {
+                let published = elo::load(&ratings_path).and_then(|r| arena.publish(&r));
+                if let Err(e) = published {
+                    println!("{e:#}");
+                }
+            }
+        }
+        let admitted = arena.admitted();
+        let agents = match arena.ratings(&admitted) {
+            Ok(agents) => agents,
+            Err(e) => {
+                println!("{e:#}");
+                vec![]
+            }
+        };
+        let pairs = pairings(&agents, jobs.div_ceil(2));
+        if pairs.is_empty() {
+            std::thread::sleep(interval.saturating_sub(scanned.map_or(interval, |t| t.elapsed())));
+            continue;
+        }
+        let names: Vec<_> = admitted.iter().map(|e| e.name.clone()).collect();
+        let commands: Vec<_> = admitted.iter().map(|e| e.command.clone()).collect();
+        settings.messages = admitted.iter().map(|e| e.messages).collect();
+        let mut schedule = vec![];
+        for (a, b) in pairs {
+            let seed = rng.gen();
+            schedule.push((vec![a, b], seed));
+            schedule.push((vec![b, a], seed));
+        }
+        tournament::play_all(
+            &commands,
+            &names,
+            &schedule,
+            0,
+            &settings,
+            jobs,
+            |_, record, _| {
+                number += 1;
+                played += 1;
+                print!("Game {number}: ");
+                record.print(&names);
+                if let Err(e) = stats::append(&games_path, &record.stats_row(&commands, &names)) {
+                    println!("{e:#}");
+                }
+                let [a, b] = [record.seats[0], record.seats[1]];
+                let mut ratings = Ratings::new();
+                let rate = |r: &mut Ratings| {
+                    elo::update(r, &names[a], &names[b], record.score(a));
+                    ratings = r.clone();
+                };
+                if let Err(e) = elo::modify(&ratings_path, rate) {
+                    println!("{e:#}");
+                }
+                if let Err(e) = arena.publish(&ratings) {
+                    println!("{e:#}");
+                }
+                games.is_none_or(|games| played < games)
+            },
+        );
+    }
+}
@@ -0,0 +1,401 @@
+//! The command line of a game, read by [`Args::parse`] without starting or
+//! loading anything, so it can be checked on its own.
+//!
+//! Anything that is not a flag is an agent. `--format`, `--protocol`,
+//! `--name` and `--agent-time-budget` apply to the next agent only.
+
+use std::{path::PathBuf, str::FromStr, time::Duration};
+
+use anyhow::{bail, Context};
+use game_def::{cards::CardSet, GameConfig, Handicap, ObjectiveMode};
+
+/// How many times each agent may be started again with `--on-crash restart`.
+pub const DEFAULT_RESTARTS: u32 = 3;
+
+/// How long an AI may think about a move unless `--move-timeout` says otherwise.
+pub const DEFAULT_MOVE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Rounds after which a game is decided by the scores unless `--max-rounds`
+/// says otherwise, so agents that never buy can't play forever.
+pub const DEFAULT_MAX_ROUNDS: usize = 100;
+
+/// The flags of the agent process limits, kept with their values for
+/// `limits::flag` to read.
+pub const LIMIT_FLAGS: [&str; 3] = ["--agent-mem", "--agent-cpus", "--agent-sandbox"];
+
+/// What happens when an AI sends an invalid action, or none at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnInvalid {
+    /// The AI resigns and the others play on, the opponent wins a two player game.
+    Forfeit,
+    /// The game stops without a result, to look at what went wrong.
+    Terminate,
+}
+
+/// An agent on the command line, with the flags given for it alone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentArg {
+    pub command: String,
+    pub msgpack: bool,
+    pub messages: bool,
+    pub name: Option<String>,
+    /// Instead of `--time-budget`.
+    pub time_budget: Option<Duration>,
+}
+
+impl AgentArg {
+    /// `command` with none of the flags for it given.
+    pub fn new(command: String) -> AgentArg {
+        AgentArg {
+            command,
+            msgpack: false,
+            messages: false,
+            name: None,
+            time_budget: None,
+        }
+    }
+}
+
+/// Everything the command line of a game asks for.
+#[derive(Debug)]
+pub struct Args {
+    pub assist: bool,
+    pub paranoid: bool,
+    pub verbose: bool,
+    pub config: GameConfig,
+    /// Coins of each color for the number of agents given.
+    pub coins: Option<usize>,
+    /// The commands of the observers, started in order.
+    pub observers: Vec<String>,
+    pub observers_see_public: bool,
+    pub move_timeout: Duration,
+    pub time_budget: Option<Duration>,
+    pub increment: Duration,
+    pub record: Option<String>,
+    pub export_notation: Option<String>,
+    pub stats_file: Option<String>,
+    pub seed: Option<u64>,
+    /// A seat number, a name or `random`.
+    pub first: String,
+    pub on_invalid: OnInvalid,
+    pub max_restarts: u32,
+    pub invalid_retries: u32,
+    pub agent_log_dir: Option<PathBuf>,
+    pub transcript_dir: Option<PathBuf>,
+    pub reconnect_grace: Duration,
+    pub serve: Option<String>,
+    pub emit_events: Option<String>,
+    pub quiet: bool,
+    pub tui: bool,
+    pub compact: bool,
+    pub full_board_every: usize,
+    pub players: Option<usize>,
+    /// Takes the `--format` and `--protocol` given before it, but leaves
+    /// `--name` and `--agent-time-budget` to the next agent.
+    pub hint_agent: Option<AgentArg>,
+    pub allow_undo_ai: bool,
+    pub delay: Option<Duration>,
+    pub step: bool,
+    pub clear: bool,
+    pub hotseat: bool,
+    /// `--output json`.
+    pub json: bool,
+    /// `auto`, `always` or `never`.
+    pub color: String,
+    /// The path of a saved game, and whether it is `--load-redacted`.
+    pub load: Option<(String, bool)>,
+    /// The [`LIMIT_FLAGS`] given, each followed by its value.
+    pub limits: Vec<String>,
+    pub agents: Vec<AgentArg>,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Args {
+            assist: true,
+            paranoid: false,
+            verbose: false,
+            config: GameConfig {
+                max_rounds: Some(DEFAULT_MAX_ROUNDS),
+                ..GameConfig::default()
+            },
+            coins: None,
+            observers: vec![],
+            observers_see_public: false,
+            move_timeout: DEFAULT_MOVE_TIMEOUT,
+            time_budget: None,
+            increment: Duration::ZERO,
+            record: None,
+            export_notation: None,
+            stats_file: None,
+            seed: None,
+            first: "random".to_owned(),
+            on_invalid: OnInvalid::Forfeit,
+            max_restarts: 0,
+            invalid_retries: 0,
+            agent_log_dir: None,
+            transcript_dir: None,
+            reconnect_grace: Duration::ZERO,
+            serve: None,
+            emit_events: None,
+            quiet: false,
+            tui: false,
+            compact: false,
+            full_board_every: 0,
+            players: None,
+            hint_agent: None,
+            allow_undo_ai: false,
+            delay: None,
+            step: false,
+            clear: true,
+            hotseat: false,
+            json: false,
+            color: "auto".to_owned(),
+            load: None,
+            limits: vec![],
+            agents: vec![],
+        }
+    }
+}
+
+impl Args {
+    /// Reads the arguments after the program name, once a subcommand and a
+    /// run config were ruled out.
+    pub fn parse(argv: impl IntoIterator<Item = String>) -> anyhow::Result<Args> {
+        let mut args = Args::default();
+        // The flags for the next agent.
+        let mut next = AgentArg::new(String::new());
+        let mut argv = argv.into_iter();
+        while let Some(arg) = argv.next() {
+            let argv = &mut argv;
+            match arg.as_str() {
+                "--no-assist" => args.assist = false,
+                "--paranoid" => args.paranoid = true,
+                "--verbose" => args.verbose = true,
+                "--load-state" | "--load-redacted" => {
+                    let path = value(argv, &arg, "a path")?;
+                    args.load = Some((path, arg == "--load-redacted"));
+                }
+                "--delay" => {
+                    let delay = argv.next().as_deref().and_then(parse_delay);
+                    args.delay = Some(delay.context("--delay needs a time like 500ms or 2s")?);
+                }
+                "--step" => args.step = true,
+                "--hotseat" => args.hotseat = true,
+                "--no-clear" => args.clear = false,
+                "--allow-undo-ai" => args.allow_undo_ai = true,
+                "--render" => {
+                    args.compact = match argv.next().as_deref() {
+                        Some("full") => false,
+                        Some("compact") => true,
+                        _ => bail!("--render needs full or compact"),
+                    }
+                }
+                "--full-board-every" => args.full_board_every = number(argv, &arg)?,
+                "--quiet" => args.quiet = true,
+                "--tui" => args.tui = true,
+                "--output" => {
+                    args.json = match argv.next().as_deref() {
+                        Some("text") => false,
+                        Some("json") => true,
+                        _ => bail!("--output needs text or json"),
+                    }
+                }
+                "--color" => {
+                    args.color = argv
+                        .next()
+                        .filter(|mode| ["auto", "always", "never"].contains(&mode.as_str()))
+                        .context("--color needs auto, always or never")?
+                }
+                "--target-score" => args.config.target_score = number(argv, &arg)?,
+                "--coins" => args.coins = Some(number(argv, &arg)?),
+                "--handicap" => {
+                    let (seat, handicap) = parse_handicap(&argv.next().unwrap_or_default())?;
+                    let both = args.config.handicaps.entry(seat).or_default();
+                    both.coins += &handicap.coins;
+                    both.score += handicap.score;
+                    both.time_ms += handicap.time_ms;
+                }
+                "--max-rounds" => args.config.max_rounds = Some(number(argv, &arg)?),
+                "--wilds" => args.config.wilds = number(argv, &arg)?,
+                "--move-timeout" => args.move_timeout = seconds(argv, &arg)?,
+                "--time-budget" => args.time_budget = Some(seconds(argv, &arg)?),
+                "--increment" => args.increment = seconds(argv, &arg)?,
+                "--on-invalid" => {
+                    args.on_invalid = match argv.next().as_deref() {
+                        Some("forfeit") => OnInvalid::Forfeit,
+                        Some("terminate") => OnInvalid::Terminate,
+                        _ => bail!("--on-invalid needs forfeit or terminate"),
+                    }
+                }
+                "--on-crash" => {
+                    let restarts = match argv.next().as_deref() {
+                        Some("forfeit") => Some(0),
+                        Some("restart") => Some(DEFAULT_RESTARTS),
+                        Some(x) => x.strip_prefix("restart:").and_then(|n| n.parse().ok()),
+                        None => None,
+                    };
+                    args.max_restarts =
+                        restarts.context("--on-crash needs forfeit, restart or restart:<times>")?;
+                }
+                "--invalid-retries" => args.invalid_retries = number(argv, &arg)?,
+                "--agent-log-dir" => {
+                    args.agent_log_dir = Some(value(argv, &arg, "a directory")?.into())
+                }
+                "--transcript-dir" => {
+                    args.transcript_dir = Some(value(argv, &arg, "a directory")?.into())
+                }
+                "--reconnect-grace" => args.reconnect_grace = seconds(argv, &arg)?,
+                "--serve" => args.serve = Some(value(argv, &arg, "an address")?),
+                "--emit-events" => {
+                    args.emit_events = Some(value(argv, &arg, "a path, or - for stdout")?)
+                }
+                "--players" => match number(argv, &arg)? {
+                    n @ 2..=4 => args.players = Some(n),
+                    n => bail!("--players must be 2, 3 or 4, not {n}"),
+                },
+                "--seed" => args.seed = Some(number(argv, &arg)?),
+                "--first" => args.first = value(argv, &arg, "a seat number, a name or random")?,
+                "--record" => args.record = Some(value(argv, &arg, "a path")?),
+                "--export-notation" => args.export_notation = Some(value(argv, &arg, "a path")?),
+                "--stats-file" => args.stats_file = Some(value(argv, &arg, "a path")?),
+                "--variant" => {
+                    args.config.objective_mode = match argv.next().as_deref() {
+                        Some("classic") => ObjectiveMode::Nobles,
+                        Some("cities") => ObjectiveMode::Cities,
+                        _ => bail!("--variant needs classic or cities"),
+                    }
+                }
+                "--cards" => {
+                    let path = value(argv, &arg, "a path")?;
+                    args.config.cards = Some(CardSet::load(&path)?.into());
+                }
+                "--format" => {
+                    next.msgpack = match argv.next().as_deref() {
+                        Some("json") => false,
+                        Some("msgpack") => true,
+                        _ => bail!("--format needs json or msgpack"),
+                    }
+                }
+                "--protocol" => {
+                    next.messages = match argv.next().as_deref() {
+                        Some("states") => false,
+                        Some("messages") => true,
+                        _ => bail!("--protocol needs states or messages"),
+                    }
+                }
+                "--observer" => args.observers.push(value(argv, &arg, "a command")?),
+                "--observer-sees" => {
+                    args.observers_see_public = match argv.next().as_deref() {
+                        Some("hidden") => false,
+                        Some("public") => true,
+                        _ => bail!("--observer-sees needs hidden or public"),
+                    }
+                }
+                "--hint-agent" => {
+                    args.hint_agent = Some(AgentArg {
+                        msgpack: std::mem::take(&mut next.msgpack),
+                        messages: std::mem::take(&mut next.messages),
+                        ..AgentArg::new(value(argv, &arg, "a command")?)
+                    });
+                }
+                flag if LIMIT_FLAGS.contains(&flag) => {
+                    args.limits.push(arg.clone());
+                    args.limits.extend(argv.next());
+                }
+                "--name" => next.name = Some(value(argv, &arg, "a name")?),
+                "--agent-time-budget" => next.time_budget = Some(seconds(argv, &arg)?),
+                _ => {
+                    next.command = arg;
+                    let next = std::mem::replace(&mut next, AgentArg::new(String::new()));
+                    args.agents.push(next);
+                }
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// The value after `flag`, described as `what` when it is missing.
+fn value(
+    argv: &mut impl Iterator<Item = String>,
+    flag: &str,
+    what: &str,
+) -> anyhow::Result<String> {
+    argv.next().with_context(|| format!("{flag} needs {what}"))
+}
+
+fn number<T: FromStr>(argv: &mut impl Iterator<Item = String>, flag: &str) -> anyhow::Result<T> {
+    argv.next()
+        .and_then(|x| x.parse().ok())
+        .with_context(|| format!("{flag} needs a number"))
+}
+
+fn seconds(argv: &mut impl Iterator<Item = String>, flag: &str) -> anyhow::Result<Duration> {
+    Duration::try_from_secs_f64(number(argv, flag)?)
+        .ok()
+        .with_context(|| format!("{flag} needs a positive number of seconds"))
+}
+
+/// `500ms`, `2s`, or a bare number of milliseconds.
+pub fn parse_delay(text: &str) -> Option<Duration> {
+    let (number, unit) = match text.strip_suffix("ms") {
+        Some(number) => (number, 0.001),
+        None => match text.strip_suffix('s') {
+            Some(number) => (number, 1.0),
+            None => (text, 0.001),
+        },
+    };
+    Duration::try_from_secs_f64(number.parse::<f64>().ok()? * unit).ok()
+}
+
+/// Reads `--handicap <seat>:<key>=<value>[:<key>=<value>...]` into the seat
+/// and the handicap. The keys are `coins` like `2w+1u`, `gold`, `score` and
+/// `time` like `+30s`.
+pub fn parse_handicap(spec: &str) -> anyhow::Result<(usize, Handicap)> {
+    let usage =
+        "--handicap needs <seat>:coins=<coins>, :gold=<n>, :score=<points> or :time=+<time>";
+    let mut parts = spec.split(':');
+    let seat = parts.next().and_then(|seat| seat.parse().ok());
+    let Some(seat) = seat else {
+        bail!("{usage}, not {spec}");
+    };
+    let bad = || format!("Bad handicap {spec}");
+    let mut handicap = Handicap::default();
+    let mut given = false;
+    for part in parts {
+        let Some((key, value)) = part.split_once('=') else {
+            bail!("{usage}, not {spec}");
+        };
+        match key {
+            "coins" => {
+                handicap.coins.gems = value
+                    .parse()
+                    .map_err(|e| anyhow::anyhow!("{}: {e}", bad()))?
+            }
+            "gold" => {
+                handicap.coins.gold = value
+                    .parse()
+                    .map_err(|e| anyhow::anyhow!("{}: {e}", bad()))?
+            }
+            "score" => {
+                handicap.score = value
+                    .parse()
+                    .map_err(|e| anyhow::anyhow!("{}: {e}", bad()))?
+            }
+            "time" => {
+                let time = value.strip_prefix('+').and_then(parse_delay);
+                let Some(time) = time else {
+                    bail!("A time handicap needs a time like +30s, not {value}");
+                };
+                handicap.time_ms = time.as_millis() as u64;
+            }
+            _ => bail!("{usage}, there is no {key}"),
+        }
+        given = true;
+    }
+    if !given {
+        bail!("{usage}, not {spec}");
+    }
+    Ok((seat, handicap))
+}
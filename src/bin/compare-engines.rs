@@ -0,0 +1,296 @@
+//! Feeds a suite of positions to two engines and reports where their moves differ.
+//!
+//! Usage: `compare-engines --suite <file> --old <engine> --new <engine>
+//! [--threshold <points>] [--budget <seconds>] [--diff-suite <file>] [--max-diff <n>]`
+//!
+//! A suite is a file with one JSON position per line: either an observation, the
+//! same lines the runner sends to agents, or a full state, which is turned into the
+//! observation of the player to move. An engine is a command, sent a bare stream of
+//! observations like the runner does, or a built-in agent like
+//! `builtin:alphabeta?depth=2`, sent the message protocol.
+//!
+//! The moves of the two engines are told apart by how the position they lead to
+//! scores for the player who made them, by the default weights of the alpha-beta
+//! agent. Moves within `--threshold` points of each other (0 by default) count as
+//! the same. `--budget` is the most seconds an engine may take for a move.
+//! `--diff-suite` writes the positions where the engines differ as a suite of their
+//! own, to compare the next engine on them only.
+//!
+//! The exit code is non-zero when more than `--max-diff` positions differ, so this
+//! can be used directly as a `git bisect run` script. Errors exit with 125, which
+//! tells `git bisect run` to skip the commit.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{ExitCode, Stdio},
+    sync::mpsc::{channel, Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+use alpha_beta_agent::Weights;
+use anyhow::{bail, Context};
+use game_def::{Action, Observation, RunnerMessage, State, PROTOCOL_VERSION};
+use rand::{rngs::StdRng, SeedableRng};
+
+// The runner's built-in agents, so they can be compared without a program.
+#[allow(dead_code)]
+#[path = "../builtin.rs"]
+mod builtin;
+
+const USAGE: &str = "Usage: compare-engines --suite <file> --old <engine> --new <engine> [--threshold <points>] [--budget <seconds>] [--diff-suite <file>] [--max-diff <n>]";
+
+/// What the command line asks for.
+struct Options {
+    suite: String,
+    old: String,
+    new: String,
+    threshold: i32,
+    budget: Option<Duration>,
+    diff_suite: Option<String>,
+    max_diff: usize,
+}
+
+impl Options {
+    fn parse(args: impl IntoIterator<Item = String>) -> anyhow::Result<Options> {
+        let (mut suite, mut old, mut new) = (None, None, None);
+        let mut options = Options {
+            suite: String::new(),
+            old: String::new(),
+            new: String::new(),
+            threshold: 0,
+            budget: None,
+            diff_suite: None,
+            max_diff: 0,
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().with_context(|| format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--suite" => suite = Some(value()?),
+                "--old" => old = Some(value()?),
+                "--new" => new = Some(value()?),
+                "--threshold" => {
+                    options.threshold = value()?
+                        .parse()
+                        .ok()
+                        .filter(|&t| t >= 0)
+                        .context("--threshold needs a number of points")?
+                }
+                "--budget" => {
+                    let seconds = value()?
+                        .parse()
+                        .ok()
+                        .and_then(|x| Duration::try_from_secs_f64(x).ok())
+                        .context("--budget needs a positive number of seconds")?;
+                    options.budget = Some(seconds);
+                }
+                "--diff-suite" => options.diff_suite = Some(value()?),
+                "--max-diff" => {
+                    options.max_diff = value()?.parse().context("--max-diff needs a number")?
+                }
+                _ => bail!("Unknown argument {arg}\n{USAGE}"),
+            }
+        }
+        let (Some(suite), Some(old), Some(new)) = (suite, old, new) else {
+            bail!("{USAGE}");
+        };
+        Ok(Options {
+            suite,
+            old,
+            new,
+            ..options
+        })
+    }
+}
+
+struct Engine {
+    name: String,
+    writer: Box<dyn Write + Send>,
+    /// Each action the engine writes, read on a separate thread so a slow
+    /// engine can be timed out.
+    replies: Receiver<anyhow::Result<Action>>,
+    /// Talks the message protocol instead of a bare stream of observations.
+    messages: bool,
+    /// Whether it was sent [`RunnerMessage::Init`] yet.
+    started: bool,
+}
+
+impl Engine {
+    fn start(spec: &str) -> anyhow::Result<Self> {
+        let (writer, reader, messages): (Box<dyn Write + Send>, Box<dyn BufRead + Send>, _) =
+            match spec.strip_prefix("builtin:") {
+                Some(builtin) => {
+                    let (_, writer, reader) = builtin::Builtin::parse(builtin)?.start()?;
+                    (Box::new(writer), Box::new(reader), true)
+                }
+                None => {
+                    let words = shlex::split(spec)
+                        .filter(|words| !words.is_empty())
+                        .with_context(|| format!("`{spec}` is not a valid command line"))?;
+                    let mut process = std::process::Command::new(&words[0])
+                        .args(&words[1..])
+                        .stdin(Stdio::piped())
+                        .stdout(Stdio::piped())
+                        .spawn()
+                        .with_context(|| format!("Failed to start {spec}"))?;
+                    let reader = BufReader::new(process.stdout.take().unwrap());
+                    (
+                        Box::new(process.stdin.take().unwrap()),
+                        Box::new(reader),
+                        false,
+                    )
+                }
+            };
+        let (send, replies) = channel();
+        std::thread::spawn(move || {
+            for line in reader.lines() {
+                let reply = line
+                    .context("Failed to read a move")
+                    .and_then(|line| Ok(serde_json::from_str(&line)?));
+                if send.send(reply).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Engine {
+            name: spec.to_owned(),
+            writer,
+            replies,
+            messages,
+            started: false,
+        })
+    }
+
+    /// The move the engine plays in `observation`, within `budget`.
+    fn best_move(
+        &mut self,
+        observation: &Observation,
+        budget: Option<Duration>,
+    ) -> anyhow::Result<Action> {
+        let time_left_ms = budget.map(|b| b.as_millis() as u64);
+        if self.messages && !self.started {
+            let init = RunnerMessage::Init {
+                protocol_version: PROTOCOL_VERSION,
+                player_index: observation.state.turn,
+                config: observation.state.config.clone(),
+                new_games: false,
+            };
+            writeln!(self.writer, "{}", serde_json::to_string(&init)?)?;
+            self.started = true;
+        }
+        let message = match self.messages {
+            true => serde_json::to_string(&RunnerMessage::MoveRequest {
+                observation: Box::new(observation.clone()),
+                time_left_ms,
+            })?,
+            false => observation.json(),
+        };
+        writeln!(self.writer, "{message}")?;
+        self.writer.flush()?;
+        let reply = match budget {
+            Some(budget) => self.replies.recv_timeout(budget),
+            None => self
+                .replies
+                .recv()
+                .map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match reply {
+            Ok(action) => action.with_context(|| format!("{} sent a bad move", self.name)),
+            Err(RecvTimeoutError::Timeout) => {
+                bail!("{} took more than {budget:?} for a move", self.name)
+            }
+            Err(RecvTimeoutError::Disconnected) => bail!("{} closed its output", self.name),
+        }
+    }
+}
+
+/// What `action` leads to for the player to move, by the default weights of
+/// the alpha-beta agent. The cards nobody can see are dealt the same way for
+/// every move.
+fn judge(observation: &Observation, action: &Action) -> anyhow::Result<i32> {
+    let mut state = observation.to_determinized_state(&mut StdRng::seed_from_u64(0));
+    let me = state.turn;
+    state
+        .run(action.clone())
+        .with_context(|| format!("{action} is not legal"))?;
+    Ok(Weights::default().evaluate(&state, me))
+}
+
+fn main() -> ExitCode {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e:#}");
+            return ExitCode::from(2);
+        }
+    };
+    match compare(&options) {
+        Ok((total, diff)) => {
+            println!(
+                "{diff} of {total} positions differ (allowed {})",
+                options.max_diff
+            );
+            if diff > options.max_diff {
+                println!("FAIL");
+                ExitCode::FAILURE
+            } else {
+                println!("PASS");
+                ExitCode::SUCCESS
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            ExitCode::from(125)
+        }
+    }
+}
+
+/// The number of positions in the suite and of those where the engines differ.
+fn compare(options: &Options) -> anyhow::Result<(usize, usize)> {
+    let positions = std::fs::read_to_string(&options.suite)
+        .with_context(|| format!("Failed to read {}", options.suite))?;
+    let mut old = Engine::start(&options.old)?;
+    let mut new = Engine::start(&options.new)?;
+    let mut diff_suite = match &options.diff_suite {
+        Some(path) => {
+            Some(std::fs::File::create(path).with_context(|| format!("Failed to create {path}"))?)
+        }
+        None => None,
+    };
+    let mut total = 0;
+    let mut diff = 0;
+    for (i, line) in positions.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        total += 1;
+        let observation = match serde_json::from_str::<Observation>(line) {
+            Ok(observation) => observation,
+            Err(_) => {
+                let state: State = serde_json::from_str(line).with_context(|| {
+                    format!("Position {i} is neither an observation nor a state")
+                })?;
+                state.observation_for(state.turn)
+            }
+        };
+        let old_move = old.best_move(&observation, options.budget)?;
+        let new_move = new.best_move(&observation, options.budget)?;
+        if old_move == new_move {
+            continue;
+        }
+        let (old_score, new_score) = (
+            judge(&observation, &old_move)?,
+            judge(&observation, &new_move)?,
+        );
+        let gap = new_score.abs_diff(old_score);
+        if gap <= options.threshold as u32 {
+            continue;
+        }
+        diff += 1;
+        println!("Position {i}: old plays {old_move}, new plays {new_move}, {gap} points apart");
+        if let Some(out) = &mut diff_suite {
+            writeln!(out, "{line}")?;
+        }
+    }
+    Ok((total, diff))
+}
@@ -0,0 +1,199 @@
+//! A stand-in agent for testing the runner: plays the first legal action, like
+//! [`RandomAgent`], after sleeping on each move.
+//!
+//! Usage: `dummy-agent [--sleep-ms <n>] [--answer <line>] [--wrong-first]
+//! [--chatter <n>] [--pid-file <path>] [--exit-after <n>] [--hog-mb <n>]
+//! [--follow] [--announce] [--new-games] [--game-log <path>]`
+//!
+//! With `--answer`, it replies to every line it reads with `<line>` as is,
+//! to send the runner invalid or garbled actions. With `--wrong-first`, its
+//! first action buys a card that isn't there; over the message protocol, it
+//! stops with an error if the runner then asks again without saying why.
+//! `--chatter` prints that many lines to stderr before each move, and
+//! `--pid-file` writes the process id there on start. `--exit-after` exits
+//! with status 7 when asked for one more move than that, like a crash, and
+//! `--hog-mb` fills that much memory before each move. `--follow` prints
+//! each action of the others it is told of and each move it is asked for to
+//! stderr, in the order they come, and `--announce` prints the seat it is
+//! given and how the game ended there too. `--new-games` tells the runner it can
+//! play another game, and `--game-log` adds a line with the process id to
+//! that file for each game it starts.
+
+use std::io::{BufRead, Write};
+
+use anyhow::Context;
+use game_def::{
+    run_agent, Action, Agent, AgentHandler, GameConfig, GameResult, Observation, RandomAgent,
+    RuleError, Tier,
+};
+
+struct Dummy {
+    sleep: std::time::Duration,
+    wrong_first: bool,
+    /// The first action was wrong and the runner hasn't said so yet.
+    waiting_for_rejection: bool,
+    messages: bool,
+    chatter: usize,
+    moves: usize,
+    exit_after: Option<usize>,
+    hog_mb: usize,
+    follow: bool,
+    announce: bool,
+    new_games: bool,
+    game_log: Option<String>,
+}
+
+impl AgentHandler for Dummy {
+    fn init(&mut self, player_index: usize, _config: &GameConfig) {
+        if self.announce {
+            eprintln!("playing seat {player_index}");
+        }
+        if let Some(path) = &self.game_log {
+            let line = format!("{}\n", std::process::id());
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path);
+            file.and_then(|mut file| file.write_all(line.as_bytes()))
+                .unwrap();
+        }
+    }
+
+    fn new_games(&self) -> bool {
+        self.new_games
+    }
+
+    fn act(&mut self, observation: Observation) -> anyhow::Result<Action> {
+        std::thread::sleep(self.sleep);
+        if self.exit_after == Some(self.moves) {
+            eprintln!("giving up after {} moves", self.moves);
+            std::process::exit(7);
+        }
+        self.moves += 1;
+        if self.follow {
+            eprintln!("asked to move");
+        }
+        std::hint::black_box(vec![1u8; self.hog_mb << 20]);
+        let mut stderr = std::io::stderr().lock();
+        for line in 0..self.chatter {
+            writeln!(stderr, "move {} line {line}", self.moves)?;
+        }
+        drop(stderr);
+        if self.waiting_for_rejection && self.messages {
+            anyhow::bail!("Asked again without a rejection");
+        }
+        if self.wrong_first {
+            self.wrong_first = false;
+            self.waiting_for_rejection = true;
+            return Ok(Action::Purchase {
+                deck: Tier::One,
+                card: 9,
+                payment: None,
+                wilds: None,
+                id: None,
+            });
+        }
+        Ok(RandomAgent.act(&observation.state))
+    }
+
+    fn rejected(&mut self, action: &Action, error: &RuleError) {
+        eprintln!("{action} was rejected: {error}");
+        self.waiting_for_rejection = false;
+    }
+
+    fn on_opponent_action(&mut self, player: usize, action: &Action) {
+        if self.follow {
+            eprintln!("player {player} played {action}");
+        }
+    }
+
+    fn game_over(&mut self, result: &GameResult) {
+        if self.announce {
+            eprintln!("game over: {result:?}");
+        }
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let mut sleep_ms = 0;
+    let mut answer = None;
+    let mut wrong_first = false;
+    let mut messages = false;
+    let mut chatter = 0;
+    let mut exit_after = None;
+    let mut hog_mb = 0;
+    let mut follow = false;
+    let mut announce = false;
+    let mut new_games = false;
+    let mut game_log = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sleep-ms" => {
+                sleep_ms = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .context("--sleep-ms needs a number")?
+            }
+            "--answer" => answer = Some(args.next().context("--answer needs a line")?),
+            "--wrong-first" => wrong_first = true,
+            "--pid-file" => {
+                let path = args.next().context("--pid-file needs a path")?;
+                std::fs::write(&path, std::process::id().to_string())
+                    .with_context(|| format!("Failed to write {path}"))?;
+            }
+            "--chatter" => {
+                chatter = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .context("--chatter needs a number")?
+            }
+            "--exit-after" => {
+                exit_after = Some(
+                    args.next()
+                        .and_then(|x| x.parse().ok())
+                        .context("--exit-after needs a number")?,
+                )
+            }
+            "--hog-mb" => {
+                hog_mb = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .context("--hog-mb needs a number")?
+            }
+            "--follow" => follow = true,
+            "--announce" => announce = true,
+            "--new-games" => new_games = true,
+            "--game-log" => game_log = Some(args.next().context("--game-log needs a path")?),
+            // Read again by `run_agent`.
+            "--format" => _ = args.next(),
+            "--protocol" => messages = args.next().as_deref() == Some("messages"),
+            _ => anyhow::bail!("Unknown argument {arg}"),
+        }
+    }
+    let sleep = std::time::Duration::from_millis(sleep_ms);
+    if let Some(answer) = answer {
+        let mut stdout = std::io::stdout().lock();
+        for line in std::io::stdin().lock().lines() {
+            line?;
+            std::thread::sleep(sleep);
+            writeln!(stdout, "{answer}")?;
+            stdout.flush()?;
+        }
+        return Ok(());
+    }
+    run_agent(Dummy {
+        sleep,
+        wrong_first,
+        waiting_for_rejection: false,
+        messages,
+        chatter,
+        moves: 0,
+        exit_after,
+        hog_mb,
+        follow,
+        announce,
+        new_games,
+        game_log,
+    })
+}
@@ -0,0 +1,256 @@
+//! Writes JSON Schemas of the protocol messages for agents written in other
+//! languages.
+//!
+//! Usage: `gen-schema [<dir>]`, writing `observation.json`, `state.json` and
+//! `action.json` into `<dir>` (default `schemas`).
+//!
+//! The schemas are written by hand here, next to the serde attributes they
+//! describe, and have to be updated together with them.
+
+use serde_json::{json, Value};
+
+fn count() -> Value {
+    json!({ "type": "integer", "minimum": 0 })
+}
+
+/// Per deck lists, cheapest deck first.
+fn per_tier(items: Value) -> Value {
+    json!({ "type": "array", "items": items, "minItems": 3, "maxItems": 3 })
+}
+
+fn tier() -> Value {
+    json!({ "$ref": "#/$defs/Tier" })
+}
+
+fn resource_map() -> Value {
+    let colors = ["red", "blue", "green", "white", "black"];
+    json!({
+        "description": "Amount of each color; all five colors are always present.",
+        "type": "object",
+        "properties": colors.iter().map(|c| (c.to_string(), count())).collect::<serde_json::Map<_, _>>(),
+        "required": colors,
+        "additionalProperties": false,
+    })
+}
+
+fn coin_map() -> Value {
+    let kinds = ["red", "blue", "green", "white", "black", "gold"];
+    json!({
+        "description": "Coins of each color and gold, the wild coin; all six are always present.",
+        "type": "object",
+        "properties": kinds.iter().map(|c| (c.to_string(), count())).collect::<serde_json::Map<_, _>>(),
+        "required": kinds,
+        "additionalProperties": false,
+    })
+}
+
+fn object(properties: Value, required: &[&str]) -> Value {
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+fn definitions() -> Value {
+    json!({
+        "ResourceKind": { "enum": ["red", "blue", "green", "white", "black"] },
+        "ColorCode": {
+            "description": "A color in actions: red, blue, green, white and black. The names of ResourceKind are read too.",
+            "enum": ["r", "u", "g", "w", "k"],
+        },
+        "Tier": { "description": "Deck, cheapest first.", "enum": [0, 1, 2] },
+        "ResourceMap": resource_map(),
+        "CoinMap": coin_map(),
+        "Card": object(json!({
+            "id": count(),
+            "cost": { "$ref": "#/$defs/ResourceMap" },
+            "score": count(),
+            "adds": { "$ref": "#/$defs/ResourceMap" },
+        }), &["cost", "score", "adds"]),
+        "Objective": object(json!({
+            "cost": { "$ref": "#/$defs/ResourceMap" },
+            "score": count(),
+            "min_score": count(),
+            "ends_game": { "type": "boolean", "description": "A city, whose claiming ends the game." },
+        }), &["cost", "score"]),
+        "Player": object(json!({
+            "mortal": { "$ref": "#/$defs/CoinMap", "description": "Coins in hand." },
+            "immortal": { "$ref": "#/$defs/ResourceMap", "description": "Discounts from purchased cards." },
+            "score": count(),
+            "purchased": { "type": "array", "items": count(), "description": "Ids of the cards bought, in order." },
+            "reserved": { "type": "array", "items": { "$ref": "#/$defs/Card" } },
+            "reserved_blind": { "type": "array", "items": { "type": "boolean" } },
+            "display_name": { "type": "string" },
+            "record": { "$ref": "#/$defs/PlayerRecord" },
+            "resigned": { "type": "boolean" },
+        }), &["mortal", "immortal", "score", "reserved", "display_name"]),
+        "PlayerRecord": object(json!({
+            "card_points": count(),
+            "nobel_points": count(),
+            "cards_by_tier": { "type": "array", "items": count(), "minItems": 3, "maxItems": 3 },
+            "nobels": count(),
+            "turns": count(),
+            "cities": count(),
+            "coins_taken": count(),
+            "reserves": count(),
+            "wilds_spent": count(),
+        }), &["card_points", "nobel_points", "cards_by_tier", "nobels", "turns"]),
+        "GameConfig": object(json!({
+            "target_score": count(),
+            "coins_per_color": {
+                "type": "object",
+                "description": "Starting coins of each color, keyed by player count.",
+                "additionalProperties": count(),
+            },
+            "wilds": count(),
+            "visible_cards": count(),
+            "extra_nobels": count(),
+            "max_coins": count(),
+            "max_reserved": count(),
+            "pick_two_minimum": count(),
+            "max_rounds": { "anyOf": [count(), { "type": "null" }] },
+            "objective_mode": { "enum": ["nobles", "cities"] },
+            "cards": { "$ref": "#/$defs/CardSet" },
+            "handicaps": {
+                "type": "object",
+                "description": "Head starts, keyed by seat.",
+                "additionalProperties": { "$ref": "#/$defs/Handicap" },
+            },
+        }), &[
+            "target_score", "coins_per_color", "wilds", "visible_cards", "extra_nobels",
+            "max_coins", "max_reserved", "pick_two_minimum",
+        ]),
+        "Handicap": object(json!({
+            "coins": { "$ref": "#/$defs/CoinMap", "description": "Taken from the bank at the start." },
+            "score": count(),
+            "time_ms": { "type": "integer", "minimum": 0, "description": "Thinking time on top of the time budget." },
+        }), &[]),
+        "CardSet": object(json!({
+            "name": { "type": "string", "description": "The file the cards were read from." },
+            "hash": { "type": "string", "description": "FNV-1a of the file, in hex." },
+            "decks": per_tier(json!({ "type": "array", "items": { "$ref": "#/$defs/Card" } })),
+            "nobels": { "type": "array", "items": { "$ref": "#/$defs/Objective" } },
+        }), &["name", "hash", "decks", "nobels"]),
+        "State": object(json!({
+            "decks": per_tier(json!({ "type": "array", "items": { "$ref": "#/$defs/Card" } })),
+            "nobels": { "type": "array", "items": { "$ref": "#/$defs/Objective" } },
+            "players": { "type": "array", "items": { "$ref": "#/$defs/Player" } },
+            "coins": { "$ref": "#/$defs/CoinMap" },
+            "turn": count(),
+            "phase": { "enum": ["play", "discard", "choose_nobel"] },
+            "first_player": count(),
+            "round": count(),
+            "passes": count(),
+            "config": { "$ref": "#/$defs/GameConfig" },
+        }), &["decks", "nobels", "players", "coins", "turn"]),
+        "Observation": object(json!({
+            "protocol_version": { "const": game_def::PROTOCOL_VERSION },
+            "state": { "$ref": "#/$defs/State" },
+            "hidden_deck_cards": per_tier(count()),
+            "hidden_reserved": { "type": "array", "items": count() },
+        }), &["protocol_version", "state", "hidden_deck_cards", "hidden_reserved"]),
+        "Action": { "oneOf": actions(), "examples": examples() },
+    })
+}
+
+fn action(kind: &str, properties: Value, required: &[&str]) -> Value {
+    let mut properties = properties;
+    properties["type"] = json!({ "const": kind });
+    let mut required = required.to_vec();
+    required.push("type");
+    object(properties, &required)
+}
+
+fn actions() -> Vec<Value> {
+    let color = json!({ "$ref": "#/$defs/ColorCode" });
+    let payment = json!({ "$ref": "#/$defs/ResourceMap" });
+    vec![
+        action(
+            "pick_three",
+            json!({ "one": color, "two": color, "three": color }),
+            &["one", "two", "three"],
+        ),
+        action("pick_two", json!({ "color": color }), &["color"]),
+        action(
+            "pick_fewer",
+            json!({ "colors": { "type": "array", "items": color, "minItems": 1, "maxItems": 2 } }),
+            &["colors"],
+        ),
+        action(
+            "purchase",
+            json!({ "deck": tier(), "card": count(), "payment": payment, "wilds": count(), "id": count() }),
+            &["deck", "card"],
+        ),
+        action(
+            "purchase_reserved",
+            json!({ "index": count(), "payment": payment, "wilds": count() }),
+            &["index"],
+        ),
+        action(
+            "reserve",
+            json!({ "deck": tier(), "card": count(), "id": count() }),
+            &["deck", "card"],
+        ),
+        action("reserve_blind", json!({ "deck": tier() }), &["deck"]),
+        action(
+            "discard",
+            json!({ "coins": payment, "wilds": count() }),
+            &["coins"],
+        ),
+        action("choose_nobel", json!({ "index": count() }), &["index"]),
+        action("skip", json!({}), &[]),
+        action("resign", json!({}), &[]),
+    ]
+}
+
+/// The fixtures the `Action` doc test round trips, one per variant.
+fn examples() -> Vec<Value> {
+    macro_rules! fixtures {
+        ($($name:literal),*) => {
+            [$(include_str!(concat!("../../game-def/fixtures/actions/", $name, ".json"))),*]
+        };
+    }
+    fixtures!(
+        "pick_three",
+        "pick_two",
+        "pick_fewer",
+        "purchase",
+        "purchase_reserved",
+        "reserve",
+        "reserve_blind",
+        "discard",
+        "choose_nobel",
+        "skip",
+        "resign"
+    )
+    .iter()
+    .map(|s| serde_json::from_str(s).unwrap())
+    .collect()
+}
+
+fn schema(root: &str) -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": root,
+        "$ref": format!("#/$defs/{root}"),
+        "$defs": definitions(),
+    })
+}
+
+fn main() -> anyhow::Result<()> {
+    let dir = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "schemas".to_owned());
+    std::fs::create_dir_all(&dir)?;
+    for (file, root) in [
+        ("observation.json", "Observation"),
+        ("state.json", "State"),
+        ("action.json", "Action"),
+    ] {
+        let path = format!("{dir}/{file}");
+        std::fs::write(&path, serde_json::to_string_pretty(&schema(root))? + "\n")?;
+        println!("Wrote {path}");
+    }
+    Ok(())
+}
@@ -0,0 +1,168 @@
+//! Agents that play inside the runner instead of as programs of their own:
+//! `builtin:random` and `builtin:alphabeta`, which takes options like
+//! `builtin:alphabeta?depth=2&time=0.5&table=16&model=maxn`, the most
+//! rounds it looks ahead, the seconds it takes to deepen its search, the
+//! megabytes of its transposition table and what it takes the others to
+//! play for. `weights=<file>` reads the weights it scores positions by,
+//! `eval=<file>` a learned network to score them by instead, `threads=<n>`
+//! searches on that many threads, `endgame=<points>` is how close to the
+//! target score it looks for a win it can prove and `determinizations=<n>`
+//! how many ways of dealing the cards it can't see it searches.
+//! `difficulty=easy|medium|hard|custom` plays below full strength, see
+//! [`Difficulty`], with the other options changing the level's settings,
+//! and `temperature=<points>` and `blunder=<rate>` set how often it plays
+//! another move than the best by hand. They run
+//! the code of the alpha-beta-agent program, and of `random-agent
+//! --legacy-first`, each on a thread talking the message protocol over
+//! pipes, so they are timed like any other agent and mix with them.
+
+use std::{
+    io::{BufReader, PipeReader, PipeWriter},
+    sync::Arc,
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use alpha_beta_agent::{AlphaBeta, Difficulty, Network, Weights, MAX_BLUNDER};
+use anyhow::{bail, Context};
+use game_def::{run_agent_over_io, Agent, Observation, RandomAgent};
+
+/// A built-in agent, from what follows `builtin:`.
+pub enum Builtin {
+    Random,
+    AlphaBeta(Box<AlphaBeta>, Difficulty),
+}
+
+impl Builtin {
+    pub fn parse(spec: &str) -> anyhow::Result<Builtin> {
+        let (name, options) = spec.split_once('?').unwrap_or((spec, ""));
+        let mut options = options.split('&').filter(|option| !option.is_empty());
+        match name {
+            "random" => match options.next() {
+                Some(_) => bail!("builtin:random has no options"),
+                None => Ok(Builtin::Random),
+            },
+            "alphabeta" => {
+                let mut agent = AlphaBeta::default();
+                let options: Vec<_> = options.collect();
+                // The level first, for the other options to change.
+                let difficulty = match options
+                    .iter()
+                    .find_map(|option| option.strip_prefix("difficulty="))
+                {
+                    Some(level) => level.parse()?,
+                    None => Difficulty::Custom,
+                };
+                difficulty.apply(&mut agent);
+                for option in options {
+                    match option.split_once('=') {
+                        Some(("depth", depth)) => {
+                            agent.depth = depth
+                                .parse()
+                                .ok()
+                                .filter(|&depth| depth > 0)
+                                .context("depth needs a positive number")?
+                        }
+                        Some(("time", seconds)) => {
+                            agent.time_limit = seconds
+                                .parse()
+                                .ok()
+                                .and_then(|x| Duration::try_from_secs_f64(x).ok())
+                                .context("time needs a positive number of seconds")?
+                        }
+                        Some(("table", megabytes)) => {
+                            let megabytes: usize =
+                                megabytes.parse().context("table needs a number of megabytes")?;
+                            agent.table_memory = megabytes << 20;
+                        }
+                        Some(("model", model)) => agent.model = model.parse()?,
+                        Some(("weights", path)) => agent.weights = Weights::load(path.as_ref())?,
+                        Some(("eval", path)) => {
+                            agent.network = Some(Arc::new(Network::load(path.as_ref())?))
+                        }
+                        Some(("threads", threads)) => {
+                            agent.threads = threads
+                                .parse()
+                                .ok()
+                                .filter(|&threads| threads > 0)
+                                .context("threads needs a positive number")?
+                        }
+                        Some(("endgame", points)) => {
+                            agent.endgame =
+                                points.parse().context("endgame needs a number of points")?
+                        }
+                        Some(("determinizations", n)) => {
+                            agent.determinizations = n
+                                .parse()
+                                .ok()
+                                .filter(|&n| n > 0)
+                                .context("determinizations needs a positive number")?
+                        }
+                        Some(("difficulty", _)) => (),
+                        Some(("temperature", points)) => {
+                            agent.temperature = points
+                                .parse()
+                                .ok()
+                                .filter(|&t: &f64| t >= 0.0 && t.is_finite())
+                                .context("temperature needs a number of points")?
+                        }
+                        Some(("blunder", rate)) => {
+                            agent.blunder = rate
+                                .parse()
+                                .ok()
+                                .filter(|rate| (0.0..=MAX_BLUNDER).contains(rate))
+                                .with_context(|| {
+                                    format!("blunder needs a rate from 0 to {MAX_BLUNDER}")
+                                })?
+                        }
+                        _ => bail!(
+                            "builtin:alphabeta has no option {option}, only depth=<n>, time=<seconds>, table=<megabytes>, model=paranoid|maxn, weights=<file>, eval=<file>, threads=<n>, endgame=<points>, determinizations=<n>, difficulty=easy|medium|hard|custom, temperature=<points> and blunder=<rate>"
+                        ),
+                    }
+                }
+                Ok(Builtin::AlphaBeta(Box::new(agent), difficulty))
+            }
+            _ => {
+                bail!("There is no built-in agent {name}, try builtin:random or builtin:alphabeta")
+            }
+        }
+    }
+
+    /// What it is called without `--name`.
+    pub fn name(&self) -> String {
+        match self {
+            Builtin::Random => "random".to_owned(),
+            Builtin::AlphaBeta(_, Difficulty::Custom) => "alphabeta".to_owned(),
+            Builtin::AlphaBeta(_, difficulty) => format!("alphabeta-{difficulty}"),
+        }
+    }
+
+    /// The level it plays at, `None` unless one was given.
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        match self {
+            Builtin::AlphaBeta(_, difficulty) if *difficulty != Difficulty::Custom => {
+                Some(*difficulty)
+            }
+            _ => None,
+        }
+    }
+
+    /// Starts the agent on a thread of its own. Returns the thread, the pipe
+    /// to write its messages to and the one to read its actions from. The
+    /// thread ends once the first is closed.
+    pub fn start(self) -> std::io::Result<(JoinHandle<()>, PipeWriter, BufReader<PipeReader>)> {
+        let (input, to_agent) = std::io::pipe()?;
+        let (from_agent, output) = std::io::pipe()?;
+        let thread = std::thread::spawn(move || {
+            let input = BufReader::new(input);
+            // Fails only when the runner is gone before the action is written.
+            _ = match self {
+                Builtin::Random => run_agent_over_io(input, output, false, |o: Observation| {
+                    RandomAgent.act(&o.state)
+                }),
+                Builtin::AlphaBeta(agent, _) => run_agent_over_io(input, output, false, *agent),
+            };
+        });
+        Ok((thread, to_agent, BufReader::new(from_agent)))
+    }
+}
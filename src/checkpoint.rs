@@ -0,0 +1,95 @@
+//! `--checkpoint <path>` and `--resume <path>` of `splendor-ai tournament`:
+//! a tournament that was cut short, to go on with later.
+//!
+//! The file is written again after each game, with every game of the
+//! tournament and the results of those played so far. Resuming plays the
+//! rest and comes out as if the tournament hadn't stopped, as long as the
+//! same agents are given in the same order. The seed, `--games`,
+//! `--players`, `--mirrored`, `--format` and `--rounds` come from the file,
+//! and the other options from the command line as usual.
+//!
+//! A Swiss tournament only has the rounds paired so far in its schedule,
+//! and pairs the next once the last is played, resumed or not.
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::tournament::GameRecord;
+
+/// Changed whenever a checkpoint of an older runner can't be resumed.
+pub const VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub version: u32,
+    pub seed: u64,
+    /// The name and command of each agent.
+    pub agents: Vec<(String, String)>,
+    /// The seats, seed and mirrored pair of each game, in order.
+    pub schedule: Vec<(Vec<usize>, u64, Option<usize>)>,
+    /// The games played so far, the first of `schedule`.
+    pub played: Vec<GameRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub swiss: Option<Swiss>,
+}
+
+/// The rounds of a Swiss tournament, see `--format swiss`.
+#[derive(Serialize, Deserialize)]
+pub struct Swiss {
+    pub rounds: usize,
+    /// The games of each pair in a round, twice `--games` if mirrored.
+    pub games: usize,
+    /// Who sat out each round paired so far.
+    pub byes: Vec<Option<usize>>,
+}
+
+impl Checkpoint {
+    pub fn load(path: &str) -> anyhow::Result<Checkpoint> {
+        #[derive(Deserialize)]
+        struct Versioned {
+            version: u32,
+        }
+        let json =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+        let not_checkpoint = || format!("{path} is not a tournament checkpoint");
+        let Versioned { version } = serde_json::from_str(&json).with_context(not_checkpoint)?;
+        if version != VERSION {
+            bail!("{path} is a checkpoint of version {version}, but this runner resumes version {VERSION}");
+        }
+        serde_json::from_str(&json).with_context(not_checkpoint)
+    }
+
+    /// Writes to a temporary file first, so a tournament killed while
+    /// saving still has the last checkpoint.
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        let json = serde_json::to_string(self)?;
+        let temporary = format!("{path}.tmp");
+        std::fs::write(&temporary, json + "\n")
+            .with_context(|| format!("Failed to write {temporary}"))?;
+        std::fs::rename(&temporary, path).with_context(|| format!("Failed to write {path}"))
+    }
+
+    /// Checks that `agents` are those of the checkpoint, listing the
+    /// differences if not.
+    pub fn check(&self, agents: &[(String, String)]) -> anyhow::Result<()> {
+        if self.agents == agents {
+            return Ok(());
+        }
+        let mut diff = String::new();
+        for i in 0..self.agents.len().max(agents.len()) {
+            let line = |(name, command): &(String, String)| format!("{name}: {command}");
+            match (self.agents.get(i), agents.get(i)) {
+                (Some(old), Some(new)) if old == new => diff += &format!("\n  {}", line(old)),
+                (old, new) => {
+                    if let Some(old) = old {
+                        diff += &format!("\n- {}", line(old));
+                    }
+                    if let Some(new) = new {
+                        diff += &format!("\n+ {}", line(new));
+                    }
+                }
+            }
+        }
+        bail!("The agents differ from those of the checkpoint (-) and can't be resumed with (+):{diff}")
+    }
+}
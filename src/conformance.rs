@@ -0,0 +1,326 @@
+//! `splendor-ai test-agent [--format msgpack] [--protocol messages]
+//! [--move-timeout <seconds>] <command>`: checks an agent before it plays for
+//! real. It is started afresh for each of a few positions, from the opening
+//! to ones agents tend to get wrong, and has to answer each with an action
+//! the rules allow in time. Over the message protocol it also gets its
+//! [`RunnerMessage::Init`] first and [`RunnerMessage::GameOver`] after, and
+//! has to exit once its input is closed.
+//!
+//! Prints a line for each position and the observation of the first that
+//! failed, to run the agent on. Exits with 1 if any failed.
+//!
+//! With `--transcript <file>`, the agent is sent the runner's side of a
+//! [`game_def::transcript`] instead, in the format and protocol it names,
+//! and has to answer where the recorded agent did. Each answer is checked
+//! against the last observation it was sent and compared with the recorded
+//! one.
+
+use std::{
+    io::Write,
+    process::ExitCode,
+    sync::mpsc::RecvTimeoutError,
+    time::{Duration, Instant},
+};
+
+use game_def::{
+    transcript::{self, Direction},
+    Action, GameConfig, GameResult, Observation, ResourceKind, RunnerMessage, State, Tier,
+};
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{request_move, transport, Agent, AgentLog, Failure, DEFAULT_MOVE_TIMEOUT};
+
+/// How long an agent has to exit after [`RunnerMessage::GameOver`].
+const EXIT_GRACE: Duration = Duration::from_secs(1);
+
+/// The positions, each with what it is.
+fn positions() -> Vec<(&'static str, State)> {
+    let deal = |names: &[&str], config| State::setup(names, config, &mut StdRng::seed_from_u64(1));
+    let opening = deal(&["you", "opponent"], GameConfig::default());
+
+    // First legal actions until someone is close to winning.
+    let mut late = deal(&["you", "second", "third"], GameConfig::default());
+    while late.players.iter().all(|p| p.score < 12) && !late.is_finished() && late.round < 100 {
+        let action = late.legal_actions().next().unwrap();
+        late.run(action).unwrap();
+    }
+
+    let mut empty_colors = opening.clone();
+    for kind in [ResourceKind::Red, ResourceKind::Blue] {
+        let n = empty_colors.coins.gems[kind];
+        empty_colors.coins.gems[kind] = 0;
+        empty_colors.players[1].mortal.gems[kind] += n;
+    }
+
+    // No coins to take, none to buy with and no room to reserve.
+    let mut config = GameConfig::default();
+    config.coins_per_color.insert(2, 0);
+    let mut only_skip = deal(&["you", "opponent"], config);
+    for _ in 0..3 {
+        let card = only_skip.decks[Tier::Three].pop().unwrap();
+        only_skip.players[0].reserved.push(card);
+        only_skip.players[0].reserved_blind.push(false);
+    }
+
+    // One coin short of the limit, so most takes go over it.
+    let mut hand_limit = opening.clone();
+    let coins = [
+        (ResourceKind::Red, 2),
+        (ResourceKind::Green, 2),
+        (ResourceKind::White, 2),
+        (ResourceKind::Black, 3),
+    ];
+    for (kind, n) in coins {
+        hand_limit.coins.gems[kind] -= n;
+        hand_limit.players[0].mortal.gems[kind] += n;
+    }
+    let mut discard = hand_limit.clone();
+    discard.run("take u u".parse().unwrap()).unwrap();
+
+    let mut positions = vec![
+        ("opening", opening),
+        ("late game", late),
+        ("empty colors in the bank", empty_colors),
+        ("only skip is legal", only_skip),
+        ("one coin short of the limit", hand_limit),
+        ("coins to discard", discard),
+    ];
+    for (_, state) in &mut positions {
+        state.rehash();
+    }
+    positions
+}
+
+pub fn main(mut argv: impl Iterator<Item = String>) -> ExitCode {
+    let mut msgpack = false;
+    let mut messages = false;
+    let mut move_timeout = DEFAULT_MOVE_TIMEOUT;
+    let mut transcript = None;
+    let mut commands = vec![];
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "--transcript" => {
+                let Some(path) = argv.next() else {
+                    println!("--transcript needs a path");
+                    return ExitCode::FAILURE;
+                };
+                transcript = Some(path);
+            }
+            "--format" => match argv.next().as_deref() {
+                Some("json") => msgpack = false,
+                Some("msgpack") => msgpack = true,
+                _ => {
+                    println!("--format needs json or msgpack");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--protocol" => match argv.next().as_deref() {
+                Some("states") => messages = false,
+                Some("messages") => messages = true,
+                _ => {
+                    println!("--protocol needs states or messages");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--move-timeout" => {
+                let timeout = argv.next().and_then(|x| x.parse().ok());
+                let Some(timeout) = timeout.and_then(|x| Duration::try_from_secs_f64(x).ok())
+                else {
+                    println!("--move-timeout needs a positive number of seconds");
+                    return ExitCode::FAILURE;
+                };
+                move_timeout = timeout;
+            }
+            _ => commands.push(arg),
+        }
+    }
+    let [command] = &commands[..] else {
+        println!("Usage: splendor-ai test-agent [--format msgpack] [--protocol messages] [--move-timeout <seconds>] [--transcript <file>] <command>");
+        return ExitCode::FAILURE;
+    };
+    if let Some(path) = transcript {
+        return match replay(command, &path, move_timeout) {
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => ExitCode::FAILURE,
+            Err(e) => {
+                println!("Error: {e:#}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+    let positions = positions();
+    let mut first_failure = None;
+    let mut slowest = Duration::ZERO;
+    for (name, state) in &positions {
+        match check(command, msgpack, messages, move_timeout, state) {
+            Ok((action, elapsed)) => {
+                slowest = slowest.max(elapsed);
+                println!("PASS {name}: {action} in {elapsed:.1?}");
+            }
+            Err(e) => {
+                println!("FAIL {name}: {e:#}");
+                first_failure.get_or_insert((name, state));
+            }
+        }
+    }
+    match first_failure {
+        None => {
+            println!(
+                "All {} positions passed, the slowest answer took {slowest:.1?}",
+                positions.len()
+            );
+            ExitCode::SUCCESS
+        }
+        Some((name, state)) => {
+            println!("The agent was sent this observation in {name}:");
+            println!("{}", state.observation_for(state.turn).json());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Checks `command` in every position like `test-agent`, for the arena to
+/// admit it, and fails with the first it got wrong.
+pub fn passes(command: &str, messages: bool, move_timeout: Duration) -> anyhow::Result<()> {
+    for (name, state) in &positions() {
+        check(command, false, messages, move_timeout, state)
+            .map_err(|e| e.context(format!("in {name}")))?;
+    }
+    Ok(())
+}
+
+/// Starts `command` and asks it for an action in `state`, as the player to
+/// move. Returns the action and how long it took.
+fn check(
+    command: &str,
+    msgpack: bool,
+    messages: bool,
+    move_timeout: Duration,
+    state: &State,
+) -> anyhow::Result<(Action, Duration)> {
+    let name = "agent".to_owned();
+    let log = AgentLog::Labelled(name.clone());
+    let mut agent = Agent::start(command, name, msgpack, messages, None, log)?;
+    agent.notify(RunnerMessage::Init {
+        protocol_version: game_def::PROTOCOL_VERSION,
+        player_index: state.turn,
+        config: state.config.clone(),
+        new_games: false,
+    });
+    let (reply, elapsed) = request_move(
+        &mut agent,
+        state,
+        move_timeout,
+        Duration::ZERO,
+        0,
+        |_, _| (),
+    );
+    let action = reply.map_err(|e| anyhow::anyhow!("the agent {e}"))?;
+    if let Err(e) = state.check_action(&action) {
+        anyhow::bail!("{action} is invalid: {e}");
+    }
+    if messages {
+        agent.notify(RunnerMessage::GameOver {
+            result: GameResult::Ongoing,
+        });
+        let Agent::AI {
+            writer, transport, ..
+        } = &mut agent
+        else {
+            unreachable!()
+        };
+        drop(writer.take());
+        // Only processes tell how they exited.
+        let deadline = Instant::now() + EXIT_GRACE;
+        let mut exit = transport.exit();
+        while exit.as_ref().is_some_and(|e| e.status.is_none()) && Instant::now() < deadline {
+            exit = transport.exit();
+        }
+        match exit.map(|exit| exit.status) {
+            Some(None) => anyhow::bail!("{action}, but didn't exit after the game"),
+            Some(Some(status)) if status != "status 0" => {
+                anyhow::bail!("{action}, but exited with {status} after the game")
+            }
+            _ => (),
+        }
+    }
+    Ok((action, elapsed))
+}
+
+/// Sends `command` the runner's side of the transcript at `path`, printing a
+/// line for each answer. Returns whether they all passed.
+fn replay(command: &str, path: &str, move_timeout: Duration) -> anyhow::Result<bool> {
+    let recorded = transcript::read(path)?;
+    let (msgpack, messages) = (recorded.msgpack, recorded.messages);
+    let name = "agent".to_owned();
+    let log = AgentLog::Labelled(name.clone());
+    let mut agent = Agent::start(command, name, msgpack, messages, None, log)?;
+    let Agent::AI {
+        writer: Some(writer),
+        replies,
+        transport,
+        ..
+    } = &mut agent
+    else {
+        unreachable!("agents started from a command are AIs")
+    };
+    let mut observation = None;
+    let mut passed = true;
+    let mut n = 0;
+    for (direction, wire) in &recorded.lines {
+        if *direction == Direction::ToAgent {
+            let sent = match messages {
+                false => transport::decode_wire::<Observation>(wire, msgpack).ok(),
+                true => match transport::decode_wire(wire, msgpack) {
+                    Ok(
+                        RunnerMessage::MoveRequest { observation, .. }
+                        | RunnerMessage::Rejected { observation, .. },
+                    ) => Some(*observation),
+                    _ => None,
+                },
+            };
+            observation = sent.or(observation);
+            if writer.write_all(wire).and_then(|_| writer.flush()).is_err() {
+                println!("FAIL the agent closed its input");
+                return Ok(false);
+            }
+            continue;
+        }
+        n += 1;
+        let started = Instant::now();
+        let action = match replies.recv_timeout(move_timeout) {
+            Ok(Ok(action)) => action,
+            reply => {
+                let failure = match reply {
+                    Ok(Err(e)) => Failure::Garbled(e),
+                    Err(RecvTimeoutError::Timeout) => Failure::TimedOut(started.elapsed()),
+                    _ => Failure::Closed(transport.exit()),
+                };
+                // The answers after it wouldn't line up.
+                println!("FAIL reply {n}: the agent {failure}");
+                return Ok(false);
+            }
+        };
+        let elapsed = started.elapsed();
+        let mut line = format!("{action} in {elapsed:.1?}");
+        match transport::decode_wire::<Action>(wire, msgpack) {
+            Ok(before) if before == action => (),
+            Ok(before) => line += &format!(", recorded {before}"),
+            Err(_) => line += ", recorded something that is not an action",
+        }
+        // The hidden cards are dealt at random, only their number matters.
+        let rng = &mut StdRng::seed_from_u64(0);
+        let state = observation.as_ref().map(|o| o.to_determinized_state(rng));
+        match state.map(|state| state.check_action(&action)) {
+            Some(Err(e)) => {
+                passed = false;
+                println!("FAIL reply {n}: {line}, which is invalid: {e}");
+            }
+            _ => println!("PASS reply {n}: {line}"),
+        }
+    }
+    if passed {
+        println!("All {n} replies passed");
+    }
+    Ok(passed)
+}
@@ -0,0 +1,196 @@
+//! Elo ratings kept in a JSON file across tournaments, and the `splendor-ai
+//! ratings <file>` leaderboard.
+//!
+//! The file maps each agent name to its [`Rating`]. Agents a tournament
+//! doesn't play keep their entry untouched. Every update holds a lock file
+//! next to it while it reads, changes and writes the file, so tournaments run
+//! side by side don't lose each other's games.
+//!
+//! A win scores 1, a draw ½ and a loss 0. A forfeit, including an agent that
+//! failed to start, is a loss like any other.
+
+use std::{
+    collections::BTreeMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+pub const INITIAL_RATING: f64 = 1500.0;
+/// Agents with fewer games move faster, to find their level.
+const PROVISIONAL_GAMES: u32 = 30;
+/// A lock older than this is left from a crashed tournament.
+const STALE_LOCK: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rating {
+    pub rating: f64,
+    pub games: u32,
+    /// Seconds since the Unix epoch.
+    pub updated: u64,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Rating {
+            rating: INITIAL_RATING,
+            games: 0,
+            updated: 0,
+        }
+    }
+}
+
+impl Rating {
+    /// 40 while provisional, then 20, and 10 from 2400 on.
+    pub fn k_factor(&self) -> f64 {
+        if self.games < PROVISIONAL_GAMES {
+            40.0
+        } else if self.rating < 2400.0 {
+            20.0
+        } else {
+            10.0
+        }
+    }
+
+    /// Half the width of a rough 95% interval, for an agent scoring around
+    /// half its games.
+    pub fn uncertainty(&self) -> f64 {
+        680.0 / (self.games.max(1) as f64).sqrt()
+    }
+}
+
+/// The score `a` is expected to make against `b`, between 0 and 1.
+pub fn expected_score(a: f64, b: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((b - a) / 400.0))
+}
+
+pub type Ratings = BTreeMap<String, Rating>;
+
+/// Rates one game between `a` and `b`, where `a` scored `score`.
+pub fn update(ratings: &mut Ratings, a: &str, b: &str, score: f64) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let old_a = ratings.get(a).cloned().unwrap_or_default();
+    let old_b = ratings.get(b).cloned().unwrap_or_default();
+    let expected = expected_score(old_a.rating, old_b.rating);
+    for (name, old, delta) in [(a, &old_a, score - expected), (b, &old_b, expected - score)] {
+        ratings.insert(
+            name.to_owned(),
+            Rating {
+                rating: old.rating + old.k_factor() * delta,
+                games: old.games + 1,
+                updated: now,
+            },
+        );
+    }
+}
+
+/// The ratings in `path`, none if it doesn't exist yet.
+pub fn load(path: &str) -> anyhow::Result<Ratings> {
+    match std::fs::read_to_string(path) {
+        Ok(json) => {
+            serde_json::from_str(&json).with_context(|| format!("{path} is not a ratings file"))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Ratings::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {path}")),
+    }
+}
+
+/// Runs `change` on the ratings in `path` and writes them back, holding
+/// `path.lock` meanwhile.
+pub fn modify(path: &str, change: impl FnOnce(&mut Ratings)) -> anyhow::Result<()> {
+    locked(path, || {
+        let mut ratings = load(path)?;
+        change(&mut ratings);
+        let json = serde_json::to_string_pretty(&ratings)?;
+        let temporary = format!("{path}.tmp");
+        std::fs::write(&temporary, json + "\n")
+            .with_context(|| format!("Failed to write {temporary}"))?;
+        std::fs::rename(&temporary, path).with_context(|| format!("Failed to write {path}"))
+    })
+}
+
+/// Runs `f` holding `path.lock`, which other runners wait for.
+pub fn locked<T>(path: &str, f: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    let lock = format!("{path}.lock");
+    loop {
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&lock)
+        {
+            Ok(_) => break,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                let age = std::fs::metadata(&lock)
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .and_then(|t| t.elapsed().ok());
+                if age.is_some_and(|age| age > STALE_LOCK) {
+                    _ = std::fs::remove_file(&lock);
+                } else {
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+            }
+            Err(e) => return Err(e).with_context(|| format!("Failed to create {lock}")),
+        }
+    }
+    let result = f();
+    _ = std::fs::remove_file(&lock);
+    result
+}
+
+fn ago(updated: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let seconds = now.saturating_sub(updated);
+    match seconds {
+        0..60 => "just now".to_owned(),
+        60..3600 => format!("{}m ago", seconds / 60),
+        3600..86400 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+pub fn main(mut argv: impl Iterator<Item = String>) {
+    let (Some(path), None) = (argv.next(), argv.next()) else {
+        println!("Usage: splendor-ai ratings <file>");
+        return;
+    };
+    let ratings = match load(&path) {
+        Ok(ratings) => ratings,
+        Err(e) => {
+            println!("Error: {e:#}");
+            return;
+        }
+    };
+    let mut leaderboard: Vec<_> = ratings.iter().collect();
+    leaderboard.sort_by(|a, b| b.1.rating.total_cmp(&a.1.rating));
+    let width = leaderboard
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Agent".len());
+    println!(
+        "{:>4}  {:<width$}  {:>6}  {:>5}  {:>5}  {:>4}  Updated",
+        "Rank", "Agent", "Rating", "", "Games", ""
+    );
+    for (rank, (name, r)) in leaderboard.into_iter().enumerate() {
+        let provisional = if r.games < PROVISIONAL_GAMES {
+            "prov"
+        } else {
+            ""
+        };
+        println!(
+            "{:>4}  {name:<width$}  {:>6.0}  ±{:<4.0}  {:>5}  {provisional:>4}  {}",
+            rank + 1,
+            r.rating,
+            r.uncertainty(),
+            r.games,
+            ago(r.updated)
+        );
+    }
+}
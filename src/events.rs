@@ -0,0 +1,141 @@
+//! `--emit-events <path|->`: a line of JSON for everything that happens in a
+//! game, for tools that follow it live. Each line is flushed as it is
+//! written, so `tail -f` works, and `-` writes to stdout.
+//!
+//! Every line has a `type` and `ply`, the number of actions played before it.
+//! The runner's own lines are:
+//!
+//! - `game_start`: `seed`, `agents` (their names by seat), `config` and the
+//!   `state` with the face down cards taken out.
+//! - `move_request`: `player` was sent an observation, with `time_left_ms`
+//!   under `--time-budget`. Humans are not asked.
+//! - `action_received`: `player` sent `action` after `think_ms`.
+//! - `rejected`: the rules refused `action` of `player` with `error`. With
+//!   `retry` the player is asked again, otherwise it forfeits.
+//! - `agent_failed`: `player` gave no action, for `reason`.
+//! - `move`: `player` played `action`, and `diff` is what it changed, as
+//!   [`game_def::StateDiff`].
+//! - `forfeit`: `player` lost the game for `reason`.
+//! - `undo`: a human took back the `move` of `player` with the same `ply`,
+//!   so the next line has that `ply` again.
+//! - `game_end`: `result`, every `forfeits` and why the game was
+//!   `terminated` early, if it was.
+//!
+//! After each `move` come the [`GameEvent`]s it caused, with their own
+//! types, like `nobel_visited` when a player gets a nobel.
+
+use std::io::Write;
+
+use game_def::{Action, Forfeit, GameConfig, GameEvent, GameResult, RuleError, State, StateDiff};
+use serde::Serialize;
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event<'a> {
+    GameStart {
+        seed: u64,
+        agents: &'a [&'a str],
+        config: &'a GameConfig,
+        state: Box<State>,
+    },
+    MoveRequest {
+        player: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        time_left_ms: Option<u64>,
+    },
+    ActionReceived {
+        player: usize,
+        action: &'a Action,
+        think_ms: u64,
+    },
+    Rejected {
+        player: usize,
+        action: &'a Action,
+        error: &'a RuleError,
+        retry: bool,
+    },
+    AgentFailed {
+        player: usize,
+        reason: String,
+    },
+    Move {
+        player: usize,
+        action: &'a Action,
+        diff: StateDiff,
+    },
+    Forfeit(&'a Forfeit),
+    Undo {
+        player: usize,
+        action: &'a Action,
+    },
+    GameEnd {
+        result: GameResult,
+        forfeits: &'a [Forfeit],
+        terminated: Option<&'a str>,
+    },
+}
+
+#[derive(Serialize)]
+struct Line<'a, T> {
+    #[serde(flatten)]
+    event: &'a T,
+    ply: usize,
+}
+
+pub struct EventLog {
+    writer: Box<dyn Write>,
+    /// Actions played so far.
+    pub ply: usize,
+}
+
+impl EventLog {
+    pub fn create(path: &str) -> std::io::Result<EventLog> {
+        let writer: Box<dyn Write> = match path {
+            "-" => Box::new(std::io::stdout()),
+            _ => Box::new(std::fs::File::create(path)?),
+        };
+        Ok(EventLog { writer, ply: 0 })
+    }
+
+    pub fn emit(&mut self, event: &Event) {
+        self.write(event);
+    }
+
+    /// Writes `move` and then the events it caused.
+    pub fn moved(&mut self, player: usize, action: &Action, diff: StateDiff, events: &[GameEvent]) {
+        self.write(&Event::Move {
+            player,
+            action,
+            diff,
+        });
+        for event in events {
+            self.write(event);
+        }
+        self.ply += 1;
+    }
+
+    /// Writes `undo` for the last move, which `player` played.
+    pub fn undone(&mut self, player: usize, action: &Action) {
+        self.ply -= 1;
+        self.write(&Event::Undo { player, action });
+    }
+
+    fn write(&mut self, event: &impl Serialize) {
+        let line = Line {
+            event,
+            ply: self.ply,
+        };
+        let json = serde_json::to_string(&line).unwrap();
+        // A full disk shouldn't stop the game.
+        if let Err(e) = writeln!(self.writer, "{json}").and_then(|()| self.writer.flush()) {
+            say!("Failed to write an event: {e}");
+        }
+    }
+}
+
+/// Emits `event` if there is an event log.
+pub fn emit(log: &mut Option<EventLog>, event: Event) {
+    if let Some(log) = log {
+        log.emit(&event);
+    }
+}
@@ -0,0 +1,328 @@
+//! `splendor-ai gauntlet --candidate <cmd> --baseline <cmd>... [options]`:
+//! plays one agent against each of a pool of reference agents, to tell
+//! whether a change made it stronger than all of them.
+//!
+//! Each baseline plays `--games-per` games (20 by default) with the
+//! candidate, in pairs on the same board with the seats swapped, so neither
+//! the shuffle nor going first favors anyone. The candidate's wins, draws
+//! and losses are reported for each baseline and in all, with Elo
+//! differences like for a match, see [`Tally::elo`].
+//!
+//! A game a baseline loses by crashing, timing out or playing an invalid
+//! action says nothing of the candidate, so it is left out of the numbers
+//! and counted on its own. The candidate's failures count as losses.
+//!
+//! With `--ratings <path>`, the baselines are taken at their ratings in
+//! that file, which isn't changed, and the candidate is given the average
+//! of theirs, weighted by games, plus its Elo difference. Baselines without
+//! one are taken at [`elo::INITIAL_RATING`].
+//!
+//! `--json <path>` writes the report, dated, to compare from night to night.
+//! `--seed`, `--jobs`, `--record`, `--stats-file`, `--name` and the settings
+//! of each game work like for a tournament.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Serialize;
+
+use crate::{
+    elo,
+    matchup::Tally,
+    notation, number_arg, stats,
+    tournament::{self, Cards, GameRecord, Settings, MAX_AGENT_PROCESSES},
+    unique_names,
+};
+
+const DEFAULT_GAMES_PER: usize = 20;
+
+/// The candidate's results against one baseline.
+#[derive(Serialize)]
+struct Baseline {
+    name: String,
+    command: String,
+    /// From `--ratings`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rating: Option<f64>,
+    #[serde(flatten)]
+    tally: Tally,
+    win_rate: f64,
+    elo: f64,
+    elo_margin: f64,
+    /// Games the baseline lost by failing to move, left out of the tally.
+    baseline_failures: usize,
+    /// Games the candidate lost by failing to move, in the tally.
+    candidate_failures: usize,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    /// The day it ran, in UTC.
+    date: String,
+    seed: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cards: Option<Cards>,
+    candidate: String,
+    command: String,
+    baselines: Vec<Baseline>,
+    #[serde(flatten)]
+    tally: Tally,
+    /// 1 for a win and ½ for a draw, averaged.
+    score: f64,
+    elo: f64,
+    elo_margin: f64,
+    /// With `--ratings`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rating: Option<f64>,
+    games: Vec<GameRecord>,
+}
+
+pub fn main(mut argv: impl Iterator<Item = String>) {
+    let mut games_per = DEFAULT_GAMES_PER;
+    let mut jobs = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut seed = rand::random();
+    let mut json = None;
+    let mut stats_file = None;
+    let mut record_dir = None;
+    let mut ratings = None;
+    let mut settings = Settings::default();
+    let mut candidate = None;
+    let mut baselines = vec![];
+    let mut name = None;
+    while let Some(arg) = argv.next() {
+        match settings.flag(&arg, &mut argv) {
+            Some(true) => continue,
+            Some(false) => (),
+            None => return,
+        }
+        if arg == "--candidate" || arg == "--baseline" {
+            let Some(command) = argv.next() else {
+                println!("{arg} needs a command");
+                return;
+            };
+            let named = (name.take().unwrap_or_else(|| command.clone()), command);
+            match arg == "--candidate" {
+                true => candidate = Some(named),
+                false => baselines.push(named),
+            }
+        } else if arg == "--name" {
+            // Applies to the next agent only.
+            let Some(x) = argv.next() else {
+                println!("--name needs a name");
+                return;
+            };
+            name = Some(x);
+        } else if arg == "--games-per" {
+            match number_arg(&mut argv, &arg) {
+                Some(0) => {
+                    println!("--games-per needs at least 1");
+                    return;
+                }
+                Some(n) => games_per = n,
+                None => return,
+            }
+        } else if arg == "--jobs" {
+            match number_arg(&mut argv, &arg) {
+                Some(0) => {
+                    println!("--jobs needs at least 1");
+                    return;
+                }
+                Some(n) => jobs = n,
+                None => return,
+            }
+        } else if arg == "--seed" {
+            let Some(x) = number_arg(&mut argv, &arg) else {
+                return;
+            };
+            seed = x;
+        } else if arg == "--ratings" {
+            let Some(path) = argv.next() else {
+                println!("--ratings needs a path");
+                return;
+            };
+            match elo::load(&path) {
+                Ok(x) => ratings = Some(x),
+                Err(e) => {
+                    println!("{e:#}");
+                    return;
+                }
+            }
+        } else if arg == "--record" {
+            let Some(dir) = argv.next() else {
+                println!("--record needs a directory");
+                return;
+            };
+            if let Err(e) = std::fs::create_dir_all(&dir) {
+                println!("Failed to create {dir}: {e}");
+                return;
+            }
+            record_dir = Some(dir);
+        } else if arg == "--json" {
+            let Some(path) = argv.next() else {
+                println!("--json needs a path");
+                return;
+            };
+            json = Some(path);
+        } else if arg == "--stats-file" {
+            let Some(path) = argv.next() else {
+                println!("--stats-file needs a path");
+                return;
+            };
+            stats_file = Some(path);
+        } else {
+            println!("Unknown argument {arg}, agents are given with --candidate and --baseline");
+            return;
+        }
+    }
+    let (Some(candidate), false) = (candidate, baselines.is_empty()) else {
+        println!("A gauntlet needs --candidate and at least one --baseline");
+        return;
+    };
+    // The candidate is agent 0, and baseline i agent i + 1.
+    let (names, commands): (Vec<_>, Vec<_>) = std::iter::once(candidate).chain(baselines).unzip();
+    let names = unique_names(names);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut schedule = vec![];
+    for baseline in 1..commands.len() {
+        let mut seed = 0;
+        for game in 0..games_per {
+            if game % 2 == 0 {
+                seed = rng.gen();
+                schedule.push((vec![0, baseline], seed));
+            } else {
+                schedule.push((vec![baseline, 0], seed));
+            }
+        }
+    }
+    let jobs = jobs.min(MAX_AGENT_PROCESSES / 2).min(schedule.len().max(1));
+    println!(
+        "Gauntlet seed {seed}, {} games, {jobs} at a time",
+        schedule.len()
+    );
+    let mut records: Vec<GameRecord> = vec![];
+    tournament::play_all(
+        &commands,
+        &names,
+        &schedule,
+        0,
+        &settings,
+        jobs,
+        |n, record, replay| {
+            if let (Some(dir), Some(replay)) = (&record_dir, replay) {
+                let path = format!("{dir}/game-{}.json", n + 1);
+                if let Err(e) = replay.save(&path) {
+                    println!("{e:#}");
+                }
+            }
+            if let Some(path) = &stats_file {
+                if let Err(e) = stats::append(path, &record.stats_row(&commands, &names)) {
+                    println!("{e:#}");
+                }
+            }
+            print!("Game {}: ", n + 1);
+            record.print(&names);
+            records.push(record);
+            true
+        },
+    );
+    let mut baselines: Vec<Baseline> = (1..commands.len())
+        .map(|i| Baseline {
+            name: names[i].clone(),
+            command: commands[i].clone(),
+            rating: ratings.as_ref().map(|r| {
+                r.get(&names[i])
+                    .map_or(elo::INITIAL_RATING, |rating| rating.rating)
+            }),
+            tally: Tally::default(),
+            win_rate: 0.0,
+            elo: 0.0,
+            elo_margin: 0.0,
+            baseline_failures: 0,
+            candidate_failures: 0,
+        })
+        .collect();
+    let mut total = Tally::default();
+    for record in &records {
+        let [a, b] = record.seats[..] else {
+            unreachable!("gauntlet games have two players")
+        };
+        let baseline = &mut baselines[a.max(b) - 1];
+        match record.forfeit.as_ref().map(|f| f.agent) {
+            Some(0) => baseline.candidate_failures += 1,
+            Some(_) => {
+                baseline.baseline_failures += 1;
+                continue;
+            }
+            None => (),
+        }
+        let seat = record.seats.iter().position(|&i| i == 0).unwrap();
+        let first = record.places.iter().filter(|&&p| p == 1).count();
+        baseline.tally.add(record.places[seat], first);
+        total.add(record.places[seat], first);
+    }
+    for baseline in &mut baselines {
+        baseline.win_rate = baseline.tally.wins as f64 / baseline.tally.games().max(1) as f64;
+        (baseline.elo, baseline.elo_margin) = baseline.tally.elo();
+    }
+    let (elo, elo_margin) = total.elo();
+    // The baselines' ratings weighted by the games that count.
+    let rating = ratings.map(|_| {
+        let rated: f64 = baselines
+            .iter()
+            .map(|b| b.rating.unwrap() * b.tally.games() as f64)
+            .sum();
+        rated / total.games().max(1) as f64 + elo
+    });
+    let summary = Summary {
+        date: notation::day(std::time::SystemTime::now()),
+        seed,
+        cards: settings.cards(),
+        candidate: names[0].clone(),
+        command: commands[0].clone(),
+        baselines,
+        tally: total,
+        score: (total.wins as f64 + total.draws as f64 / 2.0) / total.games().max(1) as f64,
+        elo,
+        elo_margin,
+        rating,
+        games: records,
+    };
+    print_summary(&summary);
+    if let Some(path) = json {
+        let json = serde_json::to_string_pretty(&summary).unwrap();
+        if let Err(e) = std::fs::write(&path, json + "\n") {
+            println!("Failed to write {path}: {e}");
+        }
+    }
+}
+
+fn print_summary(summary: &Summary) {
+    let candidate = &summary.candidate;
+    println!();
+    for b in &summary.baselines {
+        let t = &b.tally;
+        print!(
+            "{candidate} vs {}: {}-{}-{}, {:.0}% won, {:+.1} ± {:.1} Elo",
+            b.name,
+            t.wins,
+            t.draws,
+            t.losses,
+            b.win_rate * 100.0,
+            b.elo,
+            b.elo_margin
+        );
+        match b.baseline_failures {
+            0 => println!(),
+            n => println!(", {n} games left out where {} failed", b.name),
+        }
+    }
+    let t = &summary.tally;
+    println!(
+        "{candidate} scored {:.1}% in {} games, {:+.1} ± {:.1} Elo against the baselines",
+        summary.score * 100.0,
+        t.games(),
+        summary.elo,
+        summary.elo_margin
+    );
+    if let Some(rating) = summary.rating {
+        println!("{candidate} is rated about {rating:.0}");
+    }
+}
@@ -0,0 +1,106 @@
+//! `hint` at the human prompt: what an advisor would play here, shown in the
+//! short notation and never played.
+//!
+//! With `--hint-agent <cmd>` the advisor is an agent like the seated ones,
+//! started on the first hint and held to `--move-timeout`. It is never given
+//! a seat, so nothing it writes can be taken for a seated agent's move. An
+//! advisor that fails is stopped and started again for the next hint.
+//! Without one, the hint is the best move one ply ahead, see [`greedy`].
+
+use std::time::Duration;
+
+use anyhow::Context;
+use game_def::{Action, RunnerMessage, State, PROTOCOL_VERSION};
+
+use crate::{request_move, Agent, AgentLog};
+
+/// `--hint-agent`, with the `--format` and `--protocol` given before it.
+pub struct HintAgent {
+    pub command: String,
+    pub msgpack: bool,
+    pub messages: bool,
+}
+
+pub struct Advisor {
+    command: Option<HintAgent>,
+    /// Started on the first hint.
+    agent: Option<Agent>,
+    move_timeout: Duration,
+}
+
+impl Advisor {
+    pub fn new(command: Option<HintAgent>, move_timeout: Duration) -> Advisor {
+        Advisor {
+            command,
+            agent: None,
+            move_timeout,
+        }
+    }
+
+    /// What the player to move in `state` could play, checked against the
+    /// rules.
+    pub fn hint(&mut self, state: &State) -> anyhow::Result<Action> {
+        let Some(command) = &self.command else {
+            return Ok(greedy(state));
+        };
+        let agent = match &mut self.agent {
+            Some(agent) => agent,
+            None => {
+                let log = AgentLog::Labelled("advisor".to_owned());
+                let name = "advisor".to_owned();
+                let (msgpack, messages) = (command.msgpack, command.messages);
+                let mut agent = Agent::start(&command.command, name, msgpack, messages, None, log)
+                    .with_context(|| format!("Failed to start {}", command.command))?;
+                agent.notify(RunnerMessage::Init {
+                    protocol_version: PROTOCOL_VERSION,
+                    player_index: state.turn,
+                    config: state.config.clone(),
+                    new_games: false,
+                });
+                self.agent.insert(agent)
+            }
+        };
+        let (reply, _) = request_move(
+            agent,
+            state,
+            self.move_timeout,
+            Duration::ZERO,
+            0,
+            |_, _| (),
+        );
+        let action = match reply {
+            Ok(action) => action,
+            Err(e) => {
+                self.agent = None;
+                anyhow::bail!("The advisor {e}");
+            }
+        };
+        state
+            .check_action(&action)
+            .with_context(|| format!("The advisor suggested {action}, which is not allowed"))?;
+        Ok(action)
+    }
+}
+
+/// The move that leaves the player to move best off right after it: most
+/// points, then most cards, then most coins. Ties go to the first in
+/// [`State::legal_actions`], which lists purchases first.
+pub fn greedy(state: &State) -> Action {
+    let me = state.turn;
+    let mut best = None;
+    for action in state.legal_actions() {
+        if action == Action::Resign {
+            continue;
+        }
+        let mut after = state.clone();
+        after
+            .run(action.clone())
+            .expect("legal_actions yielded an illegal action");
+        let p = &after.players[me];
+        let value = (p.score, p.immortal.sum(), p.mortal.total());
+        if best.as_ref().is_none_or(|(best, _)| value > *best) {
+            best = Some((value, action));
+        }
+    }
+    best.map_or(Action::Resign, |(_, action)| action)
+}
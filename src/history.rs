@@ -0,0 +1,120 @@
+//! The moves of the game being played. `history [n]` at the prompt, saved
+//! games and the `--record` file all read them from here, so they can't
+//! disagree.
+
+use std::collections::VecDeque;
+
+use game_def::{Action, GameEvent, Objective, Replay, State};
+use serde::Serialize;
+
+/// How many of the last moves keep the events they caused. Older moves keep
+/// just the action, which is all a saved game or a replay needs.
+const EVENT_LIMIT: usize = 200;
+
+pub struct History {
+    /// Who made each move and the move, from the start of the game.
+    moves: Vec<(usize, Action)>,
+    /// How many of `moves` were played before a saved game was resumed.
+    resumed: usize,
+    /// What the last moves caused, the last of `moves` last.
+    events: VecDeque<Vec<GameEvent>>,
+    claims: Vec<Claim>,
+}
+
+/// A nobel, or a city, that visited a player.
+#[derive(Clone, Serialize)]
+pub struct Claim {
+    pub player: usize,
+    pub nobel: Objective,
+    /// The number of the move it came with, counted from 1.
+    #[serde(rename = "move")]
+    pub ply: usize,
+}
+
+impl History {
+    /// Goes on from the `moves` of a saved game.
+    pub fn new(moves: Vec<(usize, Action)>) -> History {
+        History {
+            resumed: moves.len(),
+            moves,
+            events: VecDeque::new(),
+            claims: vec![],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn moves(&self) -> &[(usize, Action)] {
+        &self.moves
+    }
+
+    pub fn push(&mut self, player: usize, action: Action, events: Vec<GameEvent>) {
+        self.moves.push((player, action));
+        for event in &events {
+            if let GameEvent::NobelVisited { player, nobel, .. } = event {
+                self.claims.push(Claim {
+                    player: *player,
+                    nobel: nobel.clone(),
+                    ply: self.moves.len(),
+                });
+            }
+        }
+        if self.events.len() == EVENT_LIMIT {
+            self.events.pop_front();
+        }
+        self.events.push_back(events);
+    }
+
+    /// Takes back the last `count` moves, here and in `replay`.
+    pub fn undo(&mut self, count: usize, replay: Option<&mut Replay>) {
+        self.moves.truncate(self.moves.len() - count);
+        self.claims.retain(|claim| claim.ply <= self.moves.len());
+        self.events
+            .truncate(self.events.len().saturating_sub(count));
+        if let Some(replay) = replay {
+            replay.undo(count);
+        }
+    }
+
+    /// The nobels claimed in order, leaving out those of a saved game before
+    /// it was resumed.
+    pub fn claims(&self) -> &[Claim] {
+        &self.claims
+    }
+
+    /// Brings `replay`, which starts where this run did, up to date.
+    pub fn record(&self, replay: &mut Replay) {
+        replay.actions = self.moves[self.resumed..].to_vec();
+    }
+
+    /// Lists the last `last` moves, or all of them, numbered from the
+    /// start of the game.
+    pub fn print(&self, state: &State, last: Option<usize>) {
+        let from = self.moves.len() - last.unwrap_or(usize::MAX).min(self.moves.len());
+        // Where the moves with events start.
+        let with_events = self.moves.len() - self.events.len();
+        for (i, (player, action)) in self.moves.iter().enumerate().skip(from) {
+            let notes: Vec<String> = match i.checked_sub(with_events) {
+                Some(j) => self.events[j].iter().filter_map(note).collect(),
+                None => vec![],
+            };
+            let name = &state.players[*player].display_name;
+            match notes.is_empty() {
+                true => say!("{:>4}  {name} did {action}", i + 1),
+                false => say!("{:>4}  {name} did {action}, {}", i + 1, notes.join(", ")),
+            }
+        }
+    }
+}
+
+/// What the list shows of `event`, if it isn't plain from the action.
+fn note(event: &GameEvent) -> Option<String> {
+    match event {
+        GameEvent::WildReceived { .. } => Some("got a gold coin".to_owned()),
+        GameEvent::NobelVisited { nobel, .. } => Some(format!("got a nobel for {}", nobel.score)),
+        GameEvent::GameFinished { .. } => Some("which ended the game".to_owned()),
+        _ => None,
+    }
+}
@@ -0,0 +1,92 @@
+//! `splendor-ai export-html <replay> -o <file>`: a game recorded with
+//! `--record` as one HTML page, to send to someone without the runner.
+//!
+//! The page is [`PAGE`] with the game put in, and steps through it like
+//! `splendor-ai replay`: previous, next, playing itself out, or a click on a
+//! move. Each ply holds what anyone watching knew, see [`State::redacted`],
+//! so the page doesn't give away the order of the decks.
+
+use game_def::{Replay, State};
+use serde::Serialize;
+
+use crate::playback;
+
+const PAGE: &str = include_str!("replay.html");
+/// Where the game goes in [`PAGE`].
+const SLOT: &str = "{{REPLAY}}";
+
+#[derive(Serialize)]
+struct Ply {
+    /// Who did what to get here, see [`playback::describe`].
+    text: String,
+    state: State,
+    /// Face down cards left in each deck.
+    left: Vec<usize>,
+    /// Cards each player reserved face down.
+    face_down: Vec<usize>,
+}
+
+#[derive(Serialize)]
+struct Game {
+    plies: Vec<Ply>,
+    /// How it ended, see [`playback::outcome`].
+    outcome: String,
+}
+
+pub fn main(mut argv: impl Iterator<Item = String>) {
+    let mut path = None;
+    let mut output = None;
+    while let Some(arg) = argv.next() {
+        if arg == "-o" || arg == "--output" {
+            let Some(x) = argv.next() else {
+                println!("{arg} needs a path");
+                return;
+            };
+            output = Some(x);
+        } else if path.is_none() {
+            path = Some(arg);
+        } else {
+            println!("Unexpected argument {arg}");
+            return;
+        }
+    }
+    let (Some(path), Some(output)) = (path, output) else {
+        println!("Usage: splendor-ai export-html <replay> -o <file>");
+        return;
+    };
+    let loaded = Replay::load(&path).and_then(|replay| {
+        let states = replay.checked_states()?;
+        Ok((replay, states))
+    });
+    let (replay, states) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            println!("Error: {e:#}");
+            return;
+        }
+    };
+    let plies = states
+        .iter()
+        .enumerate()
+        .map(|(ply, state)| Ply {
+            text: playback::describe(&replay, &states, ply),
+            state: state.redacted(),
+            left: (state.decks.values())
+                .map(|deck| deck.len().saturating_sub(state.config.visible_cards))
+                .collect(),
+            face_down: (state.players.iter())
+                .map(|p| p.reserved_blind.iter().filter(|&&blind| blind).count())
+                .collect(),
+        })
+        .collect();
+    let game = Game {
+        plies,
+        outcome: playback::outcome(&replay.result, states.last().unwrap()),
+    };
+    // A name with `</script>` in it would end the script early.
+    let json = serde_json::to_string(&game).unwrap().replace("</", "<\\/");
+    match std::fs::write(&output, PAGE.replacen(SLOT, &json, 1)) {
+        Ok(()) => println!("Wrote {} plies to {output}", game.plies.len()),
+        Err(e) => println!("Failed to write {output}: {e}"),
+    }
+}
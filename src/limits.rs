@@ -0,0 +1,221 @@
+//! What an agent process may use, for games against programs nobody
+//! checked: `--agent-mem 2G` caps its address space, `--agent-cpus 1` pins
+//! it to that many CPUs, and `--agent-sandbox <prefix>` runs every agent
+//! command after a prefix like `systemd-run --scope -p TasksMax=64 --`, for
+//! anything else. Agent processes never get the file descriptors of the
+//! runner either way.
+//!
+//! The limits are for every agent the runner starts. Built-in agents and
+//! those over TCP or WebSocket are not started by the runner, so they are
+//! not limited.
+
+use std::{
+    process::{Command, ExitStatus},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use crate::split_command;
+
+/// Set by the flags, read by every agent started afterwards.
+static LIMITS: Mutex<Limits> = Mutex::new(Limits {
+    memory: None,
+    cpus: None,
+    sandbox: Vec::new(),
+});
+
+/// How many agents were pinned, so the next goes on the next CPUs.
+static PINNED: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone)]
+pub struct Limits {
+    /// In bytes.
+    pub memory: Option<u64>,
+    pub cpus: Option<usize>,
+    pub sandbox: Vec<String>,
+}
+
+/// Reads `arg` and its value if it is one of the limits, like
+/// [`crate::tournament::Settings::flag`]. Returns whether it was, or `None`
+/// after printing what is wrong with the value.
+pub fn flag(arg: &str, argv: &mut impl Iterator<Item = String>) -> Option<bool> {
+    let mut limits = LIMITS.lock().unwrap();
+    match arg {
+        "--agent-mem" => {
+            let Some(bytes) = argv.next().as_deref().and_then(parse_size) else {
+                say!("--agent-mem needs a size like 512M or 2G");
+                return None;
+            };
+            if cfg!(unix) {
+                limits.memory = Some(bytes);
+            } else {
+                say!("--agent-mem only works on Unix, agents can use any memory");
+            }
+        }
+        "--agent-cpus" => {
+            let Some(cpus) = argv.next().and_then(|x| x.parse().ok()).filter(|&n| n > 0) else {
+                say!("--agent-cpus needs a positive number");
+                return None;
+            };
+            if cfg!(target_os = "linux") {
+                limits.cpus = Some(cpus);
+            } else {
+                say!("--agent-cpus only works on Linux, agents can use every CPU");
+            }
+        }
+        "--agent-sandbox" => {
+            let Some(prefix) = argv.next() else {
+                say!("--agent-sandbox needs a command to run agents with");
+                return None;
+            };
+            match split_command(&prefix) {
+                Ok(words) => limits.sandbox = words,
+                Err(e) => {
+                    say!("{e:#}");
+                    return None;
+                }
+            }
+        }
+        _ => return Some(false),
+    }
+    Some(true)
+}
+
+/// Like `512M`, `2G` or a plain number of bytes.
+fn parse_size(size: &str) -> Option<u64> {
+    let (number, unit) = match size.char_indices().find(|(_, c)| c.is_ascii_alphabetic()) {
+        Some((i, _)) => size.split_at(i),
+        None => (size, ""),
+    };
+    let unit: u64 = match unit.to_ascii_uppercase().trim_end_matches(['B', 'I']) {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return None,
+    };
+    number.trim().parse::<u64>().ok()?.checked_mul(unit)
+}
+
+/// Like `2G`, for people to read.
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 30 && b % (1 << 30) == 0 => format!("{}G", b >> 30),
+        b if b >= 1 << 20 && b % (1 << 20) == 0 => format!("{}M", b >> 20),
+        b => format!("{b} bytes"),
+    }
+}
+
+impl Limits {
+    pub fn current() -> Limits {
+        LIMITS.lock().unwrap().clone()
+    }
+
+    /// The agent `command` under the sandbox, if any, with the limits applied
+    /// to it once it is started.
+    pub fn command(&self, command: &[String]) -> Command {
+        let command: Vec<&String> = self.sandbox.iter().chain(command).collect();
+        let mut process = Command::new(command[0]);
+        process.args(&command[1..]);
+        #[cfg(unix)]
+        self.apply(&mut process);
+        process
+    }
+
+    #[cfg(unix)]
+    fn apply(&self, process: &mut Command) {
+        use std::os::unix::process::CommandExt;
+
+        let memory = self.memory;
+        #[cfg(target_os = "linux")]
+        let cpus = self.cpus.map(pick_cpus);
+        // Only calls that don't allocate, between fork and exec.
+        let limit = move || {
+            if let Some(bytes) = memory {
+                let limit = libc::rlimit {
+                    rlim_cur: bytes as libc::rlim_t,
+                    rlim_max: bytes as libc::rlim_t,
+                };
+                if unsafe { libc::setrlimit(libc::RLIMIT_AS, &limit) } != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            #[cfg(target_os = "linux")]
+            if let Some(cpus) = &cpus {
+                let size = std::mem::size_of::<libc::cpu_set_t>();
+                if unsafe { libc::sched_setaffinity(0, size, cpus) } != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            close_inherited();
+            Ok(())
+        };
+        unsafe { process.pre_exec(limit) };
+    }
+
+    /// What a limit has to do with the agent dying with `status`, if it
+    /// looks like one killed it.
+    pub fn explain(&self, status: ExitStatus) -> Option<String> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+
+            let bytes = self.memory?;
+            // Rust aborts when an allocation fails, C tends to crash.
+            matches!(
+                status.signal()?,
+                libc::SIGABRT | libc::SIGKILL | libc::SIGSEGV | libc::SIGBUS
+            )
+            .then(|| format!("likely over its memory limit of {}", format_size(bytes)))
+        }
+        #[cfg(not(unix))]
+        {
+            _ = status;
+            None
+        }
+    }
+}
+
+/// The next `count` of the CPUs the runner may use, so agents of games
+/// played at once don't share them while there are enough.
+#[cfg(target_os = "linux")]
+fn pick_cpus(count: usize) -> libc::cpu_set_t {
+    let size = std::mem::size_of::<libc::cpu_set_t>();
+    let mut allowed: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    unsafe { libc::sched_getaffinity(0, size, &mut allowed) };
+    let allowed: Vec<usize> = (0..libc::CPU_SETSIZE as usize)
+        .filter(|&cpu| unsafe { libc::CPU_ISSET(cpu, &allowed) })
+        .collect();
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    let first = PINNED.fetch_add(1, Ordering::Relaxed) * count;
+    for i in 0..count.min(allowed.len()) {
+        unsafe { libc::CPU_SET(allowed[(first + i) % allowed.len()], &mut set) };
+    }
+    set
+}
+
+/// Marks every descriptor past stderr close-on-exec, so the agent doesn't get
+/// those the runner inherited. Ours are already, the standard library opens
+/// them so.
+#[cfg(unix)]
+fn close_inherited() {
+    #[cfg(target_os = "linux")]
+    if unsafe {
+        libc::syscall(
+            libc::SYS_close_range,
+            3,
+            u32::MAX,
+            libc::CLOSE_RANGE_CLOEXEC,
+        )
+    } == 0
+    {
+        return;
+    }
+    // Without close_range, one by one.
+    let max = unsafe { libc::sysconf(libc::_SC_OPEN_MAX) };
+    for fd in 3..max.clamp(3, 1 << 16) as libc::c_int {
+        unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+    }
+}
@@ -1,281 +1,2433 @@
 use std::{
-    io::{BufRead, BufReader, Write},
-    process::{Child, ChildStdin, ChildStdout, Stdio},
+    cell::Cell,
+    collections::VecDeque,
+    io::{BufRead, BufReader, IsTerminal, Write},
+    process::{ExitCode, Stdio},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, SyncSender, TrySendError},
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use alpha_beta_agent::Difficulty;
+use clap::error::ErrorKind;
+use clap_repl::{
+    reedline::{DefaultPrompt, Signal},
+    ClapEditor, ReadCommandOutput,
+};
+use game_def::{
+    repl::Command,
+    transcript::{Direction, Transcript},
+    Action, AgentMessage, EndReason, Forfeit, GameEvent, GameResult, InvalidAttempt,
+    ObserverMessage, Replay, Restart, RuleError, RunnerMessage, ScoreBreakdown, State,
+    Substitution, Timing, PROTOCOL_VERSION,
 };
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Serialize;
 
-use clap_repl::ReadCommandOutput;
-use enum_map::enum_map;
-use game_def::{Action, Card, Nobel, Player, ResourceKind, ResourceMap, State};
-use rand::seq::SliceRandom;
+/// Set by `--output json`, which keeps stdout for the summary.
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Set by `--color`: boards in boxes, and whether they are painted.
+static PRETTY: AtomicBool = AtomicBool::new(false);
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Set by `--hotseat`: boards leave out the blind reserves of the other
+/// players.
+static HOTSEAT: AtomicBool = AtomicBool::new(false);
+
+/// `println!`, or `eprintln!` with `--output json`, or a line of the
+/// `--tui` screen.
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if crate::tui::active() {
+            crate::tui::say(format!($($arg)*))
+        } else if crate::JSON_OUTPUT.load(std::sync::atomic::Ordering::Relaxed) {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+mod arena;
+mod args;
+mod builtin;
+mod checkpoint;
+mod conformance;
+mod elo;
+mod events;
+mod gauntlet;
+mod hint;
+mod history;
+mod html;
+mod limits;
+mod matchup;
+mod notation;
+mod playback;
+mod run_config;
+mod saved;
+mod spectator;
+mod stats;
+mod tournament;
+mod transport;
+mod tui;
+mod websocket;
+
+use args::{AgentArg, Args, OnInvalid, DEFAULT_MAX_ROUNDS, DEFAULT_MOVE_TIMEOUT};
+use builtin::Builtin;
+use events::{Event, EventLog};
+use hint::{Advisor, HintAgent};
+use history::{Claim, History};
+use limits::Limits;
+use saved::SavedGame;
+use spectator::Spectators;
+use stats::Termination;
+use transport::{AgentLog, AgentThread, Exit, Process, TcpAddress, TranscriptSlot, Transport};
+use websocket::WsAddress;
 
 enum Agent {
     Human {
         name: String,
     },
     AI {
-        #[allow(unused)]
-        process: Child,
-        reader: BufReader<ChildStdout>,
-        writer: ChildStdin,
+        transport: Box<dyn Transport>,
+        /// Each action the agent writes, read on a separate thread so a
+        /// silent agent can be timed out.
+        replies: Receiver<anyhow::Result<Action>>,
+        /// Only `None` while the agent is being dropped.
+        writer: Option<Box<dyn Write>>,
         name: String,
+        /// Talks MessagePack frames instead of JSON lines.
+        msgpack: bool,
+        /// Speaks the message protocol of [`RunnerMessage`] instead of a bare
+        /// stream of observations.
+        messages: bool,
+        /// Thinking time left for the rest of the game, with `--time-budget`.
+        clock: Option<Duration>,
+        transcript: TranscriptSlot,
+        /// Set once it answered [`AgentMessage::NewGames`], so it may play
+        /// another game when this one is over, see `--reuse-agents`.
+        new_games: std::sync::Arc<AtomicBool>,
     },
 }
 
+/// An agent on the command line, before it is started.
+enum Seat {
+    Human,
+    /// Split by [`split_command`].
+    Command(Vec<String>),
+    Builtin(Builtin),
+    /// Connected and past the hello.
+    Tcp(std::net::TcpStream, BufReader<std::net::TcpStream>),
+    /// The seat of a WebSocket agent on the [`websocket::Server`].
+    WebSocket(usize),
+}
+
+/// Splits the command line of an agent into words like a shell, so
+/// `"python3 bot.py --depth 5"` works. The path of an existing file is kept
+/// whole, spaces and all. There is no WASM runtime in the runner, so a
+/// `wasm:<module>` agent is refused here, before the game starts.
+fn split_command(command: &str) -> std::io::Result<Vec<String>> {
+    if let Some(module) = command.strip_prefix("wasm:") {
+        let message = format!(
+            "WASM agents can't run in-process, start {module} under a WASI runtime \
+             like `wasmtime {module}` instead"
+        );
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            message,
+        ));
+    }
+    if std::path::Path::new(command).is_file() {
+        return Ok(vec![command.to_owned()]);
+    }
+    match shlex::split(command) {
+        Some(words) if !words.is_empty() => Ok(words),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("`{command}` is not a valid command line"),
+        )),
+    }
+}
+
+/// What an agent started with `words` is called without `--name`: the file
+/// name of its program.
+fn program_name(words: &[String]) -> String {
+    let program = std::path::Path::new(&words[0]);
+    match program.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => words[0].clone(),
+    }
+}
+
+/// Numbers the names that appear more than once, so two copies of an agent
+/// become `name-1` and `name-2`.
+fn unique_names(names: Vec<String>) -> Vec<String> {
+    let mut seen = std::collections::HashMap::<String, usize>::new();
+    let numbered: Vec<_> = names
+        .iter()
+        .map(|name| {
+            let count = seen.entry(name.clone()).or_default();
+            *count += 1;
+            *count
+        })
+        .collect();
+    names
+        .iter()
+        .zip(numbered)
+        .map(|(name, i)| match seen[name] {
+            1 => name.clone(),
+            _ => format!("{name}-{i}"),
+        })
+        .collect()
+}
+
+impl Seat {
+    /// The level it plays at when it gives one: the `difficulty=` of a
+    /// built-in agent, or the `--difficulty` of a program.
+    fn difficulty(&self) -> Option<Difficulty> {
+        match self {
+            Seat::Builtin(builtin) => builtin.difficulty(),
+            Seat::Command(words) => {
+                let k = words.iter().position(|word| word == "--difficulty")?;
+                let difficulty: Difficulty = words.get(k + 1)?.parse().ok()?;
+                (difficulty != Difficulty::Custom).then_some(difficulty)
+            }
+            _ => None,
+        }
+    }
+}
+
 impl Agent {
+    /// Starts `command`: a built-in agent after `builtin:`, see
+    /// [`crate::builtin`], or a program split by [`split_command`].
+    fn start(
+        command: &str,
+        name: String,
+        msgpack: bool,
+        messages: bool,
+        time_budget: Option<Duration>,
+        log: AgentLog,
+    ) -> anyhow::Result<Agent> {
+        if let Some(spec) = command.strip_prefix("builtin:") {
+            return Ok(Agent::builtin(Builtin::parse(spec)?, name, time_budget)?);
+        }
+        let words = split_command(command)?;
+        Ok(Agent::spawn(
+            &words,
+            name,
+            msgpack,
+            messages,
+            time_budget,
+            log,
+        )?)
+    }
+
+    /// Starts the program in `command`, split by [`split_command`], as an
+    /// agent called `name` with a clock of `time_budget` if any.
+    fn spawn(
+        command: &[String],
+        name: String,
+        msgpack: bool,
+        messages: bool,
+        time_budget: Option<Duration>,
+        log: AgentLog,
+    ) -> std::io::Result<Agent> {
+        let limits = Limits::current();
+        let mut process = limits.command(command);
+        if msgpack {
+            process.args(["--format", "msgpack"]);
+        }
+        if messages {
+            process.args(["--protocol", "messages"]);
+        }
+        // Ctrl+C in the terminal is for the runner, which stops its agents
+        // once the game is saved.
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut process, 0);
+        let mut process = process
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let reader = BufReader::new(process.stdout.take().unwrap());
+        let writer = process.stdin.take().unwrap();
+        let (log, tail) = match log.spawn(process.stderr.take().unwrap()) {
+            Ok(log) => log,
+            Err(e) => {
+                _ = process.kill();
+                _ = process.wait();
+                return Err(e);
+            }
+        };
+        let process = Process {
+            child: process,
+            log,
+            tail,
+            limits,
+        };
+        let transcript = TranscriptSlot::default();
+        let new_games = std::sync::Arc::default();
+        Ok(Agent::AI {
+            transport: Box::new(process),
+            replies: spawn_reader(reader, msgpack, transcript.clone(), &new_games),
+            writer: Some(Box::new(writer)),
+            name,
+            msgpack,
+            messages,
+            clock: time_budget,
+            transcript,
+            new_games,
+        })
+    }
+
+    /// An agent at the other end of `stream`, which [`TcpAddress::connect`]
+    /// set up.
+    fn tcp(
+        stream: std::net::TcpStream,
+        reader: BufReader<std::net::TcpStream>,
+        name: String,
+        msgpack: bool,
+        messages: bool,
+        time_budget: Option<Duration>,
+    ) -> std::io::Result<Agent> {
+        let transcript = TranscriptSlot::default();
+        let new_games = std::sync::Arc::default();
+        Ok(Agent::AI {
+            writer: Some(Box::new(stream.try_clone()?)),
+            transport: Box::new(stream),
+            replies: spawn_reader(reader, msgpack, transcript.clone(), &new_games),
+            name,
+            msgpack,
+            messages,
+            clock: time_budget,
+            transcript,
+            new_games,
+        })
+    }
+
+    /// `builtin` on a thread of the runner, which talks the message protocol.
+    fn builtin(
+        builtin: Builtin,
+        name: String,
+        time_budget: Option<Duration>,
+    ) -> std::io::Result<Agent> {
+        let (thread, writer, reader) = builtin.start()?;
+        let transcript = TranscriptSlot::default();
+        let new_games = std::sync::Arc::default();
+        Ok(Agent::AI {
+            transport: Box::new(AgentThread(thread)),
+            replies: spawn_reader(reader, false, transcript.clone(), &new_games),
+            writer: Some(Box::new(writer)),
+            name,
+            msgpack: false,
+            messages: true,
+            clock: time_budget,
+            transcript,
+            new_games,
+        })
+    }
+
+    /// The agent in seat `slot` of `server`, which always talks JSON.
+    fn websocket(
+        server: &std::sync::Arc<websocket::Server>,
+        slot: usize,
+        name: String,
+        messages: bool,
+        time_budget: Option<Duration>,
+    ) -> Agent {
+        let transcript = TranscriptSlot::default();
+        let (transport, writer, replies) = server.agent(slot, name.clone(), transcript.clone());
+        Agent::AI {
+            transport,
+            replies,
+            writer: Some(writer),
+            name,
+            msgpack: false,
+            messages,
+            clock: time_budget,
+            transcript,
+            new_games: std::sync::Arc::default(),
+        }
+    }
+
     fn name(&self) -> String {
         match self {
-            Agent::Human { name } => format!("Human {name}"),
-            Agent::AI { name, .. } => format!("AI {name}"),
-        }
-    }
-}
-
-fn main() {
-    let deck0 = enum_map![
-        ResourceKind::Black => vec![
-            (0, "1w+1u+1g+1r"),
-            (0, "1w+2u+1g+1r"),
-            (0, "2w+2u+1r"),
-            (0, "1g+3r+1k"),
-            (0, "2g+1r"),
-            (0, "2w+2g"),
-            (0, "3g"),
-            (1, "4u"),
-        ],
-        ResourceKind::Blue => vec![
-            (0, "1w+1g+1r+1k"),
-            (0, "1w+1g+2r+1k"),
-            (0, "1w+2g+2r"),
-            (0, "1u+3g+1r"),
-            (0, "1w+2k"),
-            (0, "2g+2k"),
-            (0, "3k"),
-            (1, "4r"),
-        ],
-        ResourceKind::White => vec![
-            (0, "1u+1g+1r+1k"),
-            (0, "1u+2g+1r+1k"),
-            (0, "2u+2g+1k"),
-            (0, "3w+1u+1k"),
-            (0, "2r+1k"),
-            (0, "2u+2k"),
-            (0, "3u"),
-            (1, "4g"),
-        ],
-        ResourceKind::Green => vec![
-            (0, "1w+1u+1r+1k"),
-            (0, "1w+1u+1r+2k"),
-            (0, "1u+2r+2k"),
-            (0, "1w+3u+1g"),
-            (0, "2w+1u"),
-            (0, "2u+2r"),
-            (0, "3r"),
-            (1, "4k"),
-        ],
-        ResourceKind::Red => vec![
-            (0, "1w+1u+1g+1k"),
-            (0, "2w+1u+1g+1k"),
-            (0, "2w+1g+2k"),
-            (0, "1w+1r+3k"),
-            (0, "2u+1g"),
-            (0, "2w+2r"),
-            (0, "3w"),
-            (1, "4w"),
-        ],
-    ];
-    let deck1 = enum_map![
-        ResourceKind::Black => vec![
-            (1, "3w+2u+2g"),
-            (1, "3w+3g+2k"),
-            (2, "1u+4g+2r"),
-            (2, "5g+3r"),
-            (2, "5w"),
-            (3, "6k"),
-        ],
-        ResourceKind::Blue => vec![
-            (1, "2u+2g+3r"),
-            (1, "2u+3g+3k"),
-            (2, "5w+3u"),
-            (2, "2w+1r+4k"),
-            (2, "5u"),
-            (3, "6u"),
-        ],
-        ResourceKind::White => vec![
-            (1, "3g+2r+2k"),
-            (1, "2w+3u+3r"),
-            (2, "1g+4r+2k"),
-            (2, "5r+3k"),
-            (2, "5r"),
-            (3, "6w"),
-        ],
-        ResourceKind::Green => vec![
-            (1, "3w+2g+3r"),
-            (1, "2w+3u+2k"),
-            (2, "4w+2u+1k"),
-            (2, "5u+3g"),
-            (2, "5g"),
-            (3, "6g"),
-        ],
-        ResourceKind::Red => vec![
-            (1, "2w+2r+3k"),
-            (1, "3u+2r+3k"),
-            (2, "1w+4u+2g"),
-            (2, "3w+5k"),
-            (2, "5k"),
-            (3, "6r"),
-        ],
-    ];
-    let deck2 = enum_map![
-        ResourceKind::Black => vec![
-            (3, "3w+3u+5g+3r"),
-            (4, "7r"),
-            (4, "3g+6r+3k"),
-            (5, "7r+3k"),
-        ],
-        ResourceKind::Blue => vec![
-            (3, "3w+3g+3r+5k"),
-            (4, "7w"),
-            (4, "6w+3u+3k"),
-            (5, "7w+3u"),
-        ],
-        ResourceKind::White => vec![
-            (3, "3u+3g+5r+3k"),
-            (4, "7k"),
-            (4, "3w+3r+6k"),
-            (5, "3w+7k"),
-        ],
-        ResourceKind::Green => vec![
-            (3, "5w+3u+3r+3k"),
-            (4, "7u"),
-            (4, "3w+6u+3g"),
-            (5, "7u+3g"),
-        ],
-        ResourceKind::Red => vec![
-            (3, "3w+5u+3g+3k"),
-            (4, "7g"),
-            (4, "3u+6g+3r"),
-            (5, "7g+3r"),
-        ],
-    ];
-    let mut nobels = vec![
-        "4r+4g", "4u+4w", "4k+4w", "4u+4g", "4k+4r", "3k+3r+3w", "3g+3u+3r", "3g+3u+3w",
-        "3k+3u+3w", "3k+3r+3g",
-    ];
-    let decks = [deck0, deck1, deck2];
-    let mut agents = std::env::args()
-        .skip(1)
-        .map(|arg| {
-            if let Some(name) = arg.strip_prefix("human-") {
-                Agent::Human {
-                    name: name.to_owned(),
+            Agent::Human { name } | Agent::AI { name, .. } => name.clone(),
+        }
+    }
+
+    /// What is left of `--time-budget`, `None` for humans.
+    fn clock(&self) -> Option<Duration> {
+        match self {
+            Agent::Human { .. } => None,
+            Agent::AI { clock, .. } => *clock,
+        }
+    }
+
+    /// The agent again for another game, with a clock of `time_budget`, if
+    /// it answered [`AgentMessage::NewGames`]. Any other is stopped as it is
+    /// dropped.
+    fn next_game(mut self, time_budget: Option<Duration>) -> Option<Agent> {
+        let Agent::AI {
+            clock, new_games, ..
+        } = &mut self
+        else {
+            return None;
+        };
+        if !new_games.load(Ordering::Relaxed) {
+            return None;
+        }
+        *clock = time_budget;
+        Some(self)
+    }
+
+    /// Sends a JSON line or MessagePack frame to an AI, does nothing for humans.
+    fn send(&mut self, message: &impl Serialize) -> anyhow::Result<()> {
+        let Agent::AI {
+            writer: Some(writer),
+            msgpack,
+            transcript,
+            ..
+        } = self
+        else {
+            return Ok(());
+        };
+        let wire = game_def::encode_message(message, *msgpack)?;
+        if let Some(transcript) = transcript.get() {
+            transcript.message(Direction::ToAgent, &wire);
+        }
+        writer.write_all(&wire)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes the agent's messages to `path` from now on, see `--transcript-dir`.
+    fn transcribe(&self, path: &std::path::Path) -> anyhow::Result<()> {
+        if let Agent::AI {
+            msgpack,
+            messages,
+            transcript,
+            ..
+        } = self
+        {
+            _ = transcript.set(Transcript::create(path, *msgpack, *messages)?);
+        }
+        Ok(())
+    }
+
+    /// Adds a note to the agent's transcript, if it has one.
+    fn note(&self, text: &str) {
+        if let Agent::AI { transcript, .. } = self {
+            if let Some(transcript) = transcript.get() {
+                transcript.note(text);
+            }
+        }
+    }
+
+    /// Sends the agent a message, in whichever protocol it speaks, if it wants one.
+    fn notify(&mut self, message: RunnerMessage) {
+        if matches!(self, Agent::AI { messages: true, .. }) {
+            // The agent may be gone already, it is not our problem then.
+            _ = self.send(&message);
+        }
+    }
+}
+
+impl Drop for Agent {
+    /// Closes the input of an AI, which tells it to exit, and then its
+    /// [`Transport`].
+    fn drop(&mut self) {
+        let Agent::AI {
+            transport, writer, ..
+        } = self
+        else {
+            return;
+        };
+        drop(writer.take());
+        transport.close();
+    }
+}
+
+/// Reads the actions of an agent until it closes its output, or the
+/// connection to it fails. An [`AgentMessage::NewGames`] sets `new_games`
+/// instead.
+fn spawn_reader(
+    mut reader: impl BufRead + Send + 'static,
+    msgpack: bool,
+    transcript: TranscriptSlot,
+    new_games: &std::sync::Arc<AtomicBool>,
+) -> Receiver<anyhow::Result<Action>> {
+    let (sender, receiver) = channel();
+    let new_games = new_games.clone();
+    std::thread::spawn(move || {
+        while let Ok(Some(wire)) = transport::read_wire(&mut reader, msgpack) {
+            if let Some(transcript) = transcript.get() {
+                transcript.message(Direction::FromAgent, &wire);
+            }
+            if let Ok(AgentMessage::NewGames) = transport::decode_wire(&wire, msgpack) {
+                new_games.store(true, Ordering::Relaxed);
+                continue;
+            }
+            if sender.send(transport::decode_wire(&wire, msgpack)).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+/// Why an AI didn't give an action it was asked for.
+enum Failure {
+    TimedOut(Duration),
+    /// Used up the time budget of the whole game.
+    OutOfTime(Duration),
+    /// Closed its output, or its input, with how it ended if it is a process.
+    Closed(Option<Exit>),
+    Garbled(anyhow::Error),
+}
+
+impl Failure {
+    fn termination(&self) -> Termination {
+        match self {
+            Failure::TimedOut(_) | Failure::OutOfTime(_) => Termination::Timeout,
+            Failure::Closed(_) | Failure::Garbled(_) => Termination::Crash,
+        }
+    }
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Failure::TimedOut(elapsed) => write!(f, "timed out after {elapsed:.1?}"),
+            Failure::OutOfTime(elapsed) => write!(f, "lost on time after {elapsed:.1?}"),
+            Failure::Closed(Some(Exit {
+                status: Some(status),
+                limit,
+                ..
+            })) => match limit {
+                Some(limit) => write!(f, "exited ({status}) while thinking, {limit}"),
+                None => write!(f, "exited ({status}) while thinking"),
+            },
+            Failure::Closed(_) => write!(f, "closed its output"),
+            Failure::Garbled(e) => write!(f, "sent something that is not an action: {e:#}"),
+        }
+    }
+}
+
+/// Tells the agents in the other seats that `player` played `action`.
+fn broadcast_played(agents: &mut [Agent], player: usize, action: &Action) {
+    for (seat, agent) in agents.iter_mut().enumerate() {
+        if seat != player {
+            agent.notify(RunnerMessage::Played {
+                player,
+                action: action.clone(),
+            });
+        }
+    }
+}
+
+/// Sends the observation of the player to move to `agent`, an AI, and waits
+/// for its action, charging the time it took to its clock.
+///
+/// Up to `retries` actions `state` refuses are passed to `rejected` and the
+/// agent is asked again, in the same time. The last action is returned even
+/// if it is invalid.
+fn request_move(
+    agent: &mut Agent,
+    state: &State,
+    move_timeout: Duration,
+    increment: Duration,
+    mut retries: u32,
+    mut rejected: impl FnMut(&Action, &RuleError),
+) -> (Result<Action, Failure>, Duration) {
+    let started = Instant::now();
+    let mut rejection = None;
+    let reply = loop {
+        let Agent::AI {
+            messages, clock, ..
+        } = agent
+        else {
+            unreachable!("humans are not asked for moves")
+        };
+        let observation = Box::new(state.observation_for(state.turn));
+        let time_left_ms =
+            clock.map(|left| left.saturating_sub(started.elapsed()).as_millis() as u64);
+        // Agents without the message protocol can't be told why, they only
+        // see the same observation again.
+        let sent = match rejection.take() {
+            _ if !*messages => agent.send(&observation),
+            None => agent.send(&RunnerMessage::MoveRequest {
+                observation,
+                time_left_ms,
+            }),
+            Some((action, error)) => agent.send(&RunnerMessage::Rejected {
+                action,
+                error,
+                observation,
+                time_left_ms,
+            }),
+        };
+        if sent.is_err() {
+            break Err(RecvTimeoutError::Disconnected);
+        }
+        let Agent::AI { replies, clock, .. } = agent else {
+            unreachable!()
+        };
+        let limit = clock.map_or(move_timeout, |left| left.min(move_timeout));
+        match replies.recv_timeout(limit.saturating_sub(started.elapsed())) {
+            Ok(Ok(action)) if retries > 0 => match state.check_action(&action) {
+                Ok(()) => break Ok(Ok(action)),
+                Err(error) => {
+                    agent.note(&format!("{action} is invalid: {error}, asking again"));
+                    rejected(&action, &error);
+                    retries -= 1;
+                    rejection = Some((action, error));
                 }
-            } else {
-                let mut process = std::process::Command::new(&arg)
-                    .stdin(Stdio::piped())
-                    .stdout(Stdio::piped())
-                    .spawn()
-                    .unwrap();
-                let reader = BufReader::new(process.stdout.take().unwrap());
-                let writer = process.stdin.take().unwrap();
-                Agent::AI {
-                    process,
-                    reader,
-                    writer,
-                    name: arg,
+            },
+            reply => break reply,
+        }
+    };
+    let Agent::AI {
+        clock, transport, ..
+    } = agent
+    else {
+        unreachable!()
+    };
+    let elapsed = started.elapsed();
+    let flagged = clock.is_some_and(|left| elapsed >= left);
+    if let Some(left) = clock {
+        *left = left.saturating_sub(elapsed) + increment;
+    }
+    let reply = match reply {
+        _ if flagged => Err(Failure::OutOfTime(elapsed)),
+        Ok(Ok(action)) => Ok(action),
+        Ok(Err(e)) => Err(Failure::Garbled(e)),
+        Err(RecvTimeoutError::Timeout) => Err(Failure::TimedOut(elapsed)),
+        Err(RecvTimeoutError::Disconnected) => Err(Failure::Closed(transport.exit())),
+    };
+    if let Err(failure) = &reply {
+        agent.note(&failure.to_string());
+    }
+    (reply, elapsed)
+}
+
+/// Exit code of a second Ctrl+C, which doesn't wait for anything.
+const EXIT_FORCED: i32 = 130;
+
+/// Sets the returned flag on Ctrl+C, for the game loop to save the game and
+/// stop once the move in progress is done. A second Ctrl+C exits right away.
+/// Without a handler, Ctrl+C kills the runner as usual.
+fn catch_interrupts() -> std::sync::Arc<AtomicBool> {
+    use signal_hook::{consts::SIGINT, flag};
+
+    let interrupted = std::sync::Arc::new(AtomicBool::new(false));
+    // Registered first, so it only sees the flag a previous Ctrl+C set.
+    if flag::register_conditional_shutdown(SIGINT, EXIT_FORCED, interrupted.clone())
+        .and_then(|_| flag::register(SIGINT, interrupted.clone()))
+        .is_err()
+    {
+        say!("Failed to catch Ctrl+C, it won't save the game");
+    }
+    interrupted
+}
+
+/// Exit code of a game that stopped without a result, see `--on-invalid`.
+const EXIT_TERMINATED: u8 = 2;
+/// Exit code of a game where an AI crashed or sent something that is not an
+/// action, whether the game went on without it or not.
+const EXIT_CRASHED: u8 = 3;
+
+/// What `--output json` prints once the game is over.
+#[derive(Serialize)]
+struct Summary {
+    agents: Vec<AgentSummary>,
+    seed: u64,
+    /// The seat that moved first, see `--first`.
+    first: usize,
+    /// Turns played, by all players together.
+    turns: usize,
+    rounds: usize,
+    /// `ongoing` when the game stopped without a result.
+    result: GameResult,
+    /// Why the game stopped without a result.
+    terminated: Option<String>,
+    /// How the game finished, `round_limit` when it was adjudicated by score.
+    end_reason: Option<EndReason>,
+    forfeits: Vec<Forfeit>,
+    duration_ms: u64,
+    /// The nobels that visited someone, in order.
+    nobels: Vec<Claim>,
+    /// What `--config` came to, if there was one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    run_config: Option<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+struct AgentSummary {
+    name: String,
+    /// As given on the command line, see `controllers` for the agents that
+    /// took over.
+    agent: String,
+    score: u8,
+    /// 1 for the winners, shared on a tie, see [`State::placements`].
+    place: usize,
+    #[serde(flatten)]
+    breakdown: ScoreBreakdown,
+    /// Time spent on its moves, waiting for a human included.
+    think_ms: u64,
+    timing: Timing,
+    /// The time of each move.
+    move_ms: Vec<u64>,
+    /// Who played the seat when it changed hands, in order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    controllers: Vec<ControllerSummary>,
+    /// How it ended if it crashed, for debugging.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit: Option<Exit>,
+    /// Times it was started again after a crash, see `--on-crash`.
+    restarts: u32,
+    /// What it started with on top of the others, see `--handicap`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    handicap: Option<game_def::Handicap>,
+    /// The level it played at, see [`Seat::difficulty`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    difficulty: Option<String>,
+}
+
+/// One of the agents that played a seat, and only its moves.
+#[derive(Serialize)]
+struct ControllerSummary {
+    agent: String,
+    /// The first of the seat's moves it made, counted from 0.
+    from_move: usize,
+    timing: Timing,
+}
+
+/// The agents that played a seat from `controllers`, each from which of its
+/// moves in `move_ms` on, or none if the seat never changed hands.
+fn controller_summaries(
+    controllers: &[(usize, String)],
+    move_ms: &[u64],
+) -> Vec<ControllerSummary> {
+    if controllers.len() == 1 {
+        return vec![];
+    }
+    controllers
+        .iter()
+        .enumerate()
+        .map(|(i, (from, agent))| {
+            let to = controllers.get(i + 1).map_or(move_ms.len(), |next| next.0);
+            ControllerSummary {
+                agent: agent.clone(),
+                from_move: *from,
+                timing: Timing::of(&move_ms[*from..to]),
+            }
+        })
+        .collect()
+}
+
+/// How many messages may queue up for a slow observer before it is detached.
+const OBSERVER_BUFFER: usize = 1024;
+/// How long we give observers to drain their queue once the game is over.
+const OBSERVER_GRACE: Duration = Duration::from_secs(2);
+
+/// Waits until `thread` is done, or for at most `grace`.
+fn wait_finished(thread: &JoinHandle<()>, grace: Duration) {
+    let deadline = Instant::now() + grace;
+    while !thread.is_finished() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// A process that receives every [`ObserverMessage`] but never acts. Writes
+/// happen on a separate thread, so a slow or crashed observer can never
+/// stall the game.
+struct Observer {
+    name: String,
+    sender: SyncSender<String>,
+    thread: JoinHandle<()>,
+}
+
+impl Observer {
+    /// Starts `command`, split by [`split_command`].
+    fn spawn(command: &str) -> std::io::Result<Self> {
+        let words = split_command(command)?;
+        let mut process = std::process::Command::new(&words[0])
+            .args(&words[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()?;
+        let mut writer = process.stdin.take().unwrap();
+        let (sender, receiver) = sync_channel::<String>(OBSERVER_BUFFER);
+        let thread = std::thread::spawn(move || {
+            for line in receiver {
+                if writeln!(writer, "{line}").is_err() {
+                    break;
                 }
             }
+            drop(writer);
+            _ = process.wait();
+        });
+        Ok(Observer {
+            name: command.to_owned(),
+            sender,
+            thread,
         })
-        .collect::<Vec<_>>();
-    if agents.len() < 2 {
-        println!("{} agent is not enough", agents.len());
+    }
+
+    /// Returns `false` once the observer is gone or too far behind, and
+    /// should be dropped.
+    fn send(&self, line: String) -> bool {
+        match self.sender.try_send(line) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                say!("Observer {} is lagging behind, detaching it", self.name);
+                false
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                say!("Observer {} stopped, detaching it", self.name);
+                false
+            }
+        }
+    }
+
+    fn finish(self) {
+        drop(self.sender);
+        wait_finished(&self.thread, OBSERVER_GRACE);
+    }
+}
+
+/// Sends `message` to every observer, and drops those that are gone or too
+/// far behind.
+fn observe(observers: &mut Vec<Observer>, message: &ObserverMessage) {
+    if observers.is_empty() {
         return;
     }
-    let mut state = State {
-        decks: decks
-            .into_iter()
-            .map(|d| {
-                let mut r: Vec<Card> = d
-                    .into_iter()
-                    .flat_map(|(c, l)| l.into_iter().map(move |(s, d)| (c, s, d)))
-                    .map(|(c, s, d)| Card::new(c, s, ResourceMap::from_code(d)))
-                    .collect();
-                r.shuffle(&mut rand::thread_rng());
-                r
-            })
-            .collect(),
-        nobels: {
-            nobels.shuffle(&mut rand::thread_rng());
-            nobels[0..agents.len() + 1]
+    let line = serde_json::to_string(message).unwrap();
+    observers.retain(|o| o.send(line.clone()));
+}
+
+/// Sends `state` to every observer, as much of it as `--observer-sees`
+/// lets them see.
+fn observe_state(observers: &mut Vec<Observer>, state: &State, public: bool) {
+    if observers.is_empty() {
+        return;
+    }
+    let state = match public {
+        true => state.redacted(),
+        false => state.clone(),
+    };
+    let state = Box::new(state);
+    observe(observers, &ObserverMessage::State { state });
+}
+
+/// Where each player's points came from and what they did over the game,
+/// once it is over, then the nobels and who they visited.
+fn print_report(state: &State, played: &History) {
+    let width = state
+        .players
+        .iter()
+        .map(|p| p.display_name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Player".len());
+    say!(
+        "{:<width$}  {:>6}  {:>5}  {:>6}  {:>10}  {:>5}  {:>8}  {:>9}  {:>5}",
+        "Player",
+        "Points",
+        "Cards",
+        "Nobels",
+        "Tier 1/2/3",
+        "Taken",
+        "Reserved",
+        "Gold used",
+        "Turns"
+    );
+    for (player, b) in state.players.iter().zip(state.final_scores()) {
+        let [one, two, three] = b.cards_by_tier;
+        say!(
+            "{:<width$}  {:>6}  {:>5}  {:>6}  {:>10}  {:>5}  {:>8}  {:>9}  {:>5}",
+            player.display_name,
+            player.score,
+            b.card_points,
+            b.noble_points,
+            format!("{one}/{two}/{three}"),
+            b.coins_taken,
+            b.reserves,
+            b.wilds_spent,
+            b.turns_taken,
+        );
+    }
+    let claims: Vec<String> = played
+        .claims()
+        .iter()
+        .map(|c| {
+            let name = &state.players[c.player].display_name;
+            format!("{} to {name} on move {}", c.nobel.cost, c.ply)
+        })
+        .collect();
+    let turns: usize = state.players.iter().map(|p| p.record.turns).sum();
+    match claims.is_empty() {
+        true => say!("{} rounds, {turns} turns, no nobels visited", state.round),
+        false => say!(
+            "{} rounds, {turns} turns, nobels {}",
+            state.round,
+            claims.join(", ")
+        ),
+    }
+}
+
+/// A table of how long each player took over its moves.
+fn print_timings(state: &State, timings: &[Timing]) {
+    let width = state
+        .players
+        .iter()
+        .map(|p| p.display_name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Time".len());
+    say!(
+        "{:<width$}  {:>5}  {:>8}  {:>7}  {:>7}",
+        "Time",
+        "Moves",
+        "Total",
+        "Mean",
+        "Max"
+    );
+    for (player, t) in state.players.iter().zip(timings) {
+        let [total, mean, max] = [t.total_ms, t.mean_ms, t.max_ms].map(Duration::from_millis);
+        say!(
+            "{:<width$}  {:>5}  {:>8}  {:>7}  {:>7}",
+            player.display_name,
+            t.moves,
+            brief(total),
+            brief(mean),
+            brief(max)
+        );
+    }
+}
+
+/// Like `2.3s`, or `40ms` under a second.
+fn brief(time: Duration) -> String {
+    match time.as_millis() {
+        ms @ 0..1000 => format!("{ms}ms"),
+        _ => format!("{:.1}s", time.as_secs_f64()),
+    }
+}
+
+/// Cheap look-ahead for humans: what the other players could do on their next turn.
+fn print_threats(state: &State) {
+    let mut lines = vec![];
+    for (i, p) in state.players.iter().enumerate() {
+        if i == state.turn {
+            continue;
+        }
+        for (deck, card) in state.affordable_cards(i) {
+            lines.push(format!(
+                "{} can buy deck {deck} card {card}: {:?} (reserve it to deny)",
+                p.display_name, state.decks[deck][card]
+            ));
+        }
+        for nobel in state.nobels_in_reach(i) {
+            lines.push(format!(
+                "{} is one card away from nobel {:?}",
+                p.display_name, state.nobels[nobel].cost
+            ));
+        }
+    }
+    if lines.is_empty() {
+        return;
+    }
+    say!("Threats:");
+    for line in lines {
+        say!("   {line}");
+    }
+}
+
+/// The cheat sheet printed by `help`.
+const HELP: &str = "\
+Moves, in short or as subcommands (add --help to one for its options):
+   take r g u        pick-three r g u    three different colors
+   take r r          pick-two r          two of one color
+   buy 2.3           purchase 2 3        the fourth card of tier 2
+   buy 2.3 pay=2r wilds=1                paying exactly this
+   buy-reserved 0    purchase-reserved 0
+   reserve 1.0       reserve 1 0
+   reserve-blind 3                       the top card of the tier 3 deck
+   discard 2r+1u     discard 2r+1u       when over the coin limit
+   nobel 1           choose-nobel 1      when more than one visits
+   skip, resign
+Colors are r(ed), u (blue), g(reen), w(hite) and k (black), or their names.
+Decks are numbered from 1 and cards from 0.
+   moves             lists the legal moves, play one by its number
+   board             prints the board again
+   history [n]       lists the moves so far, or the last n
+   hint              what the advisor would play, see --hint-agent
+   save <file>       writes the game to go on with it later, see --load-state
+   undo              takes back the last move of a human, see --allow-undo-ai
+   takeover <seat>   plays a seat yourself from its next turn, seats count from 0
+   handoff <seat> <command>
+                     starts an agent for the seat from its next turn
+   help              prints this
+Ctrl-D ends the game.";
+
+/// How many moves `undo` can take back.
+const UNDO_LIMIT: usize = 200;
+
+/// Adds a move to the history of `undo`, forgetting the oldest past
+/// [`UNDO_LIMIT`].
+fn remember(
+    history: &mut VecDeque<(State, usize, Action)>,
+    before: State,
+    player: usize,
+    action: Action,
+) {
+    if history.len() == UNDO_LIMIT {
+        history.pop_front();
+    }
+    history.push_back((before, player, action));
+}
+
+/// What to do after an AI move under `--step`.
+enum Pause {
+    Next,
+    /// Go on without stepping.
+    Continue,
+    Quit,
+    /// Ctrl+C, which saves the game.
+    Interrupted,
+    /// `takeover` or `handoff`, see [`Handover`].
+    Handover(Handover),
+}
+
+/// A seat changing hands, from its next turn on.
+struct Handover {
+    seat: usize,
+    /// The agent command for `handoff`, `None` for a human.
+    command: Option<String>,
+}
+
+/// Waits for Enter after an AI move, answering `board`, `moves` and
+/// `history` meanwhile.
+fn pause(
+    ed: &mut ClapEditor<Action>,
+    state: &State,
+    played: &History,
+    perspective: Option<usize>,
+) -> Pause {
+    say!("Enter for the next move, c to go on without stepping, q to quit");
+    loop {
+        let line = match tui::read_line(|| ed.get_editor().read_line(&DefaultPrompt::default())) {
+            Ok(Signal::Success(line)) => line,
+            Ok(Signal::CtrlC) => return Pause::Interrupted,
+            Ok(Signal::CtrlD) => return Pause::Quit,
+            Err(e) => {
+                say!("Can't step without a terminal: {e}");
+                return Pause::Continue;
+            }
+        };
+        match line.trim() {
+            "" => return Pause::Next,
+            "c" => return Pause::Continue,
+            "q" => return Pause::Quit,
+            line => match line.parse() {
+                Ok(Command::Board) => print_state(state, perspective),
+                Ok(Command::Moves) => print_moves(state),
+                Ok(Command::History(last)) => played.print(state, last),
+                Ok(Command::Takeover(seat)) => {
+                    return Pause::Handover(Handover {
+                        seat,
+                        command: None,
+                    })
+                }
+                Ok(Command::Handoff(seat, command)) => {
+                    return Pause::Handover(Handover {
+                        seat,
+                        command: Some(command),
+                    })
+                }
+                _ => say!("Enter, c, q, board, moves, history, takeover or handoff"),
+            },
+        }
+    }
+}
+
+/// Lists the legal moves, numbered for [`Command::Play`].
+fn print_moves(state: &State) {
+    for (i, action) in state.legal_actions().enumerate() {
+        say!("{i:>4}  {action}");
+    }
+}
+
+/// Like [`ClapEditor::read_command`], but the short notation (`take r g u`,
+/// `buy 2.3`) and the commands of [`Command`] are accepted too.
+fn read_command(ed: &mut ClapEditor<Action>) -> ReadCommandOutput<Command> {
+    let line = match tui::read_line(|| ed.get_editor().read_line(&DefaultPrompt::default())) {
+        Ok(Signal::Success(line)) => line,
+        Ok(Signal::CtrlC) => return ReadCommandOutput::CtrlC,
+        Ok(Signal::CtrlD) => return ReadCommandOutput::CtrlD,
+        Err(e) => return ReadCommandOutput::ReedlineError(e),
+    };
+    if line.trim().is_empty() {
+        return ReadCommandOutput::EmptyLine;
+    }
+    match line.parse() {
+        Ok(command) => ReadCommandOutput::Command(command),
+        Err(e) => ReadCommandOutput::ClapError(e),
+    }
+}
+
+fn print_events(state: &State, events: &[GameEvent]) {
+    for event in events {
+        match event {
+            GameEvent::Resigned { player } => {
+                say!("{} resigned", state.players[*player].display_name)
+            }
+            // Which card it was is on the board of the player who took it.
+            GameEvent::CardReserved {
+                player,
+                blind: true,
+                ..
+            } if HOTSEAT.load(Ordering::Relaxed) => {
+                say!(
+                    "   {} reserved a card face down",
+                    state.players[*player].display_name
+                )
+            }
+            _ => say!("   {event:?}"),
+        }
+    }
+}
+
+/// Brings the replay up to date with `played` and the result, and writes it
+/// out with `--record`.
+fn save_replay(replay: &mut Option<(Option<String>, Replay)>, state: &State, played: &History) {
+    if let Some((path, replay)) = replay {
+        played.record(replay);
+        replay.result = state.result();
+        replay.end_reason = state.end_reason();
+        if let Some(Err(e)) = path.as_ref().map(|path| replay.save(path)) {
+            say!("{e:#}");
+        }
+    }
+}
+
+/// `--color auto|always|never`. `auto` paints the boxed board in a terminal,
+/// leaves out the paint with `NO_COLOR` set, and keeps the plain board for
+/// dumb terminals, pipes and logs. `never` still draws the boxes.
+fn set_color(mode: &str) -> bool {
+    let terminal = std::io::stdout().is_terminal()
+        && std::env::var("TERM").map_or(true, |term| term != "dumb");
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|x| !x.is_empty());
+    let (pretty, color) = match mode {
+        "auto" => (terminal, terminal && !no_color),
+        "always" => (true, true),
+        "never" => (true, false),
+        _ => return false,
+    };
+    PRETTY.store(pretty, Ordering::Relaxed);
+    COLOR.store(color, Ordering::Relaxed);
+    true
+}
+
+/// The agent to play `handover.seat` from its next turn: a human, or the
+/// command started like the agents on the command line. A new agent talks
+/// like the one it replaces and keeps what is left of the seat's clock, or
+/// gets a whole `time_budget` after a human. Returns the agent and how a
+/// replay names it.
+fn take_seat(
+    agents: &[Agent],
+    handover: &Handover,
+    time_budget: Option<Duration>,
+    log_dir: Option<&std::path::Path>,
+    transcript_dir: Option<&std::path::Path>,
+    ply: usize,
+) -> anyhow::Result<(Agent, String)> {
+    let Some(previous) = agents.get(handover.seat) else {
+        anyhow::bail!(
+            "There is no seat {}, seats count from 0 to {}",
+            handover.seat,
+            agents.len() - 1
+        );
+    };
+    let name = previous.name();
+    let Some(command) = &handover.command else {
+        if let Agent::Human { .. } = previous {
+            anyhow::bail!("{name} is played by a human already");
+        }
+        return Ok((Agent::Human { name: name.clone() }, format!("human-{name}")));
+    };
+    let (msgpack, messages, clock) = match previous {
+        Agent::AI {
+            msgpack,
+            messages,
+            clock,
+            ..
+        } => (*msgpack, *messages, *clock),
+        Agent::Human { .. } => (false, false, time_budget),
+    };
+    let log = match log_dir {
+        Some(dir) => AgentLog::File(dir.join(format!("{}-{name}-{ply}.log", handover.seat))),
+        None => AgentLog::Labelled(name.clone()),
+    };
+    let agent = Agent::start(command, name.clone(), msgpack, messages, clock, log)
+        .map_err(|e| anyhow::anyhow!("Failed to start {command}: {e:#}"))?;
+    if let Some(dir) = transcript_dir {
+        let seat = handover.seat;
+        agent.transcribe(&dir.join(format!("{seat}-{name}-{ply}.transcript")))?;
+    }
+    Ok((agent, command.clone()))
+}
+
+/// Prints the board the way `--color` asks, see [`State::render`]. With
+/// `--hotseat`, as `perspective` may see it.
+pub fn print_state(state: &State, perspective: Option<usize>) {
+    if tui::active() {
+        match perspective {
+            Some(p) if HOTSEAT.load(Ordering::Relaxed) => {
+                let observation = state.observation_for(p);
+                tui::show(observation.state, perspective, observation.hidden_reserved)
+            }
+            _ => tui::show(state.clone(), perspective, vec![]),
+        }
+        return;
+    }
+    let color = COLOR.load(Ordering::Relaxed);
+    let hotseat = HOTSEAT.load(Ordering::Relaxed);
+    match (PRETTY.load(Ordering::Relaxed), perspective) {
+        (true, Some(p)) if hotseat => {
+            print!("{}", state.observation_for(p).render_pretty(p, color))
+        }
+        (false, Some(p)) if hotseat => print!("{}", state.observation_for(p).render(p)),
+        (true, _) => print!("{}", state.render_pretty(perspective, color)),
+        (false, _) => print!("{}", state.render(perspective)),
+    }
+}
+
+/// `--hotseat`: hides the board from the last player, unless `--no-clear`,
+/// and waits for `name` to take over.
+fn pass_device(ed: &mut ClapEditor<Action>, name: &str, clear: bool) -> Signal {
+    if tui::active() {
+        tui::conceal();
+    } else if clear {
+        // Clears the scrollback too, so the last board can't be scrolled to.
+        print!("\x1b[2J\x1b[3J\x1b[H");
+    }
+    say!("Pass the device to {name}, press Enter");
+    match tui::read_line(|| ed.get_editor().read_line(&DefaultPrompt::default())) {
+        Ok(signal) => signal,
+        Err(e) => panic!("{e}"),
+    }
+}
+
+fn number_arg<T: FromStr>(argv: &mut impl Iterator<Item = String>, flag: &str) -> Option<T> {
+    let value = argv.next().and_then(|x| x.parse().ok());
+    if value.is_none() {
+        say!("{flag} needs a number");
+    }
+    value
+}
+
+fn main() -> ExitCode {
+    set_color("auto");
+    let argv = match run_config::expand(std::env::args().skip(1).collect()) {
+        Ok(argv) => argv,
+        Err(e) => {
+            say!("{e:#}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match argv.first().map(String::as_str) {
+        Some("replay") => {
+            playback::main(argv.into_iter().skip(1));
+            return ExitCode::SUCCESS;
+        }
+        Some("arena") => {
+            arena::main(argv.into_iter().skip(1));
+            return ExitCode::SUCCESS;
+        }
+        Some("tournament") => {
+            tournament::main(argv.into_iter().skip(1));
+            return ExitCode::SUCCESS;
+        }
+        Some("match") => {
+            matchup::main(argv.into_iter().skip(1));
+            return ExitCode::SUCCESS;
+        }
+        Some("gauntlet") => {
+            gauntlet::main(argv.into_iter().skip(1));
+            return ExitCode::SUCCESS;
+        }
+        Some("export-html") => {
+            html::main(argv.into_iter().skip(1));
+            return ExitCode::SUCCESS;
+        }
+        Some("stats") => {
+            stats::main(argv.into_iter().skip(1));
+            return ExitCode::SUCCESS;
+        }
+        Some("notation") => {
+            notation::main(argv.into_iter().skip(1));
+            return ExitCode::SUCCESS;
+        }
+        Some("test-agent") => return conformance::main(argv.into_iter().skip(1)),
+        Some("ratings") => {
+            elo::main(argv.into_iter().skip(1));
+            return ExitCode::SUCCESS;
+        }
+        _ => (),
+    }
+    let args = match Args::parse(argv) {
+        Ok(args) => args,
+        Err(e) => {
+            say!("{e:#}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let Args {
+        assist,
+        paranoid,
+        verbose,
+        mut config,
+        coins,
+        observers,
+        observers_see_public,
+        move_timeout,
+        time_budget,
+        increment,
+        record,
+        export_notation,
+        stats_file,
+        seed,
+        first,
+        on_invalid,
+        max_restarts,
+        invalid_retries,
+        agent_log_dir,
+        transcript_dir,
+        reconnect_grace,
+        serve,
+        emit_events,
+        quiet,
+        tui,
+        compact,
+        full_board_every,
+        players,
+        hint_agent,
+        allow_undo_ai,
+        delay,
+        mut step,
+        clear,
+        hotseat,
+        json,
+        color,
+        load,
+        limits,
+        agents: mut args,
+    } = args;
+    set_color(&color);
+    HOTSEAT.store(hotseat, Ordering::Relaxed);
+    JSON_OUTPUT.store(json, Ordering::Relaxed);
+    let mut limits = limits.into_iter();
+    while let Some(flag) = limits.next() {
+        if limits::flag(&flag, &mut limits).is_none() {
+            return ExitCode::FAILURE;
+        }
+    }
+    let mut observers: Vec<_> = observers
+        .into_iter()
+        .filter_map(|command| match Observer::spawn(&command) {
+            Ok(observer) => Some(observer),
+            Err(e) => {
+                say!("Failed to start observer {command}: {e}");
+                None
+            }
+        })
+        .collect();
+    let hint_agent = hint_agent.map(|agent| HintAgent {
+        command: agent.command,
+        msgpack: agent.msgpack,
+        messages: agent.messages,
+    });
+    let saved = match &load {
+        None => None,
+        Some((path, _)) => match SavedGame::load(path) {
+            Ok(saved) => Some(saved),
+            Err(e) => {
+                say!("{e:#}");
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+    if let Some(saved) = &saved {
+        if args.is_empty() {
+            args = saved
+                .agents
                 .iter()
-                .map(|x| Nobel {
-                    cost: ResourceMap::from_code(x),
-                    score: 3,
-                })
-                .collect()
+                .map(|a| AgentArg::new(a.clone()))
+                .collect();
+        }
+        if let Err(e) = saved.check(args.len()) {
+            say!("{e:#}");
+            return ExitCode::FAILURE;
+        }
+    }
+    let mut commands: Vec<String> = args.iter().map(|a| a.command.clone()).collect();
+    // WebSocket and TCP agents are connected first, to know what they are
+    // called.
+    let ws_addresses: Vec<WsAddress> = args
+        .iter()
+        .filter_map(|a| WsAddress::parse(&a.command))
+        .collect();
+    if args
+        .iter()
+        .any(|a| a.msgpack && WsAddress::parse(&a.command).is_some())
+    {
+        say!("WebSocket agents only talk JSON");
+        return ExitCode::FAILURE;
+    }
+    let ws_server = match websocket::Server::listen(&ws_addresses, reconnect_grace) {
+        Ok(server) => server,
+        Err(e) => {
+            say!("{e:#}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut ws_names = ws_server.wait_for_agents().into_iter();
+    let mut seats = vec![];
+    let mut names = vec![];
+    // What the TCP and WebSocket agents said hello with.
+    let mut hellos = vec![];
+    for AgentArg {
+        command: arg,
+        msgpack,
+        name,
+        ..
+    } in &args
+    {
+        let (seat, default_name) = if let Some(human) = arg.strip_prefix("human-") {
+            (Seat::Human, human.to_owned())
+        } else if let Some(spec) = arg.strip_prefix("builtin:") {
+            match Builtin::parse(spec) {
+                Ok(builtin) => {
+                    let name = builtin.name();
+                    (Seat::Builtin(builtin), name)
+                }
+                Err(e) => {
+                    say!("{e:#}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        } else if WsAddress::parse(arg).is_some() {
+            let slot = ws_addresses.len() - ws_names.len();
+            (Seat::WebSocket(slot), ws_names.next().unwrap())
+        } else if let Some(address) = TcpAddress::parse(arg) {
+            match address.connect(*msgpack) {
+                Ok((stream, reader, hello)) => (Seat::Tcp(stream, reader), hello.name),
+                Err(e) => {
+                    say!("{e:#}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        } else {
+            match split_command(arg) {
+                Ok(words) => {
+                    let name = program_name(&words);
+                    (Seat::Command(words), name)
+                }
+                Err(e) => {
+                    say!("{e}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        };
+        let connected = matches!(seat, Seat::Tcp(..) | Seat::WebSocket(_));
+        hellos.push(connected.then(|| default_name.clone()));
+        seats.push(seat);
+        names.push(name.clone().unwrap_or(default_name));
+    }
+    let names = match &saved {
+        // The names are part of the game.
+        Some(saved) => saved
+            .state
+            .players
+            .iter()
+            .map(|p| p.display_name.clone())
+            .collect(),
+        None => unique_names(names),
+    };
+    let difficulties: Vec<_> = seats.iter().map(Seat::difficulty).collect();
+    for dir in [&agent_log_dir, &transcript_dir].into_iter().flatten() {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            say!("Failed to create {}: {e}", dir.display());
+            return ExitCode::FAILURE;
+        }
+    }
+    let mut agents = vec![];
+    for (i, (agent, seat)) in args.into_iter().zip(seats).enumerate() {
+        let AgentArg {
+            command: arg,
+            msgpack,
+            messages,
+            time_budget: budget,
+            ..
+        } = agent;
+        let name = names[i].clone();
+        let time_budget = budget.or(time_budget);
+        let agent = match seat {
+            Seat::Human => Ok(Agent::Human { name }),
+            Seat::Builtin(builtin) => Agent::builtin(builtin, name, time_budget),
+            Seat::Tcp(stream, reader) => {
+                Agent::tcp(stream, reader, name, msgpack, messages, time_budget)
+            }
+            Seat::WebSocket(slot) => Ok(Agent::websocket(
+                &ws_server,
+                slot,
+                name,
+                messages,
+                time_budget,
+            )),
+            Seat::Command(words) => {
+                let log = match &agent_log_dir {
+                    Some(dir) => AgentLog::File(dir.join(format!("{i}-{name}.log"))),
+                    None => AgentLog::Labelled(name.clone()),
+                };
+                Agent::spawn(&words, name, msgpack, messages, time_budget, log)
+            }
+        };
+        let agent = match agent {
+            Ok(agent) => agent,
+            Err(e) => {
+                // The agents started so far are stopped as they are dropped.
+                say!("Failed to start {arg}: {e}");
+                return ExitCode::FAILURE;
+            }
+        };
+        if let Some(dir) = &transcript_dir {
+            let name = agent.name();
+            if let Err(e) = agent.transcribe(&dir.join(format!("{i}-{name}.transcript"))) {
+                say!("{e:#}");
+                return ExitCode::FAILURE;
+            }
+            if let Some(hello) = &hellos[i] {
+                agent.note(&format!("connected and said hello as {hello}"));
+            }
+        }
+        agents.push(agent);
+    }
+    match players {
+        Some(n) if n != agents.len() => {
+            say!("--players {n} but {} agents were given", agents.len());
+            return ExitCode::FAILURE;
+        }
+        _ if !(2..=4).contains(&agents.len()) => {
+            say!("Splendor is for 2 to 4 players, not {}", agents.len());
+            return ExitCode::FAILURE;
+        }
+        _ => (),
+    }
+    if let Some(coins) = coins {
+        config.coins_per_color.insert(agents.len(), coins);
+    }
+    if !config.handicaps.is_empty() && load.is_some() {
+        say!("--handicap is for a new game, a saved game keeps the handicaps it was dealt with");
+        return ExitCode::FAILURE;
+    }
+    if let Err(e) = config.check_handicaps(agents.len()) {
+        say!("{e:#}");
+        return ExitCode::FAILURE;
+    }
+    if config.handicaps.values().any(|h| h.time_ms > 0) && time_budget.is_none() {
+        say!("A time handicap needs --time-budget");
+        return ExitCode::FAILURE;
+    }
+    let names: Vec<&str> = names.iter().map(|x| x.as_str()).collect();
+    let (seed, mut state, played) = match (saved, &load) {
+        (Some(saved), Some((path, false))) => {
+            say!("Seed {}, resuming {path}", saved.seed);
+            (saved.seed, saved.state, saved.history)
+        }
+        (Some(saved), Some((path, _))) => {
+            let seed = seed.unwrap_or_else(rand::random);
+            say!("Seed {seed}, resuming {path} with the face down cards dealt again");
+            let state = saved.state.reshuffled(&mut StdRng::seed_from_u64(seed));
+            (seed, state, saved.history)
+        }
+        _ => {
+            // Printed so that any game can be dealt again.
+            let seed = seed.unwrap_or_else(rand::random);
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut state = State::setup(&names, config, &mut rng);
+            // Drawn after the deal, so the seed deals the same cards whoever starts.
+            let first = match first.as_str() {
+                "random" => rng.gen_range(0..names.len()),
+                first => match first.parse::<usize>() {
+                    Ok(seat) if seat < names.len() => seat,
+                    Ok(seat) => {
+                        say!("--first {seat} but there are {} players", names.len());
+                        return ExitCode::FAILURE;
+                    }
+                    Err(_) => match names.iter().position(|&name| name == first) {
+                        Some(seat) => seat,
+                        None => {
+                            say!("--first {first} is not a seat number, a player or random");
+                            return ExitCode::FAILURE;
+                        }
+                    },
+                },
+            };
+            state.set_first_player(first);
+            say!("Seed {seed}, {} moves first", names[first]);
+            (seed, state, vec![])
+        }
+    };
+    for (&seat, handicap) in &state.config.handicaps {
+        let name = &state.players[seat].display_name;
+        say!("{name} starts with a handicap of {handicap}");
+        if handicap.time_ms == 0 {
+            continue;
+        }
+        let Agent::AI {
+            clock: Some(left), ..
+        } = &mut agents[seat]
+        else {
+            say!("{name} has no clock for a time handicap, see --time-budget");
+            return ExitCode::FAILURE;
+        };
+        *left += Duration::from_millis(handicap.time_ms);
+    }
+    // Kept apart from the state, which moves it on when the first player resigns.
+    let first = state.first_player;
+    if let Some(set) = &state.config.cards {
+        say!("Cards from {} ({})", set.name, set.hash);
+    }
+    // Every move so far, for `history`, saving the game and the replay.
+    let mut played = History::new(played);
+    // Saved again after every move, so a crash still leaves the game so far.
+    // Kept for `--export-notation` too, which writes it out at the end.
+    let mut replay = (record.is_some() || export_notation.is_some()).then(|| {
+        let mut replay = Replay::new(state.clone());
+        replay.seed = Some(seed);
+        replay.agents = commands.clone();
+        replay.run_config = run_config::resolved();
+        (record, replay)
+    });
+    save_replay(&mut replay, &state, &played);
+    let spectators = match &serve {
+        None => None,
+        Some(address) => match Spectators::serve(address, &state) {
+            Ok((spectators, address)) => {
+                say!("Spectators can watch on http://{address}");
+                Some(spectators)
+            }
+            Err(e) => {
+                say!("Failed to serve on {address}: {e}");
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+    let mut events = match &emit_events {
+        None => None,
+        Some(path) => match EventLog::create(path) {
+            Ok(log) => Some(log),
+            Err(e) => {
+                say!("Failed to create {path}: {e}");
+                return ExitCode::FAILURE;
+            }
         },
-        players: agents.iter().map(|a| Player::new(&a.name())).collect(),
-        coins: ResourceMap(enum_map! {
-            ResourceKind::Red => 7,
-            ResourceKind::Blue => 7,
-            ResourceKind::Green => 7,
-            ResourceKind::White => 7,
-            ResourceKind::Black => 7,
-        }),
-        turn: 0,
-        wilds: 5,
+    };
+    events::emit(
+        &mut events,
+        Event::GameStart {
+            seed,
+            agents: &names,
+            config: &state.config,
+            state: Box::new(state.redacted()),
+        },
+    );
+    for (player_index, agent) in agents.iter_mut().enumerate() {
+        agent.notify(RunnerMessage::Init {
+            protocol_version: PROTOCOL_VERSION,
+            player_index,
+            config: state.config.clone(),
+            new_games: false,
+        });
+    }
+
+    // A lone human sees what they can buy on every board, not just on their
+    // own turn.
+    let humans: Vec<usize> = (0..agents.len())
+        .filter(|&i| matches!(agents[i], Agent::Human { .. }))
+        .collect();
+    let interrupted = catch_interrupts();
+    // The TUI shows every board, whole.
+    let tui = tui && tui::start(interrupted.clone());
+    let compact = compact && !tui;
+    // The summary is all there is on stdout with `--output json`.
+    let quiet = !tui && (quiet || JSON_OUTPUT.load(Ordering::Relaxed));
+    // With `--hotseat`, the human who was passed the device last, the only
+    // one whose board is shown.
+    let holder = Cell::new(None);
+    let print_board = |state: &State| match humans[..] {
+        _ if quiet => (),
+        _ if hotseat => {
+            if let Some(seat) = holder.get() {
+                print_state(state, Some(seat))
+            }
+        }
+        [seat] => print_state(state, Some(seat)),
+        _ => print_state(state, Some(state.turn)),
+    };
+    // With `--render compact`, what a move changed instead of the board, but
+    // the whole board every `--full-board-every` moves and at the end.
+    let print_move = |before: &State, state: &State, moves: usize| match compact {
+        _ if quiet => (),
+        true if !state.is_finished() && !moves.is_multiple_of(full_board_every) => {
+            match (hotseat, holder.get()) {
+                (false, _) => print!("{}", state.render_compact(before)),
+                (true, Some(seat)) => print!(
+                    "{}",
+                    (state.observation_for(seat).state)
+                        .render_compact(&before.observation_for(seat).state)
+                ),
+                (true, None) => (),
+            }
+        }
+        _ => print_board(state),
     };
 
-    let mut ed = clap_repl::ClapEditor::<Action>::builder().build();
-    state.print();
+    let mut ed = ClapEditor::<Action>::builder().build();
+    let mut advisor = Advisor::new(hint_agent, move_timeout);
+    // The state before each move, who made it and the move, for `undo`. Gone
+    // with the loop once the game is over, so a finished game stays finished.
+    let mut history = VecDeque::<(State, usize, Action)>::new();
+    print_board(&state);
+    observe(
+        &mut observers,
+        &ObserverMessage::Init {
+            protocol_version: PROTOCOL_VERSION,
+            config: state.config.clone(),
+            players: names.iter().map(|name| name.to_string()).collect(),
+        },
+    );
+    observe_state(&mut observers, &state, observers_see_public);
+    let mut threats_shown = false;
+    let mut turn_started = Instant::now();
+    let mut forfeits = vec![];
+    let started = Instant::now();
+    // Every answer, including those taken back with `undo`.
+    let mut move_ms = vec![vec![]; agents.len()];
+    let mut terminated = None;
+    let mut crashed = false;
+    // How each agent that crashed ended, for the summary.
+    let mut exits = vec![None; agents.len()];
+    // How many times each agent was started again, up to `--on-crash restart:<n>`.
+    let mut restarts = vec![0; agents.len()];
+    let mut termination = Termination::Normal;
+    // For `takeover` and `handoff`, the seat that changes hands before the
+    // next turn.
+    let mut handover = None;
+    // Who played each seat from which of its moves on, by their command.
+    let mut controllers: Vec<Vec<(usize, String)>> =
+        commands.iter().map(|c| vec![(0, c.clone())]).collect();
     loop {
-        if state.is_finished() {
-            println!("Game finished");
+        if tui {
+            let clocks: Vec<_> = agents.iter().map(Agent::clock).collect();
+            tui::status(&state, played.moves().last(), &clocks);
+        }
+        if paranoid {
+            if let Err(e) = state.validate() {
+                say!("State is corrupted: {e:?}");
+                say!("Terminating game");
+                terminated = Some(format!("state is corrupted: {e:?}"));
+                break;
+            }
+        }
+        match state.end_reason() {
+            None => (),
+            Some(EndReason::TargetScore | EndReason::Resignation | EndReason::CityClaimed) => (),
+            Some(EndReason::Stalemate) => say!("Every player skipped, nothing can change"),
+            Some(EndReason::RoundLimit) => {
+                say!(
+                    "Round limit of {} reached, adjudicated by score",
+                    state.round
+                )
+            }
+        }
+        match state.result() {
+            GameResult::Ongoing => (),
+            GameResult::Won(winner) => {
+                say!("Game finished, {} won", state.players[winner].display_name);
+                break;
+            }
+            GameResult::Draw(winners) => {
+                let names: Vec<_> = winners
+                    .iter()
+                    .map(|&i| state.players[i].display_name.as_str())
+                    .collect();
+                say!("Game finished, draw between {}", names.join(", "));
+                break;
+            }
+        }
+        if interrupted.load(Ordering::Relaxed) {
+            let saved = SavedGame {
+                state: state.clone(),
+                agents: commands.clone(),
+                seed,
+                history: played.moves().to_vec(),
+            };
+            match saved.autosave() {
+                Ok(path) => say!("Interrupted, saved to {path}, go on with --load-state {path}"),
+                Err(e) => say!("Interrupted, and the game couldn't be saved: {e:#}"),
+            }
+            terminated = Some("interrupted".to_owned());
             break;
         }
+        if let Some(handover) = handover.take() {
+            let log_dir = agent_log_dir.as_deref();
+            match take_seat(
+                &agents,
+                &handover,
+                time_budget,
+                log_dir,
+                transcript_dir.as_deref(),
+                played.len(),
+            ) {
+                Ok((agent, command)) => {
+                    let seat = handover.seat;
+                    // The agent it replaces is shut down as it is dropped.
+                    drop(std::mem::replace(&mut agents[seat], agent));
+                    agents[seat].notify(RunnerMessage::Init {
+                        protocol_version: PROTOCOL_VERSION,
+                        player_index: seat,
+                        config: state.config.clone(),
+                        new_games: false,
+                    });
+                    let name = &state.players[seat].display_name;
+                    match handover.command {
+                        Some(_) => say!("{name} is played by {command} from now on"),
+                        None => say!("{name} is played by a human from now on"),
+                    }
+                    controllers[seat].push((move_ms[seat].len(), command.clone()));
+                    if let Some((_, replay)) = &mut replay {
+                        replay.substitutions.push(Substitution {
+                            ply: replay.actions.len(),
+                            player: seat,
+                            agent: command.clone(),
+                        });
+                    }
+                    commands[seat] = command;
+                    save_replay(&mut replay, &state, &played);
+                    threats_shown = false;
+                }
+                Err(e) => say!("{e:#}"),
+            }
+        }
         let agent = &mut agents[state.turn];
+        if hotseat && matches!(agent, Agent::Human { .. }) && holder.get() != Some(state.turn) {
+            match pass_device(&mut ed, &state.players[state.turn].display_name, clear) {
+                Signal::Success(_) => (),
+                Signal::CtrlC => {
+                    interrupted.store(true, Ordering::Relaxed);
+                    continue;
+                }
+                Signal::CtrlD => {
+                    say!("End game requested by human player");
+                    terminated = Some("ended by a human player".to_owned());
+                    break;
+                }
+            }
+            holder.set(Some(state.turn));
+            print_board(&state);
+        }
+        if !threats_shown {
+            if assist && matches!(agent, Agent::Human { .. }) {
+                print_threats(&state);
+            }
+            // The board is out, so thinking starts now.
+            turn_started = Instant::now();
+        }
+        threats_shown = true;
         match agent {
-            Agent::Human { .. } => match ed.read_command() {
-                ReadCommandOutput::Command(action) => {
-                    let mut s = state.clone();
-                    if let Err(e) = s.run(action) {
-                        println!("Error: {e:?}");
+            Agent::Human { .. } => {
+                let action = match read_command(&mut ed) {
+                    ReadCommandOutput::Command(Command::Action(action)) => action,
+                    ReadCommandOutput::Command(Command::Play(n)) => {
+                        match state.legal_actions().nth(n) {
+                            Some(action) => action,
+                            None => {
+                                say!("There is no move {n}, type moves to list them");
+                                continue;
+                            }
+                        }
+                    }
+                    ReadCommandOutput::Command(Command::Moves) => {
+                        print_moves(&state);
+                        continue;
+                    }
+                    ReadCommandOutput::Command(Command::Board) => {
+                        print_state(&state, Some(state.turn));
+                        continue;
+                    }
+                    ReadCommandOutput::Command(Command::History(last)) => {
+                        played.print(&state, last);
+                        continue;
+                    }
+                    ReadCommandOutput::Command(Command::Hint) => {
+                        match advisor.hint(&state) {
+                            Ok(action) => say!("Hint: {action}"),
+                            Err(e) => say!("{e:#}"),
+                        }
+                        continue;
+                    }
+                    ReadCommandOutput::Command(Command::Undo) => {
+                        let is_human =
+                            |player: usize| matches!(agents[player], Agent::Human { .. });
+                        let Some(last) =
+                            history.iter().rposition(|&(_, player, _)| is_human(player))
+                        else {
+                            say!("There is no move of a human to undo");
+                            continue;
+                        };
+                        if last + 1 < history.len() && !allow_undo_ai {
+                            let ai = &agents[history.back().unwrap().1];
+                            say!(
+                                "The last move was made by {}, undoing it needs --allow-undo-ai",
+                                ai.name()
+                            );
+                            continue;
+                        }
+                        let undone = history.split_off(last);
+                        let count = undone.len();
+                        played.undo(count, replay.as_mut().map(|(_, replay)| replay));
+                        for (_, player, action) in undone.iter().rev() {
+                            say!(
+                                "Took back {action} of {}",
+                                state.players[*player].display_name
+                            );
+                            if let Some(log) = &mut events {
+                                log.undone(*player, action);
+                            }
+                        }
+                        state = undone.into_iter().next().unwrap().0;
+                        save_replay(&mut replay, &state, &played);
+                        if let Some(spectators) = &spectators {
+                            spectators.undone(&state, count);
+                        }
+                        turn_started = Instant::now();
+                        print_board(&state);
+                        threats_shown = false;
+                        observe_state(&mut observers, &state, observers_see_public);
+                        continue;
+                    }
+                    ReadCommandOutput::Command(Command::Save(path)) => {
+                        let saved = SavedGame {
+                            state: state.clone(),
+                            agents: commands.clone(),
+                            seed,
+                            history: played.moves().to_vec(),
+                        };
+                        match saved.save(&path) {
+                            Ok(()) => say!("Saved to {path}, go on with --load-state {path}"),
+                            Err(e) => say!("{e:#}"),
+                        }
+                        continue;
+                    }
+                    ReadCommandOutput::Command(Command::Takeover(seat)) => {
+                        handover = Some(Handover {
+                            seat,
+                            command: None,
+                        });
+                        continue;
+                    }
+                    ReadCommandOutput::Command(Command::Handoff(seat, command)) => {
+                        handover = Some(Handover {
+                            seat,
+                            command: Some(command),
+                        });
+                        continue;
+                    }
+                    ReadCommandOutput::Command(Command::Help) => {
+                        say!("{HELP}");
+                        continue;
+                    }
+                    ReadCommandOutput::EmptyLine => continue,
+                    ReadCommandOutput::ClapError(e) => {
+                        match e.kind() {
+                            ErrorKind::DisplayHelp | ErrorKind::DisplayVersion => {
+                                e.print().unwrap()
+                            }
+                            // Just the first line, not the usage that follows.
+                            _ => say!(
+                                "{}, type help for examples",
+                                e.to_string().lines().next().unwrap_or_default()
+                            ),
+                        }
+                        continue;
+                    }
+                    ReadCommandOutput::ShlexError => {
+                        say!("Error: input was not valid and could not be processed");
+                        continue;
+                    }
+                    ReadCommandOutput::ReedlineError(e) => {
+                        panic!("{e}");
+                    }
+                    // Saved like an interrupt of an AI game, at the top of the loop.
+                    ReadCommandOutput::CtrlC => {
+                        interrupted.store(true, Ordering::Relaxed);
+                        continue;
+                    }
+                    ReadCommandOutput::CtrlD => {
+                        say!("End game requested by human player");
+                        terminated = Some("ended by a human player".to_owned());
+                        break;
+                    }
+                };
+                let mut s = state.clone();
+                let game_events = match s.run(action.clone()) {
+                    Ok(events) => events,
+                    Err(e) => {
+                        say!("Error: {e:#}");
                         continue;
                     }
-                    state = s;
-                    state.print();
+                };
+                let ms = turn_started.elapsed().as_millis() as u64;
+                if verbose {
+                    say!("   {}", game_def::diff(&state, &s));
                 }
-                ReadCommandOutput::EmptyLine => (),
-                ReadCommandOutput::ClapError(e) => {
-                    e.print().unwrap();
+                if let Some(log) = &mut events {
+                    log.emit(&Event::ActionReceived {
+                        player: state.turn,
+                        action: &action,
+                        think_ms: ms,
+                    });
+                    log.moved(
+                        state.turn,
+                        &action,
+                        game_def::diff(&state, &s),
+                        &game_events,
+                    );
+                }
+                if let Some(spectators) = &spectators {
+                    spectators.moved(&s, state.turn, &action);
+                }
+                move_ms[state.turn].push(ms);
+                if let Some((_, replay)) = &mut replay {
+                    replay.think_ms.push(ms);
                 }
-                ReadCommandOutput::ShlexError => {
-                    println!(
-                        "{} input was not valid and could not be processed",
-                        "Error:", //style("Error:").red().bold()
+                let turn = state.turn;
+                played.push(turn, action.clone(), game_events.clone());
+                let before = std::mem::replace(&mut state, s);
+                save_replay(&mut replay, &state, &played);
+                turn_started = Instant::now();
+                say!("{} did {action}", state.players[turn].display_name);
+                print_events(&state, &game_events);
+                print_move(&before, &state, played.len());
+                broadcast_played(&mut agents, turn, &action);
+                let message = ObserverMessage::Played {
+                    player: turn,
+                    action: action.clone(),
+                };
+                observe(&mut observers, &message);
+                remember(&mut history, before, turn, action);
+                threats_shown = false;
+                observe_state(&mut observers, &state, observers_see_public);
+            }
+            Agent::AI { .. } => {
+                if !quiet {
+                    say!("AI Thinking...");
+                }
+                let name = agent.name();
+                let turn = state.turn;
+                if let Agent::AI { clock, .. } = agent {
+                    events::emit(
+                        &mut events,
+                        Event::MoveRequest {
+                            player: turn,
+                            time_left_ms: clock.map(|left| left.as_millis() as u64),
+                        },
                     );
                 }
-                ReadCommandOutput::ReedlineError(e) => {
-                    panic!("{e}");
+                let (reply, elapsed) = request_move(
+                    agent,
+                    &state,
+                    move_timeout,
+                    increment,
+                    invalid_retries,
+                    |action, e| {
+                        say!("{name} did invalid action {action}: {e}, asking again");
+                        events::emit(
+                            &mut events,
+                            Event::Rejected {
+                                player: turn,
+                                action,
+                                error: e,
+                                retry: true,
+                            },
+                        );
+                        if let Some((_, replay)) = &mut replay {
+                            replay.invalid.push(InvalidAttempt {
+                                ply: replay.actions.len(),
+                                player: turn,
+                                action: action.clone(),
+                                error: e.clone(),
+                            });
+                        }
+                    },
+                );
+                let mut forfeit = None;
+                let mut action = match reply {
+                    Ok(action) => {
+                        match agent {
+                            Agent::AI {
+                                clock: Some(left), ..
+                            } => say!(
+                                "{name} did {action}, thought for {} ({left:.1?} left)",
+                                brief(elapsed)
+                            ),
+                            _ => say!("{name} did {action}, thought for {}", brief(elapsed)),
+                        }
+                        events::emit(
+                            &mut events,
+                            Event::ActionReceived {
+                                player: turn,
+                                action: &action,
+                                think_ms: elapsed.as_millis() as u64,
+                            },
+                        );
+                        action
+                    }
+                    // Agents out of time always forfeit, there is nothing to debug.
+                    Err(e @ (Failure::TimedOut(_) | Failure::OutOfTime(_))) => {
+                        say!("{name} {e}");
+                        events::emit(
+                            &mut events,
+                            Event::AgentFailed {
+                                player: turn,
+                                reason: e.to_string(),
+                            },
+                        );
+                        termination.add(e.termination());
+                        forfeit = Some(e.to_string());
+                        Action::Resign
+                    }
+                    Err(e) => {
+                        say!("{name} {e}");
+                        events::emit(
+                            &mut events,
+                            Event::AgentFailed {
+                                player: turn,
+                                reason: e.to_string(),
+                            },
+                        );
+                        if let Failure::Closed(Some(exit)) = &e {
+                            exits[turn] = Some(exit.clone());
+                        }
+                        // Only a process that ended can be started again, and
+                        // its clock goes on where it stopped.
+                        if matches!(e, Failure::Closed(Some(_))) && restarts[turn] < max_restarts {
+                            restarts[turn] += 1;
+                            let handover = Handover {
+                                seat: turn,
+                                command: Some(commands[turn].clone()),
+                            };
+                            let log_dir = agent_log_dir.as_deref();
+                            match take_seat(
+                                &agents,
+                                &handover,
+                                time_budget,
+                                log_dir,
+                                transcript_dir.as_deref(),
+                                played.len(),
+                            ) {
+                                Ok((agent, _)) => {
+                                    drop(std::mem::replace(&mut agents[turn], agent));
+                                    agents[turn].notify(RunnerMessage::Init {
+                                        protocol_version: PROTOCOL_VERSION,
+                                        player_index: turn,
+                                        config: state.config.clone(),
+                                        new_games: false,
+                                    });
+                                    for (player, action) in played.moves() {
+                                        agents[turn].notify(RunnerMessage::Played {
+                                            player: *player,
+                                            action: action.clone(),
+                                        });
+                                    }
+                                    say!(
+                                        "{name} started again, restart {} of {max_restarts}",
+                                        restarts[turn]
+                                    );
+                                    if let Some((_, replay)) = &mut replay {
+                                        replay.restarts.push(Restart {
+                                            ply: replay.actions.len(),
+                                            player: turn,
+                                            reason: e.to_string(),
+                                        });
+                                    }
+                                    save_replay(&mut replay, &state, &played);
+                                    continue;
+                                }
+                                Err(e) => say!("{e:#}"),
+                            }
+                        }
+                        // Stepping, someone is there to keep the seat in the game.
+                        if step {
+                            say!("takeover {turn} or handoff {turn} <command> keeps {name} playing, Enter forfeits");
+                            let perspective = match hotseat {
+                                true => holder.get(),
+                                false => Some(turn),
+                            };
+                            match pause(&mut ed, &state, &played, perspective) {
+                                Pause::Handover(next) => {
+                                    handover = Some(next);
+                                    continue;
+                                }
+                                Pause::Interrupted => {
+                                    interrupted.store(true, Ordering::Relaxed);
+                                    continue;
+                                }
+                                Pause::Quit => {
+                                    terminated = Some(format!("{name} {e}"));
+                                    break;
+                                }
+                                Pause::Next | Pause::Continue => (),
+                            }
+                        }
+                        crashed = true;
+                        if on_invalid == OnInvalid::Terminate {
+                            say!("Terminating game");
+                            terminated = Some(format!("{name} {e}"));
+                            break;
+                        }
+                        termination.add(e.termination());
+                        forfeit = Some(e.to_string());
+                        Action::Resign
+                    }
+                };
+                let before = state.clone();
+                let game_events = match state.run(action.clone()) {
+                    Ok(events) => events,
+                    Err(e) => {
+                        say!("{name} did invalid action {action}: {e}");
+                        say!("Reason: {}", serde_json::to_string(&e).unwrap());
+                        agents[turn].note(&format!("{action} is invalid: {e}"));
+                        events::emit(
+                            &mut events,
+                            Event::Rejected {
+                                player: turn,
+                                action: &action,
+                                error: &e,
+                                retry: false,
+                            },
+                        );
+                        forfeit = Some(format!("did invalid action {action}: {e}"));
+                        termination.add(Termination::Forfeit);
+                        if let Some((_, replay)) = &mut replay {
+                            replay.invalid.push(InvalidAttempt {
+                                ply: replay.actions.len(),
+                                player: turn,
+                                action,
+                                error: e,
+                            });
+                        }
+                        if on_invalid == OnInvalid::Terminate {
+                            say!("Terminating game");
+                            terminated = forfeit.map(|reason| format!("{name} {reason}"));
+                            save_replay(&mut replay, &state, &played);
+                            break;
+                        }
+                        action = Action::Resign;
+                        state.run(Action::Resign).unwrap()
+                    }
+                };
+                let forfeited = forfeit.is_some();
+                if let Some(reason) = forfeit {
+                    say!("{name} forfeits");
+                    let forfeit = Forfeit {
+                        player: turn,
+                        reason,
+                    };
+                    events::emit(&mut events, Event::Forfeit(&forfeit));
+                    forfeits.push(forfeit.clone());
+                    if let Some((_, replay)) = &mut replay {
+                        replay.forfeits.push(forfeit);
+                    }
                 }
-                ReadCommandOutput::CtrlC | ReadCommandOutput::CtrlD => {
-                    println!("End game requested by human player");
-                    break;
+                if verbose || events.is_some() {
+                    let diff = game_def::diff(&before, &state);
+                    if verbose {
+                        say!("   {diff}");
+                    }
+                    if let Some(log) = &mut events {
+                        log.moved(turn, &action, diff, &game_events);
+                    }
                 }
-            },
-            Agent::AI { writer, reader, .. } => {
-                println!("AI Thinking...");
-                writeln!(writer, "{}", state.json()).unwrap();
-                let mut result = String::new();
-                reader.read_line(&mut result).unwrap();
-                let action: Action = serde_json::from_str(&result).unwrap();
-                println!("{} did {:?}", agent.name(), action);
-                if let Err(e) = state.run(action) {
-                    println!("AI did invalid action: {e:?}");
-                    println!("Terminating game");
-                    break;
+                move_ms[turn].push(elapsed.as_millis() as u64);
+                if let Some(spectators) = &spectators {
+                    spectators.moved(&state, turn, &action);
+                }
+                if let Some((_, replay)) = &mut replay {
+                    replay.think_ms.push(elapsed.as_millis() as u64);
                 }
-                state.print();
+                played.push(turn, action.clone(), game_events.clone());
+                save_replay(&mut replay, &state, &played);
+                print_events(&state, &game_events);
+                print_move(&before, &state, played.len());
+                broadcast_played(&mut agents, turn, &action);
+                let message = ObserverMessage::Played {
+                    player: turn,
+                    action: action.clone(),
+                };
+                observe(&mut observers, &message);
+                // An agent that failed isn't brought back.
+                match forfeited {
+                    true => history.clear(),
+                    false => remember(&mut history, before, turn, action),
+                }
+                threats_shown = false;
+                observe_state(&mut observers, &state, observers_see_public);
+                // After the clock has stopped, so none of this is thinking time.
+                if let Some(delay) = delay {
+                    std::thread::sleep(delay);
+                }
+                if step && !state.is_finished() {
+                    let perspective = match hotseat {
+                        true => holder.get(),
+                        false => Some(state.turn),
+                    };
+                    match pause(&mut ed, &state, &played, perspective) {
+                        Pause::Next => (),
+                        Pause::Continue => step = false,
+                        Pause::Interrupted => interrupted.store(true, Ordering::Relaxed),
+                        Pause::Handover(next) => handover = Some(next),
+                        Pause::Quit => {
+                            say!("Game stopped while stepping");
+                            terminated = Some("stopped while stepping".to_owned());
+                            break;
+                        }
+                    }
+                }
+                turn_started = Instant::now();
+            }
+        }
+    }
+    tui::stop();
+    if let Some(path) = &export_notation {
+        save_replay(&mut replay, &state, &played);
+        if let Some((_, replay)) = &replay {
+            if let Err(e) = notation::export(path, replay) {
+                say!("{e:#}");
             }
         }
     }
+    print_report(&state, &played);
+    let timings: Vec<Timing> = move_ms.iter().map(|ms| Timing::of(ms)).collect();
+    print_timings(&state, &timings);
+    for Forfeit { player, reason } in &forfeits {
+        say!(
+            "{} forfeited: {reason}",
+            state.players[*player].display_name
+        );
+    }
+    // So a handicapped result isn't taken for an even game.
+    for (&seat, handicap) in &state.config.handicaps {
+        let name = &state.players[seat].display_name;
+        say!("{name} played with a handicap of {handicap}");
+    }
+    for (player, difficulty) in state.players.iter().zip(&difficulties) {
+        if let Some(difficulty) = difficulty {
+            say!("{} played at difficulty {difficulty}", player.display_name);
+        }
+    }
+    events::emit(
+        &mut events,
+        Event::GameEnd {
+            result: state.result(),
+            forfeits: &forfeits,
+            terminated: terminated.as_deref(),
+        },
+    );
+    for agent in &mut agents {
+        agent.notify(RunnerMessage::GameOver {
+            result: state.result(),
+        });
+    }
+    let result = state.result();
+    observe(&mut observers, &ObserverMessage::GameOver { result });
+    for observer in observers {
+        observer.finish();
+    }
+    if let (Some(path), true) = (&stats_file, state.is_finished()) {
+        let seats = stats::seats(&state, &commands, first);
+        let row = stats::Row::new(seed, state.round, started.elapsed(), termination, seats);
+        if let Err(e) = stats::append(path, &row) {
+            say!("{e:#}");
+        }
+    }
+    if JSON_OUTPUT.load(Ordering::Relaxed) {
+        let mut agents = state
+            .players
+            .iter()
+            .zip(state.final_scores())
+            .zip(state.placements())
+            .zip(controllers.into_iter().zip(timings).zip(move_ms))
+            .zip(exits.into_iter().zip(restarts))
+            .map(
+                |(
+                    (((player, breakdown), place), ((controllers, timing), move_ms)),
+                    (exit, restarts),
+                )| {
+                    AgentSummary {
+                        name: player.display_name.clone(),
+                        agent: controllers[0].1.clone(),
+                        score: player.score,
+                        place,
+                        breakdown,
+                        think_ms: timing.total_ms,
+                        timing,
+                        controllers: controller_summaries(&controllers, &move_ms),
+                        move_ms,
+                        exit,
+                        restarts,
+                        handicap: None,
+                        difficulty: None,
+                    }
+                },
+            )
+            .collect::<Vec<_>>();
+        for (&seat, handicap) in &state.config.handicaps {
+            agents[seat].handicap = Some(handicap.clone());
+        }
+        for (agent, difficulty) in agents.iter_mut().zip(difficulties) {
+            agent.difficulty = difficulty.map(|difficulty| difficulty.to_string());
+        }
+        let summary = Summary {
+            agents,
+            seed,
+            first,
+            turns: state.players.iter().map(|p| p.record.turns).sum(),
+            rounds: state.round,
+            result: state.result(),
+            terminated: terminated.clone(),
+            end_reason: state.end_reason(),
+            forfeits,
+            duration_ms: started.elapsed().as_millis() as u64,
+            nobels: played.claims().to_vec(),
+            run_config: run_config::resolved(),
+        };
+        println!("{}", serde_json::to_string(&summary).unwrap());
+    }
+    if crashed {
+        ExitCode::from(EXIT_CRASHED)
+    } else if terminated.is_some() {
+        ExitCode::from(EXIT_TERMINATED)
+    } else {
+        ExitCode::SUCCESS
+    }
 }
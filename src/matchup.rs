@@ -0,0 +1,467 @@
+//! `splendor-ai match --agent-a <cmd> --agent-b <cmd> [options]`: plays two
+//! agents against each other to tell whether A is stronger than B, such as a
+//! new version of a bot against the old one.
+//!
+//! A plays first in every game, or in every other one with
+//! `--alternate-seats`. The running score is printed after each game, and at
+//! the end A's wins, draws and losses, as the first player and as the second,
+//! the average game length and the seeds of the games A lost, to watch them
+//! again with `splendor-ai --seed`.
+//!
+//! `--games` (100 by default) is the most games played. With
+//! `--sprt <elo0> <elo1>` the match stops as soon as a sequential probability
+//! ratio test decides between "A is `elo0` Elo stronger than B" (H0) and
+//! "`elo1` stronger" (H1), erring at most `--alpha` (0.05) of the time for H1
+//! and `--beta` (0.05) for H0. `--sprt 0 5` asks whether A gained 5 Elo. The
+//! test uses the normal approximation of the log likelihood ratio, like
+//! cutechess and fishtest, see [`Sprt::llr`].
+//!
+//! Both the Elo estimate and the test count half a game of each result more
+//! than was played, so a match A loses every game of still has a finite Elo
+//! and can stop.
+//!
+//! `--seed`, `--jobs`, `--json`, `--record`, `--stats-file`, `--name`,
+//! `--protocol`, `--reuse-agents` and the settings of each game work like for
+//! a tournament. With `--jobs`, games
+//! still under way when the test decides are thrown away.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Serialize;
+
+use crate::{
+    number_arg, run_config, stats,
+    tournament::{self, Cards, GameRecord, Settings, MAX_AGENT_PROCESSES},
+    unique_names,
+};
+
+const DEFAULT_GAMES: usize = 100;
+
+/// A's results.
+#[derive(Serialize, Default, Clone, Copy)]
+pub struct Tally {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl Tally {
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// The share of wins, draws and losses, with half a game of each added.
+    fn shares(&self) -> [f64; 3] {
+        let total = self.games() as f64 + 1.5;
+        [self.wins, self.draws, self.losses].map(|n| (n as f64 + 0.5) / total)
+    }
+
+    /// The average score of a game, 1 for a win and ½ for a draw, and the
+    /// variance of the average.
+    fn score(&self) -> (f64, f64) {
+        let [win, draw, _] = self.shares();
+        let score = win + draw / 2.0;
+        let variance = win + draw / 4.0 - score * score;
+        (score, variance / (self.games() as f64 + 1.5))
+    }
+
+    /// How much stronger A seems, in Elo, and half the width of a 95%
+    /// interval around it.
+    pub fn elo(&self) -> (f64, f64) {
+        let (score, variance) = self.score();
+        let margin = 1.96 * variance.sqrt();
+        let low = elo_of_score((score - margin).max(f64::EPSILON));
+        let high = elo_of_score((score + margin).min(1.0 - f64::EPSILON));
+        (elo_of_score(score), (high - low) / 2.0)
+    }
+
+    /// Counts a game where A placed `place` and `first` players shared
+    /// first place.
+    pub fn add(&mut self, place: usize, first: usize) {
+        match place {
+            1 if first == 1 => self.wins += 1,
+            1 => self.draws += 1,
+            _ => self.losses += 1,
+        }
+    }
+}
+
+/// The difference in Elo that makes the stronger player score `score` on
+/// average.
+pub fn elo_of_score(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// The average score of a player `elo` stronger than the other.
+pub fn score_of_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+#[derive(Serialize, Clone, Copy)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    H0,
+    H1,
+}
+
+impl Sprt {
+    /// H0 is accepted below the first and H1 above the second.
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// The log likelihood ratio of H1 against H0 after `tally`, taking the
+    /// average score as normally distributed with the variance seen so far:
+    /// `(s1 - s0) (2s - s0 - s1) / 2 var(s)`, where `s0` and `s1` are the
+    /// scores H0 and H1 expect.
+    pub fn llr(&self, tally: &Tally) -> f64 {
+        let (score, variance) = tally.score();
+        let (s0, s1) = (score_of_elo(self.elo0), score_of_elo(self.elo1));
+        (s1 - s0) * (2.0 * score - s0 - s1) / (2.0 * variance)
+    }
+
+    pub fn decide(&self, tally: &Tally) -> Option<Decision> {
+        let llr = self.llr(tally);
+        let (lower, upper) = self.bounds();
+        if llr <= lower {
+            Some(Decision::H0)
+        } else if llr >= upper {
+            Some(Decision::H1)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Loss {
+    game: usize,
+    seed: u64,
+    /// A's seat, 0 for first.
+    seat: usize,
+}
+
+#[derive(Serialize)]
+struct SprtResult {
+    #[serde(flatten)]
+    test: Sprt,
+    llr: f64,
+    lower: f64,
+    upper: f64,
+    decision: Option<Decision>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    seed: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cards: Option<Cards>,
+    /// A's name, then B's.
+    agents: [String; 2],
+    #[serde(flatten)]
+    tally: Tally,
+    /// A's results as the first player and as the second.
+    by_seat: [Tally; 2],
+    average_rounds: f64,
+    elo: f64,
+    elo_margin: f64,
+    sprt: Option<SprtResult>,
+    /// The games A lost.
+    lost: Vec<Loss>,
+    games: Vec<GameRecord>,
+    /// What `--config` came to, if there was one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    run_config: Option<serde_json::Value>,
+}
+
+pub fn main(mut argv: impl Iterator<Item = String>) {
+    let mut games = DEFAULT_GAMES;
+    let mut jobs = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut seed = rand::random();
+    let mut alternate = false;
+    let mut sprt = None;
+    let mut alpha = 0.05;
+    let mut beta = 0.05;
+    let mut json = None;
+    let mut stats_file = None;
+    let mut record_dir = None;
+    let mut settings = Settings::default();
+    let mut commands = [None, None];
+    let mut names = [None, None];
+    let mut name = None;
+    let mut messages = false;
+    settings.messages = vec![false; 2];
+    while let Some(arg) = argv.next() {
+        match settings.flag(&arg, &mut argv) {
+            Some(true) => continue,
+            Some(false) => (),
+            None => return,
+        }
+        if arg == "--agent-a" || arg == "--agent-b" {
+            let Some(command) = argv.next() else {
+                println!("{arg} needs a command");
+                return;
+            };
+            let i = (arg == "--agent-b") as usize;
+            names[i] = Some(name.take().unwrap_or_else(|| command.clone()));
+            settings.messages[i] = std::mem::take(&mut messages);
+            commands[i] = Some(command);
+        } else if arg == "--protocol" {
+            // Applies to the next agent only.
+            match argv.next().as_deref() {
+                Some("states") => (),
+                Some("messages") => messages = true,
+                _ => {
+                    println!("--protocol needs states or messages");
+                    return;
+                }
+            }
+        } else if arg == "--name" {
+            // Applies to the next agent only.
+            let Some(x) = argv.next() else {
+                println!("--name needs a name");
+                return;
+            };
+            name = Some(x);
+        } else if arg == "--games" {
+            let Some(x) = number_arg(&mut argv, &arg) else {
+                return;
+            };
+            games = x;
+        } else if arg == "--jobs" {
+            match number_arg(&mut argv, &arg) {
+                Some(0) => {
+                    println!("--jobs needs at least 1");
+                    return;
+                }
+                Some(n) => jobs = n,
+                None => return,
+            }
+        } else if arg == "--seed" {
+            let Some(x) = number_arg(&mut argv, &arg) else {
+                return;
+            };
+            seed = x;
+        } else if arg == "--alternate-seats" {
+            alternate = true;
+        } else if arg == "--sprt" {
+            let (Some(elo0), Some(elo1)) = (
+                number_arg::<f64>(&mut argv, &arg),
+                number_arg::<f64>(&mut argv, &arg),
+            ) else {
+                return;
+            };
+            if elo0 >= elo1 {
+                println!("--sprt needs elo0 below elo1, not {elo0} and {elo1}");
+                return;
+            }
+            sprt = Some((elo0, elo1));
+        } else if arg == "--alpha" || arg == "--beta" {
+            let Some(x) = number_arg::<f64>(&mut argv, &arg) else {
+                return;
+            };
+            if !(x > 0.0 && x < 0.5) {
+                println!("{arg} needs a probability between 0 and 0.5, not {x}");
+                return;
+            }
+            if arg == "--alpha" {
+                alpha = x;
+            } else {
+                beta = x;
+            }
+        } else if arg == "--record" {
+            let Some(dir) = argv.next() else {
+                println!("--record needs a directory");
+                return;
+            };
+            if let Err(e) = std::fs::create_dir_all(&dir) {
+                println!("Failed to create {dir}: {e}");
+                return;
+            }
+            record_dir = Some(dir);
+        } else if arg == "--json" {
+            let Some(path) = argv.next() else {
+                println!("--json needs a path");
+                return;
+            };
+            json = Some(path);
+        } else if arg == "--stats-file" {
+            let Some(path) = argv.next() else {
+                println!("--stats-file needs a path");
+                return;
+            };
+            stats_file = Some(path);
+        } else {
+            println!("Unknown argument {arg}, agents are given with --agent-a and --agent-b");
+            return;
+        }
+    }
+    let ([Some(a), Some(b)], [Some(name_a), Some(name_b)]) = (commands, names) else {
+        println!("A match needs --agent-a and --agent-b");
+        return;
+    };
+    let commands = [a, b];
+    let names = unique_names(vec![name_a, name_b]);
+    let sprt = sprt.map(|(elo0, elo1)| Sprt {
+        elo0,
+        elo1,
+        alpha,
+        beta,
+    });
+    let mut rng = StdRng::seed_from_u64(seed);
+    let schedule: Vec<_> = (0..games)
+        .map(|n| {
+            let seats = match alternate && n % 2 == 1 {
+                true => vec![1, 0],
+                false => vec![0, 1],
+            };
+            (seats, rng.gen())
+        })
+        .collect();
+    let jobs = jobs.min(MAX_AGENT_PROCESSES / 2).min(games.max(1));
+    println!("Match seed {seed}, up to {games} games, {jobs} at a time");
+    if let Some(sprt) = &sprt {
+        let (lower, upper) = sprt.bounds();
+        println!(
+            "Stopping when H0 ({} Elo) or H1 ({} Elo) is decided, LLR bounds {lower:.2} and {upper:.2}",
+            sprt.elo0, sprt.elo1
+        );
+    }
+    let mut records: Vec<GameRecord> = vec![];
+    let mut tally = Tally::default();
+    let mut decision = None;
+    tournament::play_all(
+        &commands,
+        &names,
+        &schedule,
+        0,
+        &settings,
+        jobs,
+        |n, record, replay| {
+            if let (Some(dir), Some(replay)) = (&record_dir, replay) {
+                let path = format!("{dir}/game-{}.json", n + 1);
+                if let Err(e) = replay.save(&path) {
+                    println!("{e:#}");
+                }
+            }
+            if let Some(path) = &stats_file {
+                if let Err(e) = stats::append(path, &record.stats_row(&commands, &names)) {
+                    println!("{e:#}");
+                }
+            }
+            print!("Game {}: ", n + 1);
+            record.print(&names);
+            let first = record.places.iter().filter(|&&p| p == 1).count();
+            let seat = record.seats.iter().position(|&i| i == 0).unwrap();
+            tally.add(record.places[seat], first);
+            records.push(record);
+            let points = tally.wins as f64 + tally.draws as f64 / 2.0;
+            let other = tally.games() as f64 - points;
+            print!("   {} {points} - {other} {}", names[0], names[1]);
+            let Some(sprt) = &sprt else {
+                println!();
+                return true;
+            };
+            println!(", LLR {:.2}", sprt.llr(&tally));
+            decision = sprt.decide(&tally);
+            decision.is_none()
+        },
+    );
+    let mut by_seat = [Tally::default(); 2];
+    let mut lost = vec![];
+    for (n, record) in records.iter().enumerate() {
+        let seat = record.seats.iter().position(|&i| i == 0).unwrap();
+        let first = record.places.iter().filter(|&&p| p == 1).count();
+        by_seat[seat].add(record.places[seat], first);
+        if record.places[seat] != 1 {
+            lost.push(Loss {
+                game: n + 1,
+                seed: record.seed,
+                seat,
+            });
+        }
+    }
+    let (elo, elo_margin) = tally.elo();
+    let summary = Summary {
+        seed,
+        cards: settings.cards(),
+        agents: [names[0].clone(), names[1].clone()],
+        tally,
+        by_seat,
+        average_rounds: records.iter().map(|r| r.rounds as f64).sum::<f64>()
+            / records.len().max(1) as f64,
+        elo,
+        elo_margin,
+        sprt: sprt.map(|test| {
+            let (lower, upper) = test.bounds();
+            SprtResult {
+                test,
+                llr: test.llr(&tally),
+                lower,
+                upper,
+                decision,
+            }
+        }),
+        lost,
+        games: records,
+        run_config: run_config::resolved(),
+    };
+    print_summary(&summary);
+    if let Some(path) = json {
+        let json = serde_json::to_string_pretty(&summary).unwrap();
+        if let Err(e) = std::fs::write(&path, json + "\n") {
+            println!("Failed to write {path}: {e}");
+        }
+    }
+}
+
+fn print_summary(summary: &Summary) {
+    let [a, b] = &summary.agents;
+    let t = &summary.tally;
+    let wdl = |t: &Tally| format!("{}-{}-{}", t.wins, t.draws, t.losses);
+    println!();
+    println!(
+        "{a} vs {b}: {} wins, {} draws, {} losses in {} games",
+        t.wins,
+        t.draws,
+        t.losses,
+        t.games()
+    );
+    println!(
+        "{a} is {:.1} ± {:.1} Elo stronger",
+        summary.elo, summary.elo_margin
+    );
+    println!(
+        "Wins-draws-losses as the first player {}, as the second {}",
+        wdl(&summary.by_seat[0]),
+        wdl(&summary.by_seat[1])
+    );
+    println!("{:.1} rounds a game", summary.average_rounds);
+    if let Some(sprt) = &summary.sprt {
+        let verdict = match sprt.decision {
+            Some(Decision::H0) => format!("H0 ({} Elo) accepted", sprt.test.elo0),
+            Some(Decision::H1) => format!("H1 ({} Elo) accepted", sprt.test.elo1),
+            None => "undecided".to_owned(),
+        };
+        println!(
+            "SPRT {verdict}, LLR {:.2} of {:.2} to {:.2}",
+            sprt.llr, sprt.lower, sprt.upper
+        );
+    }
+    if !summary.lost.is_empty() {
+        println!("{a} lost:");
+        for loss in &summary.lost {
+            let seat = ["first", "second"][loss.seat];
+            println!("   Game {}, seed {}, {a} {seat}", loss.game, loss.seed);
+        }
+    }
+}
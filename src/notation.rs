@@ -0,0 +1,64 @@
+//! `splendor-ai notation <replay> [<output>]`: writes a game recorded with
+//! `--record` in the notation of [`game_def::notation`], to `output` or
+//! stdout. `--export-notation <path>` writes the game being played the same
+//! way once it is over.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use game_def::Replay;
+
+/// The notation of `replay`, dated `date`.
+fn render(replay: &Replay, date: SystemTime) -> anyhow::Result<String> {
+    replay.to_notation(&[("Date", day(date))])
+}
+
+/// Writes `replay`, a game that just ended, to `path`.
+pub fn export(path: &str, replay: &Replay) -> anyhow::Result<()> {
+    let text = render(replay, SystemTime::now())?;
+    std::fs::write(path, text).with_context(|| format!("Failed to write {path}"))
+}
+
+/// Like `2024-11-02`, in UTC.
+pub fn day(time: SystemTime) -> String {
+    let days = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() / 86400) as i64;
+    // Howard Hinnant's civil_from_days, with years starting in March.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+pub fn main(mut argv: impl Iterator<Item = String>) {
+    let (Some(path), output, None) = (argv.next(), argv.next(), argv.next()) else {
+        println!("Usage: splendor-ai notation <replay> [<output>]");
+        return;
+    };
+    // The replay was last written when the game ended.
+    let converted = Replay::load(&path).and_then(|replay| {
+        let date = std::fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .unwrap_or_else(|_| SystemTime::now());
+        render(&replay, date)
+    });
+    let result = converted.and_then(|text| match &output {
+        Some(output) => {
+            std::fs::write(output, text).with_context(|| format!("Failed to write {output}"))
+        }
+        None => {
+            print!("{text}");
+            Ok(())
+        }
+    });
+    if let Err(e) = result {
+        println!("Error: {e:#}");
+    }
+}
@@ -0,0 +1,182 @@
+//! `splendor-ai replay <file> [--auto] [--delay <time>] [--color <when>]`: steps through a game
+//! recorded with `--record`.
+//!
+//! Each step prints who did what, how long it took when that was recorded,
+//! and the board after it. The last one adds how long each player took.
+//! Interactively, Enter or `next` goes forward, `back` goes back, `goto <ply>`
+//! jumps, `save <file>` writes the state shown to a JSON file, which
+//! `alpha-beta-agent --analyze` reads, and `quit` stops. With `--auto` the game plays itself out, a step every `--delay`
+//! (`500ms`, `2s`, a bare number is milliseconds). `--color` draws the board
+//! like the runner does.
+
+use std::{io::BufRead, time::Duration};
+
+use game_def::{GameResult, Replay, State};
+
+use crate::args::parse_delay;
+
+const DEFAULT_DELAY: Duration = Duration::from_secs(1);
+
+/// Who did what to get to `ply`, like `Ply 3/40: alice did take r g u in 12ms`.
+pub fn describe(replay: &Replay, states: &[State], ply: usize) -> String {
+    let last = states.len() - 1;
+    match ply.checked_sub(1) {
+        None => format!("Start of the game, {last} actions recorded"),
+        Some(i) => {
+            let (player, action) = &replay.actions[i];
+            let name = &states[i].players[*player].display_name;
+            match replay.think_ms.get(i) {
+                Some(ms) => format!("Ply {ply}/{last}: {name} did {action} in {ms}ms"),
+                None => format!("Ply {ply}/{last}: {name} did {action}"),
+            }
+        }
+    }
+}
+
+/// Like `Game finished, alice won`, for the last `state` of a game that
+/// ended with `result`.
+pub fn outcome(result: &GameResult, state: &State) -> String {
+    match result {
+        GameResult::Ongoing => "The game was stopped here".to_owned(),
+        GameResult::Won(winner) => {
+            format!("Game finished, {} won", state.players[*winner].display_name)
+        }
+        GameResult::Draw(winners) => {
+            let names: Vec<_> = winners
+                .iter()
+                .map(|&i| state.players[i].display_name.as_str())
+                .collect();
+            format!("Game finished, draw between {}", names.join(", "))
+        }
+    }
+}
+
+/// Prints the position after `ply` actions.
+fn show(replay: &Replay, states: &[State], ply: usize) {
+    let state = &states[ply];
+    let last = states.len() - 1;
+    println!("{}", describe(replay, states, ply));
+    crate::print_state(state, Some(state.turn));
+    for undone in replay.undone.iter().filter(|u| u.ply == ply) {
+        let name = &state.players[undone.player].display_name;
+        println!("{name} played {} here, but it was undone", undone.action);
+    }
+    if ply == last {
+        for invalid in &replay.invalid {
+            let name = &state.players[invalid.player].display_name;
+            println!(
+                "{name} then tried {}, which is invalid: {}",
+                invalid.action, invalid.error
+            );
+        }
+        for forfeit in &replay.forfeits {
+            let name = &state.players[forfeit.player].display_name;
+            println!("{name} forfeited: {}", forfeit.reason);
+        }
+        println!("{}", outcome(&replay.result, state));
+        if !replay.think_ms.is_empty() {
+            crate::print_timings(state, &replay.timings());
+        }
+    }
+}
+
+pub fn main(mut argv: impl Iterator<Item = String>) {
+    let mut path = None;
+    let mut auto = false;
+    let mut delay = DEFAULT_DELAY;
+    while let Some(arg) = argv.next() {
+        if arg == "--auto" {
+            auto = true;
+        } else if arg == "--color" {
+            if !argv.next().is_some_and(|mode| crate::set_color(&mode)) {
+                println!("--color needs auto, always or never");
+                return;
+            }
+        } else if arg == "--delay" {
+            let Some(x) = argv.next().as_deref().and_then(parse_delay) else {
+                println!("--delay needs a time like 500ms or 2s");
+                return;
+            };
+            delay = x;
+        } else if path.is_none() {
+            path = Some(arg);
+        } else {
+            println!("Unexpected argument {arg}");
+            return;
+        }
+    }
+    let Some(path) = path else {
+        println!("Usage: splendor-ai replay <file> [--auto] [--delay <time>] [--color <when>]");
+        return;
+    };
+    let loaded = Replay::load(&path).and_then(|replay| {
+        let states = replay.checked_states()?;
+        Ok((replay, states))
+    });
+    let (replay, states) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            println!("Error: {e:#}");
+            return;
+        }
+    };
+    let result = states.last().unwrap().result();
+    if result != replay.result {
+        println!(
+            "Warning: the game ends with {result:?}, not {:?}",
+            replay.result
+        );
+    }
+    let last = states.len() - 1;
+    let mut ply = 0;
+    show(&replay, &states, ply);
+    if auto {
+        while ply < last {
+            std::thread::sleep(delay);
+            ply += 1;
+            show(&replay, &states, ply);
+        }
+        return;
+    }
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        ply = match words[..] {
+            [] | ["n" | "next"] if ply < last => ply + 1,
+            [] | ["n" | "next"] => {
+                println!("This is the end of the game");
+                continue;
+            }
+            ["b" | "back"] if ply > 0 => ply - 1,
+            ["b" | "back"] => {
+                println!("This is the start of the game");
+                continue;
+            }
+            ["g" | "goto", to] => match to.parse() {
+                Ok(to) if to <= last => to,
+                _ => {
+                    println!("goto needs a ply between 0 and {last}");
+                    continue;
+                }
+            },
+            ["s" | "save", file] => {
+                match serde_json::to_string(&states[ply]) {
+                    Ok(json) => match std::fs::write(file, json + "\n") {
+                        Ok(()) => println!("Saved ply {ply} to {file}"),
+                        Err(e) => println!("Failed to write {file}: {e}"),
+                    },
+                    Err(e) => println!("Error: {e}"),
+                }
+                continue;
+            }
+            ["q" | "quit"] => return,
+            _ => {
+                println!("Commands: next (or Enter), back, goto <ply>, save <file>, quit");
+                continue;
+            }
+        };
+        show(&replay, &states, ply);
+    }
+}
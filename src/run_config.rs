@@ -0,0 +1,385 @@
+//! `splendor-ai --config <file.toml> [options] [agent]...`: reads the options
+//! and agents of a game, a match or a tournament from a TOML file, like
+//! `examples/match.toml`, instead of typing them out every time.
+//!
+//! The file has one `[game]`, `[match]` or `[tournament]` table with the
+//! options of that kind of run, named like their flags with `_` for `-`, so
+//! `max_rounds = 50` is `--max-rounds 50` and `mirrored = true` is
+//! `--mirrored`. The options every kind of run takes, like `seed`,
+//! `max_rounds`, `time_budget`, `cards`, `record` and `stats_file`, may come
+//! before it too. Each `[[agents]]` table is an agent with a `command`, and
+//! maybe a `name` and a `protocol`, and in a game a `format` and a
+//! `time_budget` of its own. A match takes agent A and then agent B.
+//!
+//! Options on the command line win over the file, so the same file can be
+//! run with another `--seed`, and agents on the command line come after
+//! those of the file. A key that isn't an option, or a value of the wrong
+//! type, stops the run with the line it is on. What was run in the end goes
+//! into each replay and `--json` summary as `run_config`.
+//!
+//! Only the part of TOML these files need is read: comments, tables, arrays
+//! of tables, and strings, numbers, booleans and arrays on one line.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::OnceLock,
+};
+
+use anyhow::{bail, Context};
+use config_toml::{parse, Value};
+use serde_json::json;
+
+/// The `--config` that was run, see [`resolved`].
+static RESOLVED: OnceLock<serde_json::Value> = OnceLock::new();
+
+/// The file, the kind of run, its options with the command line's over
+/// those of the file, its agents and the arguments it all came to, if the
+/// run had a `--config`.
+pub fn resolved() -> Option<serde_json::Value> {
+    RESOLVED.get().cloned()
+}
+
+/// What an option takes, and so how it becomes flags.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// `true` for the flag alone.
+    Switch,
+    /// A whole number, 0 or more.
+    Number,
+    /// Any number, like seconds.
+    Decimal,
+    /// A string, or a number for options like `first`.
+    Text,
+    /// Strings, each after the flag again.
+    List,
+    /// Two numbers after the flag.
+    Pair,
+}
+
+use Kind::*;
+
+/// The options every kind of run takes, as `(key, flag, kind)`.
+const SHARED: &[(&str, &str, Kind)] = &[
+    ("seed", "--seed", Number),
+    ("max_rounds", "--max-rounds", Number),
+    ("target_score", "--target-score", Number),
+    ("invalid_retries", "--invalid-retries", Number),
+    ("move_timeout", "--move-timeout", Decimal),
+    ("time_budget", "--time-budget", Decimal),
+    ("increment", "--increment", Decimal),
+    ("cards", "--cards", Text),
+    ("record", "--record", Text),
+    ("stats_file", "--stats-file", Text),
+];
+
+const GAME: &[(&str, &str, Kind)] = &[
+    ("players", "--players", Number),
+    ("first", "--first", Text),
+    ("variant", "--variant", Text),
+    ("coins", "--coins", Number),
+    ("wilds", "--wilds", Number),
+    ("handicaps", "--handicap", List),
+    ("on_invalid", "--on-invalid", Text),
+    ("on_crash", "--on-crash", Text),
+    ("output", "--output", Text),
+    ("observers", "--observer", List),
+    ("observer_sees", "--observer-sees", Text),
+    ("quiet", "--quiet", Switch),
+    ("export_notation", "--export-notation", Text),
+    ("agent_log_dir", "--agent-log-dir", Text),
+    ("transcript_dir", "--transcript-dir", Text),
+];
+
+const MATCH: &[(&str, &str, Kind)] = &[
+    ("games", "--games", Number),
+    ("jobs", "--jobs", Number),
+    ("alternate_seats", "--alternate-seats", Switch),
+    ("reuse_agents", "--reuse-agents", Switch),
+    ("sprt", "--sprt", Pair),
+    ("alpha", "--alpha", Decimal),
+    ("beta", "--beta", Decimal),
+    ("json", "--json", Text),
+];
+
+const TOURNAMENT: &[(&str, &str, Kind)] = &[
+    ("games", "--games", Number),
+    ("players", "--players", Number),
+    ("jobs", "--jobs", Number),
+    ("mirrored", "--mirrored", Switch),
+    ("reuse_agents", "--reuse-agents", Switch),
+    ("checkpoint", "--checkpoint", Text),
+    ("ratings", "--ratings", Text),
+    ("json", "--json", Text),
+];
+
+/// The keys of an `[[agents]]` table, of which `format` and `time_budget`
+/// are only for a game.
+const AGENT: &[(&str, &str, Kind)] = &[
+    ("command", "", Text),
+    ("name", "--name", Text),
+    ("protocol", "--protocol", Text),
+    ("format", "--format", Text),
+    ("time_budget", "--agent-time-budget", Decimal),
+];
+
+/// The options of `section`, those of every kind of run first.
+fn keys(section: &str) -> impl Iterator<Item = &'static (&'static str, &'static str, Kind)> {
+    let own = match section {
+        "game" => GAME,
+        "match" => MATCH,
+        _ => TOURNAMENT,
+    };
+    SHARED.iter().chain(own)
+}
+
+/// A number as it goes on the command line.
+fn number(value: &Value) -> Option<String> {
+    match value {
+        Value::Integer(n) => Some(n.to_string()),
+        Value::Float(x) => Some(x.to_string()),
+        _ => None,
+    }
+}
+
+/// The flags for an option of `kind` set to `value`, if it is of that
+/// kind.
+fn flags(value: &Value, flag: &str, kind: Kind) -> Option<Vec<String>> {
+    let words = match (kind, value) {
+        (Switch, &Value::Bool(on)) => on.then(|| flag.to_owned()).into_iter().collect(),
+        (Number, &Value::Integer(n)) if n >= 0 => vec![flag.to_owned(), n.to_string()],
+        (Decimal, _) => vec![flag.to_owned(), number(value)?],
+        (Text, Value::Text(s)) => vec![flag.to_owned(), s.clone()],
+        (Text, &Value::Integer(n)) => vec![flag.to_owned(), n.to_string()],
+        (List, Value::Array(items)) => {
+            let mut words = vec![];
+            for item in items {
+                let Value::Text(s) = item else {
+                    return None;
+                };
+                words.extend([flag.to_owned(), s.clone()]);
+            }
+            words
+        }
+        (Pair, Value::Array(items)) if items.len() == 2 => {
+            vec![flag.to_owned(), number(&items[0])?, number(&items[1])?]
+        }
+        _ => return None,
+    };
+    Some(words)
+}
+
+fn expected(kind: Kind) -> &'static str {
+    match kind {
+        Switch => "true or false",
+        Number => "a whole number",
+        Decimal => "a number",
+        Text => "a string",
+        List => "an array of strings",
+        Pair => "an array of two numbers",
+    }
+}
+
+/// A `--config` file, checked.
+struct RunFile {
+    section: &'static str,
+    options: BTreeMap<String, Value>,
+    agents: Vec<Vec<(String, Value)>>,
+}
+
+impl RunFile {
+    fn load(path: &str) -> anyhow::Result<RunFile> {
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+        let tables = parse(path, &text)?;
+        let mut section = None;
+        for table in &tables[1..] {
+            let at = format!("{path}:{}", table.line);
+            match (table.name.as_str(), table.array) {
+                ("agents", true) => (),
+                ("agents", false) => bail!("{at}: agents are [[agents]], one table each"),
+                (name @ ("game" | "match" | "tournament"), false) => match section {
+                    None => section = Some(name),
+                    Some(other) => bail!("{at}: [{name}] after [{other}], a file is for one run"),
+                },
+                (name, _) => {
+                    bail!("{at}: unknown table {name}, try [game], [match] or [tournament]")
+                }
+            }
+        }
+        let section = match section {
+            Some("game") => "game",
+            Some("match") => "match",
+            Some(_) => "tournament",
+            None => bail!("{path} needs a [game], [match] or [tournament] table"),
+        };
+        let mut options = BTreeMap::new();
+        let mut agents = vec![];
+        for table in &tables {
+            if table.name == "agents" {
+                let mut agent = vec![];
+                for (key, value, line) in &table.entries {
+                    let at = format!("{path}:{line}");
+                    let Some(&(_, flag, kind)) = AGENT.iter().find(|(k, ..)| k == key) else {
+                        bail!("{at}: unknown key {key} in [[agents]], try command or name");
+                    };
+                    if section != "game" && matches!(key.as_str(), "format" | "time_budget") {
+                        bail!("{at}: {key} is only for the agents of a [game]");
+                    }
+                    if flags(value, flag, kind).is_none() {
+                        bail!("{at}: {key} needs {}, not {value}", expected(kind));
+                    }
+                    agent.push((key.clone(), value.clone()));
+                }
+                if !agent.iter().any(|(key, _)| key == "command") {
+                    bail!("{path}:{}: [[agents]] needs a command", table.line);
+                }
+                agents.push(agent);
+                continue;
+            }
+            for (key, value, line) in &table.entries {
+                let at = format!("{path}:{line}");
+                let Some(&(_, flag, kind)) = keys(section).find(|(k, ..)| k == key) else {
+                    bail!("{at}: unknown key {key} for a [{section}]");
+                };
+                if flags(value, flag, kind).is_none() {
+                    bail!("{at}: {key} needs {}, not {value}", expected(kind));
+                }
+                if options.insert(key.clone(), value.clone()).is_some() {
+                    bail!("{at}: {key} is set twice");
+                }
+            }
+        }
+        if section == "match" && agents.len() > 2 {
+            bail!(
+                "{path}: a match is between two agents, not {}",
+                agents.len()
+            );
+        }
+        Ok(RunFile {
+            section,
+            options,
+            agents,
+        })
+    }
+}
+
+/// Turns a command line with `--config <file>` into the one it stands for,
+/// starting with the subcommand of a match or a tournament, and keeps what
+/// it came to for [`resolved`]. Any other command line is left as it is.
+pub fn expand(mut args: Vec<String>) -> anyhow::Result<Vec<String>> {
+    let Some(at) = args.iter().position(|arg| arg == "--config") else {
+        return Ok(args);
+    };
+    if at + 1 == args.len() {
+        bail!("--config needs a path");
+    }
+    let path = args.remove(at + 1);
+    args.remove(at);
+    let file = RunFile::load(&path)?;
+    if let Some(command) = args.first() {
+        if command == "match" || command == "tournament" {
+            if command != file.section {
+                bail!("{path} is for a [{}], not a {command}", file.section);
+            }
+            args.remove(0);
+        }
+    }
+    // The command line's options over the file's, the rest left as they are.
+    let mut options = file.options;
+    let mut listed = HashSet::new();
+    let mut rest = vec![];
+    let mut cli = args.into_iter();
+    while let Some(arg) = cli.next() {
+        let Some(&(key, flag, kind)) = keys(file.section).find(|(_, f, _)| *f == arg) else {
+            rest.push(arg);
+            continue;
+        };
+        let mut next = |expected: &str| {
+            cli.next()
+                .with_context(|| format!("{flag} needs {expected}"))
+        };
+        let value = match kind {
+            Switch => Value::Bool(true),
+            Number => {
+                let n = next("a number")?;
+                Value::Integer(
+                    n.parse::<i64>()
+                        .ok()
+                        .filter(|&n| n >= 0)
+                        .with_context(|| format!("{flag} needs a number"))?,
+                )
+            }
+            Decimal | Pair => {
+                let mut numbers = vec![];
+                for _ in 0..if kind == Pair { 2 } else { 1 } {
+                    let x: f64 = next("a number")?
+                        .parse()
+                        .ok()
+                        .with_context(|| format!("{flag} needs a number"))?;
+                    numbers.push(Value::Float(x));
+                }
+                match kind {
+                    Pair => Value::Array(numbers),
+                    _ => numbers.remove(0),
+                }
+            }
+            Text | List => Value::Text(next("a value")?),
+        };
+        if kind == List {
+            // The command line's list replaces the file's.
+            if listed.insert(key) {
+                options.insert(key.to_owned(), Value::Array(vec![]));
+            }
+            if let Some(Value::Array(items)) = options.get_mut(key) {
+                items.push(value);
+            }
+        } else {
+            options.insert(key.to_owned(), value);
+        }
+    }
+    let mut argv = vec![];
+    if file.section != "game" {
+        argv.push(file.section.to_owned());
+    }
+    for &(key, flag, kind) in keys(file.section) {
+        if let Some(value) = options.get(key) {
+            argv.extend(flags(value, flag, kind).unwrap_or_default());
+        }
+    }
+    for (i, agent) in file.agents.iter().enumerate() {
+        let mut command = None;
+        for (key, value) in agent {
+            let &(_, flag, kind) = AGENT.iter().find(|(k, ..)| k == key).unwrap();
+            match flags(value, flag, kind).unwrap_or_default().pop() {
+                Some(word) if key == "command" => command = Some(word),
+                Some(word) => argv.extend([flag.to_owned(), word]),
+                None => (),
+            }
+        }
+        if file.section == "match" {
+            argv.push(["--agent-a", "--agent-b"][i].to_owned());
+        }
+        argv.extend(command);
+    }
+    argv.extend(rest);
+    let agents: Vec<serde_json::Value> = file
+        .agents
+        .iter()
+        .map(|agent| {
+            let fields = agent.iter().map(|(key, value)| (key.clone(), value.json()));
+            serde_json::Value::Object(fields.collect())
+        })
+        .collect();
+    let options: serde_json::Map<_, _> = options
+        .iter()
+        .map(|(key, value)| (key.clone(), value.json()))
+        .collect();
+    let _ = RESOLVED.set(json!({
+        "file": path,
+        "section": file.section,
+        "options": options,
+        "agents": agents,
+        "arguments": argv,
+    }));
+    Ok(argv)
+}
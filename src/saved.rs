@@ -0,0 +1,77 @@
+//! `save <file>` at the human prompt and `--load-state <file>`: a game put
+//! aside to go on with later.
+//!
+//! The file holds the whole [`State`], face down cards included, so whoever
+//! reads it knows what comes next. Keep it from the players of a game that
+//! counts, or resume with `--load-redacted <file>` instead, which deals the
+//! face down cards again at random. That also resumes positions that were
+//! published.
+//!
+//! On Ctrl+C the runner saves the game the same way, to
+//! `splendor-autosave-<unix time>.json` in the current directory.
+//!
+//! The agents of the saved game are started again unless others are given.
+//! Their `--format` and `--protocol` aren't kept, and the rules come from
+//! the file, not from `--variant` and the like.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context};
+use game_def::{Action, State};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedGame {
+    pub state: State,
+    /// How each seat was played, as on the command line: `human-<name>` or
+    /// an agent.
+    pub agents: Vec<String>,
+    /// The seed the game was dealt with.
+    pub seed: u64,
+    /// Who played each move so far, and the move.
+    #[serde(default)]
+    pub history: Vec<(usize, Action)>,
+}
+
+impl SavedGame {
+    pub fn load(path: &str) -> anyhow::Result<SavedGame> {
+        let json =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+        serde_json::from_str(&json).with_context(|| format!("{path} is not a saved game"))
+    }
+
+    /// Writes to a temporary file first, so a save that is cut short doesn't
+    /// destroy the last one.
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        let json = serde_json::to_string(self)?;
+        let temporary = format!("{path}.tmp");
+        std::fs::write(&temporary, json + "\n")
+            .with_context(|| format!("Failed to write {temporary}"))?;
+        std::fs::rename(&temporary, path).with_context(|| format!("Failed to write {path}"))
+    }
+
+    /// Saves to a new file named after the time, and returns its path.
+    pub fn autosave(&self) -> anyhow::Result<String> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let path = format!("splendor-autosave-{now}.json");
+        self.save(&path)?;
+        Ok(path)
+    }
+
+    /// Checks that the game can go on with `agents` players.
+    pub fn check(&self, agents: usize) -> anyhow::Result<()> {
+        self.state
+            .validate()
+            .context("The saved state breaks the rules")?;
+        let players = self.state.players.len();
+        if players != agents {
+            bail!("The saved game has {players} players, but {agents} agents were given");
+        }
+        if self.state.is_finished() {
+            bail!("The saved game is already over");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+//! `--serve <addr>`: a small HTTP server to watch a game from a browser.
+//!
+//! `/` is a board view that polls the other two, `/state` is the current
+//! [`State`] as JSON and `/moves` every move so far, as a list of
+//! [`Move`]. The state includes the face down cards, so don't serve it
+//! where agents can reach it.
+//!
+//! The game loop only takes the lock to store the state after a move, and
+//! requests are answered on threads of their own, so a slow spectator can't
+//! hold up the game.
+//!
+//! [`serve`] is the server itself, which also serves the leaderboard of
+//! `splendor-ai arena`.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use game_def::{Action, State};
+use serde::Serialize;
+
+const PAGE: &str = include_str!("spectator.html");
+/// How long a spectator has to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Header lines of a request before it is refused.
+const MAX_HEADERS: usize = 100;
+
+#[derive(Serialize)]
+pub struct Move {
+    pub player: usize,
+    pub action: Action,
+    /// The action as the runner prints it, like `buy 2.9`.
+    pub text: String,
+}
+
+struct Game {
+    state: State,
+    moves: Vec<Move>,
+}
+
+/// The game as spectators see it.
+#[derive(Clone)]
+pub struct Spectators(Arc<RwLock<Game>>);
+
+impl Spectators {
+    /// Serves `state` on `address` until the runner exits.
+    pub fn serve(address: &str, state: &State) -> std::io::Result<(Spectators, SocketAddr)> {
+        let spectators = Spectators(Arc::new(RwLock::new(Game {
+            state: state.clone(),
+            moves: vec![],
+        })));
+        let shared = spectators.clone();
+        let address = serve(address, move |path| {
+            // Serialized under the lock, sent after it is released.
+            let game = shared.0.read().unwrap_or_else(|e| e.into_inner());
+            match path {
+                "/" => Some(("text/html; charset=utf-8", PAGE.to_owned())),
+                "/state" => Some(("application/json", game.state.json())),
+                "/moves" => Some((
+                    "application/json",
+                    serde_json::to_string(&game.moves).unwrap(),
+                )),
+                _ => None,
+            }
+        })?;
+        Ok((spectators, address))
+    }
+
+    /// Stores the state after `player` did `action`.
+    pub fn moved(&self, state: &State, player: usize, action: &Action) {
+        let state = state.clone();
+        let m = Move {
+            player,
+            action: action.clone(),
+            text: action.to_string(),
+        };
+        let mut game = self.0.write().unwrap_or_else(|e| e.into_inner());
+        game.state = state;
+        game.moves.push(m);
+    }
+
+    /// Takes back the last `count` moves, which leaves `state`.
+    pub fn undone(&self, state: &State, count: usize) {
+        let mut game = self.0.write().unwrap_or_else(|e| e.into_inner());
+        game.state = state.clone();
+        let left = game.moves.len().saturating_sub(count);
+        game.moves.truncate(left);
+    }
+}
+
+/// Answers GET requests on `address` until the runner exits, each with the
+/// content type and body `page` gives for its path, or 404 if none.
+pub fn serve(
+    address: &str,
+    page: impl Fn(&str) -> Option<(&'static str, String)> + Send + Sync + 'static,
+) -> std::io::Result<SocketAddr> {
+    let listener = TcpListener::bind(address)?;
+    let address = listener.local_addr()?;
+    let page = Arc::new(page);
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let page = page.clone();
+            std::thread::spawn(move || _ = respond(stream, &*page));
+        }
+    });
+    Ok(address)
+}
+
+fn respond(
+    mut stream: TcpStream,
+    page: &dyn Fn(&str) -> Option<(&'static str, String)>,
+) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    for _ in 0..MAX_HEADERS {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+    }
+    let (status, content_type, body) = match request.split_whitespace().collect::<Vec<_>>()[..] {
+        ["GET", target, _] => {
+            let path = target.split('?').next().unwrap_or_default();
+            match page(path) {
+                Some((content_type, body)) => ("200 OK", content_type, body),
+                None => ("404 Not Found", "text/plain", "Not found\n".to_owned()),
+            }
+        }
+        [_, _, _] => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "Spectators can only look\n".to_owned(),
+        ),
+        _ => ("400 Bad Request", "text/plain", "Bad request\n".to_owned()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
@@ -0,0 +1,362 @@
+//! `--stats-file <path>`: a CSV file that gets a row for every game finished,
+//! kept across runs to look back at later, and the `splendor-ai stats <file>`
+//! summary of it.
+//!
+//! The file is created with a header when missing. Rows are written once a
+//! game is over, holding `path.lock` like the ratings, so games played side
+//! by side, in a tournament or in other runners, never mix up their rows.
+//! Games stopped before the end get no row.
+//!
+//! The columns are `timestamp` (seconds since the Unix epoch at the end of
+//! the game), `seed`, `first` (the name of the player who moved first),
+//! `result` (`win` or `draw`), `rounds`, `duration_ms`, `termination` (see
+//! [`Termination`]), then `name`, `command`, `score` and `place` for each of
+//! 4 seats, empty for seats nobody played.
+
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context};
+use game_def::State;
+
+use crate::elo;
+
+const SEATS: usize = 4;
+
+/// How a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Termination {
+    /// By the rules: someone won, resigned, or the round limit was reached.
+    #[default]
+    Normal,
+    /// An agent ran out of time.
+    Timeout,
+    /// An agent played an action the rules refuse.
+    Forfeit,
+    /// An agent didn't start, closed its output or sent something that is
+    /// not an action.
+    Crash,
+}
+
+impl Termination {
+    /// Keeps the first way a game went wrong.
+    pub fn add(&mut self, termination: Termination) {
+        if *self == Termination::Normal {
+            *self = termination;
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Termination::Normal => "normal",
+            Termination::Timeout => "timeout",
+            Termination::Forfeit => "forfeit",
+            Termination::Crash => "crash",
+        }
+    }
+}
+
+impl FromStr for Termination {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Ok(match s {
+            "normal" => Termination::Normal,
+            "timeout" => Termination::Timeout,
+            "forfeit" => Termination::Forfeit,
+            "crash" => Termination::Crash,
+            _ => bail!("Unknown termination {s:?}"),
+        })
+    }
+}
+
+/// One player of a game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Seat {
+    pub name: String,
+    pub command: String,
+    pub score: u8,
+    /// 1 for the winners.
+    pub place: usize,
+}
+
+/// The players of the finished game `state`, with `commands` in their seats,
+/// from `first`, who moved first.
+pub fn seats(state: &State, commands: &[String], first: usize) -> Vec<Seat> {
+    let mut seats: Vec<Seat> = state
+        .players
+        .iter()
+        .zip(commands)
+        .zip(state.placements())
+        .map(|((player, command), place)| Seat {
+            name: player.display_name.clone(),
+            command: command.clone(),
+            score: player.score,
+            place,
+        })
+        .collect();
+    seats.rotate_left(first);
+    seats
+}
+
+/// A row of the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub timestamp: u64,
+    pub seed: u64,
+    pub rounds: usize,
+    pub duration_ms: u64,
+    pub termination: Termination,
+    /// In the order they moved.
+    pub seats: Vec<Seat>,
+}
+
+impl Row {
+    pub fn new(
+        seed: u64,
+        rounds: usize,
+        duration: Duration,
+        termination: Termination,
+        seats: Vec<Seat>,
+    ) -> Row {
+        Row {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            seed,
+            rounds,
+            duration_ms: duration.as_millis() as u64,
+            termination,
+            seats,
+        }
+    }
+
+    fn is_draw(&self) -> bool {
+        self.seats.iter().filter(|s| s.place == 1).count() > 1
+    }
+
+    fn fields(&self) -> Vec<String> {
+        let mut fields = vec![
+            self.timestamp.to_string(),
+            self.seed.to_string(),
+            self.seats[0].name.clone(),
+            if self.is_draw() { "draw" } else { "win" }.to_owned(),
+            self.rounds.to_string(),
+            self.duration_ms.to_string(),
+            self.termination.as_str().to_owned(),
+        ];
+        for i in 0..SEATS {
+            match self.seats.get(i) {
+                Some(s) => fields.extend([
+                    s.name.clone(),
+                    s.command.clone(),
+                    s.score.to_string(),
+                    s.place.to_string(),
+                ]),
+                None => fields.extend(vec![String::new(); 4]),
+            }
+        }
+        fields
+    }
+
+    fn parse(fields: &[String]) -> anyhow::Result<Row> {
+        if fields.len() != 7 + 4 * SEATS {
+            bail!("{} columns instead of {}", fields.len(), 7 + 4 * SEATS);
+        }
+        let number = |i: usize| -> anyhow::Result<u64> {
+            fields[i]
+                .parse()
+                .with_context(|| format!("{:?} is not a number", fields[i]))
+        };
+        let mut seats = vec![];
+        for seat in fields[7..].chunks(4) {
+            if seat[0].is_empty() {
+                break;
+            }
+            seats.push(Seat {
+                name: seat[0].clone(),
+                command: seat[1].clone(),
+                score: seat[2].parse().context("Invalid score")?,
+                place: seat[3].parse().context("Invalid place")?,
+            });
+        }
+        if seats.len() < 2 {
+            bail!("Fewer than 2 players");
+        }
+        Ok(Row {
+            timestamp: number(0)?,
+            seed: number(1)?,
+            rounds: number(4)? as usize,
+            duration_ms: number(5)?,
+            termination: fields[6].parse()?,
+            seats,
+        })
+    }
+}
+
+fn header() -> Vec<String> {
+    let mut header: Vec<String> = [
+        "timestamp",
+        "seed",
+        "first",
+        "result",
+        "rounds",
+        "duration_ms",
+        "termination",
+    ]
+    .map(String::from)
+    .to_vec();
+    for i in 1..=SEATS {
+        for column in ["name", "command", "score", "place"] {
+            header.push(format!("{column}_{i}"));
+        }
+    }
+    header
+}
+
+/// Quotes fields with commas, quotes or line breaks in them.
+fn csv_line(fields: &[String]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|f| match f.contains([',', '"', '\n', '\r']) {
+            true => format!("\"{}\"", f.replace('"', "\"\"")),
+            false => f.clone(),
+        })
+        .collect();
+    fields.join(",") + "\n"
+}
+
+/// Splits `text` into lines of fields, minding quotes.
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut lines = vec![];
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            '\n' if !quoted => {
+                fields.push(std::mem::take(&mut field));
+                lines.push(std::mem::take(&mut fields));
+            }
+            '\r' if !quoted => (),
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        lines.push(fields);
+    }
+    lines
+}
+
+/// Appends `row` to the file at `path`, with a header first if it is new.
+pub fn append(path: &str, row: &Row) -> anyhow::Result<()> {
+    elo::locked(path, || {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open {path}"))?;
+        let mut text = String::new();
+        if file.metadata()?.len() == 0 {
+            text += &csv_line(&header());
+        }
+        text += &csv_line(&row.fields());
+        file.write_all(text.as_bytes())
+            .with_context(|| format!("Failed to write {path}"))
+    })
+}
+
+pub fn read(path: &str) -> anyhow::Result<Vec<Row>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+    let mut lines = parse_csv(&text).into_iter().enumerate();
+    match lines.next() {
+        Some((_, first)) if first == header() => (),
+        _ => bail!("{path} doesn't start with the header of a stats file"),
+    }
+    lines
+        .map(|(i, fields)| Row::parse(&fields).with_context(|| format!("Row {} of {path}", i + 1)))
+        .collect()
+}
+
+#[derive(Default)]
+struct Totals {
+    games: usize,
+    wins: usize,
+    draws: usize,
+    losses: usize,
+    rounds: usize,
+}
+
+/// `splendor-ai stats <file>`: the games of each agent in the file.
+pub fn main(mut argv: impl Iterator<Item = String>) {
+    let (Some(path), None) = (argv.next(), argv.next()) else {
+        println!("Usage: splendor-ai stats <file>");
+        return;
+    };
+    let rows = match read(&path) {
+        Ok(rows) => rows,
+        Err(e) => {
+            println!("Error: {e:#}");
+            return;
+        }
+    };
+    let mut totals = BTreeMap::<&str, Totals>::new();
+    for row in &rows {
+        let draw = row.is_draw();
+        for seat in &row.seats {
+            let t = totals.entry(&seat.name).or_default();
+            t.games += 1;
+            t.rounds += row.rounds;
+            match seat.place {
+                1 if draw => t.draws += 1,
+                1 => t.wins += 1,
+                _ => t.losses += 1,
+            }
+        }
+    }
+    let width = totals
+        .keys()
+        .map(|name| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Agent".len());
+    let ended: Vec<String> = [
+        Termination::Normal,
+        Termination::Timeout,
+        Termination::Forfeit,
+        Termination::Crash,
+    ]
+    .iter()
+    .map(|&t| {
+        let count = rows.iter().filter(|row| row.termination == t).count();
+        format!("{count} {}", t.as_str())
+    })
+    .collect();
+    println!("{} games: {}", rows.len(), ended.join(", "));
+    println!(
+        "{:<width$}  {:>5}  {:>4}  {:>5}  {:>6}  {:>6}",
+        "Agent", "Games", "Wins", "Draws", "Losses", "Rounds"
+    );
+    for (name, t) in totals {
+        println!(
+            "{name:<width$}  {:>5}  {:>4}  {:>5}  {:>6}  {:>6.1}",
+            t.games,
+            t.wins,
+            t.draws,
+            t.losses,
+            t.rounds as f64 / t.games as f64
+        );
+    }
+}
@@ -0,0 +1,1105 @@
+//! `splendor-ai tournament [options] <agent>...`: plays every pair of agents
+//! against each other and prints a table of the results. With `--players 3`
+//! or `4`, every group of that many agents plays instead.
+//!
+//! Each agent is a command line like `"python3 bot.py --depth 5"`, and goes
+//! by it in the results and the ratings unless `--name <name>` comes before
+//! it. Agents with the same name are numbered.
+//!
+//! Each pairing is played `--games` times (2 by default), moving everyone a
+//! seat along every game. The seed of each game comes from `--seed`, so a whole
+//! tournament can be run again. An agent that times out, sends an invalid
+//! action or crashes loses the game. `--json <path>` writes the standings and
+//! every game for other tools, and `--ratings <path>` updates the Elo ratings
+//! kept there after each game. `--record <dir>` saves a replay of each game
+//! there, and `--stats-file <path>` adds a row for it to that CSV file, see
+//! [`stats`].
+//!
+//! With more than two players, an agent scores 1 for each other player it
+//! placed ahead of and ½ for each it tied with, divided by how many there
+//! were, so the sole winner scores 1. A game counts as a win only for a sole
+//! winner and as a draw for those sharing first place. Ratings are updated as
+//! if each pair of players had played a game of their own.
+//!
+//! With `--mirrored`, each game is played a second time on the same board
+//! with the seats swapped, so neither the shuffle nor going first favors
+//! anyone. The two games are scored together as a pair, next to the usual
+//! counts of each game. It only works for two players.
+//!
+//! `--jobs <n>` plays that many games at once, one for each core by default,
+//! each with agents of its own, up to [`MAX_AGENT_PROCESSES`] agents in all.
+//! Games are still reported, rated and saved in order, so a tournament comes
+//! out the same however many run at once, as long as the agents don't run
+//! out of time.
+//!
+//! With `--format swiss`, agents don't all play each other but meet over
+//! `--rounds` rounds (enough to leave one agent on top by default), each
+//! pairing agents with about the same score who haven't met yet, see
+//! [`game_def::swiss`]. A pair plays its `--games` like any other and scores
+//! the share of their points each took, so 1 for winning them all. With an
+//! odd number of agents, one sits out each round and scores 1 for it.
+//! Agents move first about as often as second, and the table ranks them by
+//! score, then by Buchholz, the scores of their opponents added up. Only
+//! tournaments of two players can be Swiss.
+//!
+//! `--checkpoint <path>` keeps the results so far in a file, which
+//! `--resume <path>` goes on from if the tournament is cut short, see
+//! [`checkpoint`].
+//!
+//! Every game starts each agent again, unless `--reuse-agents` keeps it for
+//! the next game against the same opponents, for agents that are slow to
+//! start. Only agents that speak the message protocol, with `--protocol
+//! messages` before them, and answer that they can play another game are
+//! kept, see [`game_def::AgentMessage::NewGames`]; the others are started
+//! again as usual. Each game still has a clock of its own, and an agent that
+//! forfeited is never kept.
+//!
+//! `--move-timeout`, `--time-budget`, `--increment`, `--invalid-retries`,
+//! `--max-rounds` (100 by default), `--target-score` and `--cards` work like
+//! for a single game. The name and hash of the `--cards` file go into the
+//! `--json` output, and the whole set into each replay.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::channel,
+    },
+    time::{Duration, Instant},
+};
+
+use game_def::{
+    cards::CardSet,
+    swiss::{self, Entrant},
+    Action, EndReason, GameConfig, GameResult, InvalidAttempt, Replay, RunnerMessage, State,
+    PROTOCOL_VERSION,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    broadcast_played,
+    checkpoint::{self, Checkpoint, Swiss},
+    elo, limits, number_arg, request_move, run_config,
+    stats::{self, Termination},
+    unique_names, Agent, AgentLog, DEFAULT_MAX_ROUNDS, DEFAULT_MOVE_TIMEOUT,
+};
+
+/// Agents running at once, whatever `--jobs` says.
+pub const MAX_AGENT_PROCESSES: usize = 64;
+
+/// How each game is played, the same for all of them.
+pub struct Settings {
+    pub config: GameConfig,
+    pub move_timeout: Duration,
+    pub time_budget: Option<Duration>,
+    pub increment: Duration,
+    pub invalid_retries: u32,
+    /// Which agents, by their index, speak the message protocol.
+    pub messages: Vec<bool>,
+    /// Keeps the agents that can play another game for the next one against
+    /// the same opponents, see `--reuse-agents`.
+    pub reuse_agents: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            config: GameConfig {
+                max_rounds: Some(DEFAULT_MAX_ROUNDS),
+                ..GameConfig::default()
+            },
+            move_timeout: DEFAULT_MOVE_TIMEOUT,
+            time_budget: None,
+            increment: Duration::ZERO,
+            invalid_retries: 0,
+            messages: vec![],
+            reuse_agents: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Reads `arg` and its value if it is one of the settings. Returns
+    /// whether it was, or `None` after printing what is wrong with the value.
+    pub fn flag(&mut self, arg: &str, argv: &mut impl Iterator<Item = String>) -> Option<bool> {
+        match arg {
+            "--max-rounds" => self.config.max_rounds = Some(number_arg(argv, arg)?),
+            "--target-score" => self.config.target_score = number_arg(argv, arg)?,
+            "--invalid-retries" => self.invalid_retries = number_arg(argv, arg)?,
+            "--reuse-agents" => self.reuse_agents = true,
+            "--cards" => {
+                let Some(path) = argv.next() else {
+                    println!("--cards needs a path");
+                    return None;
+                };
+                match CardSet::load(&path) {
+                    Ok(set) => self.config.cards = Some(set.into()),
+                    Err(e) => {
+                        println!("{e:#}");
+                        return None;
+                    }
+                }
+            }
+            "--move-timeout" | "--time-budget" | "--increment" => {
+                let x = number_arg(argv, arg)?;
+                let Ok(x) = Duration::try_from_secs_f64(x) else {
+                    println!("{arg} needs a positive number of seconds");
+                    return None;
+                };
+                match arg {
+                    "--move-timeout" => self.move_timeout = x,
+                    "--time-budget" => self.time_budget = Some(x),
+                    _ => self.increment = x,
+                }
+            }
+            _ => return limits::flag(arg, argv),
+        }
+        Some(true)
+    }
+
+    /// The `--cards` file played with, if any.
+    pub fn cards(&self) -> Option<Cards> {
+        self.config.cards.as_ref().map(|set| Cards {
+            name: set.name.clone(),
+            hash: set.hash.clone(),
+        })
+    }
+}
+
+/// Which [`CardSet`] the games were played with, so results aren't mixed up
+/// with those of other cards.
+#[derive(Serialize)]
+pub struct Cards {
+    pub name: String,
+    pub hash: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GameRecord {
+    /// The agent in each seat, by their index on the command line.
+    pub seats: Vec<usize>,
+    pub seed: u64,
+    /// The agents that won, more than one for a draw.
+    pub winners: Vec<usize>,
+    pub rounds: usize,
+    /// The score of each seat.
+    pub scores: Vec<u8>,
+    /// The place of each seat, 1 for the winners, shared on a tie.
+    pub places: Vec<usize>,
+    /// The agent that lost the game by failing to move, and how.
+    pub forfeit: Option<Forfeit>,
+    /// With `--mirrored`, the index of the pair of games on the same board.
+    pub pair: Option<usize>,
+    /// `round_limit` for a game adjudicated by score.
+    pub end_reason: Option<EndReason>,
+    #[serde(skip)]
+    pub termination: Termination,
+    #[serde(skip)]
+    pub duration: Duration,
+}
+
+impl GameRecord {
+    /// The row of `--stats-file` for this game.
+    pub fn stats_row(&self, commands: &[String], names: &[String]) -> stats::Row {
+        let seats = self
+            .seats
+            .iter()
+            .zip(&self.scores)
+            .zip(&self.places)
+            .map(|((&i, &score), &place)| stats::Seat {
+                name: names[i].clone(),
+                command: commands[i].clone(),
+                score,
+                place,
+            })
+            .collect();
+        stats::Row::new(
+            self.seed,
+            self.rounds,
+            self.duration,
+            self.termination,
+            seats,
+        )
+    }
+
+    /// Prints who played and how it ended, on one line.
+    pub fn print(&self, names: &[String]) {
+        let outcome = match self.winners[..] {
+            [winner] => format!("{} won", names[winner]),
+            _ => "draw".to_owned(),
+        };
+        let seated: Vec<&str> = self.seats.iter().map(|&i| names[i].as_str()).collect();
+        let adjudicated = match self.end_reason {
+            Some(EndReason::RoundLimit) => ", adjudicated by score",
+            _ => "",
+        };
+        print!(
+            "{}, {outcome} after {} rounds{adjudicated}",
+            seated.join(" vs "),
+            self.rounds
+        );
+        match &self.forfeit {
+            Some(f) => println!(", {} {}", names[f.agent], f.reason),
+            None => println!(),
+        }
+    }
+
+    fn place(&self, agent: usize) -> usize {
+        let seat = self.seats.iter().position(|&i| i == agent).unwrap();
+        self.places[seat]
+    }
+
+    /// 1 for a win, ½ for a draw and 0 for a loss against each other player,
+    /// averaged.
+    pub fn score(&self, agent: usize) -> f64 {
+        let place = self.place(agent);
+        let points: f64 = self
+            .places
+            .iter()
+            .map(|&other| match other.cmp(&place) {
+                std::cmp::Ordering::Greater => 1.0,
+                std::cmp::Ordering::Equal => 0.5,
+                std::cmp::Ordering::Less => 0.0,
+            })
+            .sum();
+        // Tied with itself.
+        (points - 0.5) / (self.places.len() - 1) as f64
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Forfeit {
+    pub agent: usize,
+    pub reason: String,
+}
+
+#[derive(Serialize, Default)]
+struct Standing {
+    name: String,
+    command: String,
+    games: usize,
+    wins: usize,
+    losses: usize,
+    draws: usize,
+    forfeits: usize,
+    average_rounds: f64,
+    average_score: f64,
+    average_place: f64,
+    /// Mirrored pairs where the agent scored more than the other, as much,
+    /// or less.
+    pair_wins: usize,
+    pair_draws: usize,
+    pair_losses: usize,
+    /// The score, Buchholz and byes of a Swiss tournament.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    points: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    buchholz: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    byes: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    seed: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cards: Option<Cards>,
+    standings: Vec<Standing>,
+    games: Vec<GameRecord>,
+    /// What `--config` came to, if there was one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    run_config: Option<serde_json::Value>,
+}
+
+/// Agents a job kept from its last game for the next, with
+/// `--reuse-agents`.
+#[derive(Default)]
+struct Kept {
+    /// The agents of that game, in order.
+    group: Vec<usize>,
+    /// Those that can play another game, with their index.
+    agents: Vec<(usize, Agent)>,
+}
+
+/// Plays game number `game` with `seats[i]` of `commands` in seat `i`,
+/// called by its name in `names`. Agents in `kept` are used again if they
+/// played the last game against the same opponents, and the others are
+/// stopped.
+fn play(
+    commands: &[String],
+    names: &[String],
+    seats: &[usize],
+    seed: u64,
+    settings: &Settings,
+    game: usize,
+    kept: &mut Kept,
+) -> (GameRecord, Option<Replay>) {
+    let started = Instant::now();
+    let mut group = seats.to_vec();
+    group.sort_unstable();
+    if kept.group != group {
+        kept.agents.clear();
+    }
+    let mut agents = vec![];
+    let mut reused = vec![];
+    for &i in seats {
+        if let Some(at) = kept.agents.iter().position(|(j, _)| *j == i) {
+            agents.push(kept.agents.swap_remove(at).1);
+            reused.push(true);
+            continue;
+        }
+        let log = AgentLog::Labelled(format!("{} in game {}", names[i], game + 1));
+        let name = names[i].clone();
+        let messages = settings.messages.get(i).copied().unwrap_or(false);
+        let time_budget = settings.time_budget;
+        let spawned = Agent::start(&commands[i], name, false, messages, time_budget, log);
+        match spawned {
+            Ok(agent) => {
+                agents.push(agent);
+                reused.push(false);
+            }
+            Err(e) => {
+                // Lost before the first move.
+                drop(agents);
+                let record = GameRecord {
+                    seats: seats.to_vec(),
+                    seed,
+                    winners: seats.iter().copied().filter(|&j| j != i).collect(),
+                    rounds: 0,
+                    scores: vec![0; seats.len()],
+                    places: seats
+                        .iter()
+                        .map(|&j| if j == i { seats.len() } else { 1 })
+                        .collect(),
+                    forfeit: Some(Forfeit {
+                        agent: i,
+                        reason: format!("failed to start: {e}"),
+                    }),
+                    pair: None,
+                    end_reason: None,
+                    termination: Termination::Crash,
+                    duration: started.elapsed(),
+                };
+                return (record, None);
+            }
+        }
+    }
+    let seated: Vec<&str> = seats.iter().map(|&i| names[i].as_str()).collect();
+    let mut state = State::setup(
+        &seated,
+        settings.config.clone(),
+        &mut StdRng::seed_from_u64(seed),
+    );
+    for (player_index, agent) in agents.iter_mut().enumerate() {
+        let config = state.config.clone();
+        agent.notify(match reused[player_index] {
+            true => RunnerMessage::NewGame {
+                player_index,
+                config,
+            },
+            false => RunnerMessage::Init {
+                protocol_version: PROTOCOL_VERSION,
+                player_index,
+                config,
+                new_games: settings.reuse_agents,
+            },
+        });
+    }
+    let mut replay = Replay::new(state.clone());
+    replay.seed = Some(seed);
+    replay.agents = seats.iter().map(|&i| commands[i].clone()).collect();
+    replay.run_config = run_config::resolved();
+    let mut forfeit = None;
+    let mut termination = Termination::Normal;
+    // Agents that forfeited may still be thinking, or gone.
+    let mut forfeited = vec![false; agents.len()];
+    while !state.is_finished() {
+        let turn = state.turn;
+        let agent = &mut agents[turn];
+        let (reply, elapsed) = request_move(
+            agent,
+            &state,
+            settings.move_timeout,
+            settings.increment,
+            settings.invalid_retries,
+            |action, e| {
+                replay.invalid.push(InvalidAttempt {
+                    ply: replay.actions.len(),
+                    player: turn,
+                    action: action.clone(),
+                    error: e.clone(),
+                })
+            },
+        );
+        let reason = match reply {
+            Ok(action) => match state.run(action.clone()) {
+                Ok(_) => {
+                    broadcast_played(&mut agents, turn, &action);
+                    replay.push(turn, action);
+                    replay.think_ms.push(elapsed.as_millis() as u64);
+                    continue;
+                }
+                Err(e) => {
+                    termination.add(Termination::Forfeit);
+                    let reason = format!("did invalid action {action}: {e}");
+                    replay.invalid.push(InvalidAttempt {
+                        ply: replay.actions.len(),
+                        player: turn,
+                        action,
+                        error: e,
+                    });
+                    reason
+                }
+            },
+            Err(e) => {
+                termination.add(e.termination());
+                e.to_string()
+            }
+        };
+        forfeited[turn] = true;
+        replay.forfeits.push(game_def::Forfeit {
+            player: turn,
+            reason: reason.clone(),
+        });
+        forfeit.get_or_insert(Forfeit {
+            agent: seats[turn],
+            reason,
+        });
+        state.run(Action::Resign).unwrap();
+        broadcast_played(&mut agents, turn, &Action::Resign);
+        replay.push(turn, Action::Resign);
+        replay.think_ms.push(elapsed.as_millis() as u64);
+    }
+    replay.result = state.result();
+    replay.end_reason = state.end_reason();
+    for agent in &mut agents {
+        agent.notify(RunnerMessage::GameOver {
+            result: state.result(),
+        });
+    }
+    kept.group = group;
+    for ((&i, agent), forfeited) in seats.iter().zip(agents).zip(forfeited) {
+        if settings.reuse_agents && !forfeited {
+            // The others are stopped as they are dropped.
+            if let Some(agent) = agent.next_game(settings.time_budget) {
+                kept.agents.push((i, agent));
+            }
+        }
+    }
+    let winners = match state.result() {
+        GameResult::Won(seat) => vec![seats[seat]],
+        GameResult::Draw(drawn) => drawn.into_iter().map(|seat| seats[seat]).collect(),
+        GameResult::Ongoing => unreachable!("the game is finished"),
+    };
+    let record = GameRecord {
+        seats: seats.to_vec(),
+        seed,
+        winners,
+        rounds: state.round,
+        scores: state.players.iter().map(|p| p.score).collect(),
+        places: state.placements(),
+        forfeit,
+        pair: None,
+        end_reason: state.end_reason(),
+        termination,
+        duration: started.elapsed(),
+    };
+    (record, Some(replay))
+}
+
+/// Plays `games`, each with its seats and seed, `jobs` at a time, and passes
+/// every game with its number to `report` in order, however they finish.
+/// The first `done` were played already. Stops starting games once `report`
+/// returns false.
+pub fn play_all(
+    commands: &[String],
+    names: &[String],
+    games: &[(Vec<usize>, u64)],
+    done: usize,
+    settings: &Settings,
+    jobs: usize,
+    mut report: impl FnMut(usize, GameRecord, Option<Replay>) -> bool,
+) {
+    let next = AtomicUsize::new(done);
+    let stop = AtomicBool::new(false);
+    let (sender, results) = channel();
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            let sender = sender.clone();
+            let (next, stop) = (&next, &stop);
+            scope.spawn(move || {
+                let mut kept = Kept::default();
+                loop {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let n = next.fetch_add(1, Ordering::Relaxed);
+                    let Some((seats, seed)) = games.get(n) else {
+                        break;
+                    };
+                    let result = play(commands, names, seats, *seed, settings, n, &mut kept);
+                    if sender.send((n, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+        // Games that finish early wait for the ones before them.
+        let mut finished = HashMap::new();
+        let mut reported = done;
+        for (n, result) in results {
+            finished.insert(n, result);
+            while let Some((record, replay)) = finished.remove(&reported) {
+                if !report(reported, record, replay) {
+                    // Games under way are finished and thrown away.
+                    stop.store(true, Ordering::Relaxed);
+                    return;
+                }
+                reported += 1;
+            }
+        }
+    });
+}
+
+fn standings(commands: &[String], names: &[String], checkpoint: &Checkpoint) -> Vec<Standing> {
+    let games = &checkpoint.played;
+    let mut standings: Vec<Standing> = commands
+        .iter()
+        .zip(names)
+        .map(|(command, name)| Standing {
+            name: name.clone(),
+            command: command.clone(),
+            ..Default::default()
+        })
+        .collect();
+    for game in games {
+        for (seat, &i) in game.seats.iter().enumerate() {
+            let s = &mut standings[i];
+            s.games += 1;
+            let first = game.places.iter().filter(|&&p| p == 1).count();
+            match game.places[seat] {
+                1 if first == 1 => s.wins += 1,
+                1 => s.draws += 1,
+                _ => s.losses += 1,
+            }
+            s.forfeits += game.forfeit.as_ref().is_some_and(|f| f.agent == i) as usize;
+            s.average_rounds += game.rounds as f64;
+            s.average_score += game.scores[seat] as f64;
+            s.average_place += game.places[seat] as f64;
+        }
+    }
+    // The two games of a pair follow each other.
+    for games in games.chunk_by(|a, b| a.pair.is_some() && a.pair == b.pair) {
+        let [first, second] = games else {
+            continue;
+        };
+        let [a, b] = [first.seats[0], first.seats[1]];
+        let score = first.score(a) + second.score(a);
+        let pair_score = first.score(b) + second.score(b);
+        for (i, score, other) in [(a, score, pair_score), (b, pair_score, score)] {
+            let s = &mut standings[i];
+            match score.total_cmp(&other) {
+                std::cmp::Ordering::Greater => s.pair_wins += 1,
+                std::cmp::Ordering::Equal => s.pair_draws += 1,
+                std::cmp::Ordering::Less => s.pair_losses += 1,
+            }
+        }
+    }
+    for s in &mut standings {
+        if s.games > 0 {
+            s.average_rounds /= s.games as f64;
+            s.average_score /= s.games as f64;
+            s.average_place /= s.games as f64;
+        }
+    }
+    if let Some(swiss) = &checkpoint.swiss {
+        let entrants = swiss_entrants(commands.len(), swiss, games);
+        for (i, s) in standings.iter_mut().enumerate() {
+            s.points = Some(entrants[i].score);
+            s.buchholz = Some(swiss::buchholz(&entrants, i));
+            s.byes = Some(entrants[i].byes);
+        }
+    }
+    standings
+}
+
+/// Ranks the agents of a Swiss tournament by score, then by Buchholz.
+fn print_table(standings: &[Standing], mirrored: bool, players: usize, swiss: bool) {
+    let mut standings: Vec<_> = standings.iter().collect();
+    if swiss {
+        standings.sort_by(|a, b| {
+            let points = b.points.unwrap_or(0.0).total_cmp(&a.points.unwrap_or(0.0));
+            points.then(
+                b.buchholz
+                    .unwrap_or(0.0)
+                    .total_cmp(&a.buchholz.unwrap_or(0.0)),
+            )
+        });
+    }
+    let width = standings
+        .iter()
+        .map(|s| s.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Agent".len());
+    print!(
+        "{:<width$}  {:>5}  {:>4}  {:>6}  {:>5}  {:>8}  {:>6}  {:>9}",
+        "Agent", "Games", "Wins", "Losses", "Draws", "Forfeits", "Rounds", "Score"
+    );
+    if players > 2 {
+        print!("  {:>5}", "Place");
+    }
+    if mirrored {
+        print!("  {:>11}", "Pairs W-D-L");
+    }
+    if swiss {
+        print!("  {:>4}  {:>6}  {:>8}", "Byes", "Points", "Buchholz");
+    }
+    println!();
+    for s in standings {
+        print!(
+            "{:<width$}  {:>5}  {:>4}  {:>6}  {:>5}  {:>8}  {:>6.1}  {:>9.1}",
+            s.name,
+            s.games,
+            s.wins,
+            s.losses,
+            s.draws,
+            s.forfeits,
+            s.average_rounds,
+            s.average_score
+        );
+        if players > 2 {
+            print!("  {:>5.2}", s.average_place);
+        }
+        if mirrored {
+            let pairs = format!("{}-{}-{}", s.pair_wins, s.pair_draws, s.pair_losses);
+            print!("  {pairs:>11}");
+        }
+        if let (Some(byes), Some(points), Some(buchholz)) = (s.byes, s.points, s.buchholz) {
+            print!("  {byes:>4}  {points:>6.2}  {buchholz:>8.2}");
+        }
+        println!();
+    }
+}
+
+/// Adds `games` games of `group` to `schedule`, moving everyone a seat along
+/// every game, or playing each twice with the seats swapped if `mirrored`.
+fn schedule_group(
+    schedule: &mut Vec<(Vec<usize>, u64, Option<usize>)>,
+    group: &[usize],
+    games: usize,
+    mirrored: bool,
+    rng: &mut StdRng,
+) {
+    for game in 0..games {
+        let seed = rng.gen();
+        let mut seats = group.to_vec();
+        if mirrored {
+            let pair = Some(schedule.len() / 2);
+            schedule.push((seats.clone(), seed, pair));
+            seats.reverse();
+            schedule.push((seats, seed, pair));
+        } else {
+            seats.rotate_left(game % group.len());
+            schedule.push((seats, seed, None));
+        }
+    }
+}
+
+/// How each agent of a Swiss tournament stands after the games `played`.
+fn swiss_entrants(agents: usize, swiss: &Swiss, played: &[GameRecord]) -> Vec<Entrant> {
+    let mut entrants = vec![Entrant::default(); agents];
+    // The games of a pair follow each other.
+    for games in played.chunks(swiss.games) {
+        let [a, b] = [games[0].seats[0], games[0].seats[1]];
+        for (i, other) in [(a, b), (b, a)] {
+            let points: f64 = games.iter().map(|game| game.score(i)).sum();
+            entrants[i].score += points / swiss.games as f64;
+            entrants[i].opponents.push(other);
+        }
+        for game in games {
+            entrants[game.seats[0]].first_balance += 1;
+            entrants[game.seats[1]].first_balance -= 1;
+        }
+    }
+    for &i in swiss.byes.iter().flatten() {
+        entrants[i].score += 1.0;
+        entrants[i].byes += 1;
+    }
+    entrants
+}
+
+/// Pairs the next round of a Swiss tournament and adds its games to the
+/// schedule, each round with seeds of its own so a resumed one pairs alike.
+fn pair_round(checkpoint: &mut Checkpoint, mirrored: bool) {
+    let Some(swiss) = &mut checkpoint.swiss else {
+        return;
+    };
+    let entrants = swiss_entrants(checkpoint.agents.len(), swiss, &checkpoint.played);
+    let round = swiss::pair(&entrants);
+    let mut rng = StdRng::seed_from_u64(checkpoint.seed.wrapping_add(swiss.byes.len() as u64));
+    let games = swiss.games / (1 + mirrored as usize);
+    for (first, second) in round.pairs {
+        schedule_group(
+            &mut checkpoint.schedule,
+            &[first, second],
+            games,
+            mirrored,
+            &mut rng,
+        );
+    }
+    swiss.byes.push(round.bye);
+}
+
+/// Every way to pick `k` of `0..n`, in order.
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    if k == 0 {
+        return vec![vec![]];
+    }
+    (k - 1..n)
+        .flat_map(|last| {
+            combinations(last, k - 1).into_iter().map(move |mut c| {
+                c.push(last);
+                c
+            })
+        })
+        .collect()
+}
+
+pub fn main(mut argv: impl Iterator<Item = String>) {
+    let mut games = 2;
+    let mut players = 2;
+    let mut jobs = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut seed = rand::random();
+    let mut json = None;
+    let mut stats_file = None;
+    let mut ratings = None;
+    let mut record_dir = None;
+    let mut mirrored = false;
+    let mut swiss = false;
+    let mut rounds = None;
+    let mut checkpoint_path = None;
+    let mut resume = false;
+    let mut settings = Settings::default();
+    let mut commands = vec![];
+    let mut names = vec![];
+    let mut name = None;
+    let mut messages = false;
+    while let Some(arg) = argv.next() {
+        match settings.flag(&arg, &mut argv) {
+            Some(true) => continue,
+            Some(false) => (),
+            None => return,
+        }
+        if arg == "--games" {
+            let Some(x) = number_arg(&mut argv, &arg) else {
+                return;
+            };
+            games = x;
+        } else if arg == "--players" {
+            match number_arg(&mut argv, &arg) {
+                Some(n @ 2..=4) => players = n,
+                Some(n) => {
+                    println!("--players must be 2, 3 or 4, not {n}");
+                    return;
+                }
+                None => return,
+            }
+        } else if arg == "--jobs" {
+            match number_arg(&mut argv, &arg) {
+                Some(0) => {
+                    println!("--jobs needs at least 1");
+                    return;
+                }
+                Some(n) => jobs = n,
+                None => return,
+            }
+        } else if arg == "--seed" {
+            let Some(x) = number_arg(&mut argv, &arg) else {
+                return;
+            };
+            seed = x;
+        } else if arg == "--mirrored" {
+            mirrored = true;
+        } else if arg == "--format" {
+            match argv.next().as_deref() {
+                Some("round-robin") => swiss = false,
+                Some("swiss") => swiss = true,
+                _ => {
+                    println!("--format needs round-robin or swiss");
+                    return;
+                }
+            }
+        } else if arg == "--rounds" {
+            match number_arg(&mut argv, &arg) {
+                Some(0) => {
+                    println!("--rounds needs at least 1");
+                    return;
+                }
+                Some(n) => rounds = Some(n),
+                None => return,
+            }
+        } else if arg == "--record" {
+            let Some(dir) = argv.next() else {
+                println!("--record needs a directory");
+                return;
+            };
+            if let Err(e) = std::fs::create_dir_all(&dir) {
+                println!("Failed to create {dir}: {e}");
+                return;
+            }
+            record_dir = Some(dir);
+        } else if arg == "--checkpoint" || arg == "--resume" {
+            let Some(path) = argv.next() else {
+                println!("{arg} needs a path");
+                return;
+            };
+            resume = arg == "--resume";
+            checkpoint_path = Some(path);
+        } else if arg == "--ratings" {
+            let Some(path) = argv.next() else {
+                println!("--ratings needs a path");
+                return;
+            };
+            ratings = Some(path);
+        } else if arg == "--json" {
+            let Some(path) = argv.next() else {
+                println!("--json needs a path");
+                return;
+            };
+            json = Some(path);
+        } else if arg == "--stats-file" {
+            let Some(path) = argv.next() else {
+                println!("--stats-file needs a path");
+                return;
+            };
+            stats_file = Some(path);
+        } else if arg == "--protocol" {
+            // Applies to the next agent only.
+            match argv.next().as_deref() {
+                Some("states") => (),
+                Some("messages") => messages = true,
+                _ => {
+                    println!("--protocol needs states or messages");
+                    return;
+                }
+            }
+        } else if arg == "--name" {
+            // Applies to the next agent only.
+            let Some(x) = argv.next() else {
+                println!("--name needs a name");
+                return;
+            };
+            name = Some(x);
+        } else {
+            names.push(name.take().unwrap_or_else(|| arg.clone()));
+            settings.messages.push(std::mem::take(&mut messages));
+            commands.push(arg);
+        }
+    }
+    let names = unique_names(names);
+    let agents: Vec<_> = names
+        .iter()
+        .cloned()
+        .zip(commands.iter().cloned())
+        .collect();
+    let mut checkpoint = match &checkpoint_path {
+        Some(path) if resume => {
+            match Checkpoint::load(path).and_then(|c| c.check(&agents).map(|()| c)) {
+                Ok(checkpoint) => checkpoint,
+                Err(e) => {
+                    println!("{e:#}");
+                    return;
+                }
+            }
+        }
+        _ => {
+            if commands.len() < players {
+                println!("A tournament of {players} players needs at least {players} agents");
+                return;
+            }
+            if mirrored && players > 2 {
+                println!("--mirrored only works for 2 players");
+                return;
+            }
+            if swiss && players > 2 {
+                println!("--format swiss only works for 2 players");
+                return;
+            }
+            let mut schedule = vec![];
+            if !swiss {
+                let mut rng = StdRng::seed_from_u64(seed);
+                for group in combinations(commands.len(), players) {
+                    schedule_group(&mut schedule, &group, games, mirrored, &mut rng);
+                }
+            }
+            let mut checkpoint = Checkpoint {
+                version: checkpoint::VERSION,
+                seed,
+                agents,
+                schedule,
+                played: vec![],
+                swiss: swiss.then(|| Swiss {
+                    // Enough that at most one agent can win every round.
+                    rounds: rounds
+                        .unwrap_or((usize::BITS - (commands.len() - 1).leading_zeros()) as usize),
+                    games: games * (1 + mirrored as usize),
+                    byes: vec![],
+                }),
+            };
+            pair_round(&mut checkpoint, mirrored);
+            checkpoint
+        }
+    };
+    let seed = checkpoint.seed;
+    let done = checkpoint.played.len();
+    let total = checkpoint.schedule.len();
+    let players = checkpoint
+        .schedule
+        .first()
+        .map_or(players, |(seats, ..)| seats.len());
+    let mirrored = checkpoint.schedule.iter().any(|(.., pair)| pair.is_some());
+    // The games of each round of a Swiss tournament.
+    let round_games = checkpoint
+        .swiss
+        .as_ref()
+        .map(|swiss| checkpoint.agents.len() / 2 * swiss.games);
+    let jobs = jobs
+        .min((MAX_AGENT_PROCESSES / players).max(1))
+        .min(round_games.unwrap_or(total - done).max(1));
+    match (&checkpoint.swiss, round_games) {
+        (Some(swiss), Some(round_games)) => println!(
+            "Tournament seed {seed}, {} rounds of {round_games} games, {jobs} at a time",
+            swiss.rounds
+        ),
+        _ => println!("Tournament seed {seed}, {total} games, {jobs} at a time"),
+    }
+    if let Some(path) = &checkpoint_path {
+        if resume {
+            println!("Resuming {path} after game {done}");
+        } else if let Err(e) = checkpoint.save(path) {
+            println!("{e:#}");
+            return;
+        }
+    }
+    for (n, record) in checkpoint.played.iter().enumerate() {
+        print!("Game {}: ", n + 1);
+        record.print(&names);
+    }
+    loop {
+        let done = checkpoint.played.len();
+        if let (Some(swiss), Some(round_games)) = (&checkpoint.swiss, round_games) {
+            let round = done / round_games;
+            if let Some(bye) = swiss.byes.get(round) {
+                print!("Round {} of {}", round + 1, swiss.rounds);
+                match bye {
+                    Some(i) => println!(", {} sits out", names[*i]),
+                    None => println!(),
+                }
+            }
+        }
+        let schedule: Vec<_> = checkpoint
+            .schedule
+            .iter()
+            .map(|(seats, seed, _)| (seats.clone(), *seed))
+            .collect();
+        play_all(
+            &commands,
+            &names,
+            &schedule,
+            done,
+            &settings,
+            jobs,
+            |n, mut record, replay| {
+                let (seats, _, pair) = &checkpoint.schedule[n];
+                let pair = *pair;
+                record.pair = pair;
+                if let (Some(dir), Some(replay)) = (&record_dir, replay) {
+                    let path = format!("{dir}/game-{}.json", n + 1);
+                    if let Err(e) = replay.save(&path) {
+                        println!("{e:#}");
+                    }
+                }
+                if let Some(path) = &stats_file {
+                    if let Err(e) = stats::append(path, &record.stats_row(&commands, &names)) {
+                        println!("{e:#}");
+                    }
+                }
+                print!("Game {}: ", n + 1);
+                record.print(&names);
+                if jobs > 1 && record.rounds == 0 && record.forfeit.is_some() {
+                    println!("   With {jobs} games at once, try fewer --jobs");
+                }
+                if let Some(path) = &ratings {
+                    let rate = |r: &mut elo::Ratings| {
+                        for (x, &a) in seats.iter().enumerate() {
+                            for (y, &b) in seats.iter().enumerate().skip(x + 1) {
+                                let score = match record.places[x].cmp(&record.places[y]) {
+                                    std::cmp::Ordering::Less => 1.0,
+                                    std::cmp::Ordering::Equal => 0.5,
+                                    std::cmp::Ordering::Greater => 0.0,
+                                };
+                                elo::update(r, &names[a], &names[b], score);
+                            }
+                        }
+                    };
+                    if let Err(e) = elo::modify(path, rate) {
+                        println!("{e:#}");
+                    }
+                }
+                let records = &mut checkpoint.played;
+                records.push(record);
+                if let (Some(pair), [.., first, second]) = (pair, &records[..]) {
+                    if first.pair == Some(pair) && second.pair == Some(pair) {
+                        let [a, b] = [first.seats[0], first.seats[1]];
+                        println!(
+                            "Pair {}: {} {} - {} {}",
+                            pair + 1,
+                            names[a],
+                            first.score(a) + second.score(a),
+                            first.score(b) + second.score(b),
+                            names[b]
+                        );
+                    }
+                }
+                if let Some(path) = &checkpoint_path {
+                    if let Err(e) = checkpoint.save(path) {
+                        println!("{e:#}");
+                    }
+                }
+                true
+            },
+        );
+        match &checkpoint.swiss {
+            Some(swiss) if swiss.byes.len() < swiss.rounds => pair_round(&mut checkpoint, mirrored),
+            _ => break,
+        }
+        if let Some(path) = &checkpoint_path {
+            if let Err(e) = checkpoint.save(path) {
+                println!("{e:#}");
+            }
+        }
+    }
+    let summary = Summary {
+        seed,
+        cards: settings.cards(),
+        standings: standings(&commands, &names, &checkpoint),
+        games: checkpoint.played,
+        run_config: run_config::resolved(),
+    };
+    println!();
+    print_table(
+        &summary.standings,
+        mirrored,
+        players,
+        checkpoint.swiss.is_some(),
+    );
+    if let Some(path) = json {
+        let json = serde_json::to_string_pretty(&summary).unwrap();
+        if let Err(e) = std::fs::write(&path, json + "\n") {
+            println!("Failed to write {path}: {e}");
+        }
+    }
+}
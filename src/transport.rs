@@ -0,0 +1,266 @@
+//! How the runner reaches an AI: a process it starts, over the process's
+//! stdin and stdout, an agent running elsewhere over TCP, or a built-in agent
+//! on a thread of the runner, see [`crate::builtin`]. The protocol on top is
+//! the same either way.
+//!
+//! A TCP agent is given as `tcp:<addr>`, where the runner listens for it, or
+//! `tcp-connect:<addr>`, where the runner connects to it. Its first message
+//! is a [`Hello`] with its name; after that a dropped connection is like an
+//! agent that crashed. WebSocket agents are in [`crate::websocket`].
+
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    path::PathBuf,
+    process::{Child, ChildStderr},
+    sync::{Arc, Mutex, OnceLock},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use game_def::{transcript::Transcript, Hello};
+use serde::Serialize;
+
+use crate::{limits::Limits, wait_finished};
+
+/// How long an agent has to exit by itself once its input is closed.
+const SHUTDOWN_GRACE: Duration = Duration::from_millis(500);
+/// How long we wait for the rest of the stderr of a stopped agent.
+const AGENT_LOG_GRACE: Duration = Duration::from_millis(500);
+/// How long a TCP agent has to say hello once connected.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long an agent that stopped talking has to exit before we take it for
+/// hung instead.
+const EXIT_GRACE: Duration = Duration::from_millis(200);
+/// How many of the last lines of its stderr are kept of each agent.
+const STDERR_TAIL: usize = 10;
+
+/// The last lines an agent wrote to stderr, newest last.
+pub type StderrTail = Arc<Mutex<VecDeque<String>>>;
+
+/// How an agent that stopped talking ended, for the summary of the game.
+#[derive(Clone, Serialize)]
+pub struct Exit {
+    /// Like `status 1` or `signal: 9 (SIGKILL)`, `None` if it is still running.
+    pub status: Option<String>,
+    /// The last lines of its stderr.
+    pub stderr: Vec<String>,
+    /// Which of the [`Limits`] it likely ran into.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<String>,
+}
+
+/// What is left of the connection to an agent once the runner has its
+/// input and output.
+pub trait Transport {
+    /// Makes sure the agent is gone, after its input is closed.
+    fn close(&mut self);
+
+    /// How the agent ended once it closed its output, if there is more to
+    /// know than that.
+    fn exit(&mut self) -> Option<Exit> {
+        None
+    }
+}
+
+/// An agent the runner started.
+pub struct Process {
+    pub child: Child,
+    /// Drains the agent's stderr, see [`AgentLog`].
+    pub log: JoinHandle<()>,
+    pub tail: StderrTail,
+    /// What it was started with, to tell whether they killed it.
+    pub limits: Limits,
+}
+
+impl Transport for Process {
+    /// Kills the agent if it is still running after [`SHUTDOWN_GRACE`].
+    /// Either way it is waited for, so no zombie is left.
+    fn close(&mut self) {
+        let deadline = Instant::now() + SHUTDOWN_GRACE;
+        while self.child.try_wait().is_ok_and(|status| status.is_none())
+            && Instant::now() < deadline
+        {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        _ = self.child.kill();
+        _ = self.child.wait();
+        // Prints what is left, unless something the agent started holds its
+        // stderr open.
+        wait_finished(&self.log, AGENT_LOG_GRACE);
+    }
+
+    /// Waits [`EXIT_GRACE`] for the agent to exit, which reaps it, and then
+    /// for the rest of its stderr.
+    fn exit(&mut self) -> Option<Exit> {
+        let deadline = Instant::now() + EXIT_GRACE;
+        let status = loop {
+            match self.child.try_wait() {
+                Ok(None) if Instant::now() < deadline => {
+                    std::thread::sleep(Duration::from_millis(10))
+                }
+                Ok(status) => break status,
+                Err(_) => break None,
+            }
+        };
+        if status.is_some() {
+            wait_finished(&self.log, AGENT_LOG_GRACE);
+        }
+        Some(Exit {
+            limit: status.and_then(|status| self.limits.explain(status)),
+            status: status.map(|status| match status.code() {
+                Some(code) => format!("status {code}"),
+                None => status.to_string(),
+            }),
+            stderr: self.tail.lock().unwrap().iter().cloned().collect(),
+        })
+    }
+}
+
+/// A built-in agent, on a thread of its own.
+pub struct AgentThread(pub JoinHandle<()>);
+
+impl Transport for AgentThread {
+    /// Gives the agent [`SHUTDOWN_GRACE`] to see its input closed. A thread
+    /// can't be killed, so one still searching is left to finish by itself.
+    fn close(&mut self) {
+        wait_finished(&self.0, SHUTDOWN_GRACE);
+    }
+}
+
+impl Transport for TcpStream {
+    fn close(&mut self) {
+        _ = self.shutdown(Shutdown::Both);
+    }
+}
+
+/// Where the stderr of an AI goes. It is read on a separate thread either
+/// way, so an agent that prints a lot never blocks while we wait for its move,
+/// and the last [`STDERR_TAIL`] lines are kept in case it crashes.
+pub enum AgentLog {
+    /// To our stderr, each line after `[label]` to tell agents apart.
+    Labelled(String),
+    /// Into a file of its own, with `--agent-log-dir`.
+    File(PathBuf),
+}
+
+impl AgentLog {
+    pub fn spawn(self, stderr: ChildStderr) -> std::io::Result<(JoinHandle<()>, StderrTail)> {
+        let (label, mut file) = match self {
+            AgentLog::Labelled(label) => (label, None),
+            AgentLog::File(path) => (String::new(), Some(std::fs::File::create(path)?)),
+        };
+        let tail = StderrTail::default();
+        let kept = tail.clone();
+        let thread = std::thread::spawn(move || {
+            let mut reader = BufReader::new(stderr);
+            let mut line = vec![];
+            while reader.read_until(b'\n', &mut line).is_ok_and(|n| n > 0) {
+                let text = String::from_utf8_lossy(&line);
+                let text = text.trim_end_matches(['\r', '\n']);
+                match &mut file {
+                    Some(file) => _ = file.write_all(&line),
+                    None if crate::tui::active() => crate::tui::say(format!("[{label}] {text}")),
+                    None => eprintln!("[{label}] {text}"),
+                }
+                let mut kept = kept.lock().unwrap();
+                if kept.len() == STDERR_TAIL {
+                    kept.pop_front();
+                }
+                kept.push_back(text.to_owned());
+                drop(kept);
+                line.clear();
+            }
+        });
+        Ok((thread, tail))
+    }
+}
+
+/// Where an agent's messages are written down with `--transcript-dir`,
+/// shared with the thread reading its replies. Empty without.
+pub type TranscriptSlot = Arc<OnceLock<Transcript>>;
+
+/// One JSON line or MessagePack frame from an agent, `None` once it closed
+/// its output.
+pub fn read_reply<T: serde::de::DeserializeOwned>(
+    reader: &mut impl BufRead,
+    msgpack: bool,
+) -> std::io::Result<Option<anyhow::Result<T>>> {
+    Ok(read_wire(reader, msgpack)?.map(|wire| decode_wire(&wire, msgpack)))
+}
+
+/// [`read_reply`] before it is decoded, as it went over the wire: the line
+/// with its newline, or the frame with its length.
+pub fn read_wire(reader: &mut impl BufRead, msgpack: bool) -> std::io::Result<Option<Vec<u8>>> {
+    if msgpack {
+        let Some(frame) = game_def::msgpack::read_frame(reader)? else {
+            return Ok(None);
+        };
+        Ok(Some(
+            [&(frame.len() as u32).to_be_bytes()[..], &frame].concat(),
+        ))
+    } else {
+        let mut line = vec![];
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line))
+    }
+}
+
+/// Decodes what [`read_wire`] read.
+pub fn decode_wire<T: serde::de::DeserializeOwned>(
+    wire: &[u8],
+    msgpack: bool,
+) -> anyhow::Result<T> {
+    match msgpack {
+        true => game_def::decode_message(&wire[4..], true),
+        false => game_def::decode_message(wire, false),
+    }
+}
+
+/// Whether `agent` is a TCP agent, and where to find it.
+pub enum TcpAddress<'a> {
+    Listen(&'a str),
+    Connect(&'a str),
+}
+
+impl<'a> TcpAddress<'a> {
+    pub fn parse(agent: &'a str) -> Option<Self> {
+        if let Some(address) = agent.strip_prefix("tcp:") {
+            Some(TcpAddress::Listen(address))
+        } else {
+            agent.strip_prefix("tcp-connect:").map(TcpAddress::Connect)
+        }
+    }
+
+    /// Waits for the agent to connect, or connects to it, and reads its
+    /// [`Hello`].
+    pub fn connect(
+        &self,
+        msgpack: bool,
+    ) -> anyhow::Result<(TcpStream, BufReader<TcpStream>, Hello)> {
+        let stream = match self {
+            TcpAddress::Listen(address) => {
+                let listener = TcpListener::bind(address)
+                    .with_context(|| format!("Failed to listen on {address}"))?;
+                say!("Waiting for an agent on {address}");
+                listener.accept()?.0
+            }
+            TcpAddress::Connect(address) => TcpStream::connect(address)
+                .with_context(|| format!("Failed to connect to {address}"))?,
+        };
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let hello = read_reply::<Hello>(&mut reader, msgpack)
+            .context("The agent did not say hello")?
+            .context("The agent left before saying hello")?
+            .context("The agent did not say hello")?;
+        // The move timeouts take over from here.
+        stream.set_read_timeout(None)?;
+        Ok((stream, reader, hello))
+    }
+}
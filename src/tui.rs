@@ -0,0 +1,359 @@
+//! `--tui`: the game on one screen instead of boards printed one after
+//! another. The board is on top with the players side by side under it,
+//! then what the runner says, and a status line with the last move and the
+//! clocks. The arrows, Page Up and Page Down, Home and End scroll what was
+//! said, and `a` brackets the cards the player to move can afford. A human
+//! types on the bottom line, where scrolling is left to the line editor.
+//!
+//! Without a terminal on stdout, or with `--output json`, the game is
+//! printed as usual. The terminal is restored when the game ends, on a
+//! panic and on a second Ctrl+C, and what was said is printed after it.
+//! Agents' stderr is shown with what the runner says, unless it goes to
+//! `--agent-log-dir`.
+
+use std::{
+    io::{IsTerminal, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use game_def::{Card, State};
+
+/// How long the key thread waits for a key before checking whether a human
+/// is typing.
+const KEY_POLL: Duration = Duration::from_millis(100);
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static SCREEN: Mutex<Option<Screen>> = Mutex::new(None);
+/// Set while the line editor reads a human's input, and held by the key
+/// thread while it waits for keys, so keys go to one or the other.
+static TYPING: AtomicBool = AtomicBool::new(false);
+static KEYS: Mutex<()> = Mutex::new(());
+
+#[derive(Default)]
+struct Screen {
+    /// The board as it is shown and the blind reserves taken out of it.
+    board: Option<(State, Vec<usize>)>,
+    perspective: Option<usize>,
+    /// What the runner said.
+    lines: Vec<String>,
+    /// How many lines are scrolled back from the last.
+    scroll: usize,
+    affordable: bool,
+    status: String,
+}
+
+pub fn active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Takes over the terminal, or says why not and returns false. Ctrl+C sets
+/// `interrupted` like the signal does without the TUI.
+pub fn start(interrupted: Arc<AtomicBool>) -> bool {
+    // With `--output json`, stdout is for the summary.
+    if crate::JSON_OUTPUT.load(Ordering::Relaxed) || !std::io::stdout().is_terminal() {
+        say!("--tui needs a terminal, printing the game instead");
+        return false;
+    }
+    let mut out = std::io::stdout();
+    if let Err(e) = terminal::enable_raw_mode()
+        .and_then(|_| crossterm::execute!(out, EnterAlternateScreen, Hide))
+    {
+        restore();
+        say!("Failed to start the TUI, printing the game instead: {e}");
+        return false;
+    }
+    *SCREEN.lock().unwrap() = Some(Screen::default());
+    ACTIVE.store(true, Ordering::Relaxed);
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore();
+        previous(info)
+    }));
+    std::thread::spawn(move || read_keys(&interrupted));
+    redraw();
+    true
+}
+
+/// Gives the terminal back and prints what was said.
+pub fn stop() {
+    if !active() {
+        return;
+    }
+    restore();
+    if let Some(screen) = SCREEN.lock().unwrap().take() {
+        for line in screen.lines {
+            println!("{line}");
+        }
+    }
+}
+
+/// Leaves raw mode and the alternate screen. Doesn't lock [`SCREEN`], as a
+/// panic while drawing still holds it.
+fn restore() {
+    if ACTIVE.swap(false, Ordering::Relaxed) {
+        _ = crossterm::execute!(std::io::stdout(), Show, LeaveAlternateScreen);
+        _ = terminal::disable_raw_mode();
+    }
+}
+
+pub fn say(text: String) {
+    if let Some(screen) = SCREEN.lock().unwrap().as_mut() {
+        for line in text.lines() {
+            screen.lines.push(line.to_owned());
+            // Scrolled back, the same lines stay in view.
+            if screen.scroll > 0 {
+                screen.scroll += 1;
+            }
+        }
+    }
+    redraw();
+}
+
+/// Shows `board` as `perspective` sees it, with `hidden` blind reserves of
+/// each player taken out.
+pub fn show(board: State, perspective: Option<usize>, hidden: Vec<usize>) {
+    if let Some(screen) = SCREEN.lock().unwrap().as_mut() {
+        screen.board = Some((board, hidden));
+        screen.perspective = perspective;
+    }
+    redraw();
+}
+
+/// Takes the board off the screen, for `--hotseat` to pass the device.
+pub fn conceal() {
+    if let Some(screen) = SCREEN.lock().unwrap().as_mut() {
+        screen.board = None;
+    }
+    redraw();
+}
+
+/// The round, who moves, the last move and each player's clock.
+pub fn status(
+    state: &State,
+    last: Option<&(usize, game_def::Action)>,
+    clocks: &[Option<Duration>],
+) {
+    let mut status = format!(
+        "Round {}, {} to move",
+        state.round + 1,
+        state.players[state.turn].display_name
+    );
+    if let Some((player, action)) = last {
+        status += &format!(", {} did {action}", state.players[*player].display_name);
+    }
+    for (player, clock) in state.players.iter().zip(clocks) {
+        if let Some(left) = clock {
+            status += &format!(", {} {left:.1?}", player.display_name);
+        }
+    }
+    if let Some(screen) = SCREEN.lock().unwrap().as_mut() {
+        screen.status = status;
+    }
+    redraw();
+}
+
+/// Runs `read`, a read of the line editor, on the bottom line.
+pub fn read_line<T>(read: impl FnOnce() -> T) -> T {
+    if !active() {
+        return read();
+    }
+    TYPING.store(true, Ordering::Relaxed);
+    let keys = KEYS.lock().unwrap();
+    let (_, rows) = terminal::size().unwrap_or((80, 24));
+    let mut out = std::io::stdout();
+    _ = crossterm::execute!(
+        out,
+        MoveTo(0, rows - 1),
+        Clear(ClearType::CurrentLine),
+        Show
+    );
+    let line = read();
+    // The editor leaves raw mode when it is done.
+    _ = terminal::enable_raw_mode();
+    _ = crossterm::execute!(out, Hide);
+    drop(keys);
+    TYPING.store(false, Ordering::Relaxed);
+    redraw();
+    line
+}
+
+fn read_keys(interrupted: &AtomicBool) {
+    while active() {
+        let keys = KEYS.lock().unwrap();
+        if TYPING.load(Ordering::Relaxed) {
+            drop(keys);
+            std::thread::sleep(KEY_POLL);
+            continue;
+        }
+        let event = match crossterm::event::poll(KEY_POLL) {
+            Ok(true) => crossterm::event::read(),
+            Ok(false) => continue,
+            Err(e) => Err(e),
+        };
+        drop(keys);
+        match event {
+            Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => on_key(key, interrupted),
+            Ok(Event::Resize(..)) => redraw(),
+            Ok(_) => (),
+            Err(_) => return,
+        }
+    }
+}
+
+fn on_key(key: KeyEvent, interrupted: &AtomicBool) {
+    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        // Like a second Ctrl+C without the TUI.
+        if interrupted.swap(true, Ordering::Relaxed) {
+            restore();
+            std::process::exit(crate::EXIT_FORCED);
+        }
+        return;
+    }
+    let (_, rows) = terminal::size().unwrap_or((80, 24));
+    let page = rows as usize / 2;
+    if let Some(screen) = SCREEN.lock().unwrap().as_mut() {
+        let most = screen.lines.len();
+        match key.code {
+            KeyCode::Char('a') => screen.affordable = !screen.affordable,
+            KeyCode::Up => screen.scroll = (screen.scroll + 1).min(most),
+            KeyCode::Down => screen.scroll = screen.scroll.saturating_sub(1),
+            KeyCode::PageUp => screen.scroll = (screen.scroll + page).min(most),
+            KeyCode::PageDown => screen.scroll = screen.scroll.saturating_sub(page),
+            KeyCode::Home => screen.scroll = most,
+            KeyCode::End => screen.scroll = 0,
+            _ => return,
+        }
+    }
+    redraw();
+}
+
+/// Like `1p u 2w+3k`, in brackets when `affordable`.
+fn card(card: &Card, affordable: bool) -> String {
+    let color = card.color().map_or('-', |c| c.code());
+    let text = format!("{}p {color} {}", card.score(), card.cost());
+    match affordable {
+        true => format!("[{text}]"),
+        false => format!(" {text} "),
+    }
+}
+
+/// The board, then the players side by side in `width` columns.
+fn board_lines(screen: &Screen, width: usize) -> Vec<String> {
+    let Some((state, hidden)) = &screen.board else {
+        return vec![];
+    };
+    let buyer = screen.perspective.unwrap_or(state.turn);
+    let can_buy = |c: &Card| screen.affordable && state.players[buyer].can_purchase(c.cost());
+    let mut lines = vec![];
+    for (tier, deck) in &state.decks {
+        let cards: Vec<_> = deck
+            .iter()
+            .take(state.config.visible_cards)
+            .map(|c| card(c, can_buy(c)))
+            .collect();
+        lines.push(format!("Tier {tier} {}", cards.join(" ")));
+    }
+    let nobels: Vec<_> = state
+        .nobels
+        .iter()
+        .map(|n| format!("{}p {}", n.score, n.cost))
+        .collect();
+    lines.push(format!("Nobels  {}", nobels.join("   ")));
+    lines.push(format!("Bank    {}", state.coins));
+    lines.push(String::new());
+
+    let columns: Vec<Vec<String>> = state
+        .players
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let to_move = if i == state.turn { "> " } else { "" };
+            let resigned = if p.resigned { " (resigned)" } else { "" };
+            let mut column = vec![
+                format!("{to_move}{}{resigned}", p.display_name),
+                format!("Score {}", p.score),
+                match p.immortal.sum() {
+                    0 => "Cards none".to_owned(),
+                    _ => format!("Cards {}", p.immortal),
+                },
+                format!("Coins {}", p.mortal),
+            ];
+            for r in &p.reserved {
+                column.push(format!("Reserved{}", card(r, i == buyer && can_buy(r))));
+            }
+            if let Some(&n @ 1..) = hidden.get(i) {
+                column.push(format!("{n} reserved face down"));
+            }
+            column
+        })
+        .collect();
+    let column_width = width / columns.len().max(1);
+    let height = columns.iter().map(Vec::len).max().unwrap_or(0);
+    for row in 0..height {
+        let mut line = String::new();
+        for column in &columns {
+            let cell = column.get(row).map_or("", String::as_str);
+            let cell: String = cell.chars().take(column_width.saturating_sub(1)).collect();
+            line += &format!("{cell:column_width$}");
+        }
+        lines.push(line.trim_end().to_owned());
+    }
+    lines
+}
+
+fn redraw() {
+    if !active() || TYPING.load(Ordering::Relaxed) {
+        return;
+    }
+    let guard = SCREEN.lock().unwrap();
+    let Some(screen) = guard.as_ref() else {
+        return;
+    };
+    let (columns, rows) = terminal::size().unwrap_or((80, 24));
+    let (width, rows) = (columns as usize, rows as usize);
+    let mut top = board_lines(screen, width);
+    // The rest is for what was said, the status and the input line.
+    top.truncate(rows.saturating_sub(3));
+    let said = rows.saturating_sub(top.len() + 3);
+    let end = screen.lines.len() - screen.scroll.min(screen.lines.len());
+    let mut rule = "─".repeat(width);
+    if screen.scroll > 0 {
+        rule = format!("── {} lines below ", screen.scroll) + &rule;
+    }
+    top.push(rule);
+    top.extend(screen.lines[end.saturating_sub(said)..end].iter().cloned());
+    top.resize(rows.saturating_sub(2), String::new());
+
+    let mut out = std::io::stdout().lock();
+    for (row, line) in top.iter().enumerate() {
+        let line: String = line.chars().take(width).collect();
+        _ = queue!(
+            out,
+            MoveTo(0, row as u16),
+            Print(line),
+            Clear(ClearType::UntilNewLine)
+        );
+    }
+    let status: String = screen.status.chars().take(width).collect();
+    _ = queue!(
+        out,
+        MoveTo(0, rows.saturating_sub(2) as u16),
+        SetAttribute(Attribute::Reverse),
+        Print(format!("{status:width$}")),
+        SetAttribute(Attribute::Reset),
+        MoveTo(0, rows.saturating_sub(1) as u16),
+        Clear(ClearType::CurrentLine),
+    );
+    _ = out.flush();
+}
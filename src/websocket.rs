@@ -0,0 +1,426 @@
+//! Agents that connect over WebSocket, like ones running in a browser. An
+//! agent is given as `ws:<port>/<path>`, or `ws:<host>:<port>/<path>` to
+//! listen on one address only, and several seats can share a port and path.
+//!
+//! The runner listens before the game and starts once every seat has an
+//! agent, each taking the first free seat for the path it connected to. An
+//! agent talks JSON, one message per text frame, and says
+//! [`Hello`](game_def::Hello) first.
+//!
+//! An agent that disconnects forfeits, unless it connects again with the
+//! same name within `--reconnect-grace`. It then gets again everything it
+//! was sent since its last action. The move timeout keeps running meanwhile.
+//!
+//! The runner pings every agent every [`PING_INTERVAL`]. One that answers
+//! nothing for [`SILENCE_LIMIT`] is taken for disconnected, except while it
+//! is thinking about a move, which the move timeout is for.
+
+use std::{
+    collections::HashMap,
+    io::{BufReader, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Condvar, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context};
+use game_def::{
+    transcript::Direction,
+    websocket::{self, Message},
+    Action, Hello,
+};
+
+use crate::transport::{TranscriptSlot, Transport};
+
+pub const PING_INTERVAL: Duration = Duration::from_secs(5);
+pub const SILENCE_LIMIT: Duration = Duration::from_secs(15);
+/// How long a new connection has to upgrade and say hello.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long writing to an agent may block before it counts as gone.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The actions of an agent, as the runner receives them.
+pub type Replies = Receiver<anyhow::Result<Action>>;
+
+/// Where a WebSocket agent connects: the address to listen on and the path.
+pub struct WsAddress {
+    pub listen: String,
+    pub path: String,
+}
+
+impl WsAddress {
+    pub fn parse(agent: &str) -> Option<WsAddress> {
+        let rest = agent.strip_prefix("ws:")?;
+        let (address, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let listen = match address.parse::<u16>() {
+            Ok(port) => format!("0.0.0.0:{port}"),
+            Err(_) => address.to_owned(),
+        };
+        Some(WsAddress {
+            listen,
+            path: path.to_owned(),
+        })
+    }
+}
+
+/// A seat for a WebSocket agent, and whoever is connected to it.
+struct Slot {
+    path: String,
+    /// What the runner calls the agent, for its messages.
+    label: String,
+    /// From the agent's hello, `None` until it first connects.
+    name: Option<String>,
+    /// `None` while the agent is disconnected.
+    stream: Option<TcpStream>,
+    /// Counts connections, so threads of an old one know to stop.
+    generation: u64,
+    /// To the runner, dropped when the agent forfeits or the game is over.
+    replies: Option<Sender<anyhow::Result<Action>>>,
+    /// Until the runner takes it with [`Server::agent`].
+    receiver: Option<Replies>,
+    /// Everything sent since the last action, sent again on reconnect.
+    unanswered: Vec<String>,
+    last_seen: Instant,
+    transcript: TranscriptSlot,
+}
+
+impl Slot {
+    /// Sends `message` to the agent if it is connected. A connection that
+    /// fails is shut down, for its reader to clean up after.
+    fn send(&mut self, message: &Message) {
+        if let Some(stream) = &mut self.stream {
+            if websocket::write_message(stream, message, None).is_err() {
+                _ = stream.shutdown(Shutdown::Both);
+            }
+        }
+    }
+}
+
+/// Accepts WebSocket agents for the whole game, so they can come back.
+pub struct Server {
+    slots: Mutex<Vec<Slot>>,
+    changed: Condvar,
+    /// How long a disconnected agent has to come back, `--reconnect-grace`.
+    grace: Duration,
+}
+
+impl Server {
+    /// Listens on every address in `addresses`, one seat for each.
+    pub fn listen(addresses: &[WsAddress], grace: Duration) -> anyhow::Result<Arc<Server>> {
+        let slots = addresses
+            .iter()
+            .map(|address| {
+                let (replies, receiver) = channel();
+                Slot {
+                    path: address.path.clone(),
+                    label: String::new(),
+                    name: None,
+                    stream: None,
+                    generation: 0,
+                    replies: Some(replies),
+                    receiver: Some(receiver),
+                    unanswered: vec![],
+                    last_seen: Instant::now(),
+                    transcript: TranscriptSlot::default(),
+                }
+            })
+            .collect();
+        let server = Arc::new(Server {
+            slots: Mutex::new(slots),
+            changed: Condvar::new(),
+            grace,
+        });
+        let mut listeners = HashMap::new();
+        for address in addresses {
+            if listeners.contains_key(&address.listen) {
+                continue;
+            }
+            let listener = TcpListener::bind(&address.listen)
+                .with_context(|| format!("Failed to listen on {}", address.listen))?;
+            listeners.insert(address.listen.clone(), listener);
+        }
+        for (address, listener) in listeners {
+            let server = server.clone();
+            say!("Waiting for agents on ws://{address}");
+            std::thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let server = server.clone();
+                    std::thread::spawn(move || server.welcome(stream));
+                }
+            });
+        }
+        Ok(server)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Slot>> {
+        self.slots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Waits until every seat has an agent, and gives the names they said
+    /// hello with.
+    pub fn wait_for_agents(&self) -> Vec<String> {
+        let slots = self
+            .changed
+            .wait_while(self.lock(), |slots| slots.iter().any(|s| s.name.is_none()))
+            .unwrap_or_else(|e| e.into_inner());
+        slots.iter().map(|s| s.name.clone().unwrap()).collect()
+    }
+
+    /// The agent in seat `slot`, called `label` by the runner, whose replies
+    /// go to `transcript` too.
+    pub fn agent(
+        self: &Arc<Self>,
+        slot: usize,
+        label: String,
+        transcript: TranscriptSlot,
+    ) -> (Box<dyn Transport>, Box<dyn Write>, Replies) {
+        let mut slots = self.lock();
+        slots[slot].label = label;
+        slots[slot].transcript = transcript;
+        let receiver = slots[slot].receiver.take().expect("one agent per seat");
+        let seat = WsSeat {
+            server: self.clone(),
+            slot,
+        };
+        let writer = WsWriter {
+            seat: WsSeat {
+                server: self.clone(),
+                slot,
+            },
+            buffer: vec![],
+        };
+        (Box::new(seat), Box::new(writer), receiver)
+    }
+
+    /// Upgrades a new connection, reads its hello and gives it a seat.
+    fn welcome(self: Arc<Self>, stream: TcpStream) {
+        let Ok(mut reader) = handshake(&stream) else {
+            return;
+        };
+        let Ok((path, hello)) = reader.hello() else {
+            return;
+        };
+        let mut slots = self.lock();
+        let unseated = |s: &Slot| s.path == path && s.name.is_none();
+        // Back from a disconnect, or new.
+        let Some(slot) = slots
+            .iter()
+            .position(|s| {
+                s.path == path
+                    && s.stream.is_none()
+                    && s.replies.is_some()
+                    && s.name.as_ref() == Some(&hello.name)
+            })
+            .or_else(|| slots.iter().position(unseated))
+        else {
+            _ = websocket::write_message(&mut &stream, &Message::Close, None);
+            return;
+        };
+        let returning = slots[slot].name.is_some();
+        let Ok(writer) = stream.try_clone() else {
+            return;
+        };
+        let seat = &mut slots[slot];
+        if returning {
+            say!("{} reconnected", seat.label);
+            if let Some(transcript) = seat.transcript.get() {
+                transcript.note("reconnected, sending what it didn't answer again");
+            }
+        } else {
+            seat.label = hello.name.clone();
+        }
+        seat.name = Some(hello.name);
+        seat.stream = Some(writer);
+        seat.generation += 1;
+        seat.last_seen = Instant::now();
+        for text in seat.unanswered.clone() {
+            seat.send(&Message::Text(text));
+        }
+        let generation = seat.generation;
+        drop(slots);
+        self.changed.notify_all();
+        let server = self.clone();
+        std::thread::spawn(move || server.ping(slot, generation));
+        self.read(slot, generation, reader.reader);
+    }
+
+    /// Passes on what the agent sends until it disconnects.
+    fn read(&self, slot: usize, generation: u64, mut reader: BufReader<TcpStream>) {
+        loop {
+            let message = websocket::read_message(&mut reader);
+            let mut slots = self.lock();
+            let seat = &mut slots[slot];
+            if seat.generation != generation {
+                return;
+            }
+            seat.last_seen = Instant::now();
+            match message {
+                Ok(Some(Message::Text(text))) => {
+                    if let Some(transcript) = seat.transcript.get() {
+                        transcript.message(Direction::FromAgent, text.as_bytes());
+                    }
+                    seat.unanswered.clear();
+                    if let Some(replies) = &seat.replies {
+                        _ = replies.send(game_def::decode_message(text.as_bytes(), false));
+                    }
+                }
+                Ok(Some(Message::Binary(_))) => {
+                    if let Some(transcript) = seat.transcript.get() {
+                        transcript.note("got a binary message");
+                    }
+                    if let Some(replies) = &seat.replies {
+                        _ = replies.send(Err(anyhow::anyhow!("Got a binary message")));
+                    }
+                }
+                Ok(Some(Message::Ping(payload))) => seat.send(&Message::Pong(payload)),
+                Ok(Some(Message::Pong(_))) => (),
+                Ok(Some(Message::Close) | None) | Err(_) => break,
+            }
+        }
+        drop(reader);
+        self.disconnected(slot, generation);
+    }
+
+    /// Gives the agent [`Server::grace`] to come back before it forfeits.
+    fn disconnected(&self, slot: usize, generation: u64) {
+        let mut slots = self.lock();
+        let seat = &mut slots[slot];
+        if seat.generation != generation || seat.replies.is_none() {
+            return;
+        }
+        if let Some(stream) = seat.stream.take() {
+            _ = stream.shutdown(Shutdown::Both);
+        }
+        if seat.receiver.is_some() {
+            // Before the game, the seat is free for anyone again.
+            seat.name = None;
+            return;
+        }
+        if self.grace.is_zero() {
+            seat.replies = None;
+            return;
+        }
+        say!(
+            "{} disconnected, waiting {:.1?} for it to come back",
+            seat.label,
+            self.grace
+        );
+        drop(slots);
+        std::thread::sleep(self.grace);
+        let mut slots = self.lock();
+        let seat = &mut slots[slot];
+        if seat.generation == generation {
+            // The runner sees it as an agent that closed its output.
+            seat.replies = None;
+        }
+    }
+
+    /// Pings the agent, and takes it for disconnected once it is silent for
+    /// too long without a move to think about.
+    fn ping(&self, slot: usize, generation: u64) {
+        loop {
+            std::thread::sleep(PING_INTERVAL);
+            let mut slots = self.lock();
+            let seat = &mut slots[slot];
+            let Some(stream) = &seat.stream else {
+                return;
+            };
+            if seat.generation != generation {
+                return;
+            }
+            if seat.unanswered.is_empty() && seat.last_seen.elapsed() > SILENCE_LIMIT {
+                _ = stream.shutdown(Shutdown::Both);
+                return;
+            }
+            seat.send(&Message::Ping(vec![]));
+        }
+    }
+}
+
+/// A connection past the WebSocket handshake.
+struct Handshake {
+    path: String,
+    reader: BufReader<TcpStream>,
+}
+
+fn handshake(stream: &TcpStream) -> anyhow::Result<Handshake> {
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let path = websocket::accept(&mut reader, &mut &*stream)?;
+    Ok(Handshake { path, reader })
+}
+
+impl Handshake {
+    /// Reads the agent's [`Hello`], after which it waits as long as it takes.
+    fn hello(&mut self) -> anyhow::Result<(String, Hello)> {
+        let text = loop {
+            match websocket::read_message(&mut self.reader)? {
+                Some(Message::Text(text)) => break text,
+                Some(Message::Ping(_) | Message::Pong(_)) => (),
+                _ => bail!("The agent did not say hello"),
+            }
+        };
+        let hello = game_def::decode_message(text.as_bytes(), false)?;
+        self.reader.get_ref().set_read_timeout(None)?;
+        Ok((self.path.clone(), hello))
+    }
+}
+
+/// The runner's end of a seat, for as long as the game goes.
+struct WsSeat {
+    server: Arc<Server>,
+    slot: usize,
+}
+
+impl Transport for WsSeat {
+    /// Says goodbye to the agent, and takes no more connections for the seat.
+    fn close(&mut self) {
+        let mut slots = self.server.lock();
+        let seat = &mut slots[self.slot];
+        seat.replies = None;
+        seat.generation += 1;
+        seat.send(&Message::Close);
+        if let Some(stream) = seat.stream.take() {
+            _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Sends each message the runner writes as a text frame, once flushed.
+struct WsWriter {
+    seat: WsSeat,
+    buffer: Vec<u8>,
+}
+
+impl Write for WsWriter {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    /// A message for an agent that is away waits for it to come back, one for
+    /// an agent that forfeited fails.
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let text = String::from_utf8_lossy(&self.buffer).trim_end().to_owned();
+        self.buffer.clear();
+        let mut slots = self.seat.server.lock();
+        let seat = &mut slots[self.seat.slot];
+        if seat.replies.is_none() {
+            return Err(std::io::ErrorKind::BrokenPipe.into());
+        }
+        seat.send(&Message::Text(text.clone()));
+        seat.unanswered.push(text);
+        Ok(())
+    }
+}
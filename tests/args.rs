@@ -0,0 +1,160 @@
+//! The command line of a game, read without running one.
+
+use std::time::Duration;
+
+use game_def::{ObjectiveMode, ResourceMap};
+
+// The runner's own parser, which only needs the rules.
+#[allow(dead_code)]
+#[path = "../src/args.rs"]
+mod args;
+
+use args::{AgentArg, Args, OnInvalid, DEFAULT_MAX_ROUNDS, DEFAULT_RESTARTS};
+
+fn parse(line: &str) -> anyhow::Result<Args> {
+    Args::parse(line.split_whitespace().map(str::to_owned))
+}
+
+fn error(line: &str) -> String {
+    format!("{:#}", parse(line).unwrap_err())
+}
+
+#[test]
+fn agents_alone_get_the_defaults() {
+    let args = parse("human-alice ./bot").unwrap();
+    assert_eq!(
+        args.agents,
+        [
+            AgentArg::new("human-alice".to_owned()),
+            AgentArg::new("./bot".to_owned())
+        ]
+    );
+    assert!(args.assist && args.clear && !args.compact);
+    assert_eq!(args.config.max_rounds, Some(DEFAULT_MAX_ROUNDS));
+    assert_eq!(args.on_invalid, OnInvalid::Forfeit);
+    assert_eq!(
+        (args.first.as_str(), args.color.as_str()),
+        ("random", "auto")
+    );
+}
+
+#[test]
+fn agent_flags_apply_to_the_next_agent_only() {
+    let args = parse(
+        "--format msgpack --name fast --agent-time-budget 2.5 ./a --protocol messages ./b ./c",
+    )
+    .unwrap();
+    assert_eq!(
+        args.agents,
+        [
+            AgentArg {
+                msgpack: true,
+                name: Some("fast".to_owned()),
+                time_budget: Some(Duration::from_millis(2500)),
+                ..AgentArg::new("./a".to_owned())
+            },
+            AgentArg {
+                messages: true,
+                ..AgentArg::new("./b".to_owned())
+            },
+            AgentArg::new("./c".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn the_hint_agent_leaves_the_name_to_the_next_agent() {
+    let args = parse("--protocol messages --name hinted --hint-agent ./hint ./a ./b").unwrap();
+    assert_eq!(
+        args.hint_agent,
+        Some(AgentArg {
+            messages: true,
+            ..AgentArg::new("./hint".to_owned())
+        })
+    );
+    assert_eq!(args.agents[0].name.as_deref(), Some("hinted"));
+    assert!(!args.agents[0].messages);
+}
+
+#[test]
+fn flags_with_values() {
+    let args = parse(
+        "--seed 7 --players 3 --first bob --target-score 20 --max-rounds 30 --wilds 3 \
+         --coins 6 --variant cities --move-timeout 1.5 --time-budget 60 --increment 2 \
+         --on-invalid terminate --on-crash restart:5 --invalid-retries 2 --render compact \
+         --full-board-every 10 --delay 250ms --output json --color never \
+         --load-redacted saved.json a b c",
+    )
+    .unwrap();
+    assert_eq!((args.seed, args.players), (Some(7), Some(3)));
+    assert_eq!(args.first, "bob");
+    let config = &args.config;
+    assert_eq!(
+        (config.target_score, config.max_rounds, config.wilds),
+        (20, Some(30), 3)
+    );
+    assert_eq!(config.objective_mode, ObjectiveMode::Cities);
+    assert_eq!(args.coins, Some(6));
+    assert_eq!(args.move_timeout, Duration::from_millis(1500));
+    assert_eq!(args.time_budget, Some(Duration::from_secs(60)));
+    assert_eq!(args.increment, Duration::from_secs(2));
+    assert_eq!(args.on_invalid, OnInvalid::Terminate);
+    assert_eq!((args.max_restarts, args.invalid_retries), (5, 2));
+    assert_eq!((args.compact, args.full_board_every), (true, 10));
+    assert_eq!(args.delay, Some(Duration::from_millis(250)));
+    assert!(args.json);
+    assert_eq!(args.color, "never");
+    assert_eq!(args.load, Some(("saved.json".to_owned(), true)));
+    assert_eq!(args.agents.len(), 3);
+    assert_eq!(
+        parse("--on-crash restart").unwrap().max_restarts,
+        DEFAULT_RESTARTS
+    );
+    assert_eq!(parse("--on-crash forfeit").unwrap().max_restarts, 0);
+}
+
+#[test]
+fn handicaps_for_a_seat_add_up() {
+    let args = parse("--handicap 1:coins=1w:score=2 --handicap 1:coins=1u:time=+30s a b").unwrap();
+    let handicap = &args.config.handicaps[&1];
+    assert_eq!(handicap.coins.gems, ResourceMap::from_code("1w+1u"));
+    assert_eq!((handicap.score, handicap.time_ms), (2, 30_000));
+    assert_eq!(args.config.handicaps.len(), 1);
+}
+
+#[test]
+fn limits_and_observers_are_kept_for_later() {
+    let args = parse("--agent-mem 2G --observer ./watch --agent-cpus 1 a b").unwrap();
+    assert_eq!(args.limits, ["--agent-mem", "2G", "--agent-cpus", "1"]);
+    assert_eq!(args.observers, ["./watch"]);
+    assert_eq!(args.agents.len(), 2);
+}
+
+#[test]
+fn bad_values_are_errors() {
+    for (line, message) in [
+        ("--seed", "--seed needs a number"),
+        ("--seed x", "--seed needs a number"),
+        (
+            "--move-timeout -1",
+            "--move-timeout needs a positive number of seconds",
+        ),
+        ("--players 5", "--players must be 2, 3 or 4, not 5"),
+        ("--render wide", "--render needs full or compact"),
+        ("--color blue", "--color needs auto, always or never"),
+        (
+            "--on-crash restart:x",
+            "--on-crash needs forfeit, restart or restart:<times>",
+        ),
+        ("--record", "--record needs a path"),
+        ("--agent-log-dir", "--agent-log-dir needs a directory"),
+        ("--format xml", "--format needs json or msgpack"),
+        ("--delay soon", "--delay needs a time like 500ms or 2s"),
+        (
+            "--handicap 0:time=30s",
+            "A time handicap needs a time like +30s, not 30s",
+        ),
+    ] {
+        assert_eq!(error(line), message, "{line}");
+    }
+}
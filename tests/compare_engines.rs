@@ -0,0 +1,153 @@
+//! The compare-engines program, on two depths of the built-in alpha-beta agent.
+
+use std::{
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+use game_def::{GameConfig, State};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const SHALLOW: &str = "builtin:alphabeta?depth=1&time=600&endgame=0";
+const DEEP: &str = "builtin:alphabeta?depth=2&time=600&endgame=0";
+
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("splendor-compare-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A suite of positions from games of random legal moves, alternating
+/// observations and full states.
+fn suite(name: &str) -> PathBuf {
+    let mut rng = StdRng::seed_from_u64(3);
+    let mut lines = vec![];
+    for game in 0..4 {
+        let mut state = State::setup(&["a", "b"], GameConfig::default(), &mut rng);
+        for ply in 0..24 {
+            let actions: Vec<_> = state.legal_actions().collect();
+            // Resign is always last.
+            let action = actions[rng.gen_range(0..actions.len() - 1)].clone();
+            state.run(action).unwrap();
+            if ply % 8 == 7 {
+                lines.push(match game % 2 {
+                    0 => state.observation_for(state.turn).json(),
+                    _ => state.json(),
+                });
+            }
+        }
+    }
+    let path = temp_dir().join(name);
+    std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+    path
+}
+
+fn compare(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_compare-engines"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn depths_disagree_the_same_way_every_time() {
+    let suite = suite("depths.suite");
+    let diff_suite = temp_dir().join("depths-diff.suite");
+    let args = [
+        "--suite",
+        suite.to_str().unwrap(),
+        "--old",
+        SHALLOW,
+        "--new",
+        DEEP,
+        "--diff-suite",
+        diff_suite.to_str().unwrap(),
+    ];
+    let first = compare(&args);
+    let text = stdout(&first);
+    assert_eq!(first.status.code(), Some(1), "{text}");
+    assert!(text.ends_with("FAIL\n"), "{text}");
+    let differ = text.lines().filter(|l| l.starts_with("Position ")).count();
+    assert!(differ > 0, "{text}");
+    assert!(text.contains(&format!("{differ} of 12 positions differ (allowed 0)")));
+    let again = compare(&args);
+    assert_eq!(stdout(&again), text);
+
+    // The diff suite holds exactly the positions that differ.
+    let diff_text = std::fs::read_to_string(&diff_suite).unwrap();
+    assert_eq!(diff_text.lines().count(), differ);
+    let output = compare(&[
+        "--suite",
+        diff_suite.to_str().unwrap(),
+        "--old",
+        SHALLOW,
+        "--new",
+        DEEP,
+        "--max-diff",
+        &differ.to_string(),
+    ]);
+    let text = stdout(&output);
+    assert!(output.status.success(), "{text}");
+    assert!(
+        text.contains(&format!("{differ} of {differ} positions differ")),
+        "{text}"
+    );
+    assert!(text.ends_with("PASS\n"), "{text}");
+}
+
+#[test]
+fn moves_within_the_threshold_count_as_the_same() {
+    let suite = suite("threshold.suite");
+    let suite = suite.to_str().unwrap();
+    let same = compare(&["--suite", suite, "--old", SHALLOW, "--new", SHALLOW]);
+    assert!(same.status.success());
+    assert!(stdout(&same).contains("0 of 12 positions differ"));
+    let output = compare(&[
+        "--suite",
+        suite,
+        "--old",
+        SHALLOW,
+        "--new",
+        DEEP,
+        "--threshold",
+        "1000000000",
+    ]);
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert!(stdout(&output).contains("0 of 12 positions differ"));
+}
+
+#[test]
+fn bad_arguments_and_engines_are_reported() {
+    let suite = suite("errors.suite");
+    let suite = suite.to_str().unwrap();
+    // Positional engines are not taken any more.
+    let output = compare(&[suite, SHALLOW, DEEP]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown argument"));
+    // Errors skip the commit under `git bisect run`.
+    let output = compare(&[
+        "--suite",
+        suite,
+        "--old",
+        SHALLOW,
+        "--new",
+        "builtin:nothing",
+    ]);
+    assert_eq!(output.status.code(), Some(125));
+    let output = compare(&[
+        "--suite",
+        suite,
+        "--old",
+        SHALLOW,
+        "--new",
+        "builtin:alphabeta?time=5",
+        "--budget",
+        "0.001",
+    ]);
+    assert_eq!(output.status.code(), Some(125));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("took more than"));
+}
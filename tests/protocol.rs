@@ -0,0 +1,131 @@
+//! The dummy agent driven through both protocols, directly and by the runner.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Command, Stdio},
+};
+
+use game_def::{Action, GameConfig, GameResult, RunnerMessage, State, PROTOCOL_VERSION};
+use rand::{rngs::StdRng, SeedableRng};
+
+fn game(seed: u64) -> State {
+    State::standard(&["a", "b"], &mut StdRng::seed_from_u64(seed))
+}
+
+/// Runs the dummy agent with `args` on `input`, and gives the actions it
+/// wrote and what it printed to stderr.
+fn dummy_agent(args: &[&str], input: &[String]) -> (Vec<Action>, String) {
+    let mut process = Command::new(env!("CARGO_BIN_EXE_dummy-agent"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = process.stdin.take().unwrap();
+    for line in input {
+        writeln!(stdin, "{line}").unwrap();
+    }
+    drop(stdin);
+    let actions = BufReader::new(process.stdout.take().unwrap())
+        .lines()
+        .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+        .collect();
+    let output = process.wait_with_output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    (actions, String::from_utf8(output.stderr).unwrap())
+}
+
+#[test]
+fn states_protocol_answers_each_observation() {
+    let states = [game(32), game(33)];
+    let input: Vec<String> = states
+        .iter()
+        .map(|s| s.observation_for(s.turn).json())
+        .collect();
+    let (actions, _) = dummy_agent(&[], &input);
+    assert_eq!(actions.len(), 2);
+    for (state, action) in states.iter().zip(actions) {
+        state.check_action(&action).unwrap();
+    }
+}
+
+#[test]
+fn messages_protocol_says_the_seat_and_how_the_game_ended() {
+    let mut state = game(34);
+    let config = GameConfig::default();
+    let first = state.legal_actions().next().unwrap();
+    state.run(first.clone()).unwrap();
+    let messages = [
+        RunnerMessage::Init {
+            protocol_version: PROTOCOL_VERSION,
+            player_index: 1,
+            config,
+            new_games: false,
+        },
+        RunnerMessage::Played {
+            player: 0,
+            action: first.clone(),
+        },
+        RunnerMessage::MoveRequest {
+            observation: Box::new(state.observation_for(1)),
+            time_left_ms: None,
+        },
+        RunnerMessage::GameOver {
+            result: GameResult::Won(0),
+        },
+    ];
+    let input: Vec<String> = messages
+        .iter()
+        .map(|m| serde_json::to_string(m).unwrap())
+        .collect();
+    let (actions, stderr) = dummy_agent(
+        &["--protocol", "messages", "--follow", "--announce"],
+        &input,
+    );
+    let [action] = &actions[..] else {
+        panic!("{actions:?}");
+    };
+    state.check_action(action).unwrap();
+    assert_eq!(
+        stderr,
+        format!("playing seat 1\nplayer 0 played {first}\nasked to move\ngame over: Won(0)\n"),
+        "{stderr}"
+    );
+}
+
+/// The runner says the seat and the end of the game to the agent talking
+/// messages, and just asks the other one for moves.
+#[cfg(unix)]
+#[test]
+fn the_runner_plays_agents_of_both_protocols() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!("splendor-protocol-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // The runner can't pass arguments to agents.
+    let announcing = dir.join("announcing");
+    let script = format!(
+        "#!/bin/sh\nexec '{}' --announce \"$@\"\n",
+        env!("CARGO_BIN_EXE_dummy-agent")
+    );
+    std::fs::write(&announcing, script).unwrap();
+    std::fs::set_permissions(&announcing, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let logs = dir.join("logs");
+    let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .args(["--seed", "35", "--first", "0", "--agent-log-dir"])
+        .arg(&logs)
+        .arg(&announcing)
+        .args(["--protocol", "messages"])
+        .arg(&announcing)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let log = String::from_utf8(output.stdout).unwrap();
+    assert!(log.contains("Game finished"), "{log}");
+    let states = std::fs::read_to_string(logs.join("0-announcing-1.log")).unwrap();
+    assert_eq!(states, "");
+    let messages = std::fs::read_to_string(logs.join("1-announcing-2.log")).unwrap();
+    assert!(messages.starts_with("playing seat 1\n"), "{messages}");
+    assert!(messages.contains("game over: "), "{messages}");
+}
@@ -0,0 +1,2591 @@
+//! Games between dummy and scripted agents, run through the runner binary.
+
+#![cfg(unix)]
+
+use std::{
+    io::Write,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::OnceLock,
+    time::Duration,
+};
+
+use anyhow::Context;
+use game_def::{
+    notation::Notation, Action, Agent, AgentHandler, EndReason, GameConfig, GameEvent, GameResult,
+    Observation, RandomAgent, Replay, State,
+};
+use rand::SeedableRng;
+
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("splendor-runner-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A script running `dummy-agent` with `args`, as the runner can't pass
+/// arguments to agents.
+fn dummy_agent(name: &str, args: &str) -> PathBuf {
+    let path = temp_dir().join(name);
+    let script = format!(
+        "#!/bin/sh\nexec '{}' {args} \"$@\"\n",
+        env!("CARGO_BIN_EXE_dummy-agent")
+    );
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+/// The scripted-agent program. It belongs to another package of the
+/// workspace, so it is built here, once, next to the runner.
+fn scripted_agent_program() -> &'static Path {
+    static PROGRAM: OnceLock<PathBuf> = OnceLock::new();
+    PROGRAM.get_or_init(|| {
+        let mut cargo = Command::new(env!("CARGO"));
+        cargo.args(["build", "--quiet", "--package", "scripted-agent"]);
+        if !cfg!(debug_assertions) {
+            cargo.arg("--release");
+        }
+        assert!(cargo.status().unwrap().success());
+        Path::new(env!("CARGO_BIN_EXE_splendor-ai")).with_file_name("scripted-agent")
+    })
+}
+
+/// A script running `scripted-agent` with `args`, playing `moves`, one a
+/// line in notation.
+fn scripted_agent(name: &str, moves: &str, args: &str) -> PathBuf {
+    let dir = temp_dir();
+    let moves_path = dir.join(format!("{name}.moves"));
+    std::fs::write(&moves_path, moves).unwrap();
+    let path = dir.join(name);
+    let script = format!(
+        "#!/bin/sh\nexec '{}' --script '{}' {args} \"$@\"\n",
+        scripted_agent_program().display(),
+        moves_path.display()
+    );
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+/// How the runner calls an agent in its output.
+fn ai_name(agent: &Path) -> String {
+    agent.file_name().unwrap().to_str().unwrap().to_owned()
+}
+
+fn run_game(args: &[&str], agents: &[PathBuf]) -> String {
+    run_game_ending(0, args, agents)
+}
+
+/// Like [`run_game`], for a runner that exits with `code`.
+fn run_game_ending(code: i32, args: &[&str], agents: &[PathBuf]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .args(args)
+        .args(agents)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(code), "{output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn slow_agent_times_out_and_forfeits() {
+    let slow = dummy_agent("slow", "--sleep-ms 2000");
+    let fast = dummy_agent("fast", "");
+    let log = run_game(
+        &["--move-timeout", "0.2", "--max-rounds", "100"],
+        &[slow.clone(), fast.clone(), fast],
+    );
+    let slow = ai_name(&slow);
+    assert!(log.contains(&format!("{slow} timed out after")), "{log}");
+    assert!(log.contains(&format!("{slow} resigned")), "{log}");
+    assert!(!log.contains(&format!("{slow} did")), "{log}");
+    assert!(log.contains(" did "), "{log}");
+    assert!(log.contains("Game finished"), "{log}");
+}
+
+/// The time left each move of `agent` reports, in seconds.
+fn clock_readings(log: &str, agent: &str) -> Vec<f64> {
+    log.lines()
+        .filter(|line| line.starts_with(&format!("{agent} did ")))
+        .map(|line| {
+            let left = line.rsplit_once(" (").unwrap().1;
+            let left = left.strip_suffix(" left)").unwrap();
+            match left.strip_suffix("ms") {
+                Some(ms) => ms.parse::<f64>().unwrap() / 1000.0,
+                None => left.strip_suffix('s').unwrap().parse().unwrap(),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn slow_agent_loses_on_time() {
+    let slow = dummy_agent("slow-clock", "--sleep-ms 300");
+    let fast = dummy_agent("fast-clock", "");
+    let log = run_game(&["--time-budget", "1"], &[slow.clone(), fast.clone()]);
+    let (slow, fast) = (ai_name(&slow), ai_name(&fast));
+    let readings = clock_readings(&log, &slow);
+    assert_eq!(readings.len(), 3, "{log}");
+    assert!(readings.windows(2).all(|w| w[1] < w[0] - 0.25), "{log}");
+    assert!(log.contains(&format!("{slow} lost on time after")), "{log}");
+    assert!(log.contains(&format!("Game finished, {fast} won")), "{log}");
+}
+
+#[test]
+fn increment_is_added_after_each_move() {
+    let slow = dummy_agent("slow-increment", "--sleep-ms 100");
+    let fast = dummy_agent("fast-increment", "");
+    let log = run_game(
+        &[
+            "--time-budget",
+            "0.5",
+            "--increment",
+            "0.2",
+            "--max-rounds",
+            "10",
+        ],
+        &[slow.clone(), fast.clone()],
+    );
+    assert!(!log.contains("lost on time"), "{log}");
+    for (agent, spent) in [(slow, 0.1), (fast, 0.0)] {
+        let readings = clock_readings(&log, &ai_name(&agent));
+        assert!(readings.len() >= 10, "{log}");
+        for (moves, left) in readings.into_iter().enumerate() {
+            let moves = (moves + 1) as f64;
+            let expected = 0.5 + moves * (0.2 - spent);
+            assert!(left <= expected && left > expected - 0.08 * moves, "{log}");
+        }
+    }
+}
+
+#[test]
+fn recorded_game_plays_back() {
+    let agent = env!("CARGO_BIN_EXE_dummy-agent");
+    let path = temp_dir().join("recorded.json");
+    let path = path.to_str().unwrap();
+    run_game(
+        &["--record", path, "--max-rounds", "50"],
+        &[agent.into(), agent.into()],
+    );
+    let replay = Replay::load(path).unwrap();
+    replay.verify().unwrap();
+    assert!(replay.result != GameResult::Ongoing);
+    assert!(replay.seed.is_some());
+    assert_eq!(replay.agents, [agent, agent]);
+    assert_eq!(replay.think_ms.len(), replay.actions.len());
+    assert!(replay.invalid.is_empty());
+}
+
+#[test]
+fn notation_reads_back_as_the_recorded_game() {
+    let agent = env!("CARGO_BIN_EXE_dummy-agent");
+    let dir = temp_dir();
+    let [path, exported, converted] = ["notation.json", "notation.txt", "converted.txt"]
+        .map(|name| dir.join(name).to_str().unwrap().to_owned());
+    run_game(
+        &[
+            "--record",
+            &path,
+            "--export-notation",
+            &exported,
+            "--seed",
+            "5",
+        ],
+        &[agent.into(), agent.into(), agent.into()],
+    );
+    let replay = Replay::load(&path).unwrap();
+    let text = std::fs::read_to_string(&exported).unwrap();
+    assert!(text.starts_with("Date: 20"), "{text}");
+    assert!(text.contains("\nSeed: 5\nResult: "), "{text}");
+    assert!(text.contains("\n1. take "), "{text}");
+    assert!(text.contains(" (nobel "), "{text}");
+
+    let notation = Notation::parse(&text).unwrap();
+    assert_eq!(notation.header("Player 2"), Some("dummy-agent-2"));
+    assert_eq!(notation.header("Agent 3"), Some(agent));
+    let played = notation.replay(replay.initial_state.clone()).unwrap();
+    assert!(played.actions == replay.actions);
+    assert_eq!(played.result, replay.result);
+    assert_eq!(played.end_reason, replay.end_reason);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .args(["notation", &path, &converted])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    // The same but maybe the date, if the day changed in between.
+    let converted = std::fs::read_to_string(&converted).unwrap();
+    assert_eq!(
+        converted.split_once('\n').unwrap().1,
+        text.split_once('\n').unwrap().1
+    );
+}
+
+fn record_game(name: &str) -> (String, Replay) {
+    let agent = env!("CARGO_BIN_EXE_dummy-agent");
+    let path = temp_dir().join(name);
+    let path = path.to_str().unwrap().to_owned();
+    run_game(
+        &["--record", &path, "--max-rounds", "50"],
+        &[agent.into(), agent.into()],
+    );
+    let replay = Replay::load(&path).unwrap();
+    (path, replay)
+}
+
+#[test]
+fn html_export_has_every_ply() {
+    let (path, replay) = record_game("html.json");
+    let page = temp_dir().join("html.html");
+    let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .args(["export-html", &path, "-o"])
+        .arg(&page)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let page = std::fs::read_to_string(page).unwrap();
+    let (_, json) = page
+        .split_once(r#"<script id="replay" type="application/json">"#)
+        .unwrap();
+    let (json, _) = json.split_once("</script>").unwrap();
+    let game: serde_json::Value = serde_json::from_str(json).unwrap();
+    let plies = game["plies"].as_array().unwrap();
+    assert_eq!(plies.len(), replay.actions.len() + 1);
+    assert!(plies[1]["text"].as_str().unwrap().starts_with("Ply 1/"));
+    // Only the face up cards, not the order of the decks.
+    let visible = replay.config.visible_cards;
+    for ply in plies {
+        for deck in ply["state"]["decks"].as_array().unwrap() {
+            assert!(deck.as_array().unwrap().len() <= visible);
+        }
+    }
+    assert!(game["outcome"]
+        .as_str()
+        .unwrap()
+        .starts_with("Game finished"));
+}
+
+fn playback(args: &[&str], input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .arg("replay")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn replay_steps_through_a_recorded_game() {
+    let (path, replay) = record_game("stepped.json");
+    let last = replay.actions.len();
+    let log = playback(&[&path], "\ngoto 5\nback\nfoo\nquit\n");
+    let plies: Vec<&str> = log.lines().filter(|l| l.starts_with("Ply ")).collect();
+    assert!(plies[0].starts_with(&format!("Ply 1/{last}: ")), "{log}");
+    assert!(plies[1].starts_with(&format!("Ply 5/{last}: ")), "{log}");
+    assert!(plies[2].starts_with(&format!("Ply 4/{last}: ")), "{log}");
+    assert!(log.contains("Commands: "), "{log}");
+
+    let log = playback(&[&path, "--auto", "--delay", "0ms"], "");
+    let plies = log.lines().filter(|l| l.starts_with("Ply ")).count();
+    assert_eq!(plies, last, "{log}");
+    assert!(log.contains("Game finished"), "{log}");
+}
+
+#[test]
+fn replay_saves_the_ply_shown() {
+    let (path, replay) = record_game("saved.json");
+    let saved = temp_dir().join("saved-ply.json");
+    let saved = saved.to_str().unwrap();
+    let log = playback(&[&path], &format!("goto 4\nsave {saved}\nquit\n"));
+    assert!(log.contains(&format!("Saved ply 4 to {saved}")), "{log}");
+    let state: State = serde_json::from_str(&std::fs::read_to_string(saved).unwrap()).unwrap();
+    assert_eq!(state.zobrist(), replay.states().nth(3).unwrap().zobrist());
+    let observation = Observation::load(saved).unwrap();
+    assert_eq!(observation.state.turn, state.turn);
+}
+
+#[test]
+fn replay_names_the_illegal_ply() {
+    let (path, mut replay) = record_game("illegal.json");
+    replay.actions[3] = replay.actions[2].clone();
+    replay.save(&path).unwrap();
+    let log = playback(&[&path, "--auto"], "");
+    assert!(log.starts_with("Error: Action 3 "), "{log}");
+}
+
+fn tournament(name: &str, args: &[&str], agents: &[PathBuf]) -> serde_json::Value {
+    let path = temp_dir().join(format!("{name}.json"));
+    let path = path.to_str().unwrap();
+    let mut all = vec!["tournament", "--json", path];
+    all.extend(args);
+    run_game(&all, agents);
+    serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn tournament_is_reproducible() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let run = || {
+        tournament(
+            "reproducible",
+            &["--seed", "5", "--games", "3"],
+            &[agent.clone(), agent.clone()],
+        )
+    };
+    let first = run();
+    assert_eq!(first, run());
+    assert_eq!(first["games"].as_array().unwrap().len(), 3);
+    let seats: Vec<_> = first["games"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|g| g["seats"].clone())
+        .collect();
+    assert_eq!(
+        seats,
+        [[0, 1], [1, 0], [0, 1]].map(|s| serde_json::json!(s))
+    );
+    for s in first["standings"].as_array().unwrap() {
+        let count = |key: &str| s[key].as_u64().unwrap();
+        assert_eq!(count("games"), 3);
+        assert_eq!(count("wins") + count("losses") + count("draws"), 3);
+    }
+}
+
+#[test]
+fn parallel_tournament_matches_serial() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let run = |jobs: &str| {
+        tournament(
+            &format!("jobs-{jobs}"),
+            &["--seed", "8", "--games", "4", "--jobs", jobs],
+            &vec![agent.clone(); 3],
+        )
+    };
+    let serial = run("1");
+    assert_eq!(serial, run("4"));
+    assert_eq!(serial["games"].as_array().unwrap().len(), 12);
+    for s in serial["standings"].as_array().unwrap() {
+        let count = |key: &str| s[key].as_u64().unwrap();
+        assert_eq!(count("games"), 8);
+        assert_eq!(count("wins") + count("losses") + count("draws"), 8);
+    }
+}
+
+fn head_to_head(name: &str, args: &[&str], a: &Path, b: &Path) -> serde_json::Value {
+    let path = temp_dir().join(format!("{name}.json"));
+    let path = path.to_str().unwrap();
+    let (a, b) = (a.to_str().unwrap(), b.to_str().unwrap());
+    let mut all = vec!["match", "--json", path, "--agent-a", a, "--agent-b", b];
+    all.extend(args);
+    run_game(&all, &[]);
+    serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn match_alternates_seats_and_lists_losses() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let summary = head_to_head(
+        "match-seats",
+        &["--games", "4", "--alternate-seats", "--seed", "2"],
+        &agent,
+        &agent,
+    );
+    let games = summary["games"].as_array().unwrap();
+    assert_eq!(games.len(), 4);
+    assert_eq!(games[1]["seats"], serde_json::json!([1, 0]));
+    assert_eq!(summary["sprt"], serde_json::Value::Null);
+    let count =
+        |t: &serde_json::Value| ["wins", "draws", "losses"].map(|key| t[key].as_u64().unwrap());
+    let [wins, draws, losses] = count(&summary);
+    assert_eq!(wins + draws + losses, 4);
+    let [first, second] = [0, 1].map(|seat| count(&summary["by_seat"][seat]));
+    assert_eq!(first.iter().sum::<u64>(), 2);
+    assert_eq!(second.iter().sum::<u64>(), 2);
+    assert_eq!(summary["lost"].as_array().unwrap().len() as u64, losses);
+    for loss in summary["lost"].as_array().unwrap() {
+        let game = &games[loss["game"].as_u64().unwrap() as usize - 1];
+        assert_eq!(loss["seed"], game["seed"]);
+        assert_eq!(game["seats"][loss["seat"].as_u64().unwrap() as usize], 0);
+    }
+}
+
+#[test]
+fn match_stops_once_decided() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let broken = dummy_agent("match-broken", "--answer nonsense");
+    // Losing every game, the log likelihood ratio of 50 Elo against 0 is
+    // -2.36 after 5 games and -3.18 after 6, below ln(0.05 / 0.95) = -2.94.
+    for jobs in ["1", "3"] {
+        let summary = head_to_head(
+            &format!("match-sprt-{jobs}"),
+            &["--games", "50", "--sprt", "0", "50", "--jobs", jobs],
+            &broken,
+            &agent,
+        );
+        assert_eq!(summary["lost"].as_array().unwrap().len(), 6);
+        assert_eq!(summary["games"].as_array().unwrap().len(), 6);
+        assert_eq!(summary["sprt"]["decision"], "H0");
+        let llr = summary["sprt"]["llr"].as_f64().unwrap();
+        assert!((llr + 3.185).abs() < 0.001, "{llr}");
+        assert!(summary["elo"].as_f64().unwrap() < -200.0);
+    }
+    // Deciding 5 Elo takes longer.
+    let summary = head_to_head(
+        "match-sprt-close",
+        &["--games", "50", "--sprt", "0", "5"],
+        &broken,
+        &agent,
+    );
+    assert_eq!(summary["games"].as_array().unwrap().len(), 22);
+}
+
+#[test]
+fn games_are_appended_to_the_stats_file() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let broken = dummy_agent("stats-broken", "--answer nonsense");
+    let path = temp_dir().join("stats.csv");
+    let path = path.to_str().unwrap();
+    _ = std::fs::remove_file(path);
+    let args = [
+        "--stats-file",
+        path,
+        "--seed",
+        "4",
+        "--name",
+        "Smith, \"J\"",
+    ];
+    run_game(&args, &[agent.clone(), agent.clone()]);
+    run_game_ending(3, &args, &[agent.clone(), broken.clone()]);
+    tournament(
+        "stats-tournament",
+        &["--stats-file", path, "--games", "2", "--jobs", "3"],
+        &[agent.clone(), agent.clone(), broken],
+    );
+    let csv = std::fs::read_to_string(path).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert!(lines[0].starts_with("timestamp,seed,first,result,rounds,duration_ms,termination,name_1,command_1,score_1,place_1,"), "{csv}");
+    assert_eq!(lines.len(), 1 + 2 + 6, "{csv}");
+    let crashed = |line: &&&str| line.contains(",crash,");
+    assert!(
+        lines[1].contains(",normal,") && crashed(&&lines[2]),
+        "{csv}"
+    );
+    assert_eq!(lines[1..].iter().filter(crashed).count(), 5, "{csv}");
+    assert!(lines[1].contains(",\"Smith, \"\"J\"\"\","), "{csv}");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .args(["stats", path])
+        .output()
+        .unwrap();
+    let summary = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        summary.starts_with("8 games: 3 normal, 0 timeout, 0 forfeit, 5 crash\n"),
+        "{summary}"
+    );
+    let smith = summary
+        .lines()
+        .find(|l| l.starts_with("Smith, \"J\" "))
+        .unwrap();
+    assert_eq!(smith.split_whitespace().nth(2), Some("2"), "{summary}");
+}
+
+#[test]
+fn tournament_scores_timeouts_as_losses() {
+    let fast = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let slow = dummy_agent("slow-tournament", "--sleep-ms 2000");
+    let summary = tournament(
+        "timeouts",
+        &["--move-timeout", "0.2"],
+        &[fast.clone(), slow, fast],
+    );
+    let standings = &summary["standings"];
+    assert_eq!(standings[1]["games"], 4);
+    assert_eq!(standings[1]["losses"], 4);
+    assert_eq!(standings[1]["forfeits"], 4);
+    assert_eq!(standings[0]["forfeits"], 0);
+    assert_eq!(standings[0]["games"], 4);
+}
+
+#[test]
+fn arena_admits_rates_and_retires_submissions() {
+    let submissions = temp_dir().join("arena-submissions");
+    std::fs::create_dir_all(&submissions).unwrap();
+    let data = temp_dir().join("arena-data");
+    let manifest = |name: &str, text: &str| {
+        let path = submissions.join(format!("{name}.agent"));
+        std::fs::write(path, text).unwrap();
+    };
+    let agent = env!("CARGO_BIN_EXE_dummy-agent");
+    manifest(
+        "alpha",
+        &format!("command = {agent}\nprotocol = messages\n"),
+    );
+    manifest("beta", &format!("command = {agent}\n"));
+    let broken = dummy_agent("arena-nonsense", "--answer nonsense");
+    std::fs::copy(broken, submissions.join("gamma")).unwrap();
+    let arena = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+            .arg("arena")
+            .arg(&submissions)
+            .arg("--data")
+            .arg(&data)
+            .args(["--interval", "0.1", "--max-rounds", "20", "--seed", "1"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let read = |name: &str| std::fs::read_to_string(data.join(name)).unwrap();
+    let ratings = || -> serde_json::Value { serde_json::from_str(&read("ratings.json")).unwrap() };
+
+    let log = arena(&["--games", "4", "--jobs", "2"]);
+    assert!(log.contains("Admitted alpha\nAdmitted beta\n"), "{log}");
+    assert!(
+        log.contains("Rejected gamma: in opening: the agent sent something"),
+        "{log}"
+    );
+    assert!(log.contains("Game 4: "), "{log}");
+    assert_eq!(ratings()["alpha"]["games"], 4);
+    assert_eq!(ratings()["beta"]["games"], 4);
+    let leaderboard = read("leaderboard.md");
+    assert!(leaderboard.contains(" | alpha | "), "{leaderboard}");
+    assert!(
+        leaderboard.contains("- gamma: in opening: "),
+        "{leaderboard}"
+    );
+
+    let log = arena(&["--dry-run", "--games", "3"]);
+    assert_eq!(log.matches("Would play ").count(), 3, "{log}");
+    assert!(!log.contains("Would test"), "{log}");
+
+    // Alpha is replaced, beta removed and delta added.
+    manifest("alpha", &format!("# Faster\ncommand = {agent}\n"));
+    std::fs::remove_file(submissions.join("beta.agent")).unwrap();
+    manifest("delta", &format!("command = {agent}\n"));
+    let log = arena(&["--games", "2"]);
+    assert!(log.contains("Retired beta\n"), "{log}");
+    assert!(
+        log.contains("alpha was replaced\nAdmitted alpha\n"),
+        "{log}"
+    );
+    assert!(log.contains("Admitted delta\n"), "{log}");
+    assert!(!log.contains("gamma"), "{log}");
+    assert!(log.contains("Game 6: "), "{log}");
+    let ratings = ratings();
+    assert_eq!(ratings["alpha"]["games"], 2);
+    assert_eq!(ratings["delta"]["games"], 2);
+    assert!(ratings.get("beta").is_none(), "{ratings}");
+    assert_eq!(read("games.csv").lines().count(), 7);
+}
+
+#[test]
+fn tournament_updates_elo_ratings() {
+    let fast = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let slow = dummy_agent("slow-elo", "--sleep-ms 2000");
+    let (fast_name, slow_name) = (fast.to_str().unwrap(), slow.to_str().unwrap());
+    let path = temp_dir().join("ratings.json");
+    let path = path.to_str().unwrap();
+    _ = std::fs::remove_file(path);
+    let args = [
+        "tournament",
+        "--games",
+        "1",
+        "--move-timeout",
+        "0.2",
+        "--ratings",
+        path,
+    ];
+    let read = || -> serde_json::Value {
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    };
+
+    // New agents start at 1500 with a K of 40.
+    run_game(&args, &[fast.clone(), slow.clone()]);
+    let ratings = read();
+    assert_eq!(ratings[fast_name]["rating"], 1520.0);
+    assert_eq!(ratings[slow_name]["rating"], 1480.0);
+    assert_eq!(ratings[slow_name]["games"], 1);
+
+    // The favorite is expected to score 1 / (1 + 10^(-200 / 400)) = 0.75975,
+    // and isn't provisional anymore, so its K is 20.
+    let seeded = serde_json::json!({
+        fast_name: { "rating": 1600.0, "games": 40, "updated": 0 },
+        slow_name: { "rating": 1400.0, "games": 5, "updated": 0 },
+        "retired": { "rating": 1700.0, "games": 90, "updated": 0 },
+    });
+    std::fs::write(path, seeded.to_string()).unwrap();
+    run_game(&args, &[fast.clone(), slow.clone()]);
+    let ratings = read();
+    let rating = |name: &str| ratings[name]["rating"].as_f64().unwrap();
+    assert!((rating(fast_name) - 1604.805).abs() < 0.001);
+    assert!((rating(slow_name) - 1390.390).abs() < 0.001);
+    assert_eq!(ratings["retired"], seeded["retired"]);
+    assert_eq!(ratings[fast_name]["games"], 41);
+
+    let board = run_game(&["ratings", path], &[]);
+    let ranks: Vec<&str> = board.lines().skip(1).collect();
+    assert!(ranks[0].contains("retired"), "{board}");
+    assert!(
+        ranks[1].contains(fast_name) && ranks[1].contains("1605"),
+        "{board}"
+    );
+    assert!(
+        ranks[2].contains(slow_name) && ranks[2].contains("prov"),
+        "{board}"
+    );
+}
+
+#[test]
+fn concurrent_tournaments_keep_all_ratings() {
+    let agent = env!("CARGO_BIN_EXE_dummy-agent");
+    let path = temp_dir().join("shared-ratings.json");
+    let path = path.to_str().unwrap();
+    _ = std::fs::remove_file(path);
+    let workers: Vec<_> = (0..4)
+        .map(|_| {
+            Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+                .args(["tournament", "--games", "3", "--max-rounds", "5"])
+                .args(["--ratings", path, agent, "other"])
+                .stdout(Stdio::null())
+                .spawn()
+                .unwrap()
+        })
+        .collect();
+    for mut worker in workers {
+        assert!(worker.wait().unwrap().success());
+    }
+    let ratings: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    assert_eq!(ratings[agent]["games"], 12);
+    assert_eq!(ratings["other"]["games"], 12);
+}
+
+#[test]
+fn mirrored_games_share_the_board() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let other = dummy_agent("mirrored", "");
+    let dir = temp_dir().join("mirrored-replays");
+    let summary = tournament(
+        "mirrored",
+        &[
+            "--mirrored",
+            "--games",
+            "2",
+            "--record",
+            dir.to_str().unwrap(),
+        ],
+        &[agent.clone(), other],
+    );
+    let games = summary["games"].as_array().unwrap();
+    assert_eq!(games.len(), 4);
+    let load =
+        |n: usize| Replay::load(dir.join(format!("game-{n}.json")).to_str().unwrap()).unwrap();
+    for pair in 0..2 {
+        let (first, second) = (&games[2 * pair], &games[2 * pair + 1]);
+        assert_eq!(first["pair"], pair);
+        assert_eq!(second["pair"], pair);
+        assert_eq!(first["seed"], second["seed"]);
+        assert_eq!(first["seats"], serde_json::json!([0, 1]));
+        assert_eq!(second["seats"], serde_json::json!([1, 0]));
+
+        let (a, b) = (load(2 * pair + 1), load(2 * pair + 2));
+        let ids = |replay: &Replay| -> Vec<Vec<usize>> {
+            let state = &replay.initial_state;
+            state
+                .decks
+                .values()
+                .map(|deck| deck.iter().map(|card| card.id()).collect())
+                .collect()
+        };
+        assert_eq!(ids(&a), ids(&b));
+        let nobels = |replay: &Replay| format!("{:?}", replay.initial_state.nobels);
+        assert_eq!(nobels(&a), nobels(&b));
+        let names = |replay: &Replay| -> Vec<String> {
+            let players = &replay.initial_state.players;
+            players.iter().map(|p| p.display_name.clone()).collect()
+        };
+        let mut swapped = names(&b);
+        swapped.reverse();
+        assert_eq!(names(&a), swapped);
+    }
+    let pairs = |i: usize| {
+        let s = &summary["standings"][i];
+        ["pair_wins", "pair_draws", "pair_losses"].map(|k| s[k].as_u64().unwrap())
+    };
+    assert_eq!(pairs(0).iter().sum::<u64>(), 2);
+    let [wins, draws, losses] = pairs(0);
+    assert_eq!(pairs(1), [losses, draws, wins]);
+}
+
+#[test]
+fn same_seed_deals_the_same_game() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let deal = |name: &str, seed: &str| {
+        let path = temp_dir().join(name);
+        let path = path.to_str().unwrap();
+        let log = run_game(
+            &["--seed", seed, "--max-rounds", "1", "--record", path],
+            &[agent.clone(), agent.clone(), agent.clone()],
+        );
+        assert!(log.starts_with(&format!("Seed {seed}, ")), "{log}");
+        Replay::load(path).unwrap()
+    };
+    let (a, b) = (deal("seeded-a.json", "42"), deal("seeded-b.json", "42"));
+    assert_eq!(a.seed, Some(42));
+    assert!(a.initial_state == b.initial_state);
+    assert_eq!(a.actions[0].0, a.initial_state.first_player);
+    assert!(a.actions == b.actions);
+    assert!(deal("seeded-c.json", "43").initial_state != a.initial_state);
+}
+
+#[test]
+fn agent_over_its_memory_limit_forfeits() {
+    let hog = dummy_agent("hog", "--hog-mb 1024");
+    let fast = dummy_agent("fast-limited", "");
+    let output = run_game_ending(
+        3,
+        &[
+            "--agent-mem",
+            "256M",
+            "--agent-cpus",
+            "1",
+            "--output",
+            "json",
+        ],
+        &[hog.clone(), fast.clone()],
+    );
+    let summary: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(summary["result"]["won"], 1, "{summary}");
+    let reason = summary["forfeits"][0]["reason"].as_str().unwrap();
+    assert!(reason.starts_with("exited (signal: "), "{reason}");
+    assert!(
+        reason.ends_with("likely over its memory limit of 256M"),
+        "{reason}"
+    );
+    assert_eq!(
+        summary["agents"][0]["exit"]["limit"],
+        "likely over its memory limit of 256M"
+    );
+
+    // Without the limit it is just greedy.
+    run_game(
+        &["--max-rounds", "1"],
+        &[dummy_agent("hog-free", "--hog-mb 300"), fast.clone()],
+    );
+    run_game_ending(1, &["--agent-mem", "lots"], &[hog, fast]);
+}
+
+#[test]
+fn first_player_is_chosen_without_moving_seats() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let agents = [agent.clone(), agent.clone(), agent];
+    let path = temp_dir().join("first.json");
+    let path = path.to_str().unwrap();
+    for (first, seat) in [("2", 2), ("dummy-agent-2", 1)] {
+        let output = run_game(
+            &["--first", first, "--output", "json", "--record", path],
+            &agents,
+        );
+        let summary: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(summary["first"], seat, "{summary}");
+        assert_eq!(summary["agents"][0]["name"], "dummy-agent-1");
+        let replay = Replay::load(path).unwrap();
+        assert_eq!(replay.initial_state.first_player, seat);
+        let turns: Vec<usize> = replay.actions.iter().take(3).map(|a| a.0).collect();
+        assert_eq!(turns, [seat, (seat + 1) % 3, (seat + 2) % 3]);
+        // Everyone gets the same number of turns.
+        let last = replay.states().last().unwrap();
+        assert!(last.is_finished());
+        let turns: Vec<usize> = last.players.iter().map(|p| p.record.turns).collect();
+        assert!(turns.iter().all(|&t| t == turns[0]), "{turns:?}");
+    }
+    run_game_ending(1, &["--first", "3"], &agents);
+    run_game_ending(1, &["--first", "nobody"], &agents);
+}
+
+#[test]
+fn handicaps_are_dealt_and_recorded() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let agents = [agent.clone(), agent];
+    let path = temp_dir().join("handicap.json");
+    let path = path.to_str().unwrap();
+    let output = run_game(
+        &[
+            "--handicap",
+            "1:coins=2w+1u:gold=1",
+            "--handicap",
+            "1:score=3",
+            "--handicap",
+            "0:time=+30s",
+            "--time-budget",
+            "60",
+            "--protocol",
+            "messages",
+            "--output",
+            "json",
+            "--record",
+            path,
+        ],
+        &agents,
+    );
+    let summary: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(
+        summary["agents"][0]["handicap"]["time_ms"], 30000,
+        "{summary}"
+    );
+    assert_eq!(summary["agents"][1]["handicap"]["score"], 3, "{summary}");
+    assert_eq!(summary["agents"][1]["handicap"]["coins"]["gold"], 1);
+    let replay = Replay::load(path).unwrap();
+    assert_eq!(replay.config.handicaps.len(), 2);
+    let start = &replay.initial_state;
+    assert_eq!(start.players[1].score, 3);
+    assert_eq!(start.players[1].mortal.to_string(), "1u+2w and 1 gold");
+    assert_eq!(start.coins.to_string(), "4r+3u+4g+2w+4k and 4 gold");
+    for state in replay.states() {
+        state.validate().unwrap();
+    }
+
+    for handicap in [
+        "1:coins=3w+2w",
+        "1:coins=5w",
+        "2:score=1",
+        "1:score=15",
+        "0:time=+1s",
+    ] {
+        run_game_ending(1, &["--handicap", handicap], &agents);
+    }
+}
+
+#[test]
+fn invalid_action_forfeits_the_game() {
+    let garbled = dummy_agent("garbled", r#"--answer '{"Purchase":{"deck":9,"card":9}}'"#);
+    let illegal = dummy_agent(
+        "illegal",
+        r#"--answer '{"type":"purchase","deck":1,"card":9}'"#,
+    );
+    let fast = dummy_agent("fast-invalid", "");
+    // Only garbage counts as a crash.
+    for (cheat, code) in [(garbled, 3), (illegal, 0)] {
+        let log = run_game_ending(code, &[], &[cheat.clone(), fast.clone()]);
+        let cheat = ai_name(&cheat);
+        let fast = ai_name(&fast);
+        assert!(log.contains(&format!("{cheat} forfeits")), "{log}");
+        assert!(log.contains(&format!("{cheat} forfeited: ")), "{log}");
+        assert!(log.contains(&format!("{fast} won")), "{log}");
+        assert!(!log.contains("Terminating game"), "{log}");
+    }
+
+    let log = run_game_ending(
+        2,
+        &["--on-invalid", "terminate"],
+        &[
+            dummy_agent(
+                "illegal-terminate",
+                r#"--answer '{"type":"purchase","deck":1,"card":9}'"#,
+            ),
+            fast,
+        ],
+    );
+    assert!(log.contains("did invalid action buy 2.9"), "{log}");
+    assert!(log.contains("Terminating game"), "{log}");
+}
+
+#[test]
+fn crashed_agent_forfeits_with_its_exit_status() {
+    let crashing = scripted_agent("crashing", "take r g u\ntake w k u\n", "--crash-after 2");
+    let fast = dummy_agent("fast-crashed", "");
+    let log = run_game_ending(3, &[], &[crashing.clone(), fast.clone()]);
+    let crashing_name = ai_name(&crashing);
+    assert_eq!(
+        log.matches(&format!("\n{crashing_name} did ")).count(),
+        2,
+        "{log}"
+    );
+    assert!(
+        log.contains(&format!("{crashing_name} exited (status 7) while thinking")),
+        "{log}"
+    );
+    assert!(log.contains(&format!("{crashing_name} forfeits")), "{log}");
+    assert!(log.contains(&format!("{} won", ai_name(&fast))), "{log}");
+
+    let output = run_game_ending(3, &["--output", "json"], &[crashing, fast]);
+    let summary: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(summary["result"]["won"], 1);
+    assert_eq!(summary["forfeits"][0]["player"], 0);
+    let exit = &summary["agents"][0]["exit"];
+    assert_eq!(exit["status"], "status 7", "{summary}");
+    assert_eq!(
+        exit["stderr"],
+        serde_json::json!(["crashing after 2 moves"])
+    );
+    assert!(summary["agents"][1].get("exit").is_none(), "{summary}");
+}
+
+#[test]
+fn forfeit_is_recorded_in_the_replay() {
+    let path = temp_dir().join("forfeit.json");
+    let illegal = scripted_agent("illegal-recorded", "take r g u\n", "--invalid-after 1");
+    let other = scripted_agent("other-recorded", "take w k u\n", "");
+    run_game(
+        &["--record", path.to_str().unwrap(), "--first", "0"],
+        &[illegal, other],
+    );
+    let replay = game_def::Replay::load(path.to_str().unwrap()).unwrap();
+    assert_eq!(replay.invalid.len(), 1);
+    assert_eq!(replay.forfeits.len(), 1);
+    assert_eq!(replay.forfeits[0].player, 0);
+    assert!(
+        replay.forfeits[0]
+            .reason
+            .starts_with("did invalid action buy 1.9"),
+        "{}",
+        replay.forfeits[0].reason
+    );
+    let played = |line: &str| line.parse::<game_def::Action>().unwrap();
+    assert_eq!(
+        replay.actions,
+        [
+            (0, played("take r g u")),
+            (1, played("take w k u")),
+            (0, game_def::Action::Resign)
+        ]
+    );
+    replay.verify().unwrap();
+}
+
+#[test]
+fn rejected_action_can_be_retried() {
+    let fast = dummy_agent("fast-retried", "");
+    for protocol in ["states", "messages"] {
+        let wrong = dummy_agent(&format!("wrong-first-{protocol}"), "--wrong-first");
+        let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+            .args(["--invalid-retries", "1", "--protocol", protocol])
+            .args([&wrong, &fast])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        let log = String::from_utf8(output.stdout).unwrap();
+        let wrong = ai_name(&wrong);
+        assert!(
+            log.contains(&format!("{wrong} did invalid action buy 1.9: ")),
+            "{log}"
+        );
+        assert!(log.contains(", asking again"), "{log}");
+        assert!(!log.contains("forfeit"), "{log}");
+        assert!(log.contains("Game finished"), "{log}");
+        if protocol == "messages" {
+            let errors = String::from_utf8(output.stderr).unwrap();
+            assert!(errors.contains("buy 1.9 was rejected: "), "{errors}");
+        }
+    }
+
+    let wrong = dummy_agent("wrong-first-no-retries", "--wrong-first");
+    let log = run_game(&[], &[wrong.clone(), fast]);
+    assert!(
+        log.contains(&format!("{} forfeits", ai_name(&wrong))),
+        "{log}"
+    );
+}
+
+#[test]
+fn retries_share_the_time_of_the_move() {
+    let wrong = dummy_agent("wrong-first-slow", "--wrong-first --sleep-ms 300");
+    let fast = dummy_agent("fast-retried-slow", "");
+    let log = run_game(
+        &["--invalid-retries", "1", "--move-timeout", "0.5"],
+        &[wrong.clone(), fast],
+    );
+    let wrong = ai_name(&wrong);
+    assert!(log.contains(", asking again"), "{log}");
+    assert!(log.contains(&format!("{wrong} timed out after")), "{log}");
+    assert!(log.contains(&format!("{wrong} forfeits")), "{log}");
+}
+
+#[test]
+fn agent_stderr_is_labelled() {
+    let chatty = dummy_agent("chatty", "--chatter 2");
+    let fast = dummy_agent("quiet", "");
+    let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .args(["--max-rounds", "5"])
+        .args([&chatty, &fast])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let errors = String::from_utf8(output.stderr).unwrap();
+    let label = "[chatty] ";
+    assert!(
+        errors.contains(&format!("{label}move 1 line 0\n")),
+        "{errors}"
+    );
+    assert!(
+        errors.contains(&format!("{label}move 5 line 1\n")),
+        "{errors}"
+    );
+    assert!(
+        errors.lines().all(|line| line.starts_with(label)),
+        "{errors}"
+    );
+}
+
+#[test]
+fn agent_stderr_goes_to_log_files() {
+    let dir = temp_dir().join("agent-logs");
+    let chatty = dummy_agent("chatty-logged", "--chatter 1");
+    let fast = dummy_agent("quiet-logged", "");
+    let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .args(["--max-rounds", "3", "--agent-log-dir"])
+        .args([&dir, &chatty, &fast])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert!(output.stderr.is_empty(), "{output:?}");
+    let log = std::fs::read_to_string(dir.join("0-chatty-logged.log")).unwrap();
+    assert_eq!(log, "move 1 line 0\nmove 2 line 0\nmove 3 line 0\n");
+    let log = std::fs::read_to_string(dir.join("1-quiet-logged.log")).unwrap();
+    assert_eq!(log, "");
+}
+
+#[test]
+fn agents_are_told_what_the_others_played() {
+    let dir = temp_dir().join("played-logs");
+    let path = temp_dir().join("played.json");
+    let follower = dummy_agent("follower", "--follow");
+    let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .args(["--max-rounds", "3", "--first", "1", "--record"])
+        .arg(&path)
+        .arg("--agent-log-dir")
+        .arg(&dir)
+        .args(["--protocol", "messages"])
+        .arg(&follower)
+        .arg(&follower)
+        .args(["--protocol", "messages"])
+        .arg(&follower)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let replay = Replay::load(path.to_str().unwrap()).unwrap();
+    for seat in 0..3 {
+        // Each action before the move request that follows it.
+        let expected: String = replay
+            .actions
+            .iter()
+            .filter(|&&(player, _)| seat != 1 || player == seat)
+            .map(|(player, action)| match *player == seat {
+                true => "asked to move\n".to_owned(),
+                false => format!("player {player} played {action}\n"),
+            })
+            .collect();
+        let log = std::fs::read_to_string(dir.join(format!("{seat}-follower-{}.log", seat + 1)));
+        assert_eq!(log.unwrap(), expected, "seat {seat}");
+    }
+}
+
+#[test]
+fn chatty_agent_does_not_block() {
+    // Far more than a pipe holds, written before each action.
+    let chatty = dummy_agent("very-chatty", "--chatter 20000");
+    let fast = dummy_agent("quiet-blocked", "");
+    let log = run_game(
+        &["--max-rounds", "3", "--move-timeout", "10"],
+        &[chatty, fast],
+    );
+    assert!(!log.contains("timed out"), "{log}");
+    assert!(log.contains("Game finished"), "{log}");
+}
+
+#[test]
+fn stuck_agent_is_killed_when_the_runner_exits() {
+    let pid_file = temp_dir().join("stuck.pid");
+    let stuck = dummy_agent(
+        "stuck",
+        &format!("--sleep-ms 1000000000 --pid-file '{}'", pid_file.display()),
+    );
+    let fast = dummy_agent("fast-unstuck", "");
+    let log = run_game(&["--move-timeout", "0.3"], &[stuck, fast]);
+    assert!(log.contains("Game finished"), "{log}");
+    let pid = std::fs::read_to_string(pid_file).unwrap();
+    assert!(
+        !std::path::Path::new(&format!("/proc/{pid}")).exists(),
+        "{pid} is still running"
+    );
+}
+
+#[test]
+fn agents_take_quoted_arguments() {
+    let pid_file = temp_dir().join("quoted argument.pid");
+    let command = format!(
+        "{} --sleep-ms 1 --pid-file '{}'",
+        env!("CARGO_BIN_EXE_dummy-agent"),
+        pid_file.display()
+    );
+    let fast = dummy_agent("fast-quoted", "");
+    let log = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .arg(&command)
+        .arg(&fast)
+        .output()
+        .unwrap();
+    assert!(log.status.success(), "{log:?}");
+    let log = String::from_utf8(log.stdout).unwrap();
+    assert!(log.contains("dummy-agent did "), "{log}");
+    assert!(pid_file.exists());
+
+    let log = run_game_ending(1, &[], &["'unterminated".into(), fast]);
+    assert!(
+        log.contains("`'unterminated` is not a valid command line"),
+        "{log}"
+    );
+}
+
+#[test]
+fn agent_path_may_have_spaces() {
+    std::fs::create_dir_all(temp_dir().join("with space")).unwrap();
+    let spaced = dummy_agent("with space/spaced-agent", "");
+    let fast = dummy_agent("fast-spaced", "");
+    let log = run_game(&[], &[spaced.clone(), fast.clone()]);
+    assert!(log.contains("spaced-agent did "), "{log}");
+
+    let quoted = PathBuf::from(format!("\"{}\" --sleep-ms 1", spaced.display()));
+    let log = run_game(&[], &[quoted, fast]);
+    assert!(log.contains("spaced-agent did "), "{log}");
+}
+
+#[test]
+fn agents_go_by_their_names() {
+    let path = temp_dir().join("named.json");
+    let fast = dummy_agent("fast-named", "");
+    let log = run_game(
+        &["--record", path.to_str().unwrap(), "--name", "alice"],
+        &[fast.clone(), fast.clone(), fast],
+    );
+    assert!(log.contains("\nalice did "), "{log}");
+    assert!(log.contains("\nfast-named-1 did "), "{log}");
+    assert!(log.contains("\nfast-named-2 did "), "{log}");
+    let replay = Replay::load(path.to_str().unwrap()).unwrap();
+    let names: Vec<_> = replay
+        .initial_state
+        .players
+        .iter()
+        .map(|p| p.display_name.as_str())
+        .collect();
+    assert_eq!(names, ["alice", "fast-named-1", "fast-named-2"]);
+}
+
+#[test]
+fn tournament_uses_agent_names() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let summary = tournament(
+        "named",
+        &["--games", "1", "--name", "first"],
+        &[agent.clone(), agent.clone(), agent],
+    );
+    let names: Vec<_> = summary["standings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["name"].as_str().unwrap().to_owned())
+        .collect();
+    let command = env!("CARGO_BIN_EXE_dummy-agent");
+    assert_eq!(
+        names,
+        [
+            "first".to_owned(),
+            format!("{command}-1"),
+            format!("{command}-2")
+        ]
+    );
+}
+
+/// Plays for `moves` moves after sleeping on each, then drops the connection.
+struct Remote {
+    moves: usize,
+    sleep: Duration,
+}
+
+impl AgentHandler for Remote {
+    fn act(&mut self, observation: Observation) -> anyhow::Result<Action> {
+        std::thread::sleep(self.sleep);
+        self.moves = self.moves.checked_sub(1).context("Leaving")?;
+        Ok(RandomAgent.act(&observation.state))
+    }
+}
+
+fn remote_agent(stream: std::net::TcpStream, name: &str, moves: usize, sleep: Duration) {
+    _ = game_def::run_agent_over_tcp(stream, name, false, false, Remote { moves, sleep });
+}
+
+#[test]
+fn runner_connects_to_tcp_agent() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("tcp-connect:{}", listener.local_addr().unwrap());
+    let remote = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        remote_agent(stream, "remote", usize::MAX, Duration::ZERO);
+    });
+    let fast = dummy_agent("fast-tcp", "");
+    let log = run_game(&[], &[address.into(), fast]);
+    remote.join().unwrap();
+    assert!(log.contains("\nremote did "), "{log}");
+    assert!(log.contains("Game finished"), "{log}");
+}
+
+#[test]
+fn tcp_agent_that_leaves_forfeits() {
+    // Found free, then left for the runner to listen on.
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let remote = std::thread::spawn(move || {
+        let stream = loop {
+            match std::net::TcpStream::connect(("127.0.0.1", port)) {
+                Ok(stream) => break stream,
+                Err(_) => std::thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        remote_agent(stream, "leaving", 2, Duration::ZERO);
+    });
+    let fast = dummy_agent("fast-tcp-left", "");
+    let log = run_game_ending(
+        3,
+        &["--name", "renamed"],
+        &[format!("tcp:127.0.0.1:{port}").into(), fast],
+    );
+    remote.join().unwrap();
+    assert_eq!(log.matches("\nrenamed did ").count(), 2, "{log}");
+    assert!(log.contains("renamed closed its output"), "{log}");
+    assert!(log.contains("renamed forfeits"), "{log}");
+    assert!(log.contains("fast-tcp-left won"), "{log}");
+}
+
+#[test]
+fn tcp_agent_times_out() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("tcp-connect:{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        remote_agent(stream, "slow-remote", 1, Duration::from_secs(2));
+    });
+    let fast = dummy_agent("fast-tcp-slow", "");
+    let log = run_game(&["--move-timeout", "0.2"], &[address.into(), fast]);
+    assert!(log.contains("slow-remote timed out after"), "{log}");
+    assert!(log.contains("slow-remote forfeits"), "{log}");
+}
+
+/// A port that was free a moment ago, for the runner to listen on.
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// Plays as `name` over WebSocket like [`remote_agent`], once the runner
+/// listens on `port`.
+fn websocket_agent(port: u16, name: &str, moves: usize, sleep: Duration) {
+    let client = loop {
+        match game_def::websocket::Client::connect(&format!("127.0.0.1:{port}"), "/agent") {
+            Ok(client) => break client,
+            Err(_) => std::thread::sleep(Duration::from_millis(10)),
+        }
+    };
+    _ = game_def::websocket::run_agent(client, name, false, Remote { moves, sleep });
+}
+
+#[test]
+fn websocket_agents_play() {
+    let port = free_port();
+    let remotes: Vec<_> = ["browser", "node"]
+        .into_iter()
+        .map(|name| {
+            std::thread::spawn(move || websocket_agent(port, name, usize::MAX, Duration::ZERO))
+        })
+        .collect();
+    let fast = dummy_agent("fast-ws", "");
+    let address = format!("ws:127.0.0.1:{port}/agent");
+    let log = run_game(&[], &[address.clone().into(), address.into(), fast]);
+    for remote in remotes {
+        remote.join().unwrap();
+    }
+    assert!(log.contains("\nbrowser did "), "{log}");
+    assert!(log.contains("\nnode did "), "{log}");
+    assert!(log.contains("Game finished"), "{log}");
+}
+
+#[test]
+fn websocket_agent_that_leaves_forfeits() {
+    let port = free_port();
+    let remote = std::thread::spawn(move || websocket_agent(port, "leaving", 2, Duration::ZERO));
+    let fast = dummy_agent("fast-ws-left", "");
+    let log = run_game_ending(
+        3,
+        &["--reconnect-grace", "0.2"],
+        &[format!("ws:127.0.0.1:{port}/agent").into(), fast],
+    );
+    remote.join().unwrap();
+    assert_eq!(log.matches("\nleaving did ").count(), 2, "{log}");
+    assert!(log.contains("leaving disconnected"), "{log}");
+    assert!(log.contains("leaving forfeits"), "{log}");
+    assert!(log.contains("fast-ws-left won"), "{log}");
+}
+
+#[test]
+fn websocket_agent_can_reconnect() {
+    let port = free_port();
+    let remote = std::thread::spawn(move || {
+        websocket_agent(port, "flaky", 2, Duration::ZERO);
+        websocket_agent(port, "flaky", usize::MAX, Duration::ZERO);
+    });
+    let fast = dummy_agent("fast-ws-flaky", "");
+    let log = run_game(
+        &["--reconnect-grace", "10"],
+        &[format!("ws:127.0.0.1:{port}/agent").into(), fast],
+    );
+    remote.join().unwrap();
+    assert!(log.contains("flaky reconnected"), "{log}");
+    assert!(!log.contains("flaky forfeits"), "{log}");
+    assert!(log.contains("Game finished"), "{log}");
+}
+
+/// The body of a GET for `path` on the runner's spectator server.
+fn http_get(port: u16, path: &str) -> std::io::Result<String> {
+    use std::io::Read;
+    let mut stream = std::net::TcpStream::connect(("127.0.0.1", port))?;
+    write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n")?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("HTTP/1.1 200 OK"), "{response}");
+    Ok(body.to_owned())
+}
+
+#[test]
+fn spectators_see_the_game() {
+    let port = free_port();
+    let slow = dummy_agent("slow-spectated", "--sleep-ms 20");
+    let runner = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .args(["--serve", &format!("127.0.0.1:{port}"), "--first", "0"])
+        .args([&slow, &slow])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let moves = loop {
+        match http_get(port, "/moves") {
+            Ok(moves) if moves != "[]" => break moves,
+            _ => std::thread::sleep(Duration::from_millis(10)),
+        }
+    };
+    let moves: Vec<serde_json::Value> = serde_json::from_str(&moves).unwrap();
+    assert_eq!(moves[0]["player"], 0);
+    let state: game_def::State = serde_json::from_str(&http_get(port, "/state").unwrap()).unwrap();
+    assert_eq!(state.players.len(), 2);
+    assert_eq!(state.players[0].display_name, "slow-spectated-1");
+    assert!(http_get(port, "/").unwrap().contains("<html>"));
+    let output = runner.wait_with_output().unwrap();
+    let log = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        log.contains(&format!("Spectators can watch on http://127.0.0.1:{port}")),
+        "{log}"
+    );
+    assert!(log.contains("Game finished"), "{log}");
+}
+
+#[test]
+fn quiet_game_skips_the_board() {
+    let fast = dummy_agent("fast-quiet", "");
+    let loud = run_game(&["--seed", "7"], &[fast.clone(), fast.clone()]);
+    let quiet = run_game(&["--seed", "7", "--quiet"], &[fast.clone(), fast]);
+    assert!(loud.contains("AI Thinking..."), "{loud}");
+    assert!(!quiet.contains("AI Thinking..."), "{quiet}");
+    assert!(quiet.len() * 2 < loud.len(), "{quiet}");
+    assert!(quiet.contains("\nfast-quiet-1 did "), "{quiet}");
+    assert!(quiet.contains("Game finished"), "{quiet}");
+}
+
+#[test]
+fn json_output_summarizes_the_game() {
+    let fast = dummy_agent("fast-json", "");
+    let garbled = dummy_agent("garbled-json", "--answer nonsense");
+    let output = run_game(
+        &["--output", "json", "--seed", "7"],
+        &[fast.clone(), fast.clone()],
+    );
+    assert_eq!(output.lines().count(), 1, "{output}");
+    let summary: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(summary["seed"], 7);
+    assert_eq!(summary["agents"][0]["name"], "fast-json-1");
+    assert_eq!(summary["agents"][1]["agent"], fast.to_str().unwrap());
+    assert!(summary["agents"][0]["think_ms"].is_u64());
+    assert!(summary["turns"].as_u64().unwrap() > 0);
+    assert!(summary["result"].get("won").is_some() || summary["result"].get("draw").is_some());
+    assert!(summary["terminated"].is_null());
+    assert_eq!(summary["forfeits"], serde_json::json!([]));
+
+    let output = run_game_ending(3, &["--output", "json"], &[garbled, fast]);
+    let summary: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(summary["result"]["won"], 1);
+    assert_eq!(summary["forfeits"][0]["player"], 0);
+    let reason = summary["forfeits"][0]["reason"].as_str().unwrap();
+    assert!(
+        reason.starts_with("sent something that is not an action"),
+        "{reason}"
+    );
+}
+
+/// A saved game of `alice` and `bob` after a few moves, played by `agents`.
+fn saved_game(
+    name: &str,
+    agents: &[PathBuf],
+    change: impl FnOnce(&mut serde_json::Value),
+) -> PathBuf {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(4);
+    let mut state = State::setup(&["alice", "bob"], GameConfig::default(), &mut rng);
+    let mut history = vec![];
+    for _ in 0..6 {
+        let action = state.legal_actions().next().unwrap();
+        history.push((state.turn, action.clone()));
+        state.run(action).unwrap();
+    }
+    let mut state = serde_json::to_value(&state).unwrap();
+    change(&mut state);
+    let saved = serde_json::json!({
+        "state": state,
+        "agents": agents,
+        "seed": 4,
+        "history": history,
+    });
+    let path = temp_dir().join(name);
+    std::fs::write(&path, saved.to_string()).unwrap();
+    path
+}
+
+#[test]
+fn saved_game_is_resumed() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let path = saved_game("resume.json", &[agent.clone(), agent.clone()], |_| ());
+    let path = path.to_str().unwrap();
+    for load in ["--load-state", "--load-redacted"] {
+        let output = run_game(&[load, path, "--output", "json"], &[]);
+        let summary: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(summary["agents"][0]["name"], "alice");
+        assert_eq!(summary["agents"][1]["agent"], agent.to_str().unwrap());
+        assert!(summary["turns"].as_u64().unwrap() > 6, "{summary}");
+        assert!(summary["terminated"].is_null(), "{summary}");
+    }
+
+    let log = run_game_ending(
+        1,
+        &["--load-state", path],
+        &[agent.clone(), agent.clone(), agent],
+    );
+    assert!(
+        log.contains("The saved game has 2 players, but 3 agents were given"),
+        "{log}"
+    );
+}
+
+#[test]
+fn resumed_game_records_the_moves_after_resuming() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let path = saved_game("resume-record.json", &[agent.clone(), agent], |_| ());
+    let record = temp_dir().join("resume-record-replay.json");
+    let args = [
+        "--load-state",
+        path.to_str().unwrap(),
+        "--record",
+        record.to_str().unwrap(),
+        "--max-rounds",
+        "8",
+    ];
+    let log = run_game(&args, &[]);
+    let replay = Replay::load(record.to_str().unwrap()).unwrap();
+    let states = replay.checked_states().unwrap();
+    assert_eq!(states.last().unwrap().result(), replay.result);
+    assert_eq!(replay.actions.len(), replay.think_ms.len());
+    let saved: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(
+        serde_json::to_value(&replay.initial_state).unwrap(),
+        saved["state"]
+    );
+    let first = replay.actions.first().unwrap();
+    let name = ["alice", "bob"][first.0];
+    assert!(log.contains(&format!("{name} did {}", first.1)), "{log}");
+}
+
+#[test]
+fn broken_saved_game_is_refused() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let path = saved_game("broken.json", &[agent.clone(), agent], |state| {
+        state["coins"]["red"] = 9.into();
+    });
+    let log = run_game_ending(1, &["--load-state", path.to_str().unwrap()], &[]);
+    assert!(log.contains("The saved state breaks the rules: "), "{log}");
+    let log = run_game_ending(1, &["--load-state", "/nonexistent/saved.json"], &[]);
+    assert!(
+        log.contains("Failed to read /nonexistent/saved.json"),
+        "{log}"
+    );
+}
+
+/// Sends SIGINT to `child`, like Ctrl+C in its terminal.
+fn interrupt(child: &std::process::Child) {
+    let status = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn interrupt_saves_the_game() {
+    let dir = temp_dir().join("interrupt");
+    std::fs::create_dir_all(&dir).unwrap();
+    let slow = dummy_agent("slow-interrupt", "--sleep-ms 50");
+    let child = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .current_dir(&dir)
+        .args([&slow, &slow])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(500));
+    interrupt(&child);
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+    let log = String::from_utf8(output.stdout).unwrap();
+    let path = log
+        .lines()
+        .find_map(|line| line.strip_prefix("Interrupted, saved to "))
+        .and_then(|rest| rest.split_once(','))
+        .unwrap_or_else(|| panic!("{log}"))
+        .0;
+    assert!(!log.contains("forfeits"), "{log}");
+
+    let saved: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.join(path)).unwrap()).unwrap();
+    let played = saved["history"].as_array().unwrap().len();
+    assert!(played > 0, "{saved}");
+    let fast = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .current_dir(&dir)
+        .args(["--load-state", path, "--output", "json"])
+        .args([&fast, &fast])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0), "{output:?}");
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(
+        summary["turns"].as_u64().unwrap() as usize > played,
+        "{summary}"
+    );
+}
+
+#[test]
+fn second_interrupt_quits_at_once() {
+    let slow = dummy_agent("slower-interrupt", "--sleep-ms 5000");
+    let started = std::time::Instant::now();
+    let child = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .current_dir(temp_dir())
+        .args([&slow, &slow])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(300));
+    interrupt(&child);
+    std::thread::sleep(Duration::from_millis(100));
+    interrupt(&child);
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(130), "{output:?}");
+    assert!(started.elapsed() < Duration::from_secs(3));
+}
+
+#[test]
+fn delay_is_not_thinking_time() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let output = run_game(
+        &["--delay", "50ms", "--max-rounds", "3", "--output", "json"],
+        &[agent.clone(), agent],
+    );
+    let summary: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(summary["turns"], 6);
+    assert!(summary["duration_ms"].as_u64().unwrap() >= 300, "{summary}");
+    for agent in summary["agents"].as_array().unwrap() {
+        assert!(agent["think_ms"].as_u64().unwrap() < 150, "{summary}");
+    }
+}
+
+#[test]
+fn move_times_are_summed_up() {
+    let slow = dummy_agent("slow-timing", "--sleep-ms 200");
+    let fast = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let path = temp_dir().join("timing.json");
+    let path = path.to_str().unwrap();
+    let args = ["--max-rounds", "3", "--delay", "100ms", "--record", path];
+    let log = run_game(&args, &[slow.clone(), fast.clone()]);
+    let name = ai_name(&slow);
+    let thoughts = log
+        .lines()
+        .filter(|l| l.starts_with(&format!("{name} did ")))
+        .map(|l| l.rsplit_once(", thought for ").unwrap().1);
+    for thought in thoughts {
+        let ms: u64 = thought.strip_suffix("ms").unwrap().parse().unwrap();
+        assert!((200..300).contains(&ms), "{log}");
+    }
+    let table = log.lines().skip_while(|l| !l.starts_with("Time ")).nth(1);
+    assert!(table.unwrap().starts_with(&format!("{name}  ")), "{log}");
+
+    let summary = run_game(&["--max-rounds", "3", "--output", "json"], &[slow, fast]);
+    let summary: serde_json::Value = serde_json::from_str(&summary).unwrap();
+    let timing = &summary["agents"][0]["timing"];
+    assert_eq!(timing["moves"], 3);
+    assert_eq!(summary["agents"][0]["move_ms"].as_array().unwrap().len(), 3);
+    assert_eq!(timing["total_ms"], summary["agents"][0]["think_ms"]);
+    let mean = timing["mean_ms"].as_u64().unwrap();
+    assert!((200..300).contains(&mean), "{summary}");
+    assert!(timing["max_ms"].as_u64().unwrap() >= mean);
+    assert!(summary["agents"][1]["timing"]["mean_ms"].as_u64().unwrap() < 100);
+
+    let timings = Replay::load(path).unwrap().timings();
+    assert_eq!(timings[0].moves, 3);
+    assert!((200..300).contains(&timings[0].mean_ms), "{timings:?}");
+}
+
+#[test]
+fn four_players_play_to_the_end() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let output = run_game(
+        &["--players", "4", "--output", "json", "--paranoid"],
+        &[agent.clone(), agent.clone(), agent.clone(), agent.clone()],
+    );
+    let summary: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert!(summary["terminated"].is_null(), "{summary}");
+    assert_eq!(summary["rounds"].as_u64().unwrap() * 4, summary["turns"]);
+    let places: Vec<u64> = summary["agents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|a| a["place"].as_u64().unwrap())
+        .collect();
+    assert_eq!(places.len(), 4);
+    assert!(places.contains(&1), "{places:?}");
+    assert!(places.iter().all(|p| (1..=4).contains(p)), "{places:?}");
+}
+
+#[test]
+fn player_count_is_checked() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .args(["--players", "3"])
+        .args([&agent, &agent])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    let log = String::from_utf8(output.stdout).unwrap();
+    assert!(log.contains("--players 3 but 2 agents were given"), "{log}");
+    let log = run_game_ending(1, &[], &vec![agent; 5]);
+    assert!(
+        log.contains("Splendor is for 2 to 4 players, not 5"),
+        "{log}"
+    );
+}
+
+#[test]
+fn swiss_tournament_pairs_without_rematches() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let agents = vec![agent; 5];
+    let path = temp_dir().join("swiss-checkpoint.json");
+    let path = path.to_str().unwrap();
+    let args = [
+        "--format",
+        "swiss",
+        "--rounds",
+        "3",
+        "--games",
+        "1",
+        "--seed",
+        "4",
+        "--checkpoint",
+        path,
+    ];
+    let straight = tournament("swiss", &args, &agents);
+    let games = straight["games"].as_array().unwrap();
+    // Two pairs a round, and one agent sits out.
+    assert_eq!(games.len(), 6);
+    let mut met = std::collections::HashSet::new();
+    for game in games {
+        let mut pair: Vec<_> = game["seats"].as_array().unwrap().clone();
+        pair.sort_by_key(|i| i.as_u64());
+        assert!(met.insert(serde_json::json!(pair)), "{pair:?} met twice");
+    }
+    let standings = straight["standings"].as_array().unwrap();
+    let total = |key: &str| {
+        standings
+            .iter()
+            .map(|s| s[key].as_f64().unwrap())
+            .sum::<f64>()
+    };
+    assert_eq!(total("byes"), 3.0);
+    assert_eq!(total("points"), 9.0);
+    for s in standings {
+        assert!(s["byes"].as_u64().unwrap() <= 1, "{s}");
+    }
+
+    // As if killed before pairing the second round.
+    let mut checkpoint: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    checkpoint["played"].as_array_mut().unwrap().truncate(2);
+    checkpoint["schedule"].as_array_mut().unwrap().truncate(2);
+    checkpoint["swiss"]["byes"]
+        .as_array_mut()
+        .unwrap()
+        .truncate(1);
+    std::fs::write(path, checkpoint.to_string()).unwrap();
+    let resumed = tournament("swiss-resumed", &["--resume", path], &agents);
+    assert_eq!(resumed, straight);
+}
+
+#[test]
+fn tournament_of_three_players() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let summary = tournament(
+        "three-players",
+        &["--players", "3", "--games", "3", "--seed", "3"],
+        &vec![agent; 4],
+    );
+    let games = summary["games"].as_array().unwrap();
+    // 4 groups of 3, each seated 3 ways.
+    assert_eq!(games.len(), 12);
+    assert_eq!(games[1]["seats"], serde_json::json!([1, 2, 0]));
+    for game in games {
+        assert_eq!(game["places"].as_array().unwrap().len(), 3);
+    }
+    for s in summary["standings"].as_array().unwrap() {
+        let count = |key: &str| s[key].as_u64().unwrap();
+        assert_eq!(count("games"), 9);
+        assert_eq!(count("wins") + count("losses") + count("draws"), 9);
+        let place = s["average_place"].as_f64().unwrap();
+        assert!((1.0..=3.0).contains(&place), "{place}");
+    }
+}
+
+/// `events` with the think times, which change every run, zeroed.
+fn normalize_events(events: &str) -> String {
+    events
+        .lines()
+        .map(|line| {
+            let mut event: serde_json::Value = serde_json::from_str(line).unwrap();
+            if let Some(think) = event.get_mut("think_ms") {
+                *think = 0.into();
+            }
+            event.to_string() + "\n"
+        })
+        .collect()
+}
+
+/// Run with `UPDATE_GOLDEN=1` to write the file again after changing the
+/// events on purpose.
+#[test]
+fn events_match_the_golden_file() {
+    let path = temp_dir().join("events.ndjson");
+    let wrong = dummy_agent("events-wrong-first", "--wrong-first");
+    let fast = dummy_agent("events-fast", "");
+    run_game(
+        &[
+            "--seed",
+            "5",
+            "--max-rounds",
+            "3",
+            "--invalid-retries",
+            "1",
+            "--emit-events",
+            path.to_str().unwrap(),
+        ],
+        &[wrong, fast],
+    );
+    let events = normalize_events(&std::fs::read_to_string(&path).unwrap());
+    let golden = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/events.ndjson");
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(golden, &events).unwrap();
+    }
+    let expected = std::fs::read_to_string(golden).unwrap();
+    for (i, (line, expected)) in events.lines().zip(expected.lines()).enumerate() {
+        assert_eq!(line, expected, "line {} of {golden}", i + 1);
+    }
+    assert_eq!(events.lines().count(), expected.lines().count());
+}
+
+#[test]
+fn boards_are_plain_unless_asked_for_color() {
+    let dummy = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let agents = [dummy.clone(), dummy];
+    let plain = run_game(&["--max-rounds", "2"], &agents);
+    assert!(plain.contains("Deck 1:"), "{plain}");
+    assert!(!plain.contains('┌'), "{plain}");
+
+    let boxed = run_game(&["--max-rounds", "2", "--color", "never"], &agents);
+    assert!(
+        boxed.contains("Deck 1, ") && boxed.contains("┌ 1.0 "),
+        "{boxed}"
+    );
+    assert!(!boxed.contains('\x1b'), "{boxed}");
+
+    let colored = run_game(&["--max-rounds", "2", "--color", "always"], &agents);
+    assert!(colored.contains("\x1b["), "{colored}");
+    run_game_ending(1, &["--color", "sometimes"], &agents);
+}
+
+#[test]
+fn compact_render_shows_changes_between_boards() {
+    let dummy = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let agents = [dummy.clone(), dummy];
+    let boards = |log: &str| log.lines().filter(|l| *l == "Deck 1:").count();
+    let full = run_game(&["--max-rounds", "3"], &agents);
+    // The deal and 6 moves, too few to fill a hand and discard.
+    assert_eq!(boards(&full), 7, "{full}");
+
+    let compact = run_game(&["--max-rounds", "3", "--render", "compact"], &agents);
+    // The deal and the end.
+    assert_eq!(boards(&compact), 2, "{compact}");
+    assert_eq!(compact.matches("   Scores: ").count(), 5, "{compact}");
+    assert!(
+        compact.contains("   Bank: ") && compact.contains('→'),
+        "{compact}"
+    );
+
+    let args = [
+        "--max-rounds",
+        "3",
+        "--render",
+        "compact",
+        "--full-board-every",
+        "2",
+    ];
+    let log = run_game(&args, &agents);
+    assert_eq!(boards(&log), 4, "{log}");
+    run_game_ending(1, &["--render", "tiny"], &agents);
+}
+
+#[test]
+fn games_are_played_with_the_cards_file() {
+    let cards = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/game-def/fixtures/cards/teaching.json"
+    );
+    let dummy = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let agents = [dummy.clone(), dummy];
+    let path = temp_dir().join("cards.json");
+    let path = path.to_str().unwrap();
+    let args = ["--cards", cards, "--max-rounds", "5", "--record", path];
+    let log = run_game(&args, &agents);
+    assert!(log.contains("Cards from teaching.json ("), "{log}");
+    let replay = Replay::load(path).unwrap();
+    let set = replay.config.cards.clone().unwrap();
+    assert_eq!(set.name, "teaching.json");
+    replay.checked_states().unwrap();
+    let ids = set
+        .decks
+        .values()
+        .flatten()
+        .map(|c| c.id())
+        .collect::<Vec<_>>();
+    assert!(replay
+        .initial_state
+        .decks
+        .values()
+        .flatten()
+        .all(|c| ids.contains(&c.id())));
+
+    let summary = tournament("cards", &["--cards", cards, "--games", "1"], &agents);
+    assert_eq!(summary["cards"]["name"], "teaching.json");
+    assert_eq!(summary["cards"]["hash"], set.hash);
+    let summary = tournament("no-cards", &["--games", "1"], &agents);
+    assert!(summary.get("cards").is_none());
+
+    let broken = temp_dir().join("broken-cards.json");
+    std::fs::write(
+        &broken,
+        r#"{"tiers": [[], [{"color": "p", "cost": "1r"}], []], "nobles": []}"#,
+    )
+    .unwrap();
+    let log = run_game_ending(1, &["--cards", broken.to_str().unwrap()], &agents);
+    assert!(
+        log.contains("Card 1 of tier 2: Unknown color \"p\""),
+        "{log}"
+    );
+}
+
+#[test]
+fn report_adds_up_the_events_of_the_game() {
+    let dummy = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let path = temp_dir().join("report.json");
+    let path = path.to_str().unwrap();
+    let args = ["--seed", "2", "--max-rounds", "40", "--record", path];
+    let summary = run_game(
+        &[&args[..], &["--output", "json"]].concat(),
+        &[dummy.clone(), dummy.clone()],
+    );
+    let summary: serde_json::Value = serde_json::from_str(&summary).unwrap();
+    let replay = Replay::load(path).unwrap();
+    let mut state = replay.initial_state.clone();
+    let mut taken = [0; 2];
+    let mut gold = [0; 2];
+    let mut nobels = vec![];
+    for (ply, (_, action)) in replay.actions.iter().enumerate() {
+        for event in state.run(action.clone()).unwrap() {
+            match event {
+                GameEvent::CoinsTaken { player, coins } => taken[player] += coins.sum(),
+                GameEvent::CardPurchased {
+                    player, payment, ..
+                } => gold[player] += payment.gold,
+                GameEvent::NobelVisited { player, nobel, .. } => nobels
+                    .push(serde_json::json!({ "player": player, "nobel": nobel, "move": ply + 1 })),
+                _ => (),
+            }
+        }
+    }
+    assert!(!nobels.is_empty());
+    assert_eq!(summary["nobels"], serde_json::json!(nobels));
+    for (i, agent) in summary["agents"].as_array().unwrap().iter().enumerate() {
+        assert_eq!(agent["coins_taken"], taken[i]);
+        assert_eq!(agent["wilds_spent"], gold[i]);
+        assert_eq!(agent["reserves"], 0);
+    }
+
+    let log = run_game(&args, &[dummy.clone(), dummy]);
+    let header = log.lines().position(|l| l.starts_with("Player ")).unwrap();
+    let lines: Vec<&str> = log.lines().skip(header).take(4).collect();
+    assert!(
+        lines[0].ends_with("Tier 1/2/3  Taken  Reserved  Gold used  Turns"),
+        "{log}"
+    );
+    let name = ai_name(&PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent")));
+    assert!(lines[1].starts_with(&format!("{name}-1  ")), "{log}");
+    assert!(
+        lines[3].contains(" rounds, ") && lines[3].contains(" on move "),
+        "{log}"
+    );
+}
+
+#[test]
+fn round_limit_is_adjudicated_by_score() {
+    let dummy = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let path = temp_dir().join("adjudicated.json");
+    let path = path.to_str().unwrap();
+    let args = ["--seed", "3", "--max-rounds", "12", "--record", path];
+    let summary = run_game(
+        &[&args[..], &["--output", "json"]].concat(),
+        &[dummy.clone(), dummy.clone()],
+    );
+    let summary: serde_json::Value = serde_json::from_str(&summary).unwrap();
+    assert_eq!(summary["end_reason"], "round_limit");
+    let replay = Replay::load(path).unwrap();
+    replay.verify().unwrap();
+    assert_eq!(replay.end_reason, Some(EndReason::RoundLimit));
+    let state = replay.states().last().unwrap();
+    assert_eq!(state.round, 12);
+    let best = state.players.iter().map(|p| p.score).max().unwrap();
+    let winners = match &replay.result {
+        GameResult::Won(winner) => vec![*winner],
+        GameResult::Draw(winners) => winners.clone(),
+        GameResult::Ongoing => panic!("the game isn't over"),
+    };
+    assert!(winners.iter().all(|&i| state.players[i].score == best));
+
+    let log = run_game(&args, &[dummy.clone(), dummy.clone()]);
+    assert!(
+        log.contains("Round limit of 12 reached, adjudicated by score"),
+        "{log}"
+    );
+
+    // Agents are told the cap in the config, 100 rounds unless given.
+    run_game(&["--seed", "3", "--record", path], &[dummy.clone(), dummy]);
+    assert_eq!(Replay::load(path).unwrap().config.max_rounds, Some(100));
+}
+
+#[test]
+fn builtin_agents_play_in_process_next_to_programs() {
+    let dummy = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let args = ["--seed", "1", "--max-rounds", "10", "--output", "json"];
+    let agents = ["builtin:random", "builtin:alphabeta?depth=1"].map(PathBuf::from);
+    let summary = run_game(&args, &[&agents[..], &[dummy]].concat());
+    let summary: serde_json::Value = serde_json::from_str(&summary).unwrap();
+    let names: Vec<&str> = (0..3)
+        .map(|i| summary["agents"][i]["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["random", "alphabeta", "dummy-agent"]);
+    assert_eq!(summary["forfeits"], serde_json::json!([]));
+    assert_eq!(summary["rounds"], 10);
+
+    // The level it plays at is in the summary.
+    let agents = ["builtin:random", "builtin:alphabeta?difficulty=easy"].map(PathBuf::from);
+    let summary = run_game(&args, &agents);
+    let summary: serde_json::Value = serde_json::from_str(&summary).unwrap();
+    assert_eq!(summary["agents"][1]["name"], "alphabeta-easy", "{summary}");
+    assert_eq!(summary["agents"][1]["difficulty"], "easy", "{summary}");
+    assert!(
+        summary["agents"][0].get("difficulty").is_none(),
+        "{summary}"
+    );
+
+    // Timed like any other agent.
+    let args = [
+        "--move-timeout",
+        "0.3",
+        "--max-rounds",
+        "10",
+        "--output",
+        "json",
+    ];
+    let agents = ["builtin:random", "builtin:alphabeta?depth=9"].map(PathBuf::from);
+    let summary = run_game(&args, &agents);
+    let summary: serde_json::Value = serde_json::from_str(&summary).unwrap();
+    assert_eq!(summary["forfeits"][0]["player"], 1, "{summary}");
+    assert_eq!(summary["result"]["won"], 0, "{summary}");
+
+    // Deepens only as long as it may, and says how far it got.
+    let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .args([
+            "--move-timeout",
+            "1",
+            "--max-rounds",
+            "3",
+            "--output",
+            "json",
+        ])
+        .args(["builtin:alphabeta?time=0.1", "builtin:random"])
+        .output()
+        .unwrap();
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["forfeits"], serde_json::json!([]), "{summary}");
+    let log = String::from_utf8(output.stderr).unwrap();
+    assert!(log.contains("alphabeta: depth "), "{log}");
+
+    let log = run_game_ending(
+        1,
+        &[],
+        &["builtin:minimax", "builtin:random"].map(PathBuf::from),
+    );
+    assert!(log.contains("There is no built-in agent minimax"), "{log}");
+}
+
+#[test]
+fn alphabeta_plays_three_player_games_to_the_end() {
+    for model in ["paranoid", "maxn"] {
+        let agent = format!("builtin:alphabeta?depth=1&model={model}");
+        let args = ["--seed", "2", "--move-timeout", "10", "--output", "json"];
+        let summary = run_game(&args, &[&agent, &agent, &agent].map(PathBuf::from));
+        let summary: serde_json::Value = serde_json::from_str(&summary).unwrap();
+        assert_eq!(summary["forfeits"], serde_json::json!([]), "{summary}");
+        let result = &summary["result"];
+        assert!(
+            result.get("won").is_some() || result.get("draw").is_some(),
+            "{summary}"
+        );
+    }
+}
+
+#[test]
+fn test_agent_passes_good_agents_and_fails_broken_ones() {
+    let test_agent = |agent: &str| {
+        Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+            .args(["test-agent", "--protocol", "messages", agent])
+            .output()
+            .unwrap()
+    };
+    let output = test_agent(env!("CARGO_BIN_EXE_dummy-agent"));
+    let log = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{log}");
+    assert!(log.contains("PASS only skip is legal: skip in "), "{log}");
+    assert!(log.contains("All 6 positions passed"), "{log}");
+
+    let broken = dummy_agent("test-agent-nonsense", "--answer nonsense");
+    let output = test_agent(broken.to_str().unwrap());
+    let log = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(1), "{log}");
+    assert!(
+        log.contains("FAIL opening: the agent sent something"),
+        "{log}"
+    );
+    assert!(!log.contains("PASS"), "{log}");
+    assert!(log.contains("this observation in opening:\n{"), "{log}");
+}
+
+#[test]
+fn transcripts_record_both_sides_and_replay() {
+    let dir = temp_dir().join("transcripts");
+    let agent_side = temp_dir().join("agent-side.transcript");
+    let wrong = dummy_agent("wrong-first-transcript", "--wrong-first");
+    let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .args([
+            "--invalid-retries",
+            "1",
+            "--max-rounds",
+            "2",
+            "--protocol",
+            "messages",
+        ])
+        .arg(&wrong)
+        .arg("builtin:random")
+        .arg("--transcript-dir")
+        .arg(&dir)
+        .env("SPLENDOR_TRANSCRIPT", &agent_side)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let path = dir.join(format!("0-{}.transcript", ai_name(&wrong)));
+    let runner_side = std::fs::read_to_string(&path).unwrap();
+    assert!(
+        runner_side.starts_with("# splendor transcript, json, messages\n"),
+        "{runner_side}"
+    );
+    for expected in [
+        r#" > {"type":"init","#,
+        r#" < {"type":"purchase","deck":0,"card":9}"#,
+        " # buy 1.9 is invalid: ",
+        r#" > {"type":"rejected","#,
+    ] {
+        assert!(
+            runner_side.contains(expected),
+            "{expected} in {runner_side}"
+        );
+    }
+    // The agent wrote down the same messages, without the runner's notes.
+    let messages = |text: &str| -> Vec<String> {
+        text.lines()
+            .skip(1)
+            .filter_map(|line| line.split_once(' ').map(|(_, rest)| rest.to_owned()))
+            .filter(|line| !line.starts_with('#'))
+            .collect()
+    };
+    let agent_side = std::fs::read_to_string(agent_side).unwrap();
+    assert_eq!(messages(&agent_side), messages(&runner_side));
+    assert!(dir.join("1-random.transcript").exists());
+
+    let replay = |agent: &Path| {
+        let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+            .args(["test-agent", "--transcript"])
+            .args([&path, agent])
+            .output()
+            .unwrap();
+        let log = String::from_utf8(output.stdout).unwrap();
+        (output.status.code(), log)
+    };
+    let (code, log) = replay(&wrong);
+    assert_eq!(code, Some(1), "{log}");
+    assert!(log.starts_with("FAIL reply 1: buy 1.9 in "), "{log}");
+    assert!(log.contains("PASS reply 2: "), "{log}");
+
+    let (code, log) = replay(Path::new(env!("CARGO_BIN_EXE_dummy-agent")));
+    assert_eq!(code, Some(0), "{log}");
+    assert!(log.contains(", recorded buy 1.9\n"), "{log}");
+    assert!(log.contains(" replies passed"), "{log}");
+}
+
+#[test]
+fn resumed_tournament_matches_uninterrupted() {
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let agents = [agent.clone(), agent.clone(), agent.clone()];
+    let path = temp_dir().join("checkpoint.json");
+    let path = path.to_str().unwrap();
+    let args = ["--seed", "5", "--checkpoint", path];
+    let straight = tournament("straight", &args, &agents);
+    assert_eq!(straight["games"].as_array().unwrap().len(), 6);
+
+    // As if killed after the second game.
+    let mut checkpoint: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    assert_eq!(checkpoint["version"], 1);
+    checkpoint["played"].as_array_mut().unwrap().truncate(2);
+    std::fs::write(path, checkpoint.to_string()).unwrap();
+    let resumed = tournament("resumed", &["--resume", path], &agents);
+    assert_eq!(resumed, straight);
+
+    let other = dummy_agent("resume-other", "");
+    let log = run_game(
+        &["tournament", "--resume", path],
+        &[agent.clone(), other.clone(), agent.clone()],
+    );
+    assert!(log.contains("differ from those of the checkpoint"), "{log}");
+    let agent = agent.display();
+    assert!(
+        log.contains(&format!("\n- {agent}-2: {agent}\n+ {}: ", other.display())),
+        "{log}"
+    );
+    assert!(!log.contains("Game 1"), "{log}");
+}
+
+#[test]
+fn gauntlet_leaves_out_baseline_failures() {
+    let dir = temp_dir();
+    let json = dir.join("gauntlet.json");
+    let ratings = dir.join("gauntlet-ratings.json");
+    std::fs::write(
+        &ratings,
+        r#"{"steady": {"rating": 1700.0, "games": 50, "updated": 0}}"#,
+    )
+    .unwrap();
+    let broken = dummy_agent("gauntlet-broken", "--answer nonsense");
+    let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .args(["gauntlet", "--games-per", "4", "--seed", "3", "--json"])
+        .arg(&json)
+        .arg("--ratings")
+        .arg(&ratings)
+        .args(["--candidate", env!("CARGO_BIN_EXE_dummy-agent")])
+        .args([
+            "--name",
+            "steady",
+            "--baseline",
+            env!("CARGO_BIN_EXE_dummy-agent"),
+        ])
+        .args(["--name", "broken", "--baseline"])
+        .arg(&broken)
+        .output()
+        .unwrap();
+    let log = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{log}");
+    assert!(
+        log.contains("4 games left out where broken failed"),
+        "{log}"
+    );
+    let summary: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&json).unwrap()).unwrap();
+    assert_eq!(summary["games"].as_array().unwrap().len(), 8);
+    let [steady, broken] = [0, 1].map(|i| &summary["baselines"][i]);
+    let games = |b: &serde_json::Value| {
+        ["wins", "draws", "losses"]
+            .map(|k| b[k].as_u64().unwrap())
+            .iter()
+            .sum::<u64>()
+    };
+    assert_eq!(games(steady), 4);
+    assert_eq!(steady["rating"], 1700.0);
+    assert_eq!(games(broken), 0);
+    assert_eq!(broken["baseline_failures"], 4);
+    assert_eq!(broken["rating"], 1500.0);
+    assert_eq!(games(&summary), 4);
+    // The same boards with the seats swapped.
+    let seeds: Vec<_> = (0..4)
+        .map(|i| summary["games"][i]["seed"].clone())
+        .collect();
+    assert_eq!(seeds[0], seeds[1]);
+    assert_eq!(seeds[2], seeds[3]);
+    assert_eq!(summary["games"][1]["seats"], serde_json::json!([1, 0]));
+    let elo = summary["elo"].as_f64().unwrap();
+    assert!((summary["rating"].as_f64().unwrap() - 1700.0 - elo).abs() < 1e-6);
+}
+
+#[test]
+fn crashed_agent_is_restarted() {
+    let dir = temp_dir().join("restart-logs");
+    let path = temp_dir().join("restarted.json");
+    // Crashes on its third move, and every third move after a restart.
+    let crashing = dummy_agent("crash-restarted", "--exit-after 2 --follow");
+    let fast = dummy_agent("fast-restarted", "");
+    let run = |code, on_crash: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+            .args(["--on-crash", on_crash, "--first", "0", "--max-rounds", "6"])
+            .args(["--output", "json", "--record"])
+            .arg(&path)
+            .arg("--agent-log-dir")
+            .arg(&dir)
+            .args(["--protocol", "messages"])
+            .args([&crashing, &fast])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(code), "{output:?}");
+        let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        (summary, Replay::load(path.to_str().unwrap()).unwrap())
+    };
+
+    let (summary, replay) = run(3, "restart:1");
+    assert_eq!(summary["agents"][0]["restarts"], 1, "{summary}");
+    assert_eq!(summary["forfeits"][0]["player"], 0, "{summary}");
+    assert_eq!(replay.restarts.len(), 1);
+    assert_eq!(replay.restarts[0].ply, 4);
+    assert!(replay.restarts[0].reason.contains("(status 7)"));
+    // The moves so far, then the move it crashed on again.
+    let log = std::fs::read_to_string(dir.join("0-crash-restarted-4.log")).unwrap();
+    let expected: String = replay.actions[..4]
+        .iter()
+        .map(|(player, action)| format!("player {player} played {action}\n"))
+        .collect();
+    assert!(log.starts_with(&(expected + "asked to move\n")), "{log}");
+
+    let (summary, replay) = run(0, "restart:10");
+    assert!(summary["forfeits"].as_array().unwrap().is_empty());
+    let restarts = summary["agents"][0]["restarts"].as_u64().unwrap();
+    assert!(restarts >= 2, "{summary}");
+    assert_eq!(replay.restarts.len() as u64, restarts);
+    assert_eq!(summary["agents"][1]["restarts"], 0);
+}
+
+#[test]
+fn tui_without_a_terminal_prints_the_game() {
+    let dummy = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let agents = [dummy.clone(), dummy];
+    let boards = |log: &str| log.lines().filter(|l| *l == "Deck 1:").count();
+    let plain = run_game(&["--seed", "5", "--max-rounds", "3"], &agents);
+    let tui = run_game(&["--seed", "5", "--max-rounds", "3", "--tui"], &agents);
+    assert!(
+        tui.contains("\n--tui needs a terminal, printing the game instead\n"),
+        "{tui}"
+    );
+    assert_eq!(boards(&tui), boards(&plain), "{tui}");
+    assert!(tui.contains("Game finished"), "{tui}");
+}
+
+#[test]
+fn config_file_is_run_with_the_command_line_over_it() {
+    let path = temp_dir().join("config-game.json");
+    let fast = dummy_agent("fast-config", "");
+    let config = temp_dir().join("game.toml");
+    let toml = format!(
+        "# A game\nseed = 3\nmax_rounds = 4\n\n[game]\nfirst = 1\noutput = 'json'\n\
+         record = {path:?}\nhandicaps = [\"1:score=2\"]\n\n\
+         [[agents]]\ncommand = {fast:?}\nname = \"timed\"\ntime_budget = 30\n\n\
+         [[agents]]\ncommand = \"builtin:random\"\n"
+    );
+    std::fs::write(&config, toml).unwrap();
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+            .arg("--config")
+            .arg(&config)
+            .args(args)
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(0), "{output:?}");
+        let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let log = String::from_utf8(output.stderr).unwrap();
+        (summary, Replay::load(path.to_str().unwrap()).unwrap(), log)
+    };
+
+    let (summary, replay, log) = run(&[]);
+    assert_eq!(summary["seed"], 3, "{summary}");
+    assert_eq!(summary["first"], 1, "{summary}");
+    assert_eq!(summary["agents"][0]["name"], "timed", "{summary}");
+    assert_eq!(summary["agents"][1]["handicap"]["score"], 2, "{summary}");
+    // Only the first agent has a clock.
+    let readings = clock_readings(&log, "timed");
+    assert!(!readings.is_empty(), "{log}");
+    assert!(readings.iter().all(|&left| left > 20.0), "{log}");
+    assert!(
+        log.lines()
+            .filter(|line| line.starts_with("random did "))
+            .all(|line| !line.ends_with(" left)")),
+        "{log}"
+    );
+    assert_eq!(replay.run_config.as_ref(), Some(&summary["run_config"]));
+    assert_eq!(summary["run_config"]["options"]["seed"], 3);
+    assert_eq!(summary["run_config"]["agents"][0]["time_budget"], 30);
+
+    // Flags win, and a list on the command line replaces the file's.
+    let (summary, replay, _) = run(&["--seed", "9", "--handicap", "0:score=1"]);
+    assert_eq!(summary["seed"], 9, "{summary}");
+    assert_eq!(replay.seed, Some(9));
+    assert_eq!(summary["agents"][0]["handicap"]["score"], 1, "{summary}");
+    assert!(summary["agents"][1]["handicap"].is_null(), "{summary}");
+    let options = &summary["run_config"]["options"];
+    assert_eq!(options["seed"], 9);
+    assert_eq!(options["handicaps"], serde_json::json!(["0:score=1"]));
+}
+
+#[test]
+fn example_config_plays_a_match() {
+    let json = temp_dir().join("example-match.json");
+    let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+        .args(["--config", "examples/match.toml", "--games", "2", "--json"])
+        .arg(&json)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let summary: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&json).unwrap()).unwrap();
+    assert_eq!(summary["seed"], 42);
+    assert_eq!(summary["agents"], serde_json::json!(["deep", "random"]));
+    assert_eq!(summary["games"].as_array().unwrap().len(), 2);
+    assert_eq!(summary["run_config"]["section"], "match");
+    assert_eq!(summary["run_config"]["options"]["games"], 2);
+}
+
+#[test]
+fn config_file_mistakes_point_at_their_line() {
+    let config = temp_dir().join("broken.toml");
+    let error = |toml: &str, args: &[&str]| {
+        std::fs::write(&config, toml).unwrap();
+        let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+            .args(args)
+            .arg("--config")
+            .arg(&config)
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1), "{output:?}");
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        stdout.replace(config.to_str().unwrap(), "file")
+    };
+    let agents = "[[agents]]\ncommand = \"builtin:random\"\n";
+
+    let log = error(
+        &format!("[tournament]\ngames = 2\nrounds = 5\n{agents}"),
+        &[],
+    );
+    assert_eq!(log, "file:3: unknown key rounds for a [tournament]\n");
+    let log = error(&format!("seed = \"abc\"\n[game]\n{agents}"), &[]);
+    assert_eq!(log, "file:1: seed needs a whole number, not \"abc\"\n");
+    let log = error("[game]\nquiet = yes\n", &[]);
+    assert_eq!(log, "file:2: `yes` is not a value, strings need quotes\n");
+    let log = error(&format!("[match]\n{agents}time_budget = 5\n"), &[]);
+    assert_eq!(
+        log,
+        "file:4: time_budget is only for the agents of a [game]\n"
+    );
+    let log = error("[game]\n\n[[agents]]\nname = \"x\"\n", &[]);
+    assert_eq!(log, "file:3: [[agents]] needs a command\n");
+    let log = error(&format!("[match]\n{agents}"), &["tournament"]);
+    assert_eq!(log, "file is for a [match], not a tournament\n");
+}
+
+#[test]
+fn reused_agents_play_every_game_in_one_process() {
+    let games = |name: &str, args: &str| {
+        let path = temp_dir().join(format!("{name}-games.txt"));
+        _ = std::fs::remove_file(&path);
+        let agent = dummy_agent(name, &format!("{args} --game-log '{}'", path.display()));
+        (agent, path)
+    };
+    let (kept, kept_games) = games("kept", "--new-games");
+    let (restarted, restarted_games) = games("restarted", "");
+    let json = temp_dir().join("reused.json");
+    let (kept, restarted) = (kept.to_str().unwrap(), restarted.to_str().unwrap());
+    run_game(
+        &[
+            "tournament",
+            "--games",
+            "4",
+            "--jobs",
+            "1",
+            "--time-budget",
+            "10",
+            "--reuse-agents",
+            "--json",
+            json.to_str().unwrap(),
+            "--protocol",
+            "messages",
+            kept,
+            "--protocol",
+            "messages",
+            restarted,
+        ],
+        &[],
+    );
+    let summary: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&json).unwrap()).unwrap();
+    assert_eq!(summary["games"].as_array().unwrap().len(), 4);
+    assert!(summary["games"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|game| game["forfeit"].is_null()));
+    let pids = |path: &Path| {
+        let text = std::fs::read_to_string(path).unwrap();
+        text.lines().map(str::to_owned).collect::<Vec<_>>()
+    };
+    let kept = pids(&kept_games);
+    assert_eq!(kept.len(), 4);
+    assert!(kept.iter().all(|pid| *pid == kept[0]), "{kept:?}");
+    // Without the answer it is started again for every game.
+    let mut restarted = pids(&restarted_games);
+    restarted.dedup();
+    assert_eq!(restarted.len(), 4, "{restarted:?}");
+}
+
+#[test]
+fn observers_get_every_message_and_never_stall() {
+    let watch = |name: &str| {
+        let log = temp_dir().join(format!("{name}.jsonl"));
+        let script = temp_dir().join(name);
+        std::fs::write(&script, format!("#!/bin/sh\ncat > '{}'\n", log.display())).unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        (script, log)
+    };
+    let record = temp_dir().join("observed.json");
+    let agent = PathBuf::from(env!("CARGO_BIN_EXE_dummy-agent"));
+    let (first, first_log) = watch("observer-1");
+    let (second, second_log) = watch("observer-2");
+    let run = |sees: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_splendor-ai"))
+            .args(["--seed", "4", "--max-rounds", "6", "--observer-sees", sees])
+            .arg("--observer")
+            .arg(&first)
+            .arg("--observer")
+            .arg(&second)
+            // Gone before the first message.
+            .args(["--observer", "true", "--record"])
+            .arg(&record)
+            .args([&agent, &agent])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let messages = |path: &Path| -> Vec<serde_json::Value> {
+        let text = std::fs::read_to_string(path).unwrap();
+        text.lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    };
+
+    let log = run("hidden");
+    assert!(log.contains("Observer true stopped, detaching it"), "{log}");
+    let replay = Replay::load(record.to_str().unwrap()).unwrap();
+    let seen = messages(&first_log);
+    assert_eq!(seen, messages(&second_log));
+    let count = |kind: &str| seen.iter().filter(|m| m["type"] == kind).count();
+    assert_eq!(seen[0]["type"], "init");
+    assert_eq!(seen[0]["players"].as_array().unwrap().len(), 2);
+    assert_eq!(count("played"), replay.actions.len());
+    assert_eq!(count("state"), replay.actions.len() + 1);
+    assert_eq!(seen.last().unwrap()["type"], "game_over");
+    let played: Vec<Action> = seen
+        .iter()
+        .filter(|m| m["type"] == "played")
+        .map(|m| serde_json::from_value(m["action"].clone()).unwrap())
+        .collect();
+    let actions: Vec<_> = replay.actions.iter().map(|(_, a)| a.clone()).collect();
+    assert_eq!(played, actions);
+    let state: State = serde_json::from_value(seen[1]["state"].clone()).unwrap();
+    assert!(state == replay.initial_state);
+
+    run("public");
+    let seen = messages(&first_log);
+    let state: State = serde_json::from_value(seen[1]["state"].clone()).unwrap();
+    assert!(state == replay.initial_state.redacted());
+}
+
+#[test]
+fn wasm_agents_are_refused_before_the_game() {
+    let agents = ["builtin:random", "wasm:bot.wasm"].map(PathBuf::from);
+    let output = run_game_ending(1, &[], &agents);
+    assert!(
+        output.contains(
+            "WASM agents can't run in-process, start bot.wasm under a WASI runtime like \
+             `wasmtime bot.wasm` instead"
+        ),
+        "{output}"
+    );
+    assert!(!output.contains("moves first"), "{output}");
+}
@@ -0,0 +1,213 @@
+//! The schemas gen-schema writes, checked against what is actually sent.
+
+use std::{
+    path::PathBuf,
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use game_def::{Action, Observation, State};
+use rand::{rngs::StdRng, seq::IteratorRandom, SeedableRng};
+use serde_json::Value;
+
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("splendor-schema-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// The schema gen-schema writes into `file`, in a directory of its own as
+/// the tests run in parallel.
+fn generated(file: &str) -> Value {
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+    let dir = temp_dir().join(RUNS.fetch_add(1, Ordering::Relaxed).to_string());
+    let status = Command::new(env!("CARGO_BIN_EXE_gen-schema"))
+        .arg(&dir)
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success());
+    serde_json::from_str(&std::fs::read_to_string(dir.join(file)).unwrap()).unwrap()
+}
+
+/// Checks `value` against `schema`, for the keywords gen-schema uses. The
+/// error says where the first mismatch is.
+fn check(root: &Value, schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+    let fail = |what: &str| Err(format!("{path}: {what}, found {value}"));
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let name = reference.strip_prefix("#/$defs/").unwrap();
+        check(root, &root["$defs"][name], value, path)?;
+    }
+    if let Some(kind) = schema.get("type").and_then(Value::as_str) {
+        let matches = match kind {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "integer" => value.is_u64() || value.is_i64(),
+            "string" => value.is_string(),
+            "boolean" => value.is_boolean(),
+            "null" => value.is_null(),
+            _ => panic!("Unknown type {kind}"),
+        };
+        if !matches {
+            return fail(&format!("expected {kind}"));
+        }
+    }
+    if let Some(minimum) = schema.get("minimum").and_then(Value::as_i64) {
+        if value.as_i64().is_some_and(|v| v < minimum) {
+            return fail(&format!("expected at least {minimum}"));
+        }
+    }
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            return fail(&format!("expected one of {options:?}"));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if value != expected {
+            return fail(&format!("expected {expected}"));
+        }
+    }
+    if let Some(options) = schema.get("anyOf").and_then(Value::as_array) {
+        if !options.iter().any(|s| check(root, s, value, path).is_ok()) {
+            return fail("matches no option");
+        }
+    }
+    if let Some(options) = schema.get("oneOf").and_then(Value::as_array) {
+        let matching = options
+            .iter()
+            .filter(|s| check(root, s, value, path).is_ok())
+            .count();
+        if matching != 1 {
+            return fail(&format!("matches {matching} options instead of one"));
+        }
+    }
+    if let Some(object) = value.as_object() {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for key in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if !object.contains_key(key.as_str().unwrap()) {
+                return fail(&format!("missing {key}"));
+            }
+        }
+        for (key, v) in object {
+            let path = format!("{path}.{key}");
+            match (
+                properties.and_then(|p| p.get(key)),
+                schema.get("additionalProperties"),
+            ) {
+                (Some(s), _) => check(root, s, v, &path)?,
+                (None, Some(Value::Bool(false))) => {
+                    return Err(format!("{path}: not allowed"));
+                }
+                (None, Some(s @ Value::Object(_))) => check(root, s, v, &path)?,
+                (None, _) => (),
+            }
+        }
+    }
+    if let Some(items) = value.as_array() {
+        let length = items.len() as u64;
+        if schema["minItems"].as_u64().is_some_and(|m| length < m)
+            || schema["maxItems"].as_u64().is_some_and(|m| length > m)
+        {
+            return fail("wrong number of items");
+        }
+        if let Some(s) = schema.get("items") {
+            for (i, item) in items.iter().enumerate() {
+                check(root, s, item, &format!("{path}[{i}]"))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn valid(schema: &Value, value: &Value) -> Result<(), String> {
+    check(schema, schema, value, "")
+}
+
+/// States along a few random games, some with every kind of action played.
+fn states() -> Vec<State> {
+    let mut rng = StdRng::seed_from_u64(16);
+    let mut states = vec![];
+    for players in 2..=4 {
+        let names = ["a", "b", "c", "d"];
+        let mut state = State::standard(&names[..players], &mut rng);
+        while !state.is_finished() {
+            states.push(state.clone());
+            let action = state
+                .legal_actions()
+                .filter(|a| *a != Action::Resign)
+                .choose(&mut rng)
+                .unwrap();
+            state.run(action).unwrap();
+        }
+        states.push(state);
+    }
+    states
+}
+
+#[test]
+fn states_observations_and_actions_match_their_schemas() {
+    let (state_schema, observation_schema, action_schema) = (
+        generated("state.json"),
+        generated("observation.json"),
+        generated("action.json"),
+    );
+    for state in states() {
+        let value = serde_json::to_value(&state).unwrap();
+        valid(&state_schema, &value).unwrap();
+        let observation: Observation = state.observation_for(state.turn);
+        valid(
+            &observation_schema,
+            &serde_json::to_value(&observation).unwrap(),
+        )
+        .unwrap();
+        for action in state.legal_actions() {
+            valid(&action_schema, &serde_json::to_value(&action).unwrap())
+                .unwrap_or_else(|e| panic!("{action}: {e}"));
+        }
+    }
+}
+
+#[test]
+fn the_schemas_catch_a_wrong_state() {
+    let schema = generated("state.json");
+    let state =
+        serde_json::to_value(State::standard(&["a", "b"], &mut rand::thread_rng())).unwrap();
+    for (pointer, wrong) in [
+        ("/coins/gold", Value::from(-1)),
+        ("/players/0/mortal/gold", Value::from("one")),
+        ("/phase", Value::from("resting")),
+        ("/decks/0/0/cost/purple", Value::from(1)),
+    ] {
+        let mut broken = state.clone();
+        match broken.pointer_mut(pointer) {
+            Some(v) => *v = wrong,
+            None => {
+                let (parent, key) = pointer.rsplit_once('/').unwrap();
+                broken.pointer_mut(parent).unwrap()[key] = wrong;
+            }
+        }
+        assert!(valid(&schema, &broken).is_err(), "{pointer}");
+    }
+    let mut broken = state;
+    broken["coins"].as_object_mut().unwrap().remove("gold");
+    assert!(valid(&schema, &broken).is_err());
+}
+
+#[test]
+fn the_committed_schemas_are_up_to_date() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/schemas");
+    for file in ["observation.json", "state.json", "action.json"] {
+        let committed: Value =
+            serde_json::from_str(&std::fs::read_to_string(format!("{dir}/{file}")).unwrap())
+                .unwrap();
+        assert!(
+            committed == generated(file),
+            "{file} is out of date, run gen-schema"
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! Tuning the [`Weights`] of the alpha-beta search by self-play, the
+//! library of the tuner program.
+//!
+//! A simple evolution strategy: each generation makes `population`
+//! candidates by scaling every weight of the best so far by a random factor,
+//! plays each of them against the best, and against any fixed baselines, and
+//! keeps the candidate with the best win rate if it beat the best. Games are
+//! played in process, in pairs with the same seed and the seats swapped.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::channel,
+    },
+    time::Duration,
+};
+
+use alpha_beta_agent::{AlphaBeta, Weights};
+use game_def::{play_game, Agent, GameConfig, GameResult, Observation, State};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Games end in a draw after this many rounds.
+const MAX_ROUNDS: usize = 100;
+/// The transposition table of each search, small with many at once.
+const TABLE_MEMORY: usize = 1 << 20;
+
+/// How candidates are made and measured. Each plays `games` games against
+/// the best and against each of `baselines`, with the search going `depth`
+/// rounds deep or for `time_limit`, `jobs` games at a time. Weights are
+/// scaled by a factor between `e^-sigma` and `e^sigma`. Everything random
+/// comes from `seed` and the number of the generation, so a run resumed at
+/// a generation plays it like it would have.
+#[derive(Debug, Clone)]
+pub struct Tuning {
+    pub population: usize,
+    pub games: usize,
+    pub depth: i32,
+    pub time_limit: Duration,
+    pub sigma: f64,
+    pub baselines: Vec<Weights>,
+    pub jobs: usize,
+    pub seed: u64,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Tuning {
+            population: 8,
+            games: 20,
+            depth: 2,
+            time_limit: Duration::from_millis(100),
+            sigma: 0.2,
+            baselines: vec![],
+            jobs: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            seed: 0,
+        }
+    }
+}
+
+/// What one generation found, a line of the progression log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Generation {
+    pub generation: usize,
+    /// The best after this generation.
+    pub best: Weights,
+    /// Whether a candidate beat the best it started from.
+    pub improved: bool,
+    /// Of the candidate ranked first, its win rate against the best it
+    /// started from, and over all its games, baselines included.
+    pub win_rate: f64,
+    pub fitness: f64,
+    /// Every candidate's fitness, in the order they were made.
+    pub candidates: Vec<f64>,
+}
+
+impl Tuning {
+    /// `best` with every weight scaled by a random factor, the score curve
+    /// kept from making more points worth less. A weight of 0 stays 0.
+    pub fn mutate(&self, best: &Weights, rng: &mut impl Rng) -> Weights {
+        let mut json = serde_json::to_value(best).expect("weights are JSON");
+        scale(&mut json, self.sigma, rng);
+        let mut weights: Weights = serde_json::from_value(json).expect("weights stay weights");
+        weights.score_growth = weights.score_growth.max(1.0);
+        weights
+    }
+
+    /// Makes and plays the candidates of generation number `generation`
+    /// from `best`.
+    pub fn generation(&self, generation: usize, best: &Weights) -> Generation {
+        let seed = self.seed ^ (generation as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let candidates: Vec<_> = (0..self.population.max(1))
+            .map(|_| self.mutate(best, &mut rng))
+            .collect();
+        let opponents: Vec<_> = std::iter::once(best)
+            .chain(&self.baselines)
+            .copied()
+            .collect();
+        // Each candidate's points against each opponent, a win counting 1.
+        let mut points = vec![vec![0.0; opponents.len()]; candidates.len()];
+        let games = self.games.max(1);
+        let mut schedule = vec![];
+        for c in 0..candidates.len() {
+            for o in 0..opponents.len() {
+                for game in 0..games {
+                    schedule.push((c, o, game));
+                }
+            }
+        }
+        let next = AtomicUsize::new(0);
+        let (sender, results) = channel();
+        std::thread::scope(|scope| {
+            for _ in 0..self.jobs.max(1) {
+                let sender = sender.clone();
+                let (next, schedule) = (&next, &schedule);
+                let (candidates, opponents) = (&candidates, &opponents);
+                scope.spawn(move || loop {
+                    let n = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(&(c, o, game)) = schedule.get(n) else {
+                        break;
+                    };
+                    // Pairs of games with the same seed, the seats swapped.
+                    let seed = seed.wrapping_add((game / 2) as u64);
+                    let won = self.play(&candidates[c], &opponents[o], game % 2, seed);
+                    if sender.send((c, o, won)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(sender);
+            for (c, o, won) in results {
+                points[c][o] += won;
+            }
+        });
+        let fitness: Vec<f64> = points
+            .iter()
+            .map(|p| p.iter().sum::<f64>() / (p.len() * games) as f64)
+            .collect();
+        let mut first = 0;
+        for c in 1..candidates.len() {
+            if fitness[c] > fitness[first] {
+                first = c;
+            }
+        }
+        let win_rate = points[first][0] / games as f64;
+        let improved = win_rate > 0.5;
+        Generation {
+            generation,
+            best: if improved { candidates[first] } else { *best },
+            improved,
+            win_rate,
+            fitness: fitness[first],
+            candidates: fitness,
+        }
+    }
+
+    /// What `candidate` scored against `opponent` sitting at `seat`, in a
+    /// game dealt with `seed`: 1 for a win, a share of 1 for a draw and half
+    /// for a game still going after [`MAX_ROUNDS`].
+    fn play(&self, candidate: &Weights, opponent: &Weights, seat: usize, seed: u64) -> f64 {
+        // Searching without the log of each move the agent writes.
+        let agent = |weights| -> Box<dyn Agent> {
+            let agent = AlphaBeta {
+                depth: self.depth,
+                time_limit: self.time_limit,
+                table_memory: TABLE_MEMORY,
+                weights,
+                seed,
+                ..AlphaBeta::default()
+            };
+            Box::new(move |state: &State| agent.think(Observation::from_shown(state)).action)
+        };
+        let mut agents = vec![agent(*opponent), agent(*opponent)];
+        agents[seat] = agent(*candidate);
+        let state = State::setup(
+            &["a", "b"],
+            GameConfig::default(),
+            &mut StdRng::seed_from_u64(seed),
+        );
+        match play_game(state, &mut agents, MAX_ROUNDS) {
+            GameResult::Won(winner) if winner == seat => 1.0,
+            GameResult::Won(_) => 0.0,
+            GameResult::Draw(drawn) if drawn.contains(&seat) => 1.0 / drawn.len() as f64,
+            GameResult::Draw(_) => 0.0,
+            GameResult::Ongoing => 0.5,
+        }
+    }
+}
+
+/// Scales every number in `json` by a factor between `e^-sigma` and
+/// `e^sigma`.
+fn scale(json: &mut serde_json::Value, sigma: f64, rng: &mut impl Rng) {
+    match json {
+        serde_json::Value::Number(n) => {
+            let factor = (sigma * rng.gen_range(-1.0..=1.0)).exp();
+            let scaled = n.as_f64().unwrap_or(0.0) * factor;
+            if let Some(scaled) = serde_json::Number::from_f64(scaled) {
+                *n = scaled;
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for value in values {
+                scale(value, sigma, rng);
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            for value in fields.values_mut() {
+                scale(value, sigma, rng);
+            }
+        }
+        _ => (),
+    }
+}
@@ -0,0 +1,142 @@
+//! Usage: `tuner [--generations <n>] [--population <n>] [--games <n>]
+//! [--depth <rounds>] [--time-ms <milliseconds>] [--sigma <s>]
+//! [--baseline default|<file>]... [--start <file>] [--jobs <n>] [--seed <n>]
+//! [--out <file>] [--log <file>] [--resume]`
+//!
+//! Tunes the weights of the alpha-beta search by self-play, see
+//! [`Tuning`]: `--generations` generations, 10 by default, of
+//! `--population` candidates, 8 by default, each playing `--games` games,
+//! 20 by default, against the best so far and against each `--baseline`,
+//! the default weights or those of a file. The search goes `--depth`
+//! rounds deep, 2 by default, or for `--time-ms` milliseconds, 100 by
+//! default. `--sigma` is how far a candidate's weights stray, 0.2 by
+//! default. It starts from the default weights, or those of `--start`, and
+//! runs `--jobs` games at a time, as many as there are cores by default.
+//!
+//! After every generation the best weights are written to `--out`,
+//! `tuned-weights.json` by default, which `alpha-beta-agent --weights`
+//! reads, and the generation is printed and appended as a line of JSON to
+//! `--log`, `tuning-log.jsonl` by default, for plotting. `--resume` goes on
+//! from the weights in `--out` after the last generation in `--log`.
+
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use alpha_beta_agent::Weights;
+use anyhow::Context;
+use tuner::{Generation, Tuning};
+
+const USAGE: &str = "usage: tuner [--generations <n>] [--population <n>] [--games <n>] [--depth <rounds>] [--time-ms <milliseconds>] [--sigma <s>] [--baseline default|<file>]... [--start <file>] [--jobs <n>] [--seed <n>] [--out <file>] [--log <file>] [--resume]";
+
+fn main() -> anyhow::Result<()> {
+    let mut tuning = Tuning::default();
+    let mut generations = 10;
+    let mut start = None;
+    let mut out = PathBuf::from("tuned-weights.json");
+    let mut log = PathBuf::from("tuning-log.jsonl");
+    let mut resume = false;
+    let mut args = std::env::args().skip(1);
+    let positive = |arg: Option<String>, name: &str| {
+        arg.and_then(|x| x.parse().ok())
+            .filter(|&n: &usize| n > 0)
+            .with_context(|| format!("{name} needs a positive number"))
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--generations" => generations = positive(args.next(), "--generations")?,
+            "--population" => tuning.population = positive(args.next(), "--population")?,
+            "--games" => tuning.games = positive(args.next(), "--games")?,
+            "--jobs" => tuning.jobs = positive(args.next(), "--jobs")?,
+            "--depth" => {
+                tuning.depth = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .filter(|&n| n > 0)
+                    .context("--depth needs a positive number of rounds")?
+            }
+            "--time-ms" => {
+                tuning.time_limit = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .map(Duration::from_millis)
+                    .context("--time-ms needs a number of milliseconds")?
+            }
+            "--sigma" => {
+                tuning.sigma = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .filter(|s: &f64| s.is_finite() && *s >= 0.0)
+                    .context("--sigma needs a number of at least 0")?
+            }
+            "--baseline" => {
+                let baseline = args.next().context("--baseline needs default or a file")?;
+                tuning.baselines.push(match baseline.as_str() {
+                    "default" => Weights::default(),
+                    path => Weights::load(path.as_ref())?,
+                });
+            }
+            "--start" => {
+                let path = args.next().context("--start needs a file")?;
+                start = Some(Weights::load(path.as_ref())?);
+            }
+            "--seed" => {
+                tuning.seed = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .context("--seed needs a number")?
+            }
+            "--out" => out = args.next().context("--out needs a file")?.into(),
+            "--log" => log = args.next().context("--log needs a file")?.into(),
+            "--resume" => resume = true,
+            _ => anyhow::bail!("Unknown argument {arg}, {USAGE}"),
+        }
+    }
+    let (mut best, first) = match resume {
+        true => (Weights::load(&out)?, last_generation(&log)? + 1),
+        false => (start.unwrap_or_default(), 0),
+    };
+    for generation in first..first + generations {
+        let found = tuning.generation(generation, &best);
+        best = found.best;
+        let json = serde_json::to_string_pretty(&best)?;
+        std::fs::write(&out, json + "\n")
+            .with_context(|| format!("Failed to write the weights {}", out.display()))?;
+        append(&log, &found)?;
+        let candidates: Vec<_> = found.candidates.iter().map(|f| format!("{f:.2}")).collect();
+        println!(
+            "Generation {generation}: {}, {:.2} against the best, fitness {:.2}, candidates {}",
+            if found.improved { "improved" } else { "kept" },
+            found.win_rate,
+            found.fitness,
+            candidates.join(" ")
+        );
+    }
+    Ok(())
+}
+
+/// The number of the last generation logged in `log`.
+fn last_generation(log: &Path) -> anyhow::Result<usize> {
+    let text = std::fs::read_to_string(log)
+        .with_context(|| format!("Failed to read the log {}", log.display()))?;
+    let last = text
+        .lines()
+        .last()
+        .with_context(|| format!("The log {} is empty", log.display()))?;
+    let generation: Generation = serde_json::from_str(last)
+        .with_context(|| format!("Failed to parse the log {}", log.display()))?;
+    Ok(generation.generation)
+}
+
+fn append(log: &Path, generation: &Generation) -> anyhow::Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log)
+        .with_context(|| format!("Failed to open the log {}", log.display()))?;
+    writeln!(file, "{}", serde_json::to_string(generation)?)?;
+    Ok(())
+}
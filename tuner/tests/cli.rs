@@ -0,0 +1,44 @@
+//! The tuner program on a generation small enough to run as a test.
+
+use std::process::Command;
+
+use alpha_beta_agent::Weights;
+use tuner::Generation;
+
+#[test]
+fn tiny_generations_checkpoint_and_resume() {
+    let dir = std::env::temp_dir().join(format!("tuner-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let out = dir.join("weights.json");
+    let log = dir.join("log.jsonl");
+    let tune = |more: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_tuner"))
+            .args(["--generations", "1", "--population", "2", "--games", "2"])
+            .args(["--depth", "1", "--time-ms", "50", "--jobs", "2"])
+            .args(["--baseline", "default"])
+            .arg("--out")
+            .arg(&out)
+            .arg("--log")
+            .arg(&log)
+            .args(more)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let printed = tune(&[]);
+    assert!(printed.starts_with("Generation 0: "), "{printed}");
+    Weights::load(&out).unwrap();
+    let printed = tune(&["--resume"]);
+    assert!(printed.starts_with("Generation 1: "), "{printed}");
+    let generations: Vec<Generation> = std::fs::read_to_string(&log)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(generations.len(), 2);
+    assert_eq!(generations[1].generation, 1);
+    assert_eq!(generations[1].candidates.len(), 2);
+    assert_eq!(Weights::load(&out).unwrap(), generations[1].best);
+    std::fs::remove_dir_all(&dir).unwrap();
+}